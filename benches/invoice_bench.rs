@@ -35,8 +35,8 @@ fn build_10_line_invoice() -> Invoice {
     for i in 1..=10 {
         builder = builder.add_line(
             LineItemBuilder::new(
-                &i.to_string(),
-                &format!("Service item {i}"),
+                i.to_string(),
+                format!("Service item {i}"),
                 dec!(5),
                 "HUR",
                 dec!(120),
@@ -71,8 +71,8 @@ fn build_1000_line_invoice() -> Invoice {
     for i in 1..=1000 {
         builder = builder.add_line(
             LineItemBuilder::new(
-                &i.to_string(),
-                &format!("Item {i}"),
+                i.to_string(),
+                format!("Item {i}"),
                 dec!(2),
                 "C62",
                 dec!(9.99),
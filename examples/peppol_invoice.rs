@@ -30,7 +30,7 @@ fn main() {
                 .build(),
         )
         .vat_id("NL123456789B01")
-        .electronic_address("NL123456789B01", "9944") // EAS 9944 = NL VAT
+        .electronic_address("9944", "NL123456789B01") // EAS 9944 = NL VAT
         .build(),
     )
     .add_line(
@@ -2,6 +2,8 @@ use chrono::NaiveDate;
 use rust_decimal::Decimal;
 
 use super::error::RechnungError;
+use super::exchange::ExchangeRate;
+use super::skonto::CashDiscount;
 use super::types::*;
 use super::validation;
 
@@ -32,6 +34,7 @@ pub struct InvoiceBuilder {
     currency_code: String,
     tax_currency_code: Option<String>,
     vat_total_in_tax_currency: Option<Decimal>,
+    exchange_rate: Option<ExchangeRate>,
     notes: Vec<String>,
     buyer_reference: Option<String>,
     project_reference: Option<String>,
@@ -46,6 +49,7 @@ pub struct InvoiceBuilder {
     allowances: Vec<AllowanceCharge>,
     charges: Vec<AllowanceCharge>,
     payment_terms: Option<String>,
+    cash_discounts: Vec<CashDiscount>,
     payment: Option<PaymentInstructions>,
     tax_point_date: Option<NaiveDate>,
     invoicing_period: Option<Period>,
@@ -53,6 +57,11 @@ pub struct InvoiceBuilder {
     prepaid: Decimal,
     preceding_invoices: Vec<PrecedingInvoiceReference>,
     attachments: Vec<DocumentAttachment>,
+    additional_references: Vec<AdditionalReference>,
+    despatch_advices: Vec<DocumentReference>,
+    delivery_notes: Vec<DocumentReference>,
+    logistics_charges: Vec<LogisticsServiceCharge>,
+    payment_schedule: Vec<PaymentTerm>,
     payee: Option<Payee>,
     tax_representative: Option<TaxRepresentative>,
 }
@@ -68,6 +77,7 @@ impl InvoiceBuilder {
             currency_code: "EUR".to_string(),
             tax_currency_code: None,
             vat_total_in_tax_currency: None,
+            exchange_rate: None,
             notes: Vec::new(),
             buyer_reference: None,
             project_reference: None,
@@ -82,6 +92,7 @@ impl InvoiceBuilder {
             allowances: Vec::new(),
             charges: Vec::new(),
             payment_terms: None,
+            cash_discounts: Vec::new(),
             payment: None,
             tax_point_date: None,
             invoicing_period: None,
@@ -89,6 +100,11 @@ impl InvoiceBuilder {
             prepaid: Decimal::ZERO,
             preceding_invoices: Vec::new(),
             attachments: Vec::new(),
+            additional_references: Vec::new(),
+            despatch_advices: Vec::new(),
+            delivery_notes: Vec::new(),
+            logistics_charges: Vec::new(),
+            payment_schedule: Vec::new(),
             payee: None,
             tax_representative: None,
         }
@@ -107,6 +123,21 @@ impl InvoiceBuilder {
         self
     }
 
+    /// Issue the invoice as self-billed (389, Gutschrift): the buyer creates
+    /// it on behalf of the seller. Adds the note "Gutschrift" (BT-22)
+    /// required by §14 Abs. 4 Nr. 10 UStG unless a note already says so.
+    pub fn self_billed(mut self) -> Self {
+        self.type_code = InvoiceTypeCode::SelfBilled;
+        if !self
+            .notes
+            .iter()
+            .any(|n| validation::has_gutschrift_wording(n))
+        {
+            self.notes.insert(0, "Gutschrift".into());
+        }
+        self
+    }
+
     /// Set the currency code (BT-5). Defaults to `"EUR"`.
     /// Must be a valid ISO 4217 code.
     pub fn currency(mut self, code: impl Into<String>) -> Self {
@@ -205,6 +236,15 @@ impl InvoiceBuilder {
         self
     }
 
+    /// Add a cash discount (Skonto) tier, e.g. 2% within 14 days.
+    ///
+    /// Rendered into BT-20 as `#SKONTO#TAGE=14#PROZENT=2.00#` ahead of any
+    /// free-text payment terms.
+    pub fn add_cash_discount(mut self, discount: CashDiscount) -> Self {
+        self.cash_discounts.push(discount);
+        self
+    }
+
     /// Set the payment instructions (BG-16) including means code and bank details.
     pub fn payment(mut self, payment: PaymentInstructions) -> Self {
         self.payment = Some(payment);
@@ -253,12 +293,69 @@ impl InvoiceBuilder {
         self
     }
 
+    /// Set the EUR exchange rate for a foreign-currency invoice.
+    ///
+    /// The tax currency (BT-6) defaults to EUR and the VAT total in EUR (BT-111)
+    /// is computed from the rate; [`Self::build`] fails if
+    /// [`Self::tax_currency`] named a different currency. Look rates up at the
+    /// tax point date with an
+    /// [`ExchangeRateProvider`](super::ExchangeRateProvider), or use
+    /// [`apply_exchange_rate`](super::apply_exchange_rate) after building.
+    /// An explicit [`Self::tax_currency`] total takes precedence for BT-111.
+    pub fn exchange_rate(mut self, rate: ExchangeRate) -> Self {
+        self.exchange_rate = Some(rate);
+        self
+    }
+
     /// Add a document attachment (BG-24). Maximum 100 attachments.
     pub fn add_attachment(mut self, attachment: DocumentAttachment) -> Self {
         self.attachments.push(attachment);
         self
     }
 
+    /// Add a referenced document: a tender or lot (BT-17, type "50"), an
+    /// invoiced object (BT-18, type "130") or, for ZUGFeRD Extended, any
+    /// other UNTDID 1001 document type.
+    pub fn add_additional_reference(mut self, reference: AdditionalReference) -> Self {
+        self.additional_references.push(reference);
+        self
+    }
+
+    /// Add a despatch advice reference (BT-16). ZUGFeRD Extended carries
+    /// several, EN 16931 only the first.
+    pub fn add_despatch_advice(mut self, id: impl Into<String>, date: Option<NaiveDate>) -> Self {
+        self.despatch_advices.push(DocumentReference {
+            id: id.into(),
+            issue_date: date,
+        });
+        self
+    }
+
+    /// Add a delivery note reference (ZUGFeRD Extended).
+    pub fn add_delivery_note(mut self, id: impl Into<String>, date: Option<NaiveDate>) -> Self {
+        self.delivery_notes.push(DocumentReference {
+            id: id.into(),
+            issue_date: date,
+        });
+        self
+    }
+
+    /// Add a logistics service charge such as freight (ZUGFeRD Extended).
+    ///
+    /// Counted like a document-level charge; formats without logistics
+    /// service charges write it as one.
+    pub fn add_logistics_charge(mut self, charge: LogisticsServiceCharge) -> Self {
+        self.logistics_charges.push(charge);
+        self
+    }
+
+    /// Add a payment instalment with its own due date, amount and
+    /// late-payment penalty (ZUGFeRD Extended).
+    pub fn add_payment_term(mut self, term: PaymentTerm) -> Self {
+        self.payment_schedule.push(term);
+        self
+    }
+
     /// Set delivery information (BG-13).
     pub fn delivery(mut self, delivery: DeliveryInformation) -> Self {
         self.delivery = Some(delivery);
@@ -349,6 +446,7 @@ impl InvoiceBuilder {
         }
 
         let vat_total_in_tax_currency = self.vat_total_in_tax_currency;
+        let exchange_rate = self.exchange_rate;
         let prepaid = self.prepaid;
 
         let mut invoice = Invoice {
@@ -358,6 +456,7 @@ impl InvoiceBuilder {
             type_code: self.type_code,
             currency_code: self.currency_code,
             tax_currency_code: self.tax_currency_code,
+            exchange_rate: None,
             notes: self.notes,
             buyer_reference: self.buyer_reference,
             project_reference: self.project_reference,
@@ -373,6 +472,7 @@ impl InvoiceBuilder {
             charges: self.charges,
             totals: None,
             payment_terms: self.payment_terms,
+            cash_discounts: self.cash_discounts,
             payment: self.payment,
             tax_point_date: self.tax_point_date,
             invoicing_period: self.invoicing_period,
@@ -380,11 +480,20 @@ impl InvoiceBuilder {
             tax_representative: self.tax_representative,
             preceding_invoices: self.preceding_invoices,
             attachments: self.attachments,
+            additional_references: self.additional_references,
+            despatch_advices: self.despatch_advices,
+            delivery_notes: self.delivery_notes,
+            logistics_charges: self.logistics_charges,
+            payment_schedule: self.payment_schedule,
             delivery: self.delivery,
         };
 
         validation::calculate_totals(&mut invoice, prepaid);
 
+        if let Some(rate) = exchange_rate {
+            super::exchange::set_exchange_rate(&mut invoice, rate)?;
+        }
+
         if let (Some(totals), Some(tax_total)) =
             (invoice.totals.as_mut(), vat_total_in_tax_currency)
         {
@@ -401,6 +510,7 @@ pub struct PartyBuilder {
     vat_id: Option<String>,
     tax_number: Option<String>,
     registration_id: Option<String>,
+    registration_scheme: Option<String>,
     trading_name: Option<String>,
     address: Address,
     contact: Option<Contact>,
@@ -415,6 +525,7 @@ impl PartyBuilder {
             vat_id: None,
             tax_number: None,
             registration_id: None,
+            registration_scheme: None,
             trading_name: None,
             address,
             contact: None,
@@ -440,6 +551,13 @@ impl PartyBuilder {
         self
     }
 
+    /// Set the ISO 6523 ICD scheme of the legal registration identifier
+    /// (BT-30-1/BT-47-1), e.g. `"0088"` for a GLN.
+    pub fn registration_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.registration_scheme = Some(scheme.into());
+        self
+    }
+
     /// Set the trading name / business name (BT-28).
     pub fn trading_name(mut self, name: impl Into<String>) -> Self {
         self.trading_name = Some(name.into());
@@ -478,6 +596,7 @@ impl PartyBuilder {
             vat_id: self.vat_id,
             tax_number: self.tax_number,
             registration_id: self.registration_id,
+            registration_scheme: self.registration_scheme,
             trading_name: self.trading_name,
             address: self.address,
             contact: self.contact,
@@ -566,6 +685,11 @@ pub struct LineItemBuilder {
     origin_country: Option<String>,
     attributes: Vec<ItemAttribute>,
     invoicing_period: Option<Period>,
+    order_line_id: Option<String>,
+    order_reference: Option<String>,
+    additional_references: Vec<AdditionalReference>,
+    parent_line_id: Option<String>,
+    line_status: Option<LineStatus>,
 }
 
 impl LineItemBuilder {
@@ -601,6 +725,11 @@ impl LineItemBuilder {
             origin_country: None,
             attributes: Vec::new(),
             invoicing_period: None,
+            order_line_id: None,
+            order_reference: None,
+            additional_references: Vec::new(),
+            parent_line_id: None,
+            line_status: None,
         }
     }
 
@@ -679,10 +808,67 @@ impl LineItemBuilder {
         self.attributes.push(ItemAttribute {
             name: name.into(),
             value: value.into(),
+            measure: None,
         });
         self
     }
 
+    /// Add a measured product characteristic, e.g. weight 2.5 KGM
+    /// (BT-160/BT-161; the unit needs ZUGFeRD Extended).
+    pub fn add_measured_attribute(
+        mut self,
+        name: impl Into<String>,
+        value: Decimal,
+        unit: impl Into<String>,
+    ) -> Self {
+        self.attributes.push(ItemAttribute {
+            name: name.into(),
+            value: value.normalize().to_string(),
+            measure: Some(Measure {
+                value,
+                unit: unit.into(),
+            }),
+        });
+        self
+    }
+
+    /// Set the referenced purchase order line (BT-132).
+    pub fn order_line_id(mut self, id: impl Into<String>) -> Self {
+        self.order_line_id = Some(id.into());
+        self
+    }
+
+    /// Set the purchase order of this line when it differs from the
+    /// document's (BT-13) (ZUGFeRD Extended).
+    pub fn order_reference(mut self, reference: impl Into<String>) -> Self {
+        self.order_reference = Some(reference.into());
+        self
+    }
+
+    /// Add a referenced document: an invoiced object (BT-128, type "130")
+    /// or, for ZUGFeRD Extended, any other UNTDID 1001 document type.
+    pub fn add_additional_reference(mut self, reference: AdditionalReference) -> Self {
+        self.additional_references.push(reference);
+        self
+    }
+
+    /// Make this line a sub-line of `parent_id` (ZUGFeRD Extended).
+    ///
+    /// Defaults the status to [`LineStatus::Detail`]: the sub-line is
+    /// counted in the totals, and its parent should be a
+    /// [`LineStatus::Group`] line, which is not.
+    pub fn parent_line(mut self, parent_id: impl Into<String>) -> Self {
+        self.parent_line_id = Some(parent_id.into());
+        self.line_status.get_or_insert(LineStatus::Detail);
+        self
+    }
+
+    /// Set the role of the line in a line group (ZUGFeRD Extended).
+    pub fn line_status(mut self, status: LineStatus) -> Self {
+        self.line_status = Some(status);
+        self
+    }
+
     /// Set the line-level invoicing period (BG-26).
     pub fn invoicing_period(mut self, start: NaiveDate, end: NaiveDate) -> Self {
         self.invoicing_period = Some(Period { start, end });
@@ -713,6 +899,11 @@ impl LineItemBuilder {
             origin_country: self.origin_country,
             attributes: self.attributes,
             invoicing_period: self.invoicing_period,
+            order_line_id: self.order_line_id,
+            order_reference: self.order_reference,
+            additional_references: self.additional_references,
+            parent_line_id: self.parent_line_id,
+            line_status: self.line_status,
         }
     }
 }
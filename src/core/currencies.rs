@@ -1,66 +1,263 @@
-//! ISO 4217 currency code validation.
+//! ISO 4217 currency codes and minor units.
 //!
-//! Provides a lookup of commonly used ISO 4217 currency codes for invoice
-//! validation. Covers all major world currencies relevant to European
-//! e-invoicing (EN 16931).
+//! Provides the active ISO 4217 currency codes together with their minor
+//! units (number of decimal places), so amounts can be rounded and
+//! formatted per currency: 0 for JPY/KRW, 2 for EUR/USD, 3 for BHD/KWD/TND.
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Decimal places used for currencies without ISO 4217 minor-unit data.
+pub const DEFAULT_MINOR_UNITS: u32 = 2;
+
+/// Most decimal places EN 16931 allows for amounts, in any currency (BR-DEC-*).
+pub const EN16931_MAX_DECIMALS: u32 = 2;
 
 /// Check whether `code` is a known ISO 4217 currency code.
 pub fn is_known_currency_code(code: &str) -> bool {
-    CURRENCY_CODES.binary_search(&code).is_ok()
+    lookup(code).is_some()
+}
+
+/// ISO 4217 minor units (decimal places) of `code`, e.g. 0 for JPY, 3 for KWD.
+///
+/// Returns `None` for unknown currency codes.
+pub fn minor_units(code: &str) -> Option<u32> {
+    lookup(code).map(|(_, units)| *units)
+}
+
+/// Decimal places to use for amounts in `code`.
+///
+/// Falls back to [`DEFAULT_MINOR_UNITS`] for unknown currencies.
+pub fn amount_scale(code: &str) -> u32 {
+    minor_units(code).unwrap_or(DEFAULT_MINOR_UNITS)
+}
+
+/// Decimal places of amounts in an EN 16931 invoice in `code`: the minor
+/// units, capped at [`EN16931_MAX_DECIMALS`] (0 for JPY, 2 for EUR and KWD).
+pub fn invoice_amount_scale(code: &str) -> u32 {
+    amount_scale(code).min(EN16931_MAX_DECIMALS)
 }
 
-/// Sorted list of common ISO 4217 currency codes.
-/// Sorted for binary search.
-static CURRENCY_CODES: &[&str] = &[
-    "AED", // UAE Dirham
-    "AMD", // Armenian Dram
-    "AUD", // Australian Dollar
-    "BGN", // Bulgarian Lev
-    "BRL", // Brazilian Real
-    "CAD", // Canadian Dollar
-    "CHF", // Swiss Franc
-    "CNY", // Chinese Yuan
-    "CZK", // Czech Koruna
-    "DKK", // Danish Krone
-    "EGP", // Egyptian Pound
-    "EUR", // Euro
-    "GBP", // Pound Sterling
-    "GEL", // Georgian Lari
-    "HKD", // Hong Kong Dollar
-    "HRK", // Croatian Kuna
-    "HUF", // Hungarian Forint
-    "IDR", // Indonesian Rupiah
-    "ILS", // Israeli Shekel
-    "INR", // Indian Rupee
-    "ISK", // Icelandic Krona
-    "JPY", // Japanese Yen
-    "KES", // Kenyan Shilling
-    "KRW", // South Korean Won
-    "KZT", // Kazakhstani Tenge
-    "MXN", // Mexican Peso
-    "MYR", // Malaysian Ringgit
-    "NGN", // Nigerian Naira
-    "NOK", // Norwegian Krone
-    "NZD", // New Zealand Dollar
-    "PHP", // Philippine Peso
-    "PLN", // Polish Zloty
-    "RON", // Romanian Leu
-    "RUB", // Russian Ruble
-    "SAR", // Saudi Riyal
-    "SEK", // Swedish Krona
-    "SGD", // Singapore Dollar
-    "THB", // Thai Baht
-    "TRY", // Turkish Lira
-    "TWD", // New Taiwan Dollar
-    "UAH", // Ukrainian Hryvnia
-    "USD", // US Dollar
-    "VND", // Vietnamese Dong
-    "ZAR", // South African Rand
+/// Round an amount of an EN 16931 invoice to [`invoice_amount_scale`].
+///
+/// ```
+/// use faktura::core::round_invoice_amount;
+/// use rust_decimal_macros::dec;
+///
+/// assert_eq!(round_invoice_amount(dec!(1234.5), "JPY"), dec!(1235));
+/// assert_eq!(round_invoice_amount(dec!(12.3455), "KWD"), dec!(12.35));
+/// ```
+pub fn round_invoice_amount(amount: Decimal, code: &str) -> Decimal {
+    amount.round_dp_with_strategy(
+        invoice_amount_scale(code),
+        RoundingStrategy::MidpointAwayFromZero,
+    )
+}
+
+/// Round an amount to the minor units of `code` (commercial rounding, half away from zero).
+///
+/// ```
+/// use faktura::core::round_amount;
+/// use rust_decimal_macros::dec;
+///
+/// assert_eq!(round_amount(dec!(1234.5), "JPY"), dec!(1235));
+/// assert_eq!(round_amount(dec!(12.3455), "KWD"), dec!(12.346));
+/// assert_eq!(round_amount(dec!(12.345), "EUR"), dec!(12.35));
+/// ```
+pub fn round_amount(amount: Decimal, code: &str) -> Decimal {
+    amount.round_dp_with_strategy(amount_scale(code), RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// Format an amount with exactly the minor units of `code`, using `.` as decimal separator.
+///
+/// ```
+/// use faktura::core::currencies::format_amount;
+/// use rust_decimal_macros::dec;
+///
+/// assert_eq!(format_amount(dec!(1500), "EUR"), "1500.00");
+/// assert_eq!(format_amount(dec!(1500), "JPY"), "1500");
+/// assert_eq!(format_amount(dec!(1.5), "BHD"), "1.500");
+/// ```
+pub fn format_amount(amount: Decimal, code: &str) -> String {
+    let scale = amount_scale(code);
+    format!("{:.*}", scale as usize, round_amount(amount, code))
+}
+
+fn lookup(code: &str) -> Option<&'static (&'static str, u32)> {
+    CURRENCIES
+        .binary_search_by(|(c, _)| c.cmp(&code))
+        .ok()
+        .map(|i| &CURRENCIES[i])
+}
+
+/// ISO 4217 currency codes with their minor units.
+/// Sorted by code for binary search.
+static CURRENCIES: &[(&str, u32)] = &[
+    ("AED", 2), // UAE Dirham
+    ("AFN", 2), // Afghani
+    ("ALL", 2), // Albanian Lek
+    ("AMD", 2), // Armenian Dram
+    ("ANG", 2), // Netherlands Antillean Guilder
+    ("AOA", 2), // Angolan Kwanza
+    ("ARS", 2), // Argentine Peso
+    ("AUD", 2), // Australian Dollar
+    ("AWG", 2), // Aruban Florin
+    ("AZN", 2), // Azerbaijan Manat
+    ("BAM", 2), // Convertible Mark
+    ("BBD", 2), // Barbados Dollar
+    ("BDT", 2), // Bangladeshi Taka
+    ("BGN", 2), // Bulgarian Lev
+    ("BHD", 3), // Bahraini Dinar
+    ("BIF", 0), // Burundi Franc
+    ("BMD", 2), // Bermudian Dollar
+    ("BND", 2), // Brunei Dollar
+    ("BOB", 2), // Boliviano
+    ("BRL", 2), // Brazilian Real
+    ("BSD", 2), // Bahamian Dollar
+    ("BTN", 2), // Ngultrum
+    ("BWP", 2), // Botswana Pula
+    ("BYN", 2), // Belarusian Ruble
+    ("BZD", 2), // Belize Dollar
+    ("CAD", 2), // Canadian Dollar
+    ("CDF", 2), // Congolese Franc
+    ("CHF", 2), // Swiss Franc
+    ("CLF", 4), // Unidad de Fomento
+    ("CLP", 0), // Chilean Peso
+    ("CNY", 2), // Chinese Yuan
+    ("COP", 2), // Colombian Peso
+    ("CRC", 2), // Costa Rican Colon
+    ("CUP", 2), // Cuban Peso
+    ("CVE", 2), // Cabo Verde Escudo
+    ("CZK", 2), // Czech Koruna
+    ("DJF", 0), // Djibouti Franc
+    ("DKK", 2), // Danish Krone
+    ("DOP", 2), // Dominican Peso
+    ("DZD", 2), // Algerian Dinar
+    ("EGP", 2), // Egyptian Pound
+    ("ERN", 2), // Nakfa
+    ("ETB", 2), // Ethiopian Birr
+    ("EUR", 2), // Euro
+    ("FJD", 2), // Fiji Dollar
+    ("FKP", 2), // Falkland Islands Pound
+    ("GBP", 2), // Pound Sterling
+    ("GEL", 2), // Georgian Lari
+    ("GHS", 2), // Ghana Cedi
+    ("GIP", 2), // Gibraltar Pound
+    ("GMD", 2), // Gambian Dalasi
+    ("GNF", 0), // Guinean Franc
+    ("GTQ", 2), // Guatemalan Quetzal
+    ("GYD", 2), // Guyana Dollar
+    ("HKD", 2), // Hong Kong Dollar
+    ("HNL", 2), // Honduran Lempira
+    ("HRK", 2), // Croatian Kuna
+    ("HTG", 2), // Haitian Gourde
+    ("HUF", 2), // Hungarian Forint
+    ("IDR", 2), // Indonesian Rupiah
+    ("ILS", 2), // Israeli Shekel
+    ("INR", 2), // Indian Rupee
+    ("IQD", 3), // Iraqi Dinar
+    ("IRR", 2), // Iranian Rial
+    ("ISK", 0), // Icelandic Krona
+    ("JMD", 2), // Jamaican Dollar
+    ("JOD", 3), // Jordanian Dinar
+    ("JPY", 0), // Japanese Yen
+    ("KES", 2), // Kenyan Shilling
+    ("KGS", 2), // Kyrgyzstani Som
+    ("KHR", 2), // Cambodian Riel
+    ("KMF", 0), // Comorian Franc
+    ("KPW", 2), // North Korean Won
+    ("KRW", 0), // South Korean Won
+    ("KWD", 3), // Kuwaiti Dinar
+    ("KYD", 2), // Cayman Islands Dollar
+    ("KZT", 2), // Kazakhstani Tenge
+    ("LAK", 2), // Lao Kip
+    ("LBP", 2), // Lebanese Pound
+    ("LKR", 2), // Sri Lanka Rupee
+    ("LRD", 2), // Liberian Dollar
+    ("LSL", 2), // Lesotho Loti
+    ("LYD", 3), // Libyan Dinar
+    ("MAD", 2), // Moroccan Dirham
+    ("MDL", 2), // Moldovan Leu
+    ("MGA", 2), // Malagasy Ariary
+    ("MKD", 2), // Macedonian Denar
+    ("MMK", 2), // Myanmar Kyat
+    ("MNT", 2), // Mongolian Tugrik
+    ("MOP", 2), // Macao Pataca
+    ("MRU", 2), // Mauritanian Ouguiya
+    ("MUR", 2), // Mauritius Rupee
+    ("MVR", 2), // Maldivian Rufiyaa
+    ("MWK", 2), // Malawi Kwacha
+    ("MXN", 2), // Mexican Peso
+    ("MYR", 2), // Malaysian Ringgit
+    ("MZN", 2), // Mozambique Metical
+    ("NAD", 2), // Namibia Dollar
+    ("NGN", 2), // Nigerian Naira
+    ("NIO", 2), // Cordoba Oro
+    ("NOK", 2), // Norwegian Krone
+    ("NPR", 2), // Nepalese Rupee
+    ("NZD", 2), // New Zealand Dollar
+    ("OMR", 3), // Rial Omani
+    ("PAB", 2), // Panamanian Balboa
+    ("PEN", 2), // Peruvian Sol
+    ("PGK", 2), // Papua New Guinean Kina
+    ("PHP", 2), // Philippine Peso
+    ("PKR", 2), // Pakistan Rupee
+    ("PLN", 2), // Polish Zloty
+    ("PYG", 0), // Paraguayan Guarani
+    ("QAR", 2), // Qatari Rial
+    ("RON", 2), // Romanian Leu
+    ("RSD", 2), // Serbian Dinar
+    ("RUB", 2), // Russian Ruble
+    ("RWF", 0), // Rwanda Franc
+    ("SAR", 2), // Saudi Riyal
+    ("SBD", 2), // Solomon Islands Dollar
+    ("SCR", 2), // Seychelles Rupee
+    ("SDG", 2), // Sudanese Pound
+    ("SEK", 2), // Swedish Krona
+    ("SGD", 2), // Singapore Dollar
+    ("SHP", 2), // Saint Helena Pound
+    ("SLE", 2), // Sierra Leonean Leone
+    ("SOS", 2), // Somali Shilling
+    ("SRD", 2), // Surinam Dollar
+    ("SSP", 2), // South Sudanese Pound
+    ("STN", 2), // Dobra
+    ("SVC", 2), // El Salvador Colon
+    ("SYP", 2), // Syrian Pound
+    ("SZL", 2), // Lilangeni
+    ("THB", 2), // Thai Baht
+    ("TJS", 2), // Somoni
+    ("TMT", 2), // Turkmenistan New Manat
+    ("TND", 3), // Tunisian Dinar
+    ("TOP", 2), // Pa'anga
+    ("TRY", 2), // Turkish Lira
+    ("TTD", 2), // Trinidad and Tobago Dollar
+    ("TWD", 2), // New Taiwan Dollar
+    ("TZS", 2), // Tanzanian Shilling
+    ("UAH", 2), // Ukrainian Hryvnia
+    ("UGX", 0), // Uganda Shilling
+    ("USD", 2), // US Dollar
+    ("UYI", 0), // Uruguay Peso en Unidades Indexadas
+    ("UYU", 2), // Peso Uruguayo
+    ("UYW", 4), // Unidad Previsional
+    ("UZS", 2), // Uzbekistan Sum
+    ("VED", 2), // Bolivar Digital
+    ("VES", 2), // Bolivar Soberano
+    ("VND", 0), // Vietnamese Dong
+    ("VUV", 0), // Vanuatu Vatu
+    ("WST", 2), // Samoan Tala
+    ("XAF", 0), // CFA Franc BEAC
+    ("XCD", 2), // East Caribbean Dollar
+    ("XOF", 0), // CFA Franc BCEAO
+    ("XPF", 0), // CFP Franc
+    ("YER", 2), // Yemeni Rial
+    ("ZAR", 2), // South African Rand
+    ("ZMW", 2), // Zambian Kwacha
+    ("ZWG", 2), // Zimbabwe Gold
 ];
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn known_currencies() {
@@ -82,13 +279,34 @@ mod tests {
 
     #[test]
     fn list_is_sorted() {
-        for window in CURRENCY_CODES.windows(2) {
+        for window in CURRENCIES.windows(2) {
             assert!(
-                window[0] < window[1],
+                window[0].0 < window[1].0,
                 "currency codes not sorted: {} >= {}",
-                window[0],
-                window[1]
+                window[0].0,
+                window[1].0
             );
         }
     }
+
+    #[test]
+    fn minor_unit_exceptions() {
+        assert_eq!(minor_units("EUR"), Some(2));
+        assert_eq!(minor_units("JPY"), Some(0));
+        assert_eq!(minor_units("KRW"), Some(0));
+        assert_eq!(minor_units("BHD"), Some(3));
+        assert_eq!(minor_units("KWD"), Some(3));
+        assert_eq!(minor_units("TND"), Some(3));
+        assert_eq!(minor_units("CLF"), Some(4));
+        assert_eq!(minor_units("XYZ"), None);
+        assert_eq!(amount_scale("XYZ"), DEFAULT_MINOR_UNITS);
+    }
+
+    #[test]
+    fn rounding_per_currency() {
+        assert_eq!(round_amount(dec!(99.5), "KRW"), dec!(100));
+        assert_eq!(round_amount(dec!(-0.0005), "TND"), dec!(-0.001));
+        assert_eq!(format_amount(dec!(0.1), "XYZ"), "0.10");
+        assert_eq!(format_amount(dec!(7), "KWD"), "7.000");
+    }
 }
@@ -0,0 +1,122 @@
+//! Minimal DER reading for X.509 certificates and the CMS and PKCS#12
+//! structures around them.
+
+#![cfg_attr(not(feature = "signature"), allow(dead_code))]
+
+use chrono::{Datelike, NaiveDateTime};
+
+use super::digest::hex;
+
+/// The DER-encoded `SubjectPublicKeyInfo` of a certificate.
+pub(crate) fn subject_public_key_info(der: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = read_tlv(der, 0x30)?;
+    let (tbs, _) = read_tlv(cert, 0x30)?;
+    let mut rest = tbs;
+    if rest.first() == Some(&0xa0) {
+        rest = read_any(rest)?.2;
+    }
+    // serial, signature algorithm, issuer, validity, subject
+    for _ in 0..5 {
+        rest = read_any(rest)?.2;
+    }
+    let (tag, _, after) = read_any(rest)?;
+    (tag == 0x30).then(|| &rest[..rest.len() - after.len()])
+}
+
+/// Read one TLV; returns tag, content and the remaining input.
+pub(crate) fn read_any(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let n = first & 0x7f;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = input
+            .get(2..2 + n)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + n)
+    };
+    let content = input.get(header..header + len)?;
+    Some((tag, content, &input[header + len..]))
+}
+
+/// Read one TLV with the expected tag.
+pub(crate) fn read_tlv(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (found, content, rest) = read_any(input)?;
+    (found == tag).then_some((content, rest))
+}
+
+/// Render a Name as `CN=…,O=…,C=…` (most specific first, as in RFC 4514).
+pub(crate) fn distinguished_name(name: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut rdns = name;
+    while !rdns.is_empty() {
+        let (set, rest) = read_tlv(rdns, 0x31)?;
+        rdns = rest;
+        let mut attrs = set;
+        while !attrs.is_empty() {
+            let (attr, rest) = read_tlv(attrs, 0x30)?;
+            attrs = rest;
+            let (oid, value) = read_tlv(attr, 0x06)?;
+            let (_, value, _) = read_any(value)?;
+            let key = match oid {
+                [0x55, 0x04, 0x03] => "CN".to_string(),
+                [0x55, 0x04, 0x06] => "C".to_string(),
+                [0x55, 0x04, 0x07] => "L".to_string(),
+                [0x55, 0x04, 0x08] => "ST".to_string(),
+                [0x55, 0x04, 0x0a] => "O".to_string(),
+                [0x55, 0x04, 0x0b] => "OU".to_string(),
+                [0x55, 0x04, 0x05] => "SERIALNUMBER".to_string(),
+                other => format!("OID.{}", hex(other)),
+            };
+            parts.push(format!("{key}={}", String::from_utf8_lossy(value)));
+        }
+    }
+    parts.reverse();
+    Some(parts.join(","))
+}
+
+/// Parse a UTCTime (0x17) or GeneralizedTime (0x18) in UTC.
+pub(crate) fn parse_time(tag: u8, value: &[u8]) -> Option<NaiveDateTime> {
+    let text = std::str::from_utf8(value).ok()?.trim_end_matches('Z');
+    match tag {
+        0x17 => {
+            let dt = NaiveDateTime::parse_from_str(&format!("20{text}"), "%Y%m%d%H%M%S").ok()?;
+            // RFC 5280: two-digit years 50–99 are 19xx
+            Some(if text.get(..2)? >= "50" {
+                dt.with_year(dt.year() - 100)?
+            } else {
+                dt
+            })
+        }
+        // Fractional seconds (RFC 3161 genTime) are dropped
+        0x18 => NaiveDateTime::parse_from_str(
+            text.split_once('.').map_or(text, |(whole, _)| whole),
+            "%Y%m%d%H%M%S",
+        )
+        .ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn utc_time_century() {
+        let t = parse_time(0x17, b"240601120000Z").unwrap();
+        assert_eq!(t.date(), NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+        let t = parse_time(0x17, b"990101000000Z").unwrap();
+        assert_eq!(t.date(), NaiveDate::from_ymd_opt(1999, 1, 1).unwrap());
+        let t = parse_time(0x18, b"20501231235959Z").unwrap();
+        assert_eq!(t.date(), NaiveDate::from_ymd_opt(2050, 12, 31).unwrap());
+        let t = parse_time(0x18, b"20260301101500.25Z").unwrap();
+        assert_eq!(t.to_string(), "2026-03-01 10:15:00");
+    }
+}
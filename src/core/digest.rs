@@ -0,0 +1,118 @@
+//! Encodings used for Peppol SML names, SMP certificates and electronic
+//! signatures. Digests come from the `sha2` and `md-5` crates.
+
+#![cfg_attr(not(feature = "peppol-smp"), allow(dead_code))]
+
+/// Lowercase hexadecimal.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Base32 (RFC 4648) without padding.
+pub(crate) fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+/// Standard Base64 with padding.
+#[cfg(any(feature = "as4", feature = "signature"))]
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode padded standard Base64, ignoring whitespace.
+///
+/// Strict, since it feeds signature verification: data after the padding,
+/// missing or excess padding and non-zero leftover bits are rejected.
+pub(crate) fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    let (mut symbols, mut padding) = (0usize, 0usize);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        symbols += 1;
+        if c == b'=' {
+            padding += 1;
+            continue;
+        }
+        if padding > 0 {
+            return None;
+        }
+        buffer = (buffer << 6) | u32::from(value(c)?);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    let leftover = buffer & ((1 << bits) - 1);
+    let valid = symbols % 4 == 0 && bits < 6 && padding == (bits / 2) as usize && leftover == 0;
+    valid.then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings() {
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base64_decode("Zm9v\nYmFy").unwrap(), b"foobar");
+        assert_eq!(base64_decode("Zm8=").unwrap(), b"fo");
+        assert!(base64_decode("Zm9v!").is_none());
+        assert!(base64_decode("Zm8=Zm9v").is_none());
+        assert!(base64_decode("Zm8=AA==").is_none());
+        assert!(base64_decode("Zm9=").is_none());
+        assert!(base64_decode("Zm8").is_none());
+        assert!(base64_decode("Zm8==").is_none());
+        assert!(base64_decode("Zg=").is_none());
+        assert!(base64_decode("A===").is_none());
+        assert!(base64_decode("Zg==").is_some());
+    }
+
+    #[cfg(any(feature = "as4", feature = "signature"))]
+    #[test]
+    fn base64_roundtrip() {
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(base64_decode(&base64_encode(&bytes)).unwrap(), bytes);
+    }
+}
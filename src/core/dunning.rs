@@ -0,0 +1,571 @@
+//! Dunning (Mahnwesen) and default interest under §288 BGB.
+//!
+//! A [`DunningPolicy`] maps the days an open item is overdue to a dunning
+//! level with its fee, and computes what may be claimed on top of the open
+//! amount: default interest at the base rate plus 5 (consumers) or 9
+//! (businesses) percentage points, and the €40 Verzugspauschale of
+//! §288 Abs. 5 BGB for business debtors. Interest is calculated day by day
+//! against the Bundesbank base rate valid for each half-year
+//! ([`BaseRateTable`]) and the balance of the item on that day.
+//!
+//! A [`DunningNotice`] can be turned into a reminder invoice that references
+//! the original invoice (BG-3) and charges interest and fees as lines not
+//! subject to VAT.
+
+use chrono::{Datelike, Days, NaiveDate};
+use rust_decimal::Decimal;
+
+use super::RechnungError;
+use super::builder::{InvoiceBuilder, LineItemBuilder};
+use super::currencies::round_amount;
+use super::ledger::{OpenItem, OpenItemsLedger};
+use super::types::{Invoice, Party, TaxCategory};
+
+/// Basiszinssatz (§247 BGB) as published by the Deutsche Bundesbank:
+/// (year, month, rate in hundredths of a percent), valid from the first day
+/// of the month.
+const BUNDESBANK_BASE_RATES: &[(i32, u32, i64)] = &[
+    (2002, 1, 257),
+    (2002, 7, 247),
+    (2003, 1, 197),
+    (2003, 7, 122),
+    (2004, 1, 114),
+    (2004, 7, 113),
+    (2005, 1, 121),
+    (2005, 7, 117),
+    (2006, 1, 137),
+    (2006, 7, 195),
+    (2007, 1, 270),
+    (2007, 7, 319),
+    (2008, 1, 332),
+    (2008, 7, 319),
+    (2009, 1, 162),
+    (2009, 7, 12),
+    (2011, 7, 37),
+    (2012, 1, 12),
+    (2013, 1, -13),
+    (2013, 7, -38),
+    (2014, 1, -63),
+    (2014, 7, -73),
+    (2015, 1, -83),
+    (2016, 7, -88),
+    (2023, 1, 162),
+    (2023, 7, 312),
+    (2024, 1, 362),
+    (2024, 7, 337),
+    (2025, 1, 227),
+    (2025, 7, 127),
+];
+
+/// Lump sum for business debtors in default (§288 Abs. 5 BGB), in EUR.
+const LUMP_SUM_EUR: Decimal = Decimal::from_parts(40, 0, 0, false, 0);
+
+/// Base interest rates (Basiszinssätze) by validity date.
+///
+/// The built-in table ends with the last published rate; it stays in force
+/// for later dates until newer rates are added with [`with_rate`](Self::with_rate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseRateTable {
+    rates: Vec<(NaiveDate, Decimal)>,
+}
+
+impl BaseRateTable {
+    /// Rates published by the Deutsche Bundesbank since 2002.
+    pub fn bundesbank() -> Self {
+        Self {
+            rates: BUNDESBANK_BASE_RATES
+                .iter()
+                .filter_map(|&(y, m, rate)| {
+                    NaiveDate::from_ymd_opt(y, m, 1).map(|d| (d, Decimal::new(rate, 2)))
+                })
+                .collect(),
+        }
+    }
+
+    /// Add or replace the rate valid from `from`.
+    pub fn with_rate(mut self, from: NaiveDate, rate: Decimal) -> Self {
+        match self.rates.binary_search_by_key(&from, |(d, _)| *d) {
+            Ok(i) => self.rates[i].1 = rate,
+            Err(i) => self.rates.insert(i, (from, rate)),
+        }
+        self
+    }
+
+    /// Base rate in percent valid on `date`, if the table covers it.
+    pub fn rate_at(&self, date: NaiveDate) -> Option<Decimal> {
+        self.rates
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= date)
+            .map(|(_, rate)| *rate)
+    }
+}
+
+impl Default for BaseRateTable {
+    fn default() -> Self {
+        Self::bundesbank()
+    }
+}
+
+/// Whether the debtor is a consumer or a business (§288 Abs. 1 / Abs. 2 BGB).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebtorKind {
+    /// Consumer: base rate + 5 percentage points.
+    Consumer,
+    /// Business (no consumer involved): base rate + 9 percentage points
+    /// and the €40 lump sum.
+    Business,
+}
+
+impl DebtorKind {
+    /// Classify a buyer: business if it has a VAT identifier, tax number or
+    /// legal registration identifier.
+    pub fn for_party(party: &Party) -> Self {
+        if party.vat_id.is_some() || party.tax_number.is_some() || party.registration_id.is_some() {
+            Self::Business
+        } else {
+            Self::Consumer
+        }
+    }
+
+    /// Percentage points above the base rate.
+    pub fn interest_margin(&self) -> Decimal {
+        match self {
+            Self::Consumer => Decimal::from(5),
+            Self::Business => Decimal::from(9),
+        }
+    }
+}
+
+/// Default interest on `principal` for each day from `from` to `to`
+/// (both inclusive), rounded to the minor units of `currency`.
+///
+/// The period is split at the half-year boundaries at which the base rate
+/// changes; each day bears 1/365 (1/366 in leap years) of the annual rate.
+pub fn default_interest(
+    principal: Decimal,
+    from: NaiveDate,
+    to: NaiveDate,
+    kind: DebtorKind,
+    rates: &BaseRateTable,
+    currency: &str,
+) -> Decimal {
+    round_amount(interest_exact(principal, from, to, kind, rates), currency)
+}
+
+fn interest_exact(
+    principal: Decimal,
+    from: NaiveDate,
+    to: NaiveDate,
+    kind: DebtorKind,
+    rates: &BaseRateTable,
+) -> Decimal {
+    let mut interest = Decimal::ZERO;
+    let mut start = from;
+    while start <= to {
+        let end = next_half_year(start).pred_opt().unwrap_or(start).min(to);
+        let days = Decimal::from((end - start).num_days() + 1);
+        let rate = rates.rate_at(start).unwrap_or_default() + kind.interest_margin();
+        let year_days = Decimal::from(if start.leap_year() { 366 } else { 365 });
+        interest += principal * rate / Decimal::ONE_HUNDRED * days / year_days;
+        start = end.succ_opt().unwrap_or(end);
+        if end == to {
+            break;
+        }
+    }
+    interest
+}
+
+/// First day of the half-year after the one containing `date`.
+fn next_half_year(date: NaiveDate) -> NaiveDate {
+    let next = if date.month() < 7 {
+        NaiveDate::from_ymd_opt(date.year(), 7, 1)
+    } else {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+    };
+    next.unwrap_or(date)
+}
+
+/// First day of default of an open item: the day after the due date, or
+/// 30 days after the issue date if no due date was agreed (§286 Abs. 3 BGB).
+pub fn default_start(item: &OpenItem) -> NaiveDate {
+    match item.due_date {
+        Some(due) => due + Days::new(1),
+        None => item.issue_date + Days::new(31),
+    }
+}
+
+/// One dunning level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DunningLevel {
+    /// Level number, starting at 1.
+    pub level: u8,
+    /// Title of the notice, e.g. "1. Mahnung".
+    pub title: String,
+    /// Days past the due date from which this level applies.
+    pub days_overdue: i64,
+    /// Fee charged with this notice.
+    pub fee: Decimal,
+    /// Days the buyer is given to pay after the notice.
+    pub payment_days: u64,
+}
+
+impl DunningLevel {
+    /// Create a level.
+    pub fn new(level: u8, title: impl Into<String>, days_overdue: i64, fee: Decimal) -> Self {
+        Self {
+            level,
+            title: title.into(),
+            days_overdue,
+            fee,
+            payment_days: 10,
+        }
+    }
+
+    /// Set the payment period granted by the notice.
+    pub fn payment_days(mut self, days: u64) -> Self {
+        self.payment_days = days;
+        self
+    }
+}
+
+/// Dunning levels and the claims made with them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DunningPolicy {
+    /// Levels, ordered by `days_overdue`.
+    pub levels: Vec<DunningLevel>,
+    /// First level at which default interest and the lump sum are claimed.
+    pub claims_from_level: u8,
+    /// Base rates used for interest.
+    pub rates: BaseRateTable,
+}
+
+impl Default for DunningPolicy {
+    /// Payment reminder after 7 days, first and second Mahnung after 21 and
+    /// 35 days (€5 each), final Mahnung after 49 days (€10). Interest and
+    /// lump sum are claimed from the first Mahnung.
+    fn default() -> Self {
+        Self {
+            levels: vec![
+                DunningLevel::new(1, "Zahlungserinnerung", 7, Decimal::ZERO),
+                DunningLevel::new(2, "1. Mahnung", 21, Decimal::from(5)),
+                DunningLevel::new(3, "2. Mahnung", 35, Decimal::from(5)),
+                DunningLevel::new(4, "Letzte Mahnung", 49, Decimal::from(10)).payment_days(7),
+            ],
+            claims_from_level: 2,
+            rates: BaseRateTable::bundesbank(),
+        }
+    }
+}
+
+impl DunningPolicy {
+    /// Policy without levels; add them with [`with_level`](Self::with_level).
+    pub fn new() -> Self {
+        Self {
+            levels: Vec::new(),
+            claims_from_level: 1,
+            rates: BaseRateTable::bundesbank(),
+        }
+    }
+
+    /// Add a level.
+    pub fn with_level(mut self, level: DunningLevel) -> Self {
+        self.levels.push(level);
+        self.levels.sort_by_key(|l| l.days_overdue);
+        self
+    }
+
+    /// Set the first level at which interest and lump sum are claimed.
+    pub fn claims_from_level(mut self, level: u8) -> Self {
+        self.claims_from_level = level;
+        self
+    }
+
+    /// Use a different base rate table.
+    pub fn with_rates(mut self, rates: BaseRateTable) -> Self {
+        self.rates = rates;
+        self
+    }
+
+    /// Highest level reached after `days_overdue` days.
+    pub fn level_for(&self, days_overdue: i64) -> Option<&DunningLevel> {
+        self.levels
+            .iter()
+            .filter(|l| days_overdue >= l.days_overdue)
+            .max_by_key(|l| l.days_overdue)
+    }
+
+    /// Default interest accrued on an item from its first day of default
+    /// through `as_of`, on the balance outstanding each day.
+    pub fn interest(&self, item: &OpenItem, as_of: NaiveDate) -> Decimal {
+        let start = default_start(item);
+        if as_of < start {
+            return Decimal::ZERO;
+        }
+        // Balance changes on posting dates; a payment stops interest on its day
+        let mut breaks: Vec<NaiveDate> = item
+            .postings
+            .iter()
+            .map(|p| p.date)
+            .filter(|d| *d > start && *d <= as_of)
+            .collect();
+        breaks.sort();
+        breaks.dedup();
+
+        let mut interest = Decimal::ZERO;
+        let mut from = start;
+        for next in breaks.into_iter().chain(as_of.succ_opt()) {
+            let balance = item.outstanding_at(from);
+            if balance > Decimal::ZERO {
+                let to = next.pred_opt().unwrap_or(next);
+                interest += interest_exact(balance, from, to, item.debtor, &self.rates);
+            }
+            from = next;
+        }
+        round_amount(interest, &item.currency)
+    }
+
+    /// Notice for an open item at `as_of`, if it has reached a level.
+    ///
+    /// Fees of all levels up to the one reached are included, as each earlier
+    /// notice is assumed to have been sent. For business debtors the €40 lump
+    /// sum (EUR invoices only) is credited against these fees (§288 Abs. 5
+    /// Satz 3 BGB), so only fees exceeding it are charged.
+    pub fn notice(&self, item: &OpenItem, as_of: NaiveDate) -> Option<DunningNotice> {
+        let outstanding = item.outstanding_at(as_of);
+        if outstanding <= Decimal::ZERO {
+            return None;
+        }
+        let default_since = default_start(item);
+        let days_overdue = (as_of - default_since).num_days() + 1;
+        let level = self.level_for(days_overdue)?;
+
+        let mut fees: Decimal = self
+            .levels
+            .iter()
+            .filter(|l| l.days_overdue <= level.days_overdue)
+            .map(|l| l.fee)
+            .sum();
+        let claims = level.level >= self.claims_from_level;
+        let mut lump_sum = Decimal::ZERO;
+        if claims && item.debtor == DebtorKind::Business && item.currency == "EUR" {
+            lump_sum = LUMP_SUM_EUR;
+            fees = (fees - LUMP_SUM_EUR).max(Decimal::ZERO);
+        }
+        let (interest, interest_rate) = if claims {
+            (
+                self.interest(item, as_of),
+                self.rates.rate_at(as_of).unwrap_or_default() + item.debtor.interest_margin(),
+            )
+        } else {
+            (Decimal::ZERO, Decimal::ZERO)
+        };
+
+        Some(DunningNotice {
+            invoice_number: item.number.clone(),
+            buyer: item.buyer.clone(),
+            currency: item.currency.clone(),
+            level: level.level,
+            title: level.title.clone(),
+            as_of,
+            default_since,
+            days_overdue,
+            outstanding,
+            interest,
+            interest_rate,
+            fees: round_amount(fees, &item.currency),
+            lump_sum,
+            payment_deadline: as_of + Days::new(level.payment_days),
+        })
+    }
+
+    /// Notices for all overdue items of a ledger, ordered by invoice number.
+    pub fn notices(&self, ledger: &OpenItemsLedger, as_of: NaiveDate) -> Vec<DunningNotice> {
+        ledger
+            .open_items(as_of)
+            .into_iter()
+            .filter_map(|item| self.notice(item, as_of))
+            .collect()
+    }
+}
+
+/// A dunning notice for one invoice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DunningNotice {
+    /// Number of the overdue invoice.
+    pub invoice_number: String,
+    /// Buyer name.
+    pub buyer: String,
+    /// Invoice currency.
+    pub currency: String,
+    /// Dunning level reached.
+    pub level: u8,
+    /// Title of the notice.
+    pub title: String,
+    /// Date of the notice.
+    pub as_of: NaiveDate,
+    /// First day of default.
+    pub default_since: NaiveDate,
+    /// Days in default at `as_of`.
+    pub days_overdue: i64,
+    /// Amount of the invoice still open.
+    pub outstanding: Decimal,
+    /// Default interest accrued.
+    pub interest: Decimal,
+    /// Annual interest rate in percent at `as_of` (base rate + margin).
+    pub interest_rate: Decimal,
+    /// Dunning fees charged.
+    pub fees: Decimal,
+    /// Verzugspauschale (§288 Abs. 5 BGB).
+    pub lump_sum: Decimal,
+    /// Date by which the total should be paid.
+    pub payment_deadline: NaiveDate,
+}
+
+impl DunningNotice {
+    /// Interest, fees and lump sum claimed on top of the open amount.
+    pub fn charges(&self) -> Decimal {
+        self.interest + self.fees + self.lump_sum
+    }
+
+    /// Total amount demanded.
+    pub fn total(&self) -> Decimal {
+        self.outstanding + self.charges()
+    }
+
+    /// Build a reminder invoice charging interest, fees and lump sum.
+    ///
+    /// Seller, buyer and payment instructions are taken from `original`,
+    /// which is referenced as preceding invoice (BG-3). The charges are
+    /// damages rather than consideration for a supply, so the lines use the
+    /// "not subject to VAT" category (O), and the seller and buyer VAT
+    /// identifiers are left out as BR-O-02 requires; the seller therefore
+    /// needs a tax number (BT-32). The open amount of the original invoice is
+    /// stated in a note.
+    pub fn to_invoice(
+        &self,
+        original: &Invoice,
+        number: impl Into<String>,
+        issue_date: NaiveDate,
+    ) -> Result<Invoice, RechnungError> {
+        if original.number != self.invoice_number {
+            return Err(RechnungError::Builder(format!(
+                "notice is for invoice {}, not {}",
+                self.invoice_number, original.number
+            )));
+        }
+        if self.charges().is_zero() {
+            return Err(RechnungError::Builder(format!(
+                "{} for invoice {} has no charges to invoice",
+                self.title, self.invoice_number
+            )));
+        }
+
+        let charges = [
+            (
+                format!(
+                    "Verzugszinsen {} % p.a. seit {}",
+                    self.interest_rate.normalize(),
+                    self.default_since.format("%d.%m.%Y")
+                ),
+                self.interest,
+            ),
+            ("Mahngebühren".to_string(), self.fees),
+            (
+                "Verzugspauschale gemäß §288 Abs. 5 BGB".to_string(),
+                self.lump_sum,
+            ),
+        ];
+        if original.seller.tax_number.is_none() {
+            return Err(RechnungError::Builder(format!(
+                "{} for invoice {} needs a seller tax number: VAT identifiers \
+                 are not allowed on lines not subject to VAT (BR-O-02)",
+                self.title, self.invoice_number
+            )));
+        }
+        let mut seller = original.seller.clone();
+        seller.vat_id = None;
+        let mut buyer = original.buyer.clone();
+        buyer.vat_id = None;
+        let mut builder = InvoiceBuilder::new(number, issue_date)
+            .currency(&original.currency_code)
+            .tax_point_date(issue_date)
+            .due_date(self.payment_deadline)
+            .seller(seller)
+            .buyer(buyer)
+            .add_preceding_invoice(&original.number, Some(original.issue_date))
+            .note(format!(
+                "{}: Rechnung {} vom {} ist seit {} überfällig. Offener Betrag {} {}, \
+                 insgesamt zu zahlen {} {} bis {}.",
+                self.title,
+                original.number,
+                original.issue_date.format("%d.%m.%Y"),
+                self.default_since.format("%d.%m.%Y"),
+                self.outstanding,
+                self.currency,
+                self.total(),
+                self.currency,
+                self.payment_deadline.format("%d.%m.%Y"),
+            ));
+        if let Some(reference) = &original.buyer_reference {
+            builder = builder.buyer_reference(reference);
+        }
+        if let Some(payment) = &original.payment {
+            builder = builder.payment(payment.clone());
+        }
+        let mut id = 0;
+        for (name, amount) in charges {
+            if amount.is_zero() {
+                continue;
+            }
+            id += 1;
+            builder = builder.add_line(
+                LineItemBuilder::new(id.to_string(), name, Decimal::ONE, "C62", amount)
+                    .tax(TaxCategory::NotSubjectToVat, Decimal::ZERO)
+                    .build(),
+            );
+        }
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn base_rates() {
+        let rates = BaseRateTable::bundesbank();
+        assert_eq!(rates.rate_at(date(2001, 12, 31)), None);
+        assert_eq!(rates.rate_at(date(2010, 3, 1)), Some(dec!(0.12)));
+        assert_eq!(rates.rate_at(date(2020, 1, 1)), Some(dec!(-0.88)));
+        assert_eq!(rates.rate_at(date(2024, 6, 30)), Some(dec!(3.62)));
+        assert_eq!(rates.rate_at(date(2024, 7, 1)), Some(dec!(3.37)));
+
+        let extended = rates.with_rate(date(2099, 1, 1), dec!(2));
+        assert_eq!(extended.rate_at(date(2099, 2, 1)), Some(dec!(2)));
+    }
+
+    #[test]
+    fn interest_across_rate_change() {
+        let rates = BaseRateTable::bundesbank();
+        // 1000 EUR, B2B: 30 days at 12.62 % and 31 days at 12.37 % (leap year)
+        let interest = default_interest(
+            dec!(1000),
+            date(2024, 6, 1),
+            date(2024, 7, 31),
+            DebtorKind::Business,
+            &rates,
+            "EUR",
+        );
+        let expected = dec!(1000) * dec!(12.62) / dec!(100) * dec!(30) / dec!(366)
+            + dec!(1000) * dec!(12.37) / dec!(100) * dec!(31) / dec!(366);
+        assert_eq!(interest, round_amount(expected, "EUR"));
+        assert_eq!(interest, dec!(20.82));
+    }
+}
@@ -0,0 +1,616 @@
+//! Electronic Address Scheme (EAS) code list and identifier validation.
+//!
+//! Complete list of EAS codes for the scheme of an electronic address
+//! (BT-34 / BT-49, `EndpointID/@schemeID`), most of them ISO 6523 ICDs,
+//! with format and check digit validation of the identifier for the
+//! schemes that define one.
+//!
+//! Legal registration identifiers (BT-30 / BT-47) take their scheme from
+//! the full ISO 6523 ICD list rather than EAS; their scheme must be an ICD,
+//! and identifiers of the ICDs above are checked the same way.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "xrechnung")]
+use super::{Invoice, ValidationError};
+
+/// An EAS (Electronic Address Scheme) code for electronic addresses and
+/// Peppol participant identification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EasScheme {
+    /// The scheme code (e.g. "0088", "9930", "EM").
+    pub code: &'static str,
+    /// Human-readable description.
+    pub description: &'static str,
+}
+
+impl EasScheme {
+    /// GS1 GLN (Global Location Number) — international.
+    pub const GLN: Self = Self {
+        code: "0088",
+        description: "Global Location Number (GLN)",
+    };
+    /// German Leitweg-ID (public sector routing).
+    pub const LEITWEG_ID: Self = Self {
+        code: "0204",
+        description: "Leitweg-ID",
+    };
+    /// Belgian enterprise number.
+    pub const BE_EN: Self = Self {
+        code: "0208",
+        description: "Belgian enterprise number (KBO/BCE)",
+    };
+    /// DIGSTORG (Denmark).
+    pub const DK_DIGST: Self = Self {
+        code: "0184",
+        description: "DIGSTORG (Danish CVR number)",
+    };
+    /// Dutch OIN.
+    pub const NL_OIN: Self = Self {
+        code: "0190",
+        description: "Dutch Originator's Identification Number (OIN)",
+    };
+    /// Dutch KvK.
+    pub const NL_KVK: Self = Self {
+        code: "0106",
+        description: "Dutch Chamber of Commerce number (KvK)",
+    };
+    /// Italian Codice Fiscale.
+    pub const IT_CF: Self = Self {
+        code: "0210",
+        description: "Italian Codice Fiscale",
+    };
+    /// Italian Partita IVA.
+    pub const IT_IVA: Self = Self {
+        code: "0211",
+        description: "Italian Partita IVA",
+    };
+    /// German VAT number (DE + 9 digits).
+    pub const DE_VAT: Self = Self {
+        code: "9930",
+        description: "German VAT number",
+    };
+    /// Austrian VAT number.
+    pub const AT_VAT: Self = Self {
+        code: "9914",
+        description: "Austrian VAT number",
+    };
+    /// Belgian VAT number.
+    pub const BE_VAT: Self = Self {
+        code: "9925",
+        description: "Belgian VAT number",
+    };
+    /// French VAT number.
+    pub const FR_VAT: Self = Self {
+        code: "9957",
+        description: "French VAT number",
+    };
+    /// Italian VAT number.
+    pub const IT_VAT: Self = Self {
+        code: "9906",
+        description: "Italian VAT number",
+    };
+    /// Dutch VAT number.
+    pub const NL_VAT: Self = Self {
+        code: "9944",
+        description: "Dutch VAT number",
+    };
+    /// Finnish OVT.
+    pub const FI_OVT: Self = Self {
+        code: "0037",
+        description: "Finnish OVT (LY-tunnus)",
+    };
+    /// Swedish Org number.
+    pub const SE_ORG: Self = Self {
+        code: "0007",
+        description: "Swedish organisation number",
+    };
+    /// Norwegian Org number.
+    pub const NO_ORG: Self = Self {
+        code: "0192",
+        description: "Norwegian organisation number",
+    };
+
+    /// Look up a scheme in the EAS code list.
+    pub fn from_code(code: &str) -> Option<Self> {
+        CATALOGUE
+            .binary_search_by(|(c, _, _)| (*c).cmp(code))
+            .ok()
+            .map(|i| Self {
+                code: CATALOGUE[i].0,
+                description: CATALOGUE[i].2,
+            })
+    }
+
+    /// All schemes of the EAS code list, ordered by code.
+    pub fn all() -> impl Iterator<Item = Self> {
+        CATALOGUE
+            .iter()
+            .map(|(code, _, description)| Self { code, description })
+    }
+
+    /// ISO 3166-1 country of the issuing agency; `None` for international
+    /// schemes.
+    pub fn country(&self) -> Option<&'static str> {
+        CATALOGUE
+            .binary_search_by(|(c, _, _)| (*c).cmp(self.code))
+            .ok()
+            .map(|i| CATALOGUE[i].1)
+            .filter(|country| !country.is_empty())
+    }
+
+    /// Check `value` against the identifier format of this scheme.
+    ///
+    /// Format and check digits are verified for GLN (0088), D-U-N-S (0060),
+    /// SIRENE/SIRET (0002/0009), Dutch KvK/OIN (0106/0190), Danish CVR
+    /// (0184), Norwegian (0192) and Belgian (0208) organisation numbers and
+    /// the Leitweg-ID (0204). Other schemes only require a non-empty value.
+    pub fn validate_identifier(&self, value: &str) -> Result<(), String> {
+        if value.trim().is_empty() {
+            return Err("identifier must not be empty".into());
+        }
+        match self.code {
+            "0002" => luhn_number(value, 9, "SIREN number"),
+            "0009" => siret(value),
+            "0060" => digits(value, 9, "D-U-N-S number"),
+            "0088" => gln(value),
+            "0106" => digits(value, 8, "KvK number"),
+            "0184" => cvr(value),
+            "0190" => digits(value, 20, "OIN"),
+            "0192" => norwegian_org(value),
+            "0204" => super::leitweg::parse(value).map(|_| ()),
+            "0208" => belgian_enterprise(value),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rule reported for an identifier that does not match its scheme's format
+/// where the syntax defines no business rule for the scheme.
+pub const IDENTIFIER_FORMAT_RULE: &str = "FAKTURA-ID-01";
+
+/// Check whether `code` is in the EAS code list.
+pub fn is_known_eas_code(code: &str) -> bool {
+    EasScheme::from_code(code).is_some()
+}
+
+/// All national schemes of a country (ISO 3166-1 alpha-2).
+pub fn eas_schemes_for_country(country_code: &str) -> Vec<EasScheme> {
+    let country = country_code.to_uppercase();
+    CATALOGUE
+        .iter()
+        .filter(|(_, c, _)| *c == country)
+        .map(|(code, _, description)| EasScheme { code, description })
+        .collect()
+}
+
+/// Check whether `code` has the form of an ISO 6523 ICD: four digits below
+/// 1000 (the 99xx codes of the EAS list are not ICDs).
+pub fn is_icd_code(code: &str) -> bool {
+    code.len() == 4 && code.bytes().all(|b| b.is_ascii_digit()) && code < "1000"
+}
+
+/// Check seller and buyer electronic addresses (BT-34 / BT-49): unknown
+/// schemes are reported with `scheme_rule`, invalid identifiers with the
+/// rule `value_rule` returns for the scheme.
+#[cfg(feature = "xrechnung")]
+pub(crate) fn electronic_address_errors(
+    invoice: &Invoice,
+    scheme_rule: &str,
+    value_rule: fn(&str) -> &'static str,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for (field, party) in [("seller", &invoice.seller), ("buyer", &invoice.buyer)] {
+        let Some(address) = &party.electronic_address else {
+            continue;
+        };
+        let Some(scheme) = EasScheme::from_code(&address.scheme) else {
+            errors.push(ValidationError::with_rule(
+                format!("{field}.electronic_address.scheme"),
+                format!("'{}' is not an EAS code", address.scheme),
+                scheme_rule,
+            ));
+            continue;
+        };
+        if let Err(message) = scheme.validate_identifier(&address.value) {
+            errors.push(ValidationError::with_rule(
+                format!("{field}.electronic_address.value"),
+                format!("{} '{}': {message}", scheme.code, address.value),
+                value_rule(scheme.code),
+            ));
+        }
+    }
+    errors
+}
+
+/// Check seller and buyer legal registration identifiers with a scheme
+/// (BT-30-1 / BT-47-1): schemes that are no ISO 6523 ICD are reported as
+/// BR-CL-11, invalid identifiers of the checked schemes with the rule
+/// `value_rule` returns for the scheme.
+#[cfg(feature = "xrechnung")]
+pub(crate) fn registration_id_errors(
+    invoice: &Invoice,
+    value_rule: fn(&str) -> &'static str,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for (field, party) in [("seller", &invoice.seller), ("buyer", &invoice.buyer)] {
+        let (Some(id), Some(scheme)) = (&party.registration_id, &party.registration_scheme) else {
+            continue;
+        };
+        if !is_icd_code(scheme) {
+            errors.push(ValidationError::with_rule(
+                format!("{field}.registration_scheme"),
+                format!("'{scheme}' is not an ISO 6523 ICD"),
+                "BR-CL-11",
+            ));
+            continue;
+        }
+        if let Some(Err(message)) = EasScheme::from_code(scheme).map(|s| s.validate_identifier(id))
+        {
+            errors.push(ValidationError::with_rule(
+                format!("{field}.registration_id"),
+                format!("{scheme} '{id}': {message}"),
+                value_rule(scheme),
+            ));
+        }
+    }
+    errors
+}
+
+fn digits(value: &str, len: usize, name: &str) -> Result<(), String> {
+    if value.len() == len && value.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(format!("{name} must consist of {len} digits"))
+    }
+}
+
+fn digit_values(value: &str) -> impl DoubleEndedIterator<Item = u32> + '_ {
+    value.bytes().map(|b| u32::from(b - b'0'))
+}
+
+/// GS1 GLN: 13 digits with GS1 mod-10 check digit.
+fn gln(value: &str) -> Result<(), String> {
+    digits(value, 13, "GLN")?;
+    let sum: u32 = digit_values(value)
+        .rev()
+        .skip(1)
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { d })
+        .sum();
+    let check = (10 - sum % 10) % 10;
+    if digit_values(value).last() == Some(check) {
+        Ok(())
+    } else {
+        Err(format!("GLN check digit must be {check}"))
+    }
+}
+
+fn luhn(value: &str) -> bool {
+    let sum: u32 = digit_values(value)
+        .rev()
+        .enumerate()
+        .map(|(i, d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+fn luhn_number(value: &str, len: usize, name: &str) -> Result<(), String> {
+    digits(value, len, name)?;
+    if luhn(value) {
+        Ok(())
+    } else {
+        Err(format!("{name} check digit is wrong"))
+    }
+}
+
+/// SIRET: 14 digits, Luhn; establishments of La Poste (SIREN 356000000)
+/// use a digit sum divisible by 5 instead.
+fn siret(value: &str) -> Result<(), String> {
+    digits(value, 14, "SIRET number")?;
+    let la_poste = value.starts_with("356000000") && digit_values(value).sum::<u32>() % 5 == 0;
+    if la_poste || luhn(value) {
+        Ok(())
+    } else {
+        Err("SIRET number check digit is wrong".into())
+    }
+}
+
+/// Danish CVR: 8 digits (optionally prefixed with `DK`), weighted mod 11.
+fn cvr(value: &str) -> Result<(), String> {
+    let number = value.strip_prefix("DK").unwrap_or(value);
+    digits(number, 8, "CVR number")?;
+    let sum: u32 = digit_values(number)
+        .zip([2, 7, 6, 5, 4, 3, 2, 1])
+        .map(|(d, w)| d * w)
+        .sum();
+    if sum % 11 == 0 {
+        Ok(())
+    } else {
+        Err("CVR number check digit is wrong".into())
+    }
+}
+
+/// Norwegian organisation number: 9 digits, weighted mod 11.
+fn norwegian_org(value: &str) -> Result<(), String> {
+    digits(value, 9, "organisation number")?;
+    let sum: u32 = digit_values(value)
+        .zip([3, 2, 7, 6, 5, 4, 3, 2])
+        .map(|(d, w)| d * w)
+        .sum();
+    let check = match 11 - sum % 11 {
+        11 => 0,
+        10 => return Err("organisation number has no valid check digit".into()),
+        check => check,
+    };
+    if digit_values(value).last() == Some(check) {
+        Ok(())
+    } else {
+        Err(format!("organisation number check digit must be {check}"))
+    }
+}
+
+/// Belgian enterprise number: 10 digits starting with 0 or 1, the last two
+/// are 97 minus the first eight modulo 97.
+fn belgian_enterprise(value: &str) -> Result<(), String> {
+    digits(value, 10, "enterprise number")?;
+    if !value.starts_with(['0', '1']) {
+        return Err("enterprise number must start with 0 or 1".into());
+    }
+    let base: u64 = value[..8].parse().unwrap_or_default();
+    let check = 97 - base % 97;
+    if value[8..].parse() == Ok(check) {
+        Ok(())
+    } else {
+        Err(format!("enterprise number check digits must be {check:02}"))
+    }
+}
+
+/// The EAS code list: code, ISO 3166-1 country of the issuing agency (empty
+/// for international schemes), description. Sorted for binary search.
+///
+/// Follows the CEF Electronic Address Scheme code list, version 13.
+static CATALOGUE: &[(&str, &str, &str)] = &[
+    (
+        "0002",
+        "FR",
+        "SIRENE (Système d'Identification du Répertoire des Entreprises)",
+    ),
+    ("0007", "SE", "Swedish organisation number"),
+    ("0009", "FR", "SIRET code"),
+    ("0037", "FI", "Finnish OVT (LY-tunnus)"),
+    (
+        "0060",
+        "",
+        "Data Universal Numbering System (D-U-N-S Number)",
+    ),
+    ("0088", "", "Global Location Number (GLN)"),
+    (
+        "0096",
+        "DK",
+        "Danish Chamber of Commerce scheme (EDIRA compliant)",
+    ),
+    ("0097", "IT", "FTI - Ediforum Italia (EDIRA compliant)"),
+    ("0106", "NL", "Dutch Chamber of Commerce number (KvK)"),
+    ("0130", "", "Directorates of the European Commission"),
+    ("0135", "IT", "SIA Object Identifiers"),
+    ("0142", "IT", "SECETI Object Identifiers"),
+    ("0147", "JP", "Standard Company Code"),
+    ("0151", "AU", "Australian Business Number (ABN)"),
+    (
+        "0158",
+        "CZ",
+        "Identification number of economic subject (IČO)",
+    ),
+    ("0170", "JP", "Teikoku Company Code"),
+    ("0177", "", "Odette International Limited"),
+    (
+        "0183",
+        "CH",
+        "Swiss Unique Business Identification Number (UIDB)",
+    ),
+    ("0184", "DK", "DIGSTORG (Danish CVR number)"),
+    (
+        "0188",
+        "JP",
+        "Corporate Number of the Social Security and Tax Number System",
+    ),
+    (
+        "0190",
+        "NL",
+        "Dutch Originator's Identification Number (OIN)",
+    ),
+    (
+        "0191",
+        "EE",
+        "Centre of Registers and Information Systems of the Ministry of Justice",
+    ),
+    ("0192", "NO", "Norwegian organisation number"),
+    ("0193", "BE", "UBL.BE party identifier"),
+    ("0194", "", "KOIOS Open Technical Dictionary"),
+    ("0195", "SG", "Singapore UEN identifier"),
+    ("0196", "IS", "Kennitala (Icelandic legal ID)"),
+    ("0198", "DK", "ERSTORG"),
+    ("0199", "", "Legal Entity Identifier (LEI)"),
+    ("0200", "LT", "Lithuanian legal entity code"),
+    ("0201", "IT", "Codice Univoco Unità Organizzativa iPA"),
+    ("0202", "IT", "Indirizzo di Posta Elettronica Certificata"),
+    ("0203", "", "eDelivery Network Participant identifier"),
+    ("0204", "DE", "Leitweg-ID"),
+    ("0205", "IT", "CODDEST"),
+    ("0208", "BE", "Belgian enterprise number (KBO/BCE)"),
+    ("0209", "", "GS1 identification keys"),
+    ("0210", "IT", "Italian Codice Fiscale"),
+    ("0211", "IT", "Italian Partita IVA"),
+    ("0212", "FI", "Finnish Organization Identifier"),
+    (
+        "0213",
+        "FI",
+        "Finnish Organization Value Add Tax Identifier",
+    ),
+    ("0215", "FI", "Net service ID"),
+    ("0216", "FI", "OVTcode"),
+    (
+        "0217",
+        "NL",
+        "Dutch Chamber of Commerce establishment number",
+    ),
+    ("0218", "LV", "Latvian unified registration number"),
+    (
+        "0221",
+        "JP",
+        "Registered number of the qualified invoice issuer",
+    ),
+    ("0225", "FR", "FRCTC Electronic Address"),
+    ("0230", "MY", "National e-Invoicing Framework"),
+    ("0235", "AE", "UAE Tax Identification Number (TIN)"),
+    ("9906", "IT", "Italian VAT number"),
+    ("9910", "HU", "Hungarian VAT number"),
+    ("9913", "", "Business Registers Network"),
+    ("9914", "AT", "Austrian VAT number"),
+    (
+        "9915",
+        "AT",
+        "Austrian administrative or organisation identifier",
+    ),
+    ("9918", "", "SWIFT Bank Identifier Code (BIC)"),
+    ("9919", "AT", "Austrian company register number"),
+    ("9920", "ES", "Spanish tax agency identifier (AEAT)"),
+    ("9922", "AD", "Andorran VAT number"),
+    ("9923", "AL", "Albanian VAT number"),
+    ("9924", "BA", "Bosnian VAT number"),
+    ("9925", "BE", "Belgian VAT number"),
+    ("9926", "BG", "Bulgarian VAT number"),
+    ("9927", "CH", "Swiss VAT number"),
+    ("9928", "CY", "Cypriot VAT number"),
+    ("9929", "CZ", "Czech VAT number"),
+    ("9930", "DE", "German VAT number"),
+    ("9931", "EE", "Estonian VAT number"),
+    ("9932", "GB", "United Kingdom VAT number"),
+    ("9933", "GR", "Greek VAT number"),
+    ("9934", "HR", "Croatian VAT number"),
+    ("9935", "IE", "Irish VAT number"),
+    ("9936", "LI", "Liechtenstein VAT number"),
+    ("9937", "LT", "Lithuanian VAT number"),
+    ("9938", "LU", "Luxembourg VAT number"),
+    ("9939", "LV", "Latvian VAT number"),
+    ("9940", "MC", "Monegasque VAT number"),
+    ("9941", "ME", "Montenegrin VAT number"),
+    ("9942", "MK", "North Macedonian VAT number"),
+    ("9943", "MT", "Maltese VAT number"),
+    ("9944", "NL", "Dutch VAT number"),
+    ("9945", "PL", "Polish VAT number"),
+    ("9946", "PT", "Portuguese VAT number"),
+    ("9947", "RO", "Romanian VAT number"),
+    ("9948", "RS", "Serbian VAT number"),
+    ("9949", "SI", "Slovenian VAT number"),
+    ("9950", "SK", "Slovak VAT number"),
+    ("9951", "SM", "San Marino VAT number"),
+    ("9952", "TR", "Turkish VAT number"),
+    ("9953", "VA", "Vatican VAT number"),
+    ("9955", "SE", "Swedish VAT number"),
+    ("9956", "BE", "Belgian Crossroad Bank of Enterprises number"),
+    ("9957", "FR", "French VAT number"),
+    ("9958", "DE", "German Leitweg-ID (superseded by 0204)"),
+    ("9959", "US", "Employer Identification Number (EIN)"),
+    ("AN", "", "ODETTE File Transfer Protocol (OFTP)"),
+    ("AQ", "", "X.400 address for mail text"),
+    ("AS", "", "AS2 exchange"),
+    ("AU", "", "File Transfer Protocol"),
+    ("EM", "", "Electronic mail (SMTP)"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogue_is_sorted() {
+        for window in CATALOGUE.windows(2) {
+            assert!(
+                window[0].0 < window[1].0,
+                "EAS codes not sorted: {} >= {}",
+                window[0].0,
+                window[1].0
+            );
+        }
+    }
+
+    #[test]
+    fn constants_are_in_catalogue() {
+        for scheme in [
+            EasScheme::GLN,
+            EasScheme::LEITWEG_ID,
+            EasScheme::BE_EN,
+            EasScheme::DK_DIGST,
+            EasScheme::NL_OIN,
+            EasScheme::NL_KVK,
+            EasScheme::IT_CF,
+            EasScheme::IT_IVA,
+            EasScheme::DE_VAT,
+            EasScheme::AT_VAT,
+            EasScheme::BE_VAT,
+            EasScheme::FR_VAT,
+            EasScheme::IT_VAT,
+            EasScheme::NL_VAT,
+            EasScheme::FI_OVT,
+            EasScheme::SE_ORG,
+            EasScheme::NO_ORG,
+        ] {
+            assert_eq!(EasScheme::from_code(scheme.code), Some(scheme));
+        }
+    }
+
+    #[test]
+    fn check_digits() {
+        let check = |code: &str, value: &str| {
+            EasScheme::from_code(code)
+                .unwrap()
+                .validate_identifier(value)
+        };
+        assert!(check("0088", "4035811991021").is_ok());
+        assert!(check("0088", "4035811991022").is_err());
+        assert!(check("0002", "732829320").is_ok());
+        assert!(check("0002", "732829321").is_err());
+        assert!(check("0009", "73282932000074").is_ok());
+        assert!(check("0009", "35600000000056").is_ok());
+        assert!(check("0225", "315143296_00014").is_ok());
+        assert!(check("0230", "C2584563200").is_ok());
+        assert!(check("0192", "974760673").is_ok());
+        assert!(check("0192", "974760674").is_err());
+        assert!(check("0184", "DK13585628").is_ok());
+        assert!(check("0184", "13585629").is_err());
+        assert!(check("0208", "0403170701").is_ok());
+        assert!(check("0208", "0403170702").is_err());
+        assert!(check("0204", "04011000-1234512345-06").is_ok());
+        assert!(check("0204", "991-33333TEST-33").is_ok());
+        assert!(check("0204", "991-12345-67").is_err());
+        assert!(check("0204", "9-12345-67").is_err());
+        assert!(check("9930", "DE123456789").is_ok());
+        assert!(check("EM", " ").is_err());
+    }
+
+    #[test]
+    fn icd_codes() {
+        assert!(is_icd_code("0088"));
+        assert!(is_icd_code("0204"));
+        assert!(!is_icd_code("9930"));
+        assert!(!is_icd_code("EM"));
+        assert!(!is_icd_code("88"));
+    }
+
+    #[test]
+    fn schemes_per_country() {
+        let de: Vec<&str> = eas_schemes_for_country("de")
+            .iter()
+            .map(|s| s.code)
+            .collect();
+        assert_eq!(de, ["0204", "9930", "9958"]);
+        assert_eq!(EasScheme::GLN.country(), None);
+        assert_eq!(EasScheme::NO_ORG.country(), Some("NO"));
+    }
+}
@@ -0,0 +1,412 @@
+//! Exchange rates for foreign-currency invoices.
+//!
+//! §16 Abs. 6 UStG requires the VAT on a foreign-currency invoice to be
+//! stated in EUR, converted with either the monthly average rate published
+//! by the BMF or the ECB reference rate of the tax point date. Rates follow
+//! the ECB quotation convention: units of foreign currency per 1 EUR.
+//!
+//! [`ExchangeRateTable`] is an offline provider that can be filled by hand
+//! or loaded from the ECB `eurofxref` XML/CSV files.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::currencies::round_amount;
+use super::error::RechnungError;
+use super::types::Invoice;
+
+/// Where an exchange rate was published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum RateSource {
+    /// ECB euro foreign exchange reference rate (published on TARGET business days).
+    EcbDaily,
+    /// BMF monthly average rate (Umsatzsteuer-Umrechnungskurse).
+    BmfMonthly,
+    /// Rate agreed or supplied by the caller.
+    Custom,
+}
+
+/// An exchange rate between EUR and a foreign currency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    /// ISO 4217 code of the foreign currency (e.g. "USD").
+    pub currency: String,
+    /// Units of `currency` per 1 EUR (ECB/DATEV quotation, e.g. 1.0708).
+    pub rate: Decimal,
+    /// Date the rate is effective for (first day of the month for BMF rates).
+    pub date: NaiveDate,
+    /// Publisher of the rate.
+    pub source: RateSource,
+}
+
+impl ExchangeRate {
+    /// Create a rate quoted as units of `currency` per 1 EUR.
+    pub fn new(
+        currency: impl Into<String>,
+        rate: Decimal,
+        date: NaiveDate,
+        source: RateSource,
+    ) -> Self {
+        Self {
+            currency: currency.into(),
+            rate,
+            date,
+            source,
+        }
+    }
+
+    /// Convert an amount in the foreign currency to EUR, rounded to cents.
+    pub fn to_eur(&self, amount: Decimal) -> Decimal {
+        if self.rate.is_zero() {
+            return Decimal::ZERO;
+        }
+        round_cents(amount / self.rate)
+    }
+
+    /// Convert an EUR amount into the foreign currency, rounded to its minor units.
+    pub fn from_eur(&self, amount: Decimal) -> Decimal {
+        round_amount(amount * self.rate, &self.currency)
+    }
+}
+
+/// Source of EUR exchange rates.
+pub trait ExchangeRateProvider {
+    /// Look up the rate of `currency` against EUR applicable on `date`.
+    ///
+    /// Returns `None` if no rate is known for that currency and date.
+    fn eur_rate(&self, currency: &str, date: NaiveDate) -> Option<ExchangeRate>;
+}
+
+/// Offline exchange rate table.
+///
+/// Lookups return the most recent rate on or before the requested date.
+/// For [`RateSource::EcbDaily`] tables, rates older than 7 days are not used
+/// (covers weekends and TARGET holidays); [`RateSource::BmfMonthly`] tables
+/// only match rates of the same calendar month.
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use faktura::core::*;
+/// use rust_decimal_macros::dec;
+///
+/// let xml = r#"<gesmes:Envelope><Cube><Cube time="2024-06-14">
+///   <Cube currency="USD" rate="1.0708"/><Cube currency="CHF" rate="0.9561"/>
+/// </Cube></Cube></gesmes:Envelope>"#;
+/// let table = ExchangeRateTable::from_ecb_xml(xml).unwrap();
+///
+/// // Saturday — falls back to Friday's fixing
+/// let rate = table.eur_rate("USD", NaiveDate::from_ymd_opt(2024, 6, 15).unwrap()).unwrap();
+/// assert_eq!(rate.rate, dec!(1.0708));
+/// assert_eq!(rate.to_eur(dec!(107.08)), dec!(100.00));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExchangeRateTable {
+    source: RateSource,
+    rates: HashMap<String, BTreeMap<NaiveDate, Decimal>>,
+}
+
+/// Maximum age of an ECB daily rate used for a lookup.
+const ECB_MAX_LOOKBACK_DAYS: i64 = 7;
+
+impl ExchangeRateTable {
+    /// Create an empty table for rates from `source`.
+    pub fn new(source: RateSource) -> Self {
+        Self {
+            source,
+            rates: HashMap::new(),
+        }
+    }
+
+    /// The publisher of the rates in this table.
+    pub fn source(&self) -> RateSource {
+        self.source
+    }
+
+    /// Add a rate (units of `currency` per 1 EUR) effective on `date`.
+    ///
+    /// For BMF monthly tables the date is normalized to the first of the month.
+    pub fn insert(&mut self, currency: impl Into<String>, date: NaiveDate, rate: Decimal) {
+        let date = match self.source {
+            RateSource::BmfMonthly => date.with_day(1).unwrap_or(date),
+            _ => date,
+        };
+        self.rates
+            .entry(currency.into().to_ascii_uppercase())
+            .or_default()
+            .insert(date, rate);
+    }
+
+    /// Number of individual rates in the table.
+    pub fn len(&self) -> usize {
+        self.rates.values().map(BTreeMap::len).sum()
+    }
+
+    /// Whether the table contains no rates.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Load ECB reference rates from `eurofxref-daily.xml` or `eurofxref-hist.xml`.
+    pub fn from_ecb_xml(xml: &str) -> Result<Self, RechnungError> {
+        let mut table = Self::new(RateSource::EcbDaily);
+        let mut current_date: Option<NaiveDate> = None;
+
+        let mut rest = xml;
+        while let Some(pos) = rest.find("<Cube") {
+            rest = &rest[pos + 5..];
+            let end = rest
+                .find('>')
+                .ok_or_else(|| RechnungError::Xml("unterminated <Cube> element".into()))?;
+            let tag = &rest[..end];
+            rest = &rest[end..];
+
+            if let Some(time) = xml_attr(tag, "time") {
+                current_date = Some(parse_ecb_date(time).ok_or_else(|| {
+                    RechnungError::Xml(format!("invalid ECB date '{}'", time.trim()))
+                })?);
+            } else if let (Some(currency), Some(rate)) =
+                (xml_attr(tag, "currency"), xml_attr(tag, "rate"))
+            {
+                let date = current_date.ok_or_else(|| {
+                    RechnungError::Xml(format!("rate for {currency} outside a dated <Cube>"))
+                })?;
+                let rate = parse_rate(rate).ok_or_else(|| {
+                    RechnungError::Xml(format!("invalid exchange rate '{}'", rate.trim()))
+                })?;
+                table.insert(currency, date, rate);
+            }
+        }
+
+        if table.is_empty() {
+            return Err(RechnungError::Xml(
+                "no exchange rates found in ECB XML".into(),
+            ));
+        }
+        Ok(table)
+    }
+
+    /// Load ECB reference rates from `eurofxref.csv` or `eurofxref-hist.csv`.
+    ///
+    /// The first row holds `Date` followed by currency codes; each further row
+    /// holds a date followed by the rates. `N/A` and empty cells are skipped.
+    pub fn from_ecb_csv(csv: &str) -> Result<Self, RechnungError> {
+        let mut table = Self::new(RateSource::EcbDaily);
+        let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+
+        let header = lines
+            .next()
+            .ok_or_else(|| RechnungError::Builder("empty ECB CSV".into()))?;
+        let currencies: Vec<&str> = header.split(',').map(str::trim).collect();
+        if !currencies
+            .first()
+            .is_some_and(|c| c.eq_ignore_ascii_case("date"))
+        {
+            return Err(RechnungError::Builder(
+                "ECB CSV header must start with 'Date'".into(),
+            ));
+        }
+
+        for line in lines {
+            let mut cells = line.split(',').map(str::trim);
+            let cell = cells.next().unwrap_or("");
+            let date = parse_ecb_date(cell).ok_or_else(|| {
+                RechnungError::Builder(format!("invalid ECB date '{}'", cell.trim()))
+            })?;
+            for (currency, cell) in currencies.iter().skip(1).zip(cells) {
+                if currency.is_empty() || cell.is_empty() || cell == "N/A" {
+                    continue;
+                }
+                let rate = parse_rate(cell).ok_or_else(|| {
+                    RechnungError::Builder(format!("invalid exchange rate '{cell}'"))
+                })?;
+                table.insert(*currency, date, rate);
+            }
+        }
+
+        if table.is_empty() {
+            return Err(RechnungError::Builder(
+                "no exchange rates found in ECB CSV".into(),
+            ));
+        }
+        Ok(table)
+    }
+}
+
+impl ExchangeRateProvider for ExchangeRateTable {
+    fn eur_rate(&self, currency: &str, date: NaiveDate) -> Option<ExchangeRate> {
+        let currency = currency.to_ascii_uppercase();
+        if currency == "EUR" {
+            return Some(ExchangeRate::new("EUR", Decimal::ONE, date, self.source));
+        }
+
+        let (&rate_date, &rate) = self.rates.get(&currency)?.range(..=date).next_back()?;
+        let usable = match self.source {
+            RateSource::EcbDaily => (date - rate_date).num_days() <= ECB_MAX_LOOKBACK_DAYS,
+            RateSource::BmfMonthly => {
+                rate_date.year() == date.year() && rate_date.month() == date.month()
+            }
+            RateSource::Custom => true,
+        };
+        usable.then(|| ExchangeRate::new(currency, rate, rate_date, self.source))
+    }
+}
+
+/// Convert the VAT total of a foreign-currency invoice into EUR (BT-111).
+///
+/// Looks up the rate at the tax point date (BT-7), falling back to the end of
+/// the invoicing period and then the issue date. Sets the tax currency (BT-6)
+/// to EUR (a different tax currency is an error), stores the rate on the
+/// invoice and fills
+/// [`Totals::vat_total_in_tax_currency`](super::Totals::vat_total_in_tax_currency).
+///
+/// EUR invoices are left unchanged.
+pub fn apply_exchange_rate(
+    invoice: &mut Invoice,
+    provider: &dyn ExchangeRateProvider,
+) -> Result<(), RechnungError> {
+    if invoice.currency_code.eq_ignore_ascii_case("EUR") {
+        return Ok(());
+    }
+
+    let date = conversion_date(invoice);
+    let rate = provider
+        .eur_rate(&invoice.currency_code, date)
+        .ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "no EUR exchange rate for {} on {date}",
+                invoice.currency_code
+            ))
+        })?;
+    set_exchange_rate(invoice, rate)
+}
+
+/// Date used for currency conversion under §16 Abs. 6 UStG.
+pub fn conversion_date(invoice: &Invoice) -> NaiveDate {
+    invoice
+        .tax_point_date
+        .or(invoice.invoicing_period.as_ref().map(|p| p.end))
+        .unwrap_or(invoice.issue_date)
+}
+
+/// Store `rate` on the invoice and derive BT-6/BT-111 from it.
+pub(crate) fn set_exchange_rate(
+    invoice: &mut Invoice,
+    rate: ExchangeRate,
+) -> Result<(), RechnungError> {
+    if !rate.currency.eq_ignore_ascii_case(&invoice.currency_code) {
+        return Err(RechnungError::Builder(format!(
+            "exchange rate is for {}, but the invoice currency is {}",
+            rate.currency, invoice.currency_code
+        )));
+    }
+    if rate.rate <= Decimal::ZERO {
+        return Err(RechnungError::Builder(format!(
+            "exchange rate for {} must be positive",
+            rate.currency
+        )));
+    }
+
+    if let Some(code) = &invoice.tax_currency_code {
+        if !code.eq_ignore_ascii_case("EUR") {
+            return Err(RechnungError::Builder(format!(
+                "exchange rate converts to EUR, but the tax currency is {code}"
+            )));
+        }
+    }
+
+    invoice.tax_currency_code = Some("EUR".into());
+    if let Some(totals) = invoice.totals.as_mut() {
+        totals.vat_total_in_tax_currency = Some(rate.to_eur(totals.vat_total));
+    }
+    invoice.exchange_rate = Some(rate);
+    Ok(())
+}
+
+fn round_cents(value: Decimal) -> Decimal {
+    round_amount(value, "EUR")
+}
+
+/// Extract `name="value"` (or single-quoted) from the inside of an XML tag.
+fn xml_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let preceded_ok = pos == 0 || rest.as_bytes()[pos - 1].is_ascii_whitespace();
+        let after = rest[pos + name.len()..].trim_start();
+        rest = &rest[pos + name.len()..];
+        if !preceded_ok {
+            continue;
+        }
+        let Some(after) = after.strip_prefix('=') else {
+            continue;
+        };
+        let after = after.trim_start();
+        let quote = after.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value = &after[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+    None
+}
+
+/// Parse the date formats used by the ECB files (`2024-06-14`, `14 June 2024`).
+fn parse_ecb_date(s: &str) -> Option<NaiveDate> {
+    let s = s.trim();
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%d %B %Y"))
+        .ok()
+}
+
+fn parse_rate(s: &str) -> Option<Decimal> {
+    s.trim().parse::<Decimal>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn xml_attr_quotes() {
+        assert_eq!(
+            xml_attr(r#" currency="USD" rate='1.07'"#, "rate"),
+            Some("1.07")
+        );
+        assert_eq!(xml_attr(r#" currency="USD""#, "currency"), Some("USD"));
+        assert_eq!(xml_attr(r#" xtime="x""#, "time"), None);
+    }
+
+    #[test]
+    fn ecb_date_formats() {
+        assert_eq!(parse_ecb_date("2024-06-14").unwrap(), date(2024, 6, 14));
+        assert_eq!(parse_ecb_date("14 June 2024").unwrap(), date(2024, 6, 14));
+        assert!(parse_ecb_date("14.06.2024").is_none());
+    }
+
+    #[test]
+    fn ecb_lookback_limited() {
+        let mut table = ExchangeRateTable::new(RateSource::EcbDaily);
+        table.insert("USD", date(2024, 6, 3), dec!(1.08));
+        assert!(table.eur_rate("USD", date(2024, 6, 10)).is_some());
+        assert!(table.eur_rate("USD", date(2024, 6, 11)).is_none());
+        assert!(table.eur_rate("USD", date(2024, 6, 2)).is_none());
+    }
+
+    #[test]
+    fn bmf_monthly_same_month_only() {
+        let mut table = ExchangeRateTable::new(RateSource::BmfMonthly);
+        table.insert("CHF", date(2024, 5, 17), dec!(0.9812));
+        let rate = table.eur_rate("chf", date(2024, 5, 31)).unwrap();
+        assert_eq!(rate.date, date(2024, 5, 1));
+        assert!(table.eur_rate("CHF", date(2024, 6, 1)).is_none());
+    }
+}
@@ -0,0 +1,81 @@
+//! Blocking HTTP and HTTPS requests over `reqwest` (rustls), for the SMP,
+//! AS4 and time-stamp authority clients.
+
+use std::time::Duration;
+
+use reqwest::Method;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+
+/// A raw HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RawResponse {
+    pub(crate) status: u16,
+    #[cfg_attr(not(any(feature = "as4", feature = "signature")), allow(dead_code))]
+    pub(crate) content_type: Option<String>,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Send one request to an `http://` or `https://` URL. Redirects are not
+/// followed; `timeout` applies to connecting and to the whole request.
+pub(crate) fn send(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    timeout: Duration,
+) -> Result<RawResponse, String> {
+    let net = |e: reqwest::Error| format!("{url}: {e}");
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!(
+            "{url}: only http:// and https:// URLs are supported"
+        ));
+    }
+    let client = Client::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .redirect(Policy::none())
+        .build()
+        .map_err(net)?;
+    let method =
+        Method::from_bytes(method.as_bytes()).map_err(|e| format!("{url}: {method}: {e}"))?;
+    let mut request = client.request(method, url);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    if !body.is_empty() {
+        request = request.body(body.to_vec());
+    }
+    let response = request.send().map_err(net)?;
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().map_err(net)?.to_vec();
+    Ok(RawResponse {
+        status,
+        content_type,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        let err = send(
+            "GET",
+            "ftp://example.org/",
+            &[],
+            &[],
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(err.contains("only http:// and https://"), "{err}");
+    }
+}
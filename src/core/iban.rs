@@ -0,0 +1,211 @@
+//! IBAN, BIC and creditor reference validation.
+//!
+//! Implements the ISO 13616 IBAN check (country length + MOD 97-10), the
+//! ISO 9362 BIC format and the ISO 11649 structured creditor reference.
+
+/// Remove whitespace and uppercase an identifier in its electronic form:
+/// IBAN, RF creditor reference or SEPA creditor identifier.
+pub fn compact_identifier(identifier: &str) -> String {
+    identifier
+        .split_whitespace()
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// Remove whitespace and uppercase an IBAN; see [`compact_identifier`].
+pub fn normalize_iban(iban: &str) -> String {
+    compact_identifier(iban)
+}
+
+/// Validate an IBAN: country code, registered length and MOD 97-10 check digits.
+///
+/// Spaces are ignored. Countries not in the registry table are accepted with
+/// any length between 15 and 34 characters.
+pub fn is_valid_iban(iban: &str) -> bool {
+    let iban = normalize_iban(iban);
+    if !iban.is_ascii() || iban.len() < 5 {
+        return false;
+    }
+    let (country, check) = (&iban[..2], &iban[2..4]);
+    if !country.chars().all(|c| c.is_ascii_uppercase())
+        || !check.chars().all(|c| c.is_ascii_digit())
+        || !iban.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return false;
+    }
+    let length_ok = match iban_length(country) {
+        Some(len) => iban.len() == len,
+        None => (15..=34).contains(&iban.len()),
+    };
+    length_ok && mod97(&format!("{}{}", &iban[4..], &iban[..4])) == Some(1)
+}
+
+/// Registered IBAN length for a country, if known.
+pub fn iban_length(country: &str) -> Option<usize> {
+    IBAN_LENGTHS
+        .binary_search_by_key(&country, |(c, _)| c)
+        .ok()
+        .map(|i| IBAN_LENGTHS[i].1)
+}
+
+/// Validate the format of a BIC (ISO 9362): 4 letters institution, 2 letters
+/// country, 2 alphanumeric location, optional 3 alphanumeric branch.
+pub fn is_valid_bic(bic: &str) -> bool {
+    let bic = bic.trim();
+    matches!(bic.len(), 8 | 11)
+        && bic.is_ascii()
+        && bic[..6].chars().all(|c| c.is_ascii_uppercase())
+        && bic[6..]
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Validate an ISO 11649 creditor reference (`RF` + 2 check digits + up to 21 chars).
+pub fn is_valid_creditor_reference(reference: &str) -> bool {
+    let reference = compact_identifier(reference);
+    if !reference.starts_with("RF")
+        || !(5..=25).contains(&reference.len())
+        || !reference.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return false;
+    }
+    let rearranged = format!("{}{}", &reference[4..], &reference[..4]);
+    mod97(&rearranged) == Some(1)
+}
+
+/// ISO 7064 MOD 97-10 over an alphanumeric string (letters count as 10–35).
+pub(crate) fn mod97(s: &str) -> Option<u32> {
+    let mut rem = 0u32;
+    for c in s.chars() {
+        let value = c.to_digit(36)?;
+        rem = if value >= 10 {
+            (rem * 100 + value) % 97
+        } else {
+            (rem * 10 + value) % 97
+        };
+    }
+    Some(rem)
+}
+
+/// IBAN lengths from the ISO 13616 registry. Sorted for binary search.
+static IBAN_LENGTHS: &[(&str, usize)] = &[
+    ("AD", 24),
+    ("AE", 23),
+    ("AL", 28),
+    ("AT", 20),
+    ("AZ", 28),
+    ("BA", 20),
+    ("BE", 16),
+    ("BG", 22),
+    ("BH", 22),
+    ("BR", 29),
+    ("BY", 28),
+    ("CH", 21),
+    ("CR", 22),
+    ("CY", 28),
+    ("CZ", 24),
+    ("DE", 22),
+    ("DK", 18),
+    ("DO", 28),
+    ("EE", 20),
+    ("EG", 29),
+    ("ES", 24),
+    ("FI", 18),
+    ("FO", 18),
+    ("FR", 27),
+    ("GB", 22),
+    ("GE", 22),
+    ("GI", 23),
+    ("GL", 18),
+    ("GR", 27),
+    ("GT", 28),
+    ("HR", 21),
+    ("HU", 28),
+    ("IE", 22),
+    ("IL", 23),
+    ("IQ", 23),
+    ("IS", 26),
+    ("IT", 27),
+    ("JO", 30),
+    ("KW", 30),
+    ("KZ", 20),
+    ("LB", 28),
+    ("LC", 32),
+    ("LI", 21),
+    ("LT", 20),
+    ("LU", 20),
+    ("LV", 21),
+    ("MC", 27),
+    ("MD", 24),
+    ("ME", 22),
+    ("MK", 19),
+    ("MR", 27),
+    ("MT", 31),
+    ("MU", 30),
+    ("NL", 18),
+    ("NO", 15),
+    ("PK", 24),
+    ("PL", 28),
+    ("PS", 29),
+    ("PT", 25),
+    ("QA", 29),
+    ("RO", 24),
+    ("RS", 22),
+    ("SA", 24),
+    ("SC", 31),
+    ("SE", 24),
+    ("SI", 19),
+    ("SK", 24),
+    ("SM", 27),
+    ("ST", 25),
+    ("SV", 28),
+    ("TL", 23),
+    ("TN", 24),
+    ("TR", 26),
+    ("UA", 29),
+    ("VA", 22),
+    ("VG", 24),
+    ("XK", 20),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iban_check_digits() {
+        assert!(is_valid_iban("DE89370400440532013000"));
+        assert!(is_valid_iban("de89 3704 0044 0532 0130 00"));
+        assert!(is_valid_iban("CH9300762011623852957"));
+        assert!(is_valid_iban("NO9386011117947"));
+        assert!(
+            !is_valid_iban("DE88370400440532013000"),
+            "wrong check digits"
+        );
+        assert!(!is_valid_iban("DE8937040044053201300"), "wrong length");
+        assert!(!is_valid_iban("DE89-3704"));
+    }
+
+    #[test]
+    fn length_table_sorted() {
+        assert!(IBAN_LENGTHS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(iban_length("DE"), Some(22));
+        assert_eq!(iban_length("US"), None);
+    }
+
+    #[test]
+    fn bic_format() {
+        assert!(is_valid_bic("COBADEFFXXX"));
+        assert!(is_valid_bic("MARKDEF1"));
+        assert!(!is_valid_bic("COBADEFF1"));
+        assert!(!is_valid_bic("C0BADEFF"));
+    }
+
+    #[test]
+    fn creditor_reference() {
+        assert!(is_valid_creditor_reference("RF18539007547034"));
+        assert!(is_valid_creditor_reference("RF18 5390 0754 7034"));
+        assert!(!is_valid_creditor_reference("RF19539007547034"));
+        assert!(!is_valid_creditor_reference("RE-2024-001"));
+    }
+}
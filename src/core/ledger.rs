@@ -0,0 +1,417 @@
+//! Open-items ledger (Offene-Posten-Liste) for issued invoices.
+//!
+//! Tracks what happens to an invoice after it is issued: payments, Skonto
+//! taken, credit notes and write-offs. The outstanding amount can be
+//! evaluated at any date, and open items are summarised in an aging report
+//! per buyer.
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use super::dunning::DebtorKind;
+use super::{ExchangeRate, Invoice, RechnungError};
+
+/// Kind of a ledger posting against an open item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PostingKind {
+    /// Payment received.
+    Payment,
+    /// Cash discount deducted by the payer within the Skonto period.
+    Skonto,
+    /// Credit note (BT-3 = 381) issued against the invoice.
+    CreditNote(String),
+    /// Bad debt written off (Forderungsverlust).
+    WriteOff,
+}
+
+/// A posting that reduces (or, if negative, increases) an open item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Posting {
+    /// Posting date.
+    pub date: NaiveDate,
+    /// Amount settled, in the invoice currency.
+    pub amount: Decimal,
+    /// What the posting is.
+    pub kind: PostingKind,
+}
+
+/// Settlement state of an open item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemStatus {
+    /// Nothing settled yet.
+    Open,
+    /// Partly settled.
+    PartiallyPaid,
+    /// Fully settled by payments (and Skonto).
+    Paid,
+    /// Settled, at least partly, by credit notes.
+    Credited,
+    /// Settled, at least partly, by a write-off.
+    WrittenOff,
+    /// More was received than was due.
+    Overpaid,
+}
+
+/// An issued invoice and everything posted against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenItem {
+    /// Invoice number (BT-1).
+    pub number: String,
+    /// Buyer name (BT-44).
+    pub buyer: String,
+    /// Whether the buyer is a consumer or a business.
+    pub debtor: DebtorKind,
+    /// Issue date (BT-2).
+    pub issue_date: NaiveDate,
+    /// Due date (BT-9), if stated.
+    pub due_date: Option<NaiveDate>,
+    /// Invoice currency (BT-5).
+    pub currency: String,
+    /// Rate to EUR for foreign-currency invoices.
+    pub exchange_rate: Option<ExchangeRate>,
+    /// Amount due at issue (BT-115); negative for unapplied credit notes.
+    pub amount: Decimal,
+    /// Postings in the order they were applied.
+    pub postings: Vec<Posting>,
+    /// Skonto tiers as (last day, discount amount).
+    discounts: Vec<(NaiveDate, Decimal)>,
+}
+
+impl OpenItem {
+    /// Outstanding amount after all postings.
+    pub fn outstanding(&self) -> Decimal {
+        self.amount - self.postings.iter().map(|p| p.amount).sum::<Decimal>()
+    }
+
+    /// Outstanding amount at the end of `date` (zero before the issue date).
+    pub fn outstanding_at(&self, date: NaiveDate) -> Decimal {
+        if date < self.issue_date {
+            return Decimal::ZERO;
+        }
+        self.amount
+            - self
+                .postings
+                .iter()
+                .filter(|p| p.date <= date)
+                .map(|p| p.amount)
+                .sum::<Decimal>()
+    }
+
+    /// Days past the due date (or issue date, if none) at `as_of`; negative if not yet due.
+    pub fn days_overdue(&self, as_of: NaiveDate) -> i64 {
+        (as_of - self.due_date.unwrap_or(self.issue_date)).num_days()
+    }
+
+    /// Current settlement state.
+    pub fn status(&self) -> ItemStatus {
+        let outstanding = self.outstanding();
+        let has = |f: fn(&PostingKind) -> bool| self.postings.iter().any(|p| f(&p.kind));
+        if self.postings.is_empty() {
+            ItemStatus::Open
+        } else if outstanding.is_sign_negative() && !outstanding.is_zero() {
+            if self.amount.is_sign_negative() {
+                ItemStatus::Open
+            } else {
+                ItemStatus::Overpaid
+            }
+        } else if !outstanding.is_zero() {
+            ItemStatus::PartiallyPaid
+        } else if has(|k| matches!(k, PostingKind::WriteOff)) {
+            ItemStatus::WrittenOff
+        } else if has(|k| matches!(k, PostingKind::CreditNote(_))) {
+            ItemStatus::Credited
+        } else {
+            ItemStatus::Paid
+        }
+    }
+
+    /// Skonto the payer may still deduct when paying on `date`.
+    fn open_discount(&self, date: NaiveDate) -> Decimal {
+        self.discounts
+            .iter()
+            .filter(|(deadline, _)| date <= *deadline)
+            .map(|(_, amount)| *amount)
+            .max()
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// Aging bucket by days past due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AgingBucket {
+    /// Not yet due.
+    NotDue,
+    /// 0–30 days overdue.
+    Days0To30,
+    /// 31–60 days overdue.
+    Days31To60,
+    /// 61–90 days overdue.
+    Days61To90,
+    /// More than 90 days overdue.
+    Over90,
+}
+
+impl AgingBucket {
+    /// Bucket for a number of days past due.
+    pub fn for_days(days_overdue: i64) -> Self {
+        match days_overdue {
+            ..0 => Self::NotDue,
+            0..=30 => Self::Days0To30,
+            31..=60 => Self::Days31To60,
+            61..=90 => Self::Days61To90,
+            _ => Self::Over90,
+        }
+    }
+}
+
+/// Open amounts of one buyer per aging bucket.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgingRow {
+    /// Buyer name.
+    pub buyer: String,
+    /// Currency of the amounts.
+    pub currency: String,
+    /// Not yet due.
+    pub not_due: Decimal,
+    /// 0–30 days overdue.
+    pub days_0_30: Decimal,
+    /// 31–60 days overdue.
+    pub days_31_60: Decimal,
+    /// 61–90 days overdue.
+    pub days_61_90: Decimal,
+    /// More than 90 days overdue.
+    pub over_90: Decimal,
+}
+
+impl AgingRow {
+    /// Total open amount of the buyer.
+    pub fn total(&self) -> Decimal {
+        self.not_due + self.days_0_30 + self.days_31_60 + self.days_61_90 + self.over_90
+    }
+
+    fn add(&mut self, bucket: AgingBucket, amount: Decimal) {
+        let slot = match bucket {
+            AgingBucket::NotDue => &mut self.not_due,
+            AgingBucket::Days0To30 => &mut self.days_0_30,
+            AgingBucket::Days31To60 => &mut self.days_31_60,
+            AgingBucket::Days61To90 => &mut self.days_61_90,
+            AgingBucket::Over90 => &mut self.over_90,
+        };
+        *slot += amount;
+    }
+}
+
+/// Aging report (Altersstrukturliste) at a reference date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgingReport {
+    /// Reference date.
+    pub as_of: NaiveDate,
+    /// One row per buyer and currency, sorted by buyer.
+    pub rows: Vec<AgingRow>,
+}
+
+/// Open items of issued invoices, keyed by invoice number.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenItemsLedger {
+    items: BTreeMap<String, OpenItem>,
+}
+
+impl OpenItemsLedger {
+    /// Create an empty ledger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an issued invoice.
+    ///
+    /// A credit note (381) that references preceding invoices (BG-3) in the
+    /// ledger is applied to them in the order referenced, up to their outstanding
+    /// amount; any remainder becomes an open credit item.
+    pub fn add_invoice(&mut self, invoice: &Invoice) -> Result<(), RechnungError> {
+        let totals = invoice.totals.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no calculated totals — call calculate_totals() first",
+                invoice.number
+            ))
+        })?;
+        if self.items.contains_key(&invoice.number) {
+            return Err(RechnungError::Builder(format!(
+                "invoice {} is already in the ledger",
+                invoice.number
+            )));
+        }
+
+        let mut amount = totals.amount_due;
+        if invoice.type_code.is_credit_note() {
+            for preceding in &invoice.preceding_invoices {
+                let Some(item) = self.items.get_mut(&preceding.number) else {
+                    continue;
+                };
+                let applied = amount.min(item.outstanding().max(Decimal::ZERO));
+                if applied > Decimal::ZERO {
+                    item.postings.push(Posting {
+                        date: invoice.issue_date,
+                        amount: applied,
+                        kind: PostingKind::CreditNote(invoice.number.clone()),
+                    });
+                    amount -= applied;
+                }
+            }
+            amount = -amount;
+        }
+
+        let discounts = invoice
+            .cash_discounts
+            .iter()
+            .map(|d| {
+                (
+                    d.deadline(invoice.issue_date),
+                    d.discount_amount(totals.amount_due, &invoice.currency_code),
+                )
+            })
+            .collect();
+        self.items.insert(
+            invoice.number.clone(),
+            OpenItem {
+                number: invoice.number.clone(),
+                buyer: invoice.buyer.name.clone(),
+                debtor: DebtorKind::for_party(&invoice.buyer),
+                issue_date: invoice.issue_date,
+                due_date: invoice.due_date,
+                currency: invoice.currency_code.clone(),
+                exchange_rate: invoice.exchange_rate.clone(),
+                amount,
+                postings: Vec::new(),
+                discounts,
+            },
+        );
+        Ok(())
+    }
+
+    /// Apply a payment received on `date`.
+    ///
+    /// If the payment leaves a remainder no larger than the Skonto still open
+    /// on that date, the remainder is posted as Skonto and the item is
+    /// settled. Returns the Skonto posted; zero or negative amounts are
+    /// rejected.
+    pub fn apply_payment(
+        &mut self,
+        number: &str,
+        date: NaiveDate,
+        amount: Decimal,
+    ) -> Result<Decimal, RechnungError> {
+        if amount <= Decimal::ZERO {
+            return Err(RechnungError::Builder(format!(
+                "payment of {amount} for invoice {number} must be positive"
+            )));
+        }
+        let item = self.item_mut(number)?;
+        let remainder = item.outstanding() - amount;
+        let first_payment = !item.postings.iter().any(|p| p.kind == PostingKind::Payment);
+        item.postings.push(Posting {
+            date,
+            amount,
+            kind: PostingKind::Payment,
+        });
+        if first_payment && remainder > Decimal::ZERO && remainder <= item.open_discount(date) {
+            item.postings.push(Posting {
+                date,
+                amount: remainder,
+                kind: PostingKind::Skonto,
+            });
+            return Ok(remainder);
+        }
+        Ok(Decimal::ZERO)
+    }
+
+    /// Write off the outstanding amount (or part of it) as uncollectible.
+    pub fn write_off(
+        &mut self,
+        number: &str,
+        date: NaiveDate,
+        amount: Option<Decimal>,
+    ) -> Result<(), RechnungError> {
+        let item = self.item_mut(number)?;
+        let amount = amount.unwrap_or_else(|| item.outstanding());
+        item.postings.push(Posting {
+            date,
+            amount,
+            kind: PostingKind::WriteOff,
+        });
+        Ok(())
+    }
+
+    /// Look up an item by invoice number.
+    pub fn item(&self, number: &str) -> Option<&OpenItem> {
+        self.items.get(number)
+    }
+
+    /// All items, ordered by invoice number.
+    pub fn items(&self) -> impl Iterator<Item = &OpenItem> {
+        self.items.values()
+    }
+
+    /// Items with a non-zero balance at the end of `as_of`.
+    pub fn open_items(&self, as_of: NaiveDate) -> Vec<&OpenItem> {
+        self.items
+            .values()
+            .filter(|i| i.issue_date <= as_of && !i.outstanding_at(as_of).is_zero())
+            .collect()
+    }
+
+    /// Total outstanding per currency at the end of `as_of`.
+    pub fn balance(&self, as_of: NaiveDate) -> BTreeMap<String, Decimal> {
+        let mut totals = BTreeMap::new();
+        for item in self.open_items(as_of) {
+            *totals.entry(item.currency.clone()).or_default() += item.outstanding_at(as_of);
+        }
+        totals
+    }
+
+    /// Aging report of receivables at `as_of`, by buyer and currency.
+    ///
+    /// Only positive balances are aged; open credits are left out.
+    pub fn aging(&self, as_of: NaiveDate) -> AgingReport {
+        let mut rows: BTreeMap<(String, String), AgingRow> = BTreeMap::new();
+        for item in self.open_items(as_of) {
+            let open = item.outstanding_at(as_of);
+            if open <= Decimal::ZERO {
+                continue;
+            }
+            rows.entry((item.buyer.clone(), item.currency.clone()))
+                .or_insert_with(|| AgingRow {
+                    buyer: item.buyer.clone(),
+                    currency: item.currency.clone(),
+                    ..Default::default()
+                })
+                .add(AgingBucket::for_days(item.days_overdue(as_of)), open);
+        }
+        AgingReport {
+            as_of,
+            rows: rows.into_values().collect(),
+        }
+    }
+
+    fn item_mut(&mut self, number: &str) -> Result<&mut OpenItem, RechnungError> {
+        self.items
+            .get_mut(number)
+            .ok_or_else(|| RechnungError::Builder(format!("invoice {number} is not in the ledger")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aging_buckets() {
+        assert_eq!(AgingBucket::for_days(-1), AgingBucket::NotDue);
+        assert_eq!(AgingBucket::for_days(0), AgingBucket::Days0To30);
+        assert_eq!(AgingBucket::for_days(30), AgingBucket::Days0To30);
+        assert_eq!(AgingBucket::for_days(31), AgingBucket::Days31To60);
+        assert_eq!(AgingBucket::for_days(90), AgingBucket::Days61To90);
+        assert_eq!(AgingBucket::for_days(91), AgingBucket::Over90);
+    }
+}
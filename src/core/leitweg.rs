@@ -0,0 +1,188 @@
+//! Leitweg-ID of German public-sector buyers.
+//!
+//! The Leitweg-ID routes XRechnung invoices to the receiving authority and
+//! is carried as buyer reference (BT-10) and, with EAS scheme 0204, as the
+//! buyer's electronic address (BT-49). It consists of a coarse part
+//! (Grobadressierung, 2–12 digits starting with the federal state code), an
+//! optional fine part (Feinadressierung, up to 30 letters and digits) and two
+//! ISO 7064 mod 97-10 check digits, separated by hyphens:
+//! `04011000-1234512345-06`.
+
+use super::RechnungError;
+
+/// A structurally valid Leitweg-ID with correct check digits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LeitwegId {
+    coarse: String,
+    fine: Option<String>,
+    check: u32,
+}
+
+impl LeitwegId {
+    /// Parse `coarse[-fine]-check` and verify the check digits.
+    pub fn parse(text: &str) -> Result<Self, RechnungError> {
+        let text = text.trim();
+        parse(text).map_err(|e| RechnungError::Validation(format!("Leitweg-ID '{text}': {e}")))
+    }
+
+    /// Build a Leitweg-ID from its coarse and fine part, computing the check
+    /// digits.
+    pub fn new(coarse: &str, fine: Option<&str>) -> Result<Self, RechnungError> {
+        check_parts(coarse, fine)
+            .map_err(|e| RechnungError::Validation(format!("Leitweg-ID: {e}")))?;
+        Ok(Self {
+            coarse: coarse.into(),
+            fine: fine.map(str::to_string),
+            check: check_digits(coarse, fine.unwrap_or("")),
+        })
+    }
+
+    /// Grobadressierung: the authority, starting with the federal state code.
+    pub fn coarse(&self) -> &str {
+        &self.coarse
+    }
+
+    /// Feinadressierung: the department or office within the authority.
+    pub fn fine(&self) -> Option<&str> {
+        self.fine.as_deref()
+    }
+
+    /// The two check digits.
+    pub fn check_digits(&self) -> u32 {
+        self.check
+    }
+
+    /// Two-digit federal state code (Bundesland-Kennzahl), `99` for the
+    /// federal administration.
+    pub fn state_code(&self) -> &str {
+        &self.coarse[..2]
+    }
+
+    /// Name of the federal state, or `Bund` for the federal administration;
+    /// `None` for codes outside 01–16 and 99.
+    pub fn state(&self) -> Option<&'static str> {
+        let name = match self.state_code() {
+            "01" => "Schleswig-Holstein",
+            "02" => "Hamburg",
+            "03" => "Niedersachsen",
+            "04" => "Bremen",
+            "05" => "Nordrhein-Westfalen",
+            "06" => "Hessen",
+            "07" => "Rheinland-Pfalz",
+            "08" => "Baden-Württemberg",
+            "09" => "Bayern",
+            "10" => "Saarland",
+            "11" => "Berlin",
+            "12" => "Brandenburg",
+            "13" => "Mecklenburg-Vorpommern",
+            "14" => "Sachsen",
+            "15" => "Sachsen-Anhalt",
+            "16" => "Thüringen",
+            "99" => "Bund",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl std::fmt::Display for LeitwegId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.fine {
+            Some(fine) => write!(f, "{}-{}-{:02}", self.coarse, fine, self.check),
+            None => write!(f, "{}-{:02}", self.coarse, self.check),
+        }
+    }
+}
+
+/// Parse a Leitweg-ID; the error describes what is wrong.
+pub(crate) fn parse(text: &str) -> Result<LeitwegId, String> {
+    let parts: Vec<&str> = text.split('-').collect();
+    let (coarse, fine, check) = match parts.as_slice() {
+        [coarse, check] => (*coarse, None, *check),
+        [coarse, fine, check] => (*coarse, Some(*fine), *check),
+        _ => return Err("must have the form coarse[-fine]-check".into()),
+    };
+    check_parts(coarse, fine)?;
+    if check.len() != 2 || !check.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("check digits must be 2 digits".into());
+    }
+    let expected = check_digits(coarse, fine.unwrap_or(""));
+    if check.parse() != Ok(expected) {
+        return Err(format!("check digits must be {expected:02}"));
+    }
+    Ok(LeitwegId {
+        coarse: coarse.into(),
+        fine: fine.map(str::to_string),
+        check: expected,
+    })
+}
+
+fn check_parts(coarse: &str, fine: Option<&str>) -> Result<(), String> {
+    if !(2..=12).contains(&coarse.len()) || !coarse.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("coarse part '{coarse}' must be 2 to 12 digits"));
+    }
+    if let Some(fine) = fine {
+        if fine.is_empty() || fine.len() > 30 || !fine.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "fine part '{fine}' must be 1 to 30 letters or digits"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// ISO 7064 mod 97-10 check digits over coarse and fine part, letters
+/// counting as 10–35.
+fn check_digits(coarse: &str, fine: &str) -> u32 {
+    let remainder = format!("{coarse}{fine}00").bytes().fold(0, |acc, b| {
+        let b = b.to_ascii_uppercase();
+        if b.is_ascii_digit() {
+            (acc * 10 + u32::from(b - b'0')) % 97
+        } else {
+            (acc * 100 + u32::from(b - b'A') + 10) % 97
+        }
+    });
+    98 - remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_with_fine_part() {
+        let id = LeitwegId::parse("04011000-1234512345-06").unwrap();
+        assert_eq!(id.coarse(), "04011000");
+        assert_eq!(id.fine(), Some("1234512345"));
+        assert_eq!(id.check_digits(), 6);
+        assert_eq!(id.state(), Some("Bremen"));
+        assert_eq!(id.to_string(), "04011000-1234512345-06");
+    }
+
+    #[test]
+    fn letters_in_fine_part() {
+        let id = LeitwegId::parse("991-33333TEST-33").unwrap();
+        assert_eq!(id.state(), Some("Bund"));
+        assert_eq!(LeitwegId::new("991", Some("33333TEST")).unwrap(), id);
+    }
+
+    #[test]
+    fn wrong_check_digits_name_the_expected_ones() {
+        let err = LeitwegId::parse("04011000-1234512345-07").unwrap_err();
+        assert!(err.to_string().contains("must be 06"), "{err}");
+    }
+
+    #[test]
+    fn malformed() {
+        for text in [
+            "",
+            "0401100012345",
+            "4-06",
+            "04011000--06",
+            "04011000-ABC-DEF-06",
+            "0401a000-06",
+        ] {
+            assert!(LeitwegId::parse(text).is_err(), "{text}");
+        }
+    }
+}
@@ -6,19 +6,44 @@
 mod builder;
 pub mod countries;
 pub mod currencies;
+#[cfg(any(feature = "peppol-smp", feature = "signature"))]
+pub(crate) mod der;
+#[cfg(any(feature = "peppol-smp", feature = "signature"))]
+pub(crate) mod digest;
+pub mod dunning;
+pub mod eas;
 mod error;
+pub mod exchange;
+#[cfg(any(feature = "peppol-smp", feature = "signature"))]
+pub(crate) mod http;
+pub mod iban;
+pub mod ledger;
+mod leitweg;
 mod numbering;
 pub mod reason_codes;
+pub mod skonto;
 mod types;
 pub mod units;
 mod validation;
 
 pub use builder::*;
 pub use countries::is_known_country_code;
-pub use currencies::is_known_currency_code;
+pub use currencies::{
+    amount_scale, invoice_amount_scale, is_known_currency_code, minor_units, round_amount,
+    round_invoice_amount,
+};
+pub use dunning::{DunningNotice, DunningPolicy};
+pub use eas::{EasScheme, eas_schemes_for_country, is_known_eas_code};
 pub use error::*;
+pub use exchange::{
+    ExchangeRate, ExchangeRateProvider, ExchangeRateTable, RateSource, apply_exchange_rate,
+};
+pub use iban::{is_valid_bic, is_valid_iban};
+pub use ledger::{AgingReport, OpenItem, OpenItemsLedger};
+pub use leitweg::LeitwegId;
 pub use numbering::*;
 pub use reason_codes::{is_known_allowance_reason, is_known_charge_reason};
+pub use skonto::CashDiscount;
 pub use types::*;
 pub use units::is_known_unit_code;
 pub use validation::*;
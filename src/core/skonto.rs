@@ -0,0 +1,225 @@
+//! Cash discount (Skonto) terms.
+//!
+//! XRechnung carries Skonto in the payment terms text (BT-20) using the
+//! structured syntax `#SKONTO#TAGE=14#PROZENT=2.00#`, optionally followed by
+//! `BASISBETRAG=100.00#`, one line per tier, each terminated by a line feed.
+//! Days are counted from the invoice issue date. The ZUGFeRD Extended profile
+//! additionally allows `ram:ApplicableTradePaymentDiscountTerms`.
+//!
+//! [`CashDiscount`] is the typed model; [`payment_terms_text`] renders it
+//! together with free-text terms and [`parse_payment_terms`] splits it back.
+
+use std::str::FromStr;
+
+use chrono::{Days, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::currencies::round_amount;
+use super::types::Invoice;
+
+/// A cash discount tier: `percent` off when paid within `days` of the issue date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CashDiscount {
+    /// Payment period in days, counted from the invoice issue date (TAGE).
+    pub days: u32,
+    /// Discount rate in percent, e.g. 2.00 (PROZENT).
+    pub percent: Decimal,
+    /// Amount the discount applies to (BASISBETRAG). Defaults to the amount due.
+    pub base_amount: Option<Decimal>,
+}
+
+impl CashDiscount {
+    /// Create a discount of `percent` for payment within `days`.
+    pub fn new(days: u32, percent: Decimal) -> Self {
+        Self {
+            days,
+            percent,
+            base_amount: None,
+        }
+    }
+
+    /// Restrict the discount to a base amount (e.g. excluding non-discountable lines).
+    pub fn with_base_amount(mut self, amount: Decimal) -> Self {
+        self.base_amount = Some(amount);
+        self
+    }
+
+    /// Last day on which payment still qualifies for the discount.
+    pub fn deadline(&self, issue_date: NaiveDate) -> NaiveDate {
+        issue_date + Days::new(u64::from(self.days))
+    }
+
+    /// Discount amount for an invoice with the given amount due, rounded to
+    /// the currency's minor units.
+    pub fn discount_amount(&self, amount_due: Decimal, currency: &str) -> Decimal {
+        let base = self.base_amount.unwrap_or(amount_due);
+        round_amount(base * self.percent / Decimal::ONE_HUNDRED, currency)
+    }
+
+    /// Render as one XRechnung BT-20 line, without the trailing line feed.
+    ///
+    /// ```
+    /// use faktura::core::CashDiscount;
+    /// use rust_decimal_macros::dec;
+    ///
+    /// let skonto = CashDiscount::new(14, dec!(2));
+    /// assert_eq!(skonto.to_xrechnung(), "#SKONTO#TAGE=14#PROZENT=2.00#");
+    /// ```
+    pub fn to_xrechnung(&self) -> String {
+        let mut s = format!(
+            "#SKONTO#TAGE={}#PROZENT={:.2}#",
+            self.days,
+            self.percent.round_dp(2)
+        );
+        if let Some(base) = self.base_amount {
+            s.push_str(&format!("BASISBETRAG={:.2}#", base.round_dp(2)));
+        }
+        s
+    }
+
+    /// Parse a single `#SKONTO#...#` line. Returns `None` if the line is not
+    /// a well-formed Skonto segment.
+    pub fn from_xrechnung(line: &str) -> Option<Self> {
+        let body = line.trim().strip_prefix("#SKONTO#")?.strip_suffix('#')?;
+        let mut days = None;
+        let mut percent = None;
+        let mut base_amount = None;
+        for part in body.split('#') {
+            let (key, value) = part.split_once('=')?;
+            match key {
+                "TAGE" => days = Some(value.parse::<u32>().ok()?),
+                "PROZENT" => percent = Some(Decimal::from_str(value).ok()?),
+                "BASISBETRAG" => base_amount = Some(Decimal::from_str(value).ok()?),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            days: days?,
+            percent: percent?,
+            base_amount,
+        })
+    }
+}
+
+/// Combine Skonto lines and free-text payment terms into the BT-20 text.
+///
+/// Skonto lines come first, each terminated by a line feed as XRechnung
+/// requires. Returns `None` if there is neither.
+pub fn payment_terms_text(invoice: &Invoice) -> Option<String> {
+    if invoice.cash_discounts.is_empty() {
+        return invoice.payment_terms.clone();
+    }
+    let mut text = String::new();
+    for discount in &invoice.cash_discounts {
+        text.push_str(&discount.to_xrechnung());
+        text.push('\n');
+    }
+    if let Some(terms) = &invoice.payment_terms {
+        text.push_str(terms);
+    }
+    Some(text)
+}
+
+/// Split a BT-20 text into its Skonto tiers and the remaining free text.
+///
+/// Lines that start with `#SKONTO#` but are malformed are kept in the free
+/// text so that validation (BR-DE-18) can still report them.
+pub fn parse_payment_terms(text: &str) -> (Vec<CashDiscount>, Option<String>) {
+    let mut discounts = Vec::new();
+    let mut rest = Vec::new();
+    for line in text.lines() {
+        match CashDiscount::from_xrechnung(line) {
+            Some(discount) => discounts.push(discount),
+            None => rest.push(line),
+        }
+    }
+    let rest = rest.join("\n");
+    let rest = rest.trim();
+    (discounts, (!rest.is_empty()).then(|| rest.to_string()))
+}
+
+/// The most favourable discount tier still open when paying on `paid_on`.
+pub fn applicable_discount(invoice: &Invoice, paid_on: NaiveDate) -> Option<&CashDiscount> {
+    let amount_due = invoice.totals.as_ref()?.amount_due;
+    invoice
+        .cash_discounts
+        .iter()
+        .filter(|d| paid_on <= d.deadline(invoice.issue_date))
+        .max_by_key(|d| d.discount_amount(amount_due, &invoice.currency_code))
+}
+
+/// Amount payable when paying on `paid_on`, after any applicable Skonto.
+///
+/// Returns zero if totals have not been calculated.
+pub fn discounted_amount_due(invoice: &Invoice, paid_on: NaiveDate) -> Decimal {
+    let Some(totals) = invoice.totals.as_ref() else {
+        return Decimal::ZERO;
+    };
+    match applicable_discount(invoice, paid_on) {
+        Some(d) => totals.amount_due - d.discount_amount(totals.amount_due, &invoice.currency_code),
+        None => totals.amount_due,
+    }
+}
+
+/// Payment schedule: for each Skonto tier, the last qualifying date and the
+/// amount payable by then, ordered by date.
+pub fn discount_schedule(invoice: &Invoice) -> Vec<(NaiveDate, Decimal)> {
+    let Some(totals) = invoice.totals.as_ref() else {
+        return Vec::new();
+    };
+    let mut schedule: Vec<_> = invoice
+        .cash_discounts
+        .iter()
+        .map(|d| {
+            (
+                d.deadline(invoice.issue_date),
+                totals.amount_due - d.discount_amount(totals.amount_due, &invoice.currency_code),
+            )
+        })
+        .collect();
+    schedule.sort();
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn xrechnung_line_roundtrip() {
+        let d = CashDiscount::new(7, dec!(2.5)).with_base_amount(dec!(100));
+        let line = d.to_xrechnung();
+        assert_eq!(line, "#SKONTO#TAGE=7#PROZENT=2.50#BASISBETRAG=100.00#");
+        assert_eq!(CashDiscount::from_xrechnung(&line), Some(d));
+    }
+
+    #[test]
+    fn malformed_lines_rejected() {
+        assert_eq!(CashDiscount::from_xrechnung("#SKONTO#TAGE=14#"), None);
+        assert_eq!(
+            CashDiscount::from_xrechnung("#SKONTO#TAGE=14#PROZENT=2.00"),
+            None
+        );
+        assert_eq!(
+            CashDiscount::from_xrechnung("#SKONTO#TAGE=x#PROZENT=2.00#"),
+            None
+        );
+        assert_eq!(CashDiscount::from_xrechnung("2% Skonto"), None);
+    }
+
+    #[test]
+    fn split_terms() {
+        let (discounts, rest) = parse_payment_terms(
+            "#SKONTO#TAGE=7#PROZENT=2.00#\n#SKONTO#TAGE=14#PROZENT=1.00#\nZahlbar netto 30 Tage",
+        );
+        assert_eq!(discounts.len(), 2);
+        assert_eq!(discounts[1].days, 14);
+        assert_eq!(rest.as_deref(), Some("Zahlbar netto 30 Tage"));
+
+        let (discounts, rest) = parse_payment_terms("#SKONTO#TAGE=7#PROZENT=2.00#\n");
+        assert_eq!(discounts.len(), 1);
+        assert_eq!(rest, None);
+    }
+}
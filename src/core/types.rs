@@ -2,6 +2,9 @@ use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use super::exchange::ExchangeRate;
+use super::skonto::CashDiscount;
+
 /// BG-0: Invoice — the top-level document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -18,6 +21,8 @@ pub struct Invoice {
     pub currency_code: String,
     /// BT-6: Tax currency code (ISO 4217), when VAT is reported in a different currency.
     pub tax_currency_code: Option<String>,
+    /// Exchange rate used to convert the VAT total into the tax currency (BT-111).
+    pub exchange_rate: Option<ExchangeRate>,
     /// BT-22: Note / free text.
     pub notes: Vec<String>,
     /// BT-10: Buyer reference (Leitweg-ID for XRechnung).
@@ -48,6 +53,8 @@ pub struct Invoice {
     pub totals: Option<Totals>,
     /// BT-20: Payment terms free text.
     pub payment_terms: Option<String>,
+    /// Cash discount (Skonto) tiers, rendered into BT-20 as `#SKONTO#` lines.
+    pub cash_discounts: Vec<CashDiscount>,
     /// BG-16: Payment instructions.
     pub payment: Option<PaymentInstructions>,
     /// BT-8: Tax point date (Leistungsdatum).
@@ -64,6 +71,18 @@ pub struct Invoice {
     pub tax_representative: Option<TaxRepresentative>,
     /// BG-24: Document attachments.
     pub attachments: Vec<DocumentAttachment>,
+    /// BT-17 / BT-18 and other referenced documents (ZUGFeRD Extended).
+    pub additional_references: Vec<AdditionalReference>,
+    /// BT-16: Despatch advice references. EN 16931 carries one, ZUGFeRD
+    /// Extended several.
+    pub despatch_advices: Vec<DocumentReference>,
+    /// Delivery note references (ZUGFeRD Extended).
+    pub delivery_notes: Vec<DocumentReference>,
+    /// Freight, packing and other logistics service charges (ZUGFeRD Extended).
+    /// Counted as document-level charges in the totals.
+    pub logistics_charges: Vec<LogisticsServiceCharge>,
+    /// Payment instalments with their own due date and amount (ZUGFeRD Extended).
+    pub payment_schedule: Vec<PaymentTerm>,
 }
 
 /// BG-4 / BG-7: Party (seller or buyer).
@@ -78,6 +97,9 @@ pub struct Party {
     pub tax_number: Option<String>,
     /// BT-30 / BT-47: Legal registration identifier.
     pub registration_id: Option<String>,
+    /// BT-30-1 / BT-47-1: Scheme of the legal registration identifier
+    /// (ISO 6523 ICD, e.g. "0088" GLN).
+    pub registration_scheme: Option<String>,
     /// BT-29 / BT-46: Trading name.
     pub trading_name: Option<String>,
     /// BG-5 / BG-8: Postal address.
@@ -173,6 +195,61 @@ pub struct LineItem {
     pub attributes: Vec<ItemAttribute>,
     /// BG-26: Line-level invoicing period.
     pub invoicing_period: Option<Period>,
+    /// BT-132: Referenced purchase order line.
+    pub order_line_id: Option<String>,
+    /// Purchase order of this line when it differs from BT-13 (ZUGFeRD Extended).
+    pub order_reference: Option<String>,
+    /// BT-128 and other line-level referenced documents (ZUGFeRD Extended).
+    pub additional_references: Vec<AdditionalReference>,
+    /// Line this sub-line belongs to (ZUGFeRD Extended).
+    pub parent_line_id: Option<String>,
+    /// Role of the line in a line group (ZUGFeRD Extended).
+    pub line_status: Option<LineStatus>,
+}
+
+impl LineItem {
+    /// Whether the line amount is part of the sum of line net amounts
+    /// (BT-106): group lines are subtotals of their detail lines, and
+    /// information lines are not counted at all.
+    pub fn counts_in_totals(&self) -> bool {
+        !matches!(
+            self.line_status,
+            Some(LineStatus::Group | LineStatus::Information)
+        )
+    }
+}
+
+/// Role of an invoice line in a line group (`ram:LineStatusReasonCode`,
+/// ZUGFeRD Extended).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineStatus {
+    /// DETAIL — a regular line, counted in the totals.
+    Detail,
+    /// GROUP — a subtotal of its sub-lines, not counted in the totals.
+    Group,
+    /// INFORMATION — shown for information only, not part of any total.
+    Information,
+}
+
+impl LineStatus {
+    /// The `LineStatusReasonCode` value.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Detail => "DETAIL",
+            Self::Group => "GROUP",
+            Self::Information => "INFORMATION",
+        }
+    }
+
+    /// Parse a `LineStatusReasonCode` value.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "DETAIL" => Some(Self::Detail),
+            "GROUP" => Some(Self::Group),
+            "INFORMATION" => Some(Self::Information),
+            _ => None,
+        }
+    }
 }
 
 /// UNTDID 5305 — Tax category codes.
@@ -259,6 +336,11 @@ pub enum InvoiceTypeCode {
     Prepayment,
     /// 326 — Partial invoice.
     Partial,
+    /// 389 — Self-billed invoice (Gutschrift), issued by the buyer on the
+    /// seller's behalf.
+    SelfBilled,
+    /// 261 — Self-billed credit note, correcting a self-billed invoice.
+    SelfBilledCreditNote,
     /// Other UNTDID 1001 code value.
     Other(u16),
 }
@@ -272,6 +354,8 @@ impl InvoiceTypeCode {
             Self::Corrected => 384,
             Self::Prepayment => 386,
             Self::Partial => 326,
+            Self::SelfBilled => 389,
+            Self::SelfBilledCreditNote => 261,
             Self::Other(c) => *c,
         }
     }
@@ -284,9 +368,26 @@ impl InvoiceTypeCode {
             384 => Self::Corrected,
             386 => Self::Prepayment,
             326 => Self::Partial,
+            389 => Self::SelfBilled,
+            261 => Self::SelfBilledCreditNote,
             other => Self::Other(other),
         })
     }
+
+    /// Whether the document is a credit note (381, 261), serialized as UBL
+    /// `CreditNote`.
+    pub fn is_credit_note(&self) -> bool {
+        matches!(self, Self::CreditNote | Self::SelfBilledCreditNote)
+    }
+
+    /// Whether the document is issued by the buyer (self-billing: 389, 261,
+    /// 527 self-billed debit note).
+    pub fn is_self_billed(&self) -> bool {
+        matches!(
+            self,
+            Self::SelfBilled | Self::SelfBilledCreditNote | Self::Other(527)
+        )
+    }
 }
 
 /// Document-level or line-level allowance/charge.
@@ -473,6 +574,108 @@ pub struct ItemAttribute {
     pub name: String,
     /// BT-161: Attribute value.
     pub value: String,
+    /// Measured value with its unit, e.g. 2.5 KGM (ZUGFeRD Extended).
+    pub measure: Option<Measure>,
+}
+
+/// A quantity with its unit of measure (UNECE Rec 20).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measure {
+    /// Numeric value.
+    pub value: Decimal,
+    /// Unit code, e.g. "KGM" or "MTR".
+    pub unit: String,
+}
+
+/// Reference to a delivery note or despatch advice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentReference {
+    /// Document identifier.
+    pub id: String,
+    /// Document date.
+    pub issue_date: Option<NaiveDate>,
+}
+
+/// Referenced document other than an attachment (BG-24).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdditionalReference {
+    /// BT-17 / BT-18 / BT-128: Document identifier.
+    pub id: String,
+    /// UNTDID 1001 document type: "50" tender or lot (BT-17), "130"
+    /// invoiced object (BT-18, BT-128); other types need ZUGFeRD Extended.
+    pub type_code: String,
+    /// BT-18-1 / BT-128-1: Identifier scheme (UNTDID 1153).
+    pub reference_type: Option<String>,
+    /// Document description.
+    pub name: Option<String>,
+    /// Document date.
+    pub issue_date: Option<NaiveDate>,
+}
+
+impl AdditionalReference {
+    /// Whether EN 16931 has a business term for this reference: BT-17 and
+    /// BT-18 on the document, BT-128 on a line.
+    pub fn is_en16931(&self, line_level: bool) -> bool {
+        match self.type_code.as_str() {
+            "130" => true,
+            "50" => !line_level,
+            _ => false,
+        }
+    }
+}
+
+/// Logistics service charge such as freight or packing (ZUGFeRD Extended).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticsServiceCharge {
+    /// Description, e.g. "Fracht".
+    pub description: String,
+    /// Charge amount.
+    pub amount: Decimal,
+    /// Tax category.
+    pub tax_category: TaxCategory,
+    /// Tax rate.
+    pub tax_rate: Decimal,
+}
+
+impl LogisticsServiceCharge {
+    /// The charge as a document-level charge (BG-21), for formats without
+    /// logistics service charges.
+    pub fn to_charge(&self) -> AllowanceCharge {
+        AllowanceCharge {
+            is_charge: true,
+            amount: self.amount,
+            percentage: None,
+            base_amount: None,
+            tax_category: self.tax_category,
+            tax_rate: self.tax_rate,
+            reason: Some(self.description.clone()),
+            reason_code: None,
+        }
+    }
+}
+
+/// One instalment of the payment terms (ZUGFeRD Extended).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentTerm {
+    /// Description of the instalment.
+    pub description: Option<String>,
+    /// Due date of the instalment.
+    pub due_date: Option<NaiveDate>,
+    /// Amount payable by the due date.
+    pub amount: Option<Decimal>,
+    /// Penalty for late payment.
+    pub penalty: Option<PaymentPenalty>,
+}
+
+/// Late-payment penalty of a [`PaymentTerm`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentPenalty {
+    /// Days after the invoice date from which the penalty applies.
+    pub days: u32,
+    /// Penalty rate in percent.
+    pub percent: Decimal,
+    /// Amount the penalty is calculated on.
+    pub base_amount: Option<Decimal>,
 }
 
 /// BG-3: Preceding invoice reference (for credit notes / corrected invoices).
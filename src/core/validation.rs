@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
+use super::currencies::{invoice_amount_scale, round_invoice_amount};
 use super::error::ValidationError;
 use super::types::*;
 
@@ -95,6 +96,16 @@ pub fn validate_14_ustg(invoice: &Invoice) -> Vec<ValidationError> {
         ));
     }
 
+    // §14 Abs. 4 Nr. 10 UStG — Self-billed invoices must say "Gutschrift"
+    if invoice.type_code == InvoiceTypeCode::SelfBilled
+        && !invoice.notes.iter().any(|n| has_gutschrift_wording(n))
+    {
+        errors.push(ValidationError::new(
+            "notes",
+            "self-billed invoice must contain the word 'Gutschrift' in a note (BT-22) (§14 Abs. 4 Nr. 10 UStG)",
+        ));
+    }
+
     // BR-16: An Invoice shall have at least one Invoice line
     if invoice.lines.is_empty() {
         errors.push(ValidationError::with_rule(
@@ -131,8 +142,13 @@ pub fn validate_arithmetic(invoice: &Invoice) -> Vec<ValidationError> {
         return errors;
     };
 
-    // BR-CO-10: Sum of line net amounts
-    let expected_line_total: Decimal = invoice.lines.iter().filter_map(|l| l.line_amount).sum();
+    // BR-CO-10: Sum of line net amounts (group and information lines excluded)
+    let expected_line_total: Decimal = invoice
+        .lines
+        .iter()
+        .filter(|l| l.counts_in_totals())
+        .filter_map(|l| l.line_amount)
+        .sum();
 
     if totals.line_net_total != expected_line_total {
         errors.push(ValidationError::with_rule(
@@ -202,18 +218,31 @@ pub fn validate_arithmetic(invoice: &Invoice) -> Vec<ValidationError> {
 
 /// Calculate totals for an invoice (mutates in place).
 pub fn calculate_totals(invoice: &mut Invoice, prepaid: Decimal) {
-    // Calculate line amounts
+    let currency = invoice.currency_code.clone();
+
+    // Calculate line amounts, rounded to the currency's minor units (at most 2)
     for line in &mut invoice.lines {
         let base = line.quantity * line.unit_price;
         let allowances: Decimal = line.allowances.iter().map(|a| a.amount).sum();
         let charges: Decimal = line.charges.iter().map(|c| c.amount).sum();
-        line.line_amount = Some(base - allowances + charges);
+        line.line_amount = Some(round_invoice_amount(base - allowances + charges, &currency));
     }
 
-    let line_net_total: Decimal = invoice.lines.iter().filter_map(|l| l.line_amount).sum();
+    // Group lines are subtotals of their sub-lines; information lines are not counted
+    let line_net_total: Decimal = invoice
+        .lines
+        .iter()
+        .filter(|l| l.counts_in_totals())
+        .filter_map(|l| l.line_amount)
+        .sum();
 
     let allowances_total: Decimal = invoice.allowances.iter().map(|a| a.amount).sum();
-    let charges_total: Decimal = invoice.charges.iter().map(|c| c.amount).sum();
+    let charges_total: Decimal = invoice.charges.iter().map(|c| c.amount).sum::<Decimal>()
+        + invoice
+            .logistics_charges
+            .iter()
+            .map(|c| c.amount)
+            .sum::<Decimal>();
 
     let net_total = line_net_total - allowances_total + charges_total;
 
@@ -221,7 +250,7 @@ pub fn calculate_totals(invoice: &mut Invoice, prepaid: Decimal) {
     let mut vat_groups: HashMap<(TaxCategory, Decimal), Decimal> = HashMap::new();
 
     // Lines
-    for line in &invoice.lines {
+    for line in invoice.lines.iter().filter(|l| l.counts_in_totals()) {
         let key = (line.tax_category, line.tax_rate);
         *vat_groups.entry(key).or_insert(Decimal::ZERO) +=
             line.line_amount.unwrap_or(Decimal::ZERO);
@@ -238,12 +267,16 @@ pub fn calculate_totals(invoice: &mut Invoice, prepaid: Decimal) {
         let key = (charge.tax_category, charge.tax_rate);
         *vat_groups.entry(key).or_insert(Decimal::ZERO) += charge.amount;
     }
+    for charge in &invoice.logistics_charges {
+        let key = (charge.tax_category, charge.tax_rate);
+        *vat_groups.entry(key).or_insert(Decimal::ZERO) += charge.amount;
+    }
 
     let mut vat_breakdown: Vec<VatBreakdown> = Vec::new();
     let mut vat_total = Decimal::ZERO;
 
     for ((category, rate), taxable_amount) in &vat_groups {
-        let tax_amount = round_half_up(*taxable_amount * *rate / dec!(100), 2);
+        let tax_amount = round_invoice_amount(*taxable_amount * *rate / dec!(100), &currency);
         vat_total += tax_amount;
 
         let exemption_reason = exemption_reason_for(*category, invoice.vat_scenario);
@@ -283,9 +316,9 @@ pub fn calculate_totals(invoice: &mut Invoice, prepaid: Decimal) {
     });
 }
 
-/// Round a Decimal to `dp` decimal places using half-up (commercial rounding).
-fn round_half_up(value: Decimal, dp: u32) -> Decimal {
-    value.round_dp_with_strategy(dp, rust_decimal::RoundingStrategy::MidpointAwayFromZero)
+/// Whether a note carries the "Gutschrift" wording of §14 Abs. 4 Nr. 10 UStG.
+pub(crate) fn has_gutschrift_wording(note: &str) -> bool {
+    note.to_lowercase().contains("gutschrift")
 }
 
 fn validate_party(party: &Party, prefix: &str, errors: &mut Vec<ValidationError>) {
@@ -729,7 +762,10 @@ pub fn validate_en16931(invoice: &Invoice) -> Vec<ValidationError> {
     // when many lines are summed per VAT category. KoSIT accepts this tolerance.
     if let Some(ref totals) = invoice.totals {
         for (i, vb) in totals.vat_breakdown.iter().enumerate() {
-            let expected = round_half_up(vb.taxable_amount * vb.rate / dec!(100), 2);
+            let expected = round_invoice_amount(
+                vb.taxable_amount * vb.rate / dec!(100),
+                &invoice.currency_code,
+            );
             let diff = (vb.tax_amount - expected).abs();
             if diff > dec!(0.02) {
                 errors.push(ValidationError::with_rule(
@@ -764,6 +800,32 @@ pub fn validate_en16931(invoice: &Invoice) -> Vec<ValidationError> {
         }
     }
 
+    // BR-O-02: Lines not subject to VAT exclude seller, tax representative
+    // and buyer VAT identifiers (BT-31, BT-63, BT-48)
+    if invoice
+        .lines
+        .iter()
+        .any(|l| l.tax_category == TaxCategory::NotSubjectToVat)
+    {
+        let vat_ids = [
+            ("seller.vat_id", invoice.seller.vat_id.is_some()),
+            (
+                "tax_representative.vat_id",
+                invoice.tax_representative.is_some(),
+            ),
+            ("buyer.vat_id", invoice.buyer.vat_id.is_some()),
+        ];
+        for (field, present) in vat_ids {
+            if present {
+                errors.push(ValidationError::with_rule(
+                    field,
+                    "invoice with not-subject-to-VAT lines must not contain a VAT identifier",
+                    "BR-O-02",
+                ));
+            }
+        }
+    }
+
     // BR-S-01 through BR-S-10: Standard rate VAT category rules
     if let Some(ref totals) = invoice.totals {
         for (i, vb) in totals.vat_breakdown.iter().enumerate() {
@@ -902,30 +964,36 @@ pub fn validate_en16931(invoice: &Invoice) -> Vec<ValidationError> {
         }
     }
 
-    // BR-DEC-01: Amounts shall have max 2 decimal places
+    // BR-DEC-01: Amounts shall have no more decimals than the currency's minor
+    // units, and never more than 2
     if let Some(ref totals) = invoice.totals {
+        let scale = invoice_amount_scale(&invoice.currency_code);
         check_decimal_places(
             &totals.net_total,
             "totals.net_total",
             "BR-DEC-01",
+            scale,
             &mut errors,
         );
         check_decimal_places(
             &totals.vat_total,
             "totals.vat_total",
             "BR-DEC-01",
+            scale,
             &mut errors,
         );
         check_decimal_places(
             &totals.gross_total,
             "totals.gross_total",
             "BR-DEC-01",
+            scale,
             &mut errors,
         );
         check_decimal_places(
             &totals.amount_due,
             "totals.amount_due",
             "BR-DEC-01",
+            scale,
             &mut errors,
         );
     }
@@ -937,14 +1005,15 @@ fn check_decimal_places(
     value: &Decimal,
     field: &str,
     rule: &str,
+    scale: u32,
     errors: &mut Vec<ValidationError>,
 ) {
-    // Check if value has more than 2 decimal places
-    let rounded = value.round_dp(2);
+    // Check if value has more decimal places than the currency allows
+    let rounded = value.round_dp(scale);
     if *value != rounded {
         errors.push(ValidationError::with_rule(
             field,
-            format!("amount {} has more than 2 decimal places", value),
+            format!("amount {} has more than {} decimal places", value, scale),
             rule,
         ));
     }
@@ -1166,6 +1235,29 @@ mod tests {
         assert_eq!(inv.totals.unwrap().vat_total, dec!(0));
     }
 
+    #[test]
+    fn self_billed_requires_gutschrift_wording() {
+        let result = InvoiceBuilder::new("GS-001", test_date())
+            .type_code(InvoiceTypeCode::SelfBilled)
+            .seller(test_seller())
+            .buyer(test_buyer())
+            .add_line(test_line())
+            .tax_point_date(test_date())
+            .build();
+        assert!(result.unwrap_err().to_string().contains("Gutschrift"));
+
+        let inv = InvoiceBuilder::new("GS-001", test_date())
+            .self_billed()
+            .seller(test_seller())
+            .buyer(test_buyer())
+            .add_line(test_line())
+            .tax_point_date(test_date())
+            .build()
+            .unwrap();
+        assert_eq!(inv.type_code.code(), 389);
+        assert_eq!(inv.notes, ["Gutschrift"]);
+    }
+
     #[test]
     fn tax_representative_exempts_seller_tax_id() {
         // Seller without VAT ID or tax number, but with tax representative
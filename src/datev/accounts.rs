@@ -49,6 +49,21 @@ pub fn revenue_account(
     }
 }
 
+/// Default bank account (Bank) for payment postings.
+pub fn bank_account(chart: ChartOfAccounts) -> u32 {
+    match chart {
+        ChartOfAccounts::SKR03 => 1200,
+        ChartOfAccounts::SKR04 => 1800,
+    }
+}
+
+/// Opening balance account for debitors (Saldenvorträge Debitoren).
+pub fn opening_balance_account(chart: ChartOfAccounts) -> u32 {
+    match chart {
+        ChartOfAccounts::SKR03 | ChartOfAccounts::SKR04 => 9008,
+    }
+}
+
 fn skr03_revenue(category: TaxCategory, rate: Decimal) -> AccountMapping {
     match category {
         TaxCategory::StandardRate => {
@@ -3,10 +3,11 @@
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use super::accounts::{self, ChartOfAccounts};
 use super::bu_key;
-use crate::core::{Invoice, InvoiceTypeCode, RechnungError, TaxCategory};
+use crate::core::{Invoice, OpenItemsLedger, RechnungError, TaxCategory, round_amount, skonto};
 
 /// Configuration for DATEV EXTF export.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +33,23 @@ pub struct DatevConfig {
     pub description: String,
     /// Lock postings on import (Festschreibung).
     pub lock_postings: bool,
+    /// Bank account for payment postings; defaults to the chart's bank account
+    /// (1200 in SKR03, 1800 in SKR04).
+    pub bank_account: Option<u32>,
+    /// Debitor accounts by buyer name (BT-44); other buyers are posted to
+    /// `default_debitor`.
+    #[serde(default)]
+    pub debitor_accounts: BTreeMap<String, u32>,
+}
+
+impl DatevConfig {
+    /// Debitor account of a buyer.
+    pub fn debitor_for(&self, buyer: &str) -> u32 {
+        self.debitor_accounts
+            .get(buyer)
+            .copied()
+            .unwrap_or(self.default_debitor)
+    }
 }
 
 impl Default for DatevConfig {
@@ -48,6 +66,8 @@ impl Default for DatevConfig {
             exported_by: String::new(),
             description: "Buchungsstapel".into(),
             lock_postings: false,
+            bank_account: None,
+            debitor_accounts: BTreeMap::new(),
         }
     }
 }
@@ -105,6 +125,12 @@ impl DatevConfigBuilder {
         self
     }
 
+    /// Set the debitor account of a buyer, by name.
+    pub fn debitor(mut self, buyer: impl Into<String>, account: u32) -> Self {
+        self.config.debitor_accounts.insert(buyer.into(), account);
+        self
+    }
+
     /// Set the source identifier (max 2 chars).
     pub fn source(mut self, source: impl Into<String>) -> Self {
         self.config.source = source.into();
@@ -129,6 +155,12 @@ impl DatevConfigBuilder {
         self
     }
 
+    /// Set the bank account used for payment postings.
+    pub fn bank_account(mut self, account: u32) -> Self {
+        self.config.bank_account = Some(account);
+        self
+    }
+
     /// Build the configuration.
     pub fn build(self) -> DatevConfig {
         self.config
@@ -138,10 +170,16 @@ impl DatevConfigBuilder {
 /// A single DATEV Buchungsstapel row (intermediate representation).
 #[derive(Debug, Clone)]
 pub struct DatevRow {
-    /// Gross amount (always positive).
+    /// Gross amount in the invoice currency (always positive).
     pub amount: Decimal,
     /// S = Soll (debit), H = Haben (credit).
     pub debit_credit: DebitCredit,
+    /// Invoice currency (WKZ Umsatz), ISO 4217.
+    pub currency: String,
+    /// Foreign currency units per 1 EUR (Kurs), for non-EUR invoices.
+    pub exchange_rate: Option<Decimal>,
+    /// Gross amount converted to EUR (Basisumsatz), for non-EUR invoices.
+    pub base_amount: Option<Decimal>,
     /// Account number (Konto).
     pub account: u32,
     /// Contra account (Gegenkonto).
@@ -158,6 +196,8 @@ pub struct DatevRow {
     pub service_date: Option<NaiveDate>,
     /// Due date (Fälligkeit).
     pub due_date: Option<NaiveDate>,
+    /// Cash discount taken (Skonto), only on payment postings.
+    pub discount: Option<Decimal>,
     /// EU country + VAT ID (for EU transactions).
     pub eu_vat_id: Option<String>,
     /// Generalumkehr flag (for storno bookings).
@@ -201,6 +241,108 @@ pub fn to_extf(invoices: &[Invoice], config: &DatevConfig) -> Result<String, Rec
         rows.extend(inv_rows);
     }
 
+    Ok(write_batch(&rows, config, period_start, period_end))
+}
+
+/// A payment received against an issued invoice.
+#[derive(Debug, Clone)]
+pub struct InvoicePayment<'a> {
+    /// The invoice being paid.
+    pub invoice: &'a Invoice,
+    /// Value date of the payment.
+    pub date: NaiveDate,
+    /// Amount received, in the invoice currency.
+    pub amount: Decimal,
+}
+
+/// Generate a DATEV EXTF Buchungsstapel CSV of payment postings (Bank an Debitor).
+///
+/// When a payment falls short of the amount due by no more than the Skonto
+/// still open on the payment date, the difference is written to the Skonto
+/// field so DATEV books it against the discount account.
+pub fn payments_to_extf(
+    payments: &[InvoicePayment<'_>],
+    config: &DatevConfig,
+) -> Result<String, RechnungError> {
+    if payments.is_empty() {
+        return Err(RechnungError::Builder("no payments to export".into()));
+    }
+
+    let period_start = payments.iter().map(|p| p.date).min().unwrap_or_default();
+    let period_end = payments.iter().map(|p| p.date).max().unwrap_or_default();
+
+    let rows = payments
+        .iter()
+        .map(|p| payment_to_row(p, config))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(write_batch(&rows, config, period_start, period_end))
+}
+
+/// Generate a DATEV EXTF Buchungsstapel CSV carrying the open items at `as_of`
+/// (OPOS-Übernahme).
+///
+/// Each open item becomes a posting between the buyer's debitor and the
+/// Saldenvortrag account, with the invoice number in Belegfeld 1 and its due
+/// date, so DATEV's open-item management can clear it against later
+/// payments. Foreign-currency items carry the invoice's exchange rate and
+/// EUR amount. Items issued before the fiscal year are dated on its first day.
+pub fn open_items_to_extf(
+    ledger: &OpenItemsLedger,
+    as_of: NaiveDate,
+    config: &DatevConfig,
+) -> Result<String, RechnungError> {
+    let items = ledger.open_items(as_of);
+    if items.is_empty() {
+        return Err(RechnungError::Builder(format!("no open items at {as_of}")));
+    }
+
+    let rows: Vec<DatevRow> = items
+        .iter()
+        .map(|item| {
+            let open = item.outstanding_at(as_of);
+            let (exchange_rate, base_amount) = match &item.exchange_rate {
+                Some(rate) if !item.currency.eq_ignore_ascii_case("EUR") => {
+                    (Some(rate.rate), Some(rate.to_eur(open.abs())))
+                }
+                _ => (None, None),
+            };
+            DatevRow {
+                amount: open.abs(),
+                debit_credit: if open.is_sign_negative() {
+                    DebitCredit::Haben
+                } else {
+                    DebitCredit::Soll
+                },
+                currency: item.currency.clone(),
+                exchange_rate,
+                base_amount,
+                account: config.debitor_for(&item.buyer),
+                contra_account: accounts::opening_balance_account(config.chart),
+                bu_key: None,
+                date: item.issue_date.max(config.fiscal_year_start),
+                document_number: truncate(&item.number, 36),
+                posting_text: truncate(&format!("OPOS {} {}", item.number, item.buyer), 60),
+                service_date: None,
+                due_date: item.due_date,
+                discount: None,
+                eu_vat_id: None,
+                general_reversal: false,
+            }
+        })
+        .collect();
+
+    let period_start = rows.iter().map(|r| r.date).min().unwrap_or(as_of);
+    let period_end = rows.iter().map(|r| r.date).max().unwrap_or(as_of);
+    Ok(write_batch(&rows, config, period_start, period_end))
+}
+
+fn write_batch(
+    rows: &[DatevRow],
+    config: &DatevConfig,
+    period_start: NaiveDate,
+    period_end: NaiveDate,
+) -> String {
     let mut out = String::new();
 
     // Line 1: EXTF header
@@ -210,11 +352,66 @@ pub fn to_extf(invoices: &[Invoice], config: &DatevConfig) -> Result<String, Rec
     write_column_headers(&mut out);
 
     // Lines 3+: Data rows
-    for row in &rows {
+    for row in rows {
         write_data_row(&mut out, row);
     }
 
-    Ok(out)
+    out
+}
+
+/// Convert a received payment into a DATEV row, with Skonto if taken.
+fn payment_to_row(
+    payment: &InvoicePayment<'_>,
+    config: &DatevConfig,
+) -> Result<DatevRow, RechnungError> {
+    let inv = payment.invoice;
+    let totals = inv.totals.as_ref().ok_or_else(|| {
+        RechnungError::Builder(format!(
+            "invoice {} has no calculated totals — call calculate_totals() first",
+            inv.number
+        ))
+    })?;
+
+    let shortfall = totals.amount_due - payment.amount;
+    let discount = skonto::applicable_discount(inv, payment.date)
+        .map(|d| d.discount_amount(totals.amount_due, &inv.currency_code))
+        .filter(|allowed| shortfall > Decimal::ZERO && shortfall <= *allowed)
+        .map(|_| shortfall);
+
+    // Refunds on credit notes run the other way
+    let debit_credit = if inv.type_code.is_credit_note() {
+        DebitCredit::Haben
+    } else {
+        DebitCredit::Soll
+    };
+
+    let (exchange_rate, base_amount) = match &inv.exchange_rate {
+        Some(rate) if !inv.currency_code.eq_ignore_ascii_case("EUR") => {
+            (Some(rate.rate), Some(rate.to_eur(payment.amount.abs())))
+        }
+        _ => (None, None),
+    };
+
+    Ok(DatevRow {
+        amount: payment.amount.abs(),
+        debit_credit,
+        currency: inv.currency_code.clone(),
+        exchange_rate,
+        base_amount,
+        account: config
+            .bank_account
+            .unwrap_or_else(|| accounts::bank_account(config.chart)),
+        contra_account: config.debitor_for(&inv.buyer.name),
+        bu_key: None,
+        date: payment.date,
+        document_number: truncate(&inv.number, 36),
+        posting_text: truncate(&format!("Zahlung {}", inv.number), 60),
+        service_date: None,
+        due_date: None,
+        discount,
+        eu_vat_id: None,
+        general_reversal: false,
+    })
 }
 
 /// Convert a single invoice into one or more DATEV rows.
@@ -229,7 +426,8 @@ fn invoice_to_rows(inv: &Invoice, config: &DatevConfig) -> Result<Vec<DatevRow>,
     })?;
 
     // Determine debit/credit direction
-    let is_credit_note = inv.type_code == InvoiceTypeCode::CreditNote;
+    let is_credit_note = inv.type_code.is_credit_note();
+    let is_eur = inv.currency_code.eq_ignore_ascii_case("EUR");
 
     let mut rows = Vec::new();
 
@@ -258,14 +456,14 @@ fn invoice_to_rows(inv: &Invoice, config: &DatevConfig) -> Result<Vec<DatevRow>,
             // Credit note: flip direction (H = credit the debitor)
             (
                 DebitCredit::Haben,
-                config.default_debitor,
+                config.debitor_for(&inv.buyer.name),
                 mapping.revenue_account,
             )
         } else {
             // Normal invoice: S = debit the debitor
             (
                 DebitCredit::Soll,
-                config.default_debitor,
+                config.debitor_for(&inv.buyer.name),
                 mapping.revenue_account,
             )
         };
@@ -277,9 +475,17 @@ fn invoice_to_rows(inv: &Invoice, config: &DatevConfig) -> Result<Vec<DatevRow>,
             _ => None,
         };
 
+        let (exchange_rate, base_amount) = match &inv.exchange_rate {
+            Some(rate) if !is_eur => (Some(rate.rate), Some(rate.to_eur(gross.abs()))),
+            _ => (None, None),
+        };
+
         rows.push(DatevRow {
             amount: gross.abs(),
             debit_credit,
+            currency: inv.currency_code.clone(),
+            exchange_rate,
+            base_amount,
             account,
             contra_account,
             bu_key,
@@ -288,6 +494,7 @@ fn invoice_to_rows(inv: &Invoice, config: &DatevConfig) -> Result<Vec<DatevRow>,
             posting_text: truncate(&posting_text, 60),
             service_date: inv.tax_point_date,
             due_date: inv.due_date,
+            discount: None,
             eu_vat_id,
             general_reversal: false,
         });
@@ -385,7 +592,7 @@ fn write_column_headers(out: &mut String) {
 
 fn write_data_row(out: &mut String, row: &DatevRow) {
     // Field 1: Umsatz — decimal with comma separator, 2 decimal places
-    out.push_str(&format_amount(row.amount));
+    out.push_str(&format_amount(round_amount(row.amount, &row.currency)));
     out.push(';');
 
     // Field 2: Soll/Haben
@@ -394,8 +601,24 @@ fn write_data_row(out: &mut String, row: &DatevRow) {
     out.push('"');
     out.push(';');
 
-    // Fields 3-6: WKZ, Kurs, Basisumsatz, WKZ Basisumsatz — empty for EUR
-    out.push_str(";;;;");
+    // Fields 3-6: WKZ Umsatz, Kurs, Basisumsatz, WKZ Basisumsatz — empty for EUR
+    if row.currency.eq_ignore_ascii_case("EUR") {
+        out.push_str(";;;;");
+    } else {
+        out.push('"');
+        out.push_str(&escape_csv(&row.currency));
+        out.push_str("\";");
+        if let Some(rate) = row.exchange_rate {
+            out.push_str(&format_rate(rate));
+        }
+        out.push(';');
+        if let Some(base) = row.base_amount {
+            out.push_str(&format_amount(base));
+            out.push_str(";\"EUR\";");
+        } else {
+            out.push_str(";;");
+        }
+    }
 
     // Field 7: Konto
     out.push_str(&row.account.to_string());
@@ -424,7 +647,10 @@ fn write_data_row(out: &mut String, row: &DatevRow) {
     // Field 12: Belegfeld 2 — empty
     out.push(';');
 
-    // Field 13: Skonto — empty
+    // Field 13: Skonto — payment postings only
+    if let Some(discount) = row.discount {
+        out.push_str(&format_amount(round_amount(discount, &row.currency)));
+    }
     out.push(';');
 
     // Field 14: Buchungstext
@@ -478,12 +704,20 @@ fn write_data_row(out: &mut String, row: &DatevRow) {
 }
 
 /// Format a Decimal as German number: comma separator, 2 decimal places.
+///
+/// DATEV amount fields hold at most 2 decimals, so amounts in currencies
+/// with 3 minor units (KWD, BHD, TND) are rounded to cents here.
 fn format_amount(d: Decimal) -> String {
     let scaled = d.round_dp(2);
     let s = format!("{:.2}", scaled);
     s.replace('.', ",")
 }
 
+/// Format an exchange rate for the Kurs field: comma separator, 6 decimal places.
+fn format_rate(d: Decimal) -> String {
+    format!("{:.6}", d.round_dp(6)).replace('.', ",")
+}
+
 fn truncate(s: &str, max: usize) -> String {
     if s.len() <= max {
         s.to_string()
@@ -522,6 +756,12 @@ mod tests {
         assert_eq!(format_amount(Decimal::new(123456, 3)), "123,46");
     }
 
+    #[test]
+    fn format_rate_six_places() {
+        assert_eq!(format_rate(Decimal::new(10708, 4)), "1,070800");
+        assert_eq!(format_rate(Decimal::new(16861, 2)), "168,610000");
+    }
+
     #[test]
     fn truncate_short() {
         assert_eq!(truncate("abc", 5), "abc");
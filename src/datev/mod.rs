@@ -26,6 +26,10 @@ mod extf;
 
 pub use accounts::{
     AccountMapping, ChartOfAccounts, NamedAccount, account_by_name, account_by_number,
+    bank_account, opening_balance_account,
 };
 pub use bu_key::{BuSchluessel, bu_schluessel};
-pub use extf::{DatevConfig, DatevConfigBuilder, DatevRow, to_extf};
+pub use extf::{
+    DatevConfig, DatevConfigBuilder, DatevRow, InvoicePayment, open_items_to_extf,
+    payments_to_extf, to_extf,
+};
@@ -6,7 +6,7 @@
 use rust_decimal::Decimal;
 use std::collections::BTreeMap;
 
-use crate::core::{Invoice, Party, RechnungError};
+use crate::core::{Invoice, Party, RechnungError, amount_scale, round_amount};
 
 /// Generate the kunden.csv and rechnungsausgang.csv content.
 pub fn generate_csvs(invoices: &[Invoice]) -> Result<(String, String), RechnungError> {
@@ -15,6 +15,19 @@ pub fn generate_csvs(invoices: &[Invoice]) -> Result<(String, String), RechnungE
     Ok((kunden, rechnungen))
 }
 
+/// Decimal places of the amount columns in rechnungsausgang.csv.
+///
+/// At least 2; raised to 3 or 4 when an invoice currency has more minor units
+/// (e.g. KWD, TND), so no invoice amount loses precision.
+pub fn amount_accuracy(invoices: &[Invoice]) -> u32 {
+    invoices
+        .iter()
+        .map(|inv| amount_scale(&inv.currency_code))
+        .max()
+        .unwrap_or(2)
+        .max(2)
+}
+
 /// Generate kunden.csv — unique customers extracted from invoices.
 ///
 /// Columns: Kundenkontonummer;Kundenname;Strasse;PLZ;Ort;Land;UStIdNr
@@ -53,7 +66,10 @@ fn generate_kunden_csv(invoices: &[Invoice]) -> String {
 /// Columns: Belegnummer;Belegdatum;Faelligkeitsdatum;Leistungsdatum;
 ///          Kundenkontonummer;Kundenname;Buchungstext;
 ///          Nettobetrag;Steuersatz;Steuerbetrag;Bruttobetrag;
-///          Waehrung;Belegtyp
+///          Waehrung;Belegtyp;Kurs;SteuerbetragEUR;BruttobetragEUR
+///
+/// The EUR columns are only filled for foreign-currency invoices with an
+/// exchange rate.
 fn generate_rechnungsausgang_csv(invoices: &[Invoice]) -> Result<String, RechnungError> {
     // Build customer ID lookup (same order as kunden_csv)
     let mut customer_ids: BTreeMap<String, String> = BTreeMap::new();
@@ -67,8 +83,11 @@ fn generate_rechnungsausgang_csv(invoices: &[Invoice]) -> Result<String, Rechnun
             });
     }
 
+    let accuracy = amount_accuracy(invoices);
+
     let mut out = String::new();
     for inv in invoices {
+        let currency = inv.currency_code.as_str();
         let totals = inv.totals.as_ref().ok_or_else(|| {
             RechnungError::Builder(format!(
                 "invoice {} has no calculated totals — call calculate_totals() first",
@@ -119,22 +138,34 @@ fn generate_rechnungsausgang_csv(invoices: &[Invoice]) -> Result<String, Rechnun
             csv_field_str(&mut out, &posting_text);
             out.push(';');
             // Nettobetrag
-            csv_field_decimal(&mut out, vb.taxable_amount);
+            csv_field_amount(&mut out, vb.taxable_amount, currency, accuracy);
             out.push(';');
             // Steuersatz
             csv_field_decimal(&mut out, vb.rate);
             out.push(';');
             // Steuerbetrag
-            csv_field_decimal(&mut out, vb.tax_amount);
+            csv_field_amount(&mut out, vb.tax_amount, currency, accuracy);
             out.push(';');
             // Bruttobetrag
-            csv_field_decimal(&mut out, gross);
+            csv_field_amount(&mut out, gross, currency, accuracy);
             out.push(';');
             // Waehrung
             csv_field_str(&mut out, &inv.currency_code);
             out.push(';');
             // Belegtyp
             csv_field_str(&mut out, &type_code);
+            out.push(';');
+            // Kurs;SteuerbetragEUR;BruttobetragEUR
+            match &inv.exchange_rate {
+                Some(rate) if !inv.currency_code.eq_ignore_ascii_case("EUR") => {
+                    csv_field_rate(&mut out, rate.rate);
+                    out.push(';');
+                    csv_field_decimal(&mut out, rate.to_eur(vb.tax_amount));
+                    out.push(';');
+                    csv_field_decimal(&mut out, rate.to_eur(gross));
+                }
+                _ => out.push_str(";;"),
+            }
             out.push_str("\r\n");
         }
     }
@@ -159,3 +190,14 @@ fn csv_field_decimal(out: &mut String, d: Decimal) {
     let s = format!("{:.2}", scaled);
     out.push_str(&s.replace('.', ","));
 }
+
+/// Write an amount rounded to the currency's minor units with `accuracy` decimals.
+fn csv_field_amount(out: &mut String, d: Decimal, currency: &str, accuracy: u32) {
+    let s = format!("{:.*}", accuracy as usize, round_amount(d, currency));
+    out.push_str(&s.replace('.', ","));
+}
+
+fn csv_field_rate(out: &mut String, d: Decimal) {
+    let s = format!("{:.6}", d.round_dp(6));
+    out.push_str(&s.replace('.', ","));
+}
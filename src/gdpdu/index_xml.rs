@@ -59,7 +59,12 @@ pub fn generate_index_xml(
     write_kunden_table(&mut writer)?;
 
     // Table: Rechnungsausgang
-    write_rechnungsausgang_table(&mut writer, &period_from, &period_to)?;
+    write_rechnungsausgang_table(
+        &mut writer,
+        &period_from,
+        &period_to,
+        super::csv_export::amount_accuracy(invoices),
+    )?;
 
     writer
         .write_event(Event::End(BytesEnd::new("Media")))
@@ -121,6 +126,7 @@ fn write_rechnungsausgang_table(
     writer: &mut Writer<Cursor<Vec<u8>>>,
     period_from: &str,
     period_to: &str,
+    amount_accuracy: u32,
 ) -> Result<(), RechnungError> {
     writer
         .write_event(Event::Start(BytesStart::new("Table")))
@@ -191,15 +197,30 @@ fn write_rechnungsausgang_table(
         Some("Rechnungsbetreff"),
         ColType::AlphaNumeric,
     )?;
-    write_variable_col(writer, "Nettobetrag", None, ColType::Numeric2)?;
+    write_variable_col(
+        writer,
+        "Nettobetrag",
+        None,
+        ColType::Numeric(amount_accuracy),
+    )?;
     write_variable_col(
         writer,
         "Steuersatz",
         Some("USt-Satz in Prozent"),
-        ColType::Numeric2,
+        ColType::Numeric(2),
+    )?;
+    write_variable_col(
+        writer,
+        "Steuerbetrag",
+        None,
+        ColType::Numeric(amount_accuracy),
+    )?;
+    write_variable_col(
+        writer,
+        "Bruttobetrag",
+        None,
+        ColType::Numeric(amount_accuracy),
     )?;
-    write_variable_col(writer, "Steuerbetrag", None, ColType::Numeric2)?;
-    write_variable_col(writer, "Bruttobetrag", None, ColType::Numeric2)?;
     write_variable_col(writer, "Waehrung", None, ColType::AlphaNumeric)?;
     write_variable_col(
         writer,
@@ -207,6 +228,24 @@ fn write_rechnungsausgang_table(
         Some("UNTDID 1001 Belegtyp"),
         ColType::AlphaNumeric,
     )?;
+    write_variable_col(
+        writer,
+        "Kurs",
+        Some("Fremdwaehrungseinheiten je EUR"),
+        ColType::Numeric(6),
+    )?;
+    write_variable_col(
+        writer,
+        "SteuerbetragEUR",
+        Some("Steuerbetrag in EUR (§16 Abs. 6 UStG)"),
+        ColType::Numeric(2),
+    )?;
+    write_variable_col(
+        writer,
+        "BruttobetragEUR",
+        Some("Bruttobetrag in EUR"),
+        ColType::Numeric(2),
+    )?;
 
     // ForeignKey to Kunden
     writer
@@ -229,7 +268,8 @@ fn write_rechnungsausgang_table(
 
 enum ColType {
     AlphaNumeric,
-    Numeric2,
+    /// Numeric with the given number of decimal places.
+    Numeric(u32),
     Date,
 }
 
@@ -283,11 +323,11 @@ fn write_col_type(
                 .write_event(Event::Empty(BytesStart::new("AlphaNumeric")))
                 .map_err(xml_err)?;
         }
-        ColType::Numeric2 => {
+        ColType::Numeric(accuracy) => {
             writer
                 .write_event(Event::Start(BytesStart::new("Numeric")))
                 .map_err(xml_err)?;
-            write_text_element(writer, "Accuracy", "2")?;
+            write_text_element(writer, "Accuracy", &accuracy.to_string())?;
             writer
                 .write_event(Event::End(BytesEnd::new("Numeric")))
                 .map_err(xml_err)?;
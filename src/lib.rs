@@ -41,6 +41,11 @@
 //! | `gdpdu` | GDPdU/IDEA tax audit export |
 //! | `vat` | VAT validation, VIES, Kleinunternehmer |
 //! | `peppol` | Peppol BIS Billing 3.0 |
+//! | `as4` | Peppol AS4 sending and receiving |
+//! | `qr` | EPC GiroCode and Swiss QR-bill payment QR codes |
+//! | `sepa` | SEPA pain.001 credit transfers and pain.008 direct debits |
+//! | `payments` | CAMT.053/054 and MT940 import, invoice payment matching |
+//! | `signature` | PAdES/XAdES signing with PKCS#12 keys, signature verification |
 //! | `all` | Everything |
 
 #[cfg(feature = "core")]
@@ -64,6 +69,18 @@ pub mod vat;
 #[cfg(feature = "peppol")]
 pub mod peppol;
 
+#[cfg(feature = "qr")]
+pub mod qr;
+
+#[cfg(feature = "sepa")]
+pub mod sepa;
+
+#[cfg(feature = "payments")]
+pub mod payments;
+
+#[cfg(feature = "signature")]
+pub mod signature;
+
 // Re-export core types at crate root for convenience
 #[cfg(feature = "core")]
 pub use crate::core::*;
@@ -0,0 +1,360 @@
+//! ISO 20022 CAMT statement parsing (`camt.052`, `camt.053`, `camt.054`).
+
+use chrono::NaiveDate;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use super::{BankStatement, CreditDebit, StatementEntry};
+use crate::core::RechnungError;
+
+/// Parse a CAMT document into its statements.
+///
+/// Handles `BkToCstmrStmt` (camt.053), `BkToCstmrDbtCdtNtfctn` (camt.054) and
+/// `BkToCstmrAcctRpt` (camt.052) in any message version. Batch bookings with
+/// several `TxDtls` are split into one entry per transaction.
+pub fn from_camt_xml(xml: &str) -> Result<Vec<BankStatement>, RechnungError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut p = CamtParsed::default();
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => {
+                let name = local_name(e.name().as_ref());
+                match name.as_str() {
+                    "Stmt" | "Ntfctn" | "Rpt" => p.statement = Some(BankStatement::default()),
+                    "Ntry" => p.entry = Some(CamtEntry::default()),
+                    "TxDtls" => p.tx = Some(CamtTx::default()),
+                    "Bal" => p.balance = Some(CamtBalance::default()),
+                    "Amt" | "TxAmt" => {
+                        for attr in e.attributes().flatten() {
+                            if attr.key.as_ref() == b"Ccy" {
+                                p.currency = Some(String::from_utf8_lossy(&attr.value).into());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().unwrap_or_default().to_string();
+                if !text.is_empty() {
+                    p.handle_text(&path, &text)?;
+                }
+            }
+            Ok(Event::End(_)) => {
+                let ended = path.pop().unwrap_or_default();
+                match ended.as_str() {
+                    "TxDtls" => {
+                        if let (Some(tx), Some(entry)) = (p.tx.take(), p.entry.as_mut()) {
+                            entry.txs.push(tx);
+                        }
+                    }
+                    "Ntry" => {
+                        if let (Some(entry), Some(stmt)) = (p.entry.take(), p.statement.as_mut()) {
+                            let currency = stmt.currency.clone();
+                            stmt.entries.extend(entry.into_entries(currency)?);
+                        }
+                    }
+                    "Bal" => {
+                        if let (Some(bal), Some(stmt)) = (p.balance.take(), p.statement.as_mut()) {
+                            bal.apply(stmt);
+                        }
+                    }
+                    "Stmt" | "Ntfctn" | "Rpt" => {
+                        if let Some(stmt) = p.statement.take() {
+                            p.statements.push(stmt);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                return Err(RechnungError::Xml(format!("XML parse error: {e}")));
+            }
+            _ => {}
+        }
+    }
+
+    if p.statements.is_empty() {
+        return Err(RechnungError::Xml(
+            "no CAMT statement or notification found".into(),
+        ));
+    }
+    Ok(p.statements)
+}
+
+/// Element name without namespace prefix.
+fn local_name(qname: &[u8]) -> String {
+    let name = std::str::from_utf8(qname).unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+fn ends_with(path: &[String], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len()
+        && path[path.len() - suffix.len()..]
+            .iter()
+            .zip(suffix)
+            .all(|(a, b)| a == b)
+}
+
+fn parse_amount(text: &str) -> Result<Decimal, RechnungError> {
+    Decimal::from_str(text.trim())
+        .map_err(|e| RechnungError::Xml(format!("invalid amount '{text}': {e}")))
+}
+
+/// Parse `YYYY-MM-DD`, also accepting the date part of an ISO date-time.
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn parse_direction(text: &str) -> Option<CreditDebit> {
+    match text.trim() {
+        "CRDT" => Some(CreditDebit::Credit),
+        "DBIT" => Some(CreditDebit::Debit),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct CamtParsed {
+    statements: Vec<BankStatement>,
+    statement: Option<BankStatement>,
+    balance: Option<CamtBalance>,
+    entry: Option<CamtEntry>,
+    tx: Option<CamtTx>,
+    /// `Ccy` attribute of the most recent amount element.
+    currency: Option<String>,
+}
+
+#[derive(Default)]
+struct CamtBalance {
+    code: Option<String>,
+    amount: Option<Decimal>,
+    direction: Option<CreditDebit>,
+}
+
+impl CamtBalance {
+    fn apply(self, stmt: &mut BankStatement) {
+        let Some(amount) = self.amount else {
+            return;
+        };
+        let signed = match self.direction {
+            Some(CreditDebit::Debit) => -amount,
+            _ => amount,
+        };
+        match self.code.as_deref() {
+            Some("OPBD" | "PRCD") => stmt.opening_balance = Some(signed),
+            Some("CLBD") => stmt.closing_balance = Some(signed),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct CamtEntry {
+    amount: Option<Decimal>,
+    currency: Option<String>,
+    direction: Option<CreditDebit>,
+    booking_date: Option<NaiveDate>,
+    value_date: Option<NaiveDate>,
+    bank_reference: Option<String>,
+    additional_info: Option<String>,
+    txs: Vec<CamtTx>,
+}
+
+#[derive(Default)]
+struct CamtTx {
+    amount: Option<Decimal>,
+    currency: Option<String>,
+    end_to_end_id: Option<String>,
+    bank_reference: Option<String>,
+    debtor_name: Option<String>,
+    debtor_iban: Option<String>,
+    creditor_name: Option<String>,
+    creditor_iban: Option<String>,
+    remittance: Vec<String>,
+    creditor_reference: Option<String>,
+}
+
+impl CamtParsed {
+    fn handle_text(&mut self, path: &[String], text: &str) -> Result<(), RechnungError> {
+        let leaf = path.last().map(String::as_str).unwrap_or("");
+
+        if let Some(tx) = self.tx.as_mut() {
+            let in_parties = path.iter().any(|p| p == "RltdPties");
+            match leaf {
+                "Amt"
+                    if ends_with(path, &["TxDtls", "Amt"])
+                        || ends_with(path, &["TxAmt", "Amt"]) =>
+                {
+                    tx.amount = Some(parse_amount(text)?);
+                    tx.currency = self.currency.take();
+                }
+                "EndToEndId" if text != "NOTPROVIDED" => tx.end_to_end_id = Some(text.into()),
+                "AcctSvcrRef" => tx.bank_reference = Some(text.into()),
+                "Nm" if in_parties && path.iter().any(|p| p == "Dbtr") => {
+                    tx.debtor_name = Some(text.into());
+                }
+                "Nm" if in_parties && path.iter().any(|p| p == "Cdtr") => {
+                    tx.creditor_name = Some(text.into());
+                }
+                "IBAN" if ends_with(path, &["DbtrAcct", "Id", "IBAN"]) => {
+                    tx.debtor_iban = Some(text.into());
+                }
+                "IBAN" if ends_with(path, &["CdtrAcct", "Id", "IBAN"]) => {
+                    tx.creditor_iban = Some(text.into());
+                }
+                "Ustrd" => tx.remittance.push(text.into()),
+                "Ref" if ends_with(path, &["CdtrRefInf", "Ref"]) => {
+                    tx.creditor_reference = Some(text.into());
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if let Some(entry) = self.entry.as_mut() {
+            match leaf {
+                "Amt" if ends_with(path, &["Ntry", "Amt"]) => {
+                    entry.amount = Some(parse_amount(text)?);
+                    entry.currency = self.currency.take();
+                }
+                "CdtDbtInd" if ends_with(path, &["Ntry", "CdtDbtInd"]) => {
+                    entry.direction = parse_direction(text);
+                }
+                "Dt" | "DtTm" if path.iter().any(|p| p == "BookgDt") => {
+                    entry.booking_date = parse_date(text);
+                }
+                "Dt" | "DtTm" if path.iter().any(|p| p == "ValDt") => {
+                    entry.value_date = parse_date(text);
+                }
+                "AcctSvcrRef" if ends_with(path, &["Ntry", "AcctSvcrRef"]) => {
+                    entry.bank_reference = Some(text.into());
+                }
+                "AddtlNtryInf" => entry.additional_info = Some(text.into()),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if let Some(bal) = self.balance.as_mut() {
+            match leaf {
+                "Cd" if path.iter().any(|p| p == "Tp") => bal.code = Some(text.into()),
+                "Amt" => bal.amount = Some(parse_amount(text)?),
+                "CdtDbtInd" => bal.direction = parse_direction(text),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if let Some(stmt) = self.statement.as_mut() {
+            match leaf {
+                "Id" if ends_with(path, &["Stmt", "Id"])
+                    || ends_with(path, &["Ntfctn", "Id"])
+                    || ends_with(path, &["Rpt", "Id"]) =>
+                {
+                    stmt.id = text.into();
+                }
+                "IBAN" if ends_with(path, &["Acct", "Id", "IBAN"]) => {
+                    stmt.account = Some(text.into());
+                }
+                "Ccy" if ends_with(path, &["Acct", "Ccy"]) => stmt.currency = Some(text.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CamtEntry {
+    fn into_entries(
+        self,
+        account_currency: Option<String>,
+    ) -> Result<Vec<StatementEntry>, RechnungError> {
+        let amount = self
+            .amount
+            .ok_or_else(|| RechnungError::Xml("CAMT entry without amount".into()))?;
+        let direction = self
+            .direction
+            .ok_or_else(|| RechnungError::Xml("CAMT entry without CdtDbtInd".into()))?;
+        let currency = self
+            .currency
+            .or(account_currency)
+            .unwrap_or_else(|| "EUR".into());
+
+        let base = StatementEntry {
+            booking_date: self.booking_date,
+            value_date: self.value_date,
+            amount,
+            currency,
+            direction,
+            counterparty_name: None,
+            counterparty_iban: None,
+            remittance_info: self.additional_info,
+            creditor_reference: None,
+            end_to_end_id: None,
+            bank_reference: self.bank_reference,
+        };
+        if self.txs.is_empty() {
+            return Ok(vec![base]);
+        }
+
+        let single = self.txs.len() == 1;
+        Ok(self
+            .txs
+            .into_iter()
+            .map(|tx| {
+                let (name, iban) = match direction {
+                    CreditDebit::Credit => (tx.debtor_name, tx.debtor_iban),
+                    CreditDebit::Debit => (tx.creditor_name, tx.creditor_iban),
+                };
+                let remittance = tx.remittance.join(" ");
+                StatementEntry {
+                    amount: match (single, tx.amount) {
+                        (false, Some(a)) => a,
+                        _ => base.amount,
+                    },
+                    currency: tx.currency.unwrap_or_else(|| base.currency.clone()),
+                    counterparty_name: name,
+                    counterparty_iban: iban,
+                    remittance_info: if remittance.is_empty() {
+                        base.remittance_info.clone()
+                    } else {
+                        Some(remittance)
+                    },
+                    creditor_reference: tx.creditor_reference,
+                    end_to_end_id: tx.end_to_end_id,
+                    bank_reference: tx.bank_reference.or_else(|| base.bank_reference.clone()),
+                    ..base.clone()
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_names() {
+        assert_eq!(local_name(b"camt:Ntry"), "Ntry");
+        assert_eq!(local_name(b"Ntry"), "Ntry");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse_date("2024-06-20T10:00:00+02:00"),
+            NaiveDate::from_ymd_opt(2024, 6, 20)
+        );
+        assert_eq!(parse_date("20.06.2024"), None);
+    }
+}
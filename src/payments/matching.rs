@@ -0,0 +1,328 @@
+//! Matching statement credits to issued invoices.
+
+use rust_decimal::Decimal;
+
+use super::{BankStatement, CreditDebit, StatementEntry};
+use crate::core::iban::normalize_iban;
+use crate::core::skonto::{discount_schedule, discounted_amount_due};
+use crate::core::{Invoice, OpenItemsLedger, RechnungError};
+
+/// Score for the invoice's remittance information (BT-83) found in the payment.
+const SCORE_REMITTANCE: u8 = 60;
+/// Score for the invoice number found in the payment.
+const SCORE_NUMBER: u8 = 50;
+/// Score for the amount due, or the Skonto amount valid on the value date.
+const SCORE_AMOUNT: u8 = 30;
+/// Score for an amount matching a Skonto tier that has already expired.
+const SCORE_LATE_SKONTO: u8 = 15;
+/// Score for a payer IBAN known for the buyer.
+const SCORE_IBAN: u8 = 30;
+/// Score for a payer name equal to the buyer name.
+const SCORE_NAME: u8 = 10;
+
+/// Outcome of matching one credit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStatus {
+    /// Paid in full, or the Skonto amount within the discount period.
+    Matched,
+    /// Less than the amount payable; the invoice remains partly open.
+    Partial,
+    /// More than the amount due.
+    Overpaid,
+    /// No invoice could be identified with sufficient confidence.
+    Unmatched,
+}
+
+/// A statement credit and the invoice it pays.
+#[derive(Debug, Clone)]
+pub struct PaymentMatch<'a> {
+    /// The booking.
+    pub entry: &'a StatementEntry,
+    /// The invoice paid, if identified.
+    pub invoice: Option<&'a Invoice>,
+    /// Classification of the payment.
+    pub status: MatchStatus,
+    /// Confidence of the assignment, 0–100.
+    pub confidence: u8,
+    /// Skonto taken by the payer.
+    pub skonto: Decimal,
+    /// Amount still open (partial) or paid in excess (overpaid).
+    pub difference: Decimal,
+}
+
+/// Matches statement credits against a set of issued invoices.
+///
+/// Each invoice is scored against the payment: remittance information or
+/// invoice number in the Verwendungszweck or end-to-end reference, the
+/// amount (full or Skonto-reduced), the payer's IBAN and name. The best
+/// candidate is accepted if it reaches the minimum confidence (default 50)
+/// and no other invoice scores equally.
+#[derive(Debug, Clone)]
+pub struct PaymentMatcher<'a> {
+    invoices: &'a [Invoice],
+    buyer_accounts: Vec<(String, String)>,
+    min_confidence: u8,
+}
+
+impl<'a> PaymentMatcher<'a> {
+    /// Create a matcher over the open invoices.
+    pub fn new(invoices: &'a [Invoice]) -> Self {
+        Self {
+            invoices,
+            buyer_accounts: Vec::new(),
+            min_confidence: 50,
+        }
+    }
+
+    /// Register a known bank account of a buyer (matched by buyer name).
+    ///
+    /// The debited account of a direct debit mandate (BT-91) is known
+    /// without registration.
+    pub fn with_buyer_account(mut self, buyer_name: &str, iban: &str) -> Self {
+        self.buyer_accounts
+            .push((buyer_name.trim().to_lowercase(), normalize_iban(iban)));
+        self
+    }
+
+    /// Set the minimum confidence for accepting a match.
+    pub fn min_confidence(mut self, confidence: u8) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    /// Match all credits of a statement. Debits are skipped.
+    pub fn match_statement(&self, statement: &'a BankStatement) -> Vec<PaymentMatch<'a>> {
+        self.match_entries(&statement.entries)
+    }
+
+    /// Match a list of bookings. Debits are skipped.
+    pub fn match_entries(&self, entries: &'a [StatementEntry]) -> Vec<PaymentMatch<'a>> {
+        entries
+            .iter()
+            .filter(|e| e.direction == CreditDebit::Credit)
+            .map(|e| self.match_entry(e))
+            .collect()
+    }
+
+    /// Match a single credit.
+    pub fn match_entry(&self, entry: &'a StatementEntry) -> PaymentMatch<'a> {
+        let mut scored: Vec<(u8, &'a Invoice)> = self
+            .invoices
+            .iter()
+            .filter(|inv| inv.currency_code.eq_ignore_ascii_case(&entry.currency))
+            .map(|inv| (self.score(entry, inv), inv))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        let unmatched = |confidence| PaymentMatch {
+            entry,
+            invoice: None,
+            status: MatchStatus::Unmatched,
+            confidence,
+            skonto: Decimal::ZERO,
+            difference: Decimal::ZERO,
+        };
+        let Some(&(best, invoice)) = scored.first() else {
+            return unmatched(0);
+        };
+        let tied = scored.get(1).is_some_and(|(score, _)| *score == best);
+        if best < self.min_confidence || tied {
+            return unmatched(best);
+        }
+        classify(entry, invoice, best)
+    }
+
+    fn score(&self, entry: &StatementEntry, invoice: &Invoice) -> u8 {
+        let text = [
+            entry.remittance_info.as_deref(),
+            entry.creditor_reference.as_deref(),
+            entry.end_to_end_id.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+        let mut score = 0u8;
+        let remittance = invoice
+            .payment
+            .as_ref()
+            .and_then(|p| p.remittance_info.as_deref());
+        if remittance.is_some_and(|r| contains_reference(&text, r)) {
+            score = SCORE_REMITTANCE;
+        } else if contains_reference(&text, &invoice.number) {
+            score = SCORE_NUMBER;
+        }
+
+        if let Some(totals) = &invoice.totals {
+            let on_time = entry
+                .date()
+                .map_or(totals.amount_due, |d| discounted_amount_due(invoice, d));
+            if entry.amount == totals.amount_due || entry.amount == on_time {
+                score += SCORE_AMOUNT;
+            } else if discount_schedule(invoice)
+                .iter()
+                .any(|(_, amount)| *amount == entry.amount)
+            {
+                score += SCORE_LATE_SKONTO;
+            }
+        }
+
+        if entry
+            .counterparty_iban
+            .as_deref()
+            .is_some_and(|iban| self.is_buyer_account(invoice, iban))
+        {
+            score += SCORE_IBAN;
+        } else if entry
+            .counterparty_name
+            .as_deref()
+            .is_some_and(|name| name.trim().eq_ignore_ascii_case(invoice.buyer.name.trim()))
+        {
+            score += SCORE_NAME;
+        }
+        score.min(100)
+    }
+
+    fn is_buyer_account(&self, invoice: &Invoice, iban: &str) -> bool {
+        let iban = normalize_iban(iban);
+        let mandate = invoice
+            .payment
+            .as_ref()
+            .and_then(|p| p.direct_debit.as_ref())
+            .and_then(|d| d.debited_account_id.as_deref());
+        if mandate.is_some_and(|m| normalize_iban(m) == iban) {
+            return true;
+        }
+        let buyer = invoice.buyer.name.trim().to_lowercase();
+        self.buyer_accounts
+            .iter()
+            .any(|(name, account)| *name == buyer && *account == iban)
+    }
+}
+
+/// Match credits against invoices with the default settings.
+pub fn match_payments<'a>(
+    entries: &'a [StatementEntry],
+    invoices: &'a [Invoice],
+) -> Vec<PaymentMatch<'a>> {
+    PaymentMatcher::new(invoices).match_entries(entries)
+}
+
+/// Post matched payments to an open-items ledger.
+///
+/// Unmatched credits are skipped; Skonto is posted by the ledger. Returns the
+/// number of payments applied.
+pub fn apply_matches(
+    ledger: &mut OpenItemsLedger,
+    matches: &[PaymentMatch<'_>],
+) -> Result<usize, RechnungError> {
+    let mut applied = 0;
+    for m in matches {
+        let (Some(invoice), Some(date)) = (m.invoice, m.entry.date()) else {
+            continue;
+        };
+        ledger.apply_payment(&invoice.number, date, m.entry.amount)?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Classify the payment of an identified invoice by amount.
+fn classify<'a>(
+    entry: &'a StatementEntry,
+    invoice: &'a Invoice,
+    confidence: u8,
+) -> PaymentMatch<'a> {
+    let amount_due = invoice
+        .totals
+        .as_ref()
+        .map_or(Decimal::ZERO, |t| t.amount_due);
+    let payable = entry
+        .date()
+        .map_or(amount_due, |d| discounted_amount_due(invoice, d));
+
+    let (status, skonto, difference) = if entry.amount == amount_due {
+        (MatchStatus::Matched, Decimal::ZERO, Decimal::ZERO)
+    } else if entry.amount == payable {
+        (MatchStatus::Matched, amount_due - payable, Decimal::ZERO)
+    } else if entry.amount > amount_due {
+        (
+            MatchStatus::Overpaid,
+            Decimal::ZERO,
+            entry.amount - amount_due,
+        )
+    } else {
+        (
+            MatchStatus::Partial,
+            Decimal::ZERO,
+            amount_due - entry.amount,
+        )
+    };
+    PaymentMatch {
+        entry,
+        invoice: Some(invoice),
+        status,
+        confidence,
+        skonto,
+        difference,
+    }
+}
+
+/// Whether `reference` occurs in `text`, ignoring case, spaces and
+/// punctuation, but not as part of a longer number.
+fn contains_reference(text: &str, reference: &str) -> bool {
+    // Alphanumeric characters, each flagged if a separator precedes it
+    let mut haystack: Vec<(char, bool)> = Vec::new();
+    let mut separated = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            haystack.push((c.to_ascii_uppercase(), separated));
+            separated = false;
+        } else {
+            separated = true;
+        }
+    }
+    let needle: Vec<char> = reference
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if needle.len() < 3 || needle.len() > haystack.len() {
+        return false;
+    }
+    (0..=haystack.len() - needle.len()).any(|start| {
+        let end = start + needle.len();
+        let joined = |i: usize| i > 0 && i < haystack.len() && !haystack[i].1;
+        haystack[start..end]
+            .iter()
+            .zip(&needle)
+            .all(|((c, _), n)| c == n)
+            && !(needle[0].is_ascii_digit()
+                && joined(start)
+                && haystack[start - 1].0.is_ascii_digit())
+            && !(needle[needle.len() - 1].is_ascii_digit()
+                && joined(end)
+                && haystack[end].0.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_boundaries() {
+        assert!(contains_reference(
+            "Rechnung RE 2024 001 vom 15.06.",
+            "RE-2024-001"
+        ));
+        assert!(contains_reference("re2024001", "RE-2024-001"));
+        assert!(!contains_reference("RE-2024-0010", "RE-2024-001"));
+        assert!(!contains_reference("Rechnung 14711", "4711"));
+        assert!(contains_reference("Rechnung 4711.", "4711"));
+        assert!(contains_reference("RE-2024-001 15.06.2024", "RE-2024-001"));
+        assert!(!contains_reference("Miete", "1"));
+    }
+}
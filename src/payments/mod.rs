@@ -0,0 +1,94 @@
+//! Bank statement import and invoice payment matching.
+//!
+//! Parses account statements in ISO 20022 CAMT format (`camt.053` end-of-day
+//! statements, `camt.054` debit/credit notifications) and SWIFT MT940, and
+//! matches incoming credits to issued invoices.
+//!
+//! Matching looks at the remittance information (BT-83), invoice numbers in
+//! the Verwendungszweck, the amount (including Skonto-reduced amounts) and
+//! the payer's IBAN, and classifies each credit as matched, partial,
+//! overpaid or unmatched with a confidence score. Matches can be posted to
+//! an [`OpenItemsLedger`](crate::core::OpenItemsLedger) with [`apply_matches`].
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::payments::*;
+//!
+//! let statements = from_camt_xml(&camt)?;
+//! let matcher = PaymentMatcher::new(&open_invoices);
+//! for m in matcher.match_statement(&statements[0]) {
+//!     println!("{:?} {:?} ({}%)", m.status, m.invoice.map(|i| &i.number), m.confidence);
+//! }
+//! ```
+
+mod camt;
+mod matching;
+mod mt940;
+
+pub use camt::from_camt_xml;
+pub use matching::{MatchStatus, PaymentMatch, PaymentMatcher, apply_matches, match_payments};
+pub use mt940::from_mt940;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+/// Direction of a booking from the account holder's perspective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreditDebit {
+    /// Money received (Gutschrift).
+    Credit,
+    /// Money paid out (Lastschrift).
+    Debit,
+}
+
+/// One account statement (CAMT `Stmt`/`Ntfctn` or one MT940 message).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BankStatement {
+    /// Statement identifier (CAMT `Id`, MT940 `:20:` / `:28C:`).
+    pub id: String,
+    /// Account the statement belongs to (IBAN, or `BLZ/Konto` for MT940).
+    pub account: Option<String>,
+    /// Account currency.
+    pub currency: Option<String>,
+    /// Opening booked balance, negative if debit.
+    pub opening_balance: Option<Decimal>,
+    /// Closing booked balance, negative if debit.
+    pub closing_balance: Option<Decimal>,
+    /// Bookings, one per transaction.
+    pub entries: Vec<StatementEntry>,
+}
+
+/// A single booked transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementEntry {
+    /// Booking date.
+    pub booking_date: Option<NaiveDate>,
+    /// Value date (Wertstellung).
+    pub value_date: Option<NaiveDate>,
+    /// Amount, always positive.
+    pub amount: Decimal,
+    /// Currency of the amount.
+    pub currency: String,
+    /// Credit or debit.
+    pub direction: CreditDebit,
+    /// Name of the payer (credits) or payee (debits).
+    pub counterparty_name: Option<String>,
+    /// IBAN of the payer (credits) or payee (debits).
+    pub counterparty_iban: Option<String>,
+    /// Unstructured remittance information (Verwendungszweck).
+    pub remittance_info: Option<String>,
+    /// Structured creditor reference (e.g. RF reference).
+    pub creditor_reference: Option<String>,
+    /// End-to-end reference set by the payer.
+    pub end_to_end_id: Option<String>,
+    /// Reference assigned by the bank.
+    pub bank_reference: Option<String>,
+}
+
+impl StatementEntry {
+    /// Value date, falling back to the booking date.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.value_date.or(self.booking_date)
+    }
+}
@@ -0,0 +1,295 @@
+//! SWIFT MT940 statement parsing, including the German `:86:` structure.
+
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use super::{BankStatement, CreditDebit, StatementEntry};
+use crate::core::RechnungError;
+
+/// SEPA keywords in the `:86:` purpose text (DFÜ-Abkommen Anlage 3).
+const SEPA_KEYWORDS: &[&str] = &[
+    "EREF+", "KREF+", "MREF+", "CRED+", "DEBT+", "COAM+", "OAMT+", "SVWZ+", "ABWA+", "ABWE+",
+    "IBAN+", "BIC+",
+];
+
+/// Parse an MT940 file into its statements.
+///
+/// Accepts files with or without SWIFT block headers. Each `:61:` line
+/// becomes one entry; the following `:86:` field supplies counterparty and
+/// Verwendungszweck, using the German `?NN` subfields and SEPA keywords
+/// (`EREF+`, `SVWZ+`) where present.
+pub fn from_mt940(text: &str) -> Result<Vec<BankStatement>, RechnungError> {
+    let mut statements = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        let trimmed = line.trim();
+        if trimmed == "-" || trimmed == "-}" {
+            if !fields.is_empty() {
+                statements.push(parse_message(&fields)?);
+                fields.clear();
+            }
+            continue;
+        }
+        let line = line.rsplit("{4:").next().unwrap_or(line);
+        match split_tag(line) {
+            Some((tag, value)) => fields.push((tag.to_string(), value.to_string())),
+            None => {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+    if fields
+        .iter()
+        .any(|(tag, _)| tag == "61" || tag.starts_with("60"))
+    {
+        statements.push(parse_message(&fields)?);
+    }
+
+    if statements.is_empty() {
+        return Err(RechnungError::Builder("no MT940 statement found".into()));
+    }
+    Ok(statements)
+}
+
+/// Split `:TAG:value` into tag and value.
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let tag = &rest[..end];
+    let valid = tag.is_ascii()
+        && (2..=3).contains(&tag.len())
+        && tag[..2].chars().all(|c| c.is_ascii_digit())
+        && tag[2..].chars().all(|c| c.is_ascii_uppercase());
+    valid.then(|| (tag, &rest[end + 1..]))
+}
+
+fn parse_message(fields: &[(String, String)]) -> Result<BankStatement, RechnungError> {
+    let mut stmt = BankStatement::default();
+    let mut reference = String::new();
+
+    for (tag, value) in fields {
+        match tag.as_str() {
+            "20" => reference = value.trim().to_string(),
+            "28C" | "28" => stmt.id = value.trim().to_string(),
+            "25" => stmt.account = Some(value.trim().to_string()),
+            "60F" | "60M" => {
+                let (amount, currency) = parse_balance(value)?;
+                stmt.opening_balance = Some(amount);
+                stmt.currency = Some(currency);
+            }
+            "62F" | "62M" => stmt.closing_balance = Some(parse_balance(value)?.0),
+            "61" => {
+                let currency = stmt.currency.clone().unwrap_or_else(|| "EUR".into());
+                stmt.entries.push(parse_statement_line(value, currency)?);
+            }
+            "86" => {
+                if let Some(entry) = stmt.entries.last_mut() {
+                    apply_information(entry, value);
+                }
+            }
+            _ => {}
+        }
+    }
+    if stmt.id.is_empty() {
+        stmt.id = reference;
+    }
+    Ok(stmt)
+}
+
+fn parse_amount(text: &str) -> Result<Decimal, RechnungError> {
+    Decimal::from_str(&text.replace(',', "."))
+        .map_err(|e| RechnungError::Builder(format!("invalid MT940 amount '{text}': {e}")))
+}
+
+fn parse_yymmdd(text: &str) -> Result<NaiveDate, RechnungError> {
+    NaiveDate::parse_from_str(text, "%y%m%d")
+        .map_err(|e| RechnungError::Builder(format!("invalid MT940 date '{text}': {e}")))
+}
+
+/// Parse a balance field: `C240614EUR12345,67`. Debit balances are negative.
+fn parse_balance(value: &str) -> Result<(Decimal, String), RechnungError> {
+    let value = value.trim();
+    let invalid = || RechnungError::Builder(format!("invalid MT940 balance '{value}'"));
+    let sign = value.get(..1).ok_or_else(invalid)?;
+    let currency = value.get(7..10).ok_or_else(invalid)?.to_string();
+    let amount = parse_amount(value.get(10..).ok_or_else(invalid)?)?;
+    Ok((if sign == "D" { -amount } else { amount }, currency))
+}
+
+/// Parse a `:61:` statement line, e.g. `2406200620CR1785,00NTRFRE-2024-001//0815`.
+fn parse_statement_line(value: &str, currency: String) -> Result<StatementEntry, RechnungError> {
+    let invalid = || RechnungError::Builder(format!("invalid MT940 statement line '{value}'"));
+    let first = value.lines().next().unwrap_or("").trim();
+    let value_date = parse_yymmdd(first.get(..6).ok_or_else(invalid)?)?;
+    let mut rest = &first[6..];
+
+    let mut booking_date = None;
+    if rest
+        .get(..4)
+        .is_some_and(|d| d.chars().all(|c| c.is_ascii_digit()))
+    {
+        let month: u32 = rest[..2].parse().map_err(|_| invalid())?;
+        let day: u32 = rest[2..4].parse().map_err(|_| invalid())?;
+        let year = match (value_date.month(), month) {
+            (1, 12) => value_date.year() - 1,
+            (12, 1) => value_date.year() + 1,
+            _ => value_date.year(),
+        };
+        booking_date = NaiveDate::from_ymd_opt(year, month, day);
+        rest = &rest[4..];
+    }
+
+    let (direction, mark_len) = if rest.starts_with("RC") {
+        (CreditDebit::Debit, 2)
+    } else if rest.starts_with("RD") {
+        (CreditDebit::Credit, 2)
+    } else if rest.starts_with('C') {
+        (CreditDebit::Credit, 1)
+    } else if rest.starts_with('D') {
+        (CreditDebit::Debit, 1)
+    } else {
+        return Err(invalid());
+    };
+    rest = &rest[mark_len..];
+    // Optional funds code (third letter of the currency code)
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .unwrap_or(rest.len());
+    let amount = parse_amount(&rest[..amount_len])?;
+    rest = &rest[amount_len..];
+    // Transaction type identification code, e.g. NTRF
+    let rest = rest.get(4..).unwrap_or("");
+    let (customer_ref, bank_ref) = match rest.split_once("//") {
+        Some((c, b)) => (c, Some(b)),
+        None => (rest, None),
+    };
+
+    Ok(StatementEntry {
+        booking_date: booking_date.or(Some(value_date)),
+        value_date: Some(value_date),
+        amount,
+        currency,
+        direction,
+        counterparty_name: None,
+        counterparty_iban: None,
+        remittance_info: None,
+        creditor_reference: None,
+        end_to_end_id: (!customer_ref.is_empty() && customer_ref != "NONREF")
+            .then(|| customer_ref.to_string()),
+        bank_reference: bank_ref.filter(|b| !b.is_empty()).map(Into::into),
+    })
+}
+
+/// Fill counterparty and remittance from a `:86:` field.
+fn apply_information(entry: &mut StatementEntry, value: &str) {
+    let text: String = value.lines().collect();
+    let separator = text
+        .get(..3)
+        .filter(|code| code.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|_| text.get(3..4))
+        .filter(|sep| !sep.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    let purpose = if let Some(separator) = separator {
+        let mut purpose = String::new();
+        let mut name = String::new();
+        for sub in text[4..].split(separator) {
+            let (code, content) = (sub.get(..2).unwrap_or(""), sub.get(2..).unwrap_or(""));
+            match code {
+                "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60"
+                | "61" | "62" | "63" => purpose.push_str(content),
+                "31" if !content.is_empty() => entry.counterparty_iban = Some(content.into()),
+                "32" | "33" => name.push_str(content),
+                _ => {}
+            }
+        }
+        if !name.trim().is_empty() {
+            entry.counterparty_name = Some(name.trim().to_string());
+        }
+        purpose
+    } else {
+        text
+    };
+
+    let keywords = split_keywords(&purpose);
+    if keywords.is_empty() {
+        let purpose = purpose.trim();
+        if !purpose.is_empty() {
+            entry.remittance_info = Some(purpose.to_string());
+        }
+        return;
+    }
+    for (keyword, content) in keywords {
+        let content = content.trim();
+        match keyword {
+            "EREF+" if content != "NOTPROVIDED" => entry.end_to_end_id = Some(content.into()),
+            "SVWZ+" => entry.remittance_info = Some(content.into()),
+            "IBAN+" if entry.counterparty_iban.is_none() => {
+                entry.counterparty_iban = Some(content.into());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Split a purpose text at SEPA keywords into `(keyword, content)` pairs.
+fn split_keywords(purpose: &str) -> Vec<(&'static str, &str)> {
+    let mut found: Vec<(usize, &'static str)> = SEPA_KEYWORDS
+        .iter()
+        .filter_map(|k| purpose.find(k).map(|pos| (pos, *k)))
+        .collect();
+    found.sort();
+    found
+        .iter()
+        .enumerate()
+        .map(|(i, &(pos, keyword))| {
+            let end = found.get(i + 1).map_or(purpose.len(), |(next, _)| *next);
+            (keyword, &purpose[pos + keyword.len()..end])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn statement_line() {
+        let entry =
+            parse_statement_line("2406200620CR1785,00NTRFNONREF//0815", "EUR".into()).unwrap();
+        assert_eq!(entry.amount, dec!(1785.00));
+        assert_eq!(entry.direction, CreditDebit::Credit);
+        assert_eq!(entry.end_to_end_id, None);
+        assert_eq!(entry.bank_reference.as_deref(), Some("0815"));
+
+        let year_end = parse_statement_line("2501021231D12,5NMSCKREF", "EUR".into()).unwrap();
+        assert_eq!(year_end.booking_date, NaiveDate::from_ymd_opt(2024, 12, 31));
+        assert_eq!(year_end.direction, CreditDebit::Debit);
+        assert_eq!(year_end.amount, dec!(12.5));
+    }
+
+    #[test]
+    fn keywords() {
+        let parts = split_keywords("EREF+RE-1 SVWZ+Rechnung RE-1");
+        assert_eq!(parts, vec![("EREF+", "RE-1 "), ("SVWZ+", "Rechnung RE-1")]);
+        assert!(split_keywords("Rechnung RE-1").is_empty());
+    }
+
+    #[test]
+    fn non_ascii_pseudo_tag() {
+        assert_eq!(split_tag(":aä:foo"), None);
+        assert_eq!(split_tag(":ä:foo"), None);
+        // Not a tag, so the line continues the previous field instead of panicking.
+        assert!(from_mt940(":20:X\n:aä:foo\n-").is_ok());
+    }
+}
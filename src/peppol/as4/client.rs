@@ -0,0 +1,199 @@
+//! Sending AS4 user messages.
+
+use super::message::{
+    BODY_ID, MESSAGING_ID, Receipt, Signal, UserMessage, compress, parse_signal,
+    user_message_envelope, with_security,
+};
+use super::mime::{MimeMessage, Part, SOAP_CONTENT_TYPE};
+use super::security::{As4Identity, SignedReference, check_envelope_signed, encrypt, sign, verify};
+use super::{As4Error, parse_xml};
+use crate::peppol::sbdh::new_instance_identifier;
+use crate::peppol::smp::{CertificateInfo, Endpoint, StdHttpClient, TRANSPORT_AS4};
+
+/// An HTTP response to an AS4 request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportResponse {
+    /// Status code.
+    pub status: u16,
+    /// Value of the `Content-Type` header.
+    pub content_type: Option<String>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+/// Performs the HTTP POST of an AS4 message.
+pub trait As4Transport {
+    /// Post `body` to `url`.
+    fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<TransportResponse, As4Error>;
+}
+
+impl As4Transport for StdHttpClient {
+    fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<TransportResponse, As4Error> {
+        let response = self
+            .send(
+                "POST",
+                url,
+                &[("Content-Type", content_type), ("MIME-Version", "1.0")],
+                body,
+            )
+            .map_err(As4Error::Network)?;
+        Ok(TransportResponse {
+            status: response.status,
+            content_type: response.content_type,
+            body: response.body,
+        })
+    }
+}
+
+/// Sends AS4 user messages as one Access Point.
+#[derive(Debug, Clone)]
+pub struct As4Client<T> {
+    identity: As4Identity,
+    transport: T,
+}
+
+impl<T: As4Transport> As4Client<T> {
+    /// Client signing with `identity`.
+    pub fn new(identity: As4Identity, transport: T) -> Self {
+        Self {
+            identity,
+            transport,
+        }
+    }
+
+    /// The sending Access Point.
+    pub fn identity(&self) -> &As4Identity {
+        &self.identity
+    }
+
+    /// The transport messages are posted with.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Send to an endpoint found in SMP metadata and return the verified receipt.
+    pub fn send(&self, message: &UserMessage, endpoint: &Endpoint) -> Result<Receipt, As4Error> {
+        if endpoint.transport_profile != TRANSPORT_AS4 {
+            return Err(As4Error::Invalid(format!(
+                "endpoint transport profile '{}' is not {TRANSPORT_AS4}",
+                endpoint.transport_profile
+            )));
+        }
+        let certificate = endpoint
+            .certificate
+            .as_ref()
+            .ok_or_else(|| As4Error::Invalid("endpoint has no certificate".into()))?;
+        self.send_to(message, &endpoint.address, certificate)
+    }
+
+    /// Send to the Access Point at `url` holding `receiver` and return the
+    /// verified receipt.
+    pub fn send_to(
+        &self,
+        message: &UserMessage,
+        url: &str,
+        receiver: &CertificateInfo,
+    ) -> Result<Receipt, As4Error> {
+        let (request, sent) = self.build_signed(message, receiver)?;
+        let response = self
+            .transport
+            .post(url, &request.content_type, &request.body)?;
+        let response_message = MimeMessage {
+            content_type: response
+                .content_type
+                .unwrap_or_else(|| SOAP_CONTENT_TYPE.into()),
+            body: response.body,
+        };
+        let (envelope, attachments) = match response_message.parts() {
+            Ok(parts) => parts,
+            Err(_) if !(200..300).contains(&response.status) => {
+                return Err(As4Error::Network(format!(
+                    "{url}: HTTP {}",
+                    response.status
+                )));
+            }
+            Err(e) => return Err(e),
+        };
+        let document = parse_xml(&envelope)?;
+
+        let (receipt, acknowledged) = match parse_signal(&document)? {
+            Signal::Error { code, description } => {
+                return Err(As4Error::Rejected { code, description });
+            }
+            Signal::Receipt {
+                receipt,
+                references,
+            } => (receipt, references),
+        };
+        let verified = verify(&document, &attachments)?;
+        if verified.certificate.der != receiver.der {
+            return Err(As4Error::Security(format!(
+                "receipt is signed by '{}', not by the receiving Access Point",
+                verified.certificate.subject
+            )));
+        }
+        check_envelope_signed(&document, &verified)?;
+        if receipt.ref_to_message_id != message.message_id {
+            return Err(As4Error::Invalid(format!(
+                "receipt refers to message '{}', not '{}'",
+                receipt.ref_to_message_id, message.message_id
+            )));
+        }
+        if let Some(missing) = sent.iter().find(|r| !acknowledged.contains(r)) {
+            return Err(As4Error::Security(format!(
+                "receipt does not acknowledge {} with the signed digest",
+                missing.uri
+            )));
+        }
+        Ok(receipt)
+    }
+
+    /// Build the signed and encrypted message for `receiver` without sending it.
+    pub fn build(
+        &self,
+        message: &UserMessage,
+        receiver: &CertificateInfo,
+    ) -> Result<MimeMessage, As4Error> {
+        Ok(self.build_signed(message, receiver)?.0)
+    }
+
+    fn build_signed(
+        &self,
+        message: &UserMessage,
+        receiver: &CertificateInfo,
+    ) -> Result<(MimeMessage, Vec<SignedReference>), As4Error> {
+        let to_party = receiver
+            .common_name()
+            .ok_or_else(|| As4Error::Invalid("receiver certificate has no common name".into()))?;
+        let payload_id = format!("{}@faktura", new_instance_identifier());
+        let envelope =
+            user_message_envelope(message, self.identity.party_id(), to_party, &payload_id)?;
+        let compressed = compress(&message.payload)?;
+
+        let (signature, references) = sign(
+            &self.identity,
+            &parse_xml(&envelope)?,
+            &[BODY_ID, MESSAGING_ID],
+            &[(&payload_id, &compressed)],
+        )?;
+        let (encryption, ciphertext) =
+            encrypt(receiver, &payload_id, "application/gzip", &compressed)?;
+        let envelope = with_security(&envelope, &format!("{encryption}{signature}"));
+        let attachment = Part {
+            content_id: payload_id,
+            content_type: "application/octet-stream".into(),
+            body: ciphertext,
+        };
+        Ok((MimeMessage::multipart(envelope, &[attachment]), references))
+    }
+}
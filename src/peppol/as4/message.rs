@@ -0,0 +1,478 @@
+//! ebMS3 user and signal messages of the Peppol AS4 profile.
+
+use std::io::{Read, Write};
+
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use super::security::SignedReference;
+use super::{
+    AGREEMENT_REF, As4Error, NS_DS, NS_EBBP, NS_EBMS, NS_SOAP, NS_WSSE, NS_WSU, PARTY_ID_TYPE,
+    ROLE_INITIATOR, ROLE_RESPONDER,
+};
+use crate::core::Invoice;
+use crate::peppol::sbdh::{
+    self, DOCTYPE_SCHEME, PARTICIPANT_SCHEME, PROCESS_SCHEME, ParticipantId,
+    new_instance_identifier,
+};
+use crate::xrechnung::c14n::Element;
+use crate::xrechnung::xml_utils::XmlWriter;
+
+/// `wsu:Id` of the ebMS header.
+pub(crate) const MESSAGING_ID: &str = "_messaging";
+/// `wsu:Id` of the SOAP body.
+pub(crate) const BODY_ID: &str = "_body";
+
+/// Media type of the compressed payload.
+const GZIP: &str = "application/gzip";
+
+/// An AS4 user message carrying one SBDH document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserMessage {
+    /// Unique message ID, e.g. `…@faktura`.
+    pub message_id: String,
+    /// Conversation ID.
+    pub conversation_id: String,
+    /// Creation time.
+    pub timestamp: DateTime<Utc>,
+    /// Process identifier without scheme (ebMS `Service`).
+    pub service: String,
+    /// Scheme of the process identifier (ebMS `Service/@type`).
+    pub service_type: String,
+    /// Document type identifier with scheme (ebMS `Action`).
+    pub action: String,
+    /// Sending participant (C1).
+    pub original_sender: ParticipantId,
+    /// Receiving participant (C4).
+    pub final_recipient: ParticipantId,
+    /// The SBDH document, uncompressed.
+    pub payload: Vec<u8>,
+}
+
+impl UserMessage {
+    /// Message for an SBDH document; routing data is taken from the header.
+    pub fn from_sbdh(xml: &str) -> Result<Self, As4Error> {
+        let (header, _) = sbdh::parse_envelope(xml)?;
+        Ok(Self {
+            message_id: format!("{}@faktura", new_instance_identifier()),
+            conversation_id: new_instance_identifier(),
+            timestamp: Utc::now(),
+            service: header.process_id,
+            service_type: PROCESS_SCHEME.into(),
+            action: format!("{DOCTYPE_SCHEME}::{}", header.document_type_id),
+            original_sender: header.sender,
+            final_recipient: header.receiver,
+            payload: xml.as_bytes().to_vec(),
+        })
+    }
+
+    /// Message for an invoice, wrapped with [`sbdh::wrap`] using the
+    /// parties' electronic addresses.
+    pub fn for_invoice(invoice: &Invoice) -> Result<Self, As4Error> {
+        Self::from_sbdh(&sbdh::wrap(invoice, None, None)?)
+    }
+
+    /// Document type identifier without scheme.
+    pub fn document_type(&self) -> &str {
+        self.action
+            .strip_prefix(DOCTYPE_SCHEME)
+            .and_then(|rest| rest.strip_prefix("::"))
+            .unwrap_or(&self.action)
+    }
+
+    /// The payload as text.
+    pub fn payload_xml(&self) -> Result<&str, As4Error> {
+        std::str::from_utf8(&self.payload)
+            .map_err(|_| As4Error::Parse("payload is not UTF-8".into()))
+    }
+}
+
+/// A non-repudiation receipt for a delivered user message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    /// Message ID of the receipt.
+    pub message_id: String,
+    /// Message ID of the acknowledged user message.
+    pub ref_to_message_id: String,
+    /// Time the receipt was created.
+    pub timestamp: DateTime<Utc>,
+    /// URIs of the acknowledged message parts.
+    pub references: Vec<String>,
+}
+
+/// A parsed signal message.
+#[derive(Debug, Clone)]
+pub(crate) enum Signal {
+    Receipt {
+        receipt: Receipt,
+        references: Vec<SignedReference>,
+    },
+    Error {
+        code: String,
+        description: String,
+    },
+}
+
+/// A parsed user message header.
+#[derive(Debug, Clone)]
+pub(crate) struct UserMessageHeader {
+    pub(crate) message: UserMessage,
+    pub(crate) from_party: String,
+    pub(crate) to_party: String,
+    /// Content IDs and compression of the payload parts.
+    pub(crate) parts: Vec<(String, bool)>,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Start the envelope up to the opening `eb:Messaging`.
+fn open_envelope() -> Result<XmlWriter, As4Error> {
+    let mut w = XmlWriter::new()?;
+    w.start_element_with_attrs(
+        "env:Envelope",
+        &[
+            ("xmlns:env", NS_SOAP),
+            ("xmlns:eb", NS_EBMS),
+            ("xmlns:wsse", NS_WSSE),
+            ("xmlns:wsu", NS_WSU),
+        ],
+    )?;
+    w.start_element("env:Header")?;
+    w.start_element_with_attrs(
+        "eb:Messaging",
+        &[("env:mustUnderstand", "true"), ("wsu:Id", MESSAGING_ID)],
+    )?;
+    Ok(w)
+}
+
+/// Close `eb:Messaging` and add an empty security header and body.
+fn close_envelope(mut w: XmlWriter) -> Result<String, As4Error> {
+    w.end_element("eb:Messaging")?;
+    w.start_element_with_attrs("wsse:Security", &[("env:mustUnderstand", "true")])?;
+    w.end_element("wsse:Security")?;
+    w.end_element("env:Header")?;
+    w.start_element_with_attrs("env:Body", &[("wsu:Id", BODY_ID)])?;
+    w.end_element("env:Body")?;
+    w.end_element("env:Envelope")?;
+    Ok(w.into_string()?)
+}
+
+fn message_info(
+    w: &mut XmlWriter,
+    message_id: &str,
+    ref_to_message_id: Option<&str>,
+    at: DateTime<Utc>,
+) -> Result<(), As4Error> {
+    w.start_element("eb:MessageInfo")?;
+    w.text_element("eb:Timestamp", &timestamp(at))?;
+    w.text_element("eb:MessageId", message_id)?;
+    if let Some(reference) = ref_to_message_id {
+        w.text_element("eb:RefToMessageId", reference)?;
+    }
+    w.end_element("eb:MessageInfo")?;
+    Ok(())
+}
+
+/// Envelope of a user message with an empty `wsse:Security` header.
+pub(crate) fn user_message_envelope(
+    message: &UserMessage,
+    from_party: &str,
+    to_party: &str,
+    payload_id: &str,
+) -> Result<String, As4Error> {
+    let mut w = open_envelope()?;
+    w.start_element("eb:UserMessage")?;
+    message_info(&mut w, &message.message_id, None, message.timestamp)?;
+
+    w.start_element("eb:PartyInfo")?;
+    for (element, party, role) in [
+        ("eb:From", from_party, ROLE_INITIATOR),
+        ("eb:To", to_party, ROLE_RESPONDER),
+    ] {
+        w.start_element(element)?;
+        w.text_element_with_attrs("eb:PartyId", party, &[("type", PARTY_ID_TYPE)])?;
+        w.text_element("eb:Role", role)?;
+        w.end_element(element)?;
+    }
+    w.end_element("eb:PartyInfo")?;
+
+    w.start_element("eb:CollaborationInfo")?;
+    w.text_element("eb:AgreementRef", AGREEMENT_REF)?;
+    w.text_element_with_attrs(
+        "eb:Service",
+        &message.service,
+        &[("type", &message.service_type)],
+    )?;
+    w.text_element("eb:Action", &message.action)?;
+    w.text_element("eb:ConversationId", &message.conversation_id)?;
+    w.end_element("eb:CollaborationInfo")?;
+
+    w.start_element("eb:MessageProperties")?;
+    for (name, participant) in [
+        ("originalSender", &message.original_sender),
+        ("finalRecipient", &message.final_recipient),
+    ] {
+        w.text_element_with_attrs(
+            "eb:Property",
+            &participant.value,
+            &[("name", name), ("type", &participant.scheme)],
+        )?;
+    }
+    w.end_element("eb:MessageProperties")?;
+
+    w.start_element("eb:PayloadInfo")?;
+    w.start_element_with_attrs("eb:PartInfo", &[("href", &format!("cid:{payload_id}"))])?;
+    w.start_element("eb:PartProperties")?;
+    w.text_element_with_attrs("eb:Property", GZIP, &[("name", "CompressionType")])?;
+    w.text_element_with_attrs("eb:Property", "application/xml", &[("name", "MimeType")])?;
+    w.end_element("eb:PartProperties")?;
+    w.end_element("eb:PartInfo")?;
+    w.end_element("eb:PayloadInfo")?;
+
+    w.end_element("eb:UserMessage")?;
+    close_envelope(w)
+}
+
+/// Envelope of a receipt quoting the signed references of the user message.
+pub(crate) fn receipt_envelope(
+    ref_to_message_id: &str,
+    reference_elements: &[String],
+) -> Result<String, As4Error> {
+    let mut w = open_envelope()?;
+    w.start_element("eb:SignalMessage")?;
+    let message_id = format!("{}@faktura", new_instance_identifier());
+    message_info(&mut w, &message_id, Some(ref_to_message_id), Utc::now())?;
+    w.start_element("eb:Receipt")?;
+    w.start_element_with_attrs("ebbp:NonRepudiationInformation", &[("xmlns:ebbp", NS_EBBP)])?;
+    w.end_element("ebbp:NonRepudiationInformation")?;
+    w.end_element("eb:Receipt")?;
+    w.end_element("eb:SignalMessage")?;
+    let envelope = close_envelope(w)?;
+
+    let parts: String = reference_elements
+        .iter()
+        .map(|r| format!("<ebbp:MessagePartNRInformation>{r}</ebbp:MessagePartNRInformation>"))
+        .collect();
+    Ok(envelope.replacen(
+        "</ebbp:NonRepudiationInformation>",
+        &format!("{parts}</ebbp:NonRepudiationInformation>"),
+        1,
+    ))
+}
+
+/// Envelope of an ebMS error signal.
+pub(crate) fn error_envelope(
+    ref_to_message_id: Option<&str>,
+    code: &str,
+    description: &str,
+) -> Result<String, As4Error> {
+    let mut w = open_envelope()?;
+    w.start_element("eb:SignalMessage")?;
+    let message_id = format!("{}@faktura", new_instance_identifier());
+    message_info(&mut w, &message_id, ref_to_message_id, Utc::now())?;
+    let (short, category) = error_kind(code);
+    let mut attrs = vec![
+        ("category", category),
+        ("errorCode", code),
+        ("origin", "ebMS"),
+        ("severity", "failure"),
+        ("shortDescription", short),
+    ];
+    if let Some(reference) = ref_to_message_id {
+        attrs.push(("refToMessageInError", reference));
+    }
+    w.start_element_with_attrs("eb:Error", &attrs)?;
+    w.text_element_with_attrs("eb:Description", description, &[("xml:lang", "en")])?;
+    w.end_element("eb:Error")?;
+    w.end_element("eb:SignalMessage")?;
+    close_envelope(w)
+}
+
+/// Short description and category of an ebMS error code.
+fn error_kind(code: &str) -> (&'static str, &'static str) {
+    match code {
+        "EBMS:0001" => ("ValueNotRecognized", "Content"),
+        "EBMS:0003" => ("ValueInconsistent", "Content"),
+        "EBMS:0009" => ("InvalidHeader", "Content"),
+        "EBMS:0101" => ("FailedAuthentication", "Processing"),
+        "EBMS:0102" => ("FailedDecryption", "Processing"),
+        "EBMS:0103" => ("PolicyNoncompliance", "Processing"),
+        "EBMS:0303" => ("DecompressionFailure", "Communication"),
+        _ => ("Other", "Content"),
+    }
+}
+
+/// Insert the `wsse:Security` content into an envelope.
+pub(crate) fn with_security(envelope: &str, security: &str) -> String {
+    envelope.replacen(
+        "</wsse:Security>",
+        &format!("{security}</wsse:Security>"),
+        1,
+    )
+}
+
+/// The `eb:Messaging` header of an envelope.
+fn messaging(document: &Element) -> Result<&Element, As4Error> {
+    if !document.is(NS_SOAP, "Envelope") {
+        return Err(As4Error::Parse("not a SOAP 1.2 envelope".into()));
+    }
+    document
+        .child(NS_SOAP, "Header")
+        .and_then(|h| h.child(NS_EBMS, "Messaging"))
+        .ok_or_else(|| As4Error::Parse("SOAP envelope without ebMS header".into()))
+}
+
+fn required(element: &Element, local: &str) -> Result<String, As4Error> {
+    element
+        .find_text(NS_EBMS, local)
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| As4Error::Invalid(format!("ebMS header without {local}")))
+}
+
+fn parse_timestamp(element: &Element) -> Result<DateTime<Utc>, As4Error> {
+    let text = required(element, "Timestamp")?;
+    DateTime::parse_from_rfc3339(&text)
+        .map(|t| t.to_utc())
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|t| t.and_utc())
+        })
+        .map_err(|_| As4Error::Invalid(format!("invalid ebMS timestamp '{text}'")))
+}
+
+/// Parse the user message header of an envelope.
+pub(crate) fn parse_user_message(document: &Element) -> Result<UserMessageHeader, As4Error> {
+    let user = messaging(document)?
+        .child(NS_EBMS, "UserMessage")
+        .ok_or_else(|| As4Error::Invalid("ebMS header without UserMessage".into()))?;
+    let info = user
+        .child(NS_EBMS, "MessageInfo")
+        .ok_or_else(|| As4Error::Invalid("ebMS header without MessageInfo".into()))?;
+    let party = |element: &str| -> Result<String, As4Error> {
+        user.child(NS_EBMS, "PartyInfo")
+            .and_then(|p| p.child(NS_EBMS, element))
+            .and_then(|p| p.find_text(NS_EBMS, "PartyId"))
+            .ok_or_else(|| As4Error::Invalid(format!("ebMS header without {element} party")))
+    };
+    let collaboration = user
+        .child(NS_EBMS, "CollaborationInfo")
+        .ok_or_else(|| As4Error::Invalid("ebMS header without CollaborationInfo".into()))?;
+    let service = collaboration
+        .child(NS_EBMS, "Service")
+        .ok_or_else(|| As4Error::Invalid("ebMS header without Service".into()))?;
+
+    let properties: Vec<&Element> = user
+        .child(NS_EBMS, "MessageProperties")
+        .map(|p| p.find_all(NS_EBMS, "Property"))
+        .unwrap_or_default();
+    let participant = |name: &str| -> Result<ParticipantId, As4Error> {
+        properties
+            .iter()
+            .find(|p| p.attribute("name") == Some(name))
+            .map(|p| ParticipantId {
+                scheme: p
+                    .attribute("type")
+                    .unwrap_or(PARTICIPANT_SCHEME)
+                    .to_string(),
+                value: p.text(),
+            })
+            .ok_or_else(|| As4Error::Invalid(format!("ebMS header without {name} property")))
+    };
+
+    let parts = user
+        .find_all(NS_EBMS, "PartInfo")
+        .into_iter()
+        .map(|part| {
+            let href = part.attribute("href").unwrap_or_default();
+            let content_id = href.strip_prefix("cid:").ok_or_else(|| {
+                As4Error::Invalid(format!("unsupported payload reference '{href}'"))
+            })?;
+            let compressed = part
+                .find_all(NS_EBMS, "Property")
+                .iter()
+                .any(|p| p.attribute("name") == Some("CompressionType") && p.text() == GZIP);
+            Ok((content_id.to_string(), compressed))
+        })
+        .collect::<Result<Vec<_>, As4Error>>()?;
+
+    Ok(UserMessageHeader {
+        message: UserMessage {
+            message_id: required(info, "MessageId")?,
+            conversation_id: collaboration
+                .find_text(NS_EBMS, "ConversationId")
+                .unwrap_or_default(),
+            timestamp: parse_timestamp(info)?,
+            service: service.text(),
+            service_type: service.attribute("type").unwrap_or_default().to_string(),
+            action: required(collaboration, "Action")?,
+            original_sender: participant("originalSender")?,
+            final_recipient: participant("finalRecipient")?,
+            payload: Vec::new(),
+        },
+        from_party: party("From")?,
+        to_party: party("To")?,
+        parts,
+    })
+}
+
+/// Parse the signal message of an envelope.
+pub(crate) fn parse_signal(document: &Element) -> Result<Signal, As4Error> {
+    let signal = messaging(document)?
+        .child(NS_EBMS, "SignalMessage")
+        .ok_or_else(|| As4Error::Parse("response is not an ebMS signal message".into()))?;
+    if let Some(error) = signal.child(NS_EBMS, "Error") {
+        return Ok(Signal::Error {
+            code: error.attribute("errorCode").unwrap_or_default().to_string(),
+            description: error
+                .find_text(NS_EBMS, "Description")
+                .filter(|d| !d.is_empty())
+                .or_else(|| error.attribute("shortDescription").map(str::to_string))
+                .unwrap_or_default(),
+        });
+    }
+    let receipt = signal
+        .child(NS_EBMS, "Receipt")
+        .ok_or_else(|| As4Error::Parse("signal message without Receipt or Error".into()))?;
+    let info = signal
+        .child(NS_EBMS, "MessageInfo")
+        .ok_or_else(|| As4Error::Invalid("ebMS header without MessageInfo".into()))?;
+    let references: Vec<SignedReference> = receipt
+        .find_all(NS_DS, "Reference")
+        .into_iter()
+        .map(|r| SignedReference {
+            uri: r.attribute("URI").unwrap_or_default().to_string(),
+            digest: r.find_text(NS_DS, "DigestValue").unwrap_or_default(),
+        })
+        .collect();
+    Ok(Signal::Receipt {
+        receipt: Receipt {
+            message_id: required(info, "MessageId")?,
+            ref_to_message_id: required(info, "RefToMessageId")?,
+            timestamp: parse_timestamp(info)?,
+            references: references.iter().map(|r| r.uri.clone()).collect(),
+        },
+        references,
+    })
+}
+
+/// Gzip-compress a payload.
+pub(crate) fn compress(payload: &[u8]) -> Result<Vec<u8>, As4Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(payload)
+        .and_then(|()| encoder.finish())
+        .map_err(|e| As4Error::Invalid(format!("compression failed: {e}")))
+}
+
+/// Decompress a gzip payload.
+pub(crate) fn decompress(compressed: &[u8]) -> Result<Vec<u8>, As4Error> {
+    let mut payload = Vec::new();
+    GzDecoder::new(compressed)
+        .read_to_end(&mut payload)
+        .map_err(|e| As4Error::Parse(format!("payload decompression failed: {e}")))?;
+    Ok(payload)
+}
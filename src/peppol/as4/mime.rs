@@ -0,0 +1,218 @@
+//! MIME `multipart/related` packaging of AS4 messages (SOAP with Attachments).
+
+use super::As4Error;
+use crate::peppol::sbdh::new_instance_identifier;
+
+/// Content type of a SOAP 1.2 envelope.
+pub(crate) const SOAP_CONTENT_TYPE: &str = "application/soap+xml";
+
+/// An AS4 message as sent over HTTP: a content type and a body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeMessage {
+    /// Value of the HTTP `Content-Type` header.
+    pub content_type: String,
+    /// HTTP body.
+    pub body: Vec<u8>,
+}
+
+/// A MIME part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Part {
+    /// Content ID without angle brackets.
+    pub(crate) content_id: String,
+    pub(crate) content_type: String,
+    pub(crate) body: Vec<u8>,
+}
+
+impl MimeMessage {
+    /// A bare SOAP envelope (used for signal messages).
+    pub(crate) fn soap(envelope: String) -> Self {
+        Self {
+            content_type: format!("{SOAP_CONTENT_TYPE}; charset=UTF-8"),
+            body: envelope.into_bytes(),
+        }
+    }
+
+    /// A SOAP envelope with attachments.
+    pub(crate) fn multipart(envelope: String, attachments: &[Part]) -> Self {
+        let boundary = format!(
+            "MIMEBoundary_{}",
+            new_instance_identifier().replace('-', "")
+        );
+        let root_id = "root.message@faktura";
+        let mut body = Vec::new();
+        let mut part = |content_id: &str, content_type: &str, content: &[u8]| {
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Type: {content_type}\r\n\
+                     Content-Transfer-Encoding: binary\r\nContent-ID: <{content_id}>\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n");
+        };
+        part(
+            root_id,
+            &format!("{SOAP_CONTENT_TYPE}; charset=UTF-8"),
+            envelope.as_bytes(),
+        );
+        for attachment in attachments {
+            part(
+                &attachment.content_id,
+                &attachment.content_type,
+                &attachment.body,
+            );
+        }
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+        Self {
+            content_type: format!(
+                "multipart/related; boundary=\"{boundary}\"; type=\"{SOAP_CONTENT_TYPE}\"; \
+                 start=\"<{root_id}>\""
+            ),
+            body,
+        }
+    }
+
+    /// Split into the SOAP envelope and the attachments.
+    pub(crate) fn parts(&self) -> Result<(String, Vec<Part>), As4Error> {
+        let media_type = self
+            .content_type
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        if media_type == SOAP_CONTENT_TYPE {
+            let envelope = String::from_utf8(self.body.clone())
+                .map_err(|_| As4Error::Parse("SOAP envelope is not UTF-8".into()))?;
+            return Ok((envelope, Vec::new()));
+        }
+        if media_type != "multipart/related" {
+            return Err(As4Error::Parse(format!(
+                "unsupported content type '{}'",
+                self.content_type
+            )));
+        }
+        let boundary = parameter(&self.content_type, "boundary")
+            .ok_or_else(|| As4Error::Parse("multipart message without boundary".into()))?;
+        let start = parameter(&self.content_type, "start")
+            .map(|s| s.trim_matches(|c| c == '<' || c == '>').to_string());
+
+        let mut parts = split_parts(&self.body, &boundary)?;
+        let root = match start {
+            Some(start) => parts.iter().position(|p| p.content_id == start),
+            None => (!parts.is_empty()).then_some(0),
+        }
+        .ok_or_else(|| As4Error::Parse("multipart message without SOAP part".into()))?;
+        let root = parts.remove(root);
+        let envelope = String::from_utf8(root.body)
+            .map_err(|_| As4Error::Parse("SOAP envelope is not UTF-8".into()))?;
+        Ok((envelope, parts))
+    }
+}
+
+/// Value of a `; name=value` content-type parameter, unquoted.
+fn parameter(content_type: &str, name: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|p| {
+        let (key, value) = p.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn split_parts(body: &[u8], boundary: &str) -> Result<Vec<Part>, As4Error> {
+    let delimiter = format!("--{boundary}").into_bytes();
+    let bad = |what: &str| As4Error::Parse(format!("malformed multipart message: {what}"));
+    let positions: Vec<usize> = body
+        .windows(delimiter.len())
+        .enumerate()
+        .filter(|(i, w)| *w == delimiter.as_slice() && (*i == 0 || body[i - 1] == b'\n'))
+        .map(|(i, _)| i)
+        .collect();
+    if positions.len() < 2 {
+        return Err(bad("missing boundary"));
+    }
+
+    let mut parts = Vec::new();
+    for pair in positions.windows(2) {
+        let mut raw = &body[pair[0] + delimiter.len()..pair[1]];
+        raw = raw
+            .strip_prefix(b"\r\n")
+            .or(raw.strip_prefix(b"\n"))
+            .unwrap_or(raw);
+        // The line break before the next delimiter belongs to the delimiter
+        raw = raw
+            .strip_suffix(b"\r\n")
+            .or(raw.strip_suffix(b"\n"))
+            .unwrap_or(raw);
+        let (head_end, body_start) = match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(i) => (i, i + 4),
+            None => {
+                let i = raw
+                    .windows(2)
+                    .position(|w| w == b"\n\n")
+                    .ok_or_else(|| bad("part without headers"))?;
+                (i, i + 2)
+            }
+        };
+        let head = String::from_utf8_lossy(&raw[..head_end]);
+        let mut part = Part {
+            content_id: String::new(),
+            content_type: "application/octet-stream".into(),
+            body: raw[body_start..].to_vec(),
+        };
+        for (name, value) in head.lines().filter_map(|l| l.split_once(':')) {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-id" => {
+                    part.content_id = value.trim_matches(|c| c == '<' || c == '>').to_string();
+                }
+                "content-type" => part.content_type = value.to_string(),
+                "content-transfer-encoding"
+                    if !matches!(
+                        value.to_ascii_lowercase().as_str(),
+                        "binary" | "8bit" | "7bit"
+                    ) =>
+                {
+                    return Err(bad(&format!("unsupported transfer encoding '{value}'")));
+                }
+                _ => {}
+            }
+        }
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_roundtrip() {
+        let attachment = Part {
+            content_id: "payload@faktura".into(),
+            content_type: "application/octet-stream".into(),
+            body: vec![0, 13, 10, 45, 45, 255],
+        };
+        let message = MimeMessage::multipart("<env/>".into(), std::slice::from_ref(&attachment));
+        assert!(message.content_type.starts_with("multipart/related;"));
+        let (envelope, parts) = message.parts().unwrap();
+        assert_eq!(envelope, "<env/>");
+        assert_eq!(parts, vec![attachment]);
+    }
+
+    #[test]
+    fn bare_soap() {
+        let (envelope, parts) = MimeMessage::soap("<env/>".into()).parts().unwrap();
+        assert_eq!(envelope, "<env/>");
+        assert!(parts.is_empty());
+        let other = MimeMessage {
+            content_type: "text/plain".into(),
+            body: Vec::new(),
+        };
+        assert!(other.parts().is_err());
+    }
+}
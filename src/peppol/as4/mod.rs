@@ -0,0 +1,136 @@
+//! Peppol AS4 message exchange (CEF eDelivery AS4 profile, Peppol AS4 2.0).
+//!
+//! An SBDH document travels as the gzip-compressed attachment of an ebMS3
+//! user message. The sending Access Point signs the SOAP body, the ebMS
+//! header and the attachment (RSA-SHA256, exclusive canonicalization),
+//! encrypts the attachment for the receiving Access Point (AES-128-GCM, key
+//! wrapped with RSA-OAEP) and expects a signed non-repudiation receipt back.
+//!
+//! - [`As4Client`] builds and sends user messages and verifies the receipt.
+//! - [`As4Receiver`] decrypts and verifies incoming messages and answers with
+//!   a signed receipt or an ebMS error.
+//! - HTTP sits behind [`As4Transport`], so a local stand-in can be used in
+//!   tests; [`StdHttpClient`] implements it for the `https://` endpoints of
+//!   Peppol Access Points.
+//!
+//! Certificates are checked against the expected peer certificate, but
+//! certificate chains and revocation are not verified.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::peppol::as4::{As4Client, As4Identity, UserMessage};
+//! use faktura::peppol::smp::{SmpClient, StdHttpClient, TRANSPORT_AS4};
+//!
+//! let identity = As4Identity::from_pem(&key_pem, &certificate_pem)?;
+//! let client = As4Client::new(identity, StdHttpClient::new());
+//! let message = UserMessage::for_invoice(&invoice)?;
+//! let endpoint = smp_info
+//!     .endpoint(&message.document_type(), &message.service, TRANSPORT_AS4)
+//!     .unwrap();
+//! let receipt = client.send(&message, endpoint)?;
+//! ```
+//!
+//! [`StdHttpClient`]: crate::peppol::smp::StdHttpClient
+
+mod client;
+mod message;
+mod mime;
+mod receiver;
+mod security;
+
+pub use client::{As4Client, As4Transport, TransportResponse};
+pub use message::{Receipt, UserMessage};
+pub use mime::MimeMessage;
+pub use receiver::{As4Receiver, ReceivedMessage, Reception};
+pub use security::As4Identity;
+
+use std::fmt;
+
+use crate::core::RechnungError;
+use crate::xrechnung::c14n::{self, Element};
+
+/// SOAP 1.2 envelope namespace.
+pub(crate) const NS_SOAP: &str = "http://www.w3.org/2003/05/soap-envelope";
+/// ebMS 3.0 core namespace.
+pub(crate) const NS_EBMS: &str = "http://docs.oasis-open.org/ebxml-msg/ebms/v3.0/ns/core/200704/";
+/// ebBP signals namespace (non-repudiation information).
+pub(crate) const NS_EBBP: &str = "http://docs.oasis-open.org/ebxml-bp/ebbp-signals-2.0";
+/// WS-Security 1.0 extension namespace.
+pub(crate) const NS_WSSE: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd";
+/// WS-Security 1.1 extension namespace.
+pub(crate) const NS_WSSE11: &str =
+    "http://docs.oasis-open.org/wss/oasis-wss-wssecurity-secext-1.1.xsd";
+/// WS-Security utility namespace.
+pub(crate) const NS_WSU: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd";
+/// XML Signature namespace.
+pub(crate) const NS_DS: &str = "http://www.w3.org/2000/09/xmldsig#";
+/// XML Encryption namespace.
+pub(crate) const NS_XENC: &str = "http://www.w3.org/2001/04/xmlenc#";
+/// XML Encryption 1.1 namespace.
+pub(crate) const NS_XENC11: &str = "http://www.w3.org/2009/xmlenc11#";
+
+/// Party ID type of Peppol Access Points.
+pub(crate) const PARTY_ID_TYPE: &str = "urn:fdc:peppol.eu:2017:identifiers:ap";
+/// Agreement reference of the Peppol Transport Infrastructure Agreement.
+pub(crate) const AGREEMENT_REF: &str = "urn:fdc:peppol.eu:2017:agreements:tia:ap_provider";
+/// Role of the sending party.
+pub(crate) const ROLE_INITIATOR: &str =
+    "http://docs.oasis-open.org/ebxml-msg/ebms/v3.0/ns/core/200704/initiator";
+/// Role of the receiving party.
+pub(crate) const ROLE_RESPONDER: &str =
+    "http://docs.oasis-open.org/ebxml-msg/ebms/v3.0/ns/core/200704/responder";
+
+/// Error from building, sending or receiving an AS4 message.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum As4Error {
+    /// Network or HTTP error.
+    Network(String),
+    /// Signature, encryption or certificate check failed.
+    Security(String),
+    /// Malformed message.
+    Parse(String),
+    /// The message does not meet the Peppol AS4 profile.
+    Invalid(String),
+    /// The receiving Access Point answered with an ebMS error.
+    Rejected {
+        /// ebMS error code, e.g. `EBMS:0101`.
+        code: String,
+        /// Description given by the receiver.
+        description: String,
+    },
+}
+
+impl fmt::Display for As4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(e) => write!(f, "AS4 network error: {e}"),
+            Self::Security(e) => write!(f, "AS4 security error: {e}"),
+            Self::Parse(e) => write!(f, "AS4 parse error: {e}"),
+            Self::Invalid(e) => write!(f, "invalid AS4 message: {e}"),
+            Self::Rejected { code, description } => {
+                write!(f, "AS4 message rejected ({code}): {description}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for As4Error {}
+
+impl From<RechnungError> for As4Error {
+    fn from(e: RechnungError) -> Self {
+        Self::Invalid(e.to_string())
+    }
+}
+
+/// Parse an XML document into a namespace-aware tree, reporting malformed
+/// XML as [`As4Error::Parse`].
+pub(crate) fn parse_xml(xml: &str) -> Result<Element, As4Error> {
+    c14n::parse(xml).map_err(|e| match e {
+        RechnungError::Xml(message) => As4Error::Parse(message),
+        other => As4Error::Parse(other.to_string()),
+    })
+}
@@ -0,0 +1,197 @@
+//! Receiving AS4 user messages.
+
+use super::message::{
+    BODY_ID, MESSAGING_ID, UserMessage, decompress, error_envelope, parse_user_message,
+    receipt_envelope, with_security,
+};
+use super::mime::MimeMessage;
+use super::security::{As4Identity, check_envelope_signed, decrypt, sign, verify};
+use super::{As4Error, parse_xml};
+use crate::core::Invoice;
+use crate::peppol::sbdh::{self, Sbdh};
+use crate::peppol::smp::CertificateInfo;
+
+/// A user message that passed decryption and signature checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedMessage {
+    /// The message, with the decompressed payload.
+    pub message: UserMessage,
+    /// Party ID of the sending Access Point.
+    pub from_party: String,
+    /// Party ID of the receiving Access Point.
+    pub to_party: String,
+    /// Certificate the message was signed with.
+    pub sender_certificate: CertificateInfo,
+}
+
+impl ReceivedMessage {
+    /// Unwrap the SBDH payload into its header and invoice.
+    pub fn document(&self) -> Result<(Sbdh, Invoice), As4Error> {
+        Ok(sbdh::unwrap(self.message.payload_xml()?)?)
+    }
+}
+
+/// Outcome of handling one incoming request.
+#[derive(Debug, Clone)]
+pub struct Reception {
+    /// The received message, or why it was refused.
+    pub message: Result<ReceivedMessage, As4Error>,
+    /// HTTP status to answer with.
+    pub status: u16,
+    /// Signed receipt or ebMS error to answer with.
+    pub response: MimeMessage,
+}
+
+/// An ebMS error code with the underlying error.
+struct Fault {
+    code: &'static str,
+    error: As4Error,
+    ref_to_message_id: Option<String>,
+}
+
+/// Handles incoming AS4 requests for one Access Point.
+///
+/// The sender's certificate is checked against the message signature only;
+/// whether it belongs to a trusted Access Point is up to the caller
+/// ([`ReceivedMessage::sender_certificate`]).
+#[derive(Debug, Clone)]
+pub struct As4Receiver {
+    identity: As4Identity,
+}
+
+impl As4Receiver {
+    /// Receiver decrypting and signing receipts with `identity`.
+    pub fn new(identity: As4Identity) -> Self {
+        Self { identity }
+    }
+
+    /// Handle an HTTP POST with the given `Content-Type` and body.
+    pub fn handle(&self, content_type: &str, body: &[u8]) -> Reception {
+        let request = MimeMessage {
+            content_type: content_type.to_string(),
+            body: body.to_vec(),
+        };
+        let result = self.receive(&request).and_then(|(received, references)| {
+            let envelope = receipt_envelope(&received.message.message_id, &references)
+                .and_then(|e| self.signed(&e))
+                .map_err(|error| Fault {
+                    code: "EBMS:0004",
+                    error,
+                    ref_to_message_id: Some(received.message.message_id.clone()),
+                })?;
+            Ok((received, envelope))
+        });
+        match result {
+            Ok((received, envelope)) => Reception {
+                message: Ok(received),
+                status: 200,
+                response: MimeMessage::soap(envelope),
+            },
+            Err(fault) => {
+                let description = fault.error.to_string();
+                let envelope = match error_envelope(
+                    fault.ref_to_message_id.as_deref(),
+                    fault.code,
+                    &description,
+                ) {
+                    Ok(envelope) => self.signed(&envelope).unwrap_or(envelope),
+                    Err(_) => String::new(),
+                };
+                Reception {
+                    message: Err(fault.error),
+                    status: 500,
+                    response: MimeMessage::soap(envelope),
+                }
+            }
+        }
+    }
+
+    fn signed(&self, envelope: &str) -> Result<String, As4Error> {
+        let (signature, _) = sign(
+            &self.identity,
+            &parse_xml(envelope)?,
+            &[BODY_ID, MESSAGING_ID],
+            &[],
+        )?;
+        Ok(with_security(envelope, &signature))
+    }
+
+    /// Decrypt and verify a request; returns the message and the canonical
+    /// signed references for the receipt.
+    fn receive(&self, request: &MimeMessage) -> Result<(ReceivedMessage, Vec<String>), Fault> {
+        let fault = |code, ref_to: Option<&str>| {
+            let ref_to_message_id = ref_to.map(str::to_string);
+            move |error| Fault {
+                code,
+                error,
+                ref_to_message_id,
+            }
+        };
+        let (envelope, mut attachments) = request.parts().map_err(fault("EBMS:0004", None))?;
+        let document = parse_xml(&envelope).map_err(fault("EBMS:0004", None))?;
+        let header = parse_user_message(&document).map_err(fault("EBMS:0009", None))?;
+        let id = Some(header.message.message_id.as_str());
+
+        if header.to_party != self.identity.party_id() {
+            return Err(fault("EBMS:0003", id)(As4Error::Invalid(format!(
+                "message is addressed to Access Point '{}'",
+                header.to_party
+            ))));
+        }
+        let [(payload_id, compressed)] = header.parts.as_slice() else {
+            return Err(fault("EBMS:0003", id)(As4Error::Invalid(format!(
+                "expected exactly one payload, found {}",
+                header.parts.len()
+            ))));
+        };
+
+        let decrypted =
+            decrypt(&self.identity, &document, &mut attachments).map_err(fault("EBMS:0102", id))?;
+        if !decrypted.contains(payload_id) {
+            return Err(fault("EBMS:0103", id)(As4Error::Security(
+                "payload is not encrypted".into(),
+            )));
+        }
+        let verified = verify(&document, &attachments).map_err(fault("EBMS:0101", id))?;
+        // The signed elements must be the body and header read above, not
+        // copies with the same Id elsewhere in the envelope
+        check_envelope_signed(&document, &verified).map_err(fault("EBMS:0101", id))?;
+        if !verified.covers_attachment(payload_id) {
+            return Err(fault("EBMS:0101", id)(As4Error::Security(format!(
+                "signature does not cover cid:{payload_id}"
+            ))));
+        }
+        if verified.certificate.common_name() != Some(header.from_party.as_str()) {
+            return Err(fault("EBMS:0101", id)(As4Error::Security(format!(
+                "sending party '{}' does not match the signing certificate '{}'",
+                header.from_party, verified.certificate.subject
+            ))));
+        }
+
+        let part = attachments
+            .iter()
+            .find(|p| &p.content_id == payload_id)
+            .ok_or_else(|| {
+                fault("EBMS:0004", id)(As4Error::Invalid(format!(
+                    "payload 'cid:{payload_id}' is missing"
+                )))
+            })?;
+        let payload = if *compressed {
+            decompress(&part.body).map_err(fault("EBMS:0303", id))?
+        } else {
+            part.body.clone()
+        };
+
+        let mut message = header.message;
+        message.payload = payload;
+        Ok((
+            ReceivedMessage {
+                message,
+                from_party: header.from_party,
+                to_party: header.to_party,
+                sender_certificate: verified.certificate,
+            },
+            verified.reference_elements,
+        ))
+    }
+}
@@ -0,0 +1,558 @@
+//! WS-Security for AS4: XML signatures over the SOAP body, the ebMS header
+//! and attachments, and attachment encryption for the receiving Access Point.
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes128Gcm, KeyInit, Nonce};
+use quick_xml::escape::escape;
+use rand_core::{OsRng, RngCore};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{BigUint, Oaep, Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+
+use super::mime::Part;
+use super::{As4Error, NS_DS, NS_EBMS, NS_SOAP, NS_WSSE, NS_WSSE11, NS_XENC, NS_XENC11, parse_xml};
+use crate::core::der::subject_public_key_info;
+use crate::core::digest::{base64_decode, base64_encode};
+use crate::peppol::sbdh::new_instance_identifier;
+use crate::peppol::smp::CertificateInfo;
+use crate::xrechnung::c14n::{Element, canonicalize};
+
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+const RSA_OAEP: &str = "http://www.w3.org/2009/xmlenc11#rsa-oaep";
+const MGF1_SHA256: &str = "http://www.w3.org/2009/xmlenc11#mgf1sha256";
+const AES128_GCM: &str = "http://www.w3.org/2009/xmlenc11#aes128-gcm";
+const ATTACHMENT_SIGNATURE: &str = "http://docs.oasis-open.org/wss/oasis-wss-SwAProfile-1.1#Attachment-Content-Signature-Transform";
+const ATTACHMENT_CIPHERTEXT: &str =
+    "http://docs.oasis-open.org/wss/oasis-wss-SwAProfile-1.1#Attachment-Ciphertext-Transform";
+const ATTACHMENT_CONTENT_ONLY: &str =
+    "http://docs.oasis-open.org/wss/oasis-wss-SwAProfile-1.1#Attachment-Content-Only";
+const X509_V3: &str =
+    "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-x509-token-profile-1.0#X509v3";
+const BASE64_BINARY: &str = "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-soap-message-security-1.0#Base64Binary";
+const ENCRYPTED_KEY_TOKEN: &str =
+    "http://docs.oasis-open.org/wss/oasis-wss-soap-message-security-1.1#EncryptedKey";
+
+/// Length of the AES-GCM nonce prepended to the ciphertext.
+const GCM_NONCE_LEN: usize = 12;
+
+/// An Access Point's private key and certificate.
+#[derive(Clone)]
+pub struct As4Identity {
+    key: RsaPrivateKey,
+    certificate: CertificateInfo,
+}
+
+impl std::fmt::Debug for As4Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("As4Identity")
+            .field("certificate", &self.certificate.subject)
+            .finish_non_exhaustive()
+    }
+}
+
+impl As4Identity {
+    /// Load an RSA private key (PKCS#8 or PKCS#1 PEM) and the matching
+    /// X.509 certificate (PEM).
+    pub fn from_pem(private_key_pem: &str, certificate_pem: &str) -> Result<Self, As4Error> {
+        let key = RsaPrivateKey::from_pkcs8_pem(private_key_pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key_pem))
+            .map_err(|e| As4Error::Security(format!("cannot read private key: {e}")))?;
+        let certificate = CertificateInfo::from_pem(certificate_pem)
+            .map_err(|e| As4Error::Security(e.to_string()))?;
+        if public_key(&certificate)? != key.to_public_key() {
+            return Err(As4Error::Security(
+                "private key does not match the certificate".into(),
+            ));
+        }
+        Ok(Self { key, certificate })
+    }
+
+    /// The Access Point certificate.
+    pub fn certificate(&self) -> &CertificateInfo {
+        &self.certificate
+    }
+
+    /// The Access Point's party ID: the common name of its certificate.
+    pub fn party_id(&self) -> &str {
+        self.certificate.common_name().unwrap_or_default()
+    }
+}
+
+/// RSA public key of a certificate.
+fn public_key(certificate: &CertificateInfo) -> Result<RsaPublicKey, As4Error> {
+    subject_public_key_info(&certificate.der)
+        .and_then(|spki| RsaPublicKey::from_public_key_der(spki).ok())
+        .ok_or_else(|| {
+            As4Error::Security(format!(
+                "certificate '{}' has no RSA public key",
+                certificate.subject
+            ))
+        })
+}
+
+fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// A signed reference: URI and SHA-256 digest value (Base64).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SignedReference {
+    pub(crate) uri: String,
+    pub(crate) digest: String,
+}
+
+/// Result of a successful signature check.
+#[derive(Debug, Clone)]
+pub(crate) struct Verified<'a> {
+    /// The signer's certificate.
+    pub(crate) certificate: CertificateInfo,
+    /// References covered by the signature.
+    pub(crate) references: Vec<SignedReference>,
+    /// Canonical `ds:Reference` elements, for non-repudiation receipts.
+    pub(crate) reference_elements: Vec<String>,
+    /// The signed elements of the document the `#Id` references resolved to.
+    signed_elements: Vec<&'a Element>,
+}
+
+impl Verified<'_> {
+    /// Whether the signature covers exactly this element of the document,
+    /// not merely another element with the same `Id`.
+    pub(crate) fn covers(&self, element: &Element) -> bool {
+        self.signed_elements
+            .iter()
+            .any(|e| std::ptr::eq(*e, element))
+    }
+
+    /// Whether the signature covers the attachment `cid:content_id`.
+    pub(crate) fn covers_attachment(&self, content_id: &str) -> bool {
+        let uri = format!("cid:{content_id}");
+        self.references.iter().any(|r| r.uri == uri)
+    }
+}
+
+/// Build the `wsse:Security` content signing the elements with the given
+/// `wsu:Id`s and the attachments (plaintext content).
+///
+/// Returns the XML to put into the `wsse:Security` header (binary security
+/// token and signature) and the signed references.
+pub(crate) fn sign(
+    identity: &As4Identity,
+    document: &Element,
+    ids: &[&str],
+    attachments: &[(&str, &[u8])],
+) -> Result<(String, Vec<SignedReference>), As4Error> {
+    let mut references = Vec::new();
+    let mut reference_xml = String::new();
+    for id in ids {
+        let element = document
+            .by_id(id)
+            .ok_or_else(|| As4Error::Invalid(format!("no element with Id '{id}' to sign")))?;
+        let digest = base64_encode(&sha256(canonicalize(element, &[]).as_bytes()));
+        reference_xml.push_str(&reference(&format!("#{id}"), EXC_C14N, &digest));
+        references.push(SignedReference {
+            uri: format!("#{id}"),
+            digest,
+        });
+    }
+    for (content_id, content) in attachments {
+        let digest = base64_encode(&sha256(content));
+        let uri = format!("cid:{content_id}");
+        reference_xml.push_str(&reference(&uri, ATTACHMENT_SIGNATURE, &digest));
+        references.push(SignedReference { uri, digest });
+    }
+
+    let signed_info = format!(
+        "<ds:SignedInfo xmlns:ds=\"{NS_DS}\">\
+         <ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"/>\
+         <ds:SignatureMethod Algorithm=\"{RSA_SHA256}\"/>{reference_xml}</ds:SignedInfo>"
+    );
+    let canonical = canonicalize(&parse_xml(&signed_info)?, &[]);
+    let signature = identity
+        .key
+        .sign(Pkcs1v15Sign::new::<Sha256>(), &sha256(canonical.as_bytes()))
+        .map_err(|e| As4Error::Security(format!("signing failed: {e}")))?;
+
+    let token_id = format!("X509-{}", new_instance_identifier());
+    let xml = format!(
+        "<wsse:BinarySecurityToken EncodingType=\"{BASE64_BINARY}\" ValueType=\"{X509_V3}\" \
+         wsu:Id=\"{token_id}\">{}</wsse:BinarySecurityToken>\
+         <ds:Signature xmlns:ds=\"{NS_DS}\" Id=\"SIG-{}\">{signed_info}\
+         <ds:SignatureValue>{}</ds:SignatureValue>\
+         <ds:KeyInfo><wsse:SecurityTokenReference>\
+         <wsse:Reference URI=\"#{token_id}\" ValueType=\"{X509_V3}\"/>\
+         </wsse:SecurityTokenReference></ds:KeyInfo></ds:Signature>",
+        base64_encode(&identity.certificate.der),
+        new_instance_identifier(),
+        base64_encode(&signature),
+    );
+    Ok((xml, references))
+}
+
+fn reference(uri: &str, transform: &str, digest: &str) -> String {
+    format!(
+        "<ds:Reference URI=\"{}\"><ds:Transforms><ds:Transform Algorithm=\"{transform}\"/>\
+         </ds:Transforms><ds:DigestMethod Algorithm=\"{SHA256}\"/>\
+         <ds:DigestValue>{digest}</ds:DigestValue></ds:Reference>",
+        escape(uri)
+    )
+}
+
+/// Check the signature in the `wsse:Security` header of `document`.
+///
+/// Attachments must already be decrypted. Documents with an `Id` used
+/// twice are rejected; callers check with [`Verified::covers`] that the
+/// elements they process are the signed ones.
+pub(crate) fn verify<'a>(
+    document: &'a Element,
+    attachments: &[Part],
+) -> Result<Verified<'a>, As4Error> {
+    if let Some(id) = document.duplicate_id() {
+        return Err(As4Error::Security(format!(
+            "Id '{id}' is used by more than one element"
+        )));
+    }
+    let security = security_header(document)?;
+    let signature = security
+        .child(NS_DS, "Signature")
+        .ok_or_else(|| As4Error::Security("message is not signed".into()))?;
+    let signed_info = signature
+        .child(NS_DS, "SignedInfo")
+        .ok_or_else(|| As4Error::Security("signature without SignedInfo".into()))?;
+
+    let method = signed_info.child(NS_DS, "CanonicalizationMethod");
+    if method.and_then(|m| m.attribute("Algorithm")) != Some(EXC_C14N) {
+        return Err(As4Error::Security(
+            "unsupported canonicalization method".into(),
+        ));
+    }
+    let signature_method = signed_info
+        .child(NS_DS, "SignatureMethod")
+        .and_then(|m| m.attribute("Algorithm"));
+    if signature_method != Some(RSA_SHA256) {
+        return Err(As4Error::Security(format!(
+            "unsupported signature method {}",
+            signature_method.unwrap_or("(none)")
+        )));
+    }
+
+    // Signer certificate from the referenced binary security token
+    let token_uri = signature
+        .find(NS_WSSE, "Reference")
+        .and_then(|r| r.attribute("URI"))
+        .ok_or_else(|| As4Error::Security("signature without key reference".into()))?;
+    let token = token_uri
+        .strip_prefix('#')
+        .and_then(|id| document.by_id(id))
+        .filter(|t| t.is(NS_WSSE, "BinarySecurityToken"))
+        .ok_or_else(|| As4Error::Security(format!("security token '{token_uri}' not found")))?;
+    let certificate = CertificateInfo::from_base64(&token.text())
+        .map_err(|e| As4Error::Security(e.to_string()))?;
+
+    let canonical = canonicalize(signed_info, &inclusive_prefixes(method));
+    let value = signature
+        .find_text(NS_DS, "SignatureValue")
+        .and_then(|v| base64_decode(&v))
+        .ok_or_else(|| As4Error::Security("signature value is not Base64".into()))?;
+    public_key(&certificate)?
+        .verify(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &sha256(canonical.as_bytes()),
+            &value,
+        )
+        .map_err(|_| As4Error::Security("signature value does not verify".into()))?;
+
+    let mut references = Vec::new();
+    let mut reference_elements = Vec::new();
+    let mut signed_elements = Vec::new();
+    for reference in signed_info.elements().filter(|e| e.is(NS_DS, "Reference")) {
+        let (checked, element) = check_reference(document, reference, attachments)?;
+        references.push(checked);
+        reference_elements.push(canonicalize(reference, &[]));
+        signed_elements.extend(element);
+    }
+    Ok(Verified {
+        certificate,
+        references,
+        reference_elements,
+        signed_elements,
+    })
+}
+
+/// Check the digest of a reference; returns it with the referenced
+/// element for `#Id` references.
+fn check_reference<'a>(
+    document: &'a Element,
+    reference: &Element,
+    attachments: &[Part],
+) -> Result<(SignedReference, Option<&'a Element>), As4Error> {
+    let uri = reference.attribute("URI").unwrap_or_default();
+    if reference
+        .child(NS_DS, "DigestMethod")
+        .and_then(|m| m.attribute("Algorithm"))
+        != Some(SHA256)
+    {
+        return Err(As4Error::Security(format!(
+            "unsupported digest method for '{uri}'"
+        )));
+    }
+    let transform = reference
+        .find(NS_DS, "Transform")
+        .and_then(|t| t.attribute("Algorithm"));
+    let mut signed_element = None;
+    let digest = if let Some(id) = uri.strip_prefix('#') {
+        let element = document
+            .by_id(id)
+            .ok_or_else(|| As4Error::Security(format!("signed element '{uri}' not found")))?;
+        if transform != Some(EXC_C14N) {
+            return Err(As4Error::Security(format!(
+                "unsupported transform for '{uri}'"
+            )));
+        }
+        let prefixes = inclusive_prefixes(reference.find(NS_DS, "Transform"));
+        signed_element = Some(element);
+        sha256(canonicalize(element, &prefixes).as_bytes())
+    } else if let Some(content_id) = uri.strip_prefix("cid:") {
+        let part = attachments
+            .iter()
+            .find(|p| p.content_id == content_id)
+            .ok_or_else(|| As4Error::Security(format!("signed attachment '{uri}' not found")))?;
+        if transform != Some(ATTACHMENT_SIGNATURE) {
+            return Err(As4Error::Security(format!(
+                "unsupported transform for '{uri}'"
+            )));
+        }
+        sha256(&part.body)
+    } else {
+        return Err(As4Error::Security(format!(
+            "unsupported reference URI '{uri}'"
+        )));
+    };
+    let expected = reference
+        .find_text(NS_DS, "DigestValue")
+        .unwrap_or_default();
+    if base64_decode(&expected).as_deref() != Some(digest.as_slice()) {
+        return Err(As4Error::Security(format!(
+            "digest of '{uri}' does not match"
+        )));
+    }
+    Ok((
+        SignedReference {
+            uri: uri.to_string(),
+            digest: expected,
+        },
+        signed_element,
+    ))
+}
+
+/// Prefixes of an `ec:InclusiveNamespaces` child, if any.
+fn inclusive_prefixes(method: Option<&Element>) -> Vec<&str> {
+    method
+        .and_then(|m| {
+            m.elements()
+                .find(|e| e.local_name() == "InclusiveNamespaces")
+        })
+        .and_then(|e| e.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().collect())
+        .unwrap_or_default()
+}
+
+/// Check that the signature covers the SOAP body and the `eb:Messaging`
+/// header of the envelope itself, the elements messages are read from.
+pub(crate) fn check_envelope_signed(
+    document: &Element,
+    verified: &Verified<'_>,
+) -> Result<(), As4Error> {
+    let body = document.child(NS_SOAP, "Body");
+    let messaging = document
+        .child(NS_SOAP, "Header")
+        .and_then(|h| h.child(NS_EBMS, "Messaging"));
+    for (element, name) in [(body, "the SOAP body"), (messaging, "the ebMS header")] {
+        if !element.is_some_and(|e| verified.covers(e)) {
+            return Err(As4Error::Security(format!(
+                "signature does not cover {name}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The `wsse:Security` header of a SOAP envelope.
+pub(crate) fn security_header(document: &Element) -> Result<&Element, As4Error> {
+    document
+        .find(NS_WSSE, "Security")
+        .ok_or_else(|| As4Error::Security("message has no WS-Security header".into()))
+}
+
+/// Encrypt an attachment for `recipient`.
+///
+/// Returns the `xenc:EncryptedKey` and `xenc:EncryptedData` elements for
+/// the `wsse:Security` header and the ciphertext (nonce ‖ ciphertext ‖ tag).
+pub(crate) fn encrypt(
+    recipient: &CertificateInfo,
+    content_id: &str,
+    mime_type: &str,
+    plaintext: &[u8],
+) -> Result<(String, Vec<u8>), As4Error> {
+    let mut key = [0u8; 16];
+    let mut nonce = [0u8; GCM_NONCE_LEN];
+    OsRng.fill_bytes(&mut key);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = Aes128Gcm::new_from_slice(&key)
+        .map_err(|e| As4Error::Security(format!("encryption failed: {e}")))?;
+    let mut ciphertext = nonce.to_vec();
+    ciphertext.extend(
+        cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|e| As4Error::Security(format!("encryption failed: {e}")))?,
+    );
+    let wrapped = public_key(recipient)?
+        .encrypt(&mut OsRng, Oaep::new::<Sha256>(), &key)
+        .map_err(|e| As4Error::Security(format!("key encryption failed: {e}")))?;
+
+    let serial = BigUint::parse_bytes(recipient.serial.as_bytes(), 16).unwrap_or_default();
+    let key_id = format!("EK-{}", new_instance_identifier());
+    let data_id = format!("ED-{}", new_instance_identifier());
+    let xml = format!(
+        "<xenc:EncryptedKey xmlns:xenc=\"{NS_XENC}\" xmlns:ds=\"{NS_DS}\" Id=\"{key_id}\">\
+         <xenc:EncryptionMethod Algorithm=\"{RSA_OAEP}\">\
+         <ds:DigestMethod Algorithm=\"{SHA256}\"/>\
+         <xenc11:MGF xmlns:xenc11=\"{NS_XENC11}\" Algorithm=\"{MGF1_SHA256}\"/>\
+         </xenc:EncryptionMethod>\
+         <ds:KeyInfo><wsse:SecurityTokenReference><ds:X509Data><ds:X509IssuerSerial>\
+         <ds:X509IssuerName>{}</ds:X509IssuerName>\
+         <ds:X509SerialNumber>{serial}</ds:X509SerialNumber>\
+         </ds:X509IssuerSerial></ds:X509Data></wsse:SecurityTokenReference></ds:KeyInfo>\
+         <xenc:CipherData><xenc:CipherValue>{}</xenc:CipherValue></xenc:CipherData>\
+         <xenc:ReferenceList><xenc:DataReference URI=\"#{data_id}\"/></xenc:ReferenceList>\
+         </xenc:EncryptedKey>\
+         <xenc:EncryptedData xmlns:xenc=\"{NS_XENC}\" xmlns:ds=\"{NS_DS}\" Id=\"{data_id}\" \
+         MimeType=\"{}\" Type=\"{ATTACHMENT_CONTENT_ONLY}\">\
+         <xenc:EncryptionMethod Algorithm=\"{AES128_GCM}\"/>\
+         <ds:KeyInfo><wsse:SecurityTokenReference xmlns:wsse11=\"{NS_WSSE11}\" \
+         wsse11:TokenType=\"{ENCRYPTED_KEY_TOKEN}\">\
+         <wsse:Reference URI=\"#{key_id}\"/></wsse:SecurityTokenReference></ds:KeyInfo>\
+         <xenc:CipherData><xenc:CipherReference URI=\"cid:{}\"><xenc:Transforms>\
+         <ds:Transform Algorithm=\"{ATTACHMENT_CIPHERTEXT}\"/></xenc:Transforms>\
+         </xenc:CipherReference></xenc:CipherData></xenc:EncryptedData>",
+        escape(&recipient.issuer),
+        base64_encode(&wrapped),
+        escape(mime_type),
+        escape(content_id),
+    );
+    Ok((xml, ciphertext))
+}
+
+/// Decrypt the encrypted attachments listed in the `wsse:Security` header
+/// in place; returns the content IDs of the decrypted parts.
+pub(crate) fn decrypt(
+    identity: &As4Identity,
+    document: &Element,
+    attachments: &mut [Part],
+) -> Result<Vec<String>, As4Error> {
+    let security = security_header(document)?;
+    let mut decrypted = Vec::new();
+    for data in security.find_all(NS_XENC, "EncryptedData") {
+        let algorithm = data
+            .child(NS_XENC, "EncryptionMethod")
+            .and_then(|m| m.attribute("Algorithm"));
+        if algorithm != Some(AES128_GCM) {
+            return Err(As4Error::Security(format!(
+                "unsupported data encryption {}",
+                algorithm.unwrap_or("(none)")
+            )));
+        }
+        let uri = data
+            .find(NS_XENC, "CipherReference")
+            .and_then(|r| r.attribute("URI"))
+            .ok_or_else(|| As4Error::Security("encrypted data without cipher reference".into()))?;
+        let part = uri
+            .strip_prefix("cid:")
+            .and_then(|cid| attachments.iter_mut().find(|p| p.content_id == cid))
+            .ok_or_else(|| As4Error::Security(format!("encrypted attachment '{uri}' not found")))?;
+
+        let key = unwrap_key(identity, encrypted_key(document, security, data)?)?;
+        let cipher = Aes128Gcm::new_from_slice(&key)
+            .map_err(|_| As4Error::Security("content key is not an AES-128 key".into()))?;
+        if part.body.len() < GCM_NONCE_LEN {
+            return Err(As4Error::Security(format!(
+                "attachment '{uri}' is truncated"
+            )));
+        }
+        let (nonce, ciphertext) = part.body.split_at(GCM_NONCE_LEN);
+        part.body = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| As4Error::Security(format!("attachment '{uri}' cannot be decrypted")))?;
+        if let Some(mime_type) = data.attribute("MimeType") {
+            part.content_type = mime_type.to_string();
+        }
+        decrypted.push(part.content_id.clone());
+    }
+    Ok(decrypted)
+}
+
+/// The `xenc:EncryptedKey` for an `xenc:EncryptedData`, found through its
+/// key reference or the key's reference list.
+fn encrypted_key<'a>(
+    document: &'a Element,
+    security: &'a Element,
+    data: &Element,
+) -> Result<&'a Element, As4Error> {
+    let by_reference = data
+        .find(NS_WSSE, "Reference")
+        .and_then(|r| r.attribute("URI"))
+        .and_then(|uri| uri.strip_prefix('#'))
+        .and_then(|id| document.by_id(id));
+    let by_list = || {
+        let data_uri = format!("#{}", data.attribute("Id").unwrap_or_default());
+        security
+            .find_all(NS_XENC, "EncryptedKey")
+            .into_iter()
+            .find(|k| {
+                k.find_all(NS_XENC, "DataReference")
+                    .iter()
+                    .any(|r| r.attribute("URI") == Some(data_uri.as_str()))
+            })
+    };
+    by_reference
+        .filter(|k| k.is(NS_XENC, "EncryptedKey"))
+        .or_else(by_list)
+        .ok_or_else(|| As4Error::Security("encrypted data without encrypted key".into()))
+}
+
+fn unwrap_key(identity: &As4Identity, key: &Element) -> Result<Vec<u8>, As4Error> {
+    let method = key.child(NS_XENC, "EncryptionMethod");
+    if method.and_then(|m| m.attribute("Algorithm")) != Some(RSA_OAEP) {
+        return Err(As4Error::Security("unsupported key transport".into()));
+    }
+    let mgf = method
+        .and_then(|m| m.find(NS_XENC11, "MGF"))
+        .and_then(|m| m.attribute("Algorithm"));
+    let digest = method
+        .and_then(|m| m.find(NS_DS, "DigestMethod"))
+        .and_then(|m| m.attribute("Algorithm"));
+    if mgf != Some(MGF1_SHA256) || digest != Some(SHA256) {
+        return Err(As4Error::Security(
+            "unsupported RSA-OAEP parameters (expected SHA-256 and MGF1-SHA-256)".into(),
+        ));
+    }
+    if let Some(serial) = key.find_text(NS_DS, "X509SerialNumber") {
+        let own = BigUint::parse_bytes(identity.certificate.serial.as_bytes(), 16);
+        if BigUint::parse_bytes(serial.as_bytes(), 10) != own {
+            return Err(As4Error::Security(
+                "message is encrypted for another certificate".into(),
+            ));
+        }
+    }
+    let wrapped = key
+        .find_text(NS_XENC, "CipherValue")
+        .and_then(|v| base64_decode(&v))
+        .ok_or_else(|| As4Error::Security("encrypted key is not Base64".into()))?;
+    identity
+        .key
+        .decrypt(Oaep::new::<Sha256>(), &wrapped)
+        .map_err(|_| As4Error::Security("content key cannot be decrypted".into()))
+}
@@ -11,12 +11,26 @@
 //!
 //! let xml = peppol::to_ubl_xml(&invoice).unwrap();
 //! let errors = peppol::validate_peppol(&invoice);
+//!
+//! // Envelope for an Access Point, routed by the parties' electronic addresses
+//! let envelope = peppol::sbdh::wrap(&invoice, None, None).unwrap();
+//! let (header, parsed) = peppol::sbdh::unwrap(&envelope).unwrap();
 //! ```
 
+#[cfg(feature = "as4")]
+pub mod as4;
 mod eas;
+pub mod response;
+pub mod sbdh;
+#[cfg(feature = "peppol-smp")]
+pub mod smp;
 mod validate;
 
-pub use eas::{EasScheme, eas_scheme_for_country};
+pub use eas::{EasScheme, eas_scheme_for_country, eas_schemes_for_country};
+pub use response::{
+    InvoiceResponse, InvoiceStatus, MessageLevelResponse, validate_invoice_response, validate_mlr,
+};
+pub use sbdh::{ParticipantId, Sbdh};
 pub use validate::{validate_peppol, validate_peppol_full};
 
 use crate::core::{Invoice, RechnungError};
@@ -34,18 +48,37 @@ pub const PEPPOL_INVOICE_DOCTYPE: &str = "urn:oasis:names:specification:ubl:sche
 /// Peppol document type identifier for credit notes (used in SMP routing).
 pub const PEPPOL_CREDIT_NOTE_DOCTYPE: &str = "urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2::CreditNote##urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0::2.1";
 
+/// Peppol BIS Self-Billing 3.0 customization identifier (BT-24).
+pub const PEPPOL_SELF_BILLING_CUSTOMIZATION_ID: &str =
+    "urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:selfbilling:3.0";
+
+/// Peppol BIS Self-Billing 3.0 profile identifier (BT-23).
+pub const PEPPOL_SELF_BILLING_PROFILE_ID: &str = crate::xrechnung::PEPPOL_SELF_BILLING_PROFILE_ID;
+
+/// Peppol document type identifier for self-billed invoices.
+pub const PEPPOL_SELF_BILLING_INVOICE_DOCTYPE: &str = "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2::Invoice##urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:selfbilling:3.0::2.1";
+
+/// Peppol document type identifier for self-billed credit notes.
+pub const PEPPOL_SELF_BILLING_CREDIT_NOTE_DOCTYPE: &str = "urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2::CreditNote##urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:selfbilling:3.0::2.1";
+
 /// Generate a Peppol BIS 3.0 compliant UBL 2.1 Invoice XML.
 ///
 /// This produces the same UBL structure as XRechnung but with the
-/// Peppol-specific CustomizationID.
+/// Peppol-specific CustomizationID. Self-billed documents (389, 261) are
+/// written for BIS Self-Billing 3.0.
 pub fn to_ubl_xml(invoice: &Invoice) -> Result<String, RechnungError> {
     // Generate XRechnung UBL (same structure, same ProfileID)
     let xml = crate::xrechnung::to_ubl_xml(invoice)?;
 
     // Replace XRechnung CustomizationID with Peppol CustomizationID
+    let customization_id = if invoice.type_code.is_self_billed() {
+        PEPPOL_SELF_BILLING_CUSTOMIZATION_ID
+    } else {
+        PEPPOL_CUSTOMIZATION_ID
+    };
     Ok(xml.replace(
         crate::xrechnung::XRECHNUNG_CUSTOMIZATION_ID,
-        PEPPOL_CUSTOMIZATION_ID,
+        customization_id,
     ))
 }
 
@@ -0,0 +1,1264 @@
+//! Peppol Invoice Response and Message Level Response documents.
+//!
+//! Both are UBL 2.1 `ApplicationResponse` documents exchanged over the
+//! Peppol network. An [`InvoiceResponse`] (BIS Invoice Response 3.0) is the
+//! buyer's business status of a received invoice — acknowledged, in process,
+//! under query, rejected, accepted, paid — with clarification reasons and
+//! requested actions. A [`MessageLevelResponse`] (BIS MLR 3.0) reports
+//! whether a received message passed validation, referencing its SBDH
+//! instance identifier and listing the detected issues.
+//!
+//! [`validate_invoice_response`] and [`validate_mlr`] check the documents
+//! against the rules of their specifications.
+
+use chrono::{NaiveDate, Utc};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use super::sbdh::{Sbdh, new_instance_identifier};
+use super::{EasScheme, ParticipantId};
+use crate::core::{Invoice, InvoiceTypeCode, RechnungError, ValidationError};
+use crate::xrechnung::xml_utils::XmlWriter;
+
+/// UBL 2.1 ApplicationResponse namespace.
+pub const APPLICATION_RESPONSE_NAMESPACE: &str =
+    "urn:oasis:names:specification:ubl:schema:xsd:ApplicationResponse-2";
+
+/// Customization identifier of Peppol Invoice Response transactions (T111).
+pub const INVOICE_RESPONSE_CUSTOMIZATION_ID: &str =
+    "urn:fdc:peppol.eu:poacc:trns:invoice_response:3";
+
+/// Profile identifier of the Peppol Invoice Response process.
+pub const INVOICE_RESPONSE_PROFILE_ID: &str = "urn:fdc:peppol.eu:poacc:bis:invoice_response:3";
+
+/// Peppol document type identifier for Invoice Responses (used in SMP routing).
+pub const PEPPOL_INVOICE_RESPONSE_DOCTYPE: &str = "urn:oasis:names:specification:ubl:schema:xsd:ApplicationResponse-2::ApplicationResponse##urn:fdc:peppol.eu:poacc:trns:invoice_response:3::2.1";
+
+/// Customization identifier of Peppol Message Level Responses (T71).
+pub const MLR_CUSTOMIZATION_ID: &str = "urn:fdc:peppol.eu:poacc:trns:mlr:3";
+
+/// Profile identifier of the Peppol Message Level Response process.
+pub const MLR_PROFILE_ID: &str = "urn:fdc:peppol.eu:poacc:bis:mlr:3";
+
+/// Peppol document type identifier for Message Level Responses (used in SMP routing).
+pub const PEPPOL_MLR_DOCTYPE: &str = "urn:oasis:names:specification:ubl:schema:xsd:ApplicationResponse-2::ApplicationResponse##urn:fdc:peppol.eu:poacc:trns:mlr:3::2.1";
+
+const NS_CAC: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2";
+const NS_CBC: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2";
+
+/// Code list of response codes (UNCL 4343 subset).
+const RESPONSE_CODE_LIST: &str = "UNCL4343OpSubset";
+/// Code list of clarification reasons.
+const REASON_CODE_LIST: &str = "OPStatusReason";
+/// Code list of clarification actions.
+const ACTION_CODE_LIST: &str = "OPStatusAction";
+/// Code list of MLR issue kinds.
+const ISSUE_CODE_LIST: &str = "PEPPOLSubset";
+
+/// Sending or receiving party of a response document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseParty {
+    /// Electronic address (EndpointID) as a Peppol participant identifier.
+    pub endpoint: ParticipantId,
+    /// Legal registration name; mandatory in Invoice Responses.
+    pub name: Option<String>,
+}
+
+impl ResponseParty {
+    fn from_party(party: &crate::core::Party, role: &str) -> Result<Self, RechnungError> {
+        let endpoint = party
+            .electronic_address
+            .as_ref()
+            .and_then(ParticipantId::from_electronic_address)
+            .ok_or_else(|| {
+                RechnungError::Builder(format!(
+                    "the {role}'s electronic address is not a Peppol EAS identifier"
+                ))
+            })?;
+        Ok(Self {
+            endpoint,
+            name: Some(party.name.clone()),
+        })
+    }
+
+    /// Whether `party`'s electronic address is this party's endpoint.
+    fn is(&self, party: &crate::core::Party) -> bool {
+        party
+            .electronic_address
+            .as_ref()
+            .and_then(ParticipantId::from_electronic_address)
+            .is_some_and(|id| id.value == self.endpoint.value)
+    }
+}
+
+/// Business status of an invoice (UNCL 4343 subset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InvoiceStatus {
+    /// AB — Message acknowledgement: the invoice was received.
+    Acknowledged,
+    /// IP — In process.
+    InProcess,
+    /// UQ — Under query: processing is on hold until questions are answered.
+    UnderQuery,
+    /// CA — Conditionally accepted.
+    ConditionallyAccepted,
+    /// RE — Rejected: the invoice will not be processed.
+    Rejected,
+    /// AP — Accepted: the invoice is approved for payment.
+    Accepted,
+    /// PD — Fully paid.
+    Paid,
+}
+
+impl InvoiceStatus {
+    /// UNCL 4343 code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Acknowledged => "AB",
+            Self::InProcess => "IP",
+            Self::UnderQuery => "UQ",
+            Self::ConditionallyAccepted => "CA",
+            Self::Rejected => "RE",
+            Self::Accepted => "AP",
+            Self::Paid => "PD",
+        }
+    }
+
+    /// Parse from UNCL 4343 code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "AB" => Some(Self::Acknowledged),
+            "IP" => Some(Self::InProcess),
+            "UQ" => Some(Self::UnderQuery),
+            "CA" => Some(Self::ConditionallyAccepted),
+            "RE" => Some(Self::Rejected),
+            "AP" => Some(Self::Accepted),
+            "PD" => Some(Self::Paid),
+            _ => None,
+        }
+    }
+
+    /// Whether the status needs a clarification reason (UQ, CA, RE).
+    pub fn requires_reason(&self) -> bool {
+        matches!(
+            self,
+            Self::UnderQuery | Self::ConditionallyAccepted | Self::Rejected
+        )
+    }
+}
+
+/// Clarification reason of an invoice status (OPStatusReason).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusReason {
+    /// NON — No issue.
+    NoIssue,
+    /// REF — References incorrect.
+    References,
+    /// LEG — Legal information incorrect.
+    LegalInformation,
+    /// REC — Receiver unknown.
+    ReceiverUnknown,
+    /// QUA — Item quality insufficient.
+    ItemQuality,
+    /// DEL — Delivery issues.
+    Delivery,
+    /// PRI — Prices incorrect.
+    Prices,
+    /// QTY — Quantity incorrect.
+    Quantity,
+    /// ITM — Items incorrect.
+    Items,
+    /// PAY — Payment terms incorrect.
+    PaymentTerms,
+    /// UNR — Not recognized.
+    NotRecognized,
+    /// FIN — Finance terms incorrect.
+    FinanceTerms,
+    /// PPD — Partially paid.
+    PartiallyPaid,
+    /// OTH — Other.
+    Other,
+}
+
+impl StatusReason {
+    /// OPStatusReason code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NoIssue => "NON",
+            Self::References => "REF",
+            Self::LegalInformation => "LEG",
+            Self::ReceiverUnknown => "REC",
+            Self::ItemQuality => "QUA",
+            Self::Delivery => "DEL",
+            Self::Prices => "PRI",
+            Self::Quantity => "QTY",
+            Self::Items => "ITM",
+            Self::PaymentTerms => "PAY",
+            Self::NotRecognized => "UNR",
+            Self::FinanceTerms => "FIN",
+            Self::PartiallyPaid => "PPD",
+            Self::Other => "OTH",
+        }
+    }
+
+    /// Parse from OPStatusReason code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "NON" => Some(Self::NoIssue),
+            "REF" => Some(Self::References),
+            "LEG" => Some(Self::LegalInformation),
+            "REC" => Some(Self::ReceiverUnknown),
+            "QUA" => Some(Self::ItemQuality),
+            "DEL" => Some(Self::Delivery),
+            "PRI" => Some(Self::Prices),
+            "QTY" => Some(Self::Quantity),
+            "ITM" => Some(Self::Items),
+            "PAY" => Some(Self::PaymentTerms),
+            "UNR" => Some(Self::NotRecognized),
+            "FIN" => Some(Self::FinanceTerms),
+            "PPD" => Some(Self::PartiallyPaid),
+            "OTH" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Action requested from the invoice issuer (OPStatusAction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusAction {
+    /// NOA — No action required.
+    NoAction,
+    /// PIN — Provide information.
+    ProvideInformation,
+    /// NIN — Issue new invoice.
+    NewInvoice,
+    /// CNF — Credit fully.
+    CreditFully,
+    /// CNP — Credit partially.
+    CreditPartially,
+    /// CNA — Credit the amount.
+    CreditAmount,
+    /// OTH — Other.
+    Other,
+}
+
+impl StatusAction {
+    /// OPStatusAction code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NoAction => "NOA",
+            Self::ProvideInformation => "PIN",
+            Self::NewInvoice => "NIN",
+            Self::CreditFully => "CNF",
+            Self::CreditPartially => "CNP",
+            Self::CreditAmount => "CNA",
+            Self::Other => "OTH",
+        }
+    }
+
+    /// Parse from OPStatusAction code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "NOA" => Some(Self::NoAction),
+            "PIN" => Some(Self::ProvideInformation),
+            "NIN" => Some(Self::NewInvoice),
+            "CNF" => Some(Self::CreditFully),
+            "CNP" => Some(Self::CreditPartially),
+            "CNA" => Some(Self::CreditAmount),
+            "OTH" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Clarification of an invoice status: a reason or a requested action,
+/// each with optional free text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clarification {
+    /// Why the invoice has this status.
+    Reason {
+        /// Reason code.
+        code: StatusReason,
+        /// Explanation.
+        text: Option<String>,
+    },
+    /// What the invoice issuer is asked to do.
+    Action {
+        /// Action code.
+        code: StatusAction,
+        /// Explanation.
+        text: Option<String>,
+    },
+}
+
+impl Clarification {
+    fn list_id_code_text(&self) -> (&'static str, &'static str, Option<&str>) {
+        match self {
+            Self::Reason { code, text } => (REASON_CODE_LIST, code.code(), text.as_deref()),
+            Self::Action { code, text } => (ACTION_CODE_LIST, code.code(), text.as_deref()),
+        }
+    }
+}
+
+/// Peppol BIS Invoice Response 3.0: status of a received invoice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceResponse {
+    /// Identifier of the response.
+    pub id: String,
+    /// Issue date of the response.
+    pub issue_date: NaiveDate,
+    /// Free-text note.
+    pub note: Option<String>,
+    /// Sender of the response — the invoice's buyer.
+    pub sender: ResponseParty,
+    /// Receiver of the response — the invoice's seller.
+    pub receiver: ResponseParty,
+    /// Status of the invoice.
+    pub status: InvoiceStatus,
+    /// Date from which the status is valid.
+    pub effective_date: Option<NaiveDate>,
+    /// Reasons and requested actions.
+    pub clarifications: Vec<Clarification>,
+    /// Number of the invoice (BT-1) the response is about.
+    pub invoice_number: String,
+    /// Issue date of that invoice (BT-2).
+    pub invoice_issue_date: Option<NaiveDate>,
+    /// Type code of that invoice (BT-3).
+    pub invoice_type_code: Option<InvoiceTypeCode>,
+}
+
+impl InvoiceResponse {
+    /// Response to `invoice`, sent by its buyer to its seller.
+    ///
+    /// Both parties need a Peppol electronic address (BT-49 / BT-34).
+    pub fn for_invoice(
+        invoice: &Invoice,
+        id: impl Into<String>,
+        issue_date: NaiveDate,
+        status: InvoiceStatus,
+    ) -> Result<Self, RechnungError> {
+        Ok(Self {
+            id: id.into(),
+            issue_date,
+            note: None,
+            sender: ResponseParty::from_party(&invoice.buyer, "buyer")?,
+            receiver: ResponseParty::from_party(&invoice.seller, "seller")?,
+            status,
+            effective_date: None,
+            clarifications: Vec::new(),
+            invoice_number: invoice.number.clone(),
+            invoice_issue_date: Some(invoice.issue_date),
+            invoice_type_code: Some(invoice.type_code),
+        })
+    }
+
+    /// Add a clarification reason.
+    pub fn reason(mut self, code: StatusReason, text: Option<&str>) -> Self {
+        self.clarifications.push(Clarification::Reason {
+            code,
+            text: text.map(str::to_string),
+        });
+        self
+    }
+
+    /// Add a requested action.
+    pub fn action(mut self, code: StatusAction, text: Option<&str>) -> Self {
+        self.clarifications.push(Clarification::Action {
+            code,
+            text: text.map(str::to_string),
+        });
+        self
+    }
+
+    /// Whether this response is about `invoice`: same number, issue date,
+    /// and seller and buyer endpoints as receiver and sender.
+    pub fn responds_to(&self, invoice: &Invoice) -> bool {
+        self.invoice_number == invoice.number
+            && self
+                .invoice_issue_date
+                .is_none_or(|date| date == invoice.issue_date)
+            && self.sender.is(&invoice.buyer)
+            && self.receiver.is(&invoice.seller)
+    }
+
+    /// Generate the UBL ApplicationResponse XML.
+    pub fn to_xml(&self) -> Result<String, RechnungError> {
+        check(validate_invoice_response(self))?;
+
+        let mut w = start_document(
+            INVOICE_RESPONSE_CUSTOMIZATION_ID,
+            INVOICE_RESPONSE_PROFILE_ID,
+            &self.id,
+            self.issue_date,
+        )?;
+        if let Some(note) = &self.note {
+            w.text_element("cbc:Note", note)?;
+        }
+        write_party(&mut w, "cac:SenderParty", &self.sender)?;
+        write_party(&mut w, "cac:ReceiverParty", &self.receiver)?;
+
+        w.start_element("cac:DocumentResponse")?;
+        w.start_element("cac:Response")?;
+        w.text_element_with_attrs(
+            "cbc:ResponseCode",
+            self.status.code(),
+            &[("listID", RESPONSE_CODE_LIST)],
+        )?;
+        if let Some(date) = self.effective_date {
+            w.text_element("cbc:EffectiveDate", &date.to_string())?;
+        }
+        for clarification in &self.clarifications {
+            let (list_id, code, text) = clarification.list_id_code_text();
+            w.start_element("cac:Status")?;
+            w.text_element_with_attrs("cbc:StatusReasonCode", code, &[("listID", list_id)])?;
+            if let Some(text) = text {
+                w.text_element("cbc:StatusReason", text)?;
+            }
+            w.end_element("cac:Status")?;
+        }
+        w.end_element("cac:Response")?;
+        w.start_element("cac:DocumentReference")?;
+        w.text_element("cbc:ID", &self.invoice_number)?;
+        if let Some(date) = self.invoice_issue_date {
+            w.text_element("cbc:IssueDate", &date.to_string())?;
+        }
+        if let Some(code) = self.invoice_type_code {
+            w.text_element("cbc:DocumentTypeCode", &code.code().to_string())?;
+        }
+        w.end_element("cac:DocumentReference")?;
+        w.end_element("cac:DocumentResponse")?;
+        w.end_element("ApplicationResponse")?;
+        w.into_string()
+    }
+
+    /// Parse an Invoice Response.
+    ///
+    /// Only the document structure is checked; use
+    /// [`validate_invoice_response`] for the business rules.
+    pub fn from_xml(xml: &str) -> Result<Self, RechnungError> {
+        let p = Parsed::parse(xml, INVOICE_RESPONSE_CUSTOMIZATION_ID)?;
+        let status = p.response_code.as_deref().unwrap_or_default();
+        let status = InvoiceStatus::from_code(status)
+            .ok_or_else(|| RechnungError::Xml(format!("unknown response code '{status}'")))?;
+        let clarifications = p
+            .statuses
+            .into_iter()
+            .map(|s| {
+                let code = s.code.unwrap_or_default();
+                let unknown = || RechnungError::Xml(format!("unknown clarification code '{code}'"));
+                match s.list_id.as_deref() {
+                    Some(ACTION_CODE_LIST) => Ok(Clarification::Action {
+                        code: StatusAction::from_code(&code).ok_or_else(unknown)?,
+                        text: s.text,
+                    }),
+                    _ => Ok(Clarification::Reason {
+                        code: StatusReason::from_code(&code).ok_or_else(unknown)?,
+                        text: s.text,
+                    }),
+                }
+            })
+            .collect::<Result<_, RechnungError>>()?;
+        let invoice_type_code = p
+            .document_type_code
+            .map(|code| {
+                code.parse()
+                    .ok()
+                    .and_then(InvoiceTypeCode::from_code)
+                    .ok_or_else(|| {
+                        RechnungError::Xml(format!("invalid document type code '{code}'"))
+                    })
+            })
+            .transpose()?;
+        Ok(Self {
+            id: required(p.id, "ID")?,
+            issue_date: date(&required(p.issue_date, "IssueDate")?)?,
+            note: p.note,
+            sender: p.sender.into_party("SenderParty")?,
+            receiver: p.receiver.into_party("ReceiverParty")?,
+            status,
+            effective_date: p.effective_date.as_deref().map(date).transpose()?,
+            clarifications,
+            invoice_number: required(p.document_id, "DocumentReference/ID")?,
+            invoice_issue_date: p.document_issue_date.as_deref().map(date).transpose()?,
+            invoice_type_code,
+        })
+    }
+
+    /// Wrap the response in an SBDH envelope for sending; `country_c1` is
+    /// the sender's country.
+    pub fn wrap(&self, country_c1: &str) -> Result<String, RechnungError> {
+        envelope(
+            &self.sender,
+            &self.receiver,
+            PEPPOL_INVOICE_RESPONSE_DOCTYPE,
+            INVOICE_RESPONSE_PROFILE_ID,
+            country_c1,
+        )
+        .wrap_payload(&self.to_xml()?)
+    }
+}
+
+/// Overall result of a Message Level Response (UNCL 4343 subset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MlrCode {
+    /// AB — Message acknowledgement, no validation result yet.
+    Acknowledged,
+    /// AP — Accepted, possibly with warnings.
+    Accepted,
+    /// RE — Rejected.
+    Rejected,
+}
+
+impl MlrCode {
+    /// UNCL 4343 code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Acknowledged => "AB",
+            Self::Accepted => "AP",
+            Self::Rejected => "RE",
+        }
+    }
+
+    /// Parse from UNCL 4343 code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "AB" => Some(Self::Acknowledged),
+            "AP" => Some(Self::Accepted),
+            "RE" => Some(Self::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of issue reported in a Message Level Response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueKind {
+    /// BV — Business rule violation, fatal.
+    BusinessRuleFatal,
+    /// BW — Business rule violation, warning.
+    BusinessRuleWarning,
+    /// SV — Syntax violation.
+    SyntaxViolation,
+}
+
+impl IssueKind {
+    /// PEPPOL subset code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BusinessRuleFatal => "BV",
+            Self::BusinessRuleWarning => "BW",
+            Self::SyntaxViolation => "SV",
+        }
+    }
+
+    /// Parse from PEPPOL subset code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "BV" => Some(Self::BusinessRuleFatal),
+            "BW" => Some(Self::BusinessRuleWarning),
+            "SV" => Some(Self::SyntaxViolation),
+            _ => None,
+        }
+    }
+
+    /// Whether the issue makes the message rejected.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, Self::BusinessRuleWarning)
+    }
+}
+
+/// One issue found in the received message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MlrIssue {
+    /// XPath of the offending element; `None` when not applicable.
+    pub location: Option<String>,
+    /// Kind of issue.
+    pub kind: IssueKind,
+    /// Description, including the rule identifier if any.
+    pub description: String,
+}
+
+impl MlrIssue {
+    /// Fatal business rule issue for a validation error.
+    pub fn from_validation(error: &ValidationError) -> Self {
+        Self {
+            location: None,
+            kind: IssueKind::BusinessRuleFatal,
+            description: error.to_string(),
+        }
+    }
+}
+
+/// Peppol BIS Message Level Response 3.0: validation result of a received
+/// message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageLevelResponse {
+    /// Identifier of the response.
+    pub id: String,
+    /// Issue date of the response.
+    pub issue_date: NaiveDate,
+    /// Sender of the response — the receiver of the message.
+    pub sender: ResponseParty,
+    /// Receiver of the response — the sender of the message.
+    pub receiver: ResponseParty,
+    /// Overall result.
+    pub code: MlrCode,
+    /// Free-text description of the result.
+    pub description: Option<String>,
+    /// SBDH instance identifier of the message responded to.
+    pub message_id: String,
+    /// Issues found.
+    pub issues: Vec<MlrIssue>,
+}
+
+impl MessageLevelResponse {
+    /// Response to the message with SBDH `header`, reporting `errors` as
+    /// fatal issues: rejected if there are any, accepted otherwise.
+    pub fn for_message(
+        header: &Sbdh,
+        id: impl Into<String>,
+        issue_date: NaiveDate,
+        errors: &[ValidationError],
+    ) -> Self {
+        let party = |endpoint: &ParticipantId| ResponseParty {
+            endpoint: endpoint.clone(),
+            name: None,
+        };
+        Self {
+            id: id.into(),
+            issue_date,
+            sender: party(&header.receiver),
+            receiver: party(&header.sender),
+            code: if errors.is_empty() {
+                MlrCode::Accepted
+            } else {
+                MlrCode::Rejected
+            },
+            description: None,
+            message_id: header.instance_identifier.clone(),
+            issues: errors.iter().map(MlrIssue::from_validation).collect(),
+        }
+    }
+
+    /// Generate the UBL ApplicationResponse XML.
+    pub fn to_xml(&self) -> Result<String, RechnungError> {
+        check(validate_mlr(self))?;
+
+        let mut w = start_document(
+            MLR_CUSTOMIZATION_ID,
+            MLR_PROFILE_ID,
+            &self.id,
+            self.issue_date,
+        )?;
+        write_party(&mut w, "cac:SenderParty", &self.sender)?;
+        write_party(&mut w, "cac:ReceiverParty", &self.receiver)?;
+
+        w.start_element("cac:DocumentResponse")?;
+        w.start_element("cac:Response")?;
+        w.text_element_with_attrs(
+            "cbc:ResponseCode",
+            self.code.code(),
+            &[("listID", RESPONSE_CODE_LIST)],
+        )?;
+        if let Some(description) = &self.description {
+            w.text_element("cbc:Description", description)?;
+        }
+        w.end_element("cac:Response")?;
+        w.start_element("cac:DocumentReference")?;
+        w.text_element("cbc:ID", &self.message_id)?;
+        w.end_element("cac:DocumentReference")?;
+        for issue in &self.issues {
+            w.start_element("cac:LineResponse")?;
+            w.start_element("cac:LineReference")?;
+            w.text_element("cbc:LineID", issue.location.as_deref().unwrap_or("NA"))?;
+            w.end_element("cac:LineReference")?;
+            w.start_element("cac:Response")?;
+            w.text_element_with_attrs(
+                "cbc:ResponseCode",
+                MlrCode::Rejected.code(),
+                &[("listID", RESPONSE_CODE_LIST)],
+            )?;
+            w.text_element("cbc:Description", &issue.description)?;
+            w.start_element("cac:Status")?;
+            w.text_element_with_attrs(
+                "cbc:StatusReasonCode",
+                issue.kind.code(),
+                &[("listID", ISSUE_CODE_LIST)],
+            )?;
+            w.end_element("cac:Status")?;
+            w.end_element("cac:Response")?;
+            w.end_element("cac:LineResponse")?;
+        }
+        w.end_element("cac:DocumentResponse")?;
+        w.end_element("ApplicationResponse")?;
+        w.into_string()
+    }
+
+    /// Parse a Message Level Response.
+    ///
+    /// Only the document structure is checked; use [`validate_mlr`] for the
+    /// business rules.
+    pub fn from_xml(xml: &str) -> Result<Self, RechnungError> {
+        let p = Parsed::parse(xml, MLR_CUSTOMIZATION_ID)?;
+        let code = p.response_code.as_deref().unwrap_or_default();
+        let code = MlrCode::from_code(code)
+            .ok_or_else(|| RechnungError::Xml(format!("unknown response code '{code}'")))?;
+        let issues = p
+            .lines
+            .into_iter()
+            .map(|line| {
+                let kind = line.reason.unwrap_or_default();
+                Ok(MlrIssue {
+                    location: line.line_id.filter(|id| id != "NA"),
+                    kind: IssueKind::from_code(&kind).ok_or_else(|| {
+                        RechnungError::Xml(format!("unknown issue code '{kind}'"))
+                    })?,
+                    description: line.description.unwrap_or_default(),
+                })
+            })
+            .collect::<Result<_, RechnungError>>()?;
+        Ok(Self {
+            id: required(p.id, "ID")?,
+            issue_date: date(&required(p.issue_date, "IssueDate")?)?,
+            sender: p.sender.into_party("SenderParty")?,
+            receiver: p.receiver.into_party("ReceiverParty")?,
+            code,
+            description: p.description,
+            message_id: required(p.document_id, "DocumentReference/ID")?,
+            issues,
+        })
+    }
+
+    /// Wrap the response in an SBDH envelope for sending; `country_c1` is
+    /// the sender's country.
+    pub fn wrap(&self, country_c1: &str) -> Result<String, RechnungError> {
+        envelope(
+            &self.sender,
+            &self.receiver,
+            PEPPOL_MLR_DOCTYPE,
+            MLR_PROFILE_ID,
+            country_c1,
+        )
+        .wrap_payload(&self.to_xml()?)
+    }
+}
+
+/// Validate an Invoice Response against the Peppol BIS Invoice Response 3.0
+/// rules.
+///
+/// Returns a list of validation errors. An empty list means the response
+/// can be sent.
+pub fn validate_invoice_response(response: &InvoiceResponse) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if response.id.trim().is_empty() {
+        errors.push(ValidationError::new("id", "response ID must not be empty"));
+    }
+    for (field, party) in [
+        ("sender", &response.sender),
+        ("receiver", &response.receiver),
+    ] {
+        validate_party(&mut errors, field, party);
+        if party.name.as_deref().is_none_or(|n| n.trim().is_empty()) {
+            errors.push(ValidationError::new(
+                format!("{field}.name"),
+                "party legal name is required",
+            ));
+        }
+    }
+    if response.invoice_number.trim().is_empty() {
+        errors.push(ValidationError::new(
+            "invoice_number",
+            "the referenced invoice number must not be empty",
+        ));
+    }
+    if let Some(date) = response.invoice_issue_date {
+        if date > response.issue_date {
+            errors.push(ValidationError::new(
+                "invoice_issue_date",
+                "the referenced invoice is issued after the response",
+            ));
+        }
+    }
+
+    let status = response.status;
+    let reasons = response.clarifications.iter().filter_map(|c| match c {
+        Clarification::Reason { code, .. } => Some(*code),
+        Clarification::Action { .. } => None,
+    });
+    if status.requires_reason() && !reasons.clone().any(|code| code != StatusReason::NoIssue) {
+        errors.push(ValidationError::new(
+            "clarifications",
+            format!(
+                "status {} requires a clarification reason other than NON",
+                status.code()
+            ),
+        ));
+    }
+    for reason in reasons {
+        if reason == StatusReason::PartiallyPaid && status != InvoiceStatus::Paid {
+            errors.push(ValidationError::new(
+                "clarifications",
+                "reason PPD (partially paid) is only allowed with status PD",
+            ));
+        }
+    }
+    if !status.requires_reason()
+        && response
+            .clarifications
+            .iter()
+            .any(|c| matches!(c, Clarification::Action { .. }))
+    {
+        errors.push(ValidationError::new(
+            "clarifications",
+            format!(
+                "actions may only be requested with status UQ, CA or RE, not {}",
+                status.code()
+            ),
+        ));
+    }
+    errors
+}
+
+/// Validate a Message Level Response against the Peppol BIS MLR 3.0 rules.
+///
+/// Returns a list of validation errors. An empty list means the response
+/// can be sent.
+pub fn validate_mlr(response: &MessageLevelResponse) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if response.id.trim().is_empty() {
+        errors.push(ValidationError::new("id", "response ID must not be empty"));
+    }
+    validate_party(&mut errors, "sender", &response.sender);
+    validate_party(&mut errors, "receiver", &response.receiver);
+    if response.message_id.trim().is_empty() {
+        errors.push(ValidationError::new(
+            "message_id",
+            "the referenced message identifier must not be empty",
+        ));
+    }
+
+    let fatal = response.issues.iter().any(|i| i.kind.is_fatal());
+    match response.code {
+        MlrCode::Rejected if !fatal => errors.push(ValidationError::new(
+            "issues",
+            "a rejection must report at least one fatal issue",
+        )),
+        MlrCode::Accepted if fatal => errors.push(ValidationError::new(
+            "issues",
+            "an accepted message may only report warnings",
+        )),
+        MlrCode::Acknowledged if !response.issues.is_empty() => errors.push(ValidationError::new(
+            "issues",
+            "an acknowledgement must not report issues",
+        )),
+        _ => {}
+    }
+    for (i, issue) in response.issues.iter().enumerate() {
+        if issue.description.trim().is_empty() {
+            errors.push(ValidationError::new(
+                format!("issues[{i}].description"),
+                "issue description must not be empty",
+            ));
+        }
+    }
+    errors
+}
+
+fn validate_party(errors: &mut Vec<ValidationError>, field: &str, party: &ResponseParty) {
+    let Some(eas) = party.endpoint.eas() else {
+        errors.push(ValidationError::new(
+            format!("{field}.endpoint"),
+            format!(
+                "'{}' is not of the form 'EAS:identifier'",
+                party.endpoint.value
+            ),
+        ));
+        return;
+    };
+    let checked = EasScheme::from_code(eas)
+        .ok_or_else(|| format!("'{eas}' is not an EAS code"))
+        .and_then(|scheme| scheme.validate_identifier(party.endpoint.identifier()));
+    if let Err(message) = checked {
+        errors.push(ValidationError::new(
+            format!("{field}.endpoint"),
+            format!("{}: {message}", party.endpoint.value),
+        ));
+    }
+}
+
+fn check(errors: Vec<ValidationError>) -> Result<(), RechnungError> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(RechnungError::Validation(
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
+fn start_document(
+    customization_id: &str,
+    profile_id: &str,
+    id: &str,
+    issue_date: NaiveDate,
+) -> Result<XmlWriter, RechnungError> {
+    let mut w = XmlWriter::new()?;
+    w.start_element_with_attrs(
+        "ApplicationResponse",
+        &[
+            ("xmlns", APPLICATION_RESPONSE_NAMESPACE),
+            ("xmlns:cac", NS_CAC),
+            ("xmlns:cbc", NS_CBC),
+        ],
+    )?;
+    w.text_element("cbc:CustomizationID", customization_id)?;
+    w.text_element("cbc:ProfileID", profile_id)?;
+    w.text_element("cbc:ID", id)?;
+    w.text_element("cbc:IssueDate", &issue_date.to_string())?;
+    Ok(w)
+}
+
+fn write_party(
+    w: &mut XmlWriter,
+    element: &str,
+    party: &ResponseParty,
+) -> Result<(), RechnungError> {
+    w.start_element(element)?;
+    w.text_element_with_attrs(
+        "cbc:EndpointID",
+        party.endpoint.identifier(),
+        &[("schemeID", party.endpoint.eas().unwrap_or_default())],
+    )?;
+    if let Some(name) = &party.name {
+        w.start_element("cac:PartyLegalEntity")?;
+        w.text_element("cbc:RegistrationName", name)?;
+        w.end_element("cac:PartyLegalEntity")?;
+    }
+    w.end_element(element)?;
+    Ok(())
+}
+
+fn envelope(
+    sender: &ResponseParty,
+    receiver: &ResponseParty,
+    document_type_id: &str,
+    process_id: &str,
+    country_c1: &str,
+) -> Sbdh {
+    Sbdh {
+        sender: sender.endpoint.clone(),
+        receiver: receiver.endpoint.clone(),
+        standard: APPLICATION_RESPONSE_NAMESPACE.into(),
+        type_version: "2.1".into(),
+        document_type: "ApplicationResponse".into(),
+        instance_identifier: new_instance_identifier(),
+        created: Utc::now().fixed_offset(),
+        document_type_id: document_type_id.into(),
+        process_id: process_id.into(),
+        country_c1: Some(country_c1.into()),
+    }
+}
+
+fn required(value: Option<String>, element: &str) -> Result<String, RechnungError> {
+    value.ok_or_else(|| RechnungError::Xml(format!("ApplicationResponse has no {element}")))
+}
+
+fn date(text: &str) -> Result<NaiveDate, RechnungError> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| RechnungError::Xml(format!("invalid date '{text}'")))
+}
+
+#[derive(Default)]
+struct ParsedParty {
+    scheme: Option<String>,
+    endpoint: Option<String>,
+    name: Option<String>,
+}
+
+impl ParsedParty {
+    fn into_party(self, element: &str) -> Result<ResponseParty, RechnungError> {
+        let (Some(scheme), Some(endpoint)) = (self.scheme, self.endpoint) else {
+            return Err(RechnungError::Xml(format!(
+                "{element} has no EndpointID with schemeID"
+            )));
+        };
+        Ok(ResponseParty {
+            endpoint: ParticipantId::new(&scheme, &endpoint),
+            name: self.name,
+        })
+    }
+}
+
+#[derive(Default)]
+struct ParsedStatus {
+    list_id: Option<String>,
+    code: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(Default)]
+struct ParsedLine {
+    line_id: Option<String>,
+    description: Option<String>,
+    reason: Option<String>,
+}
+
+/// Fields of either response document, collected by element path.
+#[derive(Default)]
+struct Parsed {
+    id: Option<String>,
+    issue_date: Option<String>,
+    note: Option<String>,
+    sender: ParsedParty,
+    receiver: ParsedParty,
+    response_code: Option<String>,
+    effective_date: Option<String>,
+    description: Option<String>,
+    statuses: Vec<ParsedStatus>,
+    document_id: Option<String>,
+    document_issue_date: Option<String>,
+    document_type_code: Option<String>,
+    lines: Vec<ParsedLine>,
+}
+
+impl Parsed {
+    /// Parse an ApplicationResponse, which must have `customization_id`.
+    fn parse(xml: &str, customization_id: &str) -> Result<Self, RechnungError> {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut path: Vec<String> = Vec::new();
+        let mut attribute = None;
+        let mut customization = None;
+        let mut p = Parsed::default();
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) => {
+                    let name = local_name(e.name().as_ref());
+                    if path.is_empty() && name != "ApplicationResponse" {
+                        return Err(RechnungError::Xml(format!(
+                            "not an ApplicationResponse: root element is {name}"
+                        )));
+                    }
+                    let key: &[u8] = match name.as_str() {
+                        "EndpointID" => b"schemeID",
+                        _ => b"listID",
+                    };
+                    attribute = e
+                        .attributes()
+                        .flatten()
+                        .find(|a| a.key.as_ref() == key)
+                        .map(|a| String::from_utf8_lossy(&a.value).into_owned());
+                    path.push(name);
+                    let p_ref: Vec<&str> = path.iter().map(String::as_str).collect();
+                    match p_ref.as_slice() {
+                        [.., "DocumentResponse", "Response", "Status"] => {
+                            p.statuses.push(ParsedStatus::default())
+                        }
+                        [.., "DocumentResponse", "LineResponse"] => {
+                            p.lines.push(ParsedLine::default())
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::Text(ref e)) => {
+                    let text = e.unescape().unwrap_or_default().to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let p_ref: Vec<&str> = path.iter().map(String::as_str).collect();
+                    match p_ref.as_slice() {
+                        ["ApplicationResponse", "CustomizationID"] => customization = Some(text),
+                        ["ApplicationResponse", "ID"] => p.id = Some(text),
+                        ["ApplicationResponse", "IssueDate"] => p.issue_date = Some(text),
+                        ["ApplicationResponse", "Note"] => p.note = Some(text),
+                        [
+                            "ApplicationResponse",
+                            party @ ("SenderParty" | "ReceiverParty"),
+                            rest @ ..,
+                        ] => {
+                            let party = if *party == "SenderParty" {
+                                &mut p.sender
+                            } else {
+                                &mut p.receiver
+                            };
+                            match rest {
+                                ["EndpointID"] => {
+                                    party.endpoint = Some(text);
+                                    party.scheme = attribute.take();
+                                }
+                                ["PartyLegalEntity", "RegistrationName"] => party.name = Some(text),
+                                _ => {}
+                            }
+                        }
+                        ["ApplicationResponse", "DocumentResponse", rest @ ..] => {
+                            p.handle_response(rest, text, attribute.take())
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Event::End(_)) => {
+                    path.pop();
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(RechnungError::Xml(format!("XML parse error: {e}"))),
+                _ => {}
+            }
+        }
+
+        match customization {
+            Some(c) if c == customization_id => Ok(p),
+            Some(c) => Err(RechnungError::Xml(format!(
+                "CustomizationID '{c}' is not '{customization_id}'"
+            ))),
+            None => Err(RechnungError::Xml(
+                "ApplicationResponse has no CustomizationID".into(),
+            )),
+        }
+    }
+
+    /// Text inside `DocumentResponse`.
+    fn handle_response(&mut self, path: &[&str], text: String, attribute: Option<String>) {
+        match path {
+            ["Response", "ResponseCode"] => self.response_code = Some(text),
+            ["Response", "EffectiveDate"] => self.effective_date = Some(text),
+            ["Response", "Description"] => self.description = Some(text),
+            ["Response", "Status", field] => {
+                if let Some(status) = self.statuses.last_mut() {
+                    match *field {
+                        "StatusReasonCode" => {
+                            status.code = Some(text);
+                            status.list_id = attribute;
+                        }
+                        "StatusReason" => status.text = Some(text),
+                        _ => {}
+                    }
+                }
+            }
+            ["DocumentReference", "ID"] => self.document_id = Some(text),
+            ["DocumentReference", "IssueDate"] => self.document_issue_date = Some(text),
+            ["DocumentReference", "DocumentTypeCode"] => self.document_type_code = Some(text),
+            ["LineResponse", rest @ ..] => {
+                if let Some(line) = self.lines.last_mut() {
+                    match rest {
+                        ["LineReference", "LineID"] => line.line_id = Some(text),
+                        ["Response", "Description"] => line.description = Some(text),
+                        ["Response", "Status", "StatusReasonCode"] => line.reason = Some(text),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Element name without namespace prefix.
+fn local_name(qname: &[u8]) -> String {
+    let name = std::str::from_utf8(qname).unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn party(eas: &str, id: &str, name: Option<&str>) -> ResponseParty {
+        ResponseParty {
+            endpoint: ParticipantId::new(eas, id),
+            name: name.map(str::to_string),
+        }
+    }
+
+    fn response(status: InvoiceStatus) -> InvoiceResponse {
+        InvoiceResponse {
+            id: "IR-1".into(),
+            issue_date: NaiveDate::from_ymd_opt(2024, 6, 20).unwrap(),
+            note: None,
+            sender: party("0204", "991-12345-73", Some("Buyer AG")),
+            receiver: party("9930", "DE123456789", Some("Seller GmbH")),
+            status,
+            effective_date: None,
+            clarifications: Vec::new(),
+            invoice_number: "INV-1".into(),
+            invoice_issue_date: NaiveDate::from_ymd_opt(2024, 6, 15),
+            invoice_type_code: Some(InvoiceTypeCode::Invoice),
+        }
+    }
+
+    #[test]
+    fn status_codes_roundtrip() {
+        for status in [
+            InvoiceStatus::Acknowledged,
+            InvoiceStatus::InProcess,
+            InvoiceStatus::UnderQuery,
+            InvoiceStatus::ConditionallyAccepted,
+            InvoiceStatus::Rejected,
+            InvoiceStatus::Accepted,
+            InvoiceStatus::Paid,
+        ] {
+            assert_eq!(InvoiceStatus::from_code(status.code()), Some(status));
+        }
+        assert_eq!(
+            StatusReason::from_code("PPD"),
+            Some(StatusReason::PartiallyPaid)
+        );
+        assert_eq!(
+            StatusAction::from_code("CNA"),
+            Some(StatusAction::CreditAmount)
+        );
+        assert_eq!(InvoiceStatus::from_code("XX"), None);
+    }
+
+    #[test]
+    fn rejection_needs_reason() {
+        let errors = validate_invoice_response(&response(InvoiceStatus::Rejected));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "clarifications");
+
+        let ok = response(InvoiceStatus::Rejected).reason(StatusReason::Prices, None);
+        assert!(validate_invoice_response(&ok).is_empty());
+        let non = response(InvoiceStatus::Rejected).reason(StatusReason::NoIssue, None);
+        assert_eq!(validate_invoice_response(&non).len(), 1);
+    }
+
+    #[test]
+    fn actions_and_ppd_restricted() {
+        let accepted = response(InvoiceStatus::Accepted).action(StatusAction::NoAction, None);
+        assert_eq!(validate_invoice_response(&accepted).len(), 1);
+        let paid = response(InvoiceStatus::Paid).reason(StatusReason::PartiallyPaid, None);
+        assert!(validate_invoice_response(&paid).is_empty());
+        let ip = response(InvoiceStatus::InProcess).reason(StatusReason::PartiallyPaid, None);
+        assert_eq!(validate_invoice_response(&ip).len(), 1);
+    }
+
+    #[test]
+    fn mlr_code_must_match_issues() {
+        let issue = |kind| MlrIssue {
+            location: None,
+            kind,
+            description: "problem".into(),
+        };
+        let mut mlr = MessageLevelResponse {
+            id: "MLR-1".into(),
+            issue_date: NaiveDate::from_ymd_opt(2024, 6, 20).unwrap(),
+            sender: party("0204", "991-12345-73", None),
+            receiver: party("9930", "DE123456789", None),
+            code: MlrCode::Rejected,
+            description: None,
+            message_id: "abc".into(),
+            issues: vec![issue(IssueKind::BusinessRuleWarning)],
+        };
+        assert_eq!(validate_mlr(&mlr).len(), 1);
+        mlr.code = MlrCode::Accepted;
+        assert!(validate_mlr(&mlr).is_empty());
+        mlr.issues.push(issue(IssueKind::SyntaxViolation));
+        assert_eq!(validate_mlr(&mlr).len(), 1);
+        mlr.code = MlrCode::Acknowledged;
+        assert_eq!(validate_mlr(&mlr).len(), 1);
+    }
+}
@@ -0,0 +1,653 @@
+//! Standard Business Document Header (SBDH) envelope for Peppol transport.
+//!
+//! Every document exchanged between Peppol Access Points is wrapped in an
+//! SBDH carrying the sender and receiver participant identifiers, the
+//! document type and process identifiers used for routing, a unique
+//! instance identifier and the country of the sender (C1). [`wrap`] builds
+//! the envelope around a UBL invoice or credit note; [`unwrap`] splits an
+//! envelope into header and invoice. Both check the header against the
+//! Peppol Envelope Specification 2.0.
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use quick_xml::NsReader;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::ResolveResult;
+use rand_core::{OsRng, RngCore};
+
+use super::{
+    PEPPOL_CREDIT_NOTE_DOCTYPE, PEPPOL_INVOICE_DOCTYPE, PEPPOL_PROFILE_ID,
+    PEPPOL_SELF_BILLING_CREDIT_NOTE_DOCTYPE, PEPPOL_SELF_BILLING_INVOICE_DOCTYPE,
+    PEPPOL_SELF_BILLING_PROFILE_ID,
+};
+use crate::core::{ElectronicAddress, Invoice, RechnungError, ValidationError};
+use crate::xrechnung::xml_utils::XmlWriter;
+
+/// SBDH namespace.
+pub const SBDH_NAMESPACE: &str =
+    "http://www.unece.org/cefact/namespaces/StandardBusinessDocumentHeader";
+
+/// Identifier scheme of Peppol participant identifiers.
+pub const PARTICIPANT_SCHEME: &str = "iso6523-actorid-upis";
+
+/// Identifier scheme of Peppol document type identifiers.
+pub const DOCTYPE_SCHEME: &str = "busdox-docid-qns";
+
+/// Identifier scheme of Peppol process identifiers.
+pub const PROCESS_SCHEME: &str = "cenbii-procid-ubl";
+
+/// UBL 2.1 invoice namespace.
+const UBL_INVOICE_NAMESPACE: &str = "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2";
+
+/// UBL 2.1 credit note namespace.
+const UBL_CREDIT_NOTE_NAMESPACE: &str = "urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2";
+
+/// A Peppol participant identifier, e.g. `iso6523-actorid-upis::0088:4035811991021`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParticipantId {
+    /// Identifier scheme, normally [`PARTICIPANT_SCHEME`].
+    pub scheme: String,
+    /// `EAS:identifier`, e.g. `0204:991-12345-73`.
+    pub value: String,
+}
+
+impl ParticipantId {
+    /// Create an identifier from an EAS code and the identifier within that scheme.
+    pub fn new(eas: &str, id: &str) -> Self {
+        Self {
+            scheme: PARTICIPANT_SCHEME.into(),
+            value: format!("{}:{}", eas.trim(), id.trim()),
+        }
+    }
+
+    /// Parse `scheme::EAS:id` or `EAS:id`.
+    pub fn parse(text: &str) -> Result<Self, RechnungError> {
+        let text = text.trim();
+        let (scheme, value) = text.split_once("::").unwrap_or((PARTICIPANT_SCHEME, text));
+        let id = Self {
+            scheme: scheme.into(),
+            value: value.into(),
+        };
+        if id.eas().is_none() {
+            return Err(RechnungError::Builder(format!(
+                "invalid Peppol participant identifier '{text}'"
+            )));
+        }
+        Ok(id)
+    }
+
+    /// Identifier from an electronic address (BT-34 / BT-49).
+    ///
+    /// Returns `None` for schemes that are not Peppol EAS codes, such as `EM`
+    /// (e-mail).
+    pub fn from_electronic_address(address: &ElectronicAddress) -> Option<Self> {
+        is_eas_code(&address.scheme).then(|| Self::new(&address.scheme, &address.value))
+    }
+
+    /// The four-digit EAS code, if the value is well-formed.
+    pub fn eas(&self) -> Option<&str> {
+        let (eas, id) = self.value.split_once(':')?;
+        (is_eas_code(eas) && !id.trim().is_empty()).then_some(eas)
+    }
+
+    /// The identifier without the EAS code.
+    pub fn identifier(&self) -> &str {
+        self.value.split_once(':').map_or("", |(_, id)| id)
+    }
+}
+
+impl std::fmt::Display for ParticipantId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}", self.scheme, self.value)
+    }
+}
+
+fn is_eas_code(code: &str) -> bool {
+    code.len() == 4 && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Contents of a Standard Business Document Header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbdh {
+    /// Sending participant (C1).
+    pub sender: ParticipantId,
+    /// Receiving participant (C4).
+    pub receiver: ParticipantId,
+    /// Namespace of the payload root element.
+    pub standard: String,
+    /// Syntax version of the payload, `2.1` for UBL.
+    pub type_version: String,
+    /// Local name of the payload root element, e.g. `Invoice`.
+    pub document_type: String,
+    /// Unique identifier of this envelope.
+    pub instance_identifier: String,
+    /// Creation time of the envelope.
+    pub created: DateTime<FixedOffset>,
+    /// Peppol document type identifier (DOCUMENTID scope).
+    pub document_type_id: String,
+    /// Peppol process identifier (PROCESSID scope).
+    pub process_id: String,
+    /// Country code of the sender (COUNTRY_C1 scope).
+    pub country_c1: Option<String>,
+}
+
+impl Sbdh {
+    /// Header for sending `invoice`, with a new instance identifier.
+    ///
+    /// Sender and receiver default to the seller's and buyer's electronic
+    /// address (BT-34 / BT-49); COUNTRY_C1 is the seller's country. A
+    /// self-billed document is sent by its issuer, the buyer, to the seller
+    /// under the BIS Self-Billing document type and process.
+    pub fn for_invoice(
+        invoice: &Invoice,
+        sender: Option<ParticipantId>,
+        receiver: Option<ParticipantId>,
+    ) -> Result<Self, RechnungError> {
+        let party_id = |party: &crate::core::Party, role: &str| {
+            party
+                .electronic_address
+                .as_ref()
+                .and_then(ParticipantId::from_electronic_address)
+                .ok_or_else(|| {
+                    RechnungError::Builder(format!(
+                        "no {role} participant identifier given and the {role}'s electronic \
+                         address is not a Peppol EAS identifier"
+                    ))
+                })
+        };
+        let self_billed = invoice.type_code.is_self_billed();
+        let ((issuer, issuer_role), (recipient, recipient_role)) = if self_billed {
+            ((&invoice.buyer, "buyer"), (&invoice.seller, "seller"))
+        } else {
+            ((&invoice.seller, "seller"), (&invoice.buyer, "buyer"))
+        };
+        let sender = match sender {
+            Some(id) => id,
+            None => party_id(issuer, issuer_role)?,
+        };
+        let receiver = match receiver {
+            Some(id) => id,
+            None => party_id(recipient, recipient_role)?,
+        };
+
+        let (standard, document_type, document_type_id) =
+            match (invoice.type_code.is_credit_note(), self_billed) {
+                (true, false) => (
+                    UBL_CREDIT_NOTE_NAMESPACE,
+                    "CreditNote",
+                    PEPPOL_CREDIT_NOTE_DOCTYPE,
+                ),
+                (true, true) => (
+                    UBL_CREDIT_NOTE_NAMESPACE,
+                    "CreditNote",
+                    PEPPOL_SELF_BILLING_CREDIT_NOTE_DOCTYPE,
+                ),
+                (false, false) => (UBL_INVOICE_NAMESPACE, "Invoice", PEPPOL_INVOICE_DOCTYPE),
+                (false, true) => (
+                    UBL_INVOICE_NAMESPACE,
+                    "Invoice",
+                    PEPPOL_SELF_BILLING_INVOICE_DOCTYPE,
+                ),
+            };
+        let process_id = if self_billed {
+            PEPPOL_SELF_BILLING_PROFILE_ID
+        } else {
+            PEPPOL_PROFILE_ID
+        };
+        Ok(Self {
+            sender,
+            receiver,
+            standard: standard.into(),
+            type_version: "2.1".into(),
+            document_type: document_type.into(),
+            instance_identifier: new_instance_identifier(),
+            created: Utc::now().fixed_offset(),
+            document_type_id: document_type_id.into(),
+            process_id: process_id.into(),
+            country_c1: Some(issuer.address.country_code.clone()),
+        })
+    }
+
+    /// Check the header against the Peppol Envelope Specification.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for (field, id) in [("sender", &self.sender), ("receiver", &self.receiver)] {
+            if id.scheme != PARTICIPANT_SCHEME {
+                errors.push(ValidationError::new(
+                    format!("{field}.scheme"),
+                    format!("identifier authority must be '{PARTICIPANT_SCHEME}'"),
+                ));
+            }
+            if id.eas().is_none() {
+                errors.push(ValidationError::new(
+                    format!("{field}.value"),
+                    format!("'{}' is not of the form 'EAS:identifier'", id.value),
+                ));
+            } else if id.value.len() > 50 {
+                errors.push(ValidationError::new(
+                    format!("{field}.value"),
+                    "participant identifier must not exceed 50 characters",
+                ));
+            }
+        }
+        if self.instance_identifier.trim().is_empty() {
+            errors.push(ValidationError::new(
+                "instance_identifier",
+                "instance identifier must not be empty",
+            ));
+        }
+        if self.type_version != "2.1" {
+            errors.push(ValidationError::new(
+                "type_version",
+                format!("type version must be '2.1', got '{}'", self.type_version),
+            ));
+        }
+        let prefix = format!("{}::{}##", self.standard, self.document_type);
+        if !self.document_type_id.starts_with(&prefix) {
+            errors.push(ValidationError::new(
+                "document_type_id",
+                format!(
+                    "document type identifier '{}' does not match standard and type '{prefix}'",
+                    self.document_type_id
+                ),
+            ));
+        }
+        if self.process_id.trim().is_empty() {
+            errors.push(ValidationError::new(
+                "process_id",
+                "PROCESSID scope must not be empty",
+            ));
+        }
+        match &self.country_c1 {
+            Some(c) if c.len() == 2 && c.chars().all(|c| c.is_ascii_uppercase()) => {}
+            Some(c) => errors.push(ValidationError::new(
+                "country_c1",
+                format!("COUNTRY_C1 '{c}' is not an ISO 3166-1 alpha-2 code"),
+            )),
+            None => errors.push(ValidationError::new(
+                "country_c1",
+                "COUNTRY_C1 scope is mandatory",
+            )),
+        }
+        errors
+    }
+
+    /// Wrap a serialized payload (with or without XML declaration).
+    pub fn wrap_payload(&self, payload: &str) -> Result<String, RechnungError> {
+        check(self)?;
+        let root = payload_root(payload)?;
+        if root.0 != self.document_type || root.1 != self.standard {
+            return Err(RechnungError::Validation(format!(
+                "payload root {{{}}}{} does not match header {{{}}}{}",
+                root.1, root.0, self.standard, self.document_type
+            )));
+        }
+
+        let mut w = XmlWriter::new()?;
+        w.start_element_with_attrs("StandardBusinessDocument", &[("xmlns", SBDH_NAMESPACE)])?;
+        w.start_element("StandardBusinessDocumentHeader")?;
+        w.text_element("HeaderVersion", "1.0")?;
+        for (element, id) in [("Sender", &self.sender), ("Receiver", &self.receiver)] {
+            w.start_element(element)?;
+            w.text_element_with_attrs("Identifier", &id.value, &[("Authority", &id.scheme)])?;
+            w.end_element(element)?;
+        }
+        w.start_element("DocumentIdentification")?;
+        w.text_element("Standard", &self.standard)?;
+        w.text_element("TypeVersion", &self.type_version)?;
+        w.text_element("InstanceIdentifier", &self.instance_identifier)?;
+        w.text_element("Type", &self.document_type)?;
+        w.text_element(
+            "CreationDateAndTime",
+            &self.created.to_rfc3339_opts(SecondsFormat::Secs, true),
+        )?;
+        w.end_element("DocumentIdentification")?;
+        w.start_element("BusinessScope")?;
+        let scopes = [
+            (
+                "DOCUMENTID",
+                self.document_type_id.as_str(),
+                Some(DOCTYPE_SCHEME),
+            ),
+            ("PROCESSID", self.process_id.as_str(), Some(PROCESS_SCHEME)),
+        ];
+        let country = self.country_c1.as_deref().map(|c| ("COUNTRY_C1", c, None));
+        for (kind, value, scheme) in scopes.into_iter().chain(country) {
+            w.start_element("Scope")?;
+            w.text_element("Type", kind)?;
+            w.text_element("InstanceIdentifier", value)?;
+            if let Some(scheme) = scheme {
+                w.text_element("Identifier", scheme)?;
+            }
+            w.end_element("Scope")?;
+        }
+        w.end_element("BusinessScope")?;
+        w.end_element("StandardBusinessDocumentHeader")?;
+
+        let mut xml = w.into_string()?;
+        xml.push('\n');
+        xml.push_str(strip_declaration(payload).trim());
+        xml.push_str("\n</StandardBusinessDocument>\n");
+        Ok(xml)
+    }
+}
+
+/// Wrap an invoice as Peppol BIS 3.0 UBL in an SBDH envelope.
+///
+/// Sender and receiver default to the seller's and buyer's electronic
+/// address, swapped for self-billed documents. Credit notes (381, 261) get
+/// the credit note document type.
+pub fn wrap(
+    invoice: &Invoice,
+    sender: Option<ParticipantId>,
+    receiver: Option<ParticipantId>,
+) -> Result<String, RechnungError> {
+    let header = Sbdh::for_invoice(invoice, sender, receiver)?;
+    header.wrap_payload(&super::to_ubl_xml(invoice)?)
+}
+
+/// Split an SBDH envelope into its header and the contained invoice.
+pub fn unwrap(xml: &str) -> Result<(Sbdh, Invoice), RechnungError> {
+    let (header, payload) = parse_envelope(xml)?;
+    let invoice = super::from_ubl_xml(payload)?;
+    Ok((header, invoice))
+}
+
+/// Parse an SBDH envelope into its header and the raw payload XML.
+///
+/// The header is checked against the Peppol Envelope Specification and
+/// against the payload's root element.
+pub fn parse_envelope(xml: &str) -> Result<(Sbdh, &str), RechnungError> {
+    let mut reader = NsReader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut p = ParsedHeader::default();
+    let mut payload_start = None;
+    let mut root = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if path.len() == 1
+                    && local_name(e.name().as_ref()) != "StandardBusinessDocumentHeader" =>
+            {
+                // Resolved here rather than on the sliced payload, which
+                // loses namespaces declared on the envelope.
+                root = Some(resolve_root(&reader, e));
+                break;
+            }
+            Ok(Event::Start(ref e)) => {
+                let name = local_name(e.name().as_ref());
+                if name == "Identifier"
+                    && matches!(path.last().map(String::as_str), Some("Sender" | "Receiver"))
+                {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"Authority" {
+                            p.authority = Some(String::from_utf8_lossy(&attr.value).into());
+                        }
+                    }
+                }
+                if name == "Scope" {
+                    p.scopes.push(Scope::default());
+                }
+                path.push(name);
+            }
+            Ok(Event::Text(ref e)) => {
+                let text = e.unescape().unwrap_or_default().to_string();
+                if !text.is_empty() {
+                    p.handle_text(&path, text);
+                }
+            }
+            Ok(Event::End(_)) => {
+                let ended = path.pop().unwrap_or_default();
+                if ended == "StandardBusinessDocumentHeader" {
+                    payload_start = Some(reader.buffer_position() as usize);
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(RechnungError::Xml(format!("XML parse error: {e}"))),
+            _ => {}
+        }
+    }
+
+    if path.first().map(String::as_str) != Some("StandardBusinessDocument") {
+        return Err(RechnungError::Xml(
+            "not an SBDH envelope: root element is not StandardBusinessDocument".into(),
+        ));
+    }
+    let start =
+        payload_start.ok_or_else(|| RechnungError::Xml("SBDH envelope has no header".into()))?;
+    let end = xml
+        .rfind("</")
+        .filter(|end| *end > start)
+        .ok_or_else(|| RechnungError::Xml("SBDH envelope has no payload".into()))?;
+    let payload = xml[start..end].trim();
+
+    let (root, namespace) =
+        root.ok_or_else(|| RechnungError::Xml("SBDH envelope has no payload".into()))?;
+
+    let header = p.into_header()?;
+    check(&header)?;
+    if root != header.document_type || namespace != header.standard {
+        return Err(RechnungError::Validation(format!(
+            "payload root {{{namespace}}}{root} does not match header {{{}}}{}",
+            header.standard, header.document_type
+        )));
+    }
+    Ok((header, payload))
+}
+
+fn check(header: &Sbdh) -> Result<(), RechnungError> {
+    let errors = header.validate();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(RechnungError::Validation(
+        errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
+/// Element name without namespace prefix.
+fn local_name(qname: &[u8]) -> String {
+    let name = std::str::from_utf8(qname).unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+fn strip_declaration(xml: &str) -> &str {
+    let xml = xml.trim_start();
+    if xml.starts_with("<?xml") {
+        xml.find("?>").map_or(xml, |end| &xml[end + 2..])
+    } else {
+        xml
+    }
+}
+
+/// Local name and namespace of the payload's root element.
+fn payload_root(payload: &str) -> Result<(String, String), RechnungError> {
+    let mut reader = NsReader::from_str(payload);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                return Ok(resolve_root(&reader, e));
+            }
+            Ok(Event::Eof) => {
+                return Err(RechnungError::Xml("SBDH payload is empty".into()));
+            }
+            Err(e) => return Err(RechnungError::Xml(format!("XML parse error: {e}"))),
+            _ => {}
+        }
+    }
+}
+
+/// Local name and resolved namespace of an element.
+fn resolve_root(reader: &NsReader<&[u8]>, e: &BytesStart) -> (String, String) {
+    let (ns, local) = reader.resolve_element(e.name());
+    let namespace = match ns {
+        ResolveResult::Bound(ns) => String::from_utf8_lossy(ns.as_ref()).into_owned(),
+        _ => String::new(),
+    };
+    (
+        String::from_utf8_lossy(local.as_ref()).into_owned(),
+        namespace,
+    )
+}
+
+/// Random UUID (version 4) for the envelope instance identifier.
+pub(crate) fn new_instance_identifier() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[derive(Default)]
+struct Scope {
+    kind: Option<String>,
+    instance_identifier: Option<String>,
+}
+
+#[derive(Default)]
+struct ParsedHeader {
+    header_version: Option<String>,
+    authority: Option<String>,
+    sender: Option<ParticipantId>,
+    receiver: Option<ParticipantId>,
+    standard: Option<String>,
+    type_version: Option<String>,
+    instance_identifier: Option<String>,
+    document_type: Option<String>,
+    created: Option<String>,
+    scopes: Vec<Scope>,
+}
+
+impl ParsedHeader {
+    fn handle_text(&mut self, path: &[String], text: String) {
+        let leaf = path.last().map(String::as_str).unwrap_or("");
+        let parent = path
+            .len()
+            .checked_sub(2)
+            .map(|i| path[i].as_str())
+            .unwrap_or("");
+        match (parent, leaf) {
+            ("StandardBusinessDocumentHeader", "HeaderVersion") => self.header_version = Some(text),
+            ("Sender" | "Receiver", "Identifier") => {
+                let id = ParticipantId {
+                    scheme: self.authority.take().unwrap_or_default(),
+                    value: text,
+                };
+                if parent == "Sender" {
+                    self.sender = Some(id);
+                } else {
+                    self.receiver = Some(id);
+                }
+            }
+            ("DocumentIdentification", "Standard") => self.standard = Some(text),
+            ("DocumentIdentification", "TypeVersion") => self.type_version = Some(text),
+            ("DocumentIdentification", "InstanceIdentifier") => {
+                self.instance_identifier = Some(text);
+            }
+            ("DocumentIdentification", "Type") => self.document_type = Some(text),
+            ("DocumentIdentification", "CreationDateAndTime") => self.created = Some(text),
+            ("Scope", "Type") => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.kind = Some(text);
+                }
+            }
+            ("Scope", "InstanceIdentifier") => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.instance_identifier = Some(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn into_header(self) -> Result<Sbdh, RechnungError> {
+        let missing = |what: &str| RechnungError::Xml(format!("SBDH without {what}"));
+        if self.header_version.as_deref() != Some("1.0") {
+            return Err(RechnungError::Validation(format!(
+                "SBDH header version must be 1.0, got {:?}",
+                self.header_version
+            )));
+        }
+        let created = self.created.ok_or_else(|| missing("CreationDateAndTime"))?;
+        let created = DateTime::parse_from_rfc3339(&created).map_err(|e| {
+            RechnungError::Validation(format!(
+                "CreationDateAndTime '{created}' must be a date-time with time zone: {e}"
+            ))
+        })?;
+        let scope = |kind: &str| -> Result<Option<String>, RechnungError> {
+            let mut found = self
+                .scopes
+                .iter()
+                .filter(|s| s.kind.as_deref() == Some(kind));
+            let first = found.next().and_then(|s| s.instance_identifier.clone());
+            if found.next().is_some() {
+                return Err(RechnungError::Validation(format!(
+                    "SBDH contains more than one {kind} scope"
+                )));
+            }
+            Ok(first)
+        };
+        Ok(Sbdh {
+            document_type_id: scope("DOCUMENTID")?.ok_or_else(|| missing("DOCUMENTID scope"))?,
+            process_id: scope("PROCESSID")?.ok_or_else(|| missing("PROCESSID scope"))?,
+            country_c1: scope("COUNTRY_C1")?,
+            sender: self.sender.ok_or_else(|| missing("Sender"))?,
+            receiver: self.receiver.ok_or_else(|| missing("Receiver"))?,
+            standard: self.standard.ok_or_else(|| missing("Standard"))?,
+            type_version: self.type_version.ok_or_else(|| missing("TypeVersion"))?,
+            instance_identifier: self
+                .instance_identifier
+                .ok_or_else(|| missing("InstanceIdentifier"))?,
+            document_type: self.document_type.ok_or_else(|| missing("Type"))?,
+            created,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn participant_ids() {
+        let id = ParticipantId::parse("iso6523-actorid-upis::0088:4035811991021").unwrap();
+        assert_eq!(id.eas(), Some("0088"));
+        assert_eq!(id.identifier(), "4035811991021");
+        assert_eq!(id, ParticipantId::parse("0088:4035811991021").unwrap());
+        assert_eq!(id.to_string(), "iso6523-actorid-upis::0088:4035811991021");
+        assert!(ParticipantId::parse("EM:info@example.com").is_err());
+        assert!(ParticipantId::parse("0088:").is_err());
+    }
+
+    #[test]
+    fn instance_identifier_is_uuid_v4() {
+        let id = new_instance_identifier();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(id, new_instance_identifier());
+    }
+
+    #[test]
+    fn declaration_stripped() {
+        assert_eq!(
+            strip_declaration("<?xml version=\"1.0\"?>\n<Invoice/>").trim(),
+            "<Invoice/>"
+        );
+        assert_eq!(strip_declaration("<Invoice/>"), "<Invoice/>");
+    }
+}
@@ -0,0 +1,105 @@
+//! Summary of the X.509 endpoint certificates published in SMP metadata.
+
+use chrono::NaiveDateTime;
+use sha2::{Digest, Sha256};
+
+use super::SmpError;
+use crate::core::der::{distinguished_name, parse_time, read_any, read_tlv};
+use crate::core::digest::{base64_decode, hex};
+
+/// Subject, issuer and validity of an endpoint certificate.
+///
+/// Only the fields needed to identify and check the certificate are read;
+/// the certificate chain and signature are not verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// Subject distinguished name, e.g. `CN=POP000123,O=ACME GmbH,C=DE`.
+    pub subject: String,
+    /// Issuer distinguished name.
+    pub issuer: String,
+    /// Serial number in hexadecimal.
+    pub serial: String,
+    /// Start of validity (UTC).
+    pub not_before: NaiveDateTime,
+    /// End of validity (UTC).
+    pub not_after: NaiveDateTime,
+    /// SHA-256 fingerprint of the DER encoding, in hexadecimal.
+    pub sha256_fingerprint: String,
+    /// The DER encoding.
+    pub der: Vec<u8>,
+}
+
+impl CertificateInfo {
+    /// Read a Base64-encoded DER certificate, as in the SMP `Certificate` element.
+    pub fn from_base64(text: &str) -> Result<Self, SmpError> {
+        let der = base64_decode(text)
+            .ok_or_else(|| SmpError::Parse("certificate is not valid Base64".into()))?;
+        Self::from_der(&der)
+    }
+
+    /// Read a PEM certificate (`-----BEGIN CERTIFICATE-----`).
+    pub fn from_pem(pem: &str) -> Result<Self, SmpError> {
+        let body = pem
+            .split_once("-----BEGIN CERTIFICATE-----")
+            .and_then(|(_, rest)| rest.split_once("-----END CERTIFICATE-----"))
+            .map(|(body, _)| body)
+            .ok_or_else(|| SmpError::Parse("no PEM certificate found".into()))?;
+        Self::from_base64(body)
+    }
+
+    /// Read a DER-encoded certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self, SmpError> {
+        let bad = || SmpError::Parse("certificate is not a DER-encoded X.509 certificate".into());
+        let (cert, _) = read_tlv(der, 0x30).ok_or_else(bad)?;
+        let (tbs, _) = read_tlv(cert, 0x30).ok_or_else(bad)?;
+
+        let mut rest = tbs;
+        if rest.first() == Some(&0xa0) {
+            rest = read_any(rest).ok_or_else(bad)?.2;
+        }
+        let (serial, rest) = read_tlv(rest, 0x02).ok_or_else(bad)?;
+        let (_, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+        let (issuer, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+        let (validity, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+        let (subject, _) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+
+        let (tag, not_before, after) = read_any(validity).ok_or_else(bad)?;
+        let not_before = parse_time(tag, not_before).ok_or_else(bad)?;
+        let (tag, not_after, _) = read_any(after).ok_or_else(bad)?;
+        let not_after = parse_time(tag, not_after).ok_or_else(bad)?;
+
+        Ok(Self {
+            subject: distinguished_name(subject).ok_or_else(bad)?,
+            issuer: distinguished_name(issuer).ok_or_else(bad)?,
+            serial: hex(serial.strip_prefix(&[0]).unwrap_or(serial)),
+            not_before,
+            not_after,
+            sha256_fingerprint: hex(&Sha256::digest(der)),
+            der: der.to_vec(),
+        })
+    }
+
+    /// Common name (CN) of the subject, e.g. the Peppol AP seat ID.
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .split(',')
+            .find_map(|rdn| rdn.trim().strip_prefix("CN="))
+    }
+
+    /// Whether `at` lies within the validity period.
+    pub fn is_valid_at(&self, at: NaiveDateTime) -> bool {
+        self.not_before <= at && at <= self.not_after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(CertificateInfo::from_der(&[0x30, 0x03, 0x02, 0x01, 0x01]).is_err());
+        assert!(CertificateInfo::from_base64("not base64!").is_err());
+        assert!(CertificateInfo::from_pem("MIIB").is_err());
+    }
+}
@@ -0,0 +1,329 @@
+//! Parsing of SMP `ServiceGroup` and `SignedServiceMetadata` documents
+//! (Peppol SMP specification 1.x, BusDox namespace).
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+use super::certificate::CertificateInfo;
+use super::{SmpError, percent_decode};
+use crate::peppol::sbdh::ParticipantId;
+
+/// Reference from a service group to the metadata of one document type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceReference {
+    /// URL of the service metadata.
+    pub href: String,
+    /// Document type identifier with scheme, e.g. `busdox-docid-qns::urn:oasis:…`.
+    pub document_type: String,
+}
+
+/// The document types a participant can receive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceGroup {
+    /// The participant.
+    pub participant: Option<ParticipantId>,
+    /// One reference per document type.
+    pub services: Vec<ServiceReference>,
+}
+
+/// An Access Point endpoint for a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    /// Transport profile, e.g. `peppol-transport-as4-v2_0`.
+    pub transport_profile: String,
+    /// Endpoint URL.
+    pub address: String,
+    /// Whether the receiver requires a business-level signature.
+    pub require_business_level_signature: bool,
+    /// Start of validity.
+    pub activation: Option<DateTime<FixedOffset>>,
+    /// End of validity.
+    pub expiration: Option<DateTime<FixedOffset>>,
+    /// Access Point certificate.
+    pub certificate: Option<CertificateInfo>,
+    /// Free-text description.
+    pub description: Option<String>,
+    /// Technical contact (URL or e-mail).
+    pub technical_contact: Option<String>,
+}
+
+/// A process and the endpoints serving it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// Process identifier with scheme, e.g. `cenbii-procid-ubl::urn:fdc:peppol.eu:2017:poacc:billing:01:1.0`.
+    pub process_id: String,
+    /// Endpoints, one per transport profile.
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// Service metadata of one participant and document type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceMetadata {
+    /// The participant.
+    pub participant: Option<ParticipantId>,
+    /// Document type identifier with scheme.
+    pub document_type: String,
+    /// Supported processes.
+    pub processes: Vec<ProcessInfo>,
+    /// Another SMP holding the metadata, if this is a redirect.
+    pub redirect: Option<String>,
+}
+
+impl ServiceMetadata {
+    /// Endpoint for a process (with or without scheme) and transport profile.
+    pub fn endpoint(&self, process_id: &str, transport_profile: &str) -> Option<&Endpoint> {
+        self.processes
+            .iter()
+            .filter(|p| identifier_value(&p.process_id) == identifier_value(process_id))
+            .flat_map(|p| &p.endpoints)
+            .find(|e| e.transport_profile == transport_profile)
+    }
+
+    /// Distinct transport profiles offered.
+    pub fn transport_profiles(&self) -> Vec<&str> {
+        let mut profiles: Vec<&str> = self
+            .processes
+            .iter()
+            .flat_map(|p| &p.endpoints)
+            .map(|e| e.transport_profile.as_str())
+            .collect();
+        profiles.sort_unstable();
+        profiles.dedup();
+        profiles
+    }
+}
+
+/// Identifier without its `scheme::` prefix.
+///
+/// Document type values contain `::` themselves (`…Invoice-2::Invoice##…`),
+/// so only a colon-free prefix counts as a scheme.
+pub(crate) fn identifier_value(id: &str) -> &str {
+    match id.split_once("::") {
+        Some((scheme, value)) if !scheme.contains(':') => value,
+        _ => id,
+    }
+}
+
+fn local_name(qname: &[u8]) -> String {
+    let name = std::str::from_utf8(qname).unwrap_or("");
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name)
+        .map(|a| String::from_utf8_lossy(&a.value).into_owned())
+}
+
+fn xml_error(e: quick_xml::Error) -> SmpError {
+    SmpError::Parse(format!("XML parse error: {e}"))
+}
+
+/// Parse an SMP `ServiceGroup` document.
+pub fn parse_service_group(xml: &str) -> Result<ServiceGroup, SmpError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut root = None;
+    let mut in_participant = false;
+    let mut scheme = None;
+    let mut group = ServiceGroup {
+        participant: None,
+        services: Vec::new(),
+    };
+
+    loop {
+        let (e, empty) = match reader.read_event().map_err(xml_error)? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(ref t) if in_participant => {
+                group.participant = Some(ParticipantId {
+                    scheme: scheme.take().unwrap_or_default(),
+                    value: t.unescape().unwrap_or_default().trim().to_string(),
+                });
+                continue;
+            }
+            Event::End(_) => {
+                in_participant = false;
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = local_name(e.name().as_ref());
+        root.get_or_insert_with(|| name.clone());
+        match name.as_str() {
+            "ParticipantIdentifier" => {
+                scheme = attribute(&e, b"scheme");
+                in_participant = !empty;
+            }
+            "ServiceMetadataReference" => {
+                if let Some(href) = attribute(&e, b"href") {
+                    let document_type = percent_decode(&href)
+                        .rsplit_once("/services/")
+                        .map(|(_, doc)| doc.to_string())
+                        .unwrap_or_default();
+                    group.services.push(ServiceReference {
+                        href,
+                        document_type,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if root.as_deref() != Some("ServiceGroup") {
+        return Err(SmpError::Parse("not an SMP ServiceGroup document".into()));
+    }
+    Ok(group)
+}
+
+/// Parse an SMP `SignedServiceMetadata` (or unsigned `ServiceMetadata`) document.
+///
+/// The XML signature is not verified.
+pub fn parse_service_metadata(xml: &str) -> Result<ServiceMetadata, SmpError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut path: Vec<String> = Vec::new();
+    let mut scheme = None;
+    let mut metadata = ServiceMetadata {
+        participant: None,
+        document_type: String::new(),
+        processes: Vec::new(),
+        redirect: None,
+    };
+    let mut endpoint: Option<Endpoint> = None;
+    let mut root = None;
+
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(ref e) => {
+                let name = local_name(e.name().as_ref());
+                root.get_or_insert_with(|| name.clone());
+                match name.as_str() {
+                    "ParticipantIdentifier" | "DocumentIdentifier" | "ProcessIdentifier" => {
+                        scheme = attribute(e, b"scheme");
+                    }
+                    "Process" => metadata.processes.push(ProcessInfo {
+                        process_id: String::new(),
+                        endpoints: Vec::new(),
+                    }),
+                    "Endpoint" => endpoint = Some(new_endpoint(e)),
+                    "Redirect" => metadata.redirect = attribute(e, b"href"),
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Event::Empty(ref e) => {
+                let name = local_name(e.name().as_ref());
+                root.get_or_insert_with(|| name.clone());
+                match name.as_str() {
+                    "Endpoint" => {
+                        if let Some(process) = metadata.processes.last_mut() {
+                            process.endpoints.push(new_endpoint(e));
+                        }
+                    }
+                    "Redirect" => metadata.redirect = attribute(e, b"href"),
+                    _ => {}
+                }
+            }
+            Event::Text(ref t) => {
+                let text = t.unescape().unwrap_or_default().trim().to_string();
+                let leaf = path.last().map(String::as_str).unwrap_or("");
+                let with_scheme = |scheme: &mut Option<String>| match scheme.take() {
+                    Some(s) => format!("{s}::{text}"),
+                    None => text.clone(),
+                };
+                if path.iter().any(|p| p == "Signature") {
+                    continue;
+                }
+                match leaf {
+                    "ParticipantIdentifier" => {
+                        metadata.participant = Some(ParticipantId {
+                            scheme: scheme.take().unwrap_or_default(),
+                            value: text,
+                        });
+                    }
+                    "DocumentIdentifier" => metadata.document_type = with_scheme(&mut scheme),
+                    "ProcessIdentifier" => {
+                        if let Some(process) = metadata.processes.last_mut() {
+                            process.process_id = with_scheme(&mut scheme);
+                        }
+                    }
+                    _ => {
+                        if let Some(ep) = endpoint.as_mut() {
+                            fill_endpoint(ep, leaf, text)?;
+                        }
+                    }
+                }
+            }
+            Event::End(_) => {
+                let closed = path.pop();
+                if closed.as_deref() == Some("Endpoint") {
+                    if let (Some(ep), Some(process)) =
+                        (endpoint.take(), metadata.processes.last_mut())
+                    {
+                        process.endpoints.push(ep);
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !matches!(
+        root.as_deref(),
+        Some("SignedServiceMetadata" | "ServiceMetadata")
+    ) {
+        return Err(SmpError::Parse(
+            "not an SMP SignedServiceMetadata document".into(),
+        ));
+    }
+    if metadata.redirect.is_none() && metadata.document_type.is_empty() {
+        return Err(SmpError::Parse(
+            "service metadata without DocumentIdentifier".into(),
+        ));
+    }
+    Ok(metadata)
+}
+
+fn new_endpoint(e: &BytesStart) -> Endpoint {
+    Endpoint {
+        transport_profile: attribute(e, b"transportProfile").unwrap_or_default(),
+        address: String::new(),
+        require_business_level_signature: false,
+        activation: None,
+        expiration: None,
+        certificate: None,
+        description: None,
+        technical_contact: None,
+    }
+}
+
+fn fill_endpoint(ep: &mut Endpoint, leaf: &str, text: String) -> Result<(), SmpError> {
+    match leaf {
+        "Address" => ep.address = text,
+        "RequireBusinessLevelSignature" => {
+            ep.require_business_level_signature = text == "true" || text == "1";
+        }
+        "ServiceActivationDate" => ep.activation = parse_datetime(&text),
+        "ServiceExpirationDate" => ep.expiration = parse_datetime(&text),
+        "Certificate" => ep.certificate = Some(CertificateInfo::from_base64(&text)?),
+        "ServiceDescription" => ep.description = Some(text),
+        "TechnicalContactUrl" | "TechnicalInformationUrl" if ep.technical_contact.is_none() => {
+            ep.technical_contact = Some(text);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parse an `xs:dateTime`; values without time zone are taken as UTC.
+fn parse_datetime(text: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(text).ok().or_else(|| {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(|dt| dt.and_utc().fixed_offset())
+    })
+}
@@ -0,0 +1,378 @@
+//! Peppol participant lookup via SML and SMP.
+//!
+//! Finding out where to send a document takes two steps:
+//!
+//! 1. **SML** — the participant identifier is hashed into a DNS name in the
+//!    SML zone ([`bdxl_name`]); its NAPTR record (service `Meta:SMP`) points
+//!    to the participant's Service Metadata Publisher.
+//! 2. **SMP** — the `ServiceGroup` lists the document types the participant
+//!    accepts, and the `SignedServiceMetadata` of each document type names
+//!    the processes, transport profiles, Access Point URLs and certificates.
+//!
+//! DNS and HTTP are abstracted by [`DnsResolver`] and [`HttpClient`];
+//! [`UdpDnsResolver`] implements DNS on the standard library,
+//! [`StdHttpClient`] HTTP and HTTPS on `reqwest`.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::peppol::{ParticipantId, PEPPOL_INVOICE_DOCTYPE};
+//! use faktura::peppol::smp::SmpClient;
+//!
+//! let client = SmpClient::system()?;
+//! let buyer = ParticipantId::new("0204", "991-12345-73");
+//! if let Some(info) = client.lookup(&buyer)? {
+//!     println!("accepts invoices: {}", info.supports(PEPPOL_INVOICE_DOCTYPE));
+//! }
+//! ```
+
+mod certificate;
+mod metadata;
+mod transport;
+
+pub use certificate::CertificateInfo;
+pub use metadata::{
+    Endpoint, ProcessInfo, ServiceGroup, ServiceMetadata, ServiceReference, parse_service_group,
+    parse_service_metadata,
+};
+pub use transport::{
+    DnsResolver, HttpClient, HttpResponse, NaptrRecord, StdHttpClient, UdpDnsResolver,
+};
+
+use std::fmt;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use super::sbdh::{DOCTYPE_SCHEME, ParticipantId};
+use crate::core::digest::{base32, hex};
+use metadata::identifier_value;
+
+/// DNS zone of the Peppol production SML.
+pub const SML_ZONE: &str = "edelivery.tech.ec.europa.eu";
+
+/// DNS zone of the Peppol test SML (SMK).
+pub const SMK_ZONE: &str = "acc.edelivery.tech.ec.europa.eu";
+
+/// Transport profile of Peppol AS4.
+pub const TRANSPORT_AS4: &str = "peppol-transport-as4-v2_0";
+
+/// NAPTR service of SMP records.
+const NAPTR_SERVICE: &str = "Meta:SMP";
+
+/// Error from an SML or SMP lookup.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SmpError {
+    /// DNS resolution failed.
+    Dns(String),
+    /// Network or HTTP error.
+    Network(String),
+    /// The SMP has no entry for the requested participant or document type.
+    NotFound(String),
+    /// Failed to parse a response.
+    Parse(String),
+}
+
+impl fmt::Display for SmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(e) => write!(f, "SML DNS error: {e}"),
+            Self::Network(e) => write!(f, "SMP network error: {e}"),
+            Self::NotFound(e) => write!(f, "SMP entry not found: {e}"),
+            Self::Parse(e) => write!(f, "SMP parse error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SmpError {}
+
+/// DNS name of the participant's NAPTR record (Peppol SML 2.0 / BDXL):
+/// the unpadded Base32 SHA-256 of the lower-cased identifier value, followed
+/// by the scheme and the SML zone.
+pub fn bdxl_name(participant: &ParticipantId, zone: &str) -> String {
+    let hash = base32(&Sha256::digest(participant.value.to_lowercase().as_bytes()));
+    format!(
+        "{}.{}.{}",
+        hash.to_lowercase(),
+        participant.scheme,
+        zone.trim_end_matches('.')
+    )
+}
+
+/// Legacy SML host name (`B-` + MD5 of the lower-cased identifier value),
+/// which resolves directly to the SMP host.
+pub fn sml_host(participant: &ParticipantId, zone: &str) -> String {
+    format!(
+        "B-{}.{}.{}",
+        hex(&Md5::digest(participant.value.to_lowercase().as_bytes())),
+        participant.scheme,
+        zone.trim_end_matches('.')
+    )
+}
+
+/// What a participant's SMP publishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParticipantInfo {
+    /// The participant.
+    pub participant: ParticipantId,
+    /// Base URL of its SMP.
+    pub smp_url: String,
+    /// Service metadata for every document type in the service group.
+    pub services: Vec<ServiceMetadata>,
+}
+
+impl ParticipantInfo {
+    /// Document type identifiers accepted, with scheme.
+    pub fn document_types(&self) -> Vec<&str> {
+        self.services
+            .iter()
+            .map(|s| s.document_type.as_str())
+            .collect()
+    }
+
+    /// Whether a document type (with or without scheme) is accepted.
+    pub fn supports(&self, document_type: &str) -> bool {
+        self.service(document_type).is_some()
+    }
+
+    /// Metadata of a document type (with or without scheme).
+    pub fn service(&self, document_type: &str) -> Option<&ServiceMetadata> {
+        let wanted = identifier_value(document_type);
+        self.services
+            .iter()
+            .find(|s| identifier_value(&s.document_type) == wanted)
+    }
+
+    /// Endpoint for a document type, process and transport profile.
+    pub fn endpoint(
+        &self,
+        document_type: &str,
+        process_id: &str,
+        transport_profile: &str,
+    ) -> Option<&Endpoint> {
+        self.service(document_type)?
+            .endpoint(process_id, transport_profile)
+    }
+}
+
+/// SML/SMP lookup client.
+#[derive(Debug, Clone)]
+pub struct SmpClient<D, H> {
+    dns: D,
+    http: H,
+    zone: String,
+}
+
+impl SmpClient<UdpDnsResolver, StdHttpClient> {
+    /// Client for the production SML using the system name server.
+    pub fn system() -> Result<Self, SmpError> {
+        Ok(Self::new(UdpDnsResolver::system()?, StdHttpClient::new()))
+    }
+}
+
+impl<D: DnsResolver, H: HttpClient> SmpClient<D, H> {
+    /// Client for the production SML.
+    pub fn new(dns: D, http: H) -> Self {
+        Self {
+            dns,
+            http,
+            zone: SML_ZONE.into(),
+        }
+    }
+
+    /// Use another SML zone, e.g. [`SMK_ZONE`] for testing.
+    pub fn with_zone(mut self, zone: impl Into<String>) -> Self {
+        self.zone = zone.into();
+        self
+    }
+
+    /// Base URL of the participant's SMP, or `None` if it is not registered.
+    pub fn smp_url(&self, participant: &ParticipantId) -> Result<Option<String>, SmpError> {
+        let mut records: Vec<NaptrRecord> = self
+            .dns
+            .naptr(&bdxl_name(participant, &self.zone))?
+            .into_iter()
+            .filter(|r| r.service == NAPTR_SERVICE && r.flags.eq_ignore_ascii_case("U"))
+            .collect();
+        records.sort_by_key(|r| (r.order, r.preference));
+        records
+            .first()
+            .map(|r| apply_naptr_regexp(&r.regexp))
+            .transpose()
+    }
+
+    /// Whether the participant is registered in the SML.
+    pub fn is_registered(&self, participant: &ParticipantId) -> Result<bool, SmpError> {
+        Ok(self.smp_url(participant)?.is_some())
+    }
+
+    /// Fetch the participant's service group.
+    pub fn service_group(&self, participant: &ParticipantId) -> Result<ServiceGroup, SmpError> {
+        let smp = self.require_smp(participant)?;
+        let url = format!("{smp}/{}", percent_encode(&participant.to_string()));
+        parse_service_group(&self.fetch(&url)?)
+    }
+
+    /// Fetch the service metadata for one document type, following a redirect.
+    ///
+    /// `document_type` may be given with or without the `busdox-docid-qns::` scheme.
+    pub fn service_metadata(
+        &self,
+        participant: &ParticipantId,
+        document_type: &str,
+    ) -> Result<ServiceMetadata, SmpError> {
+        let smp = self.require_smp(participant)?;
+        let document_type = if identifier_value(document_type) != document_type {
+            document_type.to_string()
+        } else {
+            format!("{DOCTYPE_SCHEME}::{document_type}")
+        };
+        let url = format!(
+            "{smp}/{}/services/{}",
+            percent_encode(&participant.to_string()),
+            percent_encode(&document_type)
+        );
+        self.metadata_at(&url)
+    }
+
+    /// Look up a participant: its SMP, service group and all service metadata.
+    ///
+    /// Returns `None` if the participant is not registered.
+    pub fn lookup(&self, participant: &ParticipantId) -> Result<Option<ParticipantInfo>, SmpError> {
+        let Some(smp_url) = self.smp_url(participant)? else {
+            return Ok(None);
+        };
+        let group = self.service_group(participant)?;
+        let services = group
+            .services
+            .iter()
+            .map(|reference| self.metadata_at(&reference.href))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(ParticipantInfo {
+            participant: participant.clone(),
+            smp_url,
+            services,
+        }))
+    }
+
+    fn require_smp(&self, participant: &ParticipantId) -> Result<String, SmpError> {
+        self.smp_url(participant)?.ok_or_else(|| {
+            SmpError::NotFound(format!("{participant} is not registered in the SML"))
+        })
+    }
+
+    fn metadata_at(&self, url: &str) -> Result<ServiceMetadata, SmpError> {
+        let metadata = parse_service_metadata(&self.fetch(url)?)?;
+        match &metadata.redirect {
+            Some(target) => {
+                let redirected = parse_service_metadata(&self.fetch(target)?)?;
+                if redirected.redirect.is_some() {
+                    return Err(SmpError::Parse(format!(
+                        "{url}: SMP redirect points to another redirect"
+                    )));
+                }
+                Ok(redirected)
+            }
+            None => Ok(metadata),
+        }
+    }
+
+    fn fetch(&self, url: &str) -> Result<String, SmpError> {
+        let response = self.http.get(url)?;
+        match response.status {
+            200 => Ok(response.body),
+            404 => Err(SmpError::NotFound(url.to_string())),
+            status => Err(SmpError::Network(format!("{url}: HTTP {status}"))),
+        }
+    }
+}
+
+/// Apply a Peppol NAPTR rule of the form `!^.*$!http://smp.example.com!`.
+fn apply_naptr_regexp(regexp: &str) -> Result<String, SmpError> {
+    let invalid = || SmpError::Dns(format!("unsupported NAPTR regexp '{regexp}'"));
+    let delimiter = regexp.chars().next().ok_or_else(invalid)?;
+    let parts: Vec<&str> = regexp[delimiter.len_utf8()..].split(delimiter).collect();
+    match parts.as_slice() {
+        ["^.*$", replacement, flags] if flags.is_empty() && !replacement.is_empty() => {
+            Ok(replacement.trim_end_matches('/').to_string())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters.
+pub(crate) fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes.
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dns_names() {
+        let id = ParticipantId::new("0088", "4035811991021");
+        let name = bdxl_name(&id, SML_ZONE);
+        assert!(name.ends_with(".iso6523-actorid-upis.edelivery.tech.ec.europa.eu"));
+        // SHA-256 is 52 Base32 characters without padding
+        assert_eq!(name.split('.').next().unwrap().len(), 52);
+        assert_eq!(
+            name,
+            bdxl_name(&ParticipantId::new("0088", "4035811991021"), SML_ZONE)
+        );
+        assert_eq!(
+            bdxl_name(&ParticipantId::new("9930", "DE123456789"), SMK_ZONE),
+            bdxl_name(&ParticipantId::new("9930", "de123456789"), SMK_ZONE),
+        );
+        assert!(sml_host(&id, SML_ZONE).starts_with("B-"));
+    }
+
+    #[test]
+    fn naptr_regexp() {
+        assert_eq!(
+            apply_naptr_regexp("!^.*$!http://smp.example.org/!").unwrap(),
+            "http://smp.example.org"
+        );
+        assert!(apply_naptr_regexp("!^(.*)$!http://\\1!").is_err());
+    }
+
+    #[test]
+    fn percent_coding() {
+        let id = "iso6523-actorid-upis::0088:123";
+        assert_eq!(percent_encode(id), "iso6523-actorid-upis%3A%3A0088%3A123");
+        assert_eq!(percent_decode(&percent_encode(id)), id);
+        assert_eq!(percent_decode("a%23b%2"), "a#b%2");
+    }
+}
@@ -0,0 +1,310 @@
+//! DNS and HTTP access for SMP lookups.
+//!
+//! [`DnsResolver`] and [`HttpClient`] are the seams of the client: the
+//! standard-library implementations below speak plain DNS over UDP and
+//! HTTP/1.1 over TCP, and can be replaced by any other resolver or HTTP
+//! stack (or a fixed stand-in in tests).
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use super::SmpError;
+use crate::core::http::{self, RawResponse};
+
+/// A DNS NAPTR record (RFC 3403).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaptrRecord {
+    /// Processing order, lowest first.
+    pub order: u16,
+    /// Preference among records of equal order, lowest first.
+    pub preference: u16,
+    /// Flags, `U` for a terminal URI rule.
+    pub flags: String,
+    /// Service, `Meta:SMP` for Peppol.
+    pub service: String,
+    /// Substitution expression, e.g. `!^.*$!http://smp.example.com!`.
+    pub regexp: String,
+    /// Replacement domain, `.` if unused.
+    pub replacement: String,
+}
+
+/// Resolves NAPTR records.
+pub trait DnsResolver {
+    /// NAPTR records of `name`; an empty list if the name does not exist.
+    fn naptr(&self, name: &str) -> Result<Vec<NaptrRecord>, SmpError>;
+}
+
+/// An HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code.
+    pub status: u16,
+    /// Response body.
+    pub body: String,
+}
+
+/// Performs HTTP GET requests.
+pub trait HttpClient {
+    /// Fetch `url`.
+    fn get(&self, url: &str) -> Result<HttpResponse, SmpError>;
+}
+
+/// DNS type code of NAPTR records.
+const TYPE_NAPTR: u16 = 35;
+
+/// Plain DNS resolver querying one name server over UDP.
+#[derive(Debug, Clone)]
+pub struct UdpDnsResolver {
+    server: SocketAddr,
+    timeout: Duration,
+}
+
+impl UdpDnsResolver {
+    /// Resolver using the given name server.
+    pub fn new(server: SocketAddr) -> Self {
+        Self {
+            server,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Resolver using the first name server in `/etc/resolv.conf`.
+    pub fn system() -> Result<Self, SmpError> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf")
+            .map_err(|e| SmpError::Dns(format!("cannot read /etc/resolv.conf: {e}")))?;
+        conf.lines()
+            .filter_map(|l| l.trim().strip_prefix("nameserver"))
+            .filter_map(|addr| addr.trim().parse::<std::net::IpAddr>().ok())
+            .map(|ip| Self::new(SocketAddr::new(ip, 53)))
+            .next()
+            .ok_or_else(|| SmpError::Dns("no name server in /etc/resolv.conf".into()))
+    }
+
+    /// Set the query timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl DnsResolver for UdpDnsResolver {
+    fn naptr(&self, name: &str) -> Result<Vec<NaptrRecord>, SmpError> {
+        let dns = |e: std::io::Error| SmpError::Dns(e.to_string());
+        let bind: SocketAddr = if self.server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind).map_err(dns)?;
+        socket.set_read_timeout(Some(self.timeout)).map_err(dns)?;
+        let id = (std::process::id() as u16) ^ (name.len() as u16).rotate_left(8);
+        socket
+            .send_to(&dns_query(id, name, TYPE_NAPTR)?, self.server)
+            .map_err(dns)?;
+        let mut buf = [0u8; 4096];
+        let len = socket.recv(&mut buf).map_err(dns)?;
+        parse_naptr_response(&buf[..len], id)
+    }
+}
+
+/// Encode a recursive DNS query for `name`.
+fn dns_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, SmpError> {
+    let mut q = Vec::with_capacity(name.len() + 18);
+    q.extend(id.to_be_bytes());
+    q.extend([0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(SmpError::Dns(format!("invalid DNS name '{name}'")));
+        }
+        q.push(label.len() as u8);
+        q.extend(label.as_bytes());
+    }
+    q.push(0);
+    q.extend(qtype.to_be_bytes());
+    q.extend(1u16.to_be_bytes());
+    Ok(q)
+}
+
+/// Extract the NAPTR answers from a DNS response.
+fn parse_naptr_response(msg: &[u8], id: u16) -> Result<Vec<NaptrRecord>, SmpError> {
+    let bad = || SmpError::Dns("malformed DNS response".into());
+    let u16_at = |pos: usize| -> Result<u16, SmpError> {
+        msg.get(pos..pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(bad)
+    };
+    if u16_at(0)? != id {
+        return Err(SmpError::Dns(
+            "DNS response does not match the query".into(),
+        ));
+    }
+    match u16_at(2)? & 0x000f {
+        0 => {}
+        3 => return Ok(Vec::new()),
+        rcode => return Err(SmpError::Dns(format!("DNS server returned error {rcode}"))),
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(msg, pos)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        pos = read_name(msg, pos)?.1;
+        let rtype = u16_at(pos)?;
+        let rdlength = u16_at(pos + 8)? as usize;
+        let start = pos + 10;
+        let end = start + rdlength;
+        if end > msg.len() {
+            return Err(bad());
+        }
+        if rtype == TYPE_NAPTR {
+            let order = u16_at(start)?;
+            let preference = u16_at(start + 2)?;
+            let mut p = start + 4;
+            let mut strings = Vec::new();
+            for _ in 0..3 {
+                let len = *msg.get(p).ok_or_else(bad)? as usize;
+                let text = msg.get(p + 1..p + 1 + len).ok_or_else(bad)?;
+                strings.push(String::from_utf8_lossy(text).into_owned());
+                p += 1 + len;
+            }
+            let (replacement, _) = read_name(msg, p)?;
+            let mut strings = strings.into_iter();
+            records.push(NaptrRecord {
+                order,
+                preference,
+                flags: strings.next().unwrap_or_default(),
+                service: strings.next().unwrap_or_default(),
+                regexp: strings.next().unwrap_or_default(),
+                replacement: if replacement.is_empty() {
+                    ".".into()
+                } else {
+                    replacement
+                },
+            });
+        }
+        pos = end;
+    }
+    Ok(records)
+}
+
+/// Read a possibly compressed domain name; returns it and the position after it.
+fn read_name(msg: &[u8], mut pos: usize) -> Result<(String, usize), SmpError> {
+    let bad = || SmpError::Dns("malformed DNS name".into());
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..128 {
+        let len = *msg.get(pos).ok_or_else(bad)? as usize;
+        if len == 0 {
+            return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xc0 == 0xc0 {
+            let low = *msg.get(pos + 1).ok_or_else(bad)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = ((len & 0x3f) << 8) | low;
+            continue;
+        }
+        let label = msg.get(pos + 1..pos + 1 + len).ok_or_else(bad)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    Err(bad())
+}
+
+/// Blocking HTTP client for `http://` and `https://` URLs, on `reqwest`
+/// with rustls.
+///
+/// The default transport for SMP lookups and AS4 messages. Redirects are
+/// not followed.
+#[derive(Debug, Clone)]
+pub struct StdHttpClient {
+    timeout: Duration,
+}
+
+impl Default for StdHttpClient {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl StdHttpClient {
+    /// Client with a 30 second timeout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the connect and request timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl HttpClient for StdHttpClient {
+    fn get(&self, url: &str) -> Result<HttpResponse, SmpError> {
+        let response = self
+            .send("GET", url, &[("Accept", "application/xml, text/xml")], &[])
+            .map_err(SmpError::Network)?;
+        Ok(HttpResponse {
+            status: response.status,
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+        })
+    }
+}
+
+impl StdHttpClient {
+    /// Send one request.
+    pub(crate) fn send(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<RawResponse, String> {
+        http::send(method, url, headers, body, self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn naptr_response_roundtrip() {
+        let mut msg = dns_query(7, "abc.example.org", TYPE_NAPTR).unwrap();
+        msg[2] = 0x81;
+        msg[3] = 0x80;
+        msg[7] = 1; // one answer
+        msg.extend([0xc0, 12]); // name pointer to the question
+        msg.extend(TYPE_NAPTR.to_be_bytes());
+        msg.extend([0, 1, 0, 0, 0, 60]);
+        let mut rdata = vec![0, 100, 0, 10];
+        for s in ["U", "Meta:SMP", "!^.*$!http://smp.example.org!"] {
+            rdata.push(s.len() as u8);
+            rdata.extend(s.as_bytes());
+        }
+        rdata.push(0);
+        msg.extend((rdata.len() as u16).to_be_bytes());
+        msg.extend(rdata);
+
+        let records = parse_naptr_response(&msg, 7).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].order, 100);
+        assert_eq!(records[0].service, "Meta:SMP");
+        assert_eq!(records[0].regexp, "!^.*$!http://smp.example.org!");
+        assert_eq!(records[0].replacement, ".");
+        assert!(parse_naptr_response(&msg, 8).is_err());
+    }
+
+    #[test]
+    fn nxdomain_is_empty() {
+        let mut msg = dns_query(1, "missing.example.org", TYPE_NAPTR).unwrap();
+        msg[3] = 0x83;
+        assert!(parse_naptr_response(&msg, 1).unwrap().is_empty());
+    }
+}
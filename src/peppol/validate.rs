@@ -78,13 +78,17 @@ pub fn validate_peppol(invoice: &Invoice) -> Vec<ValidationError> {
     // PEPPOL-EN16931-R051: All currency IDs must match DocumentCurrencyCode
     // (checked implicitly since we use a single currency_code field)
 
-    // PEPPOL-EN16931-P0100/P0101: Invoice type code restrictions
+    // PEPPOL-EN16931-P0100/P0101: Invoice type code restrictions; self-billed
+    // codes (389, 527, 261) are sent under BIS Self-Billing 3.0
     match invoice.type_code {
         InvoiceTypeCode::Invoice
         | InvoiceTypeCode::CreditNote
         | InvoiceTypeCode::Corrected
         | InvoiceTypeCode::Prepayment
-        | InvoiceTypeCode::Partial => {}
+        | InvoiceTypeCode::Partial
+        | InvoiceTypeCode::SelfBilled
+        | InvoiceTypeCode::SelfBilledCreditNote
+        | InvoiceTypeCode::Other(527) => {}
         InvoiceTypeCode::Other(code) => {
             errors.push(ValidationError::with_rule(
                 "type_code",
@@ -226,9 +230,33 @@ pub fn validate_peppol(invoice: &Invoice) -> Vec<ValidationError> {
         }
     }
 
+    // PEPPOL-EN16931-CL008: Electronic address schemes from the EAS code list;
+    // PEPPOL-COMMON-R040..R043: identifier formats of GLN and Nordic/Belgian numbers
+    errors.extend(crate::core::eas::electronic_address_errors(
+        invoice,
+        "PEPPOL-EN16931-CL008",
+        peppol_identifier_rule,
+    ));
+    // BR-CL-11: Legal registration identifier schemes from the ICD list
+    errors.extend(crate::core::eas::registration_id_errors(
+        invoice,
+        peppol_identifier_rule,
+    ));
+
     errors
 }
 
+/// PEPPOL-COMMON rule checking identifiers of `scheme`.
+fn peppol_identifier_rule(scheme: &str) -> &'static str {
+    match scheme {
+        "0088" => "PEPPOL-COMMON-R040",
+        "0192" => "PEPPOL-COMMON-R041",
+        "0184" => "PEPPOL-COMMON-R042",
+        "0208" => "PEPPOL-COMMON-R043",
+        _ => crate::core::eas::IDENTIFIER_FORMAT_RULE,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,336 @@
+//! EPC069-12 "Quick Response Code" payload for SEPA credit transfers (GiroCode).
+
+use rust_decimal::Decimal;
+
+use super::matrix::{EcLevel, QrCode};
+use super::{is_valid_creditor_reference, normalize_iban};
+use crate::core::{Invoice, RechnungError};
+
+/// Maximum payload size in bytes (fits a version 13-M symbol).
+pub const EPC_MAX_BYTES: usize = 331;
+
+/// EPC QR format version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EpcVersion {
+    /// 001 — BIC mandatory.
+    V001,
+    /// 002 — BIC optional within the EEA.
+    V002,
+}
+
+impl EpcVersion {
+    fn code(self) -> &'static str {
+        match self {
+            Self::V001 => "001",
+            Self::V002 => "002",
+        }
+    }
+}
+
+/// Character set of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EpcCharset {
+    /// 1 — UTF-8 (supported by all German banking apps).
+    Utf8,
+    /// 2 — ISO 8859-1 (Latin-1).
+    Latin1,
+    /// 8 — ISO 8859-15 (Latin-9, includes €).
+    Latin9,
+}
+
+impl EpcCharset {
+    fn code(self) -> char {
+        match self {
+            Self::Utf8 => '1',
+            Self::Latin1 => '2',
+            Self::Latin9 => '8',
+        }
+    }
+
+    fn encode(self, text: &str) -> Result<Vec<u8>, RechnungError> {
+        if self == Self::Utf8 {
+            return Ok(text.as_bytes().to_vec());
+        }
+        text.chars()
+            .map(|c| {
+                let byte = match (self, c) {
+                    (Self::Latin9, '€') => Some(0xA4),
+                    (Self::Latin9, 'Š') => Some(0xA6),
+                    (Self::Latin9, 'š') => Some(0xA8),
+                    (Self::Latin9, 'Ž') => Some(0xB4),
+                    (Self::Latin9, 'ž') => Some(0xB8),
+                    (Self::Latin9, 'Œ') => Some(0xBC),
+                    (Self::Latin9, 'œ') => Some(0xBD),
+                    (Self::Latin9, 'Ÿ') => Some(0xBE),
+                    (Self::Latin9, '\u{A4}' | '\u{A6}' | '\u{A8}' | '\u{B4}' | '\u{B8}')
+                    | (Self::Latin9, '\u{BC}'..='\u{BE}') => None,
+                    _ => u8::try_from(u32::from(c)).ok(),
+                };
+                byte.ok_or_else(|| {
+                    RechnungError::Builder(format!("character '{c}' not representable in {self:?}"))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Remittance information: either structured (ISO 11649 RF reference) or free text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EpcRemittance {
+    /// Creditor reference, e.g. `RF18539007547034` (max 35 chars).
+    Structured(String),
+    /// Free text, e.g. the invoice number (max 140 chars).
+    Unstructured(String),
+}
+
+/// EPC069-12 credit transfer payload.
+///
+/// ```
+/// use faktura::qr::{EpcPayload, EpcRemittance};
+/// use rust_decimal_macros::dec;
+///
+/// let mut epc = EpcPayload::new("ACME GmbH", "DE89 3704 0044 0532 0130 00");
+/// epc.amount = Some(dec!(1785.00));
+/// epc.remittance = Some(EpcRemittance::Unstructured("RE-2024-001".into()));
+/// let payload = epc.to_payload().unwrap();
+/// assert!(payload.starts_with("BCD\n002\n1\nSCT\n\nACME GmbH\nDE89370400440532013000\nEUR1785.00"));
+/// let qr = epc.to_qr().unwrap();
+/// assert!(qr.size() >= 21);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpcPayload {
+    /// Format version (default 002).
+    pub version: EpcVersion,
+    /// Character set (default UTF-8).
+    pub charset: EpcCharset,
+    /// BIC of the beneficiary bank (mandatory for version 001).
+    pub bic: Option<String>,
+    /// Beneficiary name (max 70 chars).
+    pub name: String,
+    /// Beneficiary IBAN, without spaces.
+    pub iban: String,
+    /// Amount in EUR (0.01 – 999,999,999.99). `None` lets the payer enter it.
+    pub amount: Option<Decimal>,
+    /// ISO 20022 purpose code (4 chars).
+    pub purpose: Option<String>,
+    /// Remittance information.
+    pub remittance: Option<EpcRemittance>,
+    /// Beneficiary-to-originator information shown to the payer (max 70 chars).
+    pub information: Option<String>,
+}
+
+impl EpcPayload {
+    /// Create a version 002, UTF-8 payload for the given beneficiary.
+    pub fn new(name: impl Into<String>, iban: impl AsRef<str>) -> Self {
+        Self {
+            version: EpcVersion::V002,
+            charset: EpcCharset::Utf8,
+            bic: None,
+            name: name.into(),
+            iban: normalize_iban(iban.as_ref()),
+            amount: None,
+            purpose: None,
+            remittance: None,
+            information: None,
+        }
+    }
+
+    /// Build the payload from an invoice's credit transfer instructions (BG-17).
+    ///
+    /// Uses the payee (BG-10) or account name (BT-85) as beneficiary, falling
+    /// back to the seller name, and the amount due (BT-115). The remittance
+    /// information (BT-83) is sent structured if it is a valid RF reference,
+    /// otherwise as text; without it, the invoice number is used.
+    pub fn from_invoice(invoice: &Invoice) -> Result<Self, RechnungError> {
+        if !invoice.currency_code.eq_ignore_ascii_case("EUR") {
+            return Err(RechnungError::Builder(format!(
+                "EPC QR codes only support EUR, invoice {} is in {}",
+                invoice.number, invoice.currency_code
+            )));
+        }
+        let payment = invoice.payment.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no payment instructions",
+                invoice.number
+            ))
+        })?;
+        let transfer = payment.credit_transfer.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no credit transfer account",
+                invoice.number
+            ))
+        })?;
+        let totals = invoice.totals.as_ref().ok_or_else(|| {
+            RechnungError::Builder("totals must be calculated before QR generation".into())
+        })?;
+        if totals.amount_due <= Decimal::ZERO {
+            return Err(RechnungError::Builder(format!(
+                "invoice {} has no amount due",
+                invoice.number
+            )));
+        }
+
+        let name = invoice
+            .payee
+            .as_ref()
+            .map(|p| p.name.clone())
+            .or_else(|| transfer.account_name.clone())
+            .unwrap_or_else(|| invoice.seller.name.clone());
+
+        let remittance = match &payment.remittance_info {
+            Some(r) if is_valid_creditor_reference(r) => {
+                EpcRemittance::Structured(r.split_whitespace().collect())
+            }
+            Some(r) => EpcRemittance::Unstructured(r.clone()),
+            None => EpcRemittance::Unstructured(invoice.number.clone()),
+        };
+
+        let mut epc = Self::new(name, &transfer.iban);
+        epc.bic = transfer.bic.clone();
+        epc.amount = Some(totals.amount_due);
+        epc.remittance = Some(remittance);
+        Ok(epc)
+    }
+
+    /// Check field lengths and formats against EPC069-12.
+    pub fn validate(&self) -> Result<(), RechnungError> {
+        let err = |msg: String| Err(RechnungError::Validation(msg));
+
+        match &self.bic {
+            Some(bic) if !matches!(bic.len(), 8 | 11) => {
+                return err(format!("BIC '{bic}' must have 8 or 11 characters"));
+            }
+            None if self.version == EpcVersion::V001 => {
+                return err("EPC QR version 001 requires a BIC".into());
+            }
+            _ => {}
+        }
+        if self.name.trim().is_empty() || self.name.chars().count() > 70 {
+            return err("beneficiary name must have 1 to 70 characters".into());
+        }
+        if self.iban.len() < 15
+            || self.iban.len() > 34
+            || !self.iban.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return err(format!("invalid IBAN '{}'", self.iban));
+        }
+        if let Some(amount) = self.amount {
+            if amount < Decimal::new(1, 2)
+                || amount > Decimal::new(99_999_999_999, 2)
+                || amount.normalize().scale() > 2
+            {
+                return err(format!(
+                    "amount {amount} must be between 0.01 and 999999999.99 with at most 2 decimals"
+                ));
+            }
+        }
+        if let Some(purpose) = &self.purpose {
+            if purpose.len() != 4 || !purpose.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return err(format!(
+                    "purpose code '{purpose}' must be 4 alphanumeric characters"
+                ));
+            }
+        }
+        match &self.remittance {
+            Some(EpcRemittance::Structured(r)) if r.len() > 35 => {
+                return err("structured remittance must not exceed 35 characters".into());
+            }
+            Some(EpcRemittance::Unstructured(r)) if r.chars().count() > 140 => {
+                return err("unstructured remittance must not exceed 140 characters".into());
+            }
+            _ => {}
+        }
+        if self
+            .information
+            .as_ref()
+            .is_some_and(|i| i.chars().count() > 70)
+        {
+            return err(
+                "beneficiary to originator information must not exceed 70 characters".into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Render the payload text (lines separated by LF, trailing empty lines omitted).
+    pub fn to_payload(&self) -> Result<String, RechnungError> {
+        self.validate()?;
+
+        let (structured, unstructured) = match &self.remittance {
+            Some(EpcRemittance::Structured(r)) => (r.as_str(), ""),
+            Some(EpcRemittance::Unstructured(r)) => ("", r.as_str()),
+            None => ("", ""),
+        };
+        let amount = self
+            .amount
+            .map(|a| format!("EUR{:.2}", a))
+            .unwrap_or_default();
+        let charset = self.charset.code().to_string();
+        let lines = [
+            "BCD",
+            self.version.code(),
+            &charset,
+            "SCT",
+            self.bic.as_deref().unwrap_or(""),
+            self.name.trim(),
+            &self.iban,
+            &amount,
+            self.purpose.as_deref().unwrap_or(""),
+            structured,
+            unstructured,
+            self.information.as_deref().unwrap_or(""),
+        ];
+        let used = lines.iter().rposition(|l| !l.is_empty()).unwrap_or(0) + 1;
+        let payload = lines[..used].join("\n");
+
+        let len = self.charset.encode(&payload)?.len();
+        if len > EPC_MAX_BYTES {
+            return Err(RechnungError::Validation(format!(
+                "EPC payload is {len} bytes, maximum is {EPC_MAX_BYTES}"
+            )));
+        }
+        Ok(payload)
+    }
+
+    /// Encoded payload bytes in the selected character set.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, RechnungError> {
+        self.charset.encode(&self.to_payload()?)
+    }
+
+    /// Encode as a QR code at error correction level M.
+    pub fn to_qr(&self) -> Result<QrCode, RechnungError> {
+        QrCode::encode(&self.to_bytes()?, EcLevel::M)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn latin_charsets() {
+        assert_eq!(EpcCharset::Latin1.encode("Müller").unwrap(), b"M\xfcller");
+        assert!(EpcCharset::Latin1.encode("5 €").is_err());
+        assert_eq!(EpcCharset::Latin9.encode("5 €").unwrap(), b"5 \xa4");
+        assert!(EpcCharset::Latin9.encode("\u{A4}").is_err());
+    }
+
+    #[test]
+    fn length_limits() {
+        let mut epc = EpcPayload::new("A".repeat(71), "DE89370400440532013000");
+        assert!(epc.to_payload().is_err());
+        epc.name = "ACME".into();
+        epc.amount = Some(dec!(0.001));
+        assert!(epc.to_payload().is_err());
+        epc.amount = Some(dec!(12.30));
+        epc.remittance = Some(EpcRemittance::Unstructured("x".repeat(141)));
+        assert!(epc.to_payload().is_err());
+        epc.remittance = Some(EpcRemittance::Unstructured("x".repeat(140)));
+        assert!(epc.to_payload().is_ok());
+        epc.version = EpcVersion::V001;
+        assert!(epc.to_payload().is_err(), "001 needs a BIC");
+    }
+}
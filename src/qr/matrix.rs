@@ -0,0 +1,649 @@
+//! QR code symbol encoder (ISO/IEC 18004), byte mode, versions 1–40.
+//!
+//! Payment QR codes only ever carry byte-mode text, so numeric, alphanumeric
+//! and Kanji segments are not implemented.
+
+use crate::core::RechnungError;
+
+/// Error correction level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EcLevel {
+    /// ~7% recovery.
+    L,
+    /// ~15% recovery (required by EPC069-12 and the Swiss QR-bill).
+    M,
+    /// ~25% recovery.
+    Q,
+    /// ~30% recovery.
+    H,
+}
+
+impl EcLevel {
+    fn ordinal(self) -> usize {
+        match self {
+            Self::L => 0,
+            Self::M => 1,
+            Self::Q => 2,
+            Self::H => 3,
+        }
+    }
+
+    /// Two-bit indicator used in the format information.
+    fn format_bits(self) -> u32 {
+        match self {
+            Self::L => 1,
+            Self::M => 0,
+            Self::Q => 3,
+            Self::H => 2,
+        }
+    }
+}
+
+/// Error correction codewords per block, indexed by [level][version].
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+/// Number of error correction blocks, indexed by [level][version].
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+/// A QR code symbol as a square grid of modules.
+///
+/// The grid excludes the quiet zone; renderers should leave a light margin
+/// of at least 4 modules around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    version: u8,
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode bytes in the smallest version that fits at the given level.
+    pub fn encode(data: &[u8], level: EcLevel) -> Result<Self, RechnungError> {
+        let version = (1..=40u8)
+            .find(|&v| data_bit_length(data.len(), v) <= num_data_codewords(v, level) * 8)
+            .ok_or_else(|| {
+                RechnungError::Builder(format!(
+                    "{} bytes do not fit into a QR code at level {level:?}",
+                    data.len()
+                ))
+            })?;
+
+        let codewords = encode_data(data, version, level);
+        let mut builder = Builder::new(version);
+        builder.draw_function_patterns();
+        builder.draw_codewords(&add_ecc_and_interleave(&codewords, version, level));
+
+        // Pick the mask with the lowest penalty
+        let mut best = (u32::MAX, 0u8);
+        for mask in 0..8 {
+            builder.apply_mask(mask);
+            builder.draw_format_bits(level, mask);
+            let penalty = builder.penalty_score();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            builder.apply_mask(mask);
+        }
+        builder.apply_mask(best.1);
+        builder.draw_format_bits(level, best.1);
+
+        Ok(Self {
+            version,
+            size: builder.size,
+            modules: builder.modules,
+        })
+    }
+
+    /// Symbol version (1–40).
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Width and height in modules (21 for version 1, +4 per version).
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x`, row `y` is dark. Out-of-range
+    /// coordinates are light.
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Render as a standalone SVG with a 4-module quiet zone.
+    ///
+    /// One user unit per module; scale with the `width`/`height` of the
+    /// embedding element.
+    pub fn to_svg(&self) -> String {
+        self.svg_with_overlay("")
+    }
+
+    pub(crate) fn svg_with_overlay(&self, overlay: &str) -> String {
+        let dim = self.size + 8;
+        let mut path = String::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.is_dark(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + 4, y + 4));
+                }
+            }
+        }
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+             viewBox=\"0 0 {dim} {dim}\" shape-rendering=\"crispEdges\">\
+             <rect width=\"100%\" height=\"100%\" fill=\"#FFFFFF\"/>\
+             <path d=\"{path}\" fill=\"#000000\"/>{overlay}</svg>\n"
+        )
+    }
+}
+
+struct Builder {
+    version: u8,
+    size: usize,
+    modules: Vec<bool>,
+    is_function: Vec<bool>,
+}
+
+impl Builder {
+    fn new(version: u8) -> Self {
+        let size = usize::from(version) * 4 + 17;
+        Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            is_function: vec![false; size * size],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.is_function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        // Timing patterns
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        // Finder patterns (with separators)
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        // Alignment patterns, skipping the three finder corners
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                let corner = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+                if !corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Reserve format areas, then version information
+        self.draw_format_bits(EcLevel::L, 0);
+        self.draw_version();
+    }
+
+    fn draw_finder(&mut self, cx: usize, cy: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let dist = dx.abs().max(dy.abs());
+                let x = cx as i32 + dx;
+                let y = cy as i32 + dy;
+                if (0..self.size as i32).contains(&x) && (0..self.size as i32).contains(&y) {
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dist = dx.abs().max(dy.abs());
+                self.set_function(
+                    (cx as i32 + dx) as usize,
+                    (cy as i32 + dy) as usize,
+                    dist != 1,
+                );
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, level: EcLevel, mask: u8) {
+        let data = (level.format_bits() << 3) | u32::from(mask);
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+
+        // First copy, around the top-left finder
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // Second copy, split between the other two finders
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always-dark module
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let version = u32::from(self.version);
+        let mut rem = version;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = (version << 12) | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Place data bits in the zig-zag pattern, skipping function modules.
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size;
+        let total_bits = data.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !self.is_function[y * size + x] && i < total_bits {
+                        self.modules[y * size + x] = (data[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    /// XOR the mask pattern onto all data modules (self-inverse).
+    fn apply_mask(&mut self, mask: u8) {
+        let size = self.size;
+        for y in 0..size {
+            for x in 0..size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.is_function[y * size + x] {
+                    self.modules[y * size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    /// Mask penalty per ISO/IEC 18004 §7.8.3 (rules N1–N4).
+    fn penalty_score(&self) -> u32 {
+        let size = self.size;
+        let mut score: u32 = 0;
+
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let line: Vec<bool> = (0..size)
+                    .map(|b| {
+                        if horizontal {
+                            self.get(b, a)
+                        } else {
+                            self.get(a, b)
+                        }
+                    })
+                    .collect();
+
+                // N1: runs of five or more same-coloured modules
+                let mut run: u32 = 1;
+                for b in 1..size {
+                    if line[b] == line[b - 1] {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            score += run - 2;
+                        }
+                        run = 1;
+                    }
+                }
+                if run >= 5 {
+                    score += run - 2;
+                }
+
+                // N3: finder-like 1:1:3:1:1 with four light modules on either side
+                const PATTERN: [bool; 7] = [true, false, true, true, true, false, true];
+                for b in 0..size.saturating_sub(6) {
+                    if line[b..b + 7] != PATTERN {
+                        continue;
+                    }
+                    let light_before = b >= 4 && line[b - 4..b].iter().all(|&m| !m);
+                    let light_after = b + 11 <= size && line[b + 7..b + 11].iter().all(|&m| !m);
+                    if light_before || light_after {
+                        score += 40;
+                    }
+                }
+            }
+        }
+
+        // N2: 2x2 blocks of the same colour
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = self.get(x, y);
+                if c == self.get(x + 1, y) && c == self.get(x, y + 1) && c == self.get(x + 1, y + 1)
+                {
+                    score += 3;
+                }
+            }
+        }
+
+        // N4: deviation of the dark ratio from 50%, in 5% steps
+        let total = (size * size) as u32;
+        let dark = self.modules.iter().filter(|&&m| m).count() as u32;
+        let deviation = (dark * 20).abs_diff(total * 10);
+        score += deviation.div_ceil(total).saturating_sub(1) * 10;
+
+        score
+    }
+}
+
+fn alignment_positions(version: u8) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let version = usize::from(version);
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Modules available for data and error correction after function patterns.
+fn num_raw_data_modules(version: u8) -> usize {
+    let v = usize::from(version);
+    let mut result = (16 * v + 128) * v + 64;
+    if v >= 2 {
+        let align = v / 7 + 2;
+        result -= (25 * align - 10) * align - 55;
+        if v >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: u8, level: EcLevel) -> usize {
+    let v = usize::from(version);
+    let l = level.ordinal();
+    num_raw_data_modules(version) / 8
+        - usize::from(ECC_CODEWORDS_PER_BLOCK[l][v])
+            * usize::from(NUM_ERROR_CORRECTION_BLOCKS[l][v])
+}
+
+fn char_count_bits(version: u8) -> usize {
+    if version <= 9 { 8 } else { 16 }
+}
+
+fn data_bit_length(len: usize, version: u8) -> usize {
+    if len >= 1 << char_count_bits(version) {
+        return usize::MAX;
+    }
+    4 + char_count_bits(version) + len * 8
+}
+
+/// Byte-mode segment, terminator and pad codewords.
+fn encode_data(data: &[u8], version: u8, level: EcLevel) -> Vec<u8> {
+    let mut bits = BitBuffer::default();
+    bits.append(0b0100, 4);
+    bits.append(data.len() as u32, char_count_bits(version));
+    for &b in data {
+        bits.append(u32::from(b), 8);
+    }
+
+    let capacity = num_data_codewords(version, level) * 8;
+    bits.append(0, (capacity - bits.len).min(4));
+    bits.append(0, (8 - bits.len % 8) % 8);
+
+    let mut codewords = bits.bytes;
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() * 8 >= capacity {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitBuffer {
+    fn append(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len % 8 == 0 {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// Split into blocks, append Reed-Solomon codewords and interleave.
+fn add_ecc_and_interleave(data: &[u8], version: u8, level: EcLevel) -> Vec<u8> {
+    let v = usize::from(version);
+    let l = level.ordinal();
+    let num_blocks = usize::from(NUM_ERROR_CORRECTION_BLOCKS[l][v]);
+    let ecc_len = usize::from(ECC_CODEWORDS_PER_BLOCK[l][v]);
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+
+    let divisor = reed_solomon_divisor(ecc_len);
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let data_len = short_block_len - ecc_len + usize::from(i >= num_short_blocks);
+        let mut block = data[k..k + data_len].to_vec();
+        k += data_len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < num_short_blocks {
+            block.push(0);
+        }
+        block.extend(ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_block_len {
+        for (j, block) in blocks.iter().enumerate() {
+            // Skip the padding byte of short blocks
+            if i != short_block_len - ecc_len || j >= num_short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((u32::from(y) >> i) & 1) * u32::from(x);
+    }
+    z as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_tables() {
+        // Byte-mode capacities at level M from ISO/IEC 18004 Table 7
+        assert_eq!(num_data_codewords(1, EcLevel::M), 16);
+        assert_eq!(num_data_codewords(10, EcLevel::M), 216);
+        assert_eq!(num_data_codewords(13, EcLevel::M), 334);
+        assert_eq!(num_data_codewords(40, EcLevel::L), 2956);
+    }
+
+    #[test]
+    fn alignment_pattern_positions() {
+        assert_eq!(alignment_positions(2), vec![6, 18]);
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(32), vec![6, 34, 60, 86, 112, 138]);
+    }
+
+    #[test]
+    fn reed_solomon_known_vector() {
+        // ISO/IEC 18004 Annex I: "01234567" at 1-M
+        let data = [
+            0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
+            0xEC, 0x11,
+        ];
+        let ecc = reed_solomon_remainder(&data, &reed_solomon_divisor(10));
+        assert_eq!(
+            ecc,
+            vec![0xA5, 0x24, 0xD4, 0xC1, 0xED, 0x36, 0xC7, 0x87, 0x2C, 0x55]
+        );
+    }
+
+    #[test]
+    fn version_grows_with_payload() {
+        let small = QrCode::encode(b"BCD", EcLevel::M).unwrap();
+        assert_eq!(small.version(), 1);
+        assert_eq!(small.size(), 21);
+        let epc_max = QrCode::encode(&[b'x'; 331], EcLevel::M).unwrap();
+        assert_eq!(epc_max.version(), 13);
+        assert!(QrCode::encode(&[0; 3000], EcLevel::M).is_err());
+    }
+
+    #[test]
+    fn finder_patterns_present() {
+        let qr = QrCode::encode(b"hello", EcLevel::M).unwrap();
+        let n = qr.size();
+        for (x, y) in [(0, 0), (n - 7, 0), (0, n - 7)] {
+            assert!(qr.is_dark(x, y) && qr.is_dark(x + 6, y + 6));
+            assert!(!qr.is_dark(x + 1, y + 1));
+            assert!(qr.is_dark(x + 3, y + 3));
+        }
+        assert!(qr.is_dark(8, n - 8), "dark module");
+    }
+}
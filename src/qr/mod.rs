@@ -0,0 +1,27 @@
+//! Payment QR codes for invoice documents.
+//!
+//! Generates the EPC069-12 credit transfer payload ("GiroCode") for EUR
+//! invoices and the Swiss QR-bill payload for CHF invoices, and encodes them
+//! as QR code symbols that a PDF renderer can place.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::qr::EpcPayload;
+//!
+//! let epc = EpcPayload::from_invoice(&invoice)?;
+//! let svg = epc.to_qr()?.to_svg();
+//! ```
+
+mod epc;
+mod matrix;
+mod swiss;
+
+pub use epc::{EPC_MAX_BYTES, EpcCharset, EpcPayload, EpcRemittance, EpcVersion};
+pub use matrix::{EcLevel, QrCode};
+pub use swiss::{
+    SPC_MAX_CHARS, SwissAddress, SwissQrBill, SwissReference, is_qr_iban, is_valid_qr_reference,
+};
+
+pub use crate::core::iban::is_valid_creditor_reference;
+use crate::core::iban::normalize_iban;
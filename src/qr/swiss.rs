@@ -0,0 +1,383 @@
+//! Swiss QR-bill (Swiss Payment Code, SPC 0200) payload.
+//!
+//! Implements the Swiss Payment Standards "Implementation Guidelines for the
+//! QR-bill" v2.3 with structured addresses (type `S`), which are mandatory
+//! from November 2025.
+
+use rust_decimal::Decimal;
+
+use super::matrix::{EcLevel, QrCode};
+use super::{is_valid_creditor_reference, normalize_iban};
+use crate::core::{Address, Invoice, RechnungError};
+
+/// Maximum payload length in characters.
+pub const SPC_MAX_CHARS: usize = 997;
+
+/// Structured address of the creditor or debtor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwissAddress {
+    /// Name or company (max 70 chars).
+    pub name: String,
+    /// Street (max 70 chars).
+    pub street: Option<String>,
+    /// Building number (max 16 chars).
+    pub building_number: Option<String>,
+    /// Postal code (max 16 chars).
+    pub postal_code: String,
+    /// Town (max 35 chars).
+    pub town: String,
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: String,
+}
+
+impl SwissAddress {
+    /// Map an invoice party address; the street line is kept as one field.
+    pub fn from_address(name: impl Into<String>, address: &Address) -> Self {
+        Self {
+            name: name.into(),
+            street: address.street.clone(),
+            building_number: None,
+            postal_code: address.postal_code.clone(),
+            town: address.city.clone(),
+            country: address.country_code.clone(),
+        }
+    }
+
+    fn validate(&self, role: &str) -> Result<(), RechnungError> {
+        let limits = [
+            ("name", Some(self.name.as_str()), 70),
+            ("street", self.street.as_deref(), 70),
+            ("building number", self.building_number.as_deref(), 16),
+            ("postal code", Some(self.postal_code.as_str()), 16),
+            ("town", Some(self.town.as_str()), 35),
+        ];
+        for (field, value, max) in limits {
+            if value.is_some_and(|v| v.chars().count() > max) {
+                return Err(RechnungError::Validation(format!(
+                    "{role} {field} must not exceed {max} characters"
+                )));
+            }
+        }
+        if self.name.trim().is_empty() || self.postal_code.is_empty() || self.town.is_empty() {
+            return Err(RechnungError::Validation(format!(
+                "{role} address requires name, postal code and town"
+            )));
+        }
+        if self.country.len() != 2 {
+            return Err(RechnungError::Validation(format!(
+                "{role} country must be an ISO 3166-1 alpha-2 code"
+            )));
+        }
+        Ok(())
+    }
+
+    fn push_lines<'a>(&'a self, lines: &mut Vec<&'a str>) {
+        lines.extend([
+            "S",
+            self.name.trim(),
+            self.street.as_deref().unwrap_or(""),
+            self.building_number.as_deref().unwrap_or(""),
+            &self.postal_code,
+            &self.town,
+            &self.country,
+        ]);
+    }
+}
+
+/// Payment reference of a QR-bill.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwissReference {
+    /// QRR — 27-digit QR reference, required with a QR-IBAN.
+    Qr(String),
+    /// SCOR — ISO 11649 creditor reference (RF...).
+    Creditor(String),
+    /// NON — no reference.
+    None,
+}
+
+/// Swiss QR-bill payment part.
+///
+/// ```
+/// use faktura::qr::{SwissAddress, SwissQrBill, SwissReference};
+/// use rust_decimal_macros::dec;
+///
+/// let creditor = SwissAddress {
+///     name: "Robert Schneider AG".into(),
+///     street: Some("Rue du Lac".into()),
+///     building_number: Some("1268".into()),
+///     postal_code: "2501".into(),
+///     town: "Biel".into(),
+///     country: "CH".into(),
+/// };
+/// let mut bill = SwissQrBill::new("CH44 3199 9123 0008 8901 2", creditor);
+/// bill.amount = Some(dec!(1949.75));
+/// bill.reference = SwissReference::Qr("210000000003139471430009017".into());
+/// assert!(bill.to_payload().unwrap().starts_with("SPC\n0200\n1\nCH4431999123000889012\nS\n"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwissQrBill {
+    /// Creditor IBAN or QR-IBAN (CH/LI), without spaces.
+    pub iban: String,
+    /// Creditor.
+    pub creditor: SwissAddress,
+    /// Amount (0.01 – 999,999,999.99). `None` lets the payer enter it.
+    pub amount: Option<Decimal>,
+    /// `CHF` or `EUR`.
+    pub currency: String,
+    /// Ultimate debtor, if known.
+    pub debtor: Option<SwissAddress>,
+    /// Payment reference.
+    pub reference: SwissReference,
+    /// Unstructured message (max 140 chars together with `billing_information`).
+    pub message: Option<String>,
+    /// Structured billing information, e.g. Swico S1 (`//S1/...`).
+    pub billing_information: Option<String>,
+}
+
+impl SwissQrBill {
+    /// Create a CHF bill without amount, debtor or reference.
+    pub fn new(iban: impl AsRef<str>, creditor: SwissAddress) -> Self {
+        Self {
+            iban: normalize_iban(iban.as_ref()),
+            creditor,
+            amount: None,
+            currency: "CHF".into(),
+            debtor: None,
+            reference: SwissReference::None,
+            message: None,
+            billing_information: None,
+        }
+    }
+
+    /// Build a QR-bill from a CHF or EUR invoice with a Swiss/Liechtenstein
+    /// credit transfer account.
+    ///
+    /// The seller is the creditor and the buyer the debtor. The remittance
+    /// information (BT-83) becomes a QR reference if the IBAN is a QR-IBAN, an
+    /// SCOR reference if it is a valid RF reference, and the message otherwise.
+    pub fn from_invoice(invoice: &Invoice) -> Result<Self, RechnungError> {
+        let currency = invoice.currency_code.to_ascii_uppercase();
+        if currency != "CHF" && currency != "EUR" {
+            return Err(RechnungError::Builder(format!(
+                "Swiss QR-bills support CHF and EUR only, invoice {} is in {}",
+                invoice.number, invoice.currency_code
+            )));
+        }
+        let payment = invoice.payment.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no payment instructions",
+                invoice.number
+            ))
+        })?;
+        let transfer = payment.credit_transfer.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no credit transfer account",
+                invoice.number
+            ))
+        })?;
+        let totals = invoice.totals.as_ref().ok_or_else(|| {
+            RechnungError::Builder("totals must be calculated before QR generation".into())
+        })?;
+
+        let creditor_name = transfer
+            .account_name
+            .clone()
+            .unwrap_or_else(|| invoice.seller.name.clone());
+        let mut bill = Self::new(
+            &transfer.iban,
+            SwissAddress::from_address(creditor_name, &invoice.seller.address),
+        );
+        bill.currency = currency;
+        bill.amount = (totals.amount_due > Decimal::ZERO).then_some(totals.amount_due);
+        bill.debtor = Some(SwissAddress::from_address(
+            invoice.buyer.name.clone(),
+            &invoice.buyer.address,
+        ));
+
+        let remittance = payment.remittance_info.as_deref().map(str::trim);
+        match remittance {
+            Some(r) if is_qr_iban(&bill.iban) => {
+                bill.reference = SwissReference::Qr(r.split_whitespace().collect());
+                bill.message = Some(invoice.number.clone());
+            }
+            Some(r) if is_valid_creditor_reference(r) => {
+                bill.reference = SwissReference::Creditor(r.split_whitespace().collect());
+                bill.message = Some(invoice.number.clone());
+            }
+            Some(r) => bill.message = Some(r.to_string()),
+            None => bill.message = Some(invoice.number.clone()),
+        }
+        Ok(bill)
+    }
+
+    /// Check the payment part against the QR-bill implementation guidelines.
+    pub fn validate(&self) -> Result<(), RechnungError> {
+        let err = |msg: String| Err(RechnungError::Validation(msg));
+
+        if self.iban.len() != 21 || !(self.iban.starts_with("CH") || self.iban.starts_with("LI")) {
+            return err(format!(
+                "'{}' is not a Swiss or Liechtenstein IBAN",
+                self.iban
+            ));
+        }
+        self.creditor.validate("creditor")?;
+        if let Some(debtor) = &self.debtor {
+            debtor.validate("debtor")?;
+        }
+        if self.currency != "CHF" && self.currency != "EUR" {
+            return err(format!(
+                "currency must be CHF or EUR, not {}",
+                self.currency
+            ));
+        }
+        if let Some(amount) = self.amount {
+            if amount < Decimal::new(1, 2)
+                || amount > Decimal::new(99_999_999_999, 2)
+                || amount.normalize().scale() > 2
+            {
+                return err(format!(
+                    "amount {amount} must be between 0.01 and 999999999.99 with at most 2 decimals"
+                ));
+            }
+        }
+        match (&self.reference, is_qr_iban(&self.iban)) {
+            (SwissReference::Qr(r), true) if is_valid_qr_reference(r) => {}
+            (SwissReference::Qr(r), true) => return err(format!("invalid QR reference '{r}'")),
+            (_, true) => return err("a QR-IBAN requires a QR reference (QRR)".into()),
+            (SwissReference::Qr(_), false) => {
+                return err("QR references (QRR) require a QR-IBAN".into());
+            }
+            (SwissReference::Creditor(r), false) if !is_valid_creditor_reference(r) => {
+                return err(format!("invalid creditor reference '{r}'"));
+            }
+            _ => {}
+        }
+        let text_len = self.message.as_ref().map_or(0, |m| m.chars().count())
+            + self
+                .billing_information
+                .as_ref()
+                .map_or(0, |b| b.chars().count());
+        if text_len > 140 {
+            return err("message and billing information must not exceed 140 characters".into());
+        }
+        Ok(())
+    }
+
+    /// Render the SPC payload (lines separated by LF).
+    pub fn to_payload(&self) -> Result<String, RechnungError> {
+        self.validate()?;
+
+        let amount = self.amount.map(|a| format!("{:.2}", a)).unwrap_or_default();
+        let (ref_type, reference) = match &self.reference {
+            SwissReference::Qr(r) => ("QRR", r.as_str()),
+            SwissReference::Creditor(r) => ("SCOR", r.as_str()),
+            SwissReference::None => ("NON", ""),
+        };
+
+        let mut lines: Vec<&str> = vec!["SPC", "0200", "1", &self.iban];
+        self.creditor.push_lines(&mut lines);
+        // Ultimate creditor: reserved for future use, must be empty
+        lines.extend([""; 7]);
+        lines.extend([amount.as_str(), &self.currency]);
+        match &self.debtor {
+            Some(debtor) => debtor.push_lines(&mut lines),
+            None => lines.extend([""; 7]),
+        }
+        lines.extend([
+            ref_type,
+            reference,
+            self.message.as_deref().unwrap_or(""),
+            "EPD",
+        ]);
+        if let Some(info) = &self.billing_information {
+            lines.push(info);
+        }
+
+        let payload = lines.join("\n");
+        let len = payload.chars().count();
+        if len > SPC_MAX_CHARS {
+            return Err(RechnungError::Validation(format!(
+                "QR-bill payload is {len} characters, maximum is {SPC_MAX_CHARS}"
+            )));
+        }
+        Ok(payload)
+    }
+
+    /// Encode as a QR code at error correction level M.
+    pub fn to_qr(&self) -> Result<QrCode, RechnungError> {
+        QrCode::encode(self.to_payload()?.as_bytes(), EcLevel::M)
+    }
+
+    /// Render the QR code as SVG with the Swiss cross in the centre, as the
+    /// QR-bill style guide requires (7 mm cross on a 46 mm code).
+    pub fn to_svg(&self) -> Result<String, RechnungError> {
+        let qr = self.to_qr()?;
+        let code = qr.size() as f64;
+        let centre = 4.0 + code / 2.0;
+        let cross = code * 7.0 / 46.0;
+        let bar_long = cross * 0.6;
+        let bar_short = cross * 0.19;
+        let overlay = format!(
+            "<rect x=\"{x0:.3}\" y=\"{x0:.3}\" width=\"{c:.3}\" height=\"{c:.3}\" \
+             fill=\"#000000\" stroke=\"#FFFFFF\" stroke-width=\"{sw:.3}\"/>\
+             <rect x=\"{lx:.3}\" y=\"{sx:.3}\" width=\"{l:.3}\" height=\"{s:.3}\" fill=\"#FFFFFF\"/>\
+             <rect x=\"{sx:.3}\" y=\"{lx:.3}\" width=\"{s:.3}\" height=\"{l:.3}\" fill=\"#FFFFFF\"/>",
+            x0 = centre - cross / 2.0,
+            c = cross,
+            sw = cross * 0.08,
+            lx = centre - bar_long / 2.0,
+            sx = centre - bar_short / 2.0,
+            l = bar_long,
+            s = bar_short,
+        );
+        Ok(qr.svg_with_overlay(&overlay))
+    }
+}
+
+/// QR-IBANs have an institution identification (IID) of 30000–31999.
+pub fn is_qr_iban(iban: &str) -> bool {
+    let iban = normalize_iban(iban);
+    (iban.starts_with("CH") || iban.starts_with("LI"))
+        && iban
+            .get(4..9)
+            .and_then(|iid| iid.parse::<u32>().ok())
+            .is_some_and(|iid| (30000..=31999).contains(&iid))
+}
+
+/// Validate a 27-digit QR reference (modulo 10, recursive check digit).
+pub fn is_valid_qr_reference(reference: &str) -> bool {
+    let digits: Vec<u32> = reference
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+    if digits.len() != 27 {
+        return false;
+    }
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    let carry = digits[..26]
+        .iter()
+        .fold(0, |carry, &d| TABLE[((carry + d) % 10) as usize]);
+    (10 - carry) % 10 == digits[26]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qr_reference_check_digit() {
+        assert!(is_valid_qr_reference("210000000003139471430009017"));
+        assert!(is_valid_qr_reference("21 00000 00003 13947 14300 09017"));
+        assert!(!is_valid_qr_reference("210000000003139471430009018"));
+        assert!(!is_valid_qr_reference("RF18539007547034"));
+    }
+
+    #[test]
+    fn qr_iban_range() {
+        assert!(is_qr_iban("CH44 3199 9123 0008 8901 2"));
+        assert!(!is_qr_iban("CH93 0076 2011 6238 5295 7"));
+        assert!(!is_qr_iban("DE89370400440532013000"));
+    }
+}
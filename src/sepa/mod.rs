@@ -0,0 +1,296 @@
+//! SEPA payment files (ISO 20022 pain messages).
+//!
+//! Generates credit transfer batches (`pain.001.001.09`) for paying incoming
+//! invoices and SEPA direct debit batches (`pain.008.001.08`, CORE and B2B)
+//! for collecting outgoing ones. Transactions are built from an invoice's
+//! payment instructions (BG-16) and grouped into one payment information
+//! block per execution or collection date.
+//!
+//! Amounts honour Skonto: a transaction dated within a discount period
+//! carries the discounted amount due.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::sepa::*;
+//!
+//! let debtor = SepaAccount::new("ACME GmbH", "DE89370400440532013000");
+//! let mut batch = CreditTransferBatch::new("PAY-2024-06-15", created, debtor);
+//! for invoice in &incoming {
+//!     let date = suggested_execution_date(invoice, today);
+//!     batch.add(CreditTransferTransaction::from_invoice(invoice, date)?);
+//! }
+//! let xml = batch.to_xml()?;
+//! ```
+
+mod pain001;
+mod pain008;
+
+pub use pain001::{
+    CreditTransferBatch, CreditTransferTransaction, PAIN_001_NAMESPACE, suggested_execution_date,
+};
+pub use pain008::{
+    DirectDebitBatch, DirectDebitTransaction, PAIN_008_NAMESPACE, SepaScheme, SequenceType,
+    is_valid_creditor_id,
+};
+
+use rust_decimal::Decimal;
+
+use crate::core::iban::{
+    compact_identifier, is_valid_bic, is_valid_creditor_reference, is_valid_iban, normalize_iban,
+};
+use crate::core::{Invoice, PaymentInstructions, RechnungError};
+use crate::xrechnung::xml_utils::{XmlWriter, format_amount};
+
+/// An account holder taking part in a SEPA payment (debtor or creditor).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SepaAccount {
+    /// Account holder name (max 70 chars).
+    pub name: String,
+    /// IBAN, without spaces.
+    pub iban: String,
+    /// BIC of the account-servicing bank (optional for SEPA payments).
+    pub bic: Option<String>,
+}
+
+impl SepaAccount {
+    /// Create an account without BIC.
+    pub fn new(name: impl Into<String>, iban: impl AsRef<str>) -> Self {
+        Self {
+            name: name.into(),
+            iban: normalize_iban(iban.as_ref()),
+            bic: None,
+        }
+    }
+
+    /// Set the BIC.
+    pub fn with_bic(mut self, bic: impl Into<String>) -> Self {
+        self.bic = Some(bic.into());
+        self
+    }
+
+    fn validate(&self, role: &str) -> Result<(), RechnungError> {
+        check_text(&format!("{role} name"), &self.name, 70)?;
+        if !is_valid_iban(&self.iban) {
+            return Err(RechnungError::Validation(format!(
+                "{role} IBAN '{}' is invalid",
+                self.iban
+            )));
+        }
+        if let Some(bic) = &self.bic {
+            if !is_valid_bic(bic) {
+                return Err(RechnungError::Validation(format!(
+                    "{role} BIC '{bic}' is invalid"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remittance information of a transaction (`RmtInf`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SepaRemittance {
+    /// Free text (`Ustrd`, max 140 chars).
+    Unstructured(String),
+    /// ISO 11649 creditor reference (`Strd/CdtrRefInf`, type SCOR).
+    Structured(String),
+}
+
+impl SepaRemittance {
+    /// Remittance from the invoice's BT-83, falling back to the invoice number.
+    ///
+    /// A valid RF creditor reference is sent structured.
+    fn from_invoice(invoice: &Invoice, payment: &PaymentInstructions) -> Self {
+        match &payment.remittance_info {
+            Some(r) if is_valid_creditor_reference(r) => Self::Structured(compact_identifier(r)),
+            Some(r) => Self::Unstructured(truncate(r, 140)),
+            None => Self::Unstructured(truncate(&invoice.number, 140)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), RechnungError> {
+        match self {
+            Self::Unstructured(text) => check_text("remittance information", text, 140),
+            Self::Structured(r) if !is_valid_creditor_reference(r) => Err(
+                RechnungError::Validation(format!("creditor reference '{r}' is invalid")),
+            ),
+            Self::Structured(_) => Ok(()),
+        }
+    }
+
+    fn write(&self, w: &mut XmlWriter) -> Result<(), RechnungError> {
+        w.start_element("RmtInf")?;
+        match self {
+            Self::Unstructured(text) => {
+                w.text_element("Ustrd", text)?;
+            }
+            Self::Structured(reference) => {
+                w.start_element("Strd")?;
+                w.start_element("CdtrRefInf")?;
+                w.start_element("Tp")?;
+                w.start_element("CdOrPrtry")?;
+                w.text_element("Cd", "SCOR")?;
+                w.end_element("CdOrPrtry")?;
+                w.end_element("Tp")?;
+                w.text_element("Ref", reference)?;
+                w.end_element("CdtrRefInf")?;
+                w.end_element("Strd")?;
+            }
+        }
+        w.end_element("RmtInf")?;
+        Ok(())
+    }
+}
+
+/// Ensure the invoice is payable through SEPA (EUR) and return its payment instructions.
+fn sepa_payment(invoice: &Invoice) -> Result<&PaymentInstructions, RechnungError> {
+    if !invoice.currency_code.eq_ignore_ascii_case("EUR") {
+        return Err(RechnungError::Builder(format!(
+            "SEPA payments must be in EUR, invoice {} is in {}",
+            invoice.number, invoice.currency_code
+        )));
+    }
+    invoice.payment.as_ref().ok_or_else(|| {
+        RechnungError::Builder(format!(
+            "invoice {} has no payment instructions",
+            invoice.number
+        ))
+    })
+}
+
+/// Check a transaction amount: 0.01 – 999,999,999.99 with at most 2 decimals.
+fn check_amount(amount: Decimal, id: &str) -> Result<(), RechnungError> {
+    if amount < Decimal::new(1, 2)
+        || amount > Decimal::new(99_999_999_999, 2)
+        || amount.normalize().scale() > 2
+    {
+        return Err(RechnungError::Validation(format!(
+            "amount {amount} of transaction {id} must be between 0.01 and 999999999.99 with at most 2 decimals"
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a text field is non-empty and within the length limit.
+fn check_text(field: &str, value: &str, max: usize) -> Result<(), RechnungError> {
+    let len = value.trim().chars().count();
+    if len == 0 || len > max {
+        return Err(RechnungError::Validation(format!(
+            "{field} must have 1 to {max} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Cut a text to at most `max` characters.
+fn truncate(text: &str, max: usize) -> String {
+    text.trim().chars().take(max).collect()
+}
+
+/// `PmtInfId` of the `index`-th payment block: the message ID shortened so
+/// the "-n" suffix fits in 35 characters and block IDs stay unique.
+fn block_id(message_id: &str, index: usize) -> String {
+    let suffix = format!("-{}", index + 1);
+    format!("{}{suffix}", truncate(message_id, 35 - suffix.len()))
+}
+
+/// Write `GrpHdr` with the control sum over all transactions.
+fn write_group_header(
+    w: &mut XmlWriter,
+    message_id: &str,
+    created: chrono::NaiveDateTime,
+    initiator: &str,
+    amounts: &[Decimal],
+) -> Result<(), RechnungError> {
+    w.start_element("GrpHdr")?;
+    w.text_element("MsgId", message_id)?;
+    w.text_element("CreDtTm", &created.format("%Y-%m-%dT%H:%M:%S").to_string())?;
+    w.text_element("NbOfTxs", &amounts.len().to_string())?;
+    w.text_element("CtrlSum", &control_sum(amounts))?;
+    w.start_element("InitgPty")?;
+    w.text_element("Nm", initiator)?;
+    w.end_element("InitgPty")?;
+    w.end_element("GrpHdr")?;
+    Ok(())
+}
+
+/// Write `NbOfTxs` and `CtrlSum` of a payment information block.
+fn write_block_totals(w: &mut XmlWriter, amounts: &[Decimal]) -> Result<(), RechnungError> {
+    w.text_element("NbOfTxs", &amounts.len().to_string())?;
+    w.text_element("CtrlSum", &control_sum(amounts))?;
+    Ok(())
+}
+
+fn control_sum(amounts: &[Decimal]) -> String {
+    format_amount(amounts.iter().sum(), "EUR")
+}
+
+/// Write an account (`DbtrAcct`, `CdtrAcct`) identified by IBAN.
+fn write_account(w: &mut XmlWriter, element: &str, iban: &str) -> Result<(), RechnungError> {
+    w.start_element(element)?;
+    w.start_element("Id")?;
+    w.text_element("IBAN", iban)?;
+    w.end_element("Id")?;
+    w.end_element(element)?;
+    Ok(())
+}
+
+/// Write a financial institution (`DbtrAgt`, `CdtrAgt`) by BIC, or
+/// `NOTPROVIDED` where the agent is mandatory but unknown.
+fn write_agent(w: &mut XmlWriter, element: &str, bic: Option<&str>) -> Result<(), RechnungError> {
+    w.start_element(element)?;
+    w.start_element("FinInstnId")?;
+    match bic {
+        Some(bic) => {
+            w.text_element("BICFI", bic)?;
+        }
+        None => {
+            w.start_element("Othr")?;
+            w.text_element("Id", "NOTPROVIDED")?;
+            w.end_element("Othr")?;
+        }
+    }
+    w.end_element("FinInstnId")?;
+    w.end_element(element)?;
+    Ok(())
+}
+
+/// Write a party name element (`Dbtr`, `Cdtr`).
+fn write_party(w: &mut XmlWriter, element: &str, name: &str) -> Result<(), RechnungError> {
+    w.start_element(element)?;
+    w.text_element("Nm", name.trim())?;
+    w.end_element(element)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_validation() {
+        let account = SepaAccount::new("ACME GmbH", "DE89 3704 0044 0532 0130 00");
+        assert_eq!(account.iban, "DE89370400440532013000");
+        assert!(account.validate("debtor").is_ok());
+        assert!(
+            account
+                .clone()
+                .with_bic("COBADEFF")
+                .validate("debtor")
+                .is_ok()
+        );
+        assert!(account.clone().with_bic("COBA").validate("debtor").is_err());
+        let err = SepaAccount::new("ACME", "DE88370400440532013000")
+            .validate("debtor")
+            .unwrap_err();
+        assert!(err.to_string().contains("debtor IBAN"));
+    }
+
+    #[test]
+    fn text_limits() {
+        assert!(check_text("name", "", 70).is_err());
+        assert!(check_text("name", &"x".repeat(71), 70).is_err());
+        assert_eq!(truncate(&"ä".repeat(50), 35).chars().count(), 35);
+    }
+}
@@ -0,0 +1,236 @@
+//! SEPA credit transfer initiation (`pain.001.001.09`).
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+
+use super::{
+    SepaAccount, SepaRemittance, block_id, check_amount, check_text, sepa_payment, truncate,
+    write_account, write_agent, write_block_totals, write_group_header, write_party,
+};
+use crate::core::iban::normalize_iban;
+use crate::core::skonto::{discount_schedule, discounted_amount_due};
+use crate::core::{Invoice, RechnungError};
+use crate::xrechnung::xml_utils::{XmlWriter, format_amount};
+
+/// XML namespace of `pain.001.001.09`.
+pub const PAIN_001_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:pain.001.001.09";
+
+/// A single credit transfer (`CdtTrfTxInf`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreditTransferTransaction {
+    /// End-to-end reference passed on to the creditor (max 35 chars).
+    pub end_to_end_id: String,
+    /// Amount in EUR.
+    pub amount: Decimal,
+    /// Beneficiary.
+    pub creditor: SepaAccount,
+    /// Remittance information (Verwendungszweck).
+    pub remittance: Option<SepaRemittance>,
+    /// Requested execution date.
+    pub execution_date: NaiveDate,
+}
+
+impl CreditTransferTransaction {
+    /// Build a transfer paying an incoming invoice on `execution_date`.
+    ///
+    /// Pays the invoice's credit transfer account (BG-17) to the payee
+    /// (BG-10), account name (BT-85) or seller, in that order. The amount is
+    /// the amount due less any Skonto still open on the execution date; the
+    /// invoice number is used as end-to-end reference.
+    pub fn from_invoice(
+        invoice: &Invoice,
+        execution_date: NaiveDate,
+    ) -> Result<Self, RechnungError> {
+        let payment = sepa_payment(invoice)?;
+        let transfer = payment.credit_transfer.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no credit transfer account",
+                invoice.number
+            ))
+        })?;
+        if invoice.totals.is_none() {
+            return Err(RechnungError::Builder(
+                "totals must be calculated before creating a payment".into(),
+            ));
+        }
+        let amount = discounted_amount_due(invoice, execution_date);
+        if amount <= Decimal::ZERO {
+            return Err(RechnungError::Builder(format!(
+                "invoice {} has no amount due",
+                invoice.number
+            )));
+        }
+
+        let name = invoice
+            .payee
+            .as_ref()
+            .map(|p| p.name.clone())
+            .or_else(|| transfer.account_name.clone())
+            .unwrap_or_else(|| invoice.seller.name.clone());
+
+        Ok(Self {
+            end_to_end_id: truncate(&invoice.number, 35),
+            amount,
+            creditor: SepaAccount {
+                name: truncate(&name, 70),
+                iban: normalize_iban(&transfer.iban),
+                bic: transfer.bic.clone(),
+            },
+            remittance: Some(SepaRemittance::from_invoice(invoice, payment)),
+            execution_date,
+        })
+    }
+
+    fn validate(&self) -> Result<(), RechnungError> {
+        check_text("end-to-end ID", &self.end_to_end_id, 35)?;
+        check_amount(self.amount, &self.end_to_end_id)?;
+        self.creditor.validate("creditor")?;
+        if let Some(remittance) = &self.remittance {
+            remittance.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Best execution date for paying an invoice, not earlier than `today`.
+///
+/// Picks the last day of the most favourable Skonto tier still open;
+/// otherwise the due date (BT-9), or `today` if the invoice is already due
+/// or has no due date.
+pub fn suggested_execution_date(invoice: &Invoice, today: NaiveDate) -> NaiveDate {
+    let best = discount_schedule(invoice)
+        .into_iter()
+        .filter(|(date, _)| *date >= today)
+        .min_by(|(d1, a1), (d2, a2)| a1.cmp(a2).then(d2.cmp(d1)));
+    match (best, invoice.due_date) {
+        (Some((date, _)), _) => date,
+        (None, Some(due)) => due.max(today),
+        (None, None) => today,
+    }
+}
+
+/// A credit transfer initiation message (`CstmrCdtTrfInitn`).
+///
+/// Transactions are grouped into one `PmtInf` block per execution date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreditTransferBatch {
+    /// Message identification, unique per submission (max 35 chars).
+    pub message_id: String,
+    /// Creation timestamp.
+    pub created: NaiveDateTime,
+    /// Account to be debited.
+    pub debtor: SepaAccount,
+    /// Request a single booking per payment block (`BtchBookg`, default true).
+    pub batch_booking: bool,
+    /// Transfers in this message.
+    pub transactions: Vec<CreditTransferTransaction>,
+}
+
+impl CreditTransferBatch {
+    /// Create an empty batch debiting `debtor`.
+    pub fn new(message_id: impl Into<String>, created: NaiveDateTime, debtor: SepaAccount) -> Self {
+        Self {
+            message_id: message_id.into(),
+            created,
+            debtor,
+            batch_booking: true,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Add a transfer.
+    pub fn add(&mut self, transaction: CreditTransferTransaction) -> &mut Self {
+        self.transactions.push(transaction);
+        self
+    }
+
+    /// Total amount over all transactions.
+    pub fn control_sum(&self) -> Decimal {
+        self.transactions.iter().map(|t| t.amount).sum()
+    }
+
+    /// Check message, debtor and transactions against the SEPA rules.
+    pub fn validate(&self) -> Result<(), RechnungError> {
+        check_text("message ID", &self.message_id, 35)?;
+        self.debtor.validate("debtor")?;
+        if self.transactions.is_empty() {
+            return Err(RechnungError::Validation(
+                "credit transfer batch has no transactions".into(),
+            ));
+        }
+        self.transactions.iter().try_for_each(|t| t.validate())
+    }
+
+    /// Generate the `pain.001.001.09` XML document.
+    pub fn to_xml(&self) -> Result<String, RechnungError> {
+        self.validate()?;
+
+        let mut by_date: BTreeMap<NaiveDate, Vec<&CreditTransferTransaction>> = BTreeMap::new();
+        for tx in &self.transactions {
+            by_date.entry(tx.execution_date).or_default().push(tx);
+        }
+
+        let mut w = XmlWriter::new()?;
+        w.start_element_with_attrs("Document", &[("xmlns", PAIN_001_NAMESPACE)])?;
+        w.start_element("CstmrCdtTrfInitn")?;
+        let amounts: Vec<Decimal> = self.transactions.iter().map(|t| t.amount).collect();
+        write_group_header(
+            &mut w,
+            &self.message_id,
+            self.created,
+            &self.debtor.name,
+            &amounts,
+        )?;
+
+        for (i, (date, txs)) in by_date.iter().enumerate() {
+            w.start_element("PmtInf")?;
+            w.text_element("PmtInfId", &block_id(&self.message_id, i))?;
+            w.text_element("PmtMtd", "TRF")?;
+            w.text_element("BtchBookg", &self.batch_booking.to_string())?;
+            let amounts: Vec<Decimal> = txs.iter().map(|t| t.amount).collect();
+            write_block_totals(&mut w, &amounts)?;
+            w.start_element("PmtTpInf")?;
+            w.start_element("SvcLvl")?;
+            w.text_element("Cd", "SEPA")?;
+            w.end_element("SvcLvl")?;
+            w.end_element("PmtTpInf")?;
+            w.start_element("ReqdExctnDt")?;
+            w.text_element("Dt", &date.format("%Y-%m-%d").to_string())?;
+            w.end_element("ReqdExctnDt")?;
+            write_party(&mut w, "Dbtr", &self.debtor.name)?;
+            write_account(&mut w, "DbtrAcct", &self.debtor.iban)?;
+            write_agent(&mut w, "DbtrAgt", self.debtor.bic.as_deref())?;
+            w.text_element("ChrgBr", "SLEV")?;
+
+            for tx in txs {
+                w.start_element("CdtTrfTxInf")?;
+                w.start_element("PmtId")?;
+                w.text_element("EndToEndId", &tx.end_to_end_id)?;
+                w.end_element("PmtId")?;
+                w.start_element("Amt")?;
+                w.text_element_with_attrs(
+                    "InstdAmt",
+                    &format_amount(tx.amount, "EUR"),
+                    &[("Ccy", "EUR")],
+                )?;
+                w.end_element("Amt")?;
+                if let Some(bic) = &tx.creditor.bic {
+                    write_agent(&mut w, "CdtrAgt", Some(bic))?;
+                }
+                write_party(&mut w, "Cdtr", &tx.creditor.name)?;
+                write_account(&mut w, "CdtrAcct", &tx.creditor.iban)?;
+                if let Some(remittance) = &tx.remittance {
+                    remittance.write(&mut w)?;
+                }
+                w.end_element("CdtTrfTxInf")?;
+            }
+            w.end_element("PmtInf")?;
+        }
+
+        w.end_element("CstmrCdtTrfInitn")?;
+        w.end_element("Document")?;
+        w.into_string()
+    }
+}
@@ -0,0 +1,383 @@
+//! SEPA direct debit initiation (`pain.008.001.08`).
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+
+use super::{
+    SepaAccount, SepaRemittance, block_id, check_amount, check_text, sepa_payment, truncate,
+    write_account, write_agent, write_block_totals, write_group_header, write_party,
+};
+use crate::core::iban::{compact_identifier, iban_length, mod97};
+use crate::core::skonto::discounted_amount_due;
+use crate::core::{Invoice, RechnungError};
+use crate::xrechnung::xml_utils::{XmlWriter, format_amount};
+
+/// XML namespace of `pain.008.001.08`.
+pub const PAIN_008_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:pain.008.001.08";
+
+/// SEPA direct debit scheme (`LclInstrm`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SepaScheme {
+    /// CORE — Basislastschrift, consumers and businesses.
+    Core,
+    /// B2B — Firmenlastschrift, businesses only, no refund right.
+    B2b,
+}
+
+impl SepaScheme {
+    /// Local instrument code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Core => "CORE",
+            Self::B2b => "B2B",
+        }
+    }
+}
+
+/// Position of a collection within its mandate (`SeqTp`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SequenceType {
+    /// FRST — first collection of a recurring mandate.
+    First,
+    /// RCUR — follow-up collection (may also be used for the first one).
+    Recurring,
+    /// FNAL — last collection of a recurring mandate.
+    Final,
+    /// OOFF — one-off mandate.
+    OneOff,
+}
+
+impl SequenceType {
+    /// Sequence type code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::First => "FRST",
+            Self::Recurring => "RCUR",
+            Self::Final => "FNAL",
+            Self::OneOff => "OOFF",
+        }
+    }
+}
+
+/// Validate a SEPA creditor identifier (Gläubiger-ID), e.g. `DE98ZZZ09999999999`.
+///
+/// The check digits cover country code and national identifier; the
+/// three-character creditor business code is excluded.
+pub fn is_valid_creditor_id(creditor_id: &str) -> bool {
+    let id = compact_identifier(creditor_id);
+    if !id.is_ascii() || id.len() < 8 || id.len() > 35 {
+        return false;
+    }
+    if id.starts_with("DE") && id.len() != 18 {
+        return false;
+    }
+    let (country, check, national) = (&id[..2], &id[2..4], &id[7..]);
+    country.chars().all(|c| c.is_ascii_uppercase())
+        && check.chars().all(|c| c.is_ascii_digit())
+        && id[4..7].chars().all(|c| c.is_ascii_alphanumeric())
+        && mod97(&format!("{national}{country}{check}")) == Some(1)
+        && iban_length(country).is_some()
+}
+
+/// A single direct debit (`DrctDbtTxInf`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectDebitTransaction {
+    /// End-to-end reference passed on to the debtor (max 35 chars).
+    pub end_to_end_id: String,
+    /// Amount in EUR.
+    pub amount: Decimal,
+    /// Account to be debited.
+    pub debtor: SepaAccount,
+    /// Mandate reference (BT-89, max 35 chars).
+    pub mandate_id: String,
+    /// Date the mandate was signed.
+    pub mandate_signed: NaiveDate,
+    /// Sequence type (default RCUR).
+    pub sequence: SequenceType,
+    /// Creditor identifier stated on the invoice (BT-90), checked against the batch.
+    pub creditor_id: Option<String>,
+    /// Remittance information (Verwendungszweck).
+    pub remittance: Option<SepaRemittance>,
+    /// Requested collection date.
+    pub collection_date: NaiveDate,
+}
+
+impl DirectDebitTransaction {
+    /// Build a collection of an outgoing invoice on `collection_date`.
+    ///
+    /// Debits the buyer's account (BT-91) under the mandate (BT-89) signed on
+    /// `mandate_signed`. The amount is the amount due less any Skonto still
+    /// open on the collection date.
+    pub fn from_invoice(
+        invoice: &Invoice,
+        mandate_signed: NaiveDate,
+        collection_date: NaiveDate,
+    ) -> Result<Self, RechnungError> {
+        let payment = sepa_payment(invoice)?;
+        let debit = payment.direct_debit.as_ref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no direct debit information",
+                invoice.number
+            ))
+        })?;
+        let mandate_id = debit.mandate_id.as_deref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no mandate reference (BT-89)",
+                invoice.number
+            ))
+        })?;
+        let iban = debit.debited_account_id.as_deref().ok_or_else(|| {
+            RechnungError::Builder(format!(
+                "invoice {} has no debited account (BT-91)",
+                invoice.number
+            ))
+        })?;
+        if invoice.totals.is_none() {
+            return Err(RechnungError::Builder(
+                "totals must be calculated before creating a payment".into(),
+            ));
+        }
+        let amount = discounted_amount_due(invoice, collection_date);
+        if amount <= Decimal::ZERO {
+            return Err(RechnungError::Builder(format!(
+                "invoice {} has no amount due",
+                invoice.number
+            )));
+        }
+
+        Ok(Self {
+            end_to_end_id: truncate(&invoice.number, 35),
+            amount,
+            debtor: SepaAccount::new(truncate(&invoice.buyer.name, 70), iban),
+            mandate_id: mandate_id.trim().to_string(),
+            mandate_signed,
+            sequence: SequenceType::Recurring,
+            creditor_id: debit.creditor_id.clone(),
+            remittance: Some(SepaRemittance::from_invoice(invoice, payment)),
+            collection_date,
+        })
+    }
+
+    fn validate(&self, creditor_id: &str) -> Result<(), RechnungError> {
+        check_text("end-to-end ID", &self.end_to_end_id, 35)?;
+        check_amount(self.amount, &self.end_to_end_id)?;
+        check_text("mandate ID", &self.mandate_id, 35)?;
+        self.debtor.validate("debtor")?;
+        if let Some(remittance) = &self.remittance {
+            remittance.validate()?;
+        }
+        if self.mandate_signed > self.collection_date {
+            return Err(RechnungError::Validation(format!(
+                "mandate {} is signed after the collection date",
+                self.mandate_id
+            )));
+        }
+        match &self.creditor_id {
+            Some(id) if compact_identifier(id) != creditor_id => {
+                Err(RechnungError::Validation(format!(
+                    "transaction {} names creditor ID {id}, batch uses {creditor_id}",
+                    self.end_to_end_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A direct debit initiation message (`CstmrDrctDbtInitn`).
+///
+/// Transactions are grouped into one `PmtInf` block per collection date and
+/// sequence type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectDebitBatch {
+    /// Message identification, unique per submission (max 35 chars).
+    pub message_id: String,
+    /// Creation timestamp.
+    pub created: NaiveDateTime,
+    /// Account to be credited.
+    pub creditor: SepaAccount,
+    /// SEPA creditor identifier (Gläubiger-ID).
+    pub creditor_id: String,
+    /// CORE or B2B scheme.
+    pub scheme: SepaScheme,
+    /// Request a single booking per payment block (`BtchBookg`, default true).
+    pub batch_booking: bool,
+    /// Collections in this message.
+    pub transactions: Vec<DirectDebitTransaction>,
+}
+
+impl DirectDebitBatch {
+    /// Create an empty batch crediting `creditor`.
+    pub fn new(
+        message_id: impl Into<String>,
+        created: NaiveDateTime,
+        creditor: SepaAccount,
+        creditor_id: impl AsRef<str>,
+        scheme: SepaScheme,
+    ) -> Self {
+        Self {
+            message_id: message_id.into(),
+            created,
+            creditor,
+            creditor_id: compact_identifier(creditor_id.as_ref()),
+            scheme,
+            batch_booking: true,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Add a collection.
+    pub fn add(&mut self, transaction: DirectDebitTransaction) -> &mut Self {
+        self.transactions.push(transaction);
+        self
+    }
+
+    /// Total amount over all transactions.
+    pub fn control_sum(&self) -> Decimal {
+        self.transactions.iter().map(|t| t.amount).sum()
+    }
+
+    /// Check message, creditor and transactions against the SEPA rules.
+    pub fn validate(&self) -> Result<(), RechnungError> {
+        check_text("message ID", &self.message_id, 35)?;
+        self.creditor.validate("creditor")?;
+        if !is_valid_creditor_id(&self.creditor_id) {
+            return Err(RechnungError::Validation(format!(
+                "creditor ID '{}' is invalid",
+                self.creditor_id
+            )));
+        }
+        if self.transactions.is_empty() {
+            return Err(RechnungError::Validation(
+                "direct debit batch has no transactions".into(),
+            ));
+        }
+        for tx in &self.transactions {
+            tx.validate(&self.creditor_id)?;
+            if tx.collection_date < self.created.date() {
+                return Err(RechnungError::Validation(format!(
+                    "collection date {} of transaction {} lies before the creation date",
+                    tx.collection_date, tx.end_to_end_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate the `pain.008.001.08` XML document.
+    pub fn to_xml(&self) -> Result<String, RechnungError> {
+        self.validate()?;
+
+        let mut blocks: BTreeMap<(NaiveDate, &'static str), Vec<&DirectDebitTransaction>> =
+            BTreeMap::new();
+        for tx in &self.transactions {
+            blocks
+                .entry((tx.collection_date, tx.sequence.code()))
+                .or_default()
+                .push(tx);
+        }
+
+        let mut w = XmlWriter::new()?;
+        w.start_element_with_attrs("Document", &[("xmlns", PAIN_008_NAMESPACE)])?;
+        w.start_element("CstmrDrctDbtInitn")?;
+        let amounts: Vec<Decimal> = self.transactions.iter().map(|t| t.amount).collect();
+        write_group_header(
+            &mut w,
+            &self.message_id,
+            self.created,
+            &self.creditor.name,
+            &amounts,
+        )?;
+
+        for (i, ((date, sequence), txs)) in blocks.iter().enumerate() {
+            w.start_element("PmtInf")?;
+            w.text_element("PmtInfId", &block_id(&self.message_id, i))?;
+            w.text_element("PmtMtd", "DD")?;
+            w.text_element("BtchBookg", &self.batch_booking.to_string())?;
+            let amounts: Vec<Decimal> = txs.iter().map(|t| t.amount).collect();
+            write_block_totals(&mut w, &amounts)?;
+            w.start_element("PmtTpInf")?;
+            w.start_element("SvcLvl")?;
+            w.text_element("Cd", "SEPA")?;
+            w.end_element("SvcLvl")?;
+            w.start_element("LclInstrm")?;
+            w.text_element("Cd", self.scheme.code())?;
+            w.end_element("LclInstrm")?;
+            w.text_element("SeqTp", sequence)?;
+            w.end_element("PmtTpInf")?;
+            w.text_element("ReqdColltnDt", &date.format("%Y-%m-%d").to_string())?;
+            write_party(&mut w, "Cdtr", &self.creditor.name)?;
+            write_account(&mut w, "CdtrAcct", &self.creditor.iban)?;
+            write_agent(&mut w, "CdtrAgt", self.creditor.bic.as_deref())?;
+            w.text_element("ChrgBr", "SLEV")?;
+            w.start_element("CdtrSchmeId")?;
+            w.start_element("Id")?;
+            w.start_element("PrvtId")?;
+            w.start_element("Othr")?;
+            w.text_element("Id", &self.creditor_id)?;
+            w.start_element("SchmeNm")?;
+            w.text_element("Prtry", "SEPA")?;
+            w.end_element("SchmeNm")?;
+            w.end_element("Othr")?;
+            w.end_element("PrvtId")?;
+            w.end_element("Id")?;
+            w.end_element("CdtrSchmeId")?;
+
+            for tx in txs {
+                w.start_element("DrctDbtTxInf")?;
+                w.start_element("PmtId")?;
+                w.text_element("EndToEndId", &tx.end_to_end_id)?;
+                w.end_element("PmtId")?;
+                w.text_element_with_attrs(
+                    "InstdAmt",
+                    &format_amount(tx.amount, "EUR"),
+                    &[("Ccy", "EUR")],
+                )?;
+                w.start_element("DrctDbtTx")?;
+                w.start_element("MndtRltdInf")?;
+                w.text_element("MndtId", &tx.mandate_id)?;
+                w.text_element(
+                    "DtOfSgntr",
+                    &tx.mandate_signed.format("%Y-%m-%d").to_string(),
+                )?;
+                w.end_element("MndtRltdInf")?;
+                w.end_element("DrctDbtTx")?;
+                write_agent(&mut w, "DbtrAgt", tx.debtor.bic.as_deref())?;
+                write_party(&mut w, "Dbtr", &tx.debtor.name)?;
+                write_account(&mut w, "DbtrAcct", &tx.debtor.iban)?;
+                if let Some(remittance) = &tx.remittance {
+                    remittance.write(&mut w)?;
+                }
+                w.end_element("DrctDbtTxInf")?;
+            }
+            w.end_element("PmtInf")?;
+        }
+
+        w.end_element("CstmrDrctDbtInitn")?;
+        w.end_element("Document")?;
+        w.into_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creditor_id_check_digits() {
+        assert!(is_valid_creditor_id("DE98ZZZ09999999999"));
+        assert!(is_valid_creditor_id("de98 zzz 09999999999"));
+        assert!(!is_valid_creditor_id("DE97ZZZ09999999999"));
+        assert!(!is_valid_creditor_id("DE98ZZZ0999999999"));
+    }
+
+    #[test]
+    fn sequence_and_scheme_codes() {
+        assert_eq!(SequenceType::OneOff.code(), "OOFF");
+        assert_eq!(SepaScheme::B2b.code(), "B2B");
+    }
+}
@@ -0,0 +1,137 @@
+//! X.509 certificates of signers and their issuers.
+
+use std::ops::Range;
+
+use chrono::NaiveDateTime;
+use rsa::RsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+
+use super::SignatureError;
+use super::crypto::sha256;
+use super::der::{read_any, read_element, read_tlv, tlv};
+use crate::core::der::{distinguished_name, parse_time, subject_public_key_info};
+use crate::core::digest::{base64_decode, hex};
+
+/// An X.509 certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Subject distinguished name, e.g. `CN=Max Mustermann,O=ACME GmbH,C=DE`.
+    pub subject: String,
+    /// Issuer distinguished name.
+    pub issuer: String,
+    /// Serial number in hexadecimal.
+    pub serial: String,
+    /// Start of validity (UTC).
+    pub not_before: NaiveDateTime,
+    /// End of validity (UTC).
+    pub not_after: NaiveDateTime,
+    /// SHA-256 fingerprint of the DER encoding, in hexadecimal.
+    pub sha256_fingerprint: String,
+    /// The DER encoding.
+    pub der: Vec<u8>,
+    layout: Layout,
+}
+
+/// Byte ranges of the parts of `der` needed for signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+    serial: Range<usize>,
+    issuer: Range<usize>,
+}
+
+impl Certificate {
+    /// Read a DER-encoded certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self, SignatureError> {
+        let bad = || SignatureError::Certificate("not a DER-encoded X.509 certificate".into());
+        let offset = |part: &[u8]| {
+            let start = part.as_ptr() as usize - der.as_ptr() as usize;
+            start..start + part.len()
+        };
+        let (cert, _) = read_tlv(der, 0x30).ok_or_else(bad)?;
+        let (tbs, _) = read_tlv(cert, 0x30).ok_or_else(bad)?;
+        let mut rest = tbs;
+        if rest.first() == Some(&0xa0) {
+            rest = read_any(rest).ok_or_else(bad)?.2;
+        }
+        let (serial, rest) = read_tlv(rest, 0x02).ok_or_else(bad)?;
+        let (_, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+        let (issuer, rest) = read_element(rest).ok_or_else(bad)?;
+        let (validity, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+        let (subject, _) = read_element(rest).ok_or_else(bad)?;
+
+        let (tag, not_before, after) = read_any(validity).ok_or_else(bad)?;
+        let not_before = parse_time(tag, not_before).ok_or_else(bad)?;
+        let (tag, not_after, _) = read_any(after).ok_or_else(bad)?;
+        let not_after = parse_time(tag, not_after).ok_or_else(bad)?;
+        let name = |element: &[u8]| {
+            read_tlv(element, 0x30)
+                .and_then(|(content, _)| distinguished_name(content))
+                .ok_or_else(bad)
+        };
+
+        Ok(Self {
+            subject: name(subject)?,
+            issuer: name(issuer)?,
+            serial: hex(serial.strip_prefix(&[0]).unwrap_or(serial)),
+            not_before,
+            not_after,
+            sha256_fingerprint: hex(&sha256(der)),
+            layout: Layout {
+                serial: offset(serial),
+                issuer: offset(issuer),
+            },
+            der: der.to_vec(),
+        })
+    }
+
+    /// Read a PEM certificate (`-----BEGIN CERTIFICATE-----`).
+    pub fn from_pem(pem: &str) -> Result<Self, SignatureError> {
+        let body = pem
+            .split_once("-----BEGIN CERTIFICATE-----")
+            .and_then(|(_, rest)| rest.split_once("-----END CERTIFICATE-----"))
+            .map(|(body, _)| body)
+            .ok_or_else(|| SignatureError::Certificate("no PEM certificate found".into()))?;
+        let der = base64_decode(body)
+            .ok_or_else(|| SignatureError::Certificate("certificate is not valid Base64".into()))?;
+        Self::from_der(&der)
+    }
+
+    /// Common name (CN) of the subject.
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .split(',')
+            .find_map(|rdn| rdn.trim().strip_prefix("CN="))
+    }
+
+    /// Whether `at` lies within the validity period.
+    pub fn is_valid_at(&self, at: NaiveDateTime) -> bool {
+        self.not_before <= at && at <= self.not_after
+    }
+
+    /// The encoded issuer `Name`.
+    pub(crate) fn issuer_name(&self) -> &[u8] {
+        &self.der[self.layout.issuer.clone()]
+    }
+
+    /// The serial number as `INTEGER` content.
+    pub(crate) fn serial_number(&self) -> &[u8] {
+        &self.der[self.layout.serial.clone()]
+    }
+
+    /// The serial number as complete `INTEGER`.
+    pub(crate) fn serial_number_element(&self) -> Vec<u8> {
+        tlv(0x02, self.serial_number())
+    }
+
+    /// The RSA public key.
+    pub(crate) fn public_key(&self) -> Result<RsaPublicKey, SignatureError> {
+        subject_public_key_info(&self.der)
+            .and_then(|spki| RsaPublicKey::from_public_key_der(spki).ok())
+            .ok_or_else(|| {
+                SignatureError::Unsupported(format!(
+                    "certificate '{}' has no RSA public key",
+                    self.subject
+                ))
+            })
+    }
+}
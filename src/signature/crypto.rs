@@ -0,0 +1,23 @@
+//! SHA-256 digests and RSA PKCS#1 v1.5 signatures.
+
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+
+use super::SignatureError;
+
+pub(crate) fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// RSASSA-PKCS1-v1_5 signature with SHA-256.
+pub(crate) fn rsa_sign(key: &RsaPrivateKey, data: &[u8]) -> Result<Vec<u8>, SignatureError> {
+    key.sign(Pkcs1v15Sign::new::<Sha256>(), &sha256(data))
+        .map_err(|e| SignatureError::Unsupported(format!("RSA signing failed: {e}")))
+}
+
+/// Check an RSASSA-PKCS1-v1_5 signature with SHA-256.
+#[cfg(test)]
+pub(crate) fn rsa_verify(key: &rsa::RsaPublicKey, data: &[u8], signature: &[u8]) -> bool {
+    key.verify(Pkcs1v15Sign::new::<Sha256>(), &sha256(data), signature)
+        .is_ok()
+}
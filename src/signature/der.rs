@@ -0,0 +1,222 @@
+//! DER encoding and object identifiers for CMS, PKCS#12 and RFC 3161.
+//!
+//! Reading uses the TLV helpers in `core::der`; object identifiers are
+//! kept in their encoded form.
+
+// CMS signing is only used by PAdES
+#![cfg_attr(not(feature = "zugferd"), allow(dead_code))]
+
+use chrono::{DateTime, Utc};
+
+pub(crate) use crate::core::der::{read_any, read_tlv};
+
+pub(crate) const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+pub(crate) const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+pub(crate) const OID_RSA_ENCRYPTION: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+pub(crate) const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+pub(crate) const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+pub(crate) const OID_ENCRYPTED_DATA: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x06];
+
+pub(crate) const OID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+pub(crate) const OID_MESSAGE_DIGEST: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+pub(crate) const OID_SIGNING_TIME: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x05];
+pub(crate) const OID_SIGNING_CERTIFICATE_V2: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x02, 0x2f,
+];
+pub(crate) const OID_TIMESTAMP_TOKEN: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x02, 0x0e,
+];
+pub(crate) const OID_TST_INFO: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+pub(crate) const OID_KEY_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x01,
+];
+pub(crate) const OID_SHROUDED_KEY_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x02,
+];
+pub(crate) const OID_CERT_BAG: &[u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x03,
+];
+pub(crate) const OID_X509_CERTIFICATE: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x16, 0x01];
+
+pub(crate) const OID_PBE_SHA1_3DES: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01, 0x03];
+pub(crate) const OID_PBE_SHA1_RC2_128: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01, 0x05];
+pub(crate) const OID_PBE_SHA1_RC2_40: &[u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x01, 0x06];
+pub(crate) const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+pub(crate) const OID_PBKDF2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+pub(crate) const OID_HMAC_SHA1: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x07];
+pub(crate) const OID_HMAC_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09];
+pub(crate) const OID_AES128_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02];
+pub(crate) const OID_AES192_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x16];
+pub(crate) const OID_AES256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+pub(crate) const OID_DES_EDE3_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+
+/// One TLV with the given tag.
+pub(crate) fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// `SEQUENCE` of already encoded elements.
+pub(crate) fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(0x30, &parts.concat())
+}
+
+/// `SET OF` already encoded elements, sorted as DER requires.
+pub(crate) fn set_of(mut items: Vec<Vec<u8>>) -> Vec<u8> {
+    items.sort();
+    tlv(0x31, &items.concat())
+}
+
+pub(crate) fn oid(encoded: &[u8]) -> Vec<u8> {
+    tlv(0x06, encoded)
+}
+
+pub(crate) fn octet_string(content: &[u8]) -> Vec<u8> {
+    tlv(0x04, content)
+}
+
+/// `INTEGER` from an unsigned big-endian magnitude.
+pub(crate) fn integer(magnitude: &[u8]) -> Vec<u8> {
+    let trimmed = match magnitude.iter().position(|b| *b != 0) {
+        Some(i) => &magnitude[i..],
+        None => &[0][..],
+    };
+    if trimmed[0] & 0x80 != 0 {
+        tlv(0x02, &[&[0][..], trimmed].concat())
+    } else {
+        tlv(0x02, trimmed)
+    }
+}
+
+/// `AlgorithmIdentifier` without parameters, or with `NULL` parameters.
+pub(crate) fn algorithm(encoded_oid: &[u8], null_parameters: bool) -> Vec<u8> {
+    if null_parameters {
+        sequence(&[&oid(encoded_oid), &[0x05, 0x00]])
+    } else {
+        sequence(&[&oid(encoded_oid)])
+    }
+}
+
+/// `GeneralizedTime` in UTC, whole seconds.
+pub(crate) fn generalized_time(time: DateTime<Utc>) -> Vec<u8> {
+    tlv(0x18, time.format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+/// `UTCTime` for years 1950–2049, `GeneralizedTime` otherwise (RFC 5652
+/// signing time).
+pub(crate) fn time(time: DateTime<Utc>) -> Vec<u8> {
+    use chrono::Datelike;
+    if (1950..2050).contains(&time.year()) {
+        tlv(0x17, time.format("%y%m%d%H%M%SZ").to_string().as_bytes())
+    } else {
+        generalized_time(time)
+    }
+}
+
+/// Read one TLV; returns its complete encoding and the remaining input.
+pub(crate) fn read_element(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (_, _, rest) = read_any(input)?;
+    Some((&input[..input.len() - rest.len()], rest))
+}
+
+/// The elements of a constructed value's content, as complete encodings.
+pub(crate) fn elements(mut content: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut items = Vec::new();
+    while !content.is_empty() {
+        let (item, rest) = read_element(content)?;
+        items.push(item);
+        content = rest;
+    }
+    Some(items)
+}
+
+/// Content of an `OCTET STRING`, also in constructed (segmented) form.
+pub(crate) fn octets(tag: u8, content: &[u8]) -> Option<Vec<u8>> {
+    match tag {
+        0x04 | 0x80 => Some(content.to_vec()),
+        0x24 | 0xa0 => {
+            let mut out = Vec::new();
+            for part in elements(content)? {
+                let (tag, inner, _) = read_any(part)?;
+                out.extend(octets(tag, inner)?);
+            }
+            Some(out)
+        }
+        _ => None,
+    }
+}
+
+/// Dotted notation of an encoded object identifier, for messages.
+pub(crate) fn oid_string(encoded: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for byte in encoded {
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lengths_round_trip() {
+        let content = vec![7u8; 300];
+        let encoded = tlv(0x04, &content);
+        assert_eq!(&encoded[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        let (tag, read, rest) = read_any(&encoded).unwrap();
+        assert_eq!((tag, read.len(), rest.len()), (0x04, 300, 0));
+    }
+
+    #[test]
+    fn integers_are_minimal_and_positive() {
+        assert_eq!(integer(&[0, 0, 0x10, 0x01]), vec![0x02, 0x02, 0x10, 0x01]);
+        assert_eq!(integer(&[0x80]), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(&[]), vec![0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn oid_notation() {
+        assert_eq!(oid_string(OID_SHA256), "2.16.840.1.101.3.4.2.1");
+        assert_eq!(
+            oid_string(OID_SIGNING_CERTIFICATE_V2),
+            "1.2.840.113549.1.9.16.2.47"
+        );
+    }
+}
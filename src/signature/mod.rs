@@ -0,0 +1,137 @@
+//! Electronic signatures on invoices: PAdES for ZUGFeRD PDFs and XAdES
+//! for UBL and CII documents.
+//!
+//! The signer is a [`SigningIdentity`] read from a local PKCS#12 file
+//! (`.p12`/`.pfx`) with an RSA key. Signatures use SHA-256 and carry the
+//! signing certificate as a signed attribute (ETSI baseline B-B).
+//! With a [`TimestampClient`] for an RFC 3161 time-stamping authority
+//! the signature is time-stamped as well (B-T).
+//!
+//! - [`sign_pdf`] adds an invisible `ETSI.CAdES.detached` signature to a
+//!   PDF (for example the PDF/A-3 output of
+//!   [`embed_in_pdf`](crate::zugferd::embed_in_pdf)) as an incremental
+//!   update.
+//! - [`sign_xml`] adds an enveloped XAdES signature: in UBL inside
+//!   `ext:UBLExtensions`, in CII (which has no extension point) as the last
+//!   child of the root element.
+//!
+//! Incoming signed documents are not verified here; use a dedicated
+//! validator for signatures and certificate chains.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use faktura::signature::{SignOptions, SigningIdentity, sign_pdf};
+//! use faktura::zugferd::ZugferdProfile;
+//!
+//! let identity = SigningIdentity::from_pkcs12(&std::fs::read("seal.p12")?, "secret")?;
+//! let pdf = faktura::zugferd::embed_in_pdf(&pdf, &xml, ZugferdProfile::EN16931)?;
+//! let signed = sign_pdf(&pdf, &identity, &SignOptions::new().with_reason("Rechnung"))?;
+//! ```
+
+mod certificate;
+mod cms;
+mod crypto;
+mod der;
+#[cfg(feature = "zugferd")]
+mod pades;
+mod pkcs12;
+mod timestamp;
+mod xades;
+
+pub use certificate::Certificate;
+#[cfg(feature = "zugferd")]
+pub use pades::{sign_pdf, sign_pdf_timestamped};
+pub use pkcs12::SigningIdentity;
+pub use timestamp::{HttpTimestampClient, TimestampClient};
+pub use xades::{sign_xml, sign_xml_timestamped};
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+/// Error from reading keys and certificates or signing.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SignatureError {
+    /// The PKCS#12 file cannot be read or decrypted (wrong password).
+    Pkcs12(String),
+    /// Malformed certificate.
+    Certificate(String),
+    /// Malformed document to sign.
+    Parse(String),
+    /// Key, algorithm or structure not supported.
+    Unsupported(String),
+    /// The time-stamping authority failed or answered with an invalid token.
+    Timestamp(String),
+    /// Network or HTTP error.
+    Network(String),
+    /// The document cannot be signed, e.g. an encrypted PDF.
+    Document(String),
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pkcs12(e) => write!(f, "PKCS#12 error: {e}"),
+            Self::Certificate(e) => write!(f, "certificate error: {e}"),
+            Self::Parse(e) => write!(f, "signature parse error: {e}"),
+            Self::Unsupported(e) => write!(f, "unsupported: {e}"),
+            Self::Timestamp(e) => write!(f, "time-stamp error: {e}"),
+            Self::Network(e) => write!(f, "network error: {e}"),
+            Self::Document(e) => write!(f, "cannot sign document: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Signer-supplied details of a signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignOptions {
+    /// Claimed signing time; defaults to now. A time-stamp (B-T) adds a
+    /// trusted time independent of this value.
+    pub signing_time: Option<DateTime<Utc>>,
+    /// Reason for signing (PDF `/Reason`).
+    pub reason: Option<String>,
+    /// Place of signing (PDF `/Location`).
+    pub location: Option<String>,
+    /// How to reach the signer (PDF `/ContactInfo`).
+    pub contact_info: Option<String>,
+}
+
+impl SignOptions {
+    /// Options with all details unset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the claimed signing time.
+    pub fn with_signing_time(mut self, signing_time: DateTime<Utc>) -> Self {
+        self.signing_time = Some(signing_time);
+        self
+    }
+
+    /// Set the reason for signing.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Set the place of signing.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Set the signer's contact information.
+    pub fn with_contact_info(mut self, contact_info: impl Into<String>) -> Self {
+        self.contact_info = Some(contact_info.into());
+        self
+    }
+
+    /// The signing time to use.
+    pub(crate) fn signing_time(&self) -> DateTime<Utc> {
+        self.signing_time.unwrap_or_else(Utc::now)
+    }
+}
@@ -0,0 +1,388 @@
+//! PAdES baseline signatures (ETSI EN 319 142-1) on PDF documents.
+//!
+//! The signature is appended as an incremental update, so the signed
+//! revision keeps the original bytes, and with them the PDF/A-3 structure
+//! written by [`embed_in_pdf`](crate::zugferd::embed_in_pdf). The
+//! signature field is invisible: a widget with an empty rectangle on the
+//! first page.
+
+use std::ops::Range;
+
+use lopdf::{Dictionary, Document, IncrementalDocument, Object, ObjectId, StringFormat};
+
+use super::cms;
+use super::der::OID_DATA;
+use super::timestamp::TimestampClient;
+use super::{SignOptions, SignatureError, SigningIdentity};
+use crate::zugferd::{format_pdf_date, obj_to_string, pdf_text_string};
+
+/// Placeholder `ByteRange` values, replaced once offsets are known.
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+/// Room for the CMS structure beyond the certificates.
+const CONTENTS_RESERVE: usize = 8192;
+/// Further room for a time-stamp token with the TSA certificates.
+const TIMESTAMP_RESERVE: usize = 8192;
+
+/// Sign a PDF with an invisible PAdES-B-B signature
+/// (`ETSI.CAdES.detached`).
+///
+/// Reason, location and contact information from `options` go into the
+/// signature dictionary; the signing time becomes its `/M` entry.
+/// Encrypted PDFs are rejected.
+pub fn sign_pdf(
+    pdf: &[u8],
+    identity: &SigningIdentity,
+    options: &SignOptions,
+) -> Result<Vec<u8>, SignatureError> {
+    sign(pdf, identity, options, None)
+}
+
+/// Sign a PDF with an invisible PAdES-B-T signature: like [`sign_pdf`],
+/// plus a time-stamp over the signature value from `tsa`.
+pub fn sign_pdf_timestamped(
+    pdf: &[u8],
+    identity: &SigningIdentity,
+    options: &SignOptions,
+    tsa: &dyn TimestampClient,
+) -> Result<Vec<u8>, SignatureError> {
+    sign(pdf, identity, options, Some(tsa))
+}
+
+fn sign(
+    pdf: &[u8],
+    identity: &SigningIdentity,
+    options: &SignOptions,
+    tsa: Option<&dyn TimestampClient>,
+) -> Result<Vec<u8>, SignatureError> {
+    let doc = load(pdf)?;
+    if doc.is_encrypted() {
+        return Err(SignatureError::Document("the PDF is encrypted".into()));
+    }
+    let certificates: usize = std::iter::once(identity.certificate())
+        .chain(identity.chain())
+        .map(|c| c.der.len())
+        .sum();
+    let mut capacity =
+        CONTENTS_RESERVE + certificates + if tsa.is_some() { TIMESTAMP_RESERVE } else { 0 };
+    loop {
+        let (mut output, gap) = prepare(pdf, &doc, identity, options, capacity)?;
+        let digest = super::crypto::sha256(&[&output[..gap.start], &output[gap.end..]].concat());
+        let signature = cms::sign(identity, OID_DATA, None, &digest, None, tsa)?;
+        if signature.len() > capacity {
+            // Larger than estimated (long TSA chains): prepare again
+            capacity = signature.len() + CONTENTS_RESERVE;
+            continue;
+        }
+        let hex = crate::core::digest::hex(&signature);
+        output[gap.start + 1..gap.start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+        return Ok(output);
+    }
+}
+
+/// Write the incremental update with an empty signature of `capacity`
+/// bytes; returns the PDF and the byte range of the `/Contents` string.
+fn prepare(
+    pdf: &[u8],
+    doc: &Document,
+    identity: &SigningIdentity,
+    options: &SignOptions,
+    capacity: usize,
+) -> Result<(Vec<u8>, Range<usize>), SignatureError> {
+    let broken = |what: &str| SignatureError::Document(format!("broken PDF structure: {what}"));
+    let catalog_id = doc
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| broken("no catalog"))?;
+    let page_id = *doc
+        .get_pages()
+        .values()
+        .next()
+        .ok_or_else(|| broken("no pages"))?;
+
+    let mut update = IncrementalDocument::create_from(pdf.to_vec(), doc.clone());
+    let new = &mut update.new_document;
+    new.version = doc.version.lines().next().unwrap_or("1.7").to_string();
+
+    let mut signature = Dictionary::new();
+    signature.set("Type", Object::Name(b"Sig".to_vec()));
+    signature.set("Filter", Object::Name(b"Adobe.PPKLite".to_vec()));
+    signature.set("SubFilter", Object::Name(b"ETSI.CAdES.detached".to_vec()));
+    signature.set(
+        "ByteRange",
+        Object::Array(vec![
+            Object::Integer(0),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+        ]),
+    );
+    signature.set(
+        "Contents",
+        Object::String(vec![0; capacity], StringFormat::Hexadecimal),
+    );
+    signature.set(
+        "M",
+        Object::string_literal(format_pdf_date(&options.signing_time().fixed_offset())),
+    );
+    if let Some(name) = identity.certificate().common_name() {
+        signature.set("Name", pdf_text_string(name));
+    }
+    for (key, value) in [
+        ("Reason", &options.reason),
+        ("Location", &options.location),
+        ("ContactInfo", &options.contact_info),
+    ] {
+        if let Some(value) = value {
+            signature.set(key, pdf_text_string(value));
+        }
+    }
+    let signature_id = new.add_object(signature);
+
+    let field_name = field_name(doc);
+    let mut field = Dictionary::new();
+    field.set("Type", Object::Name(b"Annot".to_vec()));
+    field.set("Subtype", Object::Name(b"Widget".to_vec()));
+    field.set("FT", Object::Name(b"Sig".to_vec()));
+    field.set("T", pdf_text_string(&field_name));
+    field.set("V", Object::Reference(signature_id));
+    field.set("Rect", Object::Array(vec![Object::Integer(0); 4]));
+    // Print and Locked; PDF/A requires the print flag on annotations
+    field.set("F", Object::Integer(132));
+    field.set("P", Object::Reference(page_id));
+    let field_id = new.add_object(field);
+
+    update
+        .opt_clone_object_to_new_document(page_id)
+        .map_err(|e| broken(&e.to_string()))?;
+    let annots = dictionary(&update, page_id)?.get(b"Annots").ok().cloned();
+    push_reference(&mut update, page_id, b"Annots", annots, field_id)?;
+
+    update
+        .opt_clone_object_to_new_document(catalog_id)
+        .map_err(|e| broken(&e.to_string()))?;
+    let acro_form = dictionary(&update, catalog_id)?
+        .get(b"AcroForm")
+        .ok()
+        .cloned();
+    let form_id = match acro_form {
+        Some(Object::Reference(id)) => {
+            update
+                .opt_clone_object_to_new_document(id)
+                .map_err(|e| broken(&e.to_string()))?;
+            id
+        }
+        Some(Object::Dictionary(form)) => {
+            let id = update.new_document.add_object(form);
+            set(&mut update, catalog_id, "AcroForm", Object::Reference(id))?;
+            id
+        }
+        _ => {
+            let id = update.new_document.add_object(Dictionary::new());
+            set(&mut update, catalog_id, "AcroForm", Object::Reference(id))?;
+            id
+        }
+    };
+    let fields = dictionary(&update, form_id)?.get(b"Fields").ok().cloned();
+    push_reference(&mut update, form_id, b"Fields", fields, field_id)?;
+    // SignaturesExist | AppendOnly
+    set(&mut update, form_id, "SigFlags", Object::Integer(3))?;
+
+    let mut output = Vec::new();
+    update
+        .save_to(&mut output)
+        .map_err(|e| SignatureError::Document(format!("failed to save PDF: {e}")))?;
+
+    let appended = pdf.len();
+    let placeholder = format!("<{}>", "0".repeat(capacity * 2));
+    let start = find(&output, placeholder.as_bytes(), appended)
+        .ok_or_else(|| broken("signature placeholder not written"))?;
+    let gap = start..start + placeholder.len();
+    let ranges =
+        format!("{BYTE_RANGE_PLACEHOLDER} {BYTE_RANGE_PLACEHOLDER} {BYTE_RANGE_PLACEHOLDER}");
+    let at = find(&output, ranges.as_bytes(), appended)
+        .ok_or_else(|| broken("byte range placeholder not written"))?;
+    let actual = format!("{} {} {}", gap.start, gap.end, output.len() - gap.end);
+    let padded = format!("{actual:<width$}", width = ranges.len());
+    output[at..at + ranges.len()].copy_from_slice(padded.as_bytes());
+    Ok((output, gap))
+}
+
+/// A field name not yet used by a top-level form field.
+fn field_name(doc: &Document) -> String {
+    let existing: Vec<String> = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"AcroForm").ok())
+        .and_then(|f| resolve(doc, f).as_dict().ok())
+        .and_then(|f| f.get(b"Fields").ok())
+        .and_then(|f| resolve(doc, f).as_array().ok())
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|f| resolve(doc, f).as_dict().ok())
+                .filter_map(|f| f.get(b"T").ok().and_then(obj_to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    (1..)
+        .map(|n| format!("Signature{n}"))
+        .find(|name| !existing.contains(name))
+        .unwrap_or_default()
+}
+
+fn dictionary(update: &IncrementalDocument, id: ObjectId) -> Result<&Dictionary, SignatureError> {
+    update
+        .new_document
+        .get_dictionary(id)
+        .map_err(|e| SignatureError::Document(format!("broken PDF structure: {e}")))
+}
+
+fn set(
+    update: &mut IncrementalDocument,
+    id: ObjectId,
+    key: &str,
+    value: Object,
+) -> Result<(), SignatureError> {
+    update
+        .new_document
+        .get_dictionary_mut(id)
+        .map_err(|e| SignatureError::Document(format!("broken PDF structure: {e}")))?
+        .set(key, value);
+    Ok(())
+}
+
+/// Append `reference` to the array `key` of dictionary `id`, which is
+/// `current` (direct, indirect or missing).
+fn push_reference(
+    update: &mut IncrementalDocument,
+    id: ObjectId,
+    key: &[u8],
+    current: Option<Object>,
+    reference: ObjectId,
+) -> Result<(), SignatureError> {
+    let key = String::from_utf8_lossy(key).into_owned();
+    match current {
+        Some(Object::Reference(array_id)) => {
+            update
+                .opt_clone_object_to_new_document(array_id)
+                .map_err(|e| SignatureError::Document(format!("broken PDF structure: {e}")))?;
+            match update.new_document.get_object_mut(array_id) {
+                Ok(Object::Array(array)) => array.push(Object::Reference(reference)),
+                _ => {
+                    return Err(SignatureError::Document(format!(
+                        "broken PDF structure: /{key} is not an array"
+                    )));
+                }
+            }
+            Ok(())
+        }
+        Some(Object::Array(mut array)) => {
+            array.push(Object::Reference(reference));
+            set(update, id, &key, Object::Array(array))
+        }
+        _ => set(
+            update,
+            id,
+            &key,
+            Object::Array(vec![Object::Reference(reference)]),
+        ),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+fn load(pdf: &[u8]) -> Result<Document, SignatureError> {
+    Document::load_mem(pdf)
+        .map_err(|e| SignatureError::Document(format!("failed to load PDF: {e}")))
+}
+
+fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> &'a Object {
+    match obj {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(obj),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::crypto::sha256;
+    use crate::signature::der::{OID_TIMESTAMP_TOKEN, octet_string};
+    use crate::signature::timestamp::test_tsa::TestTsa;
+    use chrono::{TimeZone, Utc};
+
+    const PDF: &[u8] = include_bytes!("../../tests/fixtures/zugferd-pdfs/EN16931_Einfach.pdf");
+
+    fn identity() -> SigningIdentity {
+        let p12 = include_bytes!("../../tests/fixtures/signature/signer.p12");
+        SigningIdentity::from_pkcs12(p12, "faktura").unwrap()
+    }
+
+    /// The `/ByteRange` values of the last signature in `signed`.
+    fn byte_range(signed: &[u8]) -> Vec<usize> {
+        let text = String::from_utf8_lossy(&signed[PDF.len()..]);
+        let ranges = text
+            .split_once("/ByteRange[")
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .unwrap()
+            .0;
+        ranges
+            .split_whitespace()
+            .map(|n| n.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn timestamped_signature_on_third_party_pdf() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 5).unwrap();
+        let options = SignOptions::new().with_reason("Prüfung");
+        let signed = sign_pdf_timestamped(PDF, &identity(), &options, &TestTsa::new(time)).unwrap();
+        assert_eq!(&signed[..PDF.len()], PDF);
+
+        // The CMS blob carries the digest of exactly the byte ranges
+        let ranges = byte_range(&signed);
+        let content = [&signed[..ranges[1]], &signed[ranges[2]..]].concat();
+        let hex = std::str::from_utf8(&signed[ranges[1] + 1..ranges[2] - 1]).unwrap();
+        let cms: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        let contains = |needle: &[u8]| cms.windows(needle.len()).any(|w| w == needle);
+        let digest = octet_string(&sha256(&content));
+        assert!(contains(&digest));
+        assert!(contains(&identity().certificate().der));
+        assert!(contains(OID_TIMESTAMP_TOKEN));
+
+        let doc = Document::load_mem(&signed).unwrap();
+        let form = doc.catalog().unwrap().get(b"AcroForm").unwrap();
+        let form = resolve(&doc, form).as_dict().unwrap();
+        assert_eq!(form.get(b"SigFlags").unwrap().as_i64().unwrap(), 3);
+    }
+
+    #[test]
+    fn byte_range_excludes_the_contents() {
+        let signed = sign_pdf(PDF, &identity(), &SignOptions::new()).unwrap();
+        let ranges = byte_range(&signed);
+        assert_eq!(ranges[0], 0);
+        assert_eq!(signed[ranges[1]], b'<');
+        assert_eq!(signed[ranges[2] - 1], b'>');
+        assert_eq!(ranges[2] + ranges[3], signed.len());
+    }
+
+    #[test]
+    fn field_names_are_unique() {
+        let once = sign_pdf(PDF, &identity(), &SignOptions::new()).unwrap();
+        assert_eq!(field_name(&Document::load_mem(PDF).unwrap()), "Signature1");
+        assert_eq!(
+            field_name(&Document::load_mem(&once).unwrap()),
+            "Signature2"
+        );
+    }
+}
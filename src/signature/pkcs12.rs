@@ -0,0 +1,438 @@
+//! Signing keys from PKCS#12 files (RFC 7292).
+//!
+//! Supports what current and older tools write: PBES2 with PBKDF2 and
+//! AES-CBC (OpenSSL 3 default), and the legacy `pbeWithSHAAnd3-KeyTripleDES-CBC`
+//! and `pbeWithSHAAnd40BitRC2-CBC` schemes, with an HMAC-SHA-1 or
+//! HMAC-SHA-256 integrity check.
+
+use aes::{Aes128, Aes192, Aes256};
+use cbc::Decryptor;
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, InnerIvInit, KeyInit, KeyIvInit};
+use des::TdesEde3;
+use hmac::{Mac, SimpleHmac};
+use rc2::Rc2;
+use rsa::RsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use sha1::Sha1;
+use sha2::Sha256;
+use sha2::digest::Digest;
+use sha2::digest::core_api::BlockSizeUser;
+
+use super::SignatureError;
+use super::certificate::Certificate;
+use super::der::{
+    OID_AES128_CBC, OID_AES192_CBC, OID_AES256_CBC, OID_CERT_BAG, OID_DATA, OID_DES_EDE3_CBC,
+    OID_ENCRYPTED_DATA, OID_HMAC_SHA1, OID_HMAC_SHA256, OID_KEY_BAG, OID_PBE_SHA1_3DES,
+    OID_PBE_SHA1_RC2_40, OID_PBE_SHA1_RC2_128, OID_PBES2, OID_PBKDF2, OID_SHA1, OID_SHA256,
+    OID_SHROUDED_KEY_BAG, OID_X509_CERTIFICATE, elements, octets, oid_string, read_any, read_tlv,
+};
+
+/// A signer's RSA private key with its certificate and the further
+/// certificates (intermediate and root CAs) found next to it.
+#[derive(Clone)]
+pub struct SigningIdentity {
+    key: RsaPrivateKey,
+    certificate: Certificate,
+    chain: Vec<Certificate>,
+}
+
+impl std::fmt::Debug for SigningIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningIdentity")
+            .field("certificate", &self.certificate.subject)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningIdentity {
+    /// Read a PKCS#12 file (`.p12`/`.pfx`) protected by `password`.
+    ///
+    /// The file must hold one RSA private key and its certificate; other
+    /// certificates are kept as the chain. A wrong password fails the
+    /// integrity check.
+    pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, SignatureError> {
+        let bad = |what: &str| SignatureError::Pkcs12(format!("malformed PKCS#12 file: {what}"));
+        let (pfx, _) = read_tlv(der, 0x30).ok_or_else(|| bad("no PFX"))?;
+        let (version, rest) = read_tlv(pfx, 0x02).ok_or_else(|| bad("no version"))?;
+        if version != [3] {
+            return Err(bad("version is not 3"));
+        }
+        let (auth_safe, rest) = read_tlv(rest, 0x30).ok_or_else(|| bad("no authSafe"))?;
+        let content = data_content(auth_safe).ok_or_else(|| bad("authSafe is not data"))?;
+        match read_tlv(rest, 0x30) {
+            Some((mac_data, _)) => verify_mac(mac_data, &content, password)?,
+            None => return Err(bad("no integrity check (MacData)")),
+        }
+
+        let mut keys = Vec::new();
+        let mut certificates = Vec::new();
+        let (infos, _) = read_tlv(&content, 0x30).ok_or_else(|| bad("no AuthenticatedSafe"))?;
+        for info in elements(infos).ok_or_else(|| bad("AuthenticatedSafe"))? {
+            let (info_content, _) = read_tlv(info, 0x30).ok_or_else(|| bad("ContentInfo"))?;
+            let (content_type, _) =
+                read_tlv(info_content, 0x06).ok_or_else(|| bad("ContentInfo"))?;
+            let safe_contents = match content_type {
+                OID_DATA => data_content(info_content).ok_or_else(|| bad("data"))?,
+                OID_ENCRYPTED_DATA => encrypted_content(info_content, password)?,
+                other => {
+                    return Err(SignatureError::Unsupported(format!(
+                        "PKCS#12 content type {}",
+                        oid_string(other)
+                    )));
+                }
+            };
+            read_bags(&safe_contents, password, &mut keys, &mut certificates)?;
+        }
+
+        let key = match keys.len() {
+            1 => keys.remove(0),
+            0 => return Err(SignatureError::Pkcs12("no private key in the file".into())),
+            _ => {
+                return Err(SignatureError::Pkcs12(
+                    "more than one private key in the file".into(),
+                ));
+            }
+        };
+        let public_key = key.to_public_key();
+        let position = certificates
+            .iter()
+            .position(|c| c.public_key().is_ok_and(|k| k == public_key))
+            .ok_or_else(|| {
+                SignatureError::Pkcs12("no certificate for the private key in the file".into())
+            })?;
+        let certificate = certificates.remove(position);
+        Ok(Self {
+            key,
+            certificate,
+            chain: certificates,
+        })
+    }
+
+    /// The signer's certificate.
+    pub fn certificate(&self) -> &Certificate {
+        &self.certificate
+    }
+
+    /// Further certificates from the file, e.g. the issuing CA.
+    pub fn chain(&self) -> &[Certificate] {
+        &self.chain
+    }
+
+    pub(crate) fn key(&self) -> &RsaPrivateKey {
+        &self.key
+    }
+}
+
+/// Content of a `ContentInfo` of type `data` (given its SEQUENCE content).
+fn data_content(info: &[u8]) -> Option<Vec<u8>> {
+    let (content_type, rest) = read_tlv(info, 0x06)?;
+    if content_type != OID_DATA {
+        return None;
+    }
+    let (explicit, _) = read_tlv(rest, 0xa0)?;
+    let (tag, content, _) = read_any(explicit)?;
+    octets(tag, content)
+}
+
+/// Decrypt an `EncryptedData` `ContentInfo` (given its SEQUENCE content).
+fn encrypted_content(info: &[u8], password: &str) -> Result<Vec<u8>, SignatureError> {
+    let bad = || SignatureError::Pkcs12("malformed EncryptedData".into());
+    let (_, rest) = read_tlv(info, 0x06).ok_or_else(bad)?;
+    let (explicit, _) = read_tlv(rest, 0xa0).ok_or_else(bad)?;
+    let (encrypted_data, _) = read_tlv(explicit, 0x30).ok_or_else(bad)?;
+    let (_, rest) = read_tlv(encrypted_data, 0x02).ok_or_else(bad)?;
+    let (content_info, _) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+    let (_, rest) = read_tlv(content_info, 0x06).ok_or_else(bad)?;
+    let (algorithm, rest) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+    let (tag, ciphertext, _) = read_any(rest).ok_or_else(bad)?;
+    let ciphertext = octets(tag, ciphertext).ok_or_else(bad)?;
+    decrypt(algorithm, &ciphertext, password)
+}
+
+/// Collect the keys and certificates of a `SafeContents`.
+fn read_bags(
+    safe_contents: &[u8],
+    password: &str,
+    keys: &mut Vec<RsaPrivateKey>,
+    certificates: &mut Vec<Certificate>,
+) -> Result<(), SignatureError> {
+    let bad = || SignatureError::Pkcs12("malformed SafeContents".into());
+    let (bags, _) = read_tlv(safe_contents, 0x30).ok_or_else(bad)?;
+    for bag in elements(bags).ok_or_else(bad)? {
+        let (bag, _) = read_tlv(bag, 0x30).ok_or_else(bad)?;
+        let (bag_type, rest) = read_tlv(bag, 0x06).ok_or_else(bad)?;
+        let (value, _) = read_tlv(rest, 0xa0).ok_or_else(bad)?;
+        match bag_type {
+            OID_KEY_BAG => keys.push(private_key(value)?),
+            OID_SHROUDED_KEY_BAG => {
+                let (info, _) = read_tlv(value, 0x30).ok_or_else(bad)?;
+                let (algorithm, rest) = read_tlv(info, 0x30).ok_or_else(bad)?;
+                let (ciphertext, _) = read_tlv(rest, 0x04).ok_or_else(bad)?;
+                keys.push(private_key(&decrypt(algorithm, ciphertext, password)?)?);
+            }
+            OID_CERT_BAG => {
+                let (cert_bag, _) = read_tlv(value, 0x30).ok_or_else(bad)?;
+                let (cert_type, rest) = read_tlv(cert_bag, 0x06).ok_or_else(bad)?;
+                if cert_type == OID_X509_CERTIFICATE {
+                    let (explicit, _) = read_tlv(rest, 0xa0).ok_or_else(bad)?;
+                    let (der, _) = read_tlv(explicit, 0x04).ok_or_else(bad)?;
+                    certificates.push(Certificate::from_der(der)?);
+                }
+            }
+            // CRL and secret bags are not needed for signing
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn private_key(pkcs8: &[u8]) -> Result<RsaPrivateKey, SignatureError> {
+    RsaPrivateKey::from_pkcs8_der(pkcs8)
+        .map_err(|e| SignatureError::Unsupported(format!("private key is not an RSA key: {e}")))
+}
+
+/// Check the HMAC over the authenticated safe.
+fn verify_mac(mac_data: &[u8], content: &[u8], password: &str) -> Result<(), SignatureError> {
+    let bad = || SignatureError::Pkcs12("malformed MacData".into());
+    let (digest_info, rest) = read_tlv(mac_data, 0x30).ok_or_else(bad)?;
+    let (algorithm, digest_rest) = read_tlv(digest_info, 0x30).ok_or_else(bad)?;
+    let (algorithm, _) = read_tlv(algorithm, 0x06).ok_or_else(bad)?;
+    let (expected, _) = read_tlv(digest_rest, 0x04).ok_or_else(bad)?;
+    let (salt, rest) = read_tlv(rest, 0x04).ok_or_else(bad)?;
+    let iterations = match read_tlv(rest, 0x02) {
+        Some((n, _)) => integer(n).ok_or_else(bad)?,
+        None => 1,
+    };
+    let password = bmp_password(password);
+    let mac = match algorithm {
+        OID_SHA1 => {
+            let key = pkcs12_kdf::<Sha1>(&password, salt, 3, iterations, 20);
+            hmac::<Sha1>(&key, content)
+        }
+        OID_SHA256 => {
+            let key = pkcs12_kdf::<Sha256>(&password, salt, 3, iterations, 32);
+            hmac::<Sha256>(&key, content)
+        }
+        other => {
+            return Err(SignatureError::Unsupported(format!(
+                "PKCS#12 MAC digest {}",
+                oid_string(other)
+            )));
+        }
+    };
+    if mac != expected {
+        return Err(SignatureError::Pkcs12(
+            "integrity check failed: wrong password or damaged file".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decrypt with a password-based encryption scheme (`AlgorithmIdentifier`
+/// content given).
+fn decrypt(algorithm: &[u8], ciphertext: &[u8], password: &str) -> Result<Vec<u8>, SignatureError> {
+    let bad = || SignatureError::Pkcs12("malformed encryption parameters".into());
+    let (scheme, parameters) = read_tlv(algorithm, 0x06).ok_or_else(bad)?;
+    let (parameters, _) = read_tlv(parameters, 0x30).ok_or_else(bad)?;
+    let plaintext = match scheme {
+        OID_PBES2 => {
+            let (kdf, rest) = read_tlv(parameters, 0x30).ok_or_else(bad)?;
+            let (cipher, _) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+            let (cipher_oid, iv) = read_tlv(cipher, 0x06).ok_or_else(bad)?;
+            let (iv, _) = read_tlv(iv, 0x04).ok_or_else(bad)?;
+            let key_len = match cipher_oid {
+                OID_AES128_CBC => 16,
+                OID_AES192_CBC | OID_DES_EDE3_CBC => 24,
+                OID_AES256_CBC => 32,
+                other => {
+                    return Err(SignatureError::Unsupported(format!(
+                        "PBES2 cipher {}",
+                        oid_string(other)
+                    )));
+                }
+            };
+            let key = pbkdf2(kdf, password, key_len)?;
+            match cipher_oid {
+                OID_AES128_CBC => Decryptor::<Aes128>::new_from_slices(&key, iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok()),
+                OID_AES192_CBC => Decryptor::<Aes192>::new_from_slices(&key, iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok()),
+                OID_AES256_CBC => Decryptor::<Aes256>::new_from_slices(&key, iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok()),
+                _ => Decryptor::<TdesEde3>::new_from_slices(&key, iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok()),
+            }
+        }
+        OID_PBE_SHA1_3DES | OID_PBE_SHA1_RC2_40 | OID_PBE_SHA1_RC2_128 => {
+            let (salt, rest) = read_tlv(parameters, 0x04).ok_or_else(bad)?;
+            let (iterations, _) = read_tlv(rest, 0x02).ok_or_else(bad)?;
+            let iterations = integer(iterations).ok_or_else(bad)?;
+            let password = bmp_password(password);
+            let key_len = match scheme {
+                OID_PBE_SHA1_3DES => 24,
+                OID_PBE_SHA1_RC2_40 => 5,
+                _ => 16,
+            };
+            let key = pkcs12_kdf::<Sha1>(&password, salt, 1, iterations, key_len);
+            let iv = pkcs12_kdf::<Sha1>(&password, salt, 2, iterations, 8);
+            if scheme == OID_PBE_SHA1_3DES {
+                Decryptor::<TdesEde3>::new_from_slices(&key, &iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok())
+            } else {
+                let cipher = Rc2::new_with_eff_key_len(&key, key_len * 8);
+                Decryptor::<Rc2>::inner_iv_slice_init(cipher, &iv)
+                    .ok()
+                    .and_then(|d| d.decrypt_padded_vec_mut::<Pkcs7>(ciphertext).ok())
+            }
+        }
+        other => {
+            return Err(SignatureError::Unsupported(format!(
+                "PKCS#12 encryption scheme {}",
+                oid_string(other)
+            )));
+        }
+    };
+    plaintext.ok_or_else(|| SignatureError::Pkcs12("decryption failed: wrong password".into()))
+}
+
+/// PBKDF2 key from `PBKDF2-params` (SEQUENCE content of the KDF
+/// `AlgorithmIdentifier`).
+fn pbkdf2(kdf: &[u8], password: &str, key_len: usize) -> Result<Vec<u8>, SignatureError> {
+    let bad = || SignatureError::Pkcs12("malformed PBKDF2 parameters".into());
+    let (kdf_oid, rest) = read_tlv(kdf, 0x06).ok_or_else(bad)?;
+    if kdf_oid != OID_PBKDF2 {
+        return Err(SignatureError::Unsupported(format!(
+            "PBES2 key derivation {}",
+            oid_string(kdf_oid)
+        )));
+    }
+    let (parameters, _) = read_tlv(rest, 0x30).ok_or_else(bad)?;
+    let (salt, rest) = read_tlv(parameters, 0x04).ok_or_else(bad)?;
+    let (iterations, mut rest) = read_tlv(rest, 0x02).ok_or_else(bad)?;
+    let iterations = integer(iterations).ok_or_else(bad)?;
+    if let Some((_, after)) = read_tlv(rest, 0x02) {
+        rest = after;
+    }
+    let prf = match read_tlv(rest, 0x30) {
+        Some((prf, _)) => read_tlv(prf, 0x06).ok_or_else(bad)?.0,
+        None => OID_HMAC_SHA1,
+    };
+    let mut key = vec![0; key_len];
+    match prf {
+        OID_HMAC_SHA1 => {
+            pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, iterations, &mut key)
+        }
+        OID_HMAC_SHA256 => {
+            pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key)
+        }
+        other => {
+            return Err(SignatureError::Unsupported(format!(
+                "PBKDF2 function {}",
+                oid_string(other)
+            )));
+        }
+    }
+    Ok(key)
+}
+
+/// Small non-negative `INTEGER` content.
+fn integer(content: &[u8]) -> Option<u32> {
+    if content.is_empty() || content.len() > 5 || content[0] & 0x80 != 0 {
+        return None;
+    }
+    content.iter().try_fold(0u32, |acc, b| {
+        acc.checked_mul(256)?.checked_add(u32::from(*b))
+    })
+}
+
+/// Password as NUL-terminated big-endian UTF-16 (`BMPString`), as the
+/// PKCS#12 key derivation expects it.
+fn bmp_password(password: &str) -> Vec<u8> {
+    password
+        .encode_utf16()
+        .chain([0])
+        .flat_map(u16::to_be_bytes)
+        .collect()
+}
+
+/// PKCS#12 key derivation (RFC 7292, appendix B.2); `id` 1 derives keys,
+/// 2 IVs and 3 MAC keys.
+fn pkcs12_kdf<D: Digest + BlockSizeUser>(
+    password: &[u8],
+    salt: &[u8],
+    id: u8,
+    iterations: u32,
+    len: usize,
+) -> Vec<u8> {
+    let u = <D as Digest>::output_size();
+    let v = D::block_size();
+    let fill = |data: &[u8]| -> Vec<u8> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let n = v * data.len().div_ceil(v);
+        data.iter().copied().cycle().take(n).collect()
+    };
+    let mut input = fill(salt);
+    input.extend(fill(password));
+
+    let mut out = Vec::with_capacity(len + u);
+    while out.len() < len {
+        let mut hasher = D::new();
+        hasher.update(vec![id; v]);
+        hasher.update(&input);
+        let mut a = hasher.finalize().to_vec();
+        for _ in 1..iterations {
+            a = D::digest(&a).to_vec();
+        }
+        out.extend_from_slice(&a);
+
+        // I_j = (I_j + B + 1) mod 2^(8v), B = A repeated to v bytes
+        let b: Vec<u8> = a.iter().copied().cycle().take(v).collect();
+        for block in input.chunks_mut(v) {
+            let mut carry = 1u16;
+            for (x, y) in block.iter_mut().zip(&b).rev() {
+                let sum = u16::from(*x) + u16::from(*y) + carry;
+                *x = sum as u8;
+                carry = sum >> 8;
+            }
+        }
+    }
+    out.truncate(len);
+    out
+}
+
+fn hmac<D: Digest + BlockSizeUser>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <SimpleHmac<D> as KeyInit>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bmp_password_is_nul_terminated() {
+        assert_eq!(bmp_password("ab"), vec![0, b'a', 0, b'b', 0, 0]);
+    }
+
+    #[test]
+    fn kdf_matches_rfc_test_vector() {
+        // PKCS#12 KDF, SHA-1, password "smeg", 1 iteration (from the
+        // Bouncy Castle PKCS#12 test vectors)
+        let salt = [0x0a, 0x58, 0xcf, 0x64, 0x53, 0x0d, 0x82, 0x3f];
+        let key = pkcs12_kdf::<Sha1>(&bmp_password("smeg"), &salt, 1, 1, 24);
+        assert_eq!(
+            crate::core::digest::hex(&key),
+            "8aaae6297b6cb04642ab5b077851284eb7128f1a2a7fbca3"
+        );
+    }
+}
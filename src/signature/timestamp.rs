@@ -0,0 +1,326 @@
+//! RFC 3161 time-stamps over signature values (ETSI baseline B-T).
+//!
+//! [`TimestampClient`] is the seam to the time-stamping authority;
+//! [`HttpTimestampClient`] posts requests to an `http://` or `https://`
+//! TSA URL.
+
+use std::time::Duration;
+
+use rand_core::{OsRng, RngCore};
+
+use super::SignatureError;
+use super::der::{
+    OID_SHA256, OID_SIGNED_DATA, OID_TST_INFO, algorithm, elements, integer, octet_string, octets,
+    read_any, read_element, read_tlv, sequence,
+};
+use crate::core::der::parse_time;
+use crate::core::http;
+
+/// Access to an RFC 3161 time-stamping authority.
+pub trait TimestampClient {
+    /// Send a DER `TimeStampReq` and return the DER `TimeStampResp`.
+    fn request(&self, request: &[u8]) -> Result<Vec<u8>, SignatureError>;
+}
+
+/// Time-stamp client posting to an `http://` or `https://` TSA URL
+/// (RFC 3161, 3.4).
+///
+/// Use another [`TimestampClient`] for TSAs that require authentication.
+#[derive(Debug, Clone)]
+pub struct HttpTimestampClient {
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpTimestampClient {
+    /// Client for the TSA at `url`, with a 30 second timeout.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the connect and request timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl TimestampClient for HttpTimestampClient {
+    fn request(&self, request: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        let response = http::send(
+            "POST",
+            &self.url,
+            &[
+                ("Content-Type", "application/timestamp-query"),
+                ("Accept", "application/timestamp-reply"),
+            ],
+            request,
+            self.timeout,
+        )
+        .map_err(SignatureError::Network)?;
+        if response.status != 200 {
+            return Err(SignatureError::Network(format!(
+                "{}: HTTP status {}",
+                self.url, response.status
+            )));
+        }
+        if let Some(content_type) = &response.content_type {
+            if !content_type.starts_with("application/timestamp-reply") {
+                return Err(SignatureError::Timestamp(format!(
+                    "unexpected content type '{content_type}'"
+                )));
+            }
+        }
+        Ok(response.body)
+    }
+}
+
+/// Request a time-stamp token over the SHA-256 `imprint`; returns the
+/// token (`ContentInfo`) after checking imprint and nonce. The TSA
+/// signature on the token is not checked.
+pub(crate) fn request_token(
+    tsa: &dyn TimestampClient,
+    imprint: &[u8],
+) -> Result<Vec<u8>, SignatureError> {
+    let mut nonce = [0u8; 8];
+    OsRng.fill_bytes(&mut nonce);
+    let nonce = integer(&nonce);
+    let request = sequence(&[
+        &integer(&[1]),
+        &sequence(&[&algorithm(OID_SHA256, true), &octet_string(imprint)]),
+        &nonce,
+        &[0x01, 0x01, 0xff],
+    ]);
+    let response = tsa.request(&request)?;
+
+    let bad = |what: &str| SignatureError::Timestamp(format!("malformed TimeStampResp: {what}"));
+    let (response, _) = read_tlv(&response, 0x30).ok_or_else(|| bad("no TimeStampResp"))?;
+    let (status_info, rest) = read_tlv(response, 0x30).ok_or_else(|| bad("no status"))?;
+    let (status, status_rest) = read_tlv(status_info, 0x02).ok_or_else(|| bad("no status"))?;
+    if status != [0] && status != [1] {
+        let text = read_tlv(status_rest, 0x30)
+            .and_then(|(texts, _)| elements(texts))
+            .map(|texts| {
+                texts
+                    .iter()
+                    .filter_map(|t| read_any(t).map(|(_, t, _)| String::from_utf8_lossy(t)))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+            .unwrap_or_default();
+        return Err(SignatureError::Timestamp(format!(
+            "request rejected (status {}){}",
+            status.first().copied().unwrap_or_default(),
+            if text.is_empty() {
+                String::new()
+            } else {
+                format!(": {text}")
+            }
+        )));
+    }
+    let (token, _) = read_element(rest).ok_or_else(|| bad("no time-stamp token"))?;
+
+    let info = TokenInfo::parse(token)?;
+    if info.imprint != imprint {
+        return Err(SignatureError::Timestamp(
+            "the token does not cover the signature".into(),
+        ));
+    }
+    if info.nonce.as_deref() != read_tlv(&nonce, 0x02).map(|(n, _)| n) {
+        return Err(SignatureError::Timestamp("nonce mismatch".into()));
+    }
+    Ok(token.to_vec())
+}
+
+/// A parsed time-stamp token.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenInfo {
+    pub(crate) imprint: Vec<u8>,
+    nonce: Option<Vec<u8>>,
+}
+
+impl TokenInfo {
+    /// Parse a time-stamp token (`ContentInfo` with `TSTInfo` content).
+    pub(crate) fn parse(token: &[u8]) -> Result<Self, SignatureError> {
+        let bad = |what: &str| SignatureError::Timestamp(format!("malformed token: {what}"));
+        let content = encapsulated_content(token).ok_or_else(|| bad("no TSTInfo"))?;
+        let (tst_info, _) = read_tlv(&content, 0x30).ok_or_else(|| bad("TSTInfo"))?;
+        let (_, rest) = read_tlv(tst_info, 0x02).ok_or_else(|| bad("version"))?;
+        let (_, rest) = read_tlv(rest, 0x06).ok_or_else(|| bad("policy"))?;
+        let (imprint, rest) = read_tlv(rest, 0x30).ok_or_else(|| bad("messageImprint"))?;
+        let (imprint_algorithm, hashed) = read_tlv(imprint, 0x30).ok_or_else(|| bad("hash"))?;
+        if read_tlv(imprint_algorithm, 0x06).map(|(oid, _)| oid) != Some(OID_SHA256) {
+            return Err(bad("imprint hash is not SHA-256"));
+        }
+        let (hashed, _) = read_tlv(hashed, 0x04).ok_or_else(|| bad("hash"))?;
+        let (_, rest) = read_tlv(rest, 0x02).ok_or_else(|| bad("serial number"))?;
+        let (gen_time, mut rest) = read_tlv(rest, 0x18).ok_or_else(|| bad("genTime"))?;
+        parse_time(0x18, gen_time).ok_or_else(|| bad("genTime"))?;
+        let mut nonce = None;
+        while let Some((tag, value, after)) = read_any(rest) {
+            if tag == 0x02 {
+                nonce = Some(value.to_vec());
+            }
+            rest = after;
+        }
+        Ok(Self {
+            imprint: hashed.to_vec(),
+            nonce,
+        })
+    }
+}
+
+/// The `TSTInfo` encapsulated in a token: `ContentInfo` → `SignedData` →
+/// `encapContentInfo`.
+fn encapsulated_content(token: &[u8]) -> Option<Vec<u8>> {
+    let (content_info, _) = read_tlv(token, 0x30)?;
+    let (content_type, rest) = read_tlv(content_info, 0x06)?;
+    if content_type != OID_SIGNED_DATA {
+        return None;
+    }
+    let (explicit, _) = read_tlv(rest, 0xa0)?;
+    let (signed_data, _) = read_tlv(explicit, 0x30)?;
+    let (_, rest) = read_tlv(signed_data, 0x02)?;
+    let (_, rest) = read_tlv(rest, 0x31)?;
+    let (encapsulated, _) = read_tlv(rest, 0x30)?;
+    let (content_type, rest) = read_tlv(encapsulated, 0x06)?;
+    if content_type != OID_TST_INFO {
+        return None;
+    }
+    let (explicit, _) = read_tlv(rest, 0xa0)?;
+    let (tag, content, _) = read_any(explicit)?;
+    octets(tag, content)
+}
+
+/// A stand-in time-stamping authority signing with the test TSA key.
+#[cfg(test)]
+pub(crate) mod test_tsa {
+    use super::*;
+    use crate::signature::SigningIdentity;
+    use crate::signature::cms;
+    use crate::signature::crypto::sha256;
+    use crate::signature::der::{generalized_time, oid, tlv};
+    use chrono::{DateTime, Utc};
+
+    pub(crate) struct TestTsa {
+        pub(crate) identity: SigningIdentity,
+        pub(crate) time: DateTime<Utc>,
+    }
+
+    impl TestTsa {
+        pub(crate) fn new(time: DateTime<Utc>) -> Self {
+            let p12 = include_bytes!("../../tests/fixtures/signature/tsa.p12");
+            Self {
+                identity: SigningIdentity::from_pkcs12(p12, "faktura").unwrap(),
+                time,
+            }
+        }
+    }
+
+    impl TimestampClient for TestTsa {
+        fn request(&self, request: &[u8]) -> Result<Vec<u8>, SignatureError> {
+            let (request, _) = read_tlv(request, 0x30).unwrap();
+            let (_, rest) = read_tlv(request, 0x02).unwrap();
+            let (imprint, rest) = read_element(rest).unwrap();
+            let (nonce, _) = read_element(rest).unwrap();
+            let tst_info = sequence(&[
+                &integer(&[1]),
+                &oid(&[0x2a, 0x03, 0x04]),
+                imprint,
+                &integer(&[0x42]),
+                &generalized_time(self.time),
+                nonce,
+            ]);
+            let token = cms::sign(
+                &self.identity,
+                OID_TST_INFO,
+                Some(&tst_info),
+                &sha256(&tst_info),
+                None,
+                None,
+            )?;
+            Ok(sequence(&[&sequence(&[&integer(&[0])]), &token]))
+        }
+    }
+
+    /// A TSA that refuses every request.
+    pub(crate) struct RejectingTsa;
+
+    impl TimestampClient for RejectingTsa {
+        fn request(&self, _: &[u8]) -> Result<Vec<u8>, SignatureError> {
+            let text = tlv(0x0c, b"policy not supported");
+            Ok(sequence(&[&sequence(&[
+                &integer(&[2]),
+                &sequence(&[&text]),
+            ])]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_tsa::{RejectingTsa, TestTsa};
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn token_round_trip() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 0).unwrap();
+        let imprint = crate::signature::crypto::sha256(b"signature value");
+        let token = request_token(&TestTsa::new(time), &imprint).unwrap();
+        assert_eq!(TokenInfo::parse(&token).unwrap().imprint, imprint);
+    }
+
+    #[test]
+    fn http_client_posts_requests() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let time = Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 0).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/tsr", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request = vec![0; length];
+            reader.read_exact(&mut request).unwrap();
+            let response = TestTsa::new(time).request(&request).unwrap();
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/timestamp-reply\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n",
+                response.len()
+            )
+            .unwrap();
+            stream.write_all(&response).unwrap();
+        });
+
+        let imprint = crate::signature::crypto::sha256(b"signature value");
+        let token = request_token(&HttpTimestampClient::new(url), &imprint).unwrap();
+        assert_eq!(TokenInfo::parse(&token).unwrap().imprint, imprint);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rejection_is_reported() {
+        let err = request_token(&RejectingTsa, &[0; 32]).unwrap_err();
+        assert!(err.to_string().contains("policy not supported"), "{err}");
+    }
+}
@@ -0,0 +1,385 @@
+//! Enveloped XAdES signatures (ETSI EN 319 132) over UBL and CII invoices.
+//!
+//! The signature has two references: the whole document with the
+//! enveloped-signature transform, and the XAdES `SignedProperties` with the
+//! signing time and the signing certificate. Both use Exclusive XML
+//! Canonicalization.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use rand_core::{OsRng, RngCore};
+
+use super::cms::issuer_serial;
+use super::crypto::{rsa_sign, sha256};
+use super::timestamp::{self, TimestampClient};
+use super::{SignOptions, SignatureError, SigningIdentity};
+use crate::core::digest::{base64_encode, hex};
+use crate::xrechnung::c14n::{Element, canonicalize, parse};
+
+const NS_DS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const NS_XADES: &str = "http://uri.etsi.org/01903/v1.3.2#";
+const NS_UBL_EXTENSIONS: &str =
+    "urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2";
+const UBL_NAMESPACE_PREFIX: &str = "urn:oasis:names:specification:ubl:schema:xsd:";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+const SIGNED_PROPERTIES_TYPE: &str = "http://uri.etsi.org/01903#SignedProperties";
+
+/// Sign an XML invoice with an enveloped XAdES-B-B signature.
+///
+/// In UBL documents the signature goes into a new `ext:UBLExtension`
+/// (creating `ext:UBLExtensions` as the first child if needed); in CII
+/// and other documents it becomes the last child of the root element.
+pub fn sign_xml(
+    xml: &str,
+    identity: &SigningIdentity,
+    options: &SignOptions,
+) -> Result<String, SignatureError> {
+    sign(xml, identity, options, None)
+}
+
+/// Sign an XML invoice with an enveloped XAdES-B-T signature: like
+/// [`sign_xml`], plus a time-stamp over the signature value from `tsa`.
+pub fn sign_xml_timestamped(
+    xml: &str,
+    identity: &SigningIdentity,
+    options: &SignOptions,
+    tsa: &dyn TimestampClient,
+) -> Result<String, SignatureError> {
+    sign(xml, identity, options, Some(tsa))
+}
+
+fn sign(
+    xml: &str,
+    identity: &SigningIdentity,
+    options: &SignOptions,
+    tsa: Option<&dyn TimestampClient>,
+) -> Result<String, SignatureError> {
+    let (document, slot) = prepare(xml)?;
+    let document_digest = sha256(canonicalize(&parse_xml(&document)?, &[]).as_bytes());
+
+    let mut random = [0u8; 8];
+    OsRng.fill_bytes(&mut random);
+    let mut parts = Parts {
+        id: format!("xades-{}", hex(&random)),
+        signed_info: String::new(),
+        value: String::new(),
+        certificates: std::iter::once(identity.certificate())
+            .chain(identity.chain())
+            .map(|c| {
+                format!(
+                    "<ds:X509Certificate>{}</ds:X509Certificate>",
+                    base64_encode(&c.der)
+                )
+            })
+            .collect(),
+        signed_properties: String::new(),
+        unsigned_properties: String::new(),
+    };
+    parts.signed_properties = signed_properties(&parts.id, identity, options.signing_time());
+
+    let properties = canonical_by_id(&parts.xml(), &format!("{}-signedprops", parts.id))?;
+    parts.signed_info = format!(
+        "<ds:SignedInfo><ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"/>\
+         <ds:SignatureMethod Algorithm=\"{RSA_SHA256}\"/>\
+         <ds:Reference Id=\"{id}-ref0\" URI=\"\"><ds:Transforms>\
+         <ds:Transform Algorithm=\"{ENVELOPED_SIGNATURE}\"/>\
+         <ds:Transform Algorithm=\"{EXC_C14N}\"/></ds:Transforms>\
+         <ds:DigestMethod Algorithm=\"{SHA256}\"/>\
+         <ds:DigestValue>{}</ds:DigestValue></ds:Reference>\
+         <ds:Reference Type=\"{SIGNED_PROPERTIES_TYPE}\" URI=\"#{id}-signedprops\">\
+         <ds:Transforms><ds:Transform Algorithm=\"{EXC_C14N}\"/></ds:Transforms>\
+         <ds:DigestMethod Algorithm=\"{SHA256}\"/>\
+         <ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>",
+        base64_encode(&document_digest),
+        base64_encode(&sha256(properties.as_bytes())),
+        id = parts.id,
+    );
+
+    let signature = parse_xml(&parts.xml())?;
+    let signed_info = signature
+        .child(NS_DS, "SignedInfo")
+        .ok_or_else(|| SignatureError::Parse("SignedInfo missing".into()))?;
+    parts.value = base64_encode(&rsa_sign(
+        identity.key(),
+        canonicalize(signed_info, &[]).as_bytes(),
+    )?);
+
+    if let Some(tsa) = tsa {
+        let value = canonical_by_id(&parts.xml(), &format!("{}-value", parts.id))?;
+        let token = timestamp::request_token(tsa, &sha256(value.as_bytes()))?;
+        parts.unsigned_properties = format!(
+            "<xades:UnsignedProperties><xades:UnsignedSignatureProperties>\
+             <xades:SignatureTimeStamp Id=\"{}-ts\">\
+             <ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"/>\
+             <xades:EncapsulatedTimeStamp>{}</xades:EncapsulatedTimeStamp>\
+             </xades:SignatureTimeStamp></xades:UnsignedSignatureProperties>\
+             </xades:UnsignedProperties>",
+            parts.id,
+            base64_encode(&token)
+        );
+    }
+
+    Ok(format!(
+        "{}{}{}",
+        &document[..slot],
+        parts.xml(),
+        &document[slot..]
+    ))
+}
+
+/// The pieces of a `ds:Signature` element, filled in step by step.
+struct Parts {
+    id: String,
+    signed_info: String,
+    value: String,
+    certificates: String,
+    signed_properties: String,
+    unsigned_properties: String,
+}
+
+impl Parts {
+    fn xml(&self) -> String {
+        format!(
+            "<ds:Signature xmlns:ds=\"{NS_DS}\" Id=\"{id}\">{}\
+             <ds:SignatureValue Id=\"{id}-value\">{}</ds:SignatureValue>\
+             <ds:KeyInfo><ds:X509Data>{}</ds:X509Data></ds:KeyInfo>\
+             <ds:Object><xades:QualifyingProperties xmlns:xades=\"{NS_XADES}\" \
+             Target=\"#{id}\">{}{}</xades:QualifyingProperties></ds:Object></ds:Signature>",
+            self.signed_info,
+            self.value,
+            self.certificates,
+            self.signed_properties,
+            self.unsigned_properties,
+            id = self.id,
+        )
+    }
+}
+
+/// `xades:SignedProperties` with signing time and signing certificate.
+fn signed_properties(id: &str, identity: &SigningIdentity, signing_time: DateTime<Utc>) -> String {
+    let certificate = identity.certificate();
+    format!(
+        "<xades:SignedProperties Id=\"{id}-signedprops\"><xades:SignedSignatureProperties>\
+         <xades:SigningTime>{}</xades:SigningTime>\
+         <xades:SigningCertificateV2><xades:Cert><xades:CertDigest>\
+         <ds:DigestMethod Algorithm=\"{SHA256}\"/>\
+         <ds:DigestValue>{}</ds:DigestValue></xades:CertDigest>\
+         <xades:IssuerSerialV2>{}</xades:IssuerSerialV2></xades:Cert>\
+         </xades:SigningCertificateV2></xades:SignedSignatureProperties>\
+         </xades:SignedProperties>",
+        signing_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        base64_encode(&sha256(&certificate.der)),
+        base64_encode(&issuer_serial(certificate)),
+    )
+}
+
+/// Exclusive canonical form of the element with `Id` = `id` in `xml`.
+fn canonical_by_id(xml: &str, id: &str) -> Result<String, SignatureError> {
+    let root = parse_xml(xml)?;
+    unique_ids(&root)?;
+    let element = root
+        .by_id(id)
+        .ok_or_else(|| SignatureError::Parse(format!("no element with Id '{id}'")))?;
+    Ok(canonicalize(element, &[]))
+}
+
+/// Add the container for the signature to `xml`; returns the document and
+/// the byte offset where the `ds:Signature` element goes.
+fn prepare(xml: &str) -> Result<(String, usize), SignatureError> {
+    let root = parse_xml(xml)?;
+    let ubl = root.namespace.starts_with(UBL_NAMESPACE_PREFIX);
+    let has_extensions = root
+        .elements()
+        .next()
+        .is_some_and(|e| e.is(NS_UBL_EXTENSIONS, "UBLExtensions"));
+
+    let bad = |e: &dyn std::fmt::Display| SignatureError::Parse(format!("XML parse error: {e}"));
+    let mut reader = Reader::from_str(xml);
+    let mut depth = 0usize;
+    let mut root_start_end = None;
+    let mut extensions_end = None;
+    let mut root_end = None;
+    while root_end.is_none() {
+        let before = reader.buffer_position() as usize;
+        match reader.read_event().map_err(|e| bad(&e))? {
+            Event::Start(_) => {
+                depth += 1;
+                if depth == 1 {
+                    root_start_end = Some(reader.buffer_position() as usize);
+                }
+            }
+            Event::End(_) => {
+                depth -= 1;
+                match depth {
+                    0 => root_end = Some(before),
+                    1 if has_extensions && extensions_end.is_none() => {
+                        extensions_end = Some(before)
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(_) if depth == 0 => {
+                return Err(SignatureError::Document("the root element is empty".into()));
+            }
+            Event::Empty(_) if depth == 1 && has_extensions && extensions_end.is_none() => {
+                return Err(SignatureError::Document(
+                    "ext:UBLExtensions is empty".into(),
+                ));
+            }
+            Event::Eof => return Err(SignatureError::Parse("unbalanced XML".into())),
+            _ => {}
+        }
+    }
+
+    let (position, open, close) = match (ubl, extensions_end) {
+        (true, Some(end)) => (
+            end,
+            format!("<ext:UBLExtension xmlns:ext=\"{NS_UBL_EXTENSIONS}\"><ext:ExtensionContent>"),
+            "</ext:ExtensionContent></ext:UBLExtension>",
+        ),
+        (true, None) => (
+            root_start_end.unwrap_or_default(),
+            format!(
+                "<ext:UBLExtensions xmlns:ext=\"{NS_UBL_EXTENSIONS}\"><ext:UBLExtension>\
+                 <ext:ExtensionContent>"
+            ),
+            "</ext:ExtensionContent></ext:UBLExtension></ext:UBLExtensions>",
+        ),
+        (false, _) => (root_end.unwrap_or_default(), String::new(), ""),
+    };
+    let document = format!("{}{open}{close}{}", &xml[..position], &xml[position..]);
+    Ok((document, position + open.len()))
+}
+
+/// Reject documents with an `Id` used twice: references resolve to the
+/// first element, which need not be the one that is read.
+fn unique_ids(root: &Element) -> Result<(), SignatureError> {
+    match root.duplicate_id() {
+        Some(id) => Err(SignatureError::Parse(format!(
+            "Id '{id}' is used by more than one element"
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<Element, SignatureError> {
+    parse(xml).map_err(|e| SignatureError::Parse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::digest::base64_decode;
+    use crate::signature::crypto::rsa_verify;
+    use crate::signature::timestamp::TokenInfo;
+    use crate::signature::timestamp::test_tsa::{RejectingTsa, TestTsa};
+    use chrono::TimeZone;
+
+    const INVOICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100">
+  <rsm:ExchangedDocument>RE-1</rsm:ExchangedDocument>
+</rsm:CrossIndustryInvoice>"#;
+
+    fn identity() -> SigningIdentity {
+        let p12 = include_bytes!("../../tests/fixtures/signature/signer.p12");
+        SigningIdentity::from_pkcs12(p12, "faktura").unwrap()
+    }
+
+    /// The `ds:Signature` element of `signed` and the document without it.
+    fn split(signed: &str) -> (Element, String) {
+        let start = signed.find("<ds:Signature ").unwrap();
+        let end = signed.find("</ds:Signature>").unwrap() + "</ds:Signature>".len();
+        let signature = parse_xml(&signed[start..end]).unwrap();
+        (signature, format!("{}{}", &signed[..start], &signed[end..]))
+    }
+
+    fn digest_values(signature: &Element) -> Vec<Vec<u8>> {
+        signature
+            .find_all(NS_DS, "DigestValue")
+            .into_iter()
+            .map(|v| base64_decode(&v.text()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn signature_covers_document_and_properties() {
+        let signed = sign_xml(INVOICE, &identity(), &SignOptions::new()).unwrap();
+        let (signature, document) = split(&signed);
+
+        let signed_info = signature.child(NS_DS, "SignedInfo").unwrap();
+        let value = signature.find_text(NS_DS, "SignatureValue").unwrap();
+        let key = identity().certificate().public_key().unwrap();
+        assert!(rsa_verify(
+            &key,
+            canonicalize(signed_info, &[]).as_bytes(),
+            &base64_decode(&value).unwrap()
+        ));
+
+        let id = signature.attribute("Id").unwrap();
+        let properties = signature.by_id(&format!("{id}-signedprops")).unwrap();
+        let digests = digest_values(signed_info);
+        let document = canonicalize(&parse_xml(&document).unwrap(), &[]);
+        assert_eq!(digests[0], sha256(document.as_bytes()));
+        assert_eq!(digests[1], sha256(canonicalize(properties, &[]).as_bytes()));
+
+        // The signing certificate digest in the signed properties
+        let certificate = &digest_values(properties)[0];
+        assert_eq!(certificate, &sha256(&identity().certificate().der));
+    }
+
+    #[test]
+    fn timestamped_signature() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 5).unwrap();
+        let signed = sign_xml_timestamped(
+            INVOICE,
+            &identity(),
+            &SignOptions::new(),
+            &TestTsa::new(time),
+        )
+        .unwrap();
+        let (signature, _) = split(&signed);
+        let token = signature
+            .find_text(NS_XADES, "EncapsulatedTimeStamp")
+            .and_then(|t| base64_decode(&t))
+            .unwrap();
+        let value = signature.child(NS_DS, "SignatureValue").unwrap();
+        assert_eq!(
+            TokenInfo::parse(&token).unwrap().imprint,
+            sha256(canonicalize(value, &[]).as_bytes())
+        );
+    }
+
+    #[test]
+    fn rejected_timestamp_fails_signing() {
+        let err = sign_xml_timestamped(INVOICE, &identity(), &SignOptions::new(), &RejectingTsa)
+            .unwrap_err();
+        assert!(matches!(err, SignatureError::Timestamp(_)), "{err}");
+    }
+
+    #[test]
+    fn ubl_extensions_are_extended() {
+        let xml = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:ext="urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2"><ext:UBLExtensions><ext:UBLExtension><ext:ExtensionContent/></ext:UBLExtension></ext:UBLExtensions><ID>1</ID></Invoice>"#;
+        let (document, slot) = prepare(xml).unwrap();
+        assert!(document[..slot].ends_with(
+            "</ext:UBLExtension><ext:UBLExtension xmlns:ext=\"urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2\"><ext:ExtensionContent>"
+        ));
+        assert!(
+            document[slot..]
+                .starts_with("</ext:ExtensionContent></ext:UBLExtension></ext:UBLExtensions><ID>")
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let xml = INVOICE.replace(
+            "<rsm:ExchangedDocument>RE-1</rsm:ExchangedDocument>",
+            "<a Id=\"x\"/><b Id=\"x\"/>",
+        );
+        let err = canonical_by_id(&xml, "x").unwrap_err();
+        assert!(err.to_string().contains("more than one element"), "{err}");
+    }
+}
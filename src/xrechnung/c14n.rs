@@ -0,0 +1,450 @@
+//! Namespace-aware XML tree and Exclusive XML Canonicalization 1.0
+//! (without comments), as used by the WS-Security signatures of AS4 messages
+//! and by XAdES signatures over invoice documents.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::core::RechnungError;
+
+/// Namespace of the `xml:` prefix, which is never declared.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Prefix → namespace URI; the default namespace has the empty prefix.
+type Scope = Rc<BTreeMap<String, String>>;
+
+/// A node of the tree.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// An attribute (namespace declarations are kept in the element scope).
+#[derive(Debug, Clone)]
+pub(crate) struct Attribute {
+    pub(crate) name: String,
+    pub(crate) namespace: String,
+    pub(crate) value: String,
+}
+
+/// An element with resolved namespaces.
+#[derive(Debug, Clone)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) namespace: String,
+    pub(crate) attributes: Vec<Attribute>,
+    pub(crate) children: Vec<Node>,
+    scope: Scope,
+}
+
+impl Element {
+    pub(crate) fn local_name(&self) -> &str {
+        local_part(&self.name)
+    }
+
+    fn prefix(&self) -> &str {
+        self.name.split_once(':').map_or("", |(p, _)| p)
+    }
+
+    /// Whether this is `{namespace}local`.
+    pub(crate) fn is(&self, namespace: &str, local: &str) -> bool {
+        self.namespace == namespace && self.local_name() == local
+    }
+
+    /// Value of the attribute with the given local name.
+    pub(crate) fn attribute(&self, local: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|a| local_part(&a.name) == local)
+            .map(|a| a.value.as_str())
+    }
+
+    /// Child elements.
+    pub(crate) fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// First child element `{namespace}local`.
+    pub(crate) fn child(&self, namespace: &str, local: &str) -> Option<&Element> {
+        self.elements().find(|e| e.is(namespace, local))
+    }
+
+    /// First descendant (or self) `{namespace}local`.
+    // Only AS4 reads signatures; XAdES signing does not
+    #[cfg_attr(not(feature = "as4"), allow(dead_code))]
+    pub(crate) fn find(&self, namespace: &str, local: &str) -> Option<&Element> {
+        if self.is(namespace, local) {
+            return Some(self);
+        }
+        self.elements().find_map(|e| e.find(namespace, local))
+    }
+
+    /// All descendants (and self) `{namespace}local`, in document order.
+    #[cfg_attr(not(feature = "as4"), allow(dead_code))]
+    pub(crate) fn find_all<'a>(&'a self, namespace: &str, local: &str) -> Vec<&'a Element> {
+        let mut found = Vec::new();
+        self.collect(namespace, local, &mut found);
+        found
+    }
+
+    fn collect<'a>(&'a self, namespace: &str, local: &str, found: &mut Vec<&'a Element>) {
+        if self.is(namespace, local) {
+            found.push(self);
+        }
+        for e in self.elements() {
+            e.collect(namespace, local, found);
+        }
+    }
+
+    /// Descendant (or self) carrying `Id` / `wsu:Id` = `id`.
+    pub(crate) fn by_id(&self, id: &str) -> Option<&Element> {
+        if self.attribute("Id") == Some(id) {
+            return Some(self);
+        }
+        self.elements().find_map(|e| e.by_id(id))
+    }
+
+    /// The first `Id` / `wsu:Id` value carried by more than one element.
+    ///
+    /// Signature checks resolve references with [`by_id`](Self::by_id) and
+    /// must reject such documents: a second element with a signed `Id`
+    /// lets signed and processed content differ (signature wrapping).
+    pub(crate) fn duplicate_id(&self) -> Option<&str> {
+        self.first_duplicate_id(&mut BTreeSet::new())
+    }
+
+    fn first_duplicate_id<'a>(&'a self, seen: &mut BTreeSet<&'a str>) -> Option<&'a str> {
+        if let Some(id) = self.attribute("Id") {
+            if !seen.insert(id) {
+                return Some(id);
+            }
+        }
+        self.elements().find_map(|e| e.first_duplicate_id(seen))
+    }
+
+    /// Concatenated text content, trimmed.
+    #[cfg_attr(not(feature = "as4"), allow(dead_code))]
+    pub(crate) fn text(&self) -> String {
+        let mut text = String::new();
+        self.append_text(&mut text);
+        text.trim().to_string()
+    }
+
+    fn append_text(&self, out: &mut String) {
+        for node in &self.children {
+            match node {
+                Node::Text(t) => out.push_str(t),
+                Node::Element(e) => e.append_text(out),
+            }
+        }
+    }
+
+    /// Text of the first descendant `{namespace}local`.
+    #[cfg_attr(not(feature = "as4"), allow(dead_code))]
+    pub(crate) fn find_text(&self, namespace: &str, local: &str) -> Option<String> {
+        self.find(namespace, local).map(Element::text)
+    }
+}
+
+fn local_part(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, l)| l)
+}
+
+/// Parse a document into its root element.
+pub(crate) fn parse(xml: &str) -> Result<Element, RechnungError> {
+    let xml = xml.replace("\r\n", "\n");
+    let mut reader = Reader::from_str(&xml);
+    let mut stack: Vec<Element> = Vec::new();
+    let root_scope: Scope = Rc::new(BTreeMap::new());
+    let bad = |e: &dyn std::fmt::Display| RechnungError::Xml(format!("XML parse error: {e}"));
+
+    loop {
+        let event = reader.read_event().map_err(|e| bad(&e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let parent_scope = stack.last().map_or(&root_scope, |p| &p.scope);
+                let element = open_element(e, parent_scope)?;
+                if matches!(event, Event::Start(_)) {
+                    stack.push(element);
+                } else if let Some(root) = close(&mut stack, element) {
+                    return Ok(root);
+                }
+            }
+            Event::End(_) => {
+                let element = stack
+                    .pop()
+                    .ok_or_else(|| RechnungError::Xml("unbalanced XML".into()))?;
+                if let Some(root) = close(&mut stack, element) {
+                    return Ok(root);
+                }
+            }
+            Event::Text(ref t) => {
+                if let Some(parent) = stack.last_mut() {
+                    push_text(parent, &t.unescape().map_err(|e| bad(&e))?);
+                }
+            }
+            Event::CData(ref t) => {
+                if let Some(parent) = stack.last_mut() {
+                    push_text(parent, &String::from_utf8_lossy(t));
+                }
+            }
+            Event::Eof => {
+                return Err(RechnungError::Xml(
+                    "XML document has no root element".into(),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Attach a finished element to its parent; returns it if it is the root.
+fn close(stack: &mut [Element], element: Element) -> Option<Element> {
+    match stack.last_mut() {
+        Some(parent) => {
+            parent.children.push(Node::Element(element));
+            None
+        }
+        None => Some(element),
+    }
+}
+
+fn push_text(parent: &mut Element, text: &str) {
+    if let Some(Node::Text(last)) = parent.children.last_mut() {
+        last.push_str(text);
+    } else {
+        parent.children.push(Node::Text(text.to_string()));
+    }
+}
+
+fn open_element(e: &BytesStart, parent_scope: &Scope) -> Result<Element, RechnungError> {
+    let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+    let mut declared = Vec::new();
+    let mut raw_attributes = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| RechnungError::Xml(format!("XML attribute error: {e}")))?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        let value = attr
+            .unescape_value()
+            .map_err(|e| RechnungError::Xml(format!("XML attribute error: {e}")))?
+            .into_owned();
+        match key.strip_prefix("xmlns") {
+            Some("") => declared.push((String::new(), value)),
+            Some(p) if p.starts_with(':') => declared.push((p[1..].to_string(), value)),
+            _ => raw_attributes.push((key, value)),
+        }
+    }
+    let scope = if declared.is_empty() {
+        Rc::clone(parent_scope)
+    } else {
+        let mut map = (**parent_scope).clone();
+        map.extend(declared);
+        Rc::new(map)
+    };
+    let resolve = |prefix: &str| -> Result<String, RechnungError> {
+        match prefix {
+            "xml" => Ok(XML_NAMESPACE.into()),
+            _ => scope
+                .get(prefix)
+                .cloned()
+                .or_else(|| prefix.is_empty().then(String::new))
+                .ok_or_else(|| {
+                    RechnungError::Xml(format!("undeclared namespace prefix '{prefix}'"))
+                }),
+        }
+    };
+    let namespace = resolve(name.split_once(':').map_or("", |(p, _)| p))?;
+    let attributes = raw_attributes
+        .into_iter()
+        .map(|(name, value)| {
+            let namespace = match name.split_once(':') {
+                Some((p, _)) => resolve(p)?,
+                None => String::new(),
+            };
+            Ok(Attribute {
+                name,
+                namespace,
+                value,
+            })
+        })
+        .collect::<Result<_, RechnungError>>()?;
+    Ok(Element {
+        name,
+        namespace,
+        attributes,
+        children: Vec::new(),
+        scope,
+    })
+}
+
+/// Exclusive canonical form of `element`, treating the prefixes in
+/// `inclusive` (`#default` for the default namespace) as in inclusive
+/// canonicalization.
+pub(crate) fn canonicalize(element: &Element, inclusive: &[&str]) -> String {
+    let mut out = String::new();
+    write_canonical(element, inclusive, &BTreeMap::new(), &mut out);
+    out
+}
+
+fn write_canonical(
+    element: &Element,
+    inclusive: &[&str],
+    rendered: &BTreeMap<String, String>,
+    out: &mut String,
+) {
+    // Prefixes visibly utilized by the element and its attributes
+    let mut used: Vec<(&str, &str)> = vec![(element.prefix(), &element.namespace)];
+    for attr in &element.attributes {
+        if let Some((prefix, _)) = attr.name.split_once(':') {
+            used.push((prefix, &attr.namespace));
+        }
+    }
+    for prefix in inclusive {
+        let prefix = if *prefix == "#default" { "" } else { prefix };
+        if let Some(uri) = element.scope.get(prefix) {
+            used.push((prefix, uri));
+        }
+    }
+    used.sort_unstable();
+    used.dedup();
+
+    let mut scope = rendered.clone();
+    let mut declarations = Vec::new();
+    for (prefix, uri) in used {
+        if prefix == "xml" {
+            continue;
+        }
+        let current = rendered.get(prefix).map(String::as_str);
+        let needed = if prefix.is_empty() && uri.is_empty() {
+            current.is_some_and(|c| !c.is_empty())
+        } else {
+            current != Some(uri)
+        };
+        if needed {
+            declarations.push((prefix, uri));
+            scope.insert(prefix.to_string(), uri.to_string());
+        }
+    }
+
+    out.push('<');
+    out.push_str(&element.name);
+    for (prefix, uri) in declarations {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        escape_attribute(uri, out);
+        out.push('"');
+    }
+    let mut attributes: Vec<&Attribute> = element.attributes.iter().collect();
+    attributes.sort_by(|a, b| {
+        (a.namespace.as_str(), local_part(&a.name))
+            .cmp(&(b.namespace.as_str(), local_part(&b.name)))
+    });
+    for attr in attributes {
+        out.push(' ');
+        out.push_str(&attr.name);
+        out.push_str("=\"");
+        escape_attribute(&attr.value, out);
+        out.push('"');
+    }
+    out.push('>');
+    for node in &element.children {
+        match node {
+            Node::Text(text) => escape_text(text, out),
+            Node::Element(child) => write_canonical(child, inclusive, &scope, out),
+        }
+    }
+    out.push_str("</");
+    out.push_str(&element.name);
+    out.push('>');
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn escape_attribute(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclusive_canonicalization() {
+        let doc = parse(
+            r#"<?xml version="1.0"?>
+<a:Root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:u">
+  <b:Child z="1" b:y="2" a="&quot;x&quot;" Id="c1"><Empty/>t &amp; &lt;u&gt;</b:Child>
+</a:Root>"#,
+        )
+        .unwrap();
+        let child = doc.by_id("c1").unwrap();
+        assert_eq!(
+            canonicalize(child, &[]),
+            r#"<b:Child xmlns:b="urn:b" Id="c1" a="&quot;x&quot;" z="1" b:y="2"><Empty></Empty>t &amp; &lt;u&gt;</b:Child>"#
+        );
+        assert!(canonicalize(&doc, &[]).starts_with(r#"<a:Root xmlns:a="urn:a">"#));
+        assert!(
+            canonicalize(child, &["a"]).starts_with(r#"<b:Child xmlns:a="urn:a" xmlns:b="urn:b""#)
+        );
+    }
+
+    #[test]
+    fn default_namespace_undeclared() {
+        let doc =
+            parse(r#"<R xmlns="urn:r"><x:C xmlns:x="urn:x"><D xmlns=""/></x:C></R>"#).unwrap();
+        assert_eq!(
+            canonicalize(&doc, &[]),
+            r#"<R xmlns="urn:r"><x:C xmlns:x="urn:x"><D xmlns=""></D></x:C></R>"#
+        );
+        let inner = doc.find("urn:x", "C").unwrap();
+        assert_eq!(
+            canonicalize(inner, &[]),
+            r#"<x:C xmlns:x="urn:x"><D></D></x:C>"#
+        );
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let doc = parse(r#"<R Id="a"><C wsu:Id="b" xmlns:wsu="urn:wsu"/><D/></R>"#).unwrap();
+        assert_eq!(doc.duplicate_id(), None);
+        let doc = parse(r#"<R><C Id="a"/><D><E wsu:Id="a" xmlns:wsu="urn:wsu"/></D></R>"#).unwrap();
+        assert_eq!(doc.duplicate_id(), Some("a"));
+    }
+
+    #[test]
+    fn rejects_undeclared_prefix() {
+        assert!(parse("<p:a/>").is_err());
+    }
+}
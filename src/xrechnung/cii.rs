@@ -4,12 +4,24 @@ use quick_xml::events::Event;
 use rust_decimal::Decimal;
 use std::str::FromStr;
 
-use super::xml_utils::{XmlResult, XmlWriter, format_decimal};
-use super::{PEPPOL_PROFILE_ID, XRECHNUNG_CUSTOMIZATION_ID, cii_ns};
+use super::xml_utils::{XmlResult, XmlWriter, format_amount, format_decimal};
+use super::{XRECHNUNG_CUSTOMIZATION_ID, cii_ns, profile_id};
 use crate::core::*;
 
 /// Generate XRechnung-compliant CII (Cross Industry Invoice) XML.
+///
+/// Group and information lines ([`LineStatus`]) are left out; only the
+/// ZUGFeRD Extended profile carries them.
 pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
+    write_cii_xml(invoice, false)
+}
+
+/// Generate CII XML; `extended` additionally writes the ZUGFeRD Extended
+/// elements: Skonto as `ram:ApplicableTradePaymentDiscountTerms`, line
+/// grouping, line-level references, measured characteristics, further
+/// despatch advices, delivery notes, logistics service charges and
+/// payment instalments.
+pub(crate) fn write_cii_xml(invoice: &Invoice, extended: bool) -> XmlResult {
     let totals = invoice.totals.as_ref().ok_or_else(|| {
         RechnungError::Builder("totals must be calculated before XML generation".into())
     })?;
@@ -30,7 +42,7 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
     // --- ExchangedDocumentContext ---
     w.start_element("rsm:ExchangedDocumentContext")?;
     w.start_element("ram:BusinessProcessSpecifiedDocumentContextParameter")?;
-    w.text_element("ram:ID", PEPPOL_PROFILE_ID)?;
+    w.text_element("ram:ID", profile_id(invoice))?;
     w.end_element("ram:BusinessProcessSpecifiedDocumentContextParameter")?;
     w.start_element("ram:GuidelineSpecifiedDocumentContextParameter")?;
     w.text_element("ram:ID", XRECHNUNG_CUSTOMIZATION_ID)?;
@@ -52,9 +64,13 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
     // --- SupplyChainTradeTransaction ---
     w.start_element("rsm:SupplyChainTradeTransaction")?;
 
-    // Lines
-    for line in &invoice.lines {
-        write_cii_line(&mut w, line, currency)?;
+    // Lines; group and information lines only with their Extended status
+    for line in invoice
+        .lines
+        .iter()
+        .filter(|l| extended || l.counts_in_totals())
+    {
+        write_cii_line(&mut w, line, currency, extended)?;
     }
 
     // --- ApplicableHeaderTradeAgreement ---
@@ -133,6 +149,12 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
         }
         w.end_element("ram:AdditionalReferencedDocument")?;
     }
+    // BT-17/BT-18: Tender and invoiced object references; other types are Extended
+    for reference in &invoice.additional_references {
+        if extended || reference.is_en16931(false) {
+            write_cii_additional_reference(&mut w, reference)?;
+        }
+    }
     // BT-11: Project reference
     if let Some(pr) = &invoice.project_reference {
         w.start_element("ram:SpecifiedProcuringProject")?;
@@ -198,6 +220,21 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
         w.end_element("ram:ActualDeliverySupplyChainEvent")?;
     }
 
+    // BT-16: Despatch advice; Extended allows several, plus delivery notes
+    let despatch_advices = if extended {
+        &invoice.despatch_advices[..]
+    } else {
+        &invoice.despatch_advices[..invoice.despatch_advices.len().min(1)]
+    };
+    for advice in despatch_advices {
+        write_cii_referenced_document(&mut w, "ram:DespatchAdviceReferencedDocument", advice)?;
+    }
+    if extended {
+        for note in &invoice.delivery_notes {
+            write_cii_referenced_document(&mut w, "ram:DeliveryNoteReferencedDocument", note)?;
+        }
+    }
+
     if let Some(period) = &invoice.invoicing_period {
         w.start_element("ram:BillingSpecifiedPeriod")?;
         write_cii_date(&mut w, "ram:StartDateTime", &period.start)?;
@@ -279,12 +316,18 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
     // VAT breakdown
     for bd in &totals.vat_breakdown {
         w.start_element("ram:ApplicableTradeTax")?;
-        w.text_element("ram:CalculatedAmount", &format_decimal(bd.tax_amount))?;
+        w.text_element(
+            "ram:CalculatedAmount",
+            &format_amount(bd.tax_amount, currency),
+        )?;
         w.text_element("ram:TypeCode", "VAT")?;
         if let Some(reason) = &bd.exemption_reason {
             w.text_element("ram:ExemptionReason", reason)?;
         }
-        w.text_element("ram:BasisAmount", &format_decimal(bd.taxable_amount))?;
+        w.text_element(
+            "ram:BasisAmount",
+            &format_amount(bd.taxable_amount, currency),
+        )?;
         w.text_element("ram:CategoryCode", bd.category.code())?;
         if let Some(code) = &bd.exemption_reason_code {
             w.text_element("ram:ExemptionReasonCode", code)?;
@@ -305,43 +348,84 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
         }
     }
 
-    // Payment terms
-    if let Some(terms) = &invoice.payment_terms {
+    // Payment terms (BT-20, with #SKONTO# lines)
+    if let Some(terms) = skonto::payment_terms_text(invoice) {
         w.start_element("ram:SpecifiedTradePaymentTerms")?;
-        w.text_element("ram:Description", terms)?;
+        w.text_element("ram:Description", &terms)?;
         if let Some(due) = &invoice.due_date {
             write_cii_date(&mut w, "ram:DueDateDateTime", due)?;
         }
+        if extended {
+            if let Some(first) = invoice.cash_discounts.first() {
+                write_cii_discount_terms(&mut w, first, invoice.issue_date, currency)?;
+            }
+        }
         w.end_element("ram:SpecifiedTradePaymentTerms")?;
+        // Extended allows one discount per payment terms block
+        if extended {
+            for discount in invoice.cash_discounts.iter().skip(1) {
+                w.start_element("ram:SpecifiedTradePaymentTerms")?;
+                write_cii_discount_terms(&mut w, discount, invoice.issue_date, currency)?;
+                w.end_element("ram:SpecifiedTradePaymentTerms")?;
+            }
+        }
+    }
+    // Instalments and penalty terms (Extended)
+    if extended {
+        for term in &invoice.payment_schedule {
+            write_cii_payment_term(&mut w, term, invoice.issue_date, currency)?;
+        }
     }
 
     // Document-level allowances/charges
     for ac in invoice.allowances.iter().chain(invoice.charges.iter()) {
-        write_cii_allowance_charge(&mut w, ac)?;
+        write_cii_allowance_charge(&mut w, ac, currency)?;
+    }
+    // Logistics service charges; below Extended they are ordinary charges
+    for charge in &invoice.logistics_charges {
+        if extended {
+            w.start_element("ram:SpecifiedLogisticsServiceCharge")?;
+            w.text_element("ram:Description", &charge.description)?;
+            w.text_element("ram:AppliedAmount", &format_amount(charge.amount, currency))?;
+            w.start_element("ram:AppliedTradeTax")?;
+            w.text_element("ram:TypeCode", "VAT")?;
+            w.text_element("ram:CategoryCode", charge.tax_category.code())?;
+            w.text_element(
+                "ram:RateApplicablePercent",
+                &format_decimal(charge.tax_rate),
+            )?;
+            w.end_element("ram:AppliedTradeTax")?;
+            w.end_element("ram:SpecifiedLogisticsServiceCharge")?;
+        } else {
+            write_cii_allowance_charge(&mut w, &charge.to_charge(), currency)?;
+        }
     }
 
     // Monetary summation
     w.start_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
     w.text_element(
         "ram:LineTotalAmount",
-        &format_decimal(totals.line_net_total),
+        &format_amount(totals.line_net_total, currency),
     )?;
     if totals.charges_total > Decimal::ZERO {
         w.text_element(
             "ram:ChargeTotalAmount",
-            &format_decimal(totals.charges_total),
+            &format_amount(totals.charges_total, currency),
         )?;
     }
     if totals.allowances_total > Decimal::ZERO {
         w.text_element(
             "ram:AllowanceTotalAmount",
-            &format_decimal(totals.allowances_total),
+            &format_amount(totals.allowances_total, currency),
         )?;
     }
-    w.text_element("ram:TaxBasisTotalAmount", &format_decimal(totals.net_total))?;
+    w.text_element(
+        "ram:TaxBasisTotalAmount",
+        &format_amount(totals.net_total, currency),
+    )?;
     w.text_element_with_attrs(
         "ram:TaxTotalAmount",
-        &format_decimal(totals.vat_total),
+        &format_amount(totals.vat_total, currency),
         &[("currencyID", currency.as_str())],
     )?;
     // BT-111: Tax total in tax currency
@@ -350,15 +434,24 @@ pub fn to_cii_xml(invoice: &Invoice) -> XmlResult {
     {
         w.text_element_with_attrs(
             "ram:TaxTotalAmount",
-            &format_decimal(tax_total),
+            &format_amount(tax_total, tcc),
             &[("currencyID", tcc.as_str())],
         )?;
     }
-    w.text_element("ram:GrandTotalAmount", &format_decimal(totals.gross_total))?;
+    w.text_element(
+        "ram:GrandTotalAmount",
+        &format_amount(totals.gross_total, currency),
+    )?;
     if totals.prepaid > Decimal::ZERO {
-        w.text_element("ram:TotalPrepaidAmount", &format_decimal(totals.prepaid))?;
+        w.text_element(
+            "ram:TotalPrepaidAmount",
+            &format_amount(totals.prepaid, currency),
+        )?;
     }
-    w.text_element("ram:DuePayableAmount", &format_decimal(totals.amount_due))?;
+    w.text_element(
+        "ram:DuePayableAmount",
+        &format_amount(totals.amount_due, currency),
+    )?;
     w.end_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
 
     // BT-19: Buyer accounting reference
@@ -386,6 +479,65 @@ fn write_cii_date(w: &mut XmlWriter, element: &str, date: &NaiveDate) -> Result<
     Ok(())
 }
 
+fn write_cii_referenced_document(
+    w: &mut XmlWriter,
+    element: &str,
+    reference: &DocumentReference,
+) -> Result<(), RechnungError> {
+    w.start_element(element)?;
+    w.text_element("ram:IssuerAssignedID", &reference.id)?;
+    if let Some(d) = &reference.issue_date {
+        write_cii_formatted_date(w, d)?;
+    }
+    w.end_element(element)?;
+    Ok(())
+}
+
+fn write_cii_additional_reference(
+    w: &mut XmlWriter,
+    reference: &AdditionalReference,
+) -> Result<(), RechnungError> {
+    w.start_element("ram:AdditionalReferencedDocument")?;
+    w.text_element("ram:IssuerAssignedID", &reference.id)?;
+    w.text_element("ram:TypeCode", &reference.type_code)?;
+    if let Some(name) = &reference.name {
+        w.text_element("ram:Name", name)?;
+    }
+    if let Some(scheme) = &reference.reference_type {
+        w.text_element("ram:ReferenceTypeCode", scheme)?;
+    }
+    if let Some(d) = &reference.issue_date {
+        write_cii_formatted_date(w, d)?;
+    }
+    w.end_element("ram:AdditionalReferencedDocument")?;
+    Ok(())
+}
+
+/// `ram:FormattedIssueDateTime` of a referenced document (qualified data type).
+fn write_cii_formatted_date(w: &mut XmlWriter, date: &NaiveDate) -> Result<(), RechnungError> {
+    w.start_element("ram:FormattedIssueDateTime")?;
+    w.text_element_with_attrs(
+        "qdt:DateTimeString",
+        &date.format("%Y%m%d").to_string(),
+        &[("format", "102")],
+    )?;
+    w.end_element("ram:FormattedIssueDateTime")?;
+    Ok(())
+}
+
+/// BT-30 / BT-47 with its optional ICD scheme (BT-30-1 / BT-47-1).
+fn write_registration_id(
+    w: &mut XmlWriter,
+    id: &str,
+    scheme: Option<&str>,
+) -> Result<(), RechnungError> {
+    match scheme {
+        Some(scheme) => w.text_element_with_attrs("ram:ID", id, &[("schemeID", scheme)])?,
+        None => w.text_element("ram:ID", id)?,
+    };
+    Ok(())
+}
+
 fn write_cii_party(w: &mut XmlWriter, party: &Party, element: &str) -> Result<(), RechnungError> {
     // CII schema requires strict element order within TradeParty:
     // Name → SpecifiedLegalOrganization → DefinedTradeContact →
@@ -396,7 +548,7 @@ fn write_cii_party(w: &mut XmlWriter, party: &Party, element: &str) -> Result<()
     // Legal organization
     if let Some(reg_id) = &party.registration_id {
         w.start_element("ram:SpecifiedLegalOrganization")?;
-        w.text_element("ram:ID", reg_id)?;
+        write_registration_id(w, reg_id, party.registration_scheme.as_deref())?;
         if let Some(tn) = &party.trading_name {
             w.text_element("ram:TradingBusinessName", tn)?;
         }
@@ -468,13 +620,23 @@ fn write_cii_party(w: &mut XmlWriter, party: &Party, element: &str) -> Result<()
 fn write_cii_line(
     w: &mut XmlWriter,
     line: &LineItem,
-    _currency: &str,
+    currency: &str,
+    extended: bool,
 ) -> Result<(), RechnungError> {
     w.start_element("ram:IncludedSupplyChainTradeLineItem")?;
 
     // Line document
     w.start_element("ram:AssociatedDocumentLineDocument")?;
     w.text_element("ram:LineID", &line.id)?;
+    // Line grouping (Extended)
+    if extended {
+        if let Some(parent) = &line.parent_line_id {
+            w.text_element("ram:ParentLineID", parent)?;
+        }
+        if let Some(status) = line.line_status {
+            w.text_element("ram:LineStatusReasonCode", status.code())?;
+        }
+    }
     // BT-127: Line note
     if let Some(note) = &line.note {
         w.start_element("ram:IncludedNote")?;
@@ -509,6 +671,13 @@ fn write_cii_line(
     for attr in &line.attributes {
         w.start_element("ram:ApplicableProductCharacteristic")?;
         w.text_element("ram:Description", &attr.name)?;
+        if let (true, Some(measure)) = (extended, &attr.measure) {
+            w.text_element_with_attrs(
+                "ram:ValueMeasure",
+                &format_decimal(measure.value),
+                &[("unitCode", measure.unit.as_str())],
+            )?;
+        }
         w.text_element("ram:Value", &attr.value)?;
         w.end_element("ram:ApplicableProductCharacteristic")?;
     }
@@ -516,6 +685,27 @@ fn write_cii_line(
 
     // Trade agreement (BG-29: price details)
     w.start_element("ram:SpecifiedLineTradeAgreement")?;
+    // BT-132: Referenced purchase order line; the order number is Extended
+    let order_reference = line.order_reference.as_ref().filter(|_| extended);
+    if order_reference.is_some() || line.order_line_id.is_some() {
+        w.start_element("ram:BuyerOrderReferencedDocument")?;
+        if let Some(order) = order_reference {
+            w.text_element("ram:IssuerAssignedID", order)?;
+        }
+        if let Some(order_line) = &line.order_line_id {
+            w.text_element("ram:LineID", order_line)?;
+        }
+        w.end_element("ram:BuyerOrderReferencedDocument")?;
+    }
+    if extended {
+        for reference in line
+            .additional_references
+            .iter()
+            .filter(|r| !r.is_en16931(true))
+        {
+            write_cii_additional_reference(w, reference)?;
+        }
+    }
     if let Some(gp) = line.gross_price {
         w.start_element("ram:GrossPriceProductTradePrice")?;
         w.text_element("ram:ChargeAmount", &format_decimal(gp))?;
@@ -571,7 +761,7 @@ fn write_cii_line(
             "ram:ChargeIndicator",
             if ac.is_charge { "true" } else { "false" },
         )?;
-        w.text_element("ram:ActualAmount", &format_decimal(ac.amount))?;
+        w.text_element("ram:ActualAmount", &format_amount(ac.amount, currency))?;
         if let Some(reason) = &ac.reason {
             w.text_element("ram:Reason", reason)?;
         }
@@ -587,25 +777,89 @@ fn write_cii_line(
     w.end_element("ram:ApplicableTradeTax")?;
     w.start_element("ram:SpecifiedTradeSettlementLineMonetarySummation")?;
     if let Some(amt) = line.line_amount {
-        w.text_element("ram:LineTotalAmount", &format_decimal(amt))?;
+        w.text_element("ram:LineTotalAmount", &format_amount(amt, currency))?;
     }
     w.end_element("ram:SpecifiedTradeSettlementLineMonetarySummation")?;
+    // BT-128: Invoiced object identifier
+    for reference in line
+        .additional_references
+        .iter()
+        .filter(|r| r.is_en16931(true))
+    {
+        write_cii_additional_reference(w, reference)?;
+    }
     w.end_element("ram:SpecifiedLineTradeSettlement")?;
 
     w.end_element("ram:IncludedSupplyChainTradeLineItem")?;
     Ok(())
 }
 
+fn write_cii_discount_terms(
+    w: &mut XmlWriter,
+    discount: &CashDiscount,
+    issue_date: NaiveDate,
+    currency: &str,
+) -> Result<(), RechnungError> {
+    w.start_element("ram:ApplicableTradePaymentDiscountTerms")?;
+    write_cii_date(w, "ram:BasisDateTime", &issue_date)?;
+    w.text_element_with_attrs(
+        "ram:BasisPeriodMeasure",
+        &discount.days.to_string(),
+        &[("unitCode", "DAY")],
+    )?;
+    if let Some(base) = discount.base_amount {
+        w.text_element("ram:BasisAmount", &format_amount(base, currency))?;
+    }
+    w.text_element("ram:CalculationPercent", &format_decimal(discount.percent))?;
+    w.end_element("ram:ApplicableTradePaymentDiscountTerms")?;
+    Ok(())
+}
+
+fn write_cii_payment_term(
+    w: &mut XmlWriter,
+    term: &PaymentTerm,
+    issue_date: NaiveDate,
+    currency: &str,
+) -> Result<(), RechnungError> {
+    w.start_element("ram:SpecifiedTradePaymentTerms")?;
+    if let Some(description) = &term.description {
+        w.text_element("ram:Description", description)?;
+    }
+    if let Some(due) = &term.due_date {
+        write_cii_date(w, "ram:DueDateDateTime", due)?;
+    }
+    if let Some(amount) = term.amount {
+        w.text_element("ram:PartialPaymentAmount", &format_amount(amount, currency))?;
+    }
+    if let Some(penalty) = &term.penalty {
+        w.start_element("ram:ApplicableTradePaymentPenaltyTerms")?;
+        write_cii_date(w, "ram:BasisDateTime", &issue_date)?;
+        w.text_element_with_attrs(
+            "ram:BasisPeriodMeasure",
+            &penalty.days.to_string(),
+            &[("unitCode", "DAY")],
+        )?;
+        if let Some(base) = penalty.base_amount {
+            w.text_element("ram:BasisAmount", &format_amount(base, currency))?;
+        }
+        w.text_element("ram:CalculationPercent", &format_decimal(penalty.percent))?;
+        w.end_element("ram:ApplicableTradePaymentPenaltyTerms")?;
+    }
+    w.end_element("ram:SpecifiedTradePaymentTerms")?;
+    Ok(())
+}
+
 fn write_cii_allowance_charge(
     w: &mut XmlWriter,
     ac: &AllowanceCharge,
+    currency: &str,
 ) -> Result<(), RechnungError> {
     w.start_element("ram:SpecifiedTradeAllowanceCharge")?;
     w.text_element(
         "ram:ChargeIndicator",
         if ac.is_charge { "true" } else { "false" },
     )?;
-    w.text_element("ram:ActualAmount", &format_decimal(ac.amount))?;
+    w.text_element("ram:ActualAmount", &format_amount(ac.amount, currency))?;
     if let Some(reason) = &ac.reason {
         w.text_element("ram:Reason", reason)?;
     }
@@ -645,11 +899,13 @@ pub fn from_cii_xml(xml: &str) -> Result<Invoice, RechnungError> {
                     || name == "ram:ID"
                     || name == "ram:BilledQuantity"
                     || name == "ram:BasisQuantity"
+                    || name == "ram:ValueMeasure"
                     || name == "ram:GlobalID"
                     || name == "udt:DateTimeString"
                     || name == "ram:TaxTotalAmount"
                     || name == "ram:AttachmentBinaryObject"
                 {
+                    p.current_scheme_id = None;
                     for attr in e.attributes().flatten() {
                         let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
                         let val = std::str::from_utf8(&attr.value).unwrap_or("");
@@ -704,16 +960,54 @@ pub fn from_cii_xml(xml: &str) -> Result<Invoice, RechnungError> {
                         p.preceding_invoices.push(pi);
                     }
                 }
+                if ended == "ram:ApplicableTradePaymentDiscountTerms" {
+                    if let Some(terms) = p.current_discount_terms.take() {
+                        p.discount_terms.push(terms);
+                    }
+                }
+                let in_line_ctx = path
+                    .iter()
+                    .any(|p| p == "ram:IncludedSupplyChainTradeLineItem");
                 if ended == "ram:AdditionalReferencedDocument" {
-                    if let Some(att) = p.current_attachment.take() {
+                    if in_line_ctx {
+                        if let Some(line) = p.current_line.as_mut() {
+                            if let Some(reference) = line.current_reference.take() {
+                                line.additional_references.push(reference);
+                            }
+                        }
+                    } else if let Some(att) = p.current_attachment.take() {
                         p.attachments.push(att);
                     }
                 }
+                if ended == "ram:ApplicableProductCharacteristic" {
+                    if let Some(line) = p.current_line.as_mut() {
+                        if let Some(attr) = line.current_attr.take() {
+                            line.attributes.push(attr);
+                        }
+                    }
+                }
+                if ended == "ram:DespatchAdviceReferencedDocument" && !in_line_ctx {
+                    if let Some(reference) = p.current_document_reference.take() {
+                        p.despatch_advices.push(reference);
+                    }
+                }
+                if ended == "ram:DeliveryNoteReferencedDocument" && !in_line_ctx {
+                    if let Some(reference) = p.current_document_reference.take() {
+                        p.delivery_notes.push(reference);
+                    }
+                }
+                if ended == "ram:SpecifiedTradePaymentTerms" {
+                    if let Some(terms) = p.current_payment_terms.take() {
+                        p.payment_terms.push(terms);
+                    }
+                }
+                if ended == "ram:SpecifiedLogisticsServiceCharge" {
+                    if let Some(charge) = p.current_logistics_charge.take() {
+                        p.logistics_charges.push(charge);
+                    }
+                }
                 // Line-level or document-level SpecifiedTradeAllowanceCharge
                 if ended == "ram:SpecifiedTradeAllowanceCharge" {
-                    let in_line_ctx = path
-                        .iter()
-                        .any(|p| p == "ram:IncludedSupplyChainTradeLineItem");
                     if in_line_ctx {
                         if let Some(line) = p.current_line.as_mut() {
                             if let Some(ac) = line.current_ac.take() {
@@ -754,6 +1048,9 @@ struct CiiParsed {
     current_preceding: Option<CiiPrecedingInvoice>,
     attachments: Vec<CiiAttachment>,
     current_attachment: Option<CiiAttachment>,
+    despatch_advices: Vec<CiiDocumentReference>,
+    delivery_notes: Vec<CiiDocumentReference>,
+    current_document_reference: Option<CiiDocumentReference>,
     invoicing_period_start: Option<String>,
     invoicing_period_end: Option<String>,
 
@@ -761,6 +1058,7 @@ struct CiiParsed {
     seller_vat_id: Option<String>,
     seller_tax_number: Option<String>,
     seller_reg_id: Option<String>,
+    seller_reg_scheme: Option<String>,
     seller_trading_name: Option<String>,
     seller_street: Option<String>,
     seller_additional: Option<String>,
@@ -778,6 +1076,7 @@ struct CiiParsed {
     buyer_vat_id: Option<String>,
     buyer_trading_name: Option<String>,
     buyer_registration_id: Option<String>,
+    buyer_registration_scheme: Option<String>,
     buyer_street: Option<String>,
     buyer_additional: Option<String>,
     buyer_city: Option<String>,
@@ -811,8 +1110,10 @@ struct CiiParsed {
     payment_iban: Option<String>,
     payment_bic: Option<String>,
     payment_account_name: Option<String>,
-    payment_terms: Option<String>,
-    due_date: Option<String>,
+    payment_terms: Vec<CiiPaymentTerms>,
+    current_payment_terms: Option<CiiPaymentTerms>,
+    discount_terms: Vec<CiiDiscountTerms>,
+    current_discount_terms: Option<CiiDiscountTerms>,
     // BG-18: Card payment
     card_account_number: Option<String>,
     card_holder_name: Option<String>,
@@ -841,6 +1142,8 @@ struct CiiParsed {
     // Document-level allowances/charges
     doc_allowances_charges: Vec<CiiAllowanceCharge>,
     current_doc_ac: Option<CiiAllowanceCharge>,
+    logistics_charges: Vec<CiiAllowanceCharge>,
+    current_logistics_charge: Option<CiiAllowanceCharge>,
 
     // Delivery information (BG-13/BG-14/BG-15)
     delivery_actual_date: Option<String>,
@@ -890,14 +1193,28 @@ struct CiiLine {
     tax_category: Option<String>,
     tax_rate: Option<String>,
     origin_country: Option<String>,
-    attributes: Vec<(String, String)>,
-    current_attr_name: Option<String>,
+    attributes: Vec<CiiAttribute>,
+    current_attr: Option<CiiAttribute>,
+    parent_line_id: Option<String>,
+    line_status: Option<String>,
+    order_line_id: Option<String>,
+    order_reference: Option<String>,
+    additional_references: Vec<CiiAttachment>,
+    current_reference: Option<CiiAttachment>,
     invoicing_period_start: Option<String>,
     invoicing_period_end: Option<String>,
     allowances_charges: Vec<CiiAllowanceCharge>,
     current_ac: Option<CiiAllowanceCharge>,
 }
 
+#[derive(Default, Clone)]
+struct CiiAttribute {
+    name: Option<String>,
+    value: Option<String>,
+    measure: Option<String>,
+    measure_unit: Option<String>,
+}
+
 #[derive(Default, Clone)]
 struct CiiAllowanceCharge {
     is_charge: Option<String>,
@@ -908,6 +1225,27 @@ struct CiiAllowanceCharge {
     tax_rate: Option<String>,
 }
 
+#[derive(Default, Clone)]
+struct CiiDiscountTerms {
+    days: Option<String>,
+    base_amount: Option<String>,
+    percent: Option<String>,
+}
+
+#[derive(Default, Clone)]
+struct CiiPaymentTerms {
+    description: Option<String>,
+    due_date: Option<String>,
+    amount: Option<String>,
+    penalty: Option<CiiDiscountTerms>,
+}
+
+#[derive(Default, Clone)]
+struct CiiDocumentReference {
+    id: Option<String>,
+    issue_date: Option<String>,
+}
+
 #[derive(Default, Clone)]
 struct CiiPrecedingInvoice {
     number: Option<String>,
@@ -922,6 +1260,9 @@ struct CiiAttachment {
     mime_type: Option<String>,
     filename: Option<String>,
     external_uri: Option<String>,
+    type_code: Option<String>,
+    reference_type: Option<String>,
+    issue_date: Option<String>,
 }
 
 impl CiiParsed {
@@ -945,6 +1286,9 @@ impl CiiParsed {
         let in_monetary = path
             .iter()
             .any(|p| p == "ram:SpecifiedTradeSettlementHeaderMonetarySummation");
+        // FormattedIssueDateTime is qdt in D16B; older writers use udt
+        let is_issue_date = matches!(leaf, "qdt:DateTimeString" | "udt:DateTimeString")
+            && parent == "ram:FormattedIssueDateTime";
 
         // Document level
         if leaf == "ram:ID" && parent == "rsm:ExchangedDocument" {
@@ -962,7 +1306,10 @@ impl CiiParsed {
         if leaf == "ram:BuyerReference" {
             self.buyer_reference = Some(text.to_string());
         }
-        if leaf == "ram:IssuerAssignedID" && parent == "ram:BuyerOrderReferencedDocument" {
+        if leaf == "ram:IssuerAssignedID"
+            && parent == "ram:BuyerOrderReferencedDocument"
+            && !in_line
+        {
             self.order_reference = Some(text.to_string());
         }
         if leaf == "ram:IssuerAssignedID" && parent == "ram:SellerOrderReferencedDocument" {
@@ -1046,27 +1393,32 @@ impl CiiParsed {
             if leaf == "ram:IssuerAssignedID" {
                 pi.number = Some(text.to_string());
             }
-            if leaf == "udt:DateTimeString" && parent == "ram:FormattedIssueDateTime" {
+            if is_issue_date {
                 pi.issue_date = Some(text.to_string());
             }
         }
 
-        // BG-24: Document attachments
+        // BT-16: Despatch advice; delivery notes (Extended)
+        let in_delivery_ref = path.iter().any(|p| {
+            p == "ram:DespatchAdviceReferencedDocument" || p == "ram:DeliveryNoteReferencedDocument"
+        });
+        if in_delivery_ref && !in_line {
+            let reference = self
+                .current_document_reference
+                .get_or_insert_with(Default::default);
+            if leaf == "ram:IssuerAssignedID" {
+                reference.id = Some(text.to_string());
+            }
+            if is_issue_date {
+                reference.issue_date = Some(text.to_string());
+            }
+        }
+
+        // BG-24: Document attachments; BT-17/BT-18 and Extended references
         let in_additional_ref = path.iter().any(|p| p == "ram:AdditionalReferencedDocument");
         if in_additional_ref && !in_line {
             let att = self.current_attachment.get_or_insert_with(Default::default);
-            if leaf == "ram:IssuerAssignedID" && parent == "ram:AdditionalReferencedDocument" {
-                att.id = Some(text.to_string());
-            }
-            if leaf == "ram:Name" && parent == "ram:AdditionalReferencedDocument" {
-                att.description = Some(text.to_string());
-            }
-            if leaf == "ram:AttachmentBinaryObject" {
-                att.content = Some(text.to_string());
-            }
-            if leaf == "ram:URIID" && parent == "ram:AdditionalReferencedDocument" {
-                att.external_uri = Some(text.to_string());
-            }
+            Self::handle_additional_reference(att, leaf, parent, is_issue_date, text);
         }
 
         // Seller
@@ -1085,6 +1437,7 @@ impl CiiParsed {
                 }
                 "ram:ID" if parent == "ram:SpecifiedLegalOrganization" => {
                     self.seller_reg_id = Some(text.to_string());
+                    self.seller_reg_scheme = self.current_scheme_id.take();
                 }
                 "ram:TradingBusinessName" => self.seller_trading_name = Some(text.to_string()),
                 "ram:PersonName" => self.seller_contact_name = Some(text.to_string()),
@@ -1142,6 +1495,7 @@ impl CiiParsed {
                 }
                 "ram:ID" if parent == "ram:SpecifiedLegalOrganization" => {
                     self.buyer_registration_id = Some(text.to_string());
+                    self.buyer_registration_scheme = self.current_scheme_id.take();
                 }
                 "ram:TradingBusinessName" => self.buyer_trading_name = Some(text.to_string()),
                 "ram:PersonName" => self.buyer_contact_name = Some(text.to_string()),
@@ -1228,11 +1582,63 @@ impl CiiParsed {
             if leaf == "ram:AccountName" && parent == "ram:PayeePartyCreditorFinancialAccount" {
                 self.payment_account_name = Some(text.to_string());
             }
-            if leaf == "ram:Description" && parent == "ram:SpecifiedTradePaymentTerms" {
-                self.payment_terms = Some(text.to_string());
+            if path.iter().any(|p| p == "ram:SpecifiedTradePaymentTerms") {
+                let terms = self
+                    .current_payment_terms
+                    .get_or_insert_with(Default::default);
+                match leaf {
+                    "ram:Description" if parent == "ram:SpecifiedTradePaymentTerms" => {
+                        terms.description = Some(text.to_string());
+                    }
+                    "udt:DateTimeString" if parent == "ram:DueDateDateTime" => {
+                        terms.due_date = Some(text.to_string());
+                    }
+                    "ram:PartialPaymentAmount" => terms.amount = Some(text.to_string()),
+                    _ => {}
+                }
+                if path
+                    .iter()
+                    .any(|p| p == "ram:ApplicableTradePaymentPenaltyTerms")
+                {
+                    let penalty = terms.penalty.get_or_insert_with(Default::default);
+                    match leaf {
+                        "ram:BasisPeriodMeasure" => penalty.days = Some(text.to_string()),
+                        "ram:BasisAmount" => penalty.base_amount = Some(text.to_string()),
+                        "ram:CalculationPercent" => penalty.percent = Some(text.to_string()),
+                        _ => {}
+                    }
+                }
             }
-            if leaf == "udt:DateTimeString" && parent == "ram:DueDateDateTime" {
-                self.due_date = Some(text.to_string());
+            if path
+                .iter()
+                .any(|p| p == "ram:ApplicableTradePaymentDiscountTerms")
+            {
+                let terms = self
+                    .current_discount_terms
+                    .get_or_insert_with(Default::default);
+                match leaf {
+                    "ram:BasisPeriodMeasure" => terms.days = Some(text.to_string()),
+                    "ram:BasisAmount" => terms.base_amount = Some(text.to_string()),
+                    "ram:CalculationPercent" => terms.percent = Some(text.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        // Logistics service charges (Extended)
+        let in_logistics = path
+            .iter()
+            .any(|p| p == "ram:SpecifiedLogisticsServiceCharge");
+        if in_logistics && in_settlement && !in_line {
+            let charge = self
+                .current_logistics_charge
+                .get_or_insert_with(Default::default);
+            match leaf {
+                "ram:Description" => charge.reason = Some(text.to_string()),
+                "ram:AppliedAmount" => charge.amount = Some(text.to_string()),
+                "ram:CategoryCode" => charge.tax_category = Some(text.to_string()),
+                "ram:RateApplicablePercent" => charge.tax_rate = Some(text.to_string()),
+                _ => {}
             }
         }
 
@@ -1246,6 +1652,9 @@ impl CiiParsed {
             let ac = self.current_doc_ac.get_or_insert_with(Default::default);
             match leaf {
                 "ram:ChargeIndicator" => ac.is_charge = Some(text.to_string()),
+                "udt:Indicator" if parent == "ram:ChargeIndicator" => {
+                    ac.is_charge = Some(text.to_string())
+                }
                 "ram:ActualAmount" => ac.amount = Some(text.to_string()),
                 "ram:Reason" => ac.reason = Some(text.to_string()),
                 "ram:ReasonCode" => ac.reason_code = Some(text.to_string()),
@@ -1308,15 +1717,24 @@ impl CiiParsed {
                 .any(|p| p == "ram:ApplicableProductCharacteristic");
             let in_line_billing_period = path.iter().any(|p| p == "ram:BillingSpecifiedPeriod");
 
+            let in_line_ref = path.iter().any(|p| p == "ram:AdditionalReferencedDocument");
+
             if in_product_char {
-                // BT-160/BT-161: Item attributes
-                if leaf == "ram:Description" && parent == "ram:ApplicableProductCharacteristic" {
-                    line.current_attr_name = Some(text.to_string());
-                }
-                if leaf == "ram:Value" && parent == "ram:ApplicableProductCharacteristic" {
-                    let name = line.current_attr_name.take().unwrap_or_default();
-                    line.attributes.push((name, text.to_string()));
+                // BT-160/BT-161: Item attributes, measured values (Extended)
+                let attr = line.current_attr.get_or_insert_with(Default::default);
+                match leaf {
+                    "ram:Description" => attr.name = Some(text.to_string()),
+                    "ram:Value" => attr.value = Some(text.to_string()),
+                    "ram:ValueMeasure" => {
+                        attr.measure = Some(text.to_string());
+                        attr.measure_unit = self.current_unit_code.take();
+                    }
+                    _ => {}
                 }
+            } else if in_line_ref {
+                // BT-128 and Extended line references
+                let reference = line.current_reference.get_or_insert_with(Default::default);
+                Self::handle_additional_reference(reference, leaf, parent, is_issue_date, text);
             } else if in_line_billing_period {
                 // BG-26: Line invoicing period
                 if leaf == "udt:DateTimeString" && parent == "ram:StartDateTime" {
@@ -1335,6 +1753,9 @@ impl CiiParsed {
                     let ac = line.current_ac.get_or_insert_with(Default::default);
                     match leaf {
                         "ram:ChargeIndicator" => ac.is_charge = Some(text.to_string()),
+                        "udt:Indicator" if parent == "ram:ChargeIndicator" => {
+                            ac.is_charge = Some(text.to_string())
+                        }
                         "ram:ActualAmount" => ac.amount = Some(text.to_string()),
                         "ram:Reason" => ac.reason = Some(text.to_string()),
                         "ram:ReasonCode" => ac.reason_code = Some(text.to_string()),
@@ -1344,7 +1765,18 @@ impl CiiParsed {
                     }
                 } else {
                     match leaf {
-                        "ram:LineID" => line.id = Some(text.to_string()),
+                        "ram:LineID" if parent == "ram:AssociatedDocumentLineDocument" => {
+                            line.id = Some(text.to_string())
+                        }
+                        "ram:ParentLineID" => line.parent_line_id = Some(text.to_string()),
+                        "ram:LineStatusReasonCode" => line.line_status = Some(text.to_string()),
+                        // BT-132: Referenced purchase order line
+                        "ram:LineID" if parent == "ram:BuyerOrderReferencedDocument" => {
+                            line.order_line_id = Some(text.to_string())
+                        }
+                        "ram:IssuerAssignedID" if parent == "ram:BuyerOrderReferencedDocument" => {
+                            line.order_reference = Some(text.to_string())
+                        }
                         // BT-127: Line note
                         "ram:Content"
                             if parent == "ram:IncludedNote"
@@ -1405,6 +1837,44 @@ impl CiiParsed {
         }
     }
 
+    fn handle_additional_reference(
+        att: &mut CiiAttachment,
+        leaf: &str,
+        parent: &str,
+        is_issue_date: bool,
+        text: &str,
+    ) {
+        if parent == "ram:AdditionalReferencedDocument" {
+            match leaf {
+                "ram:IssuerAssignedID" => att.id = Some(text.to_string()),
+                "ram:Name" => att.description = Some(text.to_string()),
+                "ram:URIID" => att.external_uri = Some(text.to_string()),
+                "ram:TypeCode" => att.type_code = Some(text.to_string()),
+                "ram:ReferenceTypeCode" => att.reference_type = Some(text.to_string()),
+                _ => {}
+            }
+        }
+        if leaf == "ram:AttachmentBinaryObject" {
+            att.content = Some(text.to_string());
+        }
+        if is_issue_date {
+            att.issue_date = Some(text.to_string());
+        }
+    }
+
+    fn convert_reference(
+        att: CiiAttachment,
+        parse_date: &dyn Fn(&str) -> Result<NaiveDate, RechnungError>,
+    ) -> AdditionalReference {
+        AdditionalReference {
+            id: att.id.unwrap_or_default(),
+            type_code: att.type_code.unwrap_or_default(),
+            reference_type: att.reference_type,
+            name: att.description,
+            issue_date: att.issue_date.as_deref().and_then(|d| parse_date(d).ok()),
+        }
+    }
+
     fn convert_allowance_charges(
         parsed: Vec<CiiAllowanceCharge>,
         parse_decimal: &dyn Fn(&str) -> Result<Decimal, RechnungError>,
@@ -1463,6 +1933,7 @@ impl CiiParsed {
             vat_id: self.seller_vat_id,
             tax_number: self.seller_tax_number,
             registration_id: self.seller_reg_id,
+            registration_scheme: self.seller_reg_scheme,
             trading_name: self.seller_trading_name,
             address: Address {
                 street: self.seller_street,
@@ -1502,6 +1973,7 @@ impl CiiParsed {
             vat_id: self.buyer_vat_id,
             tax_number: None,
             registration_id: self.buyer_registration_id,
+            registration_scheme: self.buyer_registration_scheme,
             trading_name: self.buyer_trading_name,
             address: Address {
                 street: self.buyer_street,
@@ -1541,7 +2013,16 @@ impl CiiParsed {
             let attributes = pl
                 .attributes
                 .into_iter()
-                .map(|(n, v)| ItemAttribute { name: n, value: v })
+                .map(|a| ItemAttribute {
+                    name: a.name.unwrap_or_default(),
+                    value: a.value.unwrap_or_default(),
+                    measure: a.measure.as_deref().and_then(|m| {
+                        Some(Measure {
+                            value: parse_decimal(m).ok()?,
+                            unit: a.measure_unit.clone().unwrap_or_default(),
+                        })
+                    }),
+                })
                 .collect();
             let line_period = match (pl.invoicing_period_start, pl.invoicing_period_end) {
                 (Some(s), Some(e)) => {
@@ -1588,6 +2069,15 @@ impl CiiParsed {
                 line_amount: pl.line_total.as_deref().and_then(|s| parse_decimal(s).ok()),
                 attributes,
                 invoicing_period: line_period,
+                order_line_id: pl.order_line_id,
+                order_reference: pl.order_reference,
+                additional_references: pl
+                    .additional_references
+                    .into_iter()
+                    .map(|r| Self::convert_reference(r, &parse_cii_date))
+                    .collect(),
+                parent_line_id: pl.parent_line_id,
+                line_status: pl.line_status.as_deref().and_then(LineStatus::from_code),
             });
         }
 
@@ -1663,10 +2153,15 @@ impl CiiParsed {
             })
             .collect();
 
-        let attachments = self
-            .attachments
-            .into_iter()
-            .map(|a| DocumentAttachment {
+        // TypeCode 916 is an attachment (BG-24), anything else a reference
+        let mut attachments = Vec::new();
+        let mut additional_references = Vec::new();
+        for a in self.attachments {
+            if a.type_code.as_deref().is_some_and(|code| code != "916") {
+                additional_references.push(Self::convert_reference(a, &parse_cii_date));
+                continue;
+            }
+            attachments.push(DocumentAttachment {
                 id: a.id,
                 description: a.description,
                 external_uri: a.external_uri,
@@ -1675,8 +2170,37 @@ impl CiiParsed {
                     mime_type: a.mime_type.unwrap_or_default(),
                     filename: a.filename.unwrap_or_default(),
                 }),
+            });
+        }
+        let document_reference = |r: CiiDocumentReference| {
+            Some(DocumentReference {
+                id: r.id?,
+                issue_date: r.issue_date.as_deref().and_then(|d| parse_cii_date(d).ok()),
             })
+        };
+        let despatch_advices = self
+            .despatch_advices
+            .into_iter()
+            .filter_map(document_reference)
+            .collect();
+        let delivery_notes = self
+            .delivery_notes
+            .into_iter()
+            .filter_map(document_reference)
             .collect();
+        let logistics_charges = self
+            .logistics_charges
+            .into_iter()
+            .map(|c| {
+                Ok(LogisticsServiceCharge {
+                    description: c.reason.unwrap_or_default(),
+                    amount: parse_decimal(c.amount.as_deref().unwrap_or("0"))?,
+                    tax_category: TaxCategory::from_code(c.tax_category.as_deref().unwrap_or("S"))
+                        .unwrap_or(TaxCategory::StandardRate),
+                    tax_rate: parse_decimal(c.tax_rate.as_deref().unwrap_or("0"))?,
+                })
+            })
+            .collect::<Result<Vec<_>, RechnungError>>()?;
 
         let invoicing_period = match (self.invoicing_period_start, self.invoicing_period_end) {
             (Some(s), Some(e)) => {
@@ -1756,17 +2280,84 @@ impl CiiParsed {
             },
         });
 
+        // The first block with a description or due date carries BT-20 and
+        // BT-9; later ones and those with instalment amounts or penalties
+        // form the payment schedule (ZUGFeRD Extended).
+        let mut terms_text = None;
+        let mut due_date = None;
+        let mut payment_schedule = Vec::new();
+        for block in self.payment_terms {
+            let scheduled = block.amount.is_some() || block.penalty.is_some();
+            if block.description.is_none() && block.due_date.is_none() && !scheduled {
+                continue;
+            }
+            if !scheduled && terms_text.is_none() && due_date.is_none() {
+                terms_text = block.description;
+                due_date = block
+                    .due_date
+                    .as_deref()
+                    .and_then(|d| parse_cii_date(d).ok());
+                continue;
+            }
+            let penalty = match block.penalty {
+                Some(CiiDiscountTerms {
+                    days: Some(days),
+                    percent: Some(percent),
+                    base_amount,
+                }) => Some(PaymentPenalty {
+                    days: days.trim().parse().map_err(|_| {
+                        RechnungError::Builder(format!("invalid penalty period '{days}'"))
+                    })?,
+                    percent: parse_decimal(&percent)?,
+                    base_amount: base_amount.as_deref().map(parse_decimal).transpose()?,
+                }),
+                _ => None,
+            };
+            payment_schedule.push(PaymentTerm {
+                description: block.description,
+                due_date: block
+                    .due_date
+                    .as_deref()
+                    .and_then(|d| parse_cii_date(d).ok()),
+                amount: block.amount.as_deref().map(parse_decimal).transpose()?,
+                penalty,
+            });
+        }
+
+        // BT-20: #SKONTO# lines take precedence; structured discount terms
+        // (ZUGFeRD Extended) are used when the description has none.
+        let (mut cash_discounts, payment_terms) = match terms_text.as_deref() {
+            Some(text) => skonto::parse_payment_terms(text),
+            None => (Vec::new(), None),
+        };
+        if cash_discounts.is_empty() {
+            for terms in &self.discount_terms {
+                let (Some(days), Some(percent)) = (&terms.days, &terms.percent) else {
+                    continue;
+                };
+                cash_discounts.push(CashDiscount {
+                    days: days.trim().parse().map_err(|_| {
+                        RechnungError::Builder(format!("invalid discount period '{days}'"))
+                    })?,
+                    percent: parse_decimal(percent)?,
+                    base_amount: terms
+                        .base_amount
+                        .as_deref()
+                        .map(parse_decimal)
+                        .transpose()?,
+                });
+            }
+        }
+
         Ok(Invoice {
             number: self.number.unwrap_or_default(),
             issue_date,
-            due_date: self
-                .due_date
-                .as_deref()
-                .and_then(|d| parse_cii_date(d).ok()),
+            due_date,
             type_code: InvoiceTypeCode::from_code(type_code_num)
                 .unwrap_or(InvoiceTypeCode::Invoice),
             currency_code: self.currency_code.unwrap_or_else(|| "EUR".to_string()),
             tax_currency_code: self.tax_currency_code,
+            exchange_rate: None,
             notes: self.notes,
             buyer_reference: self.buyer_reference,
             project_reference: self.project_reference,
@@ -1794,7 +2385,8 @@ impl CiiParsed {
                 amount_due: parse_decimal(self.due_payable.as_deref().unwrap_or("0"))?,
                 vat_breakdown,
             }),
-            payment_terms: self.payment_terms,
+            payment_terms,
+            cash_discounts,
             payment,
             tax_point_date: self
                 .tax_point_date
@@ -1811,6 +2403,11 @@ impl CiiParsed {
             invoicing_period,
             preceding_invoices,
             attachments,
+            additional_references,
+            despatch_advices,
+            delivery_notes,
+            logistics_charges,
+            payment_schedule,
             delivery,
         })
     }
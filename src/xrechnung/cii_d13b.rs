@@ -0,0 +1,212 @@
+//! ZUGFeRD 1.0 `CrossIndustryDocument` (UN/CEFACT CII D13B) parsing.
+//!
+//! ZUGFeRD 1.0 predates EN 16931 and uses the D13B schema, which maps almost
+//! one to one onto the D16B `CrossIndustryInvoice`: the trade agreement,
+//! delivery and settlement groups carry `SupplyChain` names, tax rates are
+//! `ApplicablePercent`, referenced documents have an `ID` and a plain
+//! `IssueDateTime`, and freight is a `SpecifiedLogisticsServiceCharge`.
+//! [`from_cii_d13b_xml`] rewrites these into their D16B equivalents and
+//! parses the result with the CII parser, so Basic, Comfort and Extended
+//! documents end up in the same [`Invoice`] model.
+
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::{NsReader, Writer};
+
+use super::cii_ns;
+use crate::core::{Invoice, RechnungError};
+
+/// ZUGFeRD 1.0 root namespace (`rsm`).
+pub const ZUGFERD1_NAMESPACE: &str = "urn:ferd:CrossIndustryDocument:invoice:1p0";
+
+const RAM_D13B: &str =
+    "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:12";
+const UDT_D13B: &str = "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:15";
+const QDT_D13B: &str = "urn:un:unece:uncefact:data:standard:QualifiedDataType:12";
+
+/// Parse a ZUGFeRD 1.0 `CrossIndustryDocument` into an Invoice.
+pub fn from_cii_d13b_xml(xml: &str) -> Result<Invoice, RechnungError> {
+    super::from_cii_xml(&to_d16b(xml)?)
+}
+
+fn xml_err(e: impl std::fmt::Display) -> RechnungError {
+    RechnungError::Xml(format!("ZUGFeRD 1.0 XML error: {e}"))
+}
+
+/// Rewrite a D13B document into D16B element names.
+fn to_d16b(xml: &str) -> Result<String, RechnungError> {
+    let mut reader = NsReader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut w = Writer::new(Vec::new());
+    // Per open element, the D16B elements to close when it ends (innermost
+    // first); empty when the element is dropped
+    let mut open: Vec<Vec<String>> = Vec::new();
+    // Depth of an open referenced-document `IssueDateTime`, whose text is
+    // rewritten as a format 102 date
+    let mut date_depth: Option<usize> = None;
+
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(xml_err)?;
+        match event {
+            Event::Start(e) => {
+                let closes = if date_depth.is_some() {
+                    Vec::new()
+                } else {
+                    start(&mut w, &ns, &e, &open, &mut date_depth)?
+                };
+                open.push(closes);
+            }
+            Event::Empty(e) if date_depth.is_none() => {
+                for name in start(&mut w, &ns, &e, &open, &mut date_depth)? {
+                    write(&mut w, Event::End(BytesEnd::new(name)))?;
+                }
+                date_depth = None;
+            }
+            Event::Text(e) => {
+                if date_depth.is_some() {
+                    let text = e.unescape().map_err(xml_err)?;
+                    let digits: String =
+                        text.chars().filter(char::is_ascii_digit).take(8).collect();
+                    write(&mut w, Event::Text(BytesText::new(&digits)))?;
+                } else {
+                    write(&mut w, Event::Text(e))?;
+                }
+            }
+            Event::CData(e) => write(&mut w, Event::CData(e))?,
+            Event::End(_) => {
+                if date_depth == Some(open.len()) {
+                    date_depth = None;
+                }
+                for name in open.pop().unwrap_or_default() {
+                    write(&mut w, Event::End(BytesEnd::new(name)))?;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    String::from_utf8(w.into_inner()).map_err(xml_err)
+}
+
+/// Write the D16B start tag(s) for a D13B element and return the names to
+/// close at its end.
+fn start(
+    w: &mut Writer<Vec<u8>>,
+    ns: &ResolveResult,
+    e: &BytesStart,
+    open: &[Vec<String>],
+    date_depth: &mut Option<usize>,
+) -> Result<Vec<String>, RechnungError> {
+    let local = std::str::from_utf8(e.local_name().into_inner()).map_err(xml_err)?;
+    let prefix = match ns {
+        ResolveResult::Bound(Namespace(ns)) => match *ns {
+            ns if ns == ZUGFERD1_NAMESPACE.as_bytes() => Some("rsm"),
+            ns if ns == RAM_D13B.as_bytes() => Some("ram"),
+            ns if ns == UDT_D13B.as_bytes() => Some("udt"),
+            ns if ns == QDT_D13B.as_bytes() => Some("qdt"),
+            _ => None,
+        },
+        _ => None,
+    };
+    let parent = open
+        .iter()
+        .rev()
+        .find_map(|names| names.last())
+        .map(String::as_str);
+    if parent.is_none() && (prefix != Some("rsm") || local != "CrossIndustryDocument") {
+        return Err(RechnungError::Xml(
+            "not a ZUGFeRD 1.0 document: root element must be rsm:CrossIndustryDocument".into(),
+        ));
+    }
+    let in_line = open
+        .iter()
+        .flatten()
+        .any(|n| n == "ram:IncludedSupplyChainTradeLineItem");
+
+    let name = match prefix {
+        None => std::str::from_utf8(e.name().into_inner())
+            .map_err(xml_err)?
+            .to_string(),
+        Some(prefix) => {
+            let local = match (local, parent) {
+                ("CrossIndustryDocument", _) => "CrossIndustryInvoice",
+                ("SpecifiedExchangedDocumentContext", _) => "ExchangedDocumentContext",
+                ("HeaderExchangedDocument", _) => "ExchangedDocument",
+                ("SpecifiedSupplyChainTradeTransaction", _) => "SupplyChainTradeTransaction",
+                ("ApplicableSupplyChainTradeAgreement", _) => "ApplicableHeaderTradeAgreement",
+                ("ApplicableSupplyChainTradeDelivery", _) => "ApplicableHeaderTradeDelivery",
+                ("ApplicableSupplyChainTradeSettlement", _) => "ApplicableHeaderTradeSettlement",
+                ("SpecifiedSupplyChainTradeAgreement", _) => "SpecifiedLineTradeAgreement",
+                ("SpecifiedSupplyChainTradeDelivery", _) => "SpecifiedLineTradeDelivery",
+                ("SpecifiedSupplyChainTradeSettlement", _) => "SpecifiedLineTradeSettlement",
+                ("SpecifiedTradeSettlementMonetarySummation", _) if in_line => {
+                    "SpecifiedTradeSettlementLineMonetarySummation"
+                }
+                ("SpecifiedTradeSettlementMonetarySummation", _) => {
+                    "SpecifiedTradeSettlementHeaderMonetarySummation"
+                }
+                ("ApplicablePercent", Some("ram:ApplicableTradeTax" | "ram:CategoryTradeTax")) => {
+                    "RateApplicablePercent"
+                }
+                ("ID", Some(p)) if p.ends_with("ReferencedDocument") => "IssuerAssignedID",
+                ("IssueDateTime", Some(p)) if p.ends_with("ReferencedDocument") => {
+                    // Plain date in D13B, formatted date in D16B
+                    write(
+                        w,
+                        Event::Start(BytesStart::new("ram:FormattedIssueDateTime")),
+                    )?;
+                    let mut date = BytesStart::new("udt:DateTimeString");
+                    date.push_attribute(("format", "102"));
+                    write(w, Event::Start(date))?;
+                    *date_depth = Some(open.len() + 1);
+                    return Ok(vec![
+                        "udt:DateTimeString".into(),
+                        "ram:FormattedIssueDateTime".into(),
+                    ]);
+                }
+                // Freight and packing become a document-level charge
+                ("SpecifiedLogisticsServiceCharge", _) => {
+                    write(
+                        w,
+                        Event::Start(BytesStart::new("ram:SpecifiedTradeAllowanceCharge")),
+                    )?;
+                    write(w, Event::Start(BytesStart::new("ram:ChargeIndicator")))?;
+                    write(w, Event::Start(BytesStart::new("udt:Indicator")))?;
+                    write(w, Event::Text(BytesText::new("true")))?;
+                    write(w, Event::End(BytesEnd::new("udt:Indicator")))?;
+                    write(w, Event::End(BytesEnd::new("ram:ChargeIndicator")))?;
+                    return Ok(vec!["ram:SpecifiedTradeAllowanceCharge".into()]);
+                }
+                ("Description", Some("ram:SpecifiedTradeAllowanceCharge")) => "Reason",
+                ("AppliedAmount", Some("ram:SpecifiedTradeAllowanceCharge")) => "ActualAmount",
+                ("AppliedTradeTax", Some("ram:SpecifiedTradeAllowanceCharge")) => {
+                    "CategoryTradeTax"
+                }
+                (local, _) => local,
+            };
+            format!("{prefix}:{local}")
+        }
+    };
+
+    let mut elem = BytesStart::new(name.as_str());
+    if parent.is_none() {
+        elem.push_attribute(("xmlns:rsm", cii_ns::RSM));
+        elem.push_attribute(("xmlns:ram", cii_ns::RAM));
+        elem.push_attribute(("xmlns:qdt", cii_ns::QDT));
+        elem.push_attribute(("xmlns:udt", cii_ns::UDT));
+    }
+    for attr in e.attributes() {
+        let attr = attr.map_err(xml_err)?;
+        let key = attr.key.as_ref();
+        if key != b"xmlns" && !key.starts_with(b"xmlns:") {
+            elem.push_attribute(attr);
+        }
+    }
+    write(w, Event::Start(elem))?;
+    Ok(vec![name])
+}
+
+fn write(w: &mut Writer<Vec<u8>>, event: Event) -> Result<(), RechnungError> {
+    w.write_event(event).map_err(xml_err)
+}
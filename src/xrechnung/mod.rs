@@ -6,6 +6,7 @@
 //!
 //! - **UBL 2.1** — OASIS Universal Business Language (`to_ubl_xml`, `from_ubl_xml`)
 //! - **CII** — UN/CEFACT Cross Industry Invoice (`to_cii_xml`, `from_cii_xml`)
+//! - **CII D13B** — ZUGFeRD 1.0 `CrossIndustryDocument`, parsing only (`from_cii_d13b_xml`)
 //!
 //! # Example
 //!
@@ -18,12 +19,18 @@
 //! let cii_xml = xrechnung::to_cii_xml(&invoice).unwrap();
 //! ```
 
+#[cfg(any(feature = "as4", feature = "signature"))]
+pub(crate) mod c14n;
 mod cii;
+mod cii_d13b;
 mod ubl;
 mod validate;
 pub(crate) mod xml_utils;
 
+#[cfg(feature = "zugferd")]
+pub(crate) use cii::write_cii_xml;
 pub use cii::{from_cii_xml, to_cii_xml};
+pub use cii_d13b::{ZUGFERD1_NAMESPACE, from_cii_d13b_xml};
 pub use ubl::{from_ubl_xml, to_ubl_xml};
 pub use validate::{validate_xrechnung, validate_xrechnung_full};
 
@@ -37,12 +44,15 @@ pub enum XmlSyntax {
     Ubl,
     /// UN/CEFACT CII (root element `CrossIndustryInvoice`).
     Cii,
+    /// ZUGFeRD 1.0 CII D13B (root element `CrossIndustryDocument`).
+    CiiD13b,
 }
 
 /// Parse an invoice from XML, auto-detecting whether it is UBL or CII.
 ///
 /// Peeks at the root element to determine the syntax, then delegates to
-/// [`from_ubl_xml`] or [`from_cii_xml`].
+/// [`from_ubl_xml`], [`from_cii_xml`] or, for ZUGFeRD 1.0 documents,
+/// [`from_cii_d13b_xml`].
 ///
 /// ```no_run
 /// use faktura::xrechnung;
@@ -55,14 +65,15 @@ pub fn from_xml(xml: &str) -> Result<(Invoice, XmlSyntax), RechnungError> {
     match detect_syntax(xml) {
         Some(XmlSyntax::Ubl) => from_ubl_xml(xml).map(|inv| (inv, XmlSyntax::Ubl)),
         Some(XmlSyntax::Cii) => from_cii_xml(xml).map(|inv| (inv, XmlSyntax::Cii)),
+        Some(XmlSyntax::CiiD13b) => from_cii_d13b_xml(xml).map(|inv| (inv, XmlSyntax::CiiD13b)),
         None => Err(RechnungError::Xml(
-            "cannot detect XML syntax: root element is neither UBL (Invoice/CreditNote) nor CII (CrossIndustryInvoice)".into(),
+            "cannot detect XML syntax: root element is neither UBL (Invoice/CreditNote) nor CII (CrossIndustryInvoice/CrossIndustryDocument)".into(),
         )),
     }
 }
 
 /// Detect the XML syntax by scanning for the root element name.
-fn detect_syntax(xml: &str) -> Option<XmlSyntax> {
+pub(crate) fn detect_syntax(xml: &str) -> Option<XmlSyntax> {
     use quick_xml::Reader;
     use quick_xml::events::Event;
 
@@ -76,6 +87,7 @@ fn detect_syntax(xml: &str) -> Option<XmlSyntax> {
                 return match name {
                     "Invoice" | "CreditNote" => Some(XmlSyntax::Ubl),
                     "CrossIndustryInvoice" => Some(XmlSyntax::Cii),
+                    "CrossIndustryDocument" => Some(XmlSyntax::CiiD13b),
                     _ => None,
                 };
             }
@@ -94,6 +106,19 @@ pub const XRECHNUNG_CUSTOMIZATION_ID: &str =
 /// Peppol BIS Billing 3.0 profile identifier (BT-23).
 pub const PEPPOL_PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
 
+/// Peppol BIS Self-Billing 3.0 profile identifier (BT-23), used for
+/// self-billed invoices and credit notes.
+pub const PEPPOL_SELF_BILLING_PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:selfbilling:01:1.0";
+
+/// Business process (BT-23) of an invoice: self-billing or billing.
+pub(crate) fn profile_id(invoice: &Invoice) -> &'static str {
+    if invoice.type_code.is_self_billed() {
+        PEPPOL_SELF_BILLING_PROFILE_ID
+    } else {
+        PEPPOL_PROFILE_ID
+    }
+}
+
 /// UBL 2.1 namespace URIs.
 pub mod ubl_ns {
     /// UBL Invoice namespace.
@@ -5,10 +5,13 @@ use rust_decimal::Decimal;
 use std::str::FromStr;
 
 use super::xml_utils::{XmlResult, XmlWriter};
-use super::{PEPPOL_PROFILE_ID, XRECHNUNG_CUSTOMIZATION_ID, ubl_ns};
+use super::{XRECHNUNG_CUSTOMIZATION_ID, profile_id, ubl_ns};
 use crate::core::*;
 
 /// Generate XRechnung-compliant UBL 2.1 Invoice XML from an Invoice.
+///
+/// Group and information lines ([`LineStatus`]) are left out: UBL has no
+/// line status, and their amounts are not part of BT-106 (BR-CO-10).
 pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     let totals = invoice.totals.as_ref().ok_or_else(|| {
         RechnungError::Builder("totals must be calculated before XML generation".into())
@@ -18,7 +21,7 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     let mut w = XmlWriter::new()?;
 
     // Root element with namespaces
-    let is_credit_note = invoice.type_code == InvoiceTypeCode::CreditNote;
+    let is_credit_note = invoice.type_code.is_credit_note();
     let root_tag = if is_credit_note {
         "ubl:CreditNote"
     } else {
@@ -42,7 +45,7 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     // BT-24: CustomizationID
     w.text_element("cbc:CustomizationID", XRECHNUNG_CUSTOMIZATION_ID)?;
     // BT-23: ProfileID
-    w.text_element("cbc:ProfileID", PEPPOL_PROFILE_ID)?;
+    w.text_element("cbc:ProfileID", profile_id(invoice))?;
     // BT-1: Invoice number
     w.text_element("cbc:ID", &invoice.number)?;
     // BT-2: Issue date
@@ -101,6 +104,24 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
         w.end_element("cac:BillingReference")?;
     }
 
+    // BT-16: Despatch advice reference (EN 16931 carries one)
+    if let Some(advice) = invoice.despatch_advices.first() {
+        w.start_element("cac:DespatchDocumentReference")?;
+        w.text_element("cbc:ID", &advice.id)?;
+        w.end_element("cac:DespatchDocumentReference")?;
+    }
+
+    // BT-17: Tender or lot reference
+    for reference in invoice
+        .additional_references
+        .iter()
+        .filter(|r| r.type_code == "50")
+    {
+        w.start_element("cac:OriginatorDocumentReference")?;
+        w.text_element("cbc:ID", &reference.id)?;
+        w.end_element("cac:OriginatorDocumentReference")?;
+    }
+
     // BT-12: Contract reference
     if let Some(cr) = &invoice.contract_reference {
         w.start_element("cac:ContractDocumentReference")?;
@@ -131,6 +152,17 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
         w.end_element("cac:AdditionalDocumentReference")?;
     }
 
+    // BT-18: Invoiced object identifier; other reference types are Extended
+    for reference in invoice
+        .additional_references
+        .iter()
+        .filter(|r| r.type_code == "130")
+    {
+        w.start_element("cac:AdditionalDocumentReference")?;
+        write_ubl_object_reference(&mut w, reference)?;
+        w.end_element("cac:AdditionalDocumentReference")?;
+    }
+
     // BT-11: Project reference
     if let Some(pr) = &invoice.project_reference {
         w.start_element("cac:ProjectReference")?;
@@ -330,9 +362,9 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     }
 
     // BT-20: Payment terms
-    if let Some(terms) = &invoice.payment_terms {
+    if let Some(terms) = skonto::payment_terms_text(invoice) {
         w.start_element("cac:PaymentTerms")?;
-        w.text_element("cbc:Note", terms)?;
+        w.text_element("cbc:Note", &terms)?;
         w.end_element("cac:PaymentTerms")?;
     }
 
@@ -345,6 +377,10 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     for charge in &invoice.charges {
         write_ubl_allowance_charge(&mut w, charge, currency)?;
     }
+    // Logistics service charges (ZUGFeRD Extended) have no UBL equivalent
+    for charge in &invoice.logistics_charges {
+        write_ubl_allowance_charge(&mut w, &charge.to_charge(), currency)?;
+    }
 
     // BG-23: Tax total
     w.start_element("cac:TaxTotal")?;
@@ -404,8 +440,8 @@ pub fn to_ubl_xml(invoice: &Invoice) -> XmlResult {
     w.amount_element("cbc:PayableAmount", totals.amount_due, currency)?;
     w.end_element("cac:LegalMonetaryTotal")?;
 
-    // BG-25: Invoice lines
-    for line in &invoice.lines {
+    // BG-25: Invoice lines, without group and information lines
+    for line in invoice.lines.iter().filter(|l| l.counts_in_totals()) {
         write_ubl_line(&mut w, line, currency)?;
     }
 
@@ -472,7 +508,12 @@ fn write_ubl_party(w: &mut XmlWriter, party: &Party, wrapper: &str) -> Result<()
     w.start_element("cac:PartyLegalEntity")?;
     w.text_element("cbc:RegistrationName", &party.name)?;
     if let Some(reg_id) = &party.registration_id {
-        w.text_element("cbc:CompanyID", reg_id)?;
+        match &party.registration_scheme {
+            Some(scheme) => {
+                w.text_element_with_attrs("cbc:CompanyID", reg_id, &[("schemeID", scheme)])?
+            }
+            None => w.text_element("cbc:CompanyID", reg_id)?,
+        };
     }
     w.end_element("cac:PartyLegalEntity")?;
 
@@ -530,6 +571,21 @@ fn write_ubl_allowance_charge(
     Ok(())
 }
 
+/// `cbc:ID` with its scheme (BT-18-1 / BT-128-1) and the document type 130.
+fn write_ubl_object_reference(
+    w: &mut XmlWriter,
+    reference: &AdditionalReference,
+) -> Result<(), RechnungError> {
+    match &reference.reference_type {
+        Some(scheme) => {
+            w.text_element_with_attrs("cbc:ID", &reference.id, &[("schemeID", scheme)])?
+        }
+        None => w.text_element("cbc:ID", &reference.id)?,
+    };
+    w.text_element("cbc:DocumentTypeCode", "130")?;
+    Ok(())
+}
+
 fn write_ubl_line(w: &mut XmlWriter, line: &LineItem, currency: &str) -> Result<(), RechnungError> {
     w.start_element("cac:InvoiceLine")?;
     // BT-126: Line ID
@@ -553,6 +609,24 @@ fn write_ubl_line(w: &mut XmlWriter, line: &LineItem, currency: &str) -> Result<
         w.end_element("cac:InvoicePeriod")?;
     }
 
+    // BT-132: Referenced purchase order line
+    if let Some(order_line) = &line.order_line_id {
+        w.start_element("cac:OrderLineReference")?;
+        w.text_element("cbc:LineID", order_line)?;
+        w.end_element("cac:OrderLineReference")?;
+    }
+
+    // BT-128: Invoiced object identifier
+    for reference in line
+        .additional_references
+        .iter()
+        .filter(|r| r.is_en16931(true))
+    {
+        w.start_element("cac:DocumentReference")?;
+        write_ubl_object_reference(w, reference)?;
+        w.end_element("cac:DocumentReference")?;
+    }
+
     // BG-27: Line allowances
     for ac in &line.allowances {
         write_ubl_allowance_charge(w, ac, currency)?;
@@ -612,7 +686,7 @@ fn write_ubl_line(w: &mut XmlWriter, line: &LineItem, currency: &str) -> Result<
 
     // BG-29: Price details
     w.start_element("cac:Price")?;
-    w.amount_element("cbc:PriceAmount", line.unit_price, currency)?;
+    w.price_element("cbc:PriceAmount", line.unit_price, currency)?;
     // BT-149/BT-150: Base quantity
     if let Some(bq) = line.base_quantity {
         let bq_unit = line.base_quantity_unit.as_deref().unwrap_or(&line.unit);
@@ -624,8 +698,8 @@ fn write_ubl_line(w: &mut XmlWriter, line: &LineItem, currency: &str) -> Result<
         if discount > Decimal::ZERO {
             w.start_element("cac:AllowanceCharge")?;
             w.text_element("cbc:ChargeIndicator", "false")?;
-            w.amount_element("cbc:Amount", discount, currency)?;
-            w.amount_element("cbc:BaseAmount", gp, currency)?;
+            w.price_element("cbc:Amount", discount, currency)?;
+            w.price_element("cbc:BaseAmount", gp, currency)?;
             w.end_element("cac:AllowanceCharge")?;
         }
     }
@@ -662,7 +736,15 @@ pub fn from_ubl_xml(xml: &str) -> Result<Invoice, RechnungError> {
                     || name == "cbc:EmbeddedDocumentBinaryObject"
                     || name == "cbc:PaymentMeansCode"
                     || name == "cbc:BaseQuantity"
+                    || (name == "cbc:ID"
+                        && matches!(
+                            path.last().map(String::as_str),
+                            Some("cac:AdditionalDocumentReference" | "cac:DocumentReference")
+                        ))
+                    || (name == "cbc:CompanyID"
+                        && path.last().is_some_and(|p| p == "cac:PartyLegalEntity"))
                 {
+                    invoice.current_scheme_id = None;
                     for attr in e.attributes().flatten() {
                         let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
                         let val = std::str::from_utf8(&attr.value).unwrap_or("");
@@ -798,6 +880,8 @@ struct ParsedInvoice {
     current_preceding: Option<ParsedPrecedingInvoice>,
     attachments: Vec<ParsedAttachment>,
     current_attachment: Option<ParsedAttachment>,
+    despatch_advices: Vec<String>,
+    originator_references: Vec<String>,
     invoicing_period_start: Option<String>,
     invoicing_period_end: Option<String>,
 
@@ -807,6 +891,7 @@ struct ParsedInvoice {
     seller_vat_id: Option<String>,
     seller_tax_number: Option<String>,
     seller_reg_id: Option<String>,
+    seller_reg_scheme: Option<String>,
     seller_street: Option<String>,
     seller_additional: Option<String>,
     seller_city: Option<String>,
@@ -824,6 +909,7 @@ struct ParsedInvoice {
     buyer_trading_name: Option<String>,
     buyer_vat_id: Option<String>,
     buyer_reg_id: Option<String>,
+    buyer_reg_scheme: Option<String>,
     buyer_street: Option<String>,
     buyer_additional: Option<String>,
     buyer_city: Option<String>,
@@ -942,6 +1028,8 @@ struct ParsedLine {
     current_attr_name: Option<String>,
     invoicing_period_start: Option<String>,
     invoicing_period_end: Option<String>,
+    order_line_id: Option<String>,
+    object_references: Vec<(String, Option<String>)>,
     allowances_charges: Vec<ParsedAllowanceCharge>,
     current_ac: Option<ParsedAllowanceCharge>,
 }
@@ -967,6 +1055,8 @@ struct ParsedPrecedingInvoice {
 #[derive(Default, Clone)]
 struct ParsedAttachment {
     id: Option<String>,
+    scheme_id: Option<String>,
+    type_code: Option<String>,
     description: Option<String>,
     content: Option<String>,
     mime_type: Option<String>,
@@ -974,6 +1064,17 @@ struct ParsedAttachment {
     external_uri: Option<String>,
 }
 
+/// An invoiced object reference (BT-18 / BT-128, document type 130).
+fn object_reference(id: String, scheme: Option<String>) -> AdditionalReference {
+    AdditionalReference {
+        id,
+        type_code: "130".into(),
+        reference_type: scheme,
+        name: None,
+        issue_date: None,
+    }
+}
+
 /// Check if a parent element name is a UBL root (with or without `ubl:` prefix).
 fn is_ubl_root(name: &str) -> bool {
     matches!(
@@ -1051,6 +1152,12 @@ impl ParsedInvoice {
                 "cbc:ID" if parent == "cac:ContractDocumentReference" => {
                     self.contract_reference = Some(text.to_string());
                 }
+                "cbc:ID" if parent == "cac:DespatchDocumentReference" => {
+                    self.despatch_advices.push(text.to_string());
+                }
+                "cbc:ID" if parent == "cac:OriginatorDocumentReference" => {
+                    self.originator_references.push(text.to_string());
+                }
                 "cbc:ID" if parent == "cac:ProjectReference" => {
                     self.project_reference = Some(text.to_string());
                 }
@@ -1084,7 +1191,9 @@ impl ParsedInvoice {
             match leaf {
                 "cbc:ID" if parent == "cac:AdditionalDocumentReference" => {
                     att.id = Some(text.to_string());
+                    att.scheme_id = self.current_scheme_id.take();
                 }
+                "cbc:DocumentTypeCode" => att.type_code = Some(text.to_string()),
                 "cbc:DocumentDescription" => {
                     att.description = Some(text.to_string());
                 }
@@ -1250,6 +1359,7 @@ impl ParsedInvoice {
                 }
                 "cbc:CompanyID" if parent == "cac:PartyLegalEntity" => {
                     self.seller_reg_id = Some(text.to_string());
+                    self.seller_reg_scheme = self.current_scheme_id.take();
                 }
                 "cbc:CompanyID" if parent == "cac:PartyTaxScheme" => {
                     // Determine VAT vs FC by looking at TaxScheme/ID
@@ -1262,15 +1372,17 @@ impl ParsedInvoice {
                         self.seller_vat_id = Some(text.to_string());
                     }
                 }
-                "cbc:ID" if parent == "cac:TaxScheme" && grandparent == "cac:PartyTaxScheme" => {
-                    // If the TaxScheme ID is FC, the CompanyID we just stored is actually the tax number
-                    if text == "FC" {
-                        // Move the last stored vat_id to tax_number if seller_tax_number is None
-                        if self.seller_tax_number.is_none() {
-                            self.seller_tax_number = self.seller_vat_id.take();
-                        }
-                        self.in_seller_tax_scheme = true;
+                // If the TaxScheme ID is FC, the CompanyID we just stored is actually the tax number
+                "cbc:ID"
+                    if parent == "cac:TaxScheme"
+                        && grandparent == "cac:PartyTaxScheme"
+                        && text == "FC" =>
+                {
+                    // Move the last stored vat_id to tax_number if seller_tax_number is None
+                    if self.seller_tax_number.is_none() {
+                        self.seller_tax_number = self.seller_vat_id.take();
                     }
+                    self.in_seller_tax_scheme = true;
                 }
                 "cbc:StreetName" if grandparent == "cac:Party" || great_gp == "cac:Party" => {
                     self.seller_street = Some(text.to_string());
@@ -1303,6 +1415,7 @@ impl ParsedInvoice {
                 }
                 "cbc:CompanyID" if parent == "cac:PartyLegalEntity" => {
                     self.buyer_reg_id = Some(text.to_string());
+                    self.buyer_reg_scheme = self.current_scheme_id.take();
                 }
                 "cbc:Name" if parent == "cac:PartyName" => {
                     self.buyer_trading_name = Some(text.to_string());
@@ -1426,6 +1539,15 @@ impl ParsedInvoice {
                     let name = line.current_attr_name.take().unwrap_or_default();
                     line.attributes.push((name, text.to_string()));
                 }
+                // BT-132: Referenced purchase order line
+                "cbc:LineID" if parent == "cac:OrderLineReference" => {
+                    line.order_line_id = Some(text.to_string());
+                }
+                // BT-128: Invoiced object identifier
+                "cbc:ID" if parent == "cac:DocumentReference" => {
+                    let scheme = self.current_scheme_id.take();
+                    line.object_references.push((text.to_string(), scheme));
+                }
                 // BG-26: Line invoicing period
                 "cbc:StartDate" if parent == "cac:InvoicePeriod" => {
                     line.invoicing_period_start = Some(text.to_string());
@@ -1551,6 +1673,7 @@ impl ParsedInvoice {
             vat_id: self.seller_vat_id,
             tax_number: self.seller_tax_number,
             registration_id: self.seller_reg_id,
+            registration_scheme: self.seller_reg_scheme,
             trading_name: self.seller_trading_name,
             address: Address {
                 street: self.seller_street,
@@ -1590,6 +1713,7 @@ impl ParsedInvoice {
             vat_id: self.buyer_vat_id,
             tax_number: None,
             registration_id: self.buyer_reg_id,
+            registration_scheme: self.buyer_reg_scheme,
             trading_name: self.buyer_trading_name,
             address: Address {
                 street: self.buyer_street,
@@ -1640,7 +1764,11 @@ impl ParsedInvoice {
             let attributes = pl
                 .attributes
                 .into_iter()
-                .map(|(n, v)| ItemAttribute { name: n, value: v })
+                .map(|(name, value)| ItemAttribute {
+                    name,
+                    value,
+                    measure: None,
+                })
                 .collect();
             let line_period = match (pl.invoicing_period_start, pl.invoicing_period_end) {
                 (Some(s), Some(e)) => {
@@ -1688,6 +1816,15 @@ impl ParsedInvoice {
                 origin_country: pl.origin_country,
                 attributes,
                 invoicing_period: line_period,
+                order_line_id: pl.order_line_id,
+                order_reference: None,
+                additional_references: pl
+                    .object_references
+                    .into_iter()
+                    .map(|(id, scheme)| object_reference(id, scheme))
+                    .collect(),
+                parent_line_id: None,
+                line_status: None,
             });
         }
 
@@ -1779,8 +1916,28 @@ impl ParsedInvoice {
             })
             .collect();
 
-        let attachments = self
+        // BT-17 tender references and BT-18 invoiced objects (type 130)
+        let mut additional_references: Vec<AdditionalReference> = self
+            .originator_references
+            .into_iter()
+            .map(|id| AdditionalReference {
+                id,
+                type_code: "50".into(),
+                reference_type: None,
+                name: None,
+                issue_date: None,
+            })
+            .collect();
+        let (objects, attachments): (Vec<_>, Vec<_>) = self
             .attachments
+            .into_iter()
+            .partition(|a| a.type_code.as_deref() == Some("130"));
+        additional_references.extend(
+            objects
+                .into_iter()
+                .map(|a| object_reference(a.id.unwrap_or_default(), a.scheme_id)),
+        );
+        let attachments = attachments
             .into_iter()
             .map(|a| DocumentAttachment {
                 id: a.id,
@@ -1872,6 +2029,11 @@ impl ParsedInvoice {
                 None
             };
 
+        let (cash_discounts, payment_terms) = match self.payment_terms.as_deref() {
+            Some(text) => skonto::parse_payment_terms(text),
+            None => (Vec::new(), None),
+        };
+
         Ok(Invoice {
             number: self.number.unwrap_or_default(),
             issue_date,
@@ -1880,6 +2042,7 @@ impl ParsedInvoice {
                 .unwrap_or(InvoiceTypeCode::Invoice),
             currency_code: self.currency_code.unwrap_or_else(|| "EUR".to_string()),
             tax_currency_code: self.tax_currency_code,
+            exchange_rate: None,
             notes: self.notes,
             buyer_reference: self.buyer_reference,
             project_reference: self.project_reference,
@@ -1894,7 +2057,8 @@ impl ParsedInvoice {
             allowances: doc_allowances,
             charges: doc_charges,
             totals,
-            payment_terms: self.payment_terms,
+            payment_terms,
+            cash_discounts,
             payment,
             tax_point_date: self
                 .tax_point_date
@@ -1905,6 +2069,18 @@ impl ParsedInvoice {
             tax_representative,
             preceding_invoices,
             attachments,
+            additional_references,
+            despatch_advices: self
+                .despatch_advices
+                .into_iter()
+                .map(|id| DocumentReference {
+                    id,
+                    issue_date: None,
+                })
+                .collect(),
+            delivery_notes: Vec::new(),
+            logistics_charges: Vec::new(),
+            payment_schedule: Vec::new(),
             delivery,
         })
     }
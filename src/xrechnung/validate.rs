@@ -1,3 +1,5 @@
+use rust_decimal::Decimal;
+
 use crate::core::*;
 
 /// Run all validation layers for XRechnung compliance in one call.
@@ -120,12 +122,33 @@ pub fn validate_xrechnung(invoice: &Invoice) -> Vec<ValidationError> {
             "XRechnung requires buyer reference / Leitweg-ID (BT-10)",
             "BR-DE-15",
         ));
+    } else if invoice
+        .buyer
+        .electronic_address
+        .as_ref()
+        .is_some_and(|a| a.scheme == "0204")
+    {
+        // A public buyer addressed by Leitweg-ID expects it in BT-10 too;
+        // BR-DE-15 only requires BT-10 to be present
+        let reference = invoice.buyer_reference.as_deref().unwrap_or_default();
+        if let Err(e) = crate::core::LeitwegId::parse(reference) {
+            errors.push(ValidationError::with_rule(
+                "buyer_reference",
+                format!("buyer reference (BT-10) must be a valid Leitweg-ID: {e}"),
+                crate::core::eas::IDENTIFIER_FORMAT_RULE,
+            ));
+        }
     }
 
     // BR-DE-16: At least one of: seller VAT ID (BT-31), seller tax number (BT-32)
+    // when a line is subject to VAT (any category but O).
     // Exception: when a tax representative (BG-11) is present, the representative's
     // VAT ID satisfies this requirement.
-    if invoice.tax_representative.is_none()
+    if invoice
+        .lines
+        .iter()
+        .any(|l| l.tax_category != TaxCategory::NotSubjectToVat)
+        && invoice.tax_representative.is_none()
         && invoice.seller.vat_id.is_none()
         && invoice.seller.tax_number.is_none()
     {
@@ -162,6 +185,15 @@ pub fn validate_xrechnung(invoice: &Invoice) -> Vec<ValidationError> {
             ));
         }
     }
+    for (i, discount) in invoice.cash_discounts.iter().enumerate() {
+        if discount.percent < Decimal::ZERO || discount.percent > Decimal::ONE_HUNDRED {
+            errors.push(ValidationError::with_rule(
+                format!("cash_discounts[{i}].percent"),
+                "Skonto percentage must be between 0 and 100",
+                "BR-DE-18",
+            ));
+        }
+    }
 
     // BR-DE-22: Unique embedded document filenames
     if invoice.attachments.len() > 1 {
@@ -336,6 +368,18 @@ pub fn validate_xrechnung(invoice: &Invoice) -> Vec<ValidationError> {
         ));
     }
 
+    // BR-CL-25: Electronic address schemes from the EAS code list, with
+    // identifiers in the scheme's format (GLN, Leitweg-ID, ...)
+    errors.extend(crate::core::eas::electronic_address_errors(
+        invoice,
+        "BR-CL-25",
+        |_| crate::core::eas::IDENTIFIER_FORMAT_RULE,
+    ));
+    // BR-CL-11: Legal registration identifier schemes from the ICD list
+    errors.extend(crate::core::eas::registration_id_errors(invoice, |_| {
+        crate::core::eas::IDENTIFIER_FORMAT_RULE
+    }));
+
     errors
 }
 
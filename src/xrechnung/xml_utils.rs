@@ -84,14 +84,30 @@ impl XmlWriter {
         self.end_element(name)
     }
 
-    /// Write a decimal amount with currencyID attribute.
+    /// Write a monetary amount with currencyID attribute, rounded to the
+    /// currency's minor units but at most 2 decimals (EN 16931).
     pub fn amount_element(
         &mut self,
         name: &str,
         amount: Decimal,
         currency: &str,
     ) -> Result<&mut Self, RechnungError> {
-        self.text_element_with_attrs(name, &format_decimal(amount), &[("currencyID", currency)])
+        self.text_element_with_attrs(
+            name,
+            &format_amount(amount, currency),
+            &[("currencyID", currency)],
+        )
+    }
+
+    /// Write a unit price with currencyID attribute. Prices keep their full
+    /// precision (BT-146/BT-148 are not limited to the currency's minor units).
+    pub fn price_element(
+        &mut self,
+        name: &str,
+        price: Decimal,
+        currency: &str,
+    ) -> Result<&mut Self, RechnungError> {
+        self.text_element_with_attrs(name, &format_decimal(price), &[("currencyID", currency)])
     }
 
     /// Write a quantity with unitCode attribute.
@@ -105,6 +121,17 @@ impl XmlWriter {
     }
 }
 
+/// Format a monetary amount with the decimals EN 16931 allows in `currency`:
+/// its minor units, at most 2 (2 for EUR and KWD, 0 for JPY).
+pub fn format_amount(d: Decimal, currency: &str) -> String {
+    let scale = crate::core::invoice_amount_scale(currency);
+    format!(
+        "{:.*}",
+        scale as usize,
+        crate::core::round_invoice_amount(d, currency)
+    )
+}
+
 /// Format a Decimal for XML output — always include at least 2 decimal places,
 /// strip trailing zeros beyond that.
 pub fn format_decimal(d: Decimal) -> String {
@@ -136,4 +163,11 @@ mod tests {
         assert_eq!(format_decimal(dec!(0.005)), "0.005");
         assert_eq!(format_decimal(dec!(19)), "19.00");
     }
+
+    #[test]
+    fn format_amount_minor_units() {
+        assert_eq!(format_amount(dec!(1833.481), "EUR"), "1833.48");
+        assert_eq!(format_amount(dec!(1833.5), "JPY"), "1834");
+        assert_eq!(format_amount(dec!(12.555), "KWD"), "12.56");
+    }
 }
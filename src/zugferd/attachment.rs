@@ -0,0 +1,175 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+
+/// `AFRelationship` values PDF/A-3 allows on an associated file.
+pub(crate) const AF_RELATIONSHIPS: [&str; 5] =
+    ["Source", "Data", "Alternative", "Supplement", "Unspecified"];
+
+/// A file embedded in a PDF besides the invoice XML.
+///
+/// Returned by [`extract_document`](super::extract_document) and accepted by
+/// [`embed_in_pdf_with_attachments`](super::embed_in_pdf_with_attachments).
+///
+/// ```ignore
+/// let timesheet = Attachment::new("stundenzettel-2024-06.csv", csv_bytes)
+///     .with_description("Stundenzettel Juni 2024")
+///     .with_relationship("Supplement");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Filename from the file specification (`UF`, falling back to `F`).
+    pub filename: String,
+    /// MIME type from the embedded file stream's `Subtype`, e.g. `text/xml`.
+    pub mime_type: Option<String>,
+    /// `Desc` of the file specification.
+    pub description: Option<String>,
+    /// `AFRelationship` of the file specification (`Alternative`, `Data`,
+    /// `Supplement`, `Source`, `Unspecified`).
+    pub af_relationship: Option<String>,
+    /// `CreationDate` from the embedded file parameters.
+    pub created: Option<DateTime<FixedOffset>>,
+    /// `ModDate` from the embedded file parameters.
+    pub modified: Option<DateTime<FixedOffset>>,
+    /// The decoded file content.
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// A `Supplement` attachment with the MIME type guessed from the
+    /// filename extension.
+    pub fn new(filename: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        let filename = filename.into();
+        Self {
+            mime_type: Some(mime_type_for(&filename).to_string()),
+            filename,
+            description: None,
+            af_relationship: Some("Supplement".to_string()),
+            created: None,
+            modified: None,
+            data: data.into(),
+        }
+    }
+
+    /// Set the MIME type, e.g. `application/pdf`.
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Set the description shown by PDF viewers.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the `AFRelationship`: `Supplement` (default), `Source`, `Data`,
+    /// `Alternative` or `Unspecified`.
+    pub fn with_relationship(mut self, relationship: impl Into<String>) -> Self {
+        self.af_relationship = Some(relationship.into());
+        self
+    }
+
+    /// Set the creation and modification dates.
+    pub fn with_dates(
+        mut self,
+        created: DateTime<FixedOffset>,
+        modified: DateTime<FixedOffset>,
+    ) -> Self {
+        self.created = Some(created);
+        self.modified = Some(modified);
+        self
+    }
+}
+
+/// MIME type for a filename extension; `application/octet-stream` if unknown.
+pub(crate) fn mime_type_for(filename: &str) -> &'static str {
+    let ext = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "xml" => "text/xml",
+        "csv" => "text/csv",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "tif" | "tiff" => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Format a date as a PDF date string (`D:YYYYMMDDHHmmSS+HH'mm'`).
+pub(crate) fn format_pdf_date(date: &DateTime<FixedOffset>) -> String {
+    let offset = date.offset().local_minus_utc();
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!(
+        "D:{}{sign}{:02}'{:02}'",
+        date.format("%Y%m%d%H%M%S"),
+        offset / 3600,
+        offset % 3600 / 60
+    )
+}
+
+/// Parse a PDF date string; missing trailing fields default to their minimum.
+pub(crate) fn parse_pdf_date(text: &str) -> Option<DateTime<FixedOffset>> {
+    let text = text.trim();
+    let text = text.strip_prefix("D:").unwrap_or(text);
+    let digits: String = text.chars().take_while(char::is_ascii_digit).collect();
+    let field = |range: std::ops::Range<usize>, default: u32| {
+        digits
+            .get(range)
+            .map_or(Some(default), |s| s.parse::<u32>().ok())
+    };
+    let year = digits.get(0..4)?.parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, field(4..6, 1)?, field(6..8, 1)?)?.and_hms_opt(
+        field(8..10, 0)?,
+        field(10..12, 0)?,
+        field(12..14, 0)?,
+    )?;
+
+    let rest = &text[digits.len()..];
+    let offset = match rest.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let tz: String = rest[1..].chars().filter(char::is_ascii_digit).collect();
+            let hours: i32 = tz.get(0..2)?.parse().ok()?;
+            let minutes: i32 = tz.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+            let seconds = hours * 3600 + minutes * 60;
+            FixedOffset::east_opt(if sign == '-' { -seconds } else { seconds })?
+        }
+        _ => FixedOffset::east_opt(0)?,
+    };
+    offset.from_local_datetime(&date).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_date_roundtrip() {
+        let date = DateTime::parse_from_rfc3339("2024-06-15T09:30:05+02:00").unwrap();
+        assert_eq!(format_pdf_date(&date), "D:20240615093005+02'00'");
+        assert_eq!(parse_pdf_date("D:20240615093005+02'00'"), Some(date));
+        assert_eq!(
+            parse_pdf_date("D:20240615093005Z").unwrap().to_rfc3339(),
+            "2024-06-15T09:30:05+00:00"
+        );
+        assert_eq!(
+            parse_pdf_date("D:2024").unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(parse_pdf_date("yesterday"), None);
+    }
+
+    #[test]
+    fn mime_types() {
+        assert_eq!(mime_type_for("Stundenzettel.CSV"), "text/csv");
+        assert_eq!(mime_type_for("lieferschein.pdf"), "application/pdf");
+        assert_eq!(mime_type_for("README"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,637 @@
+use rust_decimal::Decimal;
+
+use super::profile::{ZugferdProfile, to_xml};
+use super::version::{from_xml, guideline_id};
+use crate::core::*;
+use crate::xrechnung;
+
+/// Profiles in ascending order of what they carry.
+const PROFILE_LADDER: [ZugferdProfile; 5] = [
+    ZugferdProfile::Minimum,
+    ZugferdProfile::BasicWl,
+    ZugferdProfile::Basic,
+    ZugferdProfile::EN16931,
+    ZugferdProfile::Extended,
+];
+
+/// Invoice data a profile cannot carry; [`to_xml`] leaves it out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedField {
+    /// Dot-separated path in the invoice (e.g. "lines" or "payment.card_payment").
+    pub field: String,
+    /// EN 16931 business term or group (e.g. "BG-25"); for Extended-only
+    /// data the CII element (e.g. "ram:ParentLineID").
+    pub business_term: &'static str,
+    /// The lowest profile that carries the field.
+    pub required_profile: ZugferdProfile,
+}
+
+impl std::fmt::Display for DroppedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}) needs {} or higher",
+            self.field,
+            self.business_term,
+            self.required_profile.conformance_level()
+        )
+    }
+}
+
+/// Result of [`convert`].
+#[derive(Debug, Clone)]
+pub struct Conversion {
+    /// CII XML in the target profile.
+    pub xml: String,
+    /// Profile of the input, if its guideline ID names one.
+    pub source_profile: Option<ZugferdProfile>,
+    /// Data of the input the target profile cannot carry.
+    pub dropped: Vec<DroppedField>,
+    /// Rules of the target profile the converted invoice violates, e.g.
+    /// missing line items after converting BasicWL up to Basic.
+    pub errors: Vec<ValidationError>,
+}
+
+/// Validate an invoice against the Factur-X rules of a profile.
+///
+/// The rules apply to the data the profile carries (see [`dropped_fields`]);
+/// data the profile cannot carry is not an error, [`to_xml`] leaves it out.
+///
+/// - **Minimum**: invoice number, currency, seller and buyer names, seller
+///   country and the document totals (BR-CO-15, BR-CO-16).
+/// - **BasicWL**: additionally the buyer country, at least one VAT
+///   breakdown, due date or payment terms for a positive amount due, an
+///   IBAN for credit transfers and the header totals arithmetic.
+/// - **Basic**, **EN16931**, **Extended**: the full EN 16931 rule set,
+///   i.e. [`validate_14_ustg`] and [`validate_en16931`]. Extended also
+///   checks line grouping: a parent line must exist, and a group line's
+///   amount must equal the sum of its detail lines.
+/// - **XRechnung**: [`validate_xrechnung_full`](xrechnung::validate_xrechnung_full).
+pub fn validate_zugferd(invoice: &Invoice, profile: ZugferdProfile) -> Vec<ValidationError> {
+    let (invoice, _) = reduce(invoice, profile);
+    match profile {
+        ZugferdProfile::Minimum => header_rules(&invoice, false),
+        ZugferdProfile::BasicWl => header_rules(&invoice, true),
+        ZugferdProfile::XRechnung => xrechnung::validate_xrechnung_full(&invoice),
+        ZugferdProfile::Extended => {
+            let mut errors = validate_14_ustg(&invoice);
+            errors.extend(validate_en16931(&invoice));
+            errors.extend(line_group_rules(&invoice));
+            errors
+        }
+        _ => {
+            let mut errors = validate_14_ustg(&invoice);
+            errors.extend(validate_en16931(&invoice));
+            errors
+        }
+    }
+}
+
+/// List the invoice data that would be lost in the given profile, e.g.
+/// line items under BasicWL or item descriptions under Basic.
+pub fn dropped_fields(invoice: &Invoice, profile: ZugferdProfile) -> Vec<DroppedField> {
+    reduce(invoice, profile).1
+}
+
+/// The lowest profile that carries all data of the invoice.
+///
+/// Minimum < BasicWL < Basic < EN16931 < Extended; XRechnung is never
+/// returned. Whether the invoice satisfies the profile's rules is up to
+/// [`validate_zugferd`].
+pub fn best_profile(invoice: &Invoice) -> ZugferdProfile {
+    PROFILE_LADDER
+        .into_iter()
+        .find(|&profile| dropped_fields(invoice, profile).is_empty())
+        .unwrap_or(ZugferdProfile::Extended)
+}
+
+/// Convert ZUGFeRD/Factur-X XML to another profile.
+///
+/// Converting down reports the data that is dropped; converting up reports
+/// what the target profile requires but the input lacks, e.g. line items
+/// when going from BasicWL to Basic.
+pub fn convert(xml: &str, target: ZugferdProfile) -> Result<Conversion, RechnungError> {
+    let (invoice, _) = from_xml(xml)?;
+    Ok(Conversion {
+        xml: to_xml(&invoice, target)?,
+        source_profile: guideline_id(xml).and_then(|id| ZugferdProfile::from_urn(&id)),
+        dropped: dropped_fields(&invoice, target),
+        errors: validate_zugferd(&invoice, target),
+    })
+}
+
+fn level(profile: ZugferdProfile) -> u8 {
+    match profile {
+        ZugferdProfile::Minimum => 0,
+        ZugferdProfile::BasicWl => 1,
+        ZugferdProfile::Basic => 2,
+        ZugferdProfile::EN16931 | ZugferdProfile::XRechnung => 3,
+        ZugferdProfile::Extended => 4,
+    }
+}
+
+/// A copy of the invoice without the data the profile cannot carry, and
+/// what was removed. Totals are left as they are; the Minimum writer only
+/// writes the document totals.
+pub(super) fn reduce(invoice: &Invoice, profile: ZugferdProfile) -> (Invoice, Vec<DroppedField>) {
+    use ZugferdProfile::{Basic, BasicWl, EN16931, Extended};
+
+    let mut inv = invoice.clone();
+    let mut r = Reduction {
+        target: profile,
+        dropped: Vec::new(),
+    };
+
+    r.vec(&mut inv.notes, "notes", "BT-22", BasicWl);
+    r.option(
+        &mut inv.tax_currency_code,
+        "tax_currency_code",
+        "BT-6",
+        BasicWl,
+    );
+    r.option(
+        &mut inv.project_reference,
+        "project_reference",
+        "BT-11",
+        EN16931,
+    );
+    r.option(
+        &mut inv.contract_reference,
+        "contract_reference",
+        "BT-12",
+        BasicWl,
+    );
+    r.option(
+        &mut inv.sales_order_reference,
+        "sales_order_reference",
+        "BT-14",
+        EN16931,
+    );
+    r.option(
+        &mut inv.buyer_accounting_reference,
+        "buyer_accounting_reference",
+        "BT-19",
+        BasicWl,
+    );
+    r.vec(
+        &mut inv.preceding_invoices,
+        "preceding_invoices",
+        "BG-3",
+        BasicWl,
+    );
+    r.vec(&mut inv.attachments, "attachments", "BG-24", EN16931);
+    r.references(
+        &mut inv.additional_references,
+        "additional_references",
+        false,
+    );
+
+    r.party(&mut inv.seller, false);
+    r.party(&mut inv.buyer, true);
+    r.option(&mut inv.payee, "payee", "BG-10", BasicWl);
+    r.option(
+        &mut inv.tax_representative,
+        "tax_representative",
+        "BG-11",
+        BasicWl,
+    );
+
+    r.option(&mut inv.tax_point_date, "tax_point_date", "BT-7", BasicWl);
+    r.option(&mut inv.delivery, "delivery", "BG-13", BasicWl);
+    r.vec(
+        &mut inv.despatch_advices,
+        "despatch_advices",
+        "BT-16",
+        EN16931,
+    );
+    if inv.despatch_advices.len() > 1 && !r.keeps(Extended) {
+        inv.despatch_advices.truncate(1);
+        r.drop(
+            "despatch_advices",
+            "ram:DespatchAdviceReferencedDocument",
+            Extended,
+        );
+    }
+    r.vec(
+        &mut inv.delivery_notes,
+        "delivery_notes",
+        "ram:DeliveryNoteReferencedDocument",
+        Extended,
+    );
+    r.option(
+        &mut inv.invoicing_period,
+        "invoicing_period",
+        "BG-14",
+        BasicWl,
+    );
+
+    r.option(&mut inv.due_date, "due_date", "BT-9", BasicWl);
+    r.option(&mut inv.payment_terms, "payment_terms", "BT-20", BasicWl);
+    r.vec(&mut inv.cash_discounts, "cash_discounts", "BT-20", BasicWl);
+    r.vec(
+        &mut inv.payment_schedule,
+        "payment_schedule",
+        "ram:SpecifiedTradePaymentTerms",
+        Extended,
+    );
+    r.option(&mut inv.payment, "payment", "BG-16", BasicWl);
+    if let Some(payment) = &mut inv.payment {
+        r.option(
+            &mut payment.means_text,
+            "payment.means_text",
+            "BT-82",
+            EN16931,
+        );
+        r.option(
+            &mut payment.card_payment,
+            "payment.card_payment",
+            "BG-18",
+            EN16931,
+        );
+        if let Some(ct) = &mut payment.credit_transfer {
+            r.option(
+                &mut ct.account_name,
+                "payment.credit_transfer.account_name",
+                "BT-85",
+                EN16931,
+            );
+            r.option(&mut ct.bic, "payment.credit_transfer.bic", "BT-86", EN16931);
+        }
+    }
+
+    // Below Extended, logistics service charges are ordinary charges
+    if !r.keeps(Extended) {
+        let logistics = std::mem::take(&mut inv.logistics_charges);
+        inv.charges
+            .extend(logistics.iter().map(LogisticsServiceCharge::to_charge));
+    }
+    r.vec(&mut inv.allowances, "allowances", "BG-20", BasicWl);
+    r.vec(&mut inv.charges, "charges", "BG-21", BasicWl);
+    if let Some(totals) = &inv.totals {
+        if !r.keeps(BasicWl) {
+            if !totals.vat_breakdown.is_empty() {
+                r.drop("totals.vat_breakdown", "BG-23", BasicWl);
+            }
+            if totals.prepaid != Decimal::ZERO {
+                r.drop("totals.prepaid", "BT-113", BasicWl);
+            }
+        }
+    }
+
+    r.vec(&mut inv.lines, "lines", "BG-25", Basic);
+    let lines = std::mem::take(&mut inv.lines);
+    for (i, mut line) in lines.into_iter().enumerate() {
+        let field = |name: &str| format!("lines[{i}].{name}");
+        // Group and information lines are not part of the totals
+        if !line.counts_in_totals() && !r.keeps(Extended) {
+            r.drop(&format!("lines[{i}]"), "ram:LineStatusReasonCode", Extended);
+            continue;
+        }
+        r.option(
+            &mut line.parent_line_id,
+            &field("parent_line_id"),
+            "ram:ParentLineID",
+            Extended,
+        );
+        r.option(
+            &mut line.line_status,
+            &field("line_status"),
+            "ram:LineStatusReasonCode",
+            Extended,
+        );
+        r.option(
+            &mut line.description,
+            &field("description"),
+            "BT-154",
+            EN16931,
+        );
+        r.option(
+            &mut line.seller_item_id,
+            &field("seller_item_id"),
+            "BT-155",
+            EN16931,
+        );
+        r.option(
+            &mut line.buyer_item_id,
+            &field("buyer_item_id"),
+            "BT-156",
+            EN16931,
+        );
+        r.option(
+            &mut line.origin_country,
+            &field("origin_country"),
+            "BT-159",
+            EN16931,
+        );
+        r.vec(&mut line.attributes, &field("attributes"), "BG-32", EN16931);
+        for (j, attr) in line.attributes.iter_mut().enumerate() {
+            r.option(
+                &mut attr.measure,
+                &field(&format!("attributes[{j}].measure")),
+                "ram:ValueMeasure",
+                Extended,
+            );
+        }
+        r.option(
+            &mut line.order_line_id,
+            &field("order_line_id"),
+            "BT-132",
+            EN16931,
+        );
+        r.option(
+            &mut line.order_reference,
+            &field("order_reference"),
+            "ram:BuyerOrderReferencedDocument",
+            Extended,
+        );
+        r.references(
+            &mut line.additional_references,
+            &field("additional_references"),
+            true,
+        );
+        inv.lines.push(line);
+    }
+
+    (inv, r.dropped)
+}
+
+struct Reduction {
+    target: ZugferdProfile,
+    dropped: Vec<DroppedField>,
+}
+
+impl Reduction {
+    fn keeps(&self, from: ZugferdProfile) -> bool {
+        level(self.target) >= level(from)
+    }
+
+    fn drop(&mut self, field: &str, business_term: &'static str, from: ZugferdProfile) {
+        self.dropped.push(DroppedField {
+            field: field.to_string(),
+            business_term,
+            required_profile: from,
+        });
+    }
+
+    fn option<T>(
+        &mut self,
+        slot: &mut Option<T>,
+        field: &str,
+        business_term: &'static str,
+        from: ZugferdProfile,
+    ) {
+        if slot.is_some() && !self.keeps(from) {
+            *slot = None;
+            self.drop(field, business_term, from);
+        }
+    }
+
+    fn vec<T>(
+        &mut self,
+        slot: &mut Vec<T>,
+        field: &str,
+        business_term: &'static str,
+        from: ZugferdProfile,
+    ) {
+        if !slot.is_empty() && !self.keeps(from) {
+            slot.clear();
+            self.drop(field, business_term, from);
+        }
+    }
+
+    /// Tender and invoiced object references need EN16931, other document
+    /// types Extended.
+    fn references(&mut self, refs: &mut Vec<AdditionalReference>, field: &str, line_level: bool) {
+        use ZugferdProfile::{EN16931, Extended};
+
+        let all = std::mem::take(refs);
+        for (i, reference) in all.into_iter().enumerate() {
+            let (term, from) = match reference.type_code.as_str() {
+                _ if !reference.is_en16931(line_level) => {
+                    ("ram:AdditionalReferencedDocument", Extended)
+                }
+                "50" => ("BT-17", EN16931),
+                _ if line_level => ("BT-128", EN16931),
+                _ => ("BT-18", EN16931),
+            };
+            if self.keeps(from) {
+                refs.push(reference);
+            } else {
+                self.drop(&format!("{field}[{i}]"), term, from);
+            }
+        }
+    }
+
+    /// Minimum carries the names, legal registrations, the seller's tax
+    /// registrations and the seller's country; BasicWL the rest except
+    /// contacts.
+    fn party(&mut self, party: &mut Party, buyer: bool) {
+        use ZugferdProfile::{BasicWl, EN16931};
+
+        let prefix = if buyer { "buyer" } else { "seller" };
+        let field = |name: &str| format!("{prefix}.{name}");
+        let term = |seller: &'static str, buyer_term: &'static str| {
+            if buyer { buyer_term } else { seller }
+        };
+
+        self.option(
+            &mut party.trading_name,
+            &field("trading_name"),
+            term("BT-28", "BT-45"),
+            BasicWl,
+        );
+        self.option(
+            &mut party.contact,
+            &field("contact"),
+            term("BG-6", "BG-9"),
+            EN16931,
+        );
+        self.option(
+            &mut party.electronic_address,
+            &field("electronic_address"),
+            term("BT-34", "BT-49"),
+            BasicWl,
+        );
+        if buyer {
+            self.option(&mut party.vat_id, &field("vat_id"), "BT-48", BasicWl);
+            self.option(
+                &mut party.tax_number,
+                &field("tax_number"),
+                "BT-48",
+                BasicWl,
+            );
+        }
+
+        let address = &mut party.address;
+        let has_content = address.street.is_some()
+            || address.additional.is_some()
+            || address.subdivision.is_some()
+            || !address.city.is_empty()
+            || !address.postal_code.is_empty()
+            || (buyer && !address.country_code.is_empty());
+        if has_content && !self.keeps(BasicWl) {
+            address.street = None;
+            address.additional = None;
+            address.subdivision = None;
+            address.city.clear();
+            address.postal_code.clear();
+            if buyer {
+                address.country_code.clear();
+            }
+            self.drop(&field("address"), term("BG-5", "BG-8"), BasicWl);
+        }
+    }
+}
+
+/// Header-level Factur-X rules for Minimum and, with `basic_wl`, BasicWL.
+fn header_rules(invoice: &Invoice, basic_wl: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    if invoice.number.trim().is_empty() {
+        errors.push(ValidationError::with_rule(
+            "number",
+            "invoice number must not be empty",
+            "BR-02",
+        ));
+    }
+    if !is_known_currency_code(&invoice.currency_code) {
+        errors.push(ValidationError::with_rule(
+            "currency_code",
+            format!(
+                "currency code '{}' is not a known ISO 4217 code",
+                invoice.currency_code
+            ),
+            "BR-05",
+        ));
+    }
+    if invoice.seller.name.trim().is_empty() {
+        errors.push(ValidationError::with_rule(
+            "seller.name",
+            "name must not be empty",
+            "BR-06",
+        ));
+    }
+    if invoice.buyer.name.trim().is_empty() {
+        errors.push(ValidationError::with_rule(
+            "buyer.name",
+            "name must not be empty",
+            "BR-07",
+        ));
+    }
+    if invoice.seller.address.country_code.trim().is_empty() {
+        errors.push(ValidationError::with_rule(
+            "seller.address.country_code",
+            "seller postal address must have a country code",
+            "BR-09",
+        ));
+    }
+    if basic_wl && invoice.buyer.address.country_code.trim().is_empty() {
+        errors.push(ValidationError::with_rule(
+            "buyer.address.country_code",
+            "buyer postal address must have a country code",
+            "BR-11",
+        ));
+    }
+
+    let Some(totals) = &invoice.totals else {
+        errors.push(ValidationError::new(
+            "totals",
+            "totals must be calculated before validation (call calculate_totals first)",
+        ));
+        return errors;
+    };
+
+    if basic_wl {
+        if totals.vat_breakdown.is_empty() {
+            errors.push(ValidationError::with_rule(
+                "totals.vat_breakdown",
+                "invoice must have at least one VAT breakdown (BG-23)",
+                "BR-CO-18",
+            ));
+        }
+        if totals.amount_due > Decimal::ZERO
+            && invoice.due_date.is_none()
+            && skonto::payment_terms_text(invoice).is_none()
+        {
+            errors.push(ValidationError::with_rule(
+                "due_date",
+                "a positive amount due requires a due date (BT-9) or payment terms (BT-20)",
+                "BR-CO-25",
+            ));
+        }
+        if let Some(payment) = &invoice.payment {
+            let credit_transfer = matches!(
+                payment.means_code,
+                PaymentMeansCode::CreditTransfer | PaymentMeansCode::SepaCreditTransfer
+            );
+            if credit_transfer && payment.credit_transfer.is_none() {
+                errors.push(ValidationError::with_rule(
+                    "payment.credit_transfer",
+                    "credit transfer requires the payment account identifier (BT-84)",
+                    "BR-61",
+                ));
+            }
+        }
+    }
+
+    // Without line items BR-CO-10 cannot hold; Minimum has no breakdown,
+    // allowances or charges either
+    let rules: &[&str] = if basic_wl {
+        &["BR-CO-11", "BR-CO-14", "BR-CO-15", "BR-CO-16"]
+    } else {
+        &["BR-CO-15", "BR-CO-16"]
+    };
+    errors.extend(
+        validate_arithmetic(invoice)
+            .into_iter()
+            .filter(|e| e.rule.as_deref().is_some_and(|rule| rules.contains(&rule))),
+    );
+
+    errors
+}
+
+/// Extended line grouping: parents must exist and be group lines, and a
+/// group line's amount is the sum of its detail lines.
+fn line_group_rules(invoice: &Invoice) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for (i, line) in invoice.lines.iter().enumerate() {
+        if let Some(parent_id) = &line.parent_line_id {
+            match invoice.lines.iter().find(|l| &l.id == parent_id) {
+                None => errors.push(ValidationError::new(
+                    format!("lines[{i}].parent_line_id"),
+                    format!("parent line '{parent_id}' does not exist"),
+                )),
+                Some(parent) if parent.counts_in_totals() => errors.push(ValidationError::new(
+                    format!("lines[{i}].parent_line_id"),
+                    format!(
+                        "parent line '{parent_id}' must have status GROUP or INFORMATION, \
+                             otherwise it is counted twice"
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+        if line.line_status != Some(LineStatus::Group) {
+            continue;
+        }
+        let details: Vec<Decimal> = invoice
+            .lines
+            .iter()
+            .filter(|l| {
+                l.parent_line_id.as_deref() == Some(line.id.as_str())
+                    && l.line_status == Some(LineStatus::Detail)
+            })
+            .filter_map(|l| l.line_amount)
+            .collect();
+        let sum: Decimal = details.iter().sum();
+        if let (false, Some(amount)) = (details.is_empty(), line.line_amount) {
+            if amount != sum {
+                errors.push(ValidationError::new(
+                    format!("lines[{i}].line_amount"),
+                    format!("group line amount {amount} does not match the sum of its detail lines {sum}"),
+                ));
+            }
+        }
+    }
+    errors
+}
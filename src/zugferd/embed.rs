@@ -1,8 +1,12 @@
-use lopdf::{Document, Object, Stream, dictionary};
+use chrono::{DateTime, FixedOffset, Utc};
+use lopdf::{Document, Object, ObjectId, Stream, StringFormat, dictionary};
+use md5::{Digest, Md5};
 
 use super::FACTURX_FILENAME;
+use super::attachment::{AF_RELATIONSHIPS, Attachment, format_pdf_date, mime_type_for};
+use super::pdfa;
 use super::profile::ZugferdProfile;
-use super::xmp;
+use super::xmp::{self, DocInfo};
 use crate::core::RechnungError;
 
 /// Embed a Factur-X/ZUGFeRD XML into a PDF, producing a PDF/A-3 compliant document.
@@ -12,30 +16,90 @@ use crate::core::RechnungError;
 ///
 /// Adds the required PDF/A-3 structures:
 /// - Embedded file stream with `factur-x.xml`
-/// - XMP metadata with Factur-X extension schema
-/// - OutputIntent with sRGB ICC profile
+/// - XMP metadata with Factur-X extension schema, merged into the existing
+///   XMP and kept in sync with the Info dictionary (title, author, producer,
+///   … are preserved)
+/// - OutputIntent with sRGB ICC profile, unless the input has one
 /// - MarkInfo tagged-PDF flag
+///
+/// PDF/A-1 and PDF/A-2 inputs are declared PDF/A-3 with their conformance
+/// level, other inputs PDF/A-3B; see [`inspect_pdf`](super::inspect_pdf)
+/// for what keeps an input from being conformant. Encrypted PDFs are
+/// rejected.
 pub fn embed_in_pdf(
     pdf_bytes: &[u8],
     xml: &str,
     profile: ZugferdProfile,
 ) -> Result<Vec<u8>, RechnungError> {
+    embed_in_pdf_with_attachments(pdf_bytes, xml, profile, &[])
+}
+
+/// Embed a Factur-X/ZUGFeRD XML together with supplementary files.
+///
+/// Like [`embed_in_pdf`], and additionally embeds each [`Attachment`]
+/// (time sheets, delivery notes, an XRechnung XML, …) with its MIME subtype,
+/// `AFRelationship`, creation/modification dates (the modification date
+/// defaults to now), size and MD5 checksum.
+/// All files are registered in the `EmbeddedFiles` name tree and the catalog
+/// `AF` array, as PDF/A-3 requires for associated files.
+///
+/// Fails if a filename is used twice (including `factur-x.xml`) or an
+/// `AFRelationship` is not one of `Source`, `Data`, `Alternative`,
+/// `Supplement` or `Unspecified`.
+pub fn embed_in_pdf_with_attachments(
+    pdf_bytes: &[u8],
+    xml: &str,
+    profile: ZugferdProfile,
+    attachments: &[Attachment],
+) -> Result<Vec<u8>, RechnungError> {
+    embed_in_pdf_with_options(
+        pdf_bytes,
+        xml,
+        profile,
+        attachments,
+        &EmbedOptions::default(),
+    )
+}
+
+/// Embed a Factur-X/ZUGFeRD XML and attachments with control over the
+/// dates, producer and trailer ID.
+///
+/// Like [`embed_in_pdf_with_attachments`]. With a fixed
+/// [`now`](EmbedOptions::now) and a [`DocumentId`] that does not depend on
+/// the clock, identical inputs give byte-identical PDFs:
+///
+/// ```ignore
+/// let options = EmbedOptions::new()
+///     .with_now(invoice_date)
+///     .with_document_id(DocumentId::XmlHash)
+///     .with_producer("Acme Billing 4.2");
+/// let pdf = embed_in_pdf_with_options(&pdf, &xml, profile, &[], &options)?;
+/// ```
+pub fn embed_in_pdf_with_options(
+    pdf_bytes: &[u8],
+    xml: &str,
+    profile: ZugferdProfile,
+    attachments: &[Attachment],
+    options: &EmbedOptions,
+) -> Result<Vec<u8>, RechnungError> {
+    check_attachments(attachments)?;
+
     let mut doc = Document::load_mem(pdf_bytes)
         .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+    if doc.is_encrypted() {
+        return Err(RechnungError::Builder(
+            "cannot embed into an encrypted PDF: PDF/A-3 forbids encryption".into(),
+        ));
+    }
 
-    embed_xml_into_document(&mut doc, xml.as_bytes(), profile)?;
+    let now = options.now.unwrap_or_else(|| Utc::now().fixed_offset());
+    embed_xml_into_document(&mut doc, xml.as_bytes(), profile, attachments, options, now)?;
 
     // PDF/A-3 requires a document ID in the trailer
     if !doc.trailer.has(b"ID") {
-        let id_bytes = Object::string_literal(format!(
-            "faktura-{}",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_nanos()
-        ));
-        doc.trailer
-            .set("ID", Object::Array(vec![id_bytes.clone(), id_bytes]));
+        let id_bytes = options.document_id.bytes(xml.as_bytes(), now);
+        let id = Object::String(id_bytes, StringFormat::Hexadecimal);
+        doc.trailer.set("ID", Object::Array(vec![id.clone(), id]));
     }
 
     // PDF/A-3 requires version 1.7 and a binary header comment (ISO 19005-3, 6.1.2).
@@ -58,46 +122,165 @@ pub fn embed_in_pdf(
     Ok(output)
 }
 
+/// Dates, producer and document ID for
+/// [`embed_in_pdf_with_options`].
+///
+/// The defaults reproduce [`embed_in_pdf`]: the system clock, a
+/// clock-derived ID and the input's producer (or `faktura`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// The current time, used for every date not set otherwise; defaults to
+    /// the system clock.
+    pub now: Option<DateTime<FixedOffset>>,
+    /// How to derive the trailer `/ID` if the input has none.
+    pub document_id: DocumentId,
+    /// Producer written to the Info dictionary and XMP; defaults to the
+    /// input's producer, then `faktura`.
+    pub producer: Option<String>,
+    /// Document creation date; defaults to the input's creation date, then
+    /// now.
+    pub created: Option<DateTime<FixedOffset>>,
+    /// Document modification date, also the default modification date of
+    /// the embedded files; defaults to now.
+    pub modified: Option<DateTime<FixedOffset>>,
+}
+
+impl EmbedOptions {
+    /// Options with every value defaulted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fix the current time instead of reading the system clock.
+    pub fn with_now(mut self, now: DateTime<FixedOffset>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Set how the trailer `/ID` is derived.
+    pub fn with_document_id(mut self, document_id: DocumentId) -> Self {
+        self.document_id = document_id;
+        self
+    }
+
+    /// Set the producer.
+    pub fn with_producer(mut self, producer: impl Into<String>) -> Self {
+        self.producer = Some(producer.into());
+        self
+    }
+
+    /// Set the creation and modification dates.
+    pub fn with_dates(
+        mut self,
+        created: DateTime<FixedOffset>,
+        modified: DateTime<FixedOffset>,
+    ) -> Self {
+        self.created = Some(created);
+        self.modified = Some(modified);
+        self
+    }
+}
+
+/// Source of the trailer `/ID` PDF/A-3 requires (ISO 19005-3, 6.1.3).
+///
+/// Only used when the input PDF has no `/ID`: an existing ID identifies
+/// the document across revisions and is kept. Both elements of a new ID
+/// are the same.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DocumentId {
+    /// `faktura-` followed by the current time in nanoseconds.
+    #[default]
+    Timestamp,
+    /// MD5 of the invoice XML, so the same invoice always gets the same ID.
+    XmlHash,
+    /// The given bytes.
+    Fixed(Vec<u8>),
+}
+
+impl DocumentId {
+    fn bytes(&self, xml: &[u8], now: DateTime<FixedOffset>) -> Vec<u8> {
+        match self {
+            Self::Timestamp => {
+                format!("faktura-{}", now.timestamp_nanos_opt().unwrap_or_default()).into_bytes()
+            }
+            Self::XmlHash => Md5::digest(xml).to_vec(),
+            Self::Fixed(bytes) => bytes.clone(),
+        }
+    }
+}
+
+fn check_attachments(attachments: &[Attachment]) -> Result<(), RechnungError> {
+    for (i, att) in attachments.iter().enumerate() {
+        if att.filename.is_empty() {
+            return Err(RechnungError::Builder(
+                "attachment filename must not be empty".into(),
+            ));
+        }
+        if att.filename.eq_ignore_ascii_case(FACTURX_FILENAME)
+            || attachments[..i]
+                .iter()
+                .any(|other| other.filename.eq_ignore_ascii_case(&att.filename))
+        {
+            return Err(RechnungError::Builder(format!(
+                "duplicate embedded filename: {}",
+                att.filename
+            )));
+        }
+        if let Some(rel) = &att.af_relationship {
+            if !AF_RELATIONSHIPS.contains(&rel.as_str()) {
+                return Err(RechnungError::Builder(format!(
+                    "invalid AFRelationship '{rel}' for {}: expected one of {}",
+                    att.filename,
+                    AF_RELATIONSHIPS.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn embed_xml_into_document(
     doc: &mut Document,
     xml_bytes: &[u8],
     profile: ZugferdProfile,
+    attachments: &[Attachment],
+    options: &EmbedOptions,
+    now: DateTime<FixedOffset>,
 ) -> Result<(), RechnungError> {
-    // 1. Create the EmbeddedFile stream
-    let ef_stream = Stream::new(
-        dictionary! {
-            "Type" => "EmbeddedFile",
-            "Subtype" => Object::Name(b"text/xml".to_vec()),
-            "Params" => dictionary! {
-                "Size" => Object::Integer(xml_bytes.len() as i64),
-            },
+    // 1–2. Create the EmbeddedFile streams and FileSpec dictionaries,
+    // the invoice XML first
+    let modified = options.modified.unwrap_or(now);
+    let xml_spec_id = add_file_spec(
+        doc,
+        &Attachment {
+            filename: FACTURX_FILENAME.to_string(),
+            mime_type: Some("text/xml".to_string()),
+            description: Some("Factur-X XML invoice".to_string()),
+            af_relationship: Some(profile.af_relationship().to_string()),
+            created: None,
+            modified: Some(modified),
+            data: xml_bytes.to_vec(),
         },
-        xml_bytes.to_vec(),
     );
-    let ef_stream_id = doc.add_object(ef_stream);
+    let mut files = vec![(pdf_text_string(FACTURX_FILENAME), xml_spec_id)];
+    for att in attachments {
+        // PDF/A-3 requires a modification date on every embedded file (6.8)
+        let att = Attachment {
+            modified: att.modified.or(Some(modified)),
+            ..att.clone()
+        };
+        files.push((pdf_text_string(&att.filename), add_file_spec(doc, &att)));
+    }
+    let af: Vec<Object> = files.iter().map(|(_, id)| Object::Reference(*id)).collect();
 
-    // 2. Create the FileSpec dictionary
-    let af_rel = profile.af_relationship();
-    let filespec = dictionary! {
-        "Type" => "Filespec",
-        "F" => Object::string_literal(FACTURX_FILENAME),
-        "UF" => Object::string_literal(FACTURX_FILENAME),
-        "Desc" => Object::string_literal("Factur-X XML invoice"),
-        "AFRelationship" => Object::Name(af_rel.as_bytes().to_vec()),
-        "EF" => dictionary! {
-            "F" => Object::Reference(ef_stream_id),
-            "UF" => Object::Reference(ef_stream_id),
-        },
-    };
-    let filespec_id = doc.add_object(filespec);
-
-    // 3. Create the EmbeddedFiles name tree
-    let ef_name_tree = dictionary! {
-        "Names" => Object::Array(vec![
-            Object::string_literal(FACTURX_FILENAME),
-            Object::Reference(filespec_id),
-        ]),
-    };
+    // 3. Create the EmbeddedFiles name tree (keys in byte order)
+    files.sort_by(|a, b| a.0.as_str().ok().cmp(&b.0.as_str().ok()));
+    let names = files
+        .into_iter()
+        .flat_map(|(key, id)| [key, Object::Reference(id)])
+        .collect();
+    let ef_name_tree = dictionary! { "Names" => Object::Array(names) };
     let ef_name_tree_id = doc.add_object(ef_name_tree);
 
     // 4. Create or update the Names dictionary
@@ -106,8 +289,27 @@ fn embed_xml_into_document(
     };
     let names_id = doc.add_object(names_dict);
 
-    // 5. Create XMP metadata stream
-    let xmp_str = xmp::build_xmp(profile);
+    // 5. Merge the XMP metadata and keep the Info dictionary in sync
+    let existing_xmp = pdfa::read_xmp_string(doc);
+    let conformance = pdfa::inspect_document(doc).output_conformance().to_string();
+    let info = existing_xmp
+        .as_deref()
+        .map(xmp::parse_doc_info)
+        .unwrap_or_default()
+        .or(pdfa::read_info(doc));
+    let info = DocInfo {
+        producer: options
+            .producer
+            .clone()
+            .or(info.producer)
+            .or_else(|| Some("faktura".to_string())),
+        created: options.created.or(info.created).or(Some(now)),
+        modified: Some(modified),
+        ..info
+    };
+    write_info(doc, &info);
+
+    let xmp_str = xmp::build_xmp(profile, &conformance, &info, existing_xmp.as_deref());
     let xmp_bytes = xmp_str.into_bytes();
     let metadata_stream = Stream::new(
         dictionary! {
@@ -119,38 +321,41 @@ fn embed_xml_into_document(
     .with_compression(false); // XMP must not be compressed per PDF/A
     let metadata_id = doc.add_object(metadata_stream);
 
-    // 6. Create sRGB ICC profile stream and OutputIntent (required for PDF/A-3)
-    let icc_bytes = build_srgb_icc_profile();
-    let icc_stream = Stream::new(
-        dictionary! {
-            "N" => Object::Integer(3),
-        },
-        icc_bytes,
-    );
-    let icc_stream_id = doc.add_object(icc_stream);
-
-    let output_intent = dictionary! {
-        "Type" => "OutputIntent",
-        "S" => Object::Name(b"GTS_PDFA1".to_vec()),
-        "OutputConditionIdentifier" => Object::string_literal("sRGB IEC61966-2.1"),
-        "RegistryName" => Object::string_literal("http://www.color.org"),
-        "Info" => Object::string_literal("sRGB IEC61966-2.1"),
-        "DestOutputProfile" => Object::Reference(icc_stream_id),
+    // 6. Create sRGB ICC profile stream and OutputIntent (required for PDF/A-3),
+    // keeping a PDF/A output intent the input already has
+    let output_intents = match existing_pdfa_output_intents(doc) {
+        Some(intents) => intents,
+        None => {
+            let icc_bytes = build_srgb_icc_profile();
+            let icc_stream = Stream::new(
+                dictionary! {
+                    "N" => Object::Integer(3),
+                },
+                icc_bytes,
+            );
+            let icc_stream_id = doc.add_object(icc_stream);
+
+            let output_intent = dictionary! {
+                "Type" => "OutputIntent",
+                "S" => Object::Name(b"GTS_PDFA1".to_vec()),
+                "OutputConditionIdentifier" => Object::string_literal("sRGB IEC61966-2.1"),
+                "RegistryName" => Object::string_literal("http://www.color.org"),
+                "Info" => Object::string_literal("sRGB IEC61966-2.1"),
+                "DestOutputProfile" => Object::Reference(icc_stream_id),
+            };
+            Object::Array(vec![Object::Reference(doc.add_object(output_intent))])
+        }
     };
-    let output_intent_id = doc.add_object(output_intent);
 
     // 7. Update the Catalog
     let catalog = doc
         .catalog_mut()
         .map_err(|e| RechnungError::Builder(format!("failed to get catalog: {e}")))?;
 
-    catalog.set("AF", Object::Array(vec![Object::Reference(filespec_id)]));
+    catalog.set("AF", Object::Array(af));
     catalog.set("Names", Object::Reference(names_id));
     catalog.set("Metadata", Object::Reference(metadata_id));
-    catalog.set(
-        "OutputIntents",
-        Object::Array(vec![Object::Reference(output_intent_id)]),
-    );
+    catalog.set("OutputIntents", output_intents);
     catalog.set(
         "MarkInfo",
         dictionary! { "Marked" => Object::Boolean(true) },
@@ -159,6 +364,144 @@ fn embed_xml_into_document(
     Ok(())
 }
 
+/// The catalog's `OutputIntents` if one of them is a PDF/A output intent.
+fn existing_pdfa_output_intents(doc: &Document) -> Option<Object> {
+    let intents = doc.catalog().ok()?.get(b"OutputIntents").ok()?;
+    let array = match intents {
+        Object::Reference(id) => doc.get_object(*id).ok()?,
+        other => other,
+    }
+    .as_array()
+    .ok()?;
+    let is_pdfa = |intent: &Object| {
+        let dict = match intent {
+            Object::Reference(id) => doc.get_dictionary(*id).ok(),
+            other => other.as_dict().ok(),
+        };
+        dict.and_then(|d| d.get(b"S").ok())
+            .and_then(|s| s.as_name().ok())
+            == Some(b"GTS_PDFA1".as_slice())
+    };
+    array
+        .iter()
+        .any(is_pdfa)
+        .then(|| Object::Array(array.clone()))
+}
+
+/// Write the document information into the Info dictionary, keeping
+/// entries not covered by [`DocInfo`].
+fn write_info(doc: &mut Document, info: &DocInfo) {
+    let mut entries = vec![
+        ("Title", info.title.as_deref().map(pdf_text_string)),
+        ("Author", info.author.as_deref().map(pdf_text_string)),
+        ("Subject", info.subject.as_deref().map(pdf_text_string)),
+        ("Keywords", info.keywords.as_deref().map(pdf_text_string)),
+        ("Creator", info.creator.as_deref().map(pdf_text_string)),
+        ("Producer", info.producer.as_deref().map(pdf_text_string)),
+    ];
+    let dates = [("CreationDate", &info.created), ("ModDate", &info.modified)];
+    for (key, date) in dates {
+        entries.push((
+            key,
+            date.as_ref()
+                .map(|d| Object::string_literal(format_pdf_date(d))),
+        ));
+    }
+    // The XMP always carries a title
+    entries[0]
+        .1
+        .get_or_insert_with(|| Object::string_literal("ZUGFeRD Invoice"));
+
+    let info_id = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) if doc.get_dictionary(*id).is_ok() => Some(*id),
+        _ => None,
+    };
+    let mut dict = match info_id {
+        Some(id) => doc.get_dictionary(id).cloned().unwrap_or_default(),
+        None => doc
+            .trailer
+            .get(b"Info")
+            .and_then(Object::as_dict)
+            .cloned()
+            .unwrap_or_default(),
+    };
+    for (key, value) in entries {
+        match value {
+            Some(value) => dict.set(key, value),
+            None => {
+                dict.remove(key.as_bytes());
+            }
+        }
+    }
+    match info_id {
+        Some(id) => {
+            doc.objects.insert(id, Object::Dictionary(dict));
+        }
+        None => {
+            let id = doc.add_object(dict);
+            doc.trailer.set("Info", Object::Reference(id));
+        }
+    }
+}
+
+/// Add an embedded file stream and its file specification; returns the
+/// file specification's object ID.
+fn add_file_spec(doc: &mut Document, att: &Attachment) -> ObjectId {
+    let mime = att
+        .mime_type
+        .as_deref()
+        .unwrap_or_else(|| mime_type_for(&att.filename));
+    let mut params = dictionary! {
+        "Size" => Object::Integer(att.data.len() as i64),
+        "CheckSum" => Object::String(Md5::digest(&att.data).to_vec(), StringFormat::Hexadecimal),
+    };
+    let dates: [(&str, &Option<DateTime<FixedOffset>>); 2] =
+        [("CreationDate", &att.created), ("ModDate", &att.modified)];
+    for (key, date) in dates {
+        if let Some(date) = date {
+            params.set(key, Object::string_literal(format_pdf_date(date)));
+        }
+    }
+    let ef_stream = Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Subtype" => Object::Name(mime.as_bytes().to_vec()),
+            "Params" => params,
+        },
+        att.data.clone(),
+    );
+    let ef_stream_id = doc.add_object(ef_stream);
+
+    let mut filespec = dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(att.filename.as_str()),
+        "UF" => pdf_text_string(&att.filename),
+        "AFRelationship" => Object::Name(
+            att.af_relationship.as_deref().unwrap_or("Unspecified").as_bytes().to_vec(),
+        ),
+        "EF" => dictionary! {
+            "F" => Object::Reference(ef_stream_id),
+            "UF" => Object::Reference(ef_stream_id),
+        },
+    };
+    if let Some(desc) = &att.description {
+        filespec.set("Desc", pdf_text_string(desc));
+    }
+    doc.add_object(filespec)
+}
+
+/// A PDF text string: PDFDocEncoding-compatible ASCII as is, anything else
+/// as UTF-16BE with byte order mark.
+pub(crate) fn pdf_text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        Object::string_literal(text)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
 /// Build a minimal valid ICC v2 sRGB profile for PDF/A-3 OutputIntent.
 ///
 /// This generates a ~290-byte ICC profile with the minimum required tags
@@ -1,105 +1,311 @@
-use lopdf::{Document, Object};
+use lopdf::{Dictionary, Document, Object};
 
-use crate::core::RechnungError;
+use super::attachment::{Attachment, parse_pdf_date};
+use super::pdfa::read_xmp_string;
+use super::profile::ZugferdProfile;
+use super::version::{ZugferdV1Profile, ZugferdVersion, detect_version, guideline_id};
+use super::xmp::{XmpMetadata, parse_xmp};
+use crate::core::{RechnungError, ValidationError};
 
-/// Extract the Factur-X/ZUGFeRD XML from a PDF.
-///
-/// Searches for `factur-x.xml` (or `zugferd-invoice.xml` for older versions)
-/// in the PDF's embedded files. Returns the XML as a string.
-pub fn extract_from_pdf(pdf_bytes: &[u8]) -> Result<String, RechnungError> {
-    let doc = Document::load_mem(pdf_bytes)
-        .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+/// Filenames the ZUGFeRD / Factur-X / XRechnung specifications use for the
+/// embedded invoice XML.
+const INVOICE_FILENAMES: [&str; 3] = ["factur-x.xml", "zugferd-invoice.xml", "xrechnung.xml"];
 
-    // Try extraction via Names > EmbeddedFiles first, then via AF array
-    extract_via_names(&doc)
-        .or_else(|_| extract_via_af(&doc))
-        .map_err(|e| RechnungError::Builder(format!("no ZUGFeRD/Factur-X XML found in PDF: {e}")))
+/// Nesting limit for the `EmbeddedFiles` name tree.
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+/// Everything a ZUGFeRD / Factur-X PDF carries besides its page content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedDocument {
+    /// The embedded invoice XML.
+    pub xml: String,
+    /// Filename of the invoice XML attachment.
+    pub filename: String,
+    /// `AFRelationship` of the invoice XML attachment.
+    pub af_relationship: Option<String>,
+    /// The document's XMP metadata, if the catalog has a metadata stream.
+    pub xmp: Option<XmpMetadata>,
+    /// All other embedded files (delivery notes, time sheets, …).
+    pub attachments: Vec<Attachment>,
 }
 
-fn extract_via_names(doc: &Document) -> Result<String, String> {
-    let catalog = doc.catalog().map_err(|e| e.to_string())?;
+impl ExtractedDocument {
+    /// The ZUGFeRD generation of the invoice XML.
+    pub fn zugferd_version(&self) -> Option<ZugferdVersion> {
+        detect_version(&self.xml)
+    }
 
-    let names_obj = catalog.get(b"Names").map_err(|e| e.to_string())?;
-    let names_dict = resolve_dict(doc, names_obj)?;
+    /// The ZUGFeRD 2.x / Factur-X profile declared in the invoice XML.
+    ///
+    /// `None` for ZUGFeRD 1.0 documents and unknown guideline URNs.
+    pub fn profile(&self) -> Option<ZugferdProfile> {
+        ZugferdProfile::from_urn(&guideline_id(&self.xml)?)
+    }
 
-    let ef_obj = names_dict
-        .get(b"EmbeddedFiles")
-        .map_err(|e| e.to_string())?;
-    let ef_dict = resolve_dict(doc, ef_obj)?;
+    /// Check that the XMP metadata describes the embedded XML.
+    ///
+    /// The XMP `ConformanceLevel` must match the profile of the XML's
+    /// `GuidelineSpecifiedDocumentContextParameter`, and `DocumentFileName`
+    /// must name the invoice attachment. Returns an empty list if both agree.
+    pub fn check_consistency(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
 
-    let names_array = ef_dict
-        .get(b"Names")
-        .map_err(|e| e.to_string())?
-        .as_array()
-        .map_err(|e| e.to_string())?;
+        let Some(xmp) = &self.xmp else {
+            errors.push(ValidationError::new(
+                "xmp",
+                "PDF has no XMP metadata describing the embedded invoice",
+            ));
+            return errors;
+        };
 
-    // Names array: [name1, ref1, name2, ref2, ...]
-    for chunk in names_array.chunks(2) {
-        if chunk.len() < 2 {
-            continue;
+        let guideline = guideline_id(&self.xml);
+        let expected = guideline
+            .as_deref()
+            .and_then(|urn| match detect_version(&self.xml) {
+                Some(ZugferdVersion::V1) => {
+                    ZugferdV1Profile::from_urn(urn).map(|p| p.conformance_level())
+                }
+                _ => ZugferdProfile::from_urn(urn).map(|p| p.conformance_level()),
+            });
+        match (&xmp.conformance_level, expected) {
+            (None, _) => errors.push(ValidationError::new(
+                "xmp.ConformanceLevel",
+                "XMP metadata has no ConformanceLevel",
+            )),
+            (Some(_), None) => errors.push(ValidationError::new(
+                "xml.GuidelineSpecifiedDocumentContextParameter",
+                format!(
+                    "unknown or missing ZUGFeRD guideline: {}",
+                    guideline.as_deref().unwrap_or("(none)")
+                ),
+            )),
+            (Some(level), Some(expected))
+                if normalize_level(level) != normalize_level(expected) =>
+            {
+                errors.push(ValidationError::new(
+                    "xmp.ConformanceLevel",
+                    format!(
+                        "XMP conformance level '{level}' does not match the XML profile \
+                         '{expected}' ({})",
+                        guideline.as_deref().unwrap_or_default()
+                    ),
+                ))
+            }
+            _ => {}
         }
 
-        let name = obj_to_string(&chunk[0]).unwrap_or_default();
-        if is_facturx_filename(&name) {
-            return extract_xml_from_filespec(doc, &chunk[1]);
+        if let Some(name) = &xmp.document_file_name {
+            if !name.eq_ignore_ascii_case(&self.filename) {
+                errors.push(ValidationError::new(
+                    "xmp.DocumentFileName",
+                    format!(
+                        "XMP names '{name}' but the invoice is embedded as '{}'",
+                        self.filename
+                    ),
+                ));
+            }
         }
+
+        errors
     }
+}
 
-    Err("factur-x.xml not found in EmbeddedFiles name tree".to_string())
+/// Extract the Factur-X/ZUGFeRD XML from a PDF.
+///
+/// Searches for `factur-x.xml` (or `zugferd-invoice.xml` for older versions)
+/// in the PDF's embedded files. Returns the XML as a string; use
+/// [`extract_document`] for the metadata and other attachments.
+pub fn extract_from_pdf(pdf_bytes: &[u8]) -> Result<String, RechnungError> {
+    extract_document(pdf_bytes).map(|doc| doc.xml)
 }
 
-fn extract_via_af(doc: &Document) -> Result<String, String> {
-    let catalog = doc.catalog().map_err(|e| e.to_string())?;
+/// Extract the invoice XML, the XMP metadata and all other attachments
+/// from a PDF.
+///
+/// Embedded files are collected from the `EmbeddedFiles` name tree and the
+/// catalog's `AF` array. The invoice is the file named by the XMP
+/// `DocumentFileName`, else one of the standard filenames, else the first
+/// XML attachment with a `CrossIndustryInvoice` / `CrossIndustryDocument`
+/// root.
+pub fn extract_document(pdf_bytes: &[u8]) -> Result<ExtractedDocument, RechnungError> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+    read_document(&doc)
+}
 
-    let af_obj = catalog.get(b"AF").map_err(|e| e.to_string())?;
-    let af_array = af_obj.as_array().map_err(|e| e.to_string())?;
+pub(super) fn read_document(doc: &Document) -> Result<ExtractedDocument, RechnungError> {
+    let xmp = read_xmp_string(doc).map(|x| parse_xmp(&x));
+    let mut attachments = collect_attachments(doc);
 
-    for obj in af_array {
-        let fs_id = obj.as_reference().map_err(|e| e.to_string())?;
-        let fs_dict = doc.get_dictionary(fs_id).map_err(|e| e.to_string())?;
+    let by_name = |name: &str| {
+        attachments
+            .iter()
+            .position(|a| a.filename.eq_ignore_ascii_case(name))
+    };
+    let index = xmp
+        .as_ref()
+        .and_then(|x| x.document_file_name.as_deref())
+        .and_then(by_name)
+        .or_else(|| INVOICE_FILENAMES.into_iter().find_map(by_name))
+        .or_else(|| {
+            attachments.iter().position(|a| {
+                std::str::from_utf8(&a.data)
+                    .ok()
+                    .and_then(detect_version)
+                    .is_some()
+            })
+        })
+        .ok_or_else(|| {
+            RechnungError::Builder(
+                "no ZUGFeRD/Factur-X XML found in PDF: no embedded invoice XML".to_string(),
+            )
+        })?;
 
-        // Check filename from UF or F
-        let fname = fs_dict
-            .get(b"UF")
-            .or_else(|_| fs_dict.get(b"F"))
-            .ok()
-            .and_then(obj_to_string)
-            .unwrap_or_default();
+    let invoice = attachments.remove(index);
+    let xml = String::from_utf8(invoice.data).map_err(|e| {
+        RechnungError::Builder(format!("no ZUGFeRD/Factur-X XML found in PDF: {e}"))
+    })?;
 
-        if is_facturx_filename(&fname) {
-            return extract_xml_from_filespec_dict(doc, fs_dict);
+    Ok(ExtractedDocument {
+        xml,
+        filename: invoice.filename,
+        af_relationship: invoice.af_relationship,
+        xmp,
+        attachments,
+    })
+}
+
+/// All embedded files, from the name tree first and then the `AF` array,
+/// each file once.
+fn collect_attachments(doc: &Document) -> Vec<Attachment> {
+    let mut specs = embedded_file_specs(doc);
+    if let Ok(catalog) = doc.catalog() {
+        if let Ok(af) = catalog.get(b"AF").and_then(Object::as_array) {
+            for obj in af {
+                if let Ok(fs_dict) = resolve_dict(doc, obj) {
+                    specs.push((String::new(), fs_dict));
+                }
+            }
         }
     }
 
-    Err("factur-x.xml not found in AF array".to_string())
+    let mut attachments: Vec<Attachment> = Vec::new();
+    for (key, fs_dict) in specs {
+        let Some(attachment) = read_attachment(doc, &key, fs_dict) else {
+            continue;
+        };
+        if !attachments
+            .iter()
+            .any(|a| a.filename == attachment.filename)
+        {
+            attachments.push(attachment);
+        }
+    }
+    attachments
 }
 
-fn extract_xml_from_filespec(doc: &Document, obj: &Object) -> Result<String, String> {
-    let fs_id = obj.as_reference().map_err(|e| e.to_string())?;
-    let fs_dict = doc.get_dictionary(fs_id).map_err(|e| e.to_string())?;
-    extract_xml_from_filespec_dict(doc, fs_dict)
+/// The file specifications in the `EmbeddedFiles` name tree with their keys.
+pub(super) fn embedded_file_specs(doc: &Document) -> Vec<(String, &Dictionary)> {
+    let mut specs = Vec::new();
+    let tree = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Names").ok())
+        .and_then(|n| resolve_dict(doc, n).ok())
+        .and_then(|names| names.get(b"EmbeddedFiles").ok())
+        .and_then(|ef| resolve_dict(doc, ef).ok());
+    if let Some(tree) = tree {
+        walk_name_tree(doc, tree, 0, &mut specs);
+    }
+    specs
 }
 
-fn extract_xml_from_filespec_dict(
-    doc: &Document,
-    fs_dict: &lopdf::Dictionary,
-) -> Result<String, String> {
-    let ef_obj = fs_dict.get(b"EF").map_err(|e| e.to_string())?;
-    let ef_dict = resolve_dict(doc, ef_obj)?;
+fn walk_name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    depth: usize,
+    specs: &mut Vec<(String, &'a Dictionary)>,
+) {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return;
+    }
+    // Names array: [name1, ref1, name2, ref2, ...]
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        for chunk in names.chunks_exact(2) {
+            if let Ok(fs_dict) = resolve_dict(doc, &chunk[1]) {
+                specs.push((obj_to_string(&chunk[0]).unwrap_or_default(), fs_dict));
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok(kid) = resolve_dict(doc, kid) {
+                walk_name_tree(doc, kid, depth + 1, specs);
+            }
+        }
+    }
+}
+
+fn read_attachment(doc: &Document, key: &str, fs_dict: &Dictionary) -> Option<Attachment> {
+    let filename = fs_dict
+        .get(b"UF")
+        .or_else(|_| fs_dict.get(b"F"))
+        .ok()
+        .and_then(obj_to_string)
+        .unwrap_or_else(|| key.to_string());
 
-    let f_obj = ef_dict.get(b"F").map_err(|e| e.to_string())?;
-    let stream_obj = resolve_obj(doc, f_obj)?;
-    let stream = stream_obj.as_stream().map_err(|e| e.to_string())?;
+    let ef_dict = resolve_dict(doc, fs_dict.get(b"EF").ok()?).ok()?;
+    let f_obj = ef_dict.get(b"UF").or_else(|_| ef_dict.get(b"F")).ok()?;
+    let stream = resolve_obj(doc, f_obj).ok()?.as_stream().ok()?;
 
     // decompressed_content() fails if no Filter key exists (uncompressed stream),
     // so fall back to raw content in that case.
-    let content = stream
+    let data = stream
         .decompressed_content()
         .unwrap_or_else(|_| stream.content.clone());
-    String::from_utf8(content).map_err(|e| e.to_string())
+
+    let params = stream
+        .dict
+        .get(b"Params")
+        .ok()
+        .and_then(|p| resolve_dict(doc, p).ok());
+    let date = |key: &[u8]| {
+        params
+            .and_then(|p| p.get(key).ok())
+            .and_then(obj_to_string)
+            .and_then(|d| parse_pdf_date(&d))
+    };
+
+    Some(Attachment {
+        filename,
+        mime_type: stream
+            .dict
+            .get(b"Subtype")
+            .and_then(Object::as_name_str)
+            .ok()
+            .map(str::to_string),
+        description: fs_dict.get(b"Desc").ok().and_then(obj_to_string),
+        af_relationship: fs_dict
+            .get(b"AFRelationship")
+            .and_then(Object::as_name_str)
+            .ok()
+            .map(str::to_string),
+        created: date(b"CreationDate"),
+        modified: date(b"ModDate"),
+        data,
+    })
+}
+
+/// Compare conformance levels ignoring case and spacing (`EN 16931` vs `EN16931`).
+fn normalize_level(level: &str) -> String {
+    level
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_ascii_uppercase()
 }
 
-fn resolve_dict<'a>(doc: &'a Document, obj: &'a Object) -> Result<&'a lopdf::Dictionary, String> {
+fn resolve_dict<'a>(doc: &'a Document, obj: &'a Object) -> Result<&'a Dictionary, String> {
     match obj {
         Object::Reference(id) => doc.get_dictionary(*id).map_err(|e| e.to_string()),
         Object::Dictionary(d) => Ok(d),
@@ -114,14 +320,23 @@ fn resolve_obj<'a>(doc: &'a Document, obj: &'a Object) -> Result<&'a Object, Str
     }
 }
 
-fn obj_to_string(obj: &Object) -> Option<String> {
+/// Decode a PDF text string (UTF-16BE with byte order mark, else UTF-8,
+/// falling back to PDFDocEncoding read as Latin-1).
+pub(crate) fn obj_to_string(obj: &Object) -> Option<String> {
     match obj {
-        Object::String(bytes, _) => String::from_utf8(bytes.clone()).ok(),
+        Object::String(bytes, _) => match bytes.strip_prefix(&[0xFE, 0xFF]) {
+            Some(utf16) => {
+                let units: Vec<u16> = utf16
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()
+            }
+            None => Some(
+                String::from_utf8(bytes.clone())
+                    .unwrap_or_else(|_| bytes.iter().map(|&b| b as char).collect()),
+            ),
+        },
         _ => None,
     }
 }
-
-fn is_facturx_filename(name: &str) -> bool {
-    let lower = name.to_lowercase();
-    lower.contains("factur-x") || lower.contains("zugferd")
-}
@@ -0,0 +1,802 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId, Stream};
+use rust_decimal::Decimal;
+
+use super::extract::read_document;
+use super::version::from_xml;
+use crate::core::{Invoice, RechnungError, ValidationError};
+
+/// Labels the invoice number is printed after.
+const NUMBER_LABELS: &[&str] = &[
+    "Rechnungsnummer",
+    "Rechnungs-Nr",
+    "Rechnung Nr",
+    "Rechnungsnr",
+    "Gutschriftsnummer",
+    "Belegnummer",
+    "Invoice number",
+    "Invoice no",
+];
+const ISSUE_DATE_LABELS: &[&str] = &["Rechnungsdatum", "Belegdatum", "Invoice date", "Datum"];
+const DUE_DATE_LABELS: &[&str] = &[
+    "Fälligkeitsdatum",
+    "Fällig am",
+    "Fällig",
+    "Zahlbar bis",
+    "Due date",
+];
+const DELIVERY_DATE_LABELS: &[&str] = &["Leistungsdatum", "Lieferdatum", "Delivery date"];
+const NET_LABELS: &[&str] = &[
+    "Nettobetrag",
+    "Summe netto",
+    "Gesamt netto",
+    "Netto",
+    "Net amount",
+    "Total net",
+    "Subtotal",
+];
+const VAT_LABELS: &[&str] = &["Umsatzsteuer", "MwSt", "USt", "VAT"];
+const GROSS_LABELS: &[&str] = &[
+    "Rechnungsbetrag",
+    "Gesamtbetrag",
+    "Bruttobetrag",
+    "Gesamt brutto",
+    "Endsumme",
+    "Brutto",
+    "Total",
+];
+const DUE_AMOUNT_LABELS: &[&str] = &["Zahlbetrag", "Zu zahlen", "Offener Betrag", "Amount due"];
+
+const MONTHS: [(&str, u32); 26] = [
+    ("januar", 1),
+    ("january", 1),
+    ("februar", 2),
+    ("february", 2),
+    ("märz", 3),
+    ("march", 3),
+    ("april", 4),
+    ("mai", 5),
+    ("may", 5),
+    ("juni", 6),
+    ("june", 6),
+    ("juli", 7),
+    ("july", 7),
+    ("august", 8),
+    ("september", 9),
+    ("oktober", 10),
+    ("october", 10),
+    ("november", 11),
+    ("dezember", 12),
+    ("december", 12),
+    ("jan", 1),
+    ("feb", 2),
+    ("mar", 3),
+    ("apr", 4),
+    ("jun", 6),
+    ("jul", 7),
+];
+
+/// Extract the text of every page of a PDF.
+///
+/// Text is read from the page content streams in drawing order and decoded
+/// with the font encodings (`ToUnicode` maps where present, falling back
+/// to Latin-1). Text shown at a new vertical position starts a new line.
+/// Text inside form XObjects is not read.
+pub fn extract_page_text(pdf_bytes: &[u8]) -> Result<Vec<String>, RechnungError> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+    Ok(doc.page_iter().map(|page| page_text(&doc, page)).collect())
+}
+
+/// Compare the visible text of a ZUGFeRD / Factur-X PDF with its embedded
+/// invoice XML.
+///
+/// The XML is legally binding, but recipients read the PDF. Extracts the
+/// page text and the embedded invoice and reports, via
+/// [`check_visible_text`], every value of the XML that the pages do not
+/// show.
+pub fn check_hybrid(pdf_bytes: &[u8]) -> Result<Vec<ValidationError>, RechnungError> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+    let (invoice, _) = from_xml(&read_document(&doc)?.xml)?;
+    let text = doc
+        .page_iter()
+        .map(|page| page_text(&doc, page))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.trim().is_empty() {
+        return Err(RechnungError::Builder(
+            "PDF has no extractable text (scanned or outlined pages?)".into(),
+        ));
+    }
+    Ok(check_visible_text(&text, &invoice))
+}
+
+/// Check that the visible text of an invoice shows the invoice's key values.
+///
+/// Looks for the invoice number (BT-1), issue, due and delivery dates
+/// (BT-2, BT-9, BT-72, or the tax point date BT-7 without one), net, VAT, gross and due totals (BT-109, BT-110,
+/// BT-112, BT-115), the VAT amount per rate (BT-117) and the IBANs (BT-84).
+/// Dates are recognised as `15.06.2024`, `2024-06-15`, `15/06/2024` and
+/// `15. Juni 2024`; amounts in German (`1.785,00`) and English (`1,785.00`)
+/// notation. For a value that is not found, the value printed next to the
+/// usual label (`Rechnungsnummer`, `Gesamtbetrag`, `IBAN`, …) is named in
+/// the message if there is one.
+pub fn check_visible_text(text: &str, invoice: &Invoice) -> Vec<ValidationError> {
+    let visible = VisibleText::new(text);
+    let mut errors = Vec::new();
+
+    if !visible.contains_token(&invoice.number) {
+        errors.push(mismatch(
+            "number",
+            "invoice number (BT-1)",
+            &invoice.number,
+            visible.labelled(NUMBER_LABELS, next_token),
+        ));
+    }
+
+    let (delivery_field, delivery_name, delivery_date) = match invoice
+        .delivery
+        .as_ref()
+        .and_then(|d| d.actual_delivery_date)
+    {
+        Some(date) => (
+            "delivery.actual_delivery_date",
+            "delivery date (BT-72)",
+            Some(date),
+        ),
+        None => (
+            "tax_point_date",
+            "tax point date (BT-7)",
+            invoice.tax_point_date,
+        ),
+    };
+    let dates = [
+        (
+            "issue_date",
+            "issue date (BT-2)",
+            Some(invoice.issue_date),
+            ISSUE_DATE_LABELS,
+        ),
+        (
+            "due_date",
+            "due date (BT-9)",
+            invoice.due_date,
+            DUE_DATE_LABELS,
+        ),
+        (
+            delivery_field,
+            delivery_name,
+            delivery_date,
+            DELIVERY_DATE_LABELS,
+        ),
+    ];
+    for (field, name, date, labels) in dates {
+        let Some(date) = date else { continue };
+        if !visible.dates.contains(&date) {
+            errors.push(mismatch(
+                field,
+                name,
+                &date.format("%d.%m.%Y").to_string(),
+                visible.labelled(labels, |rest| {
+                    parse_dates(rest).first().map(|(t, _)| t.clone())
+                }),
+            ));
+        }
+    }
+
+    if let Some(totals) = &invoice.totals {
+        let mut amounts = vec![
+            (
+                "totals.net_total",
+                "net total (BT-109)",
+                totals.net_total,
+                NET_LABELS,
+            ),
+            (
+                "totals.vat_total",
+                "VAT total (BT-110)",
+                totals.vat_total,
+                VAT_LABELS,
+            ),
+            (
+                "totals.gross_total",
+                "gross total (BT-112)",
+                totals.gross_total,
+                GROSS_LABELS,
+            ),
+        ];
+        if totals.amount_due != totals.gross_total {
+            amounts.push((
+                "totals.amount_due",
+                "amount due (BT-115)",
+                totals.amount_due,
+                DUE_AMOUNT_LABELS,
+            ));
+        }
+        for (field, name, amount, labels) in amounts {
+            if !amount.is_zero() && !visible.contains_amount(amount) {
+                errors.push(mismatch(
+                    field,
+                    name,
+                    &amount.to_string(),
+                    visible.labelled(labels, last_amount),
+                ));
+            }
+        }
+
+        // A single rate's VAT amount is the VAT total, already checked
+        if totals.vat_breakdown.len() > 1 {
+            for (i, vat) in totals.vat_breakdown.iter().enumerate() {
+                if vat.tax_amount.is_zero() || visible.contains_amount(vat.tax_amount) {
+                    continue;
+                }
+                let rate = vat.rate.normalize().to_string();
+                let shown = visible.lines.iter().find_map(|line| {
+                    let rate_shown = parse_amounts(line)
+                        .iter()
+                        .any(|(_, values)| values.contains(&vat.rate));
+                    (rate_shown && find_label(line, VAT_LABELS).is_some())
+                        .then(|| last_amount(line))
+                        .flatten()
+                });
+                errors.push(mismatch(
+                    &format!("totals.vat_breakdown[{i}].tax_amount"),
+                    &format!("VAT amount at {rate} % (BT-117)"),
+                    &vat.tax_amount.to_string(),
+                    shown,
+                ));
+            }
+        }
+    }
+
+    let ibans = invoice
+        .payment
+        .iter()
+        .filter_map(|p| p.credit_transfer.as_ref())
+        .map(|ct| ct.iban.as_str());
+    for iban in ibans {
+        let compact = compact_iban(iban);
+        if !visible.compact.contains(&compact) {
+            errors.push(mismatch(
+                "payment.credit_transfer.iban",
+                "IBAN (BT-84)",
+                iban,
+                visible.labelled(&["IBAN"], iban_after_label),
+            ));
+        }
+    }
+
+    errors
+}
+
+fn mismatch(field: &str, name: &str, expected: &str, shown: Option<String>) -> ValidationError {
+    let message = match shown {
+        Some(shown) => format!("{name} is {expected} in the XML but the PDF shows {shown}"),
+        None => format!("{name} {expected} not found in the PDF text"),
+    };
+    ValidationError::new(field, message)
+}
+
+/// The page text with the dates and amounts found in it.
+struct VisibleText<'a> {
+    text: &'a str,
+    lines: Vec<&'a str>,
+    dates: Vec<NaiveDate>,
+    amounts: Vec<Decimal>,
+    /// Upper-case text without whitespace, for IBANs printed in groups
+    compact: String,
+}
+
+impl<'a> VisibleText<'a> {
+    fn new(text: &'a str) -> Self {
+        let lines: Vec<&str> = text.lines().collect();
+        Self {
+            text,
+            dates: lines
+                .iter()
+                .flat_map(|line| parse_dates(line))
+                .map(|(_, date)| date)
+                .collect(),
+            amounts: lines
+                .iter()
+                .flat_map(|line| parse_amounts(line))
+                .flat_map(|(_, values)| values)
+                .collect(),
+            compact: compact_iban(text),
+            lines,
+        }
+    }
+
+    /// Whether `token` occurs, not as part of a longer identifier.
+    fn contains_token(&self, token: &str) -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || matches!(c, '-' | '/' | '_');
+        !token.is_empty()
+            && self.text.match_indices(token).any(|(i, _)| {
+                let before = self.text[..i].chars().next_back();
+                let after = self.text[i + token.len()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })
+    }
+
+    fn contains_amount(&self, amount: Decimal) -> bool {
+        self.amounts.contains(&amount.abs())
+    }
+
+    /// The value `read` finds after the first of `labels` that has one,
+    /// on the label's line or the next.
+    fn labelled(&self, labels: &[&str], read: impl Fn(&str) -> Option<String>) -> Option<String> {
+        self.lines.iter().enumerate().find_map(|(i, line)| {
+            let rest = &line[find_label(line, labels)?..];
+            read(rest).or_else(|| read(self.lines.get(i + 1)?))
+        })
+    }
+}
+
+/// Byte offset after the first of `labels` in `line` (case-insensitive,
+/// not followed by a letter).
+fn find_label(line: &str, labels: &[&str]) -> Option<usize> {
+    let lower = line.to_lowercase();
+    // Lower-casing keeps byte offsets for the labels' characters, but not
+    // necessarily for the rest of the line
+    if lower.len() != line.len() {
+        return None;
+    }
+    labels.iter().find_map(|label| {
+        let label = label.to_lowercase();
+        lower.match_indices(&label).find_map(|(i, _)| {
+            let end = i + label.len();
+            let before = lower[..i].chars().next_back();
+            let after = lower[end..].chars().next();
+            (!before.is_some_and(char::is_alphabetic) && !after.is_some_and(char::is_alphabetic))
+                .then_some(end)
+        })
+    })
+}
+
+/// The first word after a label, without separators such as `:` or `#`.
+fn next_token(rest: &str) -> Option<String> {
+    rest.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, ':' | '#' | '.' | ',' | ';')))
+        .find(|word| !word.is_empty() && !word.eq_ignore_ascii_case("nr"))
+        .map(str::to_string)
+}
+
+/// The last amount on a line, as printed.
+fn last_amount(rest: &str) -> Option<String> {
+    parse_amounts(rest)
+        .into_iter()
+        .rev()
+        .find(|(token, _)| token.contains(['.', ',']))
+        .map(|(token, _)| token)
+}
+
+/// The IBAN printed after the `IBAN` label: the country code and check
+/// digits, then groups containing digits.
+fn iban_after_label(rest: &str) -> Option<String> {
+    let mut words = rest
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|word| !word.is_empty());
+    let first = words.next()?;
+    if first.len() < 4 || !first[..2].chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut iban = first.to_string();
+    for word in words {
+        if iban.len() >= 34 || !word.chars().any(|c| c.is_ascii_digit()) {
+            break;
+        }
+        iban.push(' ');
+        iban.push_str(word);
+    }
+    Some(iban)
+}
+
+fn compact_iban(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Dates in a line with the text they were read from.
+fn parse_dates(line: &str) -> Vec<(String, NaiveDate)> {
+    let words: Vec<&str> = line
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, ',' | ';' | '(' | ')' | ':')))
+        .collect();
+    let mut dates = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for date in numeric_dates(word) {
+            dates.push((word.to_string(), date));
+        }
+        // 15. Juni 2024 / 15 June 2024 / June 15, 2024
+        let (Some(next), Some(year)) = (words.get(i + 1), words.get(i + 2)) else {
+            continue;
+        };
+        let Ok(year) = year.parse::<i32>() else {
+            continue;
+        };
+        let date = match (month(next), month(word)) {
+            (Some(m), _) => word
+                .trim_end_matches('.')
+                .parse()
+                .ok()
+                .and_then(|d| NaiveDate::from_ymd_opt(year, m, d)),
+            (None, Some(m)) => next
+                .parse()
+                .ok()
+                .and_then(|d| NaiveDate::from_ymd_opt(year, m, d)),
+            _ => None,
+        };
+        if let Some(date) = date {
+            dates.push((words[i..i + 3].join(" "), date));
+        }
+    }
+    dates
+}
+
+fn month(word: &str) -> Option<u32> {
+    let word = word.trim_end_matches('.').to_lowercase();
+    MONTHS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, m)| *m)
+}
+
+/// `15.06.2024`, `15.6.24`, `2024-06-15` or `15/06/2024` (also read as
+/// month/day when that is a valid date).
+fn numeric_dates(word: &str) -> Vec<NaiveDate> {
+    let word = word.trim_end_matches('.');
+    let (sep, parts): (char, Vec<&str>) = if word.contains('.') {
+        ('.', word.split('.').collect())
+    } else if word.contains('/') {
+        ('/', word.split('/').collect())
+    } else {
+        ('-', word.split('-').collect())
+    };
+    let numbers: Vec<u32> = parts.iter().filter_map(|p| p.parse().ok()).collect();
+    if parts.len() != 3 || numbers.len() != 3 || parts.iter().any(|p| p.len() > 4) {
+        return Vec::new();
+    }
+    let year = |text: &str, value: u32| match text.len() {
+        4 => Some(value as i32),
+        2 => Some(2000 + value as i32),
+        _ => None,
+    };
+    let ymd = |y: Option<i32>, m: u32, d: u32| y.and_then(|y| NaiveDate::from_ymd_opt(y, m, d));
+    let mut dates = Vec::new();
+    match sep {
+        '-' if parts[0].len() == 4 => {
+            dates.extend(ymd(year(parts[0], numbers[0]), numbers[1], numbers[2]))
+        }
+        '-' => {}
+        _ => {
+            let y = year(parts[2], numbers[2]);
+            dates.extend(ymd(y, numbers[1], numbers[0]));
+            if sep == '/' {
+                dates.extend(ymd(y, numbers[0], numbers[1]));
+            }
+        }
+    }
+    dates
+}
+
+/// Amounts in a line with the text they were read from. A token such as
+/// `1.234` is both 1234 and 1.234; dates are skipped.
+fn parse_amounts(line: &str) -> Vec<(String, Vec<Decimal>)> {
+    line.split_whitespace()
+        .filter_map(|word| {
+            let token = word.trim_matches(|c: char| !c.is_ascii_digit());
+            if token.is_empty()
+                || !token
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+                || !numeric_dates(token).is_empty()
+            {
+                return None;
+            }
+            let values = amount_values(token);
+            (!values.is_empty()).then(|| (token.to_string(), values))
+        })
+        .collect()
+}
+
+fn amount_values(token: &str) -> Vec<Decimal> {
+    let parse = |digits: String| digits.parse::<Decimal>().ok();
+    let dots = token.matches('.').count();
+    let commas = token.matches(',').count();
+    let last_sep = token.rfind(['.', ',']);
+    let values = match (dots, commas, last_sep) {
+        (0, 0, _) => vec![parse(token.to_string())],
+        // Both separators: the last one is the decimal separator
+        (d, c, Some(i)) if d > 0 && c > 0 => {
+            let int: String = token[..i].chars().filter(char::is_ascii_digit).collect();
+            let frac = &token[i + 1..];
+            if frac.contains(['.', ',']) || !thousands_grouped(&token[..i]) {
+                return Vec::new();
+            }
+            vec![parse(format!("{int}.{frac}"))]
+        }
+        // One separator: decimal, or thousands if three digits follow
+        (1, 0, Some(i)) | (0, 1, Some(i)) => {
+            let (int, frac) = (&token[..i], &token[i + 1..]);
+            let mut values = vec![parse(format!("{int}.{frac}"))];
+            if frac.len() == 3 && !int.is_empty() {
+                values.push(parse(format!("{int}{frac}")));
+            }
+            values
+        }
+        // Repeated separator: thousands grouping
+        _ if thousands_grouped(token) => vec![parse(token.replace(['.', ','], ""))],
+        _ => Vec::new(),
+    };
+    values.into_iter().flatten().collect()
+}
+
+/// `1.234.567`: groups of three digits after the first.
+fn thousands_grouped(digits: &str) -> bool {
+    let mut groups = digits.split(['.', ',']);
+    groups
+        .next()
+        .is_some_and(|first| (1..=3).contains(&first.len()))
+        && groups.all(|group| group.len() == 3)
+}
+
+/// Form XObjects nested deeper than this are not read.
+const MAX_FORM_DEPTH: usize = 8;
+
+/// Text of one page: decoded strings of the text-showing operators, with a
+/// line break whenever the vertical text position changes and a space when
+/// text is repositioned on the same line. Form XObjects drawn with `Do`
+/// (letterheads, stamped text blocks) are read in place.
+fn page_text(doc: &Document, page: ObjectId) -> String {
+    let Ok(content) = doc.get_and_decode_page_content(page) else {
+        return String::new();
+    };
+    let mut text = PageText::default();
+    text.show(doc, &content.operations, &Resources::page(doc, page), 0);
+    text.text
+}
+
+/// Fonts and form XObjects a content stream can refer to by name.
+#[derive(Default)]
+struct Resources<'a> {
+    fonts: BTreeMap<Vec<u8>, &'a Dictionary>,
+    forms: BTreeMap<Vec<u8>, ObjectId>,
+}
+
+impl<'a> Resources<'a> {
+    /// Resources of a page, including those inherited from the page tree.
+    fn page(doc: &'a Document, page: ObjectId) -> Self {
+        let mut resources = Self {
+            fonts: doc.get_page_fonts(page).unwrap_or_default(),
+            forms: BTreeMap::new(),
+        };
+        if let Ok((direct, inherited)) = doc.get_page_resources(page) {
+            let dicts = direct.into_iter().chain(
+                inherited
+                    .into_iter()
+                    .filter_map(|id| doc.get_dictionary(id).ok()),
+            );
+            for dict in dicts {
+                resources.add_forms(doc, dict);
+            }
+        }
+        resources
+    }
+
+    /// Resources of a form XObject; names it does not define fall back to
+    /// those of the content drawing it.
+    fn form(&self, doc: &'a Document, form: &'a Stream) -> Self {
+        let mut resources = Self::default();
+        if let Ok(dict) = doc.get_dict_in_dict(&form.dict, b"Resources") {
+            if let Ok(fonts) = doc.get_dict_in_dict(dict, b"Font") {
+                for (name, font) in fonts.iter() {
+                    let font = match font {
+                        Object::Reference(id) => doc.get_dictionary(*id).ok(),
+                        Object::Dictionary(font) => Some(font),
+                        _ => None,
+                    };
+                    if let Some(font) = font {
+                        resources.fonts.insert(name.clone(), font);
+                    }
+                }
+            }
+            resources.add_forms(doc, dict);
+        }
+        for (name, font) in &self.fonts {
+            resources.fonts.entry(name.clone()).or_insert(font);
+        }
+        for (name, id) in &self.forms {
+            resources.forms.entry(name.clone()).or_insert(*id);
+        }
+        resources
+    }
+
+    fn add_forms(&mut self, doc: &Document, resources: &Dictionary) {
+        let Ok(xobjects) = doc.get_dict_in_dict(resources, b"XObject") else {
+            return;
+        };
+        for (name, xobject) in xobjects.iter() {
+            if let Ok(id) = xobject.as_reference() {
+                self.forms.entry(name.clone()).or_insert(id);
+            }
+        }
+    }
+}
+
+/// Text collected from a page and the forms it draws.
+#[derive(Default)]
+struct PageText {
+    text: String,
+    shown_y: Option<f32>,
+    moved: bool,
+}
+
+impl PageText {
+    fn show(
+        &mut self,
+        doc: &Document,
+        operations: &[Operation],
+        resources: &Resources,
+        depth: usize,
+    ) {
+        let encodings: BTreeMap<&[u8], Encoding> = resources
+            .fonts
+            .iter()
+            .filter(|(_, font)| font.type_is(b"Font"))
+            .filter_map(|(name, font)| {
+                font.get_font_encoding(doc)
+                    .ok()
+                    .map(|e| (name.as_slice(), e))
+            })
+            .collect();
+
+        let mut encoding = None;
+        let (mut y, mut leading) = (0.0_f32, 0.0_f32);
+        for op in operations {
+            let number = |i: usize| {
+                op.operands
+                    .get(i)
+                    .and_then(|o| o.as_float().ok())
+                    .unwrap_or_default()
+            };
+            let shown: &[Object] = match op.operator.as_str() {
+                "Tf" => {
+                    encoding = op
+                        .operands
+                        .first()
+                        .and_then(|name| name.as_name().ok())
+                        .and_then(|name| encodings.get(name));
+                    continue;
+                }
+                "Do" => {
+                    if depth < MAX_FORM_DEPTH {
+                        self.show_form(doc, op, resources, depth);
+                    }
+                    continue;
+                }
+                "BT" => {
+                    y = 0.0;
+                    self.moved = true;
+                    continue;
+                }
+                "Tm" => {
+                    y = number(5);
+                    self.moved = true;
+                    continue;
+                }
+                "Td" | "TD" => {
+                    y += number(1);
+                    if op.operator == "TD" {
+                        leading = -number(1);
+                    }
+                    self.moved = true;
+                    continue;
+                }
+                "TL" => {
+                    leading = number(0);
+                    continue;
+                }
+                "T*" => {
+                    y -= leading;
+                    self.moved = true;
+                    continue;
+                }
+                "Tj" | "TJ" => &op.operands,
+                "'" => {
+                    y -= leading;
+                    self.moved = true;
+                    &op.operands
+                }
+                "\"" => {
+                    y -= leading;
+                    self.moved = true;
+                    op.operands.get(2..).unwrap_or_default()
+                }
+                _ => continue,
+            };
+
+            let mut piece = String::new();
+            decode_operands(shown, encoding, &mut piece);
+            if piece.is_empty() {
+                continue;
+            }
+            match self.shown_y {
+                Some(last) if (last - y).abs() > 0.5 => self.text.push('\n'),
+                Some(_) if self.moved && !self.text.ends_with(' ') && !piece.starts_with(' ') => {
+                    self.text.push(' ')
+                }
+                _ => {}
+            }
+            self.text.push_str(&piece);
+            self.shown_y = Some(y);
+            self.moved = false;
+        }
+    }
+
+    /// Read the form XObject named by a `Do` operator; images are skipped.
+    fn show_form(&mut self, doc: &Document, op: &Operation, resources: &Resources, depth: usize) {
+        let form = op
+            .operands
+            .first()
+            .and_then(|name| name.as_name().ok())
+            .and_then(|name| resources.forms.get(name))
+            .and_then(|id| doc.get_object(*id).ok())
+            .and_then(|object| object.as_stream().ok())
+            .filter(|stream| {
+                stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form")
+            });
+        let Some(form) = form else { return };
+        let data = if form.dict.has(b"Filter") {
+            form.decompressed_content().ok()
+        } else {
+            Some(form.content.clone())
+        };
+        let Some(content) = data.and_then(|data| Content::decode(&data).ok()) else {
+            return;
+        };
+        // Forms have their own coordinates: break the line around their text
+        self.break_line();
+        self.show(
+            doc,
+            &content.operations,
+            &resources.form(doc, form),
+            depth + 1,
+        );
+        self.break_line();
+    }
+
+    /// Start the next text shown on a new line.
+    fn break_line(&mut self) {
+        if self.shown_y.is_some() {
+            self.shown_y = Some(f32::INFINITY);
+        }
+    }
+}
+
+fn decode_operands(operands: &[Object], encoding: Option<&Encoding>, out: &mut String) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => {
+                match encoding.and_then(|e| Document::decode_text(e, bytes).ok()) {
+                    Some(text) => out.push_str(&text),
+                    None => out.extend(bytes.iter().map(|&b| b as char)),
+                }
+            }
+            Object::Array(items) => decode_operands(items, encoding, out),
+            // Kerning wider than a third of the font size reads as a space
+            Object::Integer(_) | Object::Real(_)
+                if operand.as_float().is_ok_and(|n| n < -300.0) && !out.ends_with(' ') =>
+            {
+                out.push(' ');
+            }
+            _ => {}
+        }
+    }
+}
@@ -13,15 +13,77 @@
 //! | EN16931 | Full European norm (recommended) |
 //! | Extended | Beyond EN 16931 |
 //! | XRechnung | German public sector |
+//!
+//! [`validate_zugferd`] checks an invoice against the Factur-X rules of a
+//! profile, [`dropped_fields`] lists the data a profile cannot carry and
+//! [`best_profile`] picks the lowest profile that keeps everything.
+//! [`convert`] moves existing XML to another profile.
+//!
+//! # Attachments
+//!
+//! [`embed_in_pdf_with_attachments`] embeds supplementary files (time
+//! sheets, delivery notes, …) next to the invoice XML; [`extract_document`]
+//! returns them together with the XML and the XMP metadata.
+//!
+//! # Reproducible output
+//!
+//! [`embed_in_pdf_with_options`] takes the current time, dates, producer
+//! and trailer ID strategy from [`EmbedOptions`], so that identical inputs
+//! give byte-identical PDFs.
+//!
+//! # Pre-flight
+//!
+//! [`preflight`] checks a finished PDF against the PDF/A-3 and Factur-X
+//! rules invoices are most often rejected for (binary header, trailer ID,
+//! output intent, embedded fonts, actions, XMP/Info consistency, associated
+//! files) and reports each finding with its ISO 19005-3 clause.
+//!
+//! # Hybrid consistency
+//!
+//! The XML is the legally binding invoice, but people read the PDF.
+//! [`check_hybrid`] extracts the page text and reports invoice number,
+//! dates, totals, VAT amounts and IBANs of the XML that the pages do not
+//! show.
+//!
+//! # ZUGFeRD 1.0
+//!
+//! Older PDFs embed a ZUGFeRD 1.0 `CrossIndustryDocument` (Basic, Comfort
+//! or Extended). [`from_xml`] parses both generations into the same
+//! `Invoice` model and reports the [`ZugferdVersion`].
 
+mod attachment;
+mod conformance;
 mod embed;
 mod extract;
+mod hybrid;
+mod pdfa;
+mod preflight;
 mod profile;
+mod version;
 mod xmp;
 
-pub use embed::embed_in_pdf;
-pub use extract::extract_from_pdf;
+pub use attachment::Attachment;
+pub use conformance::{
+    Conversion, DroppedField, best_profile, convert, dropped_fields, validate_zugferd,
+};
+pub use embed::{
+    DocumentId, EmbedOptions, embed_in_pdf, embed_in_pdf_with_attachments,
+    embed_in_pdf_with_options,
+};
+pub use extract::{ExtractedDocument, extract_document, extract_from_pdf};
+pub use hybrid::{check_hybrid, check_visible_text, extract_page_text};
+pub use pdfa::{PdfInputIssue, PdfInputKind, PdfInputReport, inspect_pdf};
+pub use preflight::{PdfaIssue, preflight};
 pub use profile::{ZugferdProfile, to_xml};
+pub use version::{ZugferdV1Profile, ZugferdVersion, detect_version, from_xml, guideline_id};
+pub use xmp::XmpMetadata;
+
+#[cfg(feature = "signature")]
+pub(crate) use attachment::format_pdf_date;
+#[cfg(feature = "signature")]
+pub(crate) use embed::pdf_text_string;
+#[cfg(feature = "signature")]
+pub(crate) use extract::obj_to_string;
 
 /// The embedded XML filename per Factur-X 1.0+ specification.
 pub const FACTURX_FILENAME: &str = "factur-x.xml";
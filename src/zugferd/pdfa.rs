@@ -0,0 +1,230 @@
+use lopdf::{Dictionary, Document, Object};
+
+use super::attachment::parse_pdf_date;
+use super::extract::obj_to_string;
+use super::xmp::{DocInfo, parse_xmp};
+use crate::core::RechnungError;
+
+/// A finding in an input PDF; [blocking](Self::is_blocking) ones prevent a
+/// conformant PDF/A-3 result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PdfInputIssue {
+    /// The document is encrypted (ISO 19005-3, 6.1.3); it cannot be embedded into.
+    Encrypted,
+    /// A font program is not embedded (6.2.11.4), e.g. a standard 14 font.
+    FontNotEmbedded(String),
+    /// Transparency is used: soft masks, constant alpha below 1 or blend
+    /// modes other than `Normal`. Informational only: unlike PDF/A-1,
+    /// PDF/A-2 and -3 allow it given an output intent (6.2.10), which the
+    /// embedded output always has.
+    Transparency(String),
+}
+
+impl PdfInputIssue {
+    /// Whether the finding keeps the output from being conformant PDF/A-3.
+    pub fn is_blocking(&self) -> bool {
+        !matches!(self, Self::Transparency(_))
+    }
+}
+
+impl std::fmt::Display for PdfInputIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Encrypted => write!(f, "document is encrypted"),
+            Self::FontNotEmbedded(name) => write!(f, "font {name} is not embedded"),
+            Self::Transparency(what) => write!(f, "transparency: {what}"),
+        }
+    }
+}
+
+/// How an input PDF relates to PDF/A-3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfInputKind {
+    /// Already PDF/A-3; the conformance level is kept.
+    PdfA3,
+    /// PDF/A-1 or PDF/A-2; declared PDF/A-3 with the same conformance level.
+    Upgradable,
+    /// Not PDF/A, but nothing found that would break PDF/A-3B.
+    Plain,
+    /// Has [blocking issues](PdfInputIssue::is_blocking); the output will
+    /// not be conformant PDF/A-3.
+    NonConformant,
+}
+
+/// What [`inspect_pdf`] found in an input PDF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfInputReport {
+    /// `pdfaid:part` declared in the XMP metadata.
+    pub pdfa_part: Option<u8>,
+    /// `pdfaid:conformance` declared in the XMP metadata.
+    pub pdfa_conformance: Option<String>,
+    /// Findings, including informational ones.
+    pub issues: Vec<PdfInputIssue>,
+}
+
+impl PdfInputReport {
+    /// Classify the input.
+    pub fn kind(&self) -> PdfInputKind {
+        match self.pdfa_part {
+            _ if self.issues.iter().any(PdfInputIssue::is_blocking) => PdfInputKind::NonConformant,
+            Some(3) => PdfInputKind::PdfA3,
+            Some(1 | 2) => PdfInputKind::Upgradable,
+            _ => PdfInputKind::Plain,
+        }
+    }
+
+    /// The PDF/A-3 conformance level the output is declared with: the
+    /// input's level for PDF/A inputs (`A`, `B` or `U`), otherwise `B`.
+    pub fn output_conformance(&self) -> &str {
+        match (self.pdfa_part, self.pdfa_conformance.as_deref()) {
+            (Some(1), Some(level @ ("A" | "B")))
+            | (Some(2 | 3), Some(level @ ("A" | "B" | "U"))) => level,
+            _ => "B",
+        }
+    }
+}
+
+/// Inspect a PDF before embedding ZUGFeRD XML into it.
+///
+/// Reports the declared PDF/A part and conformance level and the problems
+/// that keep [`embed_in_pdf`](super::embed_in_pdf) from producing a
+/// conformant PDF/A-3: encryption and fonts that are not embedded.
+/// Transparency is reported as well, but does not block.
+pub fn inspect_pdf(pdf_bytes: &[u8]) -> Result<PdfInputReport, RechnungError> {
+    let doc = Document::load_mem(pdf_bytes)
+        .map_err(|e| RechnungError::Builder(format!("failed to load PDF: {e}")))?;
+    Ok(inspect_document(&doc))
+}
+
+pub(crate) fn inspect_document(doc: &Document) -> PdfInputReport {
+    let xmp = read_xmp_string(doc).map(|x| parse_xmp(&x));
+    let mut issues = Vec::new();
+    if doc.is_encrypted() {
+        issues.push(PdfInputIssue::Encrypted);
+    }
+
+    for obj in doc.objects.values() {
+        let dict = match obj {
+            Object::Dictionary(d) => d,
+            Object::Stream(s) => &s.dict,
+            _ => continue,
+        };
+        let issue = if is_name(dict, b"Type", b"Font") {
+            font_issue(doc, dict)
+        } else {
+            transparency_issue(dict)
+        };
+        if let Some(issue) = issue {
+            if !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+    }
+
+    PdfInputReport {
+        pdfa_part: xmp.as_ref().and_then(|x| x.pdfa_part),
+        pdfa_conformance: xmp
+            .and_then(|x| x.pdfa_conformance)
+            .map(|c| c.to_ascii_uppercase()),
+        issues,
+    }
+}
+
+fn font_issue(doc: &Document, font: &Dictionary) -> Option<PdfInputIssue> {
+    // Type 3 glyphs are content streams, nothing to embed
+    if is_name(font, b"Subtype", b"Type3") {
+        return None;
+    }
+    let described = if is_name(font, b"Subtype", b"Type0") {
+        font.get(b"DescendantFonts")
+            .ok()
+            .and_then(|d| resolve(doc, d).as_array().ok())
+            .and_then(|fonts| fonts.first())
+            .and_then(|f| resolve(doc, f).as_dict().ok())
+    } else {
+        Some(font)
+    };
+    let embedded = described
+        .and_then(|f| f.get(b"FontDescriptor").ok())
+        .and_then(|d| resolve(doc, d).as_dict().ok())
+        .is_some_and(|d| d.has(b"FontFile") || d.has(b"FontFile2") || d.has(b"FontFile3"));
+    if embedded {
+        return None;
+    }
+    let name = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name_str)
+        .unwrap_or("(unnamed)");
+    Some(PdfInputIssue::FontNotEmbedded(name.to_string()))
+}
+
+fn transparency_issue(dict: &Dictionary) -> Option<PdfInputIssue> {
+    if let Ok(mask) = dict.get(b"SMask") {
+        if mask.as_name().ok() != Some(b"None".as_slice()) {
+            return Some(PdfInputIssue::Transparency("soft mask".into()));
+        }
+    }
+    for key in [b"CA".as_slice(), b"ca"] {
+        if let Ok(alpha) = dict.get(key).and_then(Object::as_float) {
+            if alpha < 1.0 {
+                return Some(PdfInputIssue::Transparency("constant alpha below 1".into()));
+            }
+        }
+    }
+    match dict.get(b"BM").and_then(Object::as_name_str) {
+        Ok("Normal" | "Compatible") | Err(_) => None,
+        Ok(mode) => Some(PdfInputIssue::Transparency(format!("blend mode {mode}"))),
+    }
+}
+
+pub(super) fn is_name(dict: &Dictionary, key: &[u8], name: &[u8]) -> bool {
+    dict.get(key).and_then(Object::as_name).ok() == Some(name)
+}
+
+pub(super) fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> &'a Object {
+    match obj {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(obj),
+        other => other,
+    }
+}
+
+/// The catalog's XMP metadata stream as text.
+pub(crate) fn read_xmp_string(doc: &Document) -> Option<String> {
+    let catalog = doc.catalog().ok()?;
+    let stream = resolve(doc, catalog.get(b"Metadata").ok()?)
+        .as_stream()
+        .ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// The document information dictionary.
+pub(crate) fn read_info(doc: &Document) -> DocInfo {
+    let Some(info) = doc
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(|i| resolve(doc, i).as_dict().ok())
+    else {
+        return DocInfo::default();
+    };
+    let text = |key: &[u8]| {
+        info.get(key)
+            .ok()
+            .and_then(obj_to_string)
+            .filter(|s| !s.trim().is_empty())
+    };
+    DocInfo {
+        title: text(b"Title"),
+        author: text(b"Author"),
+        subject: text(b"Subject"),
+        keywords: text(b"Keywords"),
+        creator: text(b"Creator"),
+        producer: text(b"Producer"),
+        created: text(b"CreationDate").and_then(|d| parse_pdf_date(&d)),
+        modified: text(b"ModDate").and_then(|d| parse_pdf_date(&d)),
+    }
+}
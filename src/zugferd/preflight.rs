@@ -0,0 +1,477 @@
+use lopdf::{Dictionary, Document, Object};
+
+use super::attachment::AF_RELATIONSHIPS;
+use super::extract::{embedded_file_specs, obj_to_string, read_document};
+use super::pdfa::{self, PdfInputIssue, is_name, resolve};
+use super::xmp::{invoice_schema_described, parse_doc_info, parse_xmp};
+
+/// Actions PDF/A-3 does not permit (ISO 19005-3, 6.5.1).
+const FORBIDDEN_ACTIONS: [&str; 11] = [
+    "Launch",
+    "Sound",
+    "Movie",
+    "ResetForm",
+    "ImportData",
+    "Hide",
+    "SetOCGState",
+    "Rendition",
+    "Trans",
+    "GoTo3DView",
+    "JavaScript",
+];
+
+const FACTURX: &str = "Factur-X";
+
+/// A PDF/A-3 or Factur-X rule a PDF violates, found by [`preflight`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfaIssue {
+    /// The rule: an ISO 19005-3 clause such as `ISO 19005-3 6.1.2`, or
+    /// `Factur-X` for the rules on the embedded invoice.
+    pub clause: &'static str,
+    /// Human-readable description of the finding.
+    pub message: String,
+}
+
+impl PdfaIssue {
+    fn new(clause: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            clause,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for PdfaIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.clause, self.message)
+    }
+}
+
+/// Check a ZUGFeRD / Factur-X PDF against the PDF/A-3 and Factur-X rules
+/// that matter in practice.
+///
+/// - binary header comment (ISO 19005-3, 6.1.2), trailer `ID` and no
+///   encryption (6.1.3)
+/// - a PDF/A output intent with an ICC profile (6.2.2)
+/// - embedded font programs (6.2.11.4.1)
+/// - no JavaScript or other forbidden actions (6.5.1) and no additional
+///   actions on the catalog or pages (6.5.2)
+/// - XMP metadata (6.6.2.1) with an extension schema for the invoice
+///   properties (6.6.2.3.1), matching the Info dictionary (6.6.3) and
+///   identifying PDF/A-3 (6.6.4)
+/// - `F`, `UF`, `AFRelationship`, MIME type and modification date of every
+///   embedded file, and an `AF` array referencing it (6.8)
+/// - an embedded invoice XML with `AFRelationship` `Data`, `Alternative` or
+///   `Source`, matching the Factur-X XMP properties
+///
+/// Returns an empty list if nothing was found. This is a pre-flight, not a
+/// full validator: colour spaces, content stream limits and the structure
+/// tree of PDF/A-3A are not checked.
+pub fn preflight(pdf_bytes: &[u8]) -> Vec<PdfaIssue> {
+    let mut issues = Vec::new();
+    check_header(pdf_bytes, &mut issues);
+    let doc = match Document::load_mem(pdf_bytes) {
+        Ok(doc) => doc,
+        Err(e) => {
+            issues.push(PdfaIssue::new(
+                "ISO 19005-3 6.1",
+                format!("file cannot be parsed: {e}"),
+            ));
+            return issues;
+        }
+    };
+
+    check_trailer(&doc, &mut issues);
+    check_output_intents(&doc, &mut issues);
+    for issue in pdfa::inspect_document(&doc).issues {
+        if let PdfInputIssue::FontNotEmbedded(name) = issue {
+            issues.push(PdfaIssue::new(
+                "ISO 19005-3 6.2.11.4.1",
+                format!("font {name} is not embedded"),
+            ));
+        }
+    }
+    check_actions(&doc, &mut issues);
+    check_metadata(&doc, &mut issues);
+    check_embedded_files(&doc, &mut issues);
+    check_invoice(&doc, &mut issues);
+    issues
+}
+
+/// `%PDF-1.n`, then a comment line starting with four bytes above 127.
+fn check_header(pdf: &[u8], issues: &mut Vec<PdfaIssue>) {
+    const CLAUSE: &str = "ISO 19005-3 6.1.2";
+    if !pdf.starts_with(b"%PDF-1.") {
+        issues.push(PdfaIssue::new(CLAUSE, "file does not start with %PDF-1.n"));
+        return;
+    }
+    let binary = pdf
+        .iter()
+        .position(|&b| b == b'\r' || b == b'\n')
+        .map(|eol| &pdf[eol..])
+        .and_then(|rest| rest.strip_prefix(b"\r\n").or_else(|| rest.get(1..)))
+        .and_then(|line| line.strip_prefix(b"%"))
+        .and_then(|comment| comment.get(..4))
+        .is_some_and(|bytes| bytes.iter().all(|&b| b > 127));
+    if !binary {
+        issues.push(PdfaIssue::new(
+            CLAUSE,
+            "header is not followed by a comment with four bytes above 127",
+        ));
+    }
+}
+
+fn check_trailer(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    const CLAUSE: &str = "ISO 19005-3 6.1.3";
+    if doc.is_encrypted() {
+        issues.push(PdfaIssue::new(CLAUSE, "document is encrypted"));
+    }
+    let has_id = doc
+        .trailer
+        .get(b"ID")
+        .and_then(|id| resolve(doc, id).as_array())
+        .is_ok_and(|ids| ids.len() == 2 && ids.iter().all(|id| id.as_str().is_ok()));
+    if !has_id {
+        issues.push(PdfaIssue::new(
+            CLAUSE,
+            "trailer has no ID array of two file identifiers",
+        ));
+    }
+}
+
+fn check_output_intents(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    const CLAUSE: &str = "ISO 19005-3 6.2.2";
+    let intents: Vec<&Dictionary> = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"OutputIntents").ok())
+        .and_then(|intents| resolve(doc, intents).as_array().ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|intent| resolve(doc, intent).as_dict().ok())
+        .filter(|intent| is_name(intent, b"S", b"GTS_PDFA1"))
+        .collect();
+    if intents.is_empty() {
+        issues.push(PdfaIssue::new(
+            CLAUSE,
+            "catalog has no GTS_PDFA1 output intent",
+        ));
+        return;
+    }
+
+    let mut profiles: Vec<&Object> = Vec::new();
+    for intent in intents {
+        let Ok(profile) = intent.get(b"DestOutputProfile") else {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                "output intent has no DestOutputProfile",
+            ));
+            continue;
+        };
+        let is_icc = resolve(doc, profile).as_stream().is_ok_and(|stream| {
+            let content = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            stream.dict.has(b"N") && content.get(36..40) == Some(b"acsp".as_slice())
+        });
+        if !is_icc {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                "DestOutputProfile is not an ICC profile stream",
+            ));
+        }
+        if !profiles.contains(&profile) {
+            profiles.push(profile);
+        }
+    }
+    if profiles.len() > 1 {
+        issues.push(PdfaIssue::new(
+            CLAUSE,
+            "output intents use different DestOutputProfile streams",
+        ));
+    }
+}
+
+fn check_actions(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    let mut found: Vec<String> = Vec::new();
+    for obj in doc.objects.values() {
+        visit_dicts(obj, &mut |dict| {
+            let Ok(action) = dict.get(b"S").and_then(Object::as_name_str) else {
+                return;
+            };
+            let is_action = !dict.has(b"Type") || is_name(dict, b"Type", b"Action");
+            if is_action
+                && FORBIDDEN_ACTIONS.contains(&action)
+                && !found.iter().any(|f| f == action)
+            {
+                found.push(action.to_string());
+            }
+        });
+    }
+    for action in found {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.5.1",
+            format!("{action} actions are not permitted"),
+        ));
+    }
+
+    let Ok(catalog) = doc.catalog() else {
+        return;
+    };
+    let has_javascript = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(|names| resolve(doc, names).as_dict().ok())
+        .is_some_and(|names| names.has(b"JavaScript"));
+    if has_javascript {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.5.1",
+            "name dictionary contains JavaScript",
+        ));
+    }
+    if catalog.has(b"AA") {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.5.2",
+            "catalog contains additional actions (AA)",
+        ));
+    }
+    if doc
+        .page_iter()
+        .any(|id| doc.get_dictionary(id).is_ok_and(|page| page.has(b"AA")))
+    {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.5.2",
+            "page contains additional actions (AA)",
+        ));
+    }
+}
+
+fn check_metadata(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    let Some(xmp) = pdfa::read_xmp_string(doc) else {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.6.2.1",
+            "catalog has no XMP metadata stream",
+        ));
+        return;
+    };
+
+    let meta = parse_xmp(&xmp);
+    let conformance = meta
+        .pdfa_conformance
+        .as_deref()
+        .map(str::to_ascii_uppercase);
+    match (meta.pdfa_part, conformance.as_deref()) {
+        (Some(3), Some("A" | "B" | "U")) => {}
+        (Some(3), level) => issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.6.4",
+            format!(
+                "invalid pdfaid:conformance {}",
+                level.unwrap_or("(missing)")
+            ),
+        )),
+        (part, _) => issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.6.4",
+            format!(
+                "XMP does not identify the file as PDF/A-3 (pdfaid:part {})",
+                part.map_or_else(|| "missing".to_string(), |p| p.to_string())
+            ),
+        )),
+    }
+
+    if invoice_schema_described(&xmp) == Some(false) {
+        issues.push(PdfaIssue::new(
+            "ISO 19005-3 6.6.2.3.1",
+            "the invoice XMP properties are not described by an extension schema",
+        ));
+    }
+
+    let info = pdfa::read_info(doc);
+    let from_xmp = parse_doc_info(&xmp);
+    let texts = [
+        ("Title", "dc:title", &info.title, &from_xmp.title),
+        ("Author", "dc:creator", &info.author, &from_xmp.author),
+        (
+            "Subject",
+            "dc:description",
+            &info.subject,
+            &from_xmp.subject,
+        ),
+        (
+            "Keywords",
+            "pdf:Keywords",
+            &info.keywords,
+            &from_xmp.keywords,
+        ),
+        (
+            "Creator",
+            "xmp:CreatorTool",
+            &info.creator,
+            &from_xmp.creator,
+        ),
+        (
+            "Producer",
+            "pdf:Producer",
+            &info.producer,
+            &from_xmp.producer,
+        ),
+    ];
+    for (key, property, info_value, xmp_value) in texts {
+        if let Some(value) = info_value {
+            if xmp_value.as_deref() != Some(value.trim()) {
+                issues.push(PdfaIssue::new(
+                    "ISO 19005-3 6.6.3",
+                    format!("Info {key} '{value}' does not match XMP {property}"),
+                ));
+            }
+        }
+    }
+    let dates = [
+        (
+            "CreationDate",
+            "xmp:CreateDate",
+            &info.created,
+            &from_xmp.created,
+        ),
+        (
+            "ModDate",
+            "xmp:ModifyDate",
+            &info.modified,
+            &from_xmp.modified,
+        ),
+    ];
+    for (key, property, info_value, xmp_value) in dates {
+        if let Some(value) = info_value {
+            if xmp_value.as_ref() != Some(value) {
+                issues.push(PdfaIssue::new(
+                    "ISO 19005-3 6.6.3",
+                    format!(
+                        "Info {key} {} does not match XMP {property}",
+                        value.to_rfc3339()
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn check_embedded_files(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    const CLAUSE: &str = "ISO 19005-3 6.8";
+    // File specifications referenced from any AF array (catalog, pages,
+    // annotations, …)
+    let mut associated: Vec<&Dictionary> = Vec::new();
+    for obj in doc.objects.values() {
+        visit_dicts(obj, &mut |dict| {
+            let Ok(af) = dict.get(b"AF").and_then(|af| resolve(doc, af).as_array()) else {
+                return;
+            };
+            associated.extend(
+                af.iter()
+                    .filter_map(|spec| resolve(doc, spec).as_dict().ok()),
+            );
+        });
+    }
+
+    for (key, spec) in embedded_file_specs(doc) {
+        let name = spec
+            .get(b"UF")
+            .or_else(|_| spec.get(b"F"))
+            .ok()
+            .and_then(obj_to_string)
+            .unwrap_or(key);
+        if !spec.has(b"F") || !spec.has(b"UF") {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("file specification of {name} lacks F or UF"),
+            ));
+        }
+        match spec.get(b"AFRelationship").and_then(Object::as_name_str) {
+            Ok(rel) if AF_RELATIONSHIPS.contains(&rel) => {}
+            Ok(rel) => issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("{name} has an invalid AFRelationship {rel}"),
+            )),
+            Err(_) => issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("{name} has no AFRelationship"),
+            )),
+        }
+        if !associated.iter().any(|af| std::ptr::eq(*af, spec)) {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("{name} is not referenced from an AF array"),
+            ));
+        }
+
+        let stream = spec
+            .get(b"EF")
+            .ok()
+            .and_then(|ef| resolve(doc, ef).as_dict().ok())
+            .and_then(|ef| ef.get(b"UF").or_else(|_| ef.get(b"F")).ok())
+            .and_then(|file| resolve(doc, file).as_stream().ok());
+        let Some(stream) = stream else {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("{name} has no embedded file stream"),
+            ));
+            continue;
+        };
+        if !stream.dict.has(b"Subtype") {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("embedded file {name} has no MIME type (Subtype)"),
+            ));
+        }
+        let has_mod_date = stream
+            .dict
+            .get(b"Params")
+            .ok()
+            .and_then(|params| resolve(doc, params).as_dict().ok())
+            .is_some_and(|params| params.has(b"ModDate"));
+        if !has_mod_date {
+            issues.push(PdfaIssue::new(
+                CLAUSE,
+                format!("embedded file {name} has no modification date (Params ModDate)"),
+            ));
+        }
+    }
+}
+
+fn check_invoice(doc: &Document, issues: &mut Vec<PdfaIssue>) {
+    let Ok(invoice) = read_document(doc) else {
+        issues.push(PdfaIssue::new(FACTURX, "no embedded invoice XML"));
+        return;
+    };
+    match invoice.af_relationship.as_deref() {
+        Some("Data" | "Alternative" | "Source") => {}
+        rel => issues.push(PdfaIssue::new(
+            FACTURX,
+            format!(
+                "{} has AFRelationship {}, expected Data, Alternative or Source",
+                invoice.filename,
+                rel.unwrap_or("(missing)")
+            ),
+        )),
+    }
+    for error in invoice.check_consistency() {
+        issues.push(PdfaIssue::new(FACTURX, error.message));
+    }
+}
+
+/// Call `f` for every dictionary in `obj`, including nested ones.
+fn visit_dicts<'a>(obj: &'a Object, f: &mut impl FnMut(&'a Dictionary)) {
+    match obj {
+        Object::Dictionary(dict) => {
+            f(dict);
+            dict.iter().for_each(|(_, value)| visit_dicts(value, f));
+        }
+        Object::Stream(stream) => {
+            f(&stream.dict);
+            stream
+                .dict
+                .iter()
+                .for_each(|(_, value)| visit_dicts(value, f));
+        }
+        Object::Array(items) => items.iter().for_each(|item| visit_dicts(item, f)),
+        _ => {}
+    }
+}
@@ -1,7 +1,8 @@
+use super::conformance::reduce;
 use crate::core::*;
 use crate::xrechnung;
 use crate::xrechnung::cii_ns;
-use crate::xrechnung::xml_utils::{XmlWriter, format_decimal};
+use crate::xrechnung::xml_utils::{XmlWriter, format_amount};
 use chrono::NaiveDate;
 
 /// ZUGFeRD / Factur-X conformance profile.
@@ -47,6 +48,32 @@ impl ZugferdProfile {
         }
     }
 
+    /// Profile for a `GuidelineSpecifiedDocumentContextParameter` URN.
+    ///
+    /// Accepts the Factur-X URNs returned by [`urn`](Self::urn), their
+    /// ZUGFeRD 2.0 (`urn:zugferd.de:2p0:…`) equivalents and any XRechnung
+    /// version. ZUGFeRD 1.0 URNs are not matched.
+    pub fn from_urn(urn: &str) -> Option<Self> {
+        let urn = urn.trim().to_ascii_lowercase();
+        if urn.contains("xeinkauf.de:kosit:xrechnung") {
+            return Some(Self::XRechnung);
+        }
+        if urn == "urn:cen.eu:en16931:2017" {
+            return Some(Self::EN16931);
+        }
+        if !(urn.contains("urn:factur-x.eu:1p0:") || urn.contains("urn:zugferd.de:2p0:")) {
+            return None;
+        }
+        match urn.rsplit(':').next()? {
+            "minimum" => Some(Self::Minimum),
+            "basicwl" => Some(Self::BasicWl),
+            "basic" => Some(Self::Basic),
+            "en16931" => Some(Self::EN16931),
+            "extended" => Some(Self::Extended),
+            _ => None,
+        }
+    }
+
     /// The AFRelationship value for the PDF FileSpec.
     pub fn af_relationship(&self) -> &'static str {
         match self {
@@ -58,15 +85,21 @@ impl ZugferdProfile {
 
 /// Generate ZUGFeRD/Factur-X CII XML for the given profile.
 ///
-/// For **Minimum** and **BasicWL** profiles, generates a reduced XML
-/// without line items (as required by the Factur-X specification).
-/// For **Basic** and above, generates the full CII XML with all line items.
+/// Data the profile cannot carry is left out, e.g. line items under
+/// **Minimum** and **BasicWL** (as required by the Factur-X specification)
+/// or item descriptions under **Basic**; [`dropped_fields`] lists it.
+///
+/// [`dropped_fields`]: super::dropped_fields
 pub fn to_xml(invoice: &Invoice, profile: ZugferdProfile) -> Result<String, RechnungError> {
+    let (invoice, _) = reduce(invoice, profile);
     match profile {
-        ZugferdProfile::Minimum => to_minimum_xml(invoice),
-        ZugferdProfile::BasicWl => to_basicwl_xml(invoice),
+        ZugferdProfile::Minimum => to_minimum_xml(&invoice),
+        ZugferdProfile::Extended => {
+            let xml = xrechnung::write_cii_xml(&invoice, true)?;
+            Ok(xml.replace(xrechnung::XRECHNUNG_CUSTOMIZATION_ID, profile.urn()))
+        }
         _ => {
-            let xml = xrechnung::to_cii_xml(invoice)?;
+            let xml = xrechnung::to_cii_xml(&invoice)?;
             Ok(xml.replace(xrechnung::XRECHNUNG_CUSTOMIZATION_ID, profile.urn()))
         }
     }
@@ -108,17 +141,36 @@ fn to_minimum_xml(invoice: &Invoice) -> Result<String, RechnungError> {
     // SupplyChainTradeTransaction (no line items for Minimum)
     w.start_element("rsm:SupplyChainTradeTransaction")?;
 
-    // ApplicableHeaderTradeAgreement — seller/buyer name only
+    // ApplicableHeaderTradeAgreement — names, legal and tax registrations,
+    // seller country and the purchase order reference
     w.start_element("ram:ApplicableHeaderTradeAgreement")?;
     if let Some(br) = &invoice.buyer_reference {
         w.text_element("ram:BuyerReference", br)?;
     }
+    let seller = &invoice.seller;
     w.start_element("ram:SellerTradeParty")?;
-    w.text_element("ram:Name", &invoice.seller.name)?;
+    w.text_element("ram:Name", &seller.name)?;
+    write_legal_organization(&mut w, seller)?;
+    w.start_element("ram:PostalTradeAddress")?;
+    w.text_element("ram:CountryID", &seller.address.country_code)?;
+    w.end_element("ram:PostalTradeAddress")?;
+    for (id, scheme) in [(&seller.vat_id, "VA"), (&seller.tax_number, "FC")] {
+        if let Some(id) = id {
+            w.start_element("ram:SpecifiedTaxRegistration")?;
+            w.text_element_with_attrs("ram:ID", id, &[("schemeID", scheme)])?;
+            w.end_element("ram:SpecifiedTaxRegistration")?;
+        }
+    }
     w.end_element("ram:SellerTradeParty")?;
     w.start_element("ram:BuyerTradeParty")?;
     w.text_element("ram:Name", &invoice.buyer.name)?;
+    write_legal_organization(&mut w, &invoice.buyer)?;
     w.end_element("ram:BuyerTradeParty")?;
+    if let Some(or) = &invoice.order_reference {
+        w.start_element("ram:BuyerOrderReferencedDocument")?;
+        w.text_element("ram:IssuerAssignedID", or)?;
+        w.end_element("ram:BuyerOrderReferencedDocument")?;
+    }
     w.end_element("ram:ApplicableHeaderTradeAgreement")?;
 
     // ApplicableHeaderTradeDelivery (empty)
@@ -129,136 +181,26 @@ fn to_minimum_xml(invoice: &Invoice) -> Result<String, RechnungError> {
     w.start_element("ram:ApplicableHeaderTradeSettlement")?;
     w.text_element("ram:InvoiceCurrencyCode", currency)?;
     w.start_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
-    w.text_element("ram:TaxBasisTotalAmount", &format_decimal(totals.net_total))?;
+    w.text_element(
+        "ram:TaxBasisTotalAmount",
+        &format_amount(totals.net_total, currency),
+    )?;
     w.text_element_with_attrs(
         "ram:TaxTotalAmount",
-        &format_decimal(totals.vat_total),
+        &format_amount(totals.vat_total, currency),
         &[("currencyID", currency.as_str())],
     )?;
-    w.text_element("ram:GrandTotalAmount", &format_decimal(totals.gross_total))?;
-    w.text_element("ram:DuePayableAmount", &format_decimal(totals.amount_due))?;
-    w.end_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
-    w.end_element("ram:ApplicableHeaderTradeSettlement")?;
-
-    w.end_element("rsm:SupplyChainTradeTransaction")?;
-    w.end_element("rsm:CrossIndustryInvoice")?;
-
-    w.into_string()
-}
-
-/// Generate BasicWL profile CII XML (full party/settlement data, no line items).
-fn to_basicwl_xml(invoice: &Invoice) -> Result<String, RechnungError> {
-    let totals = invoice.totals.as_ref().ok_or_else(|| {
-        RechnungError::Builder("totals must be calculated before XML generation".into())
-    })?;
-
-    let currency = &invoice.currency_code;
-    let mut w = XmlWriter::new()?;
-
-    w.start_element_with_attrs(
-        "rsm:CrossIndustryInvoice",
-        &[
-            ("xmlns:rsm", cii_ns::RSM),
-            ("xmlns:ram", cii_ns::RAM),
-            ("xmlns:qdt", cii_ns::QDT),
-            ("xmlns:udt", cii_ns::UDT),
-        ],
-    )?;
-
-    // ExchangedDocumentContext
-    w.start_element("rsm:ExchangedDocumentContext")?;
-    w.start_element("ram:GuidelineSpecifiedDocumentContextParameter")?;
-    w.text_element("ram:ID", ZugferdProfile::BasicWl.urn())?;
-    w.end_element("ram:GuidelineSpecifiedDocumentContextParameter")?;
-    w.end_element("rsm:ExchangedDocumentContext")?;
-
-    // ExchangedDocument
-    w.start_element("rsm:ExchangedDocument")?;
-    w.text_element("ram:ID", &invoice.number)?;
-    w.text_element("ram:TypeCode", &invoice.type_code.code().to_string())?;
-    write_cii_date(&mut w, "ram:IssueDateTime", &invoice.issue_date)?;
-    for note in &invoice.notes {
-        w.start_element("ram:IncludedNote")?;
-        w.text_element("ram:Content", note)?;
-        w.end_element("ram:IncludedNote")?;
-    }
-    w.end_element("rsm:ExchangedDocument")?;
-
-    // SupplyChainTradeTransaction (no line items for BasicWL)
-    w.start_element("rsm:SupplyChainTradeTransaction")?;
-
-    // ApplicableHeaderTradeAgreement — full party details
-    w.start_element("ram:ApplicableHeaderTradeAgreement")?;
-    if let Some(br) = &invoice.buyer_reference {
-        w.text_element("ram:BuyerReference", br)?;
-    }
-    write_cii_party(&mut w, &invoice.seller, "ram:SellerTradeParty")?;
-    write_cii_party(&mut w, &invoice.buyer, "ram:BuyerTradeParty")?;
-    w.end_element("ram:ApplicableHeaderTradeAgreement")?;
-
-    // ApplicableHeaderTradeDelivery
-    w.start_element("ram:ApplicableHeaderTradeDelivery")?;
-    if let Some(tpd) = &invoice.tax_point_date {
-        w.start_element("ram:ActualDeliverySupplyChainEvent")?;
-        write_cii_date(&mut w, "ram:OccurrenceDateTime", tpd)?;
-        w.end_element("ram:ActualDeliverySupplyChainEvent")?;
-    }
-    w.end_element("ram:ApplicableHeaderTradeDelivery")?;
-
-    // ApplicableHeaderTradeSettlement — full settlement with VAT breakdown
-    w.start_element("ram:ApplicableHeaderTradeSettlement")?;
-    w.text_element("ram:InvoiceCurrencyCode", currency)?;
-
-    // Payment means
-    if let Some(payment) = &invoice.payment {
-        w.start_element("ram:SpecifiedTradeSettlementPaymentMeans")?;
-        w.text_element("ram:TypeCode", &payment.means_code.code().to_string())?;
-        if let Some(ct) = &payment.credit_transfer {
-            w.start_element("ram:PayeePartyCreditorFinancialAccount")?;
-            w.text_element("ram:IBANID", &ct.iban)?;
-            w.end_element("ram:PayeePartyCreditorFinancialAccount")?;
-        }
-        w.end_element("ram:SpecifiedTradeSettlementPaymentMeans")?;
-    }
-
-    // VAT breakdown
-    for bd in &totals.vat_breakdown {
-        w.start_element("ram:ApplicableTradeTax")?;
-        w.text_element("ram:CalculatedAmount", &format_decimal(bd.tax_amount))?;
-        w.text_element("ram:TypeCode", "VAT")?;
-        w.text_element("ram:BasisAmount", &format_decimal(bd.taxable_amount))?;
-        w.text_element("ram:CategoryCode", bd.category.code())?;
-        w.text_element("ram:RateApplicablePercent", &format_decimal(bd.rate))?;
-        w.end_element("ram:ApplicableTradeTax")?;
-    }
-
-    // Payment terms
-    if let Some(terms) = &invoice.payment_terms {
-        w.start_element("ram:SpecifiedTradePaymentTerms")?;
-        w.text_element("ram:Description", terms)?;
-        if let Some(due) = &invoice.due_date {
-            write_cii_date(&mut w, "ram:DueDateDateTime", due)?;
-        }
-        w.end_element("ram:SpecifiedTradePaymentTerms")?;
-    }
-
-    // Monetary summation
-    w.start_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
     w.text_element(
-        "ram:LineTotalAmount",
-        &format_decimal(totals.line_net_total),
+        "ram:GrandTotalAmount",
+        &format_amount(totals.gross_total, currency),
     )?;
-    w.text_element("ram:TaxBasisTotalAmount", &format_decimal(totals.net_total))?;
-    w.text_element_with_attrs(
-        "ram:TaxTotalAmount",
-        &format_decimal(totals.vat_total),
-        &[("currencyID", currency.as_str())],
+    w.text_element(
+        "ram:DuePayableAmount",
+        &format_amount(totals.amount_due, currency),
     )?;
-    w.text_element("ram:GrandTotalAmount", &format_decimal(totals.gross_total))?;
-    w.text_element("ram:DuePayableAmount", &format_decimal(totals.amount_due))?;
     w.end_element("ram:SpecifiedTradeSettlementHeaderMonetarySummation")?;
-
     w.end_element("ram:ApplicableHeaderTradeSettlement")?;
+
     w.end_element("rsm:SupplyChainTradeTransaction")?;
     w.end_element("rsm:CrossIndustryInvoice")?;
 
@@ -276,37 +218,15 @@ fn write_cii_date(w: &mut XmlWriter, element: &str, date: &NaiveDate) -> Result<
     Ok(())
 }
 
-fn write_cii_party(w: &mut XmlWriter, party: &Party, element: &str) -> Result<(), RechnungError> {
-    w.start_element(element)?;
-    w.text_element("ram:Name", &party.name)?;
-
-    if let Some(vat_id) = &party.vat_id {
-        w.start_element("ram:SpecifiedTaxRegistration")?;
-        w.text_element_with_attrs("ram:ID", vat_id, &[("schemeID", "VA")])?;
-        w.end_element("ram:SpecifiedTaxRegistration")?;
-    }
-
-    if let Some(tax_number) = &party.tax_number {
-        w.start_element("ram:SpecifiedTaxRegistration")?;
-        w.text_element_with_attrs("ram:ID", tax_number, &[("schemeID", "FC")])?;
-        w.end_element("ram:SpecifiedTaxRegistration")?;
-    }
-
-    w.start_element("ram:PostalTradeAddress")?;
-    w.text_element("ram:PostcodeCode", &party.address.postal_code)?;
-    if let Some(street) = &party.address.street {
-        w.text_element("ram:LineOne", street)?;
-    }
-    w.text_element("ram:CityName", &party.address.city)?;
-    w.text_element("ram:CountryID", &party.address.country_code)?;
-    w.end_element("ram:PostalTradeAddress")?;
-
-    if let Some(ea) = &party.electronic_address {
-        w.start_element("ram:URIUniversalCommunication")?;
-        w.text_element_with_attrs("ram:URIID", &ea.value, &[("schemeID", &ea.scheme)])?;
-        w.end_element("ram:URIUniversalCommunication")?;
+/// BT-30 / BT-47: legal registration identifier.
+fn write_legal_organization(w: &mut XmlWriter, party: &Party) -> Result<(), RechnungError> {
+    if let Some(reg_id) = &party.registration_id {
+        w.start_element("ram:SpecifiedLegalOrganization")?;
+        match &party.registration_scheme {
+            Some(scheme) => w.text_element_with_attrs("ram:ID", reg_id, &[("schemeID", scheme)])?,
+            None => w.text_element("ram:ID", reg_id)?,
+        };
+        w.end_element("ram:SpecifiedLegalOrganization")?;
     }
-
-    w.end_element(element)?;
     Ok(())
 }
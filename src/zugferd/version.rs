@@ -0,0 +1,100 @@
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use crate::core::{Invoice, RechnungError};
+use crate::xrechnung::{self, XmlSyntax};
+
+/// ZUGFeRD generation of an embedded invoice XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZugferdVersion {
+    /// ZUGFeRD 1.0: `CrossIndustryDocument` (CII D13B), usually embedded as
+    /// `ZUGFeRD-invoice.xml`.
+    V1,
+    /// ZUGFeRD 2.x / Factur-X: `CrossIndustryInvoice` (CII D16B).
+    V2,
+}
+
+/// ZUGFeRD 1.0 conformance profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZugferdV1Profile {
+    /// Basic: simple invoices, free-text notes carry the rest.
+    Basic,
+    /// Comfort: fully structured invoice data.
+    Comfort,
+    /// Extended: Comfort plus industry-specific data.
+    Extended,
+}
+
+impl ZugferdV1Profile {
+    /// The URN used in `GuidelineSpecifiedDocumentContextParameter`.
+    pub fn urn(&self) -> &'static str {
+        match self {
+            Self::Basic => "urn:ferd:CrossIndustryDocument:invoice:1p0:basic",
+            Self::Comfort => "urn:ferd:CrossIndustryDocument:invoice:1p0:comfort",
+            Self::Extended => "urn:ferd:CrossIndustryDocument:invoice:1p0:extended",
+        }
+    }
+
+    /// The XMP `zf:ConformanceLevel` value.
+    pub fn conformance_level(&self) -> &'static str {
+        match self {
+            Self::Basic => "BASIC",
+            Self::Comfort => "COMFORT",
+            Self::Extended => "EXTENDED",
+        }
+    }
+
+    /// Profile for a guideline URN, ignoring case.
+    pub fn from_urn(urn: &str) -> Option<Self> {
+        [Self::Basic, Self::Comfort, Self::Extended]
+            .into_iter()
+            .find(|p| p.urn().eq_ignore_ascii_case(urn.trim()))
+    }
+}
+
+/// Parse an embedded ZUGFeRD / Factur-X XML of either generation.
+///
+/// ZUGFeRD 1.0 documents (Basic, Comfort, Extended) are mapped into the same
+/// [`Invoice`] model as 2.x documents; the detected version is returned
+/// alongside. UBL input is rejected.
+pub fn from_xml(xml: &str) -> Result<(Invoice, ZugferdVersion), RechnungError> {
+    match xrechnung::from_xml(xml)? {
+        (invoice, XmlSyntax::Cii) => Ok((invoice, ZugferdVersion::V2)),
+        (invoice, XmlSyntax::CiiD13b) => Ok((invoice, ZugferdVersion::V1)),
+        _ => Err(RechnungError::Xml(
+            "not a ZUGFeRD document: expected CrossIndustryInvoice or CrossIndustryDocument".into(),
+        )),
+    }
+}
+
+/// Detect the ZUGFeRD generation from the root element.
+pub fn detect_version(xml: &str) -> Option<ZugferdVersion> {
+    match xrechnung::detect_syntax(xml)? {
+        XmlSyntax::Cii => Some(ZugferdVersion::V2),
+        XmlSyntax::CiiD13b => Some(ZugferdVersion::V1),
+        _ => None,
+    }
+}
+
+/// The `GuidelineSpecifiedDocumentContextParameter` ID of a CII document
+/// (either generation), identifying its profile.
+pub fn guideline_id(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut in_guideline = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"GuidelineSpecifiedDocumentContextParameter" => in_guideline = true,
+                b"ExchangedDocument" | b"HeaderExchangedDocument" => return None,
+                _ => {}
+            },
+            Event::Text(t) if in_guideline => {
+                return Some(t.unescape().ok()?.trim().to_string());
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
@@ -1,8 +1,652 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat};
+use quick_xml::NsReader;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::name::{Namespace, ResolveResult};
+
 use super::profile::ZugferdProfile;
 
+const PDFAID_NS: &[u8] = b"http://www.aiim.org/pdfa/ns/id/";
+
+/// ZUGFeRD / Factur-X and PDF/A identification read from a PDF's XMP metadata.
+///
+/// The invoice properties are read from any of the extension schemas in use
+/// (`fx` for Factur-X, `zf` for ZUGFeRD 1.0 and 2.0), written either as
+/// elements or as `rdf:Description` attributes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmpMetadata {
+    /// `ConformanceLevel`, e.g. `EN 16931` or `EXTENDED`.
+    pub conformance_level: Option<String>,
+    /// `DocumentType`, normally `INVOICE`.
+    pub document_type: Option<String>,
+    /// `DocumentFileName`, the name of the embedded invoice XML.
+    pub document_file_name: Option<String>,
+    /// `Version` of the extension schema, e.g. `1.0`.
+    pub version: Option<String>,
+    /// `pdfaid:part`, the PDF/A part (3 for ZUGFeRD).
+    pub pdfa_part: Option<u8>,
+    /// `pdfaid:conformance`, the PDF/A conformance level (`A`, `B` or `U`).
+    pub pdfa_conformance: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum XmpField {
+    ConformanceLevel,
+    DocumentType,
+    DocumentFileName,
+    Version,
+    Part,
+    Conformance,
+}
+
+/// Parse an XMP packet; unknown properties and malformed XML are ignored.
+pub(crate) fn parse_xmp(xmp: &str) -> XmpMetadata {
+    let mut reader = NsReader::from_str(xmp);
+    reader.config_mut().trim_text(true);
+    let mut meta = XmpMetadata::default();
+    let mut current = None;
+    while let Ok((ns, event)) = reader.read_resolved_event() {
+        let field = match &event {
+            Event::Start(e) => xmp_field(&ns, e.local_name().as_ref()),
+            _ => None,
+        };
+        match event {
+            Event::Start(e) | Event::Empty(e) => {
+                current = field;
+                for attr in e.attributes().flatten() {
+                    let (ns, local) = reader.resolve_attribute(attr.key);
+                    if let Some(field) = xmp_field(&ns, local.as_ref()) {
+                        if let Ok(value) = attr.unescape_value() {
+                            set_field(&mut meta, field, &value);
+                        }
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let (Some(field), Ok(text)) = (current.take(), t.unescape()) {
+                    set_field(&mut meta, field, &text);
+                }
+            }
+            Event::End(_) => current = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    meta
+}
+
+fn xmp_field(ns: &ResolveResult, local: &[u8]) -> Option<XmpField> {
+    let ResolveResult::Bound(Namespace(uri)) = ns else {
+        return None;
+    };
+    if *uri == PDFAID_NS {
+        return match local {
+            b"part" => Some(XmpField::Part),
+            b"conformance" => Some(XmpField::Conformance),
+            _ => None,
+        };
+    }
+    // urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#,
+    // urn:zugferd:pdfa:CrossIndustryDocument:invoice:2p0#,
+    // urn:ferd:pdfa:CrossIndustryDocument:invoice:1p0#
+    if !uri.windows(21).any(|w| w == b"CrossIndustryDocument") {
+        return None;
+    }
+    match local {
+        b"ConformanceLevel" => Some(XmpField::ConformanceLevel),
+        b"DocumentType" => Some(XmpField::DocumentType),
+        b"DocumentFileName" => Some(XmpField::DocumentFileName),
+        b"Version" => Some(XmpField::Version),
+        _ => None,
+    }
+}
+
+fn set_field(meta: &mut XmpMetadata, field: XmpField, value: &str) {
+    let value = value.trim().to_string();
+    match field {
+        XmpField::ConformanceLevel => meta.conformance_level = Some(value),
+        XmpField::DocumentType => meta.document_type = Some(value),
+        XmpField::DocumentFileName => meta.document_file_name = Some(value),
+        XmpField::Version => meta.version = Some(value),
+        XmpField::Part => meta.pdfa_part = value.parse().ok(),
+        XmpField::Conformance => meta.pdfa_conformance = Some(value),
+    }
+}
+
+const PDFA_SCHEMA_NS: &[u8] = b"http://www.aiim.org/pdfa/ns/schema#";
+
+/// Whether every namespace used for invoice properties (`fx`, `zf`) is
+/// described by a `pdfaExtension:schemas` entry, as PDF/A requires for
+/// properties outside the predefined schemas. `None` if the packet has no
+/// invoice properties.
+pub(crate) fn invoice_schema_described(xmp: &str) -> Option<bool> {
+    let mut reader = NsReader::from_str(xmp);
+    reader.config_mut().trim_text(true);
+    let mut used: Vec<Vec<u8>> = Vec::new();
+    let mut described: Vec<String> = Vec::new();
+    let mut in_namespace_uri = false;
+    let record = |ns: &ResolveResult, local: &[u8], used: &mut Vec<Vec<u8>>| {
+        let is_invoice = !matches!(
+            xmp_field(ns, local),
+            None | Some(XmpField::Part | XmpField::Conformance)
+        );
+        if let (true, ResolveResult::Bound(Namespace(uri))) = (is_invoice, ns) {
+            if !used.iter().any(|u| u == uri) {
+                used.push(uri.to_vec());
+            }
+        }
+        matches!(ns, ResolveResult::Bound(Namespace(PDFA_SCHEMA_NS))) && local == b"namespaceURI"
+    };
+    while let Ok((ns, event)) = reader.read_resolved_event() {
+        let is_start = matches!(event, Event::Start(_));
+        match event {
+            Event::Start(e) | Event::Empty(e) => {
+                in_namespace_uri = record(&ns, e.local_name().as_ref(), &mut used) && is_start;
+                for attr in e.attributes().flatten() {
+                    let (ns, local) = reader.resolve_attribute(attr.key);
+                    if record(&ns, local.as_ref(), &mut used) {
+                        if let Ok(value) = attr.unescape_value() {
+                            described.push(value.trim().to_string());
+                        }
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let (true, Ok(text)) = (in_namespace_uri, t.unescape()) {
+                    described.push(text.trim().to_string());
+                }
+            }
+            Event::End(_) => in_namespace_uri = false,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    (!used.is_empty()).then(|| {
+        used.iter()
+            .all(|uri| described.iter().any(|d| d.as_bytes() == uri.as_slice()))
+    })
+}
+
+/// Document information that PDF/A requires to be identical in the Info
+/// dictionary and the XMP metadata (ISO 19005-3, 6.6.2.3).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct DocInfo {
+    /// `Title` / `dc:title`
+    pub title: Option<String>,
+    /// `Author` / `dc:creator`
+    pub author: Option<String>,
+    /// `Subject` / `dc:description`
+    pub subject: Option<String>,
+    /// `Keywords` / `pdf:Keywords`
+    pub keywords: Option<String>,
+    /// `Creator` / `xmp:CreatorTool`
+    pub creator: Option<String>,
+    /// `Producer` / `pdf:Producer`
+    pub producer: Option<String>,
+    /// `CreationDate` / `xmp:CreateDate`
+    pub created: Option<DateTime<FixedOffset>>,
+    /// `ModDate` / `xmp:ModifyDate`
+    pub modified: Option<DateTime<FixedOffset>>,
+}
+
+impl DocInfo {
+    /// Fill the fields missing here from `other`.
+    pub fn or(self, other: DocInfo) -> DocInfo {
+        DocInfo {
+            title: self.title.or(other.title),
+            author: self.author.or(other.author),
+            subject: self.subject.or(other.subject),
+            keywords: self.keywords.or(other.keywords),
+            creator: self.creator.or(other.creator),
+            producer: self.producer.or(other.producer),
+            created: self.created.or(other.created),
+            modified: self.modified.or(other.modified),
+        }
+    }
+}
+
+const DC_NS: &[u8] = b"http://purl.org/dc/elements/1.1/";
+const PDF_NS: &[u8] = b"http://ns.adobe.com/pdf/1.3/";
+const XMP_NS: &[u8] = b"http://ns.adobe.com/xap/1.0/";
+const PDFA_EXTENSION_NS: &[u8] = b"http://www.aiim.org/pdfa/ns/extension/";
+const RDF_NS: &[u8] = b"http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// How an existing XMP property is treated when merging.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Property {
+    /// Rewritten from [`DocInfo`], the PDF/A identification or the
+    /// Factur-X schema
+    Managed(Option<DocField>),
+    /// `pdfaExtension:schemas`, merged with the Factur-X schema description
+    Schemas,
+    /// Kept as is
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DocField {
+    Title,
+    Author,
+    Subject,
+    Keywords,
+    Creator,
+    Producer,
+    Created,
+    Modified,
+}
+
+fn classify(ns: &ResolveResult, local: &[u8]) -> Property {
+    let ResolveResult::Bound(Namespace(uri)) = ns else {
+        return Property::Other;
+    };
+    let field = match (*uri, local) {
+        (DC_NS, b"title") => DocField::Title,
+        (DC_NS, b"creator") => DocField::Author,
+        (DC_NS, b"description") => DocField::Subject,
+        (PDF_NS, b"Keywords") => DocField::Keywords,
+        (PDF_NS, b"Producer") => DocField::Producer,
+        (XMP_NS, b"CreatorTool") => DocField::Creator,
+        (XMP_NS, b"CreateDate") => DocField::Created,
+        (XMP_NS, b"ModifyDate") => DocField::Modified,
+        (XMP_NS, b"MetadataDate") => return Property::Managed(None),
+        (PDFA_EXTENSION_NS, b"schemas") => return Property::Schemas,
+        _ if *uri == PDFAID_NS || xmp_field(ns, local).is_some() => {
+            return Property::Managed(None);
+        }
+        _ => return Property::Other,
+    };
+    Property::Managed(Some(field))
+}
+
+/// Read the document information properties from an XMP packet.
+pub(crate) fn parse_doc_info(xmp: &str) -> DocInfo {
+    let mut reader = NsReader::from_str(xmp);
+    reader.config_mut().trim_text(true);
+    let mut info = DocInfo::default();
+    // The property being read and its element depth
+    let mut current: Option<(DocField, usize)> = None;
+    let mut depth = 0;
+    while let Ok((ns, event)) = reader.read_resolved_event() {
+        let property = match &event {
+            Event::Start(e) => classify(&ns, e.local_name().as_ref()),
+            _ => Property::Other,
+        };
+        let is_start = matches!(event, Event::Start(_));
+        match event {
+            Event::Start(e) | Event::Empty(e) => {
+                if is_start {
+                    depth += 1;
+                    if let (Property::Managed(Some(field)), None) = (property, current) {
+                        current = Some((field, depth));
+                    }
+                }
+                for attr in e.attributes().flatten() {
+                    let (ns, local) = reader.resolve_attribute(attr.key);
+                    if let (Property::Managed(Some(field)), Ok(value)) =
+                        (classify(&ns, local.as_ref()), attr.unescape_value())
+                    {
+                        set_doc_field(&mut info, field, &value);
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let (Some((field, _)), Ok(text)) = (current, t.unescape()) {
+                    set_doc_field(&mut info, field, &text);
+                }
+            }
+            Event::End(_) => {
+                if current.is_some_and(|(_, d)| d == depth) {
+                    current = None;
+                }
+                depth -= 1;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    info
+}
+
+/// Set a field unless already set (the first `rdf:li` of a `dc:title`
+/// alternative or `dc:creator` sequence wins).
+fn set_doc_field(info: &mut DocInfo, field: DocField, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+    let text = |slot: &mut Option<String>| {
+        slot.get_or_insert_with(|| value.to_string());
+    };
+    match field {
+        DocField::Title => text(&mut info.title),
+        DocField::Author => text(&mut info.author),
+        DocField::Subject => text(&mut info.subject),
+        DocField::Keywords => text(&mut info.keywords),
+        DocField::Creator => text(&mut info.creator),
+        DocField::Producer => text(&mut info.producer),
+        DocField::Created => info.created = info.created.or(parse_xmp_date(value)),
+        DocField::Modified => info.modified = info.modified.or(parse_xmp_date(value)),
+    }
+}
+
+/// Parse an XMP (ISO 8601) date; a missing time zone is taken as UTC.
+fn parse_xmp_date(text: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date);
+    }
+    let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })?;
+    Some(naive.and_utc().fixed_offset())
+}
+
+/// Parts of an existing XMP packet that survive the merge.
+#[derive(Default)]
+struct Preserved {
+    /// Namespace declarations of `x:xmpmeta` and `rdf:RDF`, repeated on
+    /// every preserved description
+    ancestor_ns: Vec<(String, String)>,
+    /// `rdf:Description` elements with their unmanaged properties
+    descriptions: Vec<String>,
+    /// Extension schema `rdf:li` entries other than the Factur-X / ZUGFeRD ones
+    schemas: Vec<String>,
+    /// Namespace declarations in scope of the preserved schema entries
+    schema_ns: Vec<(String, String)>,
+}
+
+/// A description being copied.
+struct OpenDescription {
+    name: String,
+    attrs: Vec<(String, String)>,
+    keep: bool,
+    children: Vec<String>,
+}
+
+/// Collect the unmanaged properties and extension schemas of an existing
+/// XMP packet. Malformed XMP yields nothing, i.e. a fresh packet.
+fn preserve(xmp: &str) -> Preserved {
+    let mut reader = NsReader::from_str(xmp);
+    let mut out = Preserved::default();
+    let mut depth = 0;
+    let mut rdf_depth: Option<usize> = None;
+    let mut desc: Option<OpenDescription> = None;
+    // Element depth of a skipped managed property, `pdfaExtension:schemas`,
+    // and a raw capture (property or schema entry) with its start offset
+    let mut skip: Option<usize> = None;
+    let mut schemas: Option<usize> = None;
+    let mut capture: Option<(usize, usize)> = None;
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        let Ok((ns, event)) = reader.read_resolved_event() else {
+            return Preserved::default();
+        };
+        let (empty, e) = match event {
+            Event::Start(e) => (false, e),
+            Event::Empty(e) => (true, e),
+            Event::End(_) => {
+                let end = reader.buffer_position() as usize;
+                if let Some((d, from)) = capture.filter(|(d, _)| *d == depth) {
+                    capture = None;
+                    let raw = &xmp[from..end];
+                    if schemas.is_some() && d == rdf_depth.unwrap_or_default() + 4 {
+                        if !raw.contains("CrossIndustryDocument") {
+                            out.schemas.push(raw.to_string());
+                        }
+                    } else if let Some(desc) = desc.as_mut() {
+                        desc.children.push(raw.to_string());
+                    }
+                }
+                if skip == Some(depth) {
+                    skip = None;
+                }
+                if schemas == Some(depth) {
+                    schemas = None;
+                }
+                if rdf_depth.is_some_and(|r| depth == r + 1) {
+                    if let Some(desc) = desc.take() {
+                        out.descriptions
+                            .push(close_description(desc, &out.ancestor_ns));
+                    }
+                }
+                depth -= 1;
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        depth += 1;
+        let uri = match &ns {
+            ResolveResult::Bound(Namespace(uri)) => uri.to_vec(),
+            _ => Vec::new(),
+        };
+        let local = e.local_name().as_ref().to_vec();
+        let property = classify(&ns, &local);
+
+        if skip.is_some() || capture.is_some() {
+            // inside a skipped or captured subtree
+        } else if rdf_depth.is_none() {
+            out.ancestor_ns.extend(namespace_decls(&e));
+            if uri == RDF_NS && local == b"RDF" {
+                rdf_depth = Some(depth);
+            }
+        } else if rdf_depth.is_some_and(|r| depth == r + 1) {
+            let mut open = OpenDescription {
+                name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+                attrs: Vec::new(),
+                keep: false,
+                children: Vec::new(),
+            };
+            for attr in e.attributes().flatten() {
+                let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+                let value = String::from_utf8_lossy(&attr.value).replace('"', "&quot;");
+                if key == "xmlns" || key.starts_with("xmlns:") {
+                    open.attrs.push((key, value));
+                    continue;
+                }
+                let (ns, local) = reader.resolve_attribute(attr.key);
+                if classify(&ns, local.as_ref()) == Property::Other {
+                    open.keep |= key != "rdf:about";
+                    open.attrs.push((key, value));
+                }
+            }
+            if empty {
+                out.descriptions
+                    .push(close_description(open, &out.ancestor_ns));
+            } else {
+                desc = Some(open);
+            }
+        } else if rdf_depth.is_some_and(|r| depth == r + 2) {
+            match property {
+                Property::Managed(_) if !empty => skip = Some(depth),
+                Property::Managed(_) => {}
+                Property::Schemas if !empty => {
+                    schemas = Some(depth);
+                    if let Some(desc) = &desc {
+                        out.schema_ns.extend(
+                            desc.attrs
+                                .iter()
+                                .filter(|(k, _)| k.starts_with("xmlns"))
+                                .cloned(),
+                        );
+                    }
+                }
+                Property::Schemas => {}
+                Property::Other if empty => {
+                    let end = reader.buffer_position() as usize;
+                    if let Some(desc) = desc.as_mut() {
+                        desc.children.push(xmp[start..end].to_string());
+                    }
+                }
+                Property::Other => capture = Some((depth, start)),
+            }
+        } else if schemas.is_some() && rdf_depth.is_some_and(|r| depth == r + 4) && !empty {
+            // rdf:li of the rdf:Bag in pdfaExtension:schemas
+            capture = Some((depth, start));
+        }
+
+        if empty {
+            depth -= 1;
+        }
+    }
+    out
+}
+
+fn namespace_decls(e: &quick_xml::events::BytesStart) -> Vec<(String, String)> {
+    e.attributes()
+        .flatten()
+        .filter(|a| a.key.as_ref() == b"xmlns" || a.key.as_ref().starts_with(b"xmlns:"))
+        .map(|a| {
+            (
+                String::from_utf8_lossy(a.key.as_ref()).into_owned(),
+                String::from_utf8_lossy(&a.value).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Serialize a preserved description, or nothing if all its properties
+/// were managed.
+fn close_description(desc: OpenDescription, ancestor_ns: &[(String, String)]) -> String {
+    if !desc.keep && desc.children.is_empty() {
+        return String::new();
+    }
+    let mut attrs = desc.attrs;
+    for (key, value) in ancestor_ns {
+        if !attrs.iter().any(|(k, _)| k == key) && !is_wrapper_ns(key, value) {
+            attrs.push((key.clone(), value.clone()));
+        }
+    }
+    let mut out = format!("    <{}", desc.name);
+    for (i, (key, value)) in attrs.iter().enumerate() {
+        let sep = if i == 0 { " " } else { "\n        " };
+        out.push_str(&format!("{sep}{key}=\"{value}\""));
+    }
+    if desc.children.is_empty() {
+        out.push_str("/>\n");
+    } else {
+        out.push_str(">\n");
+        for child in &desc.children {
+            out.push_str(&format!("      {child}\n"));
+        }
+        out.push_str(&format!("    </{}>\n", desc.name));
+    }
+    out
+}
+
+/// Whether a namespace declaration is already made by the `x:xmpmeta` and
+/// `rdf:RDF` elements written by [`build_xmp`].
+fn is_wrapper_ns(key: &str, value: &str) -> bool {
+    matches!(
+        (key, value.as_bytes()),
+        ("xmlns:x", b"adobe:ns:meta/") | ("xmlns:rdf", RDF_NS)
+    )
+}
+
+/// Format a date for XMP (ISO 8601 with time zone).
+fn format_xmp_date(date: &DateTime<FixedOffset>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
 /// Build the XMP metadata block for a ZUGFeRD/Factur-X PDF/A-3 document.
-pub fn build_xmp(profile: ZugferdProfile) -> String {
-    let conformance_level = profile.conformance_level();
+///
+/// The document information in `info` is written as Dublin Core, PDF and
+/// XMP basic properties, declared as PDF/A-3 with the given `conformance`
+/// level (`A`, `B` or `U`). Properties and extension schemas of an
+/// `existing` packet that are not rewritten here are carried over.
+pub(crate) fn build_xmp(
+    profile: ZugferdProfile,
+    conformance: &str,
+    info: &DocInfo,
+    existing: Option<&str>,
+) -> String {
+    let preserved = existing.map(preserve).unwrap_or_default();
+    let esc = |s: &str| escape(s).into_owned();
+
+    let mut dc = String::new();
+    let title = info.title.as_deref().unwrap_or("ZUGFeRD Invoice");
+    dc.push_str(&format!(
+        "      <dc:title>\n        <rdf:Alt>\n          <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n        </rdf:Alt>\n      </dc:title>\n",
+        esc(title)
+    ));
+    if let Some(author) = &info.author {
+        dc.push_str(&format!(
+            "      <dc:creator>\n        <rdf:Seq>\n          <rdf:li>{}</rdf:li>\n        </rdf:Seq>\n      </dc:creator>\n",
+            esc(author)
+        ));
+    }
+    if let Some(subject) = &info.subject {
+        dc.push_str(&format!(
+            "      <dc:description>\n        <rdf:Alt>\n          <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n        </rdf:Alt>\n      </dc:description>\n",
+            esc(subject)
+        ));
+    }
+
+    let mut pdf = String::new();
+    if let Some(producer) = &info.producer {
+        pdf.push_str(&format!(
+            "      <pdf:Producer>{}</pdf:Producer>\n",
+            esc(producer)
+        ));
+    }
+    if let Some(keywords) = &info.keywords {
+        pdf.push_str(&format!(
+            "      <pdf:Keywords>{}</pdf:Keywords>\n",
+            esc(keywords)
+        ));
+    }
+
+    let mut xmp = String::new();
+    if let Some(creator) = &info.creator {
+        xmp.push_str(&format!(
+            "      <xmp:CreatorTool>{}</xmp:CreatorTool>\n",
+            esc(creator)
+        ));
+    }
+    if let Some(created) = &info.created {
+        xmp.push_str(&format!(
+            "      <xmp:CreateDate>{}</xmp:CreateDate>\n",
+            format_xmp_date(created)
+        ));
+    }
+    if let Some(modified) = &info.modified {
+        xmp.push_str(&format!(
+            "      <xmp:ModifyDate>{0}</xmp:ModifyDate>\n      <xmp:MetadataDate>{0}</xmp:MetadataDate>\n",
+            format_xmp_date(modified)
+        ));
+    }
+
+    let mut extension_ns = vec![
+        (
+            "xmlns:pdfaExtension",
+            "http://www.aiim.org/pdfa/ns/extension/",
+        ),
+        ("xmlns:pdfaSchema", "http://www.aiim.org/pdfa/ns/schema#"),
+        (
+            "xmlns:pdfaProperty",
+            "http://www.aiim.org/pdfa/ns/property#",
+        ),
+    ];
+    for (key, value) in preserved.schema_ns.iter().chain(&preserved.ancestor_ns) {
+        if !extension_ns.iter().any(|(k, _)| k == key) && !is_wrapper_ns(key, value) {
+            extension_ns.push((key, value));
+        }
+    }
+    let extension_ns: String = extension_ns
+        .iter()
+        .map(|(k, v)| format!("\n        {k}=\"{v}\""))
+        .collect();
+    let other_schemas: String = preserved
+        .schemas
+        .iter()
+        .map(|s| format!("          {s}\n"))
+        .collect();
+    let descriptions: String = preserved.descriptions.concat();
 
     format!(
         r#"<?xpacket begin="{BOM}" id="W5M0MpCehiHzreSzNTczkc9d"?>
@@ -11,23 +655,21 @@ pub fn build_xmp(profile: ZugferdProfile) -> String {
     <rdf:Description rdf:about=""
         xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/">
       <pdfaid:part>3</pdfaid:part>
-      <pdfaid:conformance>B</pdfaid:conformance>
+      <pdfaid:conformance>{conformance}</pdfaid:conformance>
     </rdf:Description>
     <rdf:Description rdf:about=""
         xmlns:dc="http://purl.org/dc/elements/1.1/">
-      <dc:title>
-        <rdf:Alt>
-          <rdf:li xml:lang="x-default">ZUGFeRD Invoice</rdf:li>
-        </rdf:Alt>
-      </dc:title>
-    </rdf:Description>
+{dc}    </rdf:Description>
+    <rdf:Description rdf:about=""
+        xmlns:pdf="http://ns.adobe.com/pdf/1.3/">
+{pdf}    </rdf:Description>
     <rdf:Description rdf:about=""
-        xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/"
-        xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#"
-        xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+{xmp}    </rdf:Description>
+{descriptions}    <rdf:Description rdf:about=""{extension_ns}>
       <pdfaExtension:schemas>
         <rdf:Bag>
-          <rdf:li rdf:parseType="Resource">
+{other_schemas}          <rdf:li rdf:parseType="Resource">
             <pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
             <pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
             <pdfaSchema:prefix>fx</pdfaSchema:prefix>
@@ -74,6 +716,6 @@ pub fn build_xmp(profile: ZugferdProfile) -> String {
 </x:xmpmeta>
 <?xpacket end="w"?>"#,
         BOM = '\u{FEFF}',
-        conformance_level = conformance_level,
+        conformance_level = profile.conformance_level(),
     )
 }
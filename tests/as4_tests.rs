@@ -0,0 +1,474 @@
+#![cfg(feature = "as4")]
+
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+use chrono::NaiveDate;
+use faktura::core::*;
+use faktura::peppol::as4::*;
+use faktura::peppol::smp::{CertificateInfo, Endpoint, StdHttpClient, TRANSPORT_AS4};
+use faktura::peppol::{PEPPOL_INVOICE_DOCTYPE, ParticipantId};
+use rust_decimal_macros::dec;
+
+const AP1_KEY: &str = include_str!("fixtures/as4/ap1.key");
+const AP1_CERT: &str = include_str!("fixtures/as4/ap1.crt");
+const AP2_KEY: &str = include_str!("fixtures/as4/ap2.key");
+const AP2_CERT: &str = include_str!("fixtures/as4/ap2.crt");
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn invoice() -> Invoice {
+    InvoiceBuilder::new("AS4-001", date(2024, 6, 15))
+        .tax_point_date(date(2024, 6, 15))
+        .buyer_reference("BR-123")
+        .seller(
+            PartyBuilder::new(
+                "Seller GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .electronic_address("9930", "DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Buyer AG",
+                AddressBuilder::new("München", "80331", "DE").build(),
+            )
+            .electronic_address("0204", "991-12345-73")
+            .build(),
+        )
+        .due_date(date(2024, 7, 15))
+        .add_line(
+            LineItemBuilder::new("1", "Consulting services", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+fn sender() -> As4Identity {
+    As4Identity::from_pem(AP1_KEY, AP1_CERT).unwrap()
+}
+
+fn receiver() -> As4Identity {
+    As4Identity::from_pem(AP2_KEY, AP2_CERT).unwrap()
+}
+
+fn receiver_certificate() -> CertificateInfo {
+    CertificateInfo::from_pem(AP2_CERT).unwrap()
+}
+
+/// In-process AS4 stand-in: hands the request to an [`As4Receiver`] and
+/// keeps what it received. `tamper` rewrites the request, `answer` the
+/// response.
+struct StandIn {
+    receiver: As4Receiver,
+    received: RefCell<Vec<Result<ReceivedMessage, As4Error>>>,
+    tamper: fn(Vec<u8>) -> Vec<u8>,
+    answer: fn(Vec<u8>) -> Vec<u8>,
+}
+
+impl StandIn {
+    fn new() -> Self {
+        Self {
+            receiver: As4Receiver::new(receiver()),
+            received: RefCell::new(Vec::new()),
+            tamper: |body| body,
+            answer: |body| body,
+        }
+    }
+}
+
+impl As4Transport for StandIn {
+    fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+    ) -> Result<TransportResponse, As4Error> {
+        assert_eq!(url, "http://ap2.example.org/as4");
+        let reception = self
+            .receiver
+            .handle(content_type, &(self.tamper)(body.to_vec()));
+        self.received.borrow_mut().push(reception.message);
+        Ok(TransportResponse {
+            status: reception.status,
+            content_type: Some(reception.response.content_type),
+            body: (self.answer)(reception.response.body),
+        })
+    }
+}
+
+fn replace(body: Vec<u8>, from: &[u8], to: &[u8]) -> Vec<u8> {
+    let i = body
+        .windows(from.len())
+        .position(|w| w == from)
+        .expect("pattern present");
+    [&body[..i], to, &body[i + from.len()..]].concat()
+}
+
+#[test]
+fn identity_from_pem() {
+    let identity = sender();
+    assert_eq!(identity.party_id(), "PDE000001");
+    assert_eq!(identity.certificate().serial, "1001");
+    assert!(matches!(
+        As4Identity::from_pem(AP1_KEY, AP2_CERT),
+        Err(As4Error::Security(_))
+    ));
+    assert!(As4Identity::from_pem("no key", AP1_CERT).is_err());
+}
+
+#[test]
+fn user_message_from_invoice() {
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    assert_eq!(message.document_type(), PEPPOL_INVOICE_DOCTYPE);
+    assert_eq!(
+        message.action,
+        format!("busdox-docid-qns::{PEPPOL_INVOICE_DOCTYPE}")
+    );
+    assert_eq!(message.service_type, "cenbii-procid-ubl");
+    assert_eq!(
+        message.service,
+        "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0"
+    );
+    assert_eq!(
+        message.original_sender,
+        ParticipantId::new("9930", "DE123456789")
+    );
+    assert_eq!(
+        message.final_recipient,
+        ParticipantId::new("0204", "991-12345-73")
+    );
+    assert!(message.message_id.ends_with("@faktura"));
+    assert!(message.payload_xml().unwrap().contains("AS4-001"));
+}
+
+#[test]
+fn built_message_is_signed_and_encrypted() {
+    let client = As4Client::new(sender(), StandIn::new());
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let mime = client.build(&message, &receiver_certificate()).unwrap();
+
+    assert!(mime.content_type.starts_with("multipart/related;"));
+    assert!(mime.content_type.contains("type=\"application/soap+xml\""));
+    let text = String::from_utf8_lossy(&mime.body);
+    for expected in [
+        "<eb:PartyId type=\"urn:fdc:peppol.eu:2017:identifiers:ap\">PDE000001</eb:PartyId>",
+        "<eb:PartyId type=\"urn:fdc:peppol.eu:2017:identifiers:ap\">PDE000002</eb:PartyId>",
+        "<eb:Service type=\"cenbii-procid-ubl\">urn:fdc:peppol.eu:2017:poacc:billing:01:1.0</eb:Service>",
+        "<eb:Property name=\"finalRecipient\" type=\"iso6523-actorid-upis\">0204:991-12345-73</eb:Property>",
+        "<eb:Property name=\"CompressionType\">application/gzip</eb:Property>",
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256",
+        "http://www.w3.org/2009/xmlenc11#aes128-gcm",
+        "http://www.w3.org/2009/xmlenc11#rsa-oaep",
+        "Attachment-Content-Signature-Transform",
+        "<ds:X509SerialNumber>4098</ds:X509SerialNumber>",
+    ] {
+        assert!(text.contains(expected), "missing {expected}");
+    }
+    // The invoice travels encrypted only
+    assert!(!text.contains("AS4-001"));
+}
+
+#[test]
+fn send_and_receive_with_receipt() {
+    let client = As4Client::new(sender(), StandIn::new());
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let receipt = client
+        .send_to(
+            &message,
+            "http://ap2.example.org/as4",
+            &receiver_certificate(),
+        )
+        .unwrap();
+
+    assert_eq!(receipt.ref_to_message_id, message.message_id);
+    assert_ne!(receipt.message_id, message.message_id);
+    assert_eq!(receipt.references.len(), 3);
+    assert!(receipt.references.iter().any(|r| r.starts_with("cid:")));
+
+    let received = client_transport_received(&client);
+    let received = received.as_ref().unwrap();
+    assert_eq!(received.from_party, "PDE000001");
+    assert_eq!(received.to_party, "PDE000002");
+    assert_eq!(received.sender_certificate.common_name(), Some("PDE000001"));
+    assert_eq!(received.message.message_id, message.message_id);
+    assert_eq!(received.message.payload, message.payload);
+    assert_eq!(received.message.original_sender, message.original_sender);
+    assert_eq!(received.message.action, message.action);
+
+    let (header, parsed) = received.document().unwrap();
+    assert_eq!(header.receiver, ParticipantId::new("0204", "991-12345-73"));
+    assert_eq!(parsed.number, "AS4-001");
+}
+
+fn client_transport_received(client: &As4Client<StandIn>) -> Result<ReceivedMessage, As4Error> {
+    client
+        .transport()
+        .received
+        .borrow_mut()
+        .pop()
+        .expect("a request")
+}
+
+#[test]
+fn send_to_smp_endpoint() {
+    let client = As4Client::new(sender(), StandIn::new());
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let mut endpoint = Endpoint {
+        transport_profile: TRANSPORT_AS4.into(),
+        address: "http://ap2.example.org/as4".into(),
+        require_business_level_signature: false,
+        activation: None,
+        expiration: None,
+        certificate: Some(receiver_certificate()),
+        description: None,
+        technical_contact: None,
+    };
+    client.send(&message, &endpoint).unwrap();
+
+    endpoint.transport_profile = "busdox-transport-as2-ver1p0".into();
+    assert!(matches!(
+        client.send(&message, &endpoint),
+        Err(As4Error::Invalid(_))
+    ));
+    endpoint.transport_profile = TRANSPORT_AS4.into();
+    endpoint.certificate = None;
+    assert!(matches!(
+        client.send(&message, &endpoint),
+        Err(As4Error::Invalid(_))
+    ));
+}
+
+#[test]
+fn tampered_header_is_rejected() {
+    let mut stand_in = StandIn::new();
+    stand_in.tamper = |body| replace(body, b"ap_provider", b"ap_provideX");
+    let client = As4Client::new(sender(), stand_in);
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let err = client
+        .send_to(
+            &message,
+            "http://ap2.example.org/as4",
+            &receiver_certificate(),
+        )
+        .unwrap_err();
+    match err {
+        As4Error::Rejected { code, description } => {
+            assert_eq!(code, "EBMS:0101");
+            assert!(description.contains("digest"), "{description}");
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    assert!(matches!(
+        client_transport_received(&client),
+        Err(As4Error::Security(_))
+    ));
+}
+
+/// Move the signed `eb:Messaging` header into `wsse:Security` and put a
+/// forged copy in its place (XML signature wrapping). `rename` gives the
+/// forged header its own `wsu:Id`.
+fn wrap_header(body: Vec<u8>, rename: bool) -> Vec<u8> {
+    let find = |pattern: &[u8]| {
+        body.windows(pattern.len())
+            .position(|w| w == pattern)
+            .expect("pattern present")
+    };
+    let start = find(b"<eb:Messaging");
+    let end = find(b"</eb:Messaging>") + b"</eb:Messaging>".len();
+    let security = find(b"<wsse:Security");
+    let security = security + body[security..].iter().position(|&b| b == b'>').unwrap() + 1;
+    let signed = &body[start..end];
+    let mut forged = replace(signed.to_vec(), b"<eb:MessageId>", b"<eb:MessageId>forged-");
+    if rename {
+        forged = replace(forged, b"wsu:Id=\"_messaging\"", b"wsu:Id=\"_forged\"");
+    }
+    [
+        &body[..start],
+        &forged,
+        &body[end..security],
+        signed,
+        &body[security..],
+    ]
+    .concat()
+}
+
+#[test]
+fn wrapped_header_is_rejected() {
+    let wrappers: [fn(Vec<u8>) -> Vec<u8>; 2] = [
+        |body| wrap_header(body, false),
+        |body| wrap_header(body, true),
+    ];
+    for (tamper, expected) in wrappers.into_iter().zip(["more than one", "ebMS header"]) {
+        let mut stand_in = StandIn::new();
+        stand_in.tamper = tamper;
+        let client = As4Client::new(sender(), stand_in);
+        let message = UserMessage::for_invoice(&invoice()).unwrap();
+        let err = client
+            .send_to(
+                &message,
+                "http://ap2.example.org/as4",
+                &receiver_certificate(),
+            )
+            .unwrap_err();
+        match err {
+            As4Error::Rejected { code, description } => {
+                assert_eq!(code, "EBMS:0101");
+                assert!(description.contains(expected), "{description}");
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn wrapped_receipt_is_refused() {
+    let wrappers: [fn(Vec<u8>) -> Vec<u8>; 2] = [
+        |body| wrap_header(body, false),
+        |body| wrap_header(body, true),
+    ];
+    for (answer, expected) in wrappers.into_iter().zip(["more than one", "ebMS header"]) {
+        let mut stand_in = StandIn::new();
+        stand_in.answer = answer;
+        let client = As4Client::new(sender(), stand_in);
+        let message = UserMessage::for_invoice(&invoice()).unwrap();
+        let err = client
+            .send_to(
+                &message,
+                "http://ap2.example.org/as4",
+                &receiver_certificate(),
+            )
+            .unwrap_err();
+        assert!(
+            matches!(&err, As4Error::Security(e) if e.contains(expected)),
+            "{err:?}"
+        );
+    }
+}
+
+#[test]
+fn tampered_payload_fails_decryption() {
+    let mut stand_in = StandIn::new();
+    // Flip the last ciphertext byte (the GCM tag) before the closing boundary
+    stand_in.tamper = |mut body| {
+        let end = body
+            .windows(4)
+            .rposition(|w| w == b"\r\n--")
+            .expect("closing boundary");
+        body[end - 1] ^= 1;
+        body
+    };
+    let client = As4Client::new(sender(), stand_in);
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let err = client
+        .send_to(
+            &message,
+            "http://ap2.example.org/as4",
+            &receiver_certificate(),
+        )
+        .unwrap_err();
+    assert!(
+        matches!(&err, As4Error::Rejected { code, .. } if code == "EBMS:0102"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn message_for_another_access_point_is_rejected() {
+    // Encrypted and addressed for AP 1, but delivered to AP 2
+    let client = As4Client::new(sender(), StandIn::new());
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let err = client
+        .send_to(
+            &message,
+            "http://ap2.example.org/as4",
+            sender().certificate(),
+        )
+        .unwrap_err();
+    assert!(
+        matches!(&err, As4Error::Rejected { code, .. } if code == "EBMS:0003"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn forged_receipt_is_refused() {
+    let mut stand_in = StandIn::new();
+    stand_in.answer = |body| replace(body, b"<eb:Receipt>", b"<eb:Receipt> ");
+    let client = As4Client::new(sender(), stand_in);
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let err = client
+        .send_to(
+            &message,
+            "http://ap2.example.org/as4",
+            &receiver_certificate(),
+        )
+        .unwrap_err();
+    assert!(matches!(err, As4Error::Security(_)), "{err:?}");
+}
+
+#[test]
+fn malformed_request_gets_ebms_error() {
+    let reception = As4Receiver::new(receiver()).handle("application/soap+xml", b"<nope/>");
+    assert_eq!(reception.status, 500);
+    assert!(reception.message.is_err());
+    let text = String::from_utf8(reception.response.body).unwrap();
+    assert!(text.contains("errorCode=\"EBMS:0009\""), "{text}");
+}
+
+#[test]
+fn send_over_http_to_local_access_point() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/as4", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let receiver = As4Receiver::new(receiver());
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let (mut content_type, mut length) = (String::new(), 0);
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                match name.to_ascii_lowercase().as_str() {
+                    "content-type" => content_type = value.trim().to_string(),
+                    "content-length" => length = value.trim().parse().unwrap(),
+                    _ => {}
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        let reception = receiver.handle(&content_type, &body);
+        let mut stream = reader.into_inner();
+        write!(
+            stream,
+            "HTTP/1.1 {} OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            reception.status,
+            reception.response.content_type,
+            reception.response.body.len()
+        )
+        .unwrap();
+        stream.write_all(&reception.response.body).unwrap();
+        reception.message.unwrap()
+    });
+
+    let client = As4Client::new(sender(), StdHttpClient::new());
+    let message = UserMessage::for_invoice(&invoice()).unwrap();
+    let receipt = client
+        .send_to(&message, &url, &receiver_certificate())
+        .unwrap();
+    assert_eq!(receipt.ref_to_message_id, message.message_id);
+    let received = server.join().unwrap();
+    assert_eq!(received.message.payload, message.payload);
+}
@@ -413,6 +413,190 @@ fn rejects_wrong_tax_category_for_scenario() {
     assert!(result.unwrap_err().to_string().contains("NotSubjectToVat"));
 }
 
+// --- Foreign Currency ---
+
+const ECB_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+  <gesmes:subject>Reference rates</gesmes:subject>
+  <Cube>
+    <Cube time="2024-06-14">
+      <Cube currency="USD" rate="1.0708"/>
+      <Cube currency="JPY" rate="168.61"/>
+      <Cube currency="CHF" rate="0.9561"/>
+    </Cube>
+    <Cube time="2024-06-13">
+      <Cube currency="USD" rate="1.0784"/>
+    </Cube>
+  </Cube>
+</gesmes:Envelope>"#;
+
+fn usd_invoice() -> InvoiceBuilder {
+    InvoiceBuilder::new("RE-2024-USD", date(2024, 6, 17))
+        .currency("USD")
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(
+            LineItemBuilder::new("1", "Consulting", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+}
+
+#[test]
+fn ecb_xml_rates_loaded() {
+    let table = ExchangeRateTable::from_ecb_xml(ECB_XML).unwrap();
+    assert_eq!(table.len(), 4);
+    assert_eq!(table.source(), RateSource::EcbDaily);
+
+    let rate = table.eur_rate("USD", date(2024, 6, 13)).unwrap();
+    assert_eq!(rate.rate, dec!(1.0784));
+    assert_eq!(rate.date, date(2024, 6, 13));
+
+    // Sunday uses the Friday fixing
+    let rate = table.eur_rate("JPY", date(2024, 6, 16)).unwrap();
+    assert_eq!(rate.rate, dec!(168.61));
+    assert_eq!(rate.date, date(2024, 6, 14));
+
+    assert!(table.eur_rate("GBP", date(2024, 6, 14)).is_none());
+    assert_eq!(
+        table.eur_rate("EUR", date(2024, 6, 14)).unwrap().rate,
+        dec!(1)
+    );
+}
+
+#[test]
+fn ecb_csv_rates_loaded() {
+    let daily = "Date, USD, JPY, BGN, \n14 June 2024, 1.0708, 168.61, 1.9558, \n";
+    let table = ExchangeRateTable::from_ecb_csv(daily).unwrap();
+    assert_eq!(table.len(), 3);
+    assert_eq!(
+        table.eur_rate("BGN", date(2024, 6, 14)).unwrap().rate,
+        dec!(1.9558)
+    );
+
+    let hist = "Date,USD,CYP\n2024-06-14,1.0708,N/A\n2024-06-13,1.0784,N/A\n";
+    let table = ExchangeRateTable::from_ecb_csv(hist).unwrap();
+    assert_eq!(table.len(), 2);
+    assert!(table.eur_rate("CYP", date(2024, 6, 14)).is_none());
+}
+
+#[test]
+fn ecb_invalid_input_rejected() {
+    let xml_errors = [
+        ExchangeRateTable::from_ecb_xml("<Cube></Cube>").unwrap_err(),
+        ExchangeRateTable::from_ecb_xml("<Cube currency='USD' rate='1.07'/>").unwrap_err(),
+        ExchangeRateTable::from_ecb_xml("<Cube time='2024-06-14'><Cube").unwrap_err(),
+        ExchangeRateTable::from_ecb_xml("<Cube time='2024-06-14'><Cube currency='USD' rate='x'/>")
+            .unwrap_err(),
+    ];
+    for err in xml_errors {
+        assert!(matches!(err, RechnungError::Xml(_)), "{err}");
+    }
+    let csv_errors = [
+        ExchangeRateTable::from_ecb_csv("USD,JPY\n1.07,168.61").unwrap_err(),
+        ExchangeRateTable::from_ecb_csv("Date,USD\n2024-06-14,abc").unwrap_err(),
+    ];
+    for err in csv_errors {
+        assert!(matches!(err, RechnungError::Builder(_)), "{err}");
+    }
+}
+
+#[test]
+fn exchange_rate_computes_bt111() {
+    let table = ExchangeRateTable::from_ecb_xml(ECB_XML).unwrap();
+    let rate = table.eur_rate("USD", date(2024, 6, 14)).unwrap();
+    let inv = usd_invoice()
+        .tax_point_date(date(2024, 6, 14))
+        .exchange_rate(rate)
+        .build()
+        .unwrap();
+
+    let totals = inv.totals.as_ref().unwrap();
+    assert_eq!(totals.vat_total, dec!(285.00));
+    // 285.00 USD / 1.0708 = 266.156...
+    assert_eq!(totals.vat_total_in_tax_currency, Some(dec!(266.16)));
+    assert_eq!(inv.tax_currency_code.as_deref(), Some("EUR"));
+    assert_eq!(inv.exchange_rate.as_ref().unwrap().rate, dec!(1.0708));
+}
+
+#[test]
+fn apply_exchange_rate_uses_tax_point_date() {
+    let table = ExchangeRateTable::from_ecb_xml(ECB_XML).unwrap();
+    let mut inv = usd_invoice()
+        .tax_point_date(date(2024, 6, 13))
+        .build()
+        .unwrap();
+    apply_exchange_rate(&mut inv, &table).unwrap();
+
+    let rate = inv.exchange_rate.as_ref().unwrap();
+    assert_eq!(rate.date, date(2024, 6, 13));
+    // 285.00 / 1.0784 = 264.280...
+    assert_eq!(
+        inv.totals.as_ref().unwrap().vat_total_in_tax_currency,
+        Some(dec!(264.28))
+    );
+}
+
+#[test]
+fn apply_exchange_rate_missing_rate() {
+    let table = ExchangeRateTable::new(RateSource::BmfMonthly);
+    let mut inv = usd_invoice()
+        .tax_point_date(date(2024, 6, 13))
+        .build()
+        .unwrap();
+    assert!(apply_exchange_rate(&mut inv, &table).is_err());
+    assert!(inv.exchange_rate.is_none());
+}
+
+#[test]
+fn apply_exchange_rate_ignores_eur() {
+    let table = ExchangeRateTable::new(RateSource::EcbDaily);
+    let mut inv = InvoiceBuilder::new("RE-2024-001", date(2024, 6, 15))
+        .tax_point_date(date(2024, 6, 15))
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(LineItemBuilder::new("1", "Test", dec!(1), "C62", dec!(100)).build())
+        .build()
+        .unwrap();
+    apply_exchange_rate(&mut inv, &table).unwrap();
+    assert!(inv.tax_currency_code.is_none());
+    assert!(inv.exchange_rate.is_none());
+}
+
+#[test]
+fn exchange_rate_currency_mismatch_rejected() {
+    let rate = ExchangeRate::new("CHF", dec!(0.9561), date(2024, 6, 14), RateSource::EcbDaily);
+    let result = usd_invoice()
+        .tax_point_date(date(2024, 6, 14))
+        .exchange_rate(rate)
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn exchange_rate_keeps_explicit_tax_currency() {
+    let rate = ExchangeRate::new("USD", dec!(1.0708), date(2024, 6, 14), RateSource::EcbDaily);
+    let inv = usd_invoice()
+        .tax_point_date(date(2024, 6, 14))
+        .tax_currency("EUR", dec!(266.00))
+        .exchange_rate(rate.clone())
+        .build()
+        .unwrap();
+    assert_eq!(inv.tax_currency_code.as_deref(), Some("EUR"));
+    assert_eq!(
+        inv.totals.as_ref().unwrap().vat_total_in_tax_currency,
+        Some(dec!(266.00))
+    );
+
+    let err = usd_invoice()
+        .tax_point_date(date(2024, 6, 14))
+        .tax_currency("CHF", dec!(256.00))
+        .exchange_rate(rate)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("tax currency is CHF"), "{err}");
+}
+
 // --- Serialization ---
 
 #[test]
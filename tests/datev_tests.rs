@@ -21,6 +21,8 @@ fn default_config() -> DatevConfig {
         exported_by: "faktura".into(),
         description: "Buchungsstapel".into(),
         lock_postings: false,
+        bank_account: None,
+        debitor_accounts: Default::default(),
     }
 }
 
@@ -530,3 +532,167 @@ fn due_date_in_field_117() {
         "expected due date 15072024 in output"
     );
 }
+
+// ---------------------------------------------------------------------------
+// Foreign Currency
+// ---------------------------------------------------------------------------
+
+fn usd_invoice() -> Invoice {
+    InvoiceBuilder::new("RE-2024-USD", date(2024, 6, 17))
+        .currency("USD")
+        .tax_point_date(date(2024, 6, 14))
+        .exchange_rate(ExchangeRate::new(
+            "USD",
+            dec!(1.0708),
+            date(2024, 6, 14),
+            RateSource::EcbDaily,
+        ))
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Kunde AG",
+                AddressBuilder::new("München", "80331", "DE").build(),
+            )
+            .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn foreign_currency_fields_3_to_6() {
+    let csv = to_extf(&[usd_invoice()], &default_config()).unwrap();
+    let data_line = csv.lines().nth(2).unwrap();
+    let fields: Vec<&str> = data_line.split(';').collect();
+    assert_eq!(fields[0], "1785,00", "Umsatz stays in invoice currency");
+    assert_eq!(fields[2], "\"USD\"");
+    assert_eq!(fields[3], "1,070800");
+    // 1785.00 / 1.0708 = 1666.978...
+    assert_eq!(fields[4], "1666,98");
+    assert_eq!(fields[5], "\"EUR\"");
+}
+
+#[test]
+fn eur_invoice_leaves_currency_fields_empty() {
+    let csv = to_extf(&[domestic_invoice()], &default_config()).unwrap();
+    let data_line = csv.lines().nth(2).unwrap();
+    let fields: Vec<&str> = data_line.split(';').collect();
+    assert_eq!(&fields[2..6], &["", "", "", ""]);
+}
+
+// ---------------------------------------------------------------------------
+// Payments and Skonto
+// ---------------------------------------------------------------------------
+
+fn skonto_invoice() -> Invoice {
+    let mut inv = domestic_invoice();
+    inv.cash_discounts = vec![CashDiscount::new(14, dec!(2))];
+    inv
+}
+
+#[test]
+fn payment_within_skonto_period_fills_skonto_field() {
+    let inv = skonto_invoice();
+    // 1785.00 − 2% (35.70) = 1749.30, paid on day 10
+    let payment = InvoicePayment {
+        invoice: &inv,
+        date: date(2024, 6, 25),
+        amount: dec!(1749.30),
+    };
+    let csv = payments_to_extf(&[payment], &default_config()).unwrap();
+    let fields: Vec<&str> = csv.lines().nth(2).unwrap().split(';').collect();
+    assert_eq!(fields[0], "1749,30");
+    assert_eq!(fields[1], "\"S\"");
+    assert_eq!(fields[6], "1200", "SKR03 bank account");
+    assert_eq!(fields[7], "10000", "debitor");
+    assert_eq!(fields[10], "\"RE-2024-001\"");
+    assert_eq!(fields[12], "35,70");
+}
+
+#[test]
+fn payment_after_skonto_period_has_no_skonto() {
+    let inv = skonto_invoice();
+    let payment = InvoicePayment {
+        invoice: &inv,
+        date: date(2024, 7, 1),
+        amount: dec!(1749.30),
+    };
+    let config = DatevConfigBuilder::new(29098, 55003)
+        .chart(ChartOfAccounts::SKR04)
+        .build();
+    let csv = payments_to_extf(&[payment], &config).unwrap();
+    let fields: Vec<&str> = csv.lines().nth(2).unwrap().split(';').collect();
+    assert_eq!(fields[6], "1800", "SKR04 bank account");
+    assert_eq!(fields[12], "", "short payment outside the Skonto period");
+}
+
+#[test]
+fn open_items_export() {
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&domestic_invoice()).unwrap();
+    ledger
+        .apply_payment("RE-2024-001", date(2024, 7, 1), dec!(785))
+        .unwrap();
+    let as_of = date(2024, 7, 31);
+    let csv = open_items_to_extf(&ledger, as_of, &default_config()).unwrap();
+
+    let rows: Vec<&str> = csv.lines().skip(2).collect();
+    assert_eq!(rows.len(), 1);
+    let fields: Vec<&str> = rows[0].split(';').collect();
+    assert_eq!(fields[0], "1000,00", "outstanding amount");
+    assert_eq!(fields[1], "\"S\"");
+    assert_eq!(fields[6], "10000", "debitor");
+    assert_eq!(fields[7], "9008", "Saldenvortrag Debitoren");
+    assert_eq!(fields[9], "1506");
+    assert_eq!(fields[10], "\"RE-2024-001\"");
+    assert!(rows[0].contains("15072024"), "due date");
+
+    ledger
+        .apply_payment("RE-2024-001", date(2024, 8, 1), dec!(1000))
+        .unwrap();
+    assert!(open_items_to_extf(&ledger, date(2024, 8, 1), &default_config()).is_err());
+}
+
+#[test]
+fn open_items_export_by_buyer_and_currency() {
+    let mut other = domestic_invoice();
+    other.buyer.name = "Beta KG".into();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&usd_invoice()).unwrap();
+    ledger.add_invoice(&other).unwrap();
+    let config = DatevConfigBuilder::new(29098, 55003)
+        .debitor("Kunde AG", 10100)
+        .build();
+    let csv = open_items_to_extf(&ledger, date(2024, 7, 31), &config).unwrap();
+
+    let row = |number: &str| -> Vec<String> {
+        csv.lines()
+            .find(|l| l.contains(&format!("\"{number}\"")))
+            .unwrap()
+            .split(';')
+            .map(String::from)
+            .collect()
+    };
+    let usd = row("RE-2024-USD");
+    assert_eq!(usd[0], "1785,00");
+    assert_eq!(usd[2], "\"USD\"");
+    assert_eq!(usd[3], "1,070800");
+    assert_eq!(usd[4], "1666,98");
+    assert_eq!(usd[5], "\"EUR\"");
+    assert_eq!(usd[6], "10100", "mapped debitor");
+    let eur = row("RE-2024-001");
+    assert_eq!(&eur[2..6], &["", "", "", ""]);
+    assert_eq!(eur[6], "10000", "default debitor");
+}
@@ -0,0 +1,229 @@
+use chrono::NaiveDate;
+use faktura::core::dunning::*;
+use faktura::core::*;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+/// 1000 EUR net + 19 % VAT, issued 2024-04-01, due 2024-05-01.
+fn invoice(buyer: Party) -> Invoice {
+    InvoiceBuilder::new("RE-2024-100", date(2024, 4, 1))
+        .due_date(date(2024, 5, 1))
+        .tax_point_date(date(2024, 4, 1))
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .tax_number("30/123/45678")
+            .build(),
+        )
+        .buyer(buyer)
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(100))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+fn business() -> Party {
+    PartyBuilder::new(
+        "Kunde AG",
+        AddressBuilder::new("München", "80331", "DE").build(),
+    )
+    .vat_id("DE987654321")
+    .build()
+}
+
+fn consumer() -> Party {
+    PartyBuilder::new(
+        "Erika Mustermann",
+        AddressBuilder::new("Köln", "50667", "DE").build(),
+    )
+    .build()
+}
+
+fn ledger_with(inv: &Invoice) -> OpenItemsLedger {
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(inv).unwrap();
+    ledger
+}
+
+#[test]
+fn debtor_kind_from_buyer() {
+    assert_eq!(DebtorKind::for_party(&business()), DebtorKind::Business);
+    assert_eq!(DebtorKind::for_party(&consumer()), DebtorKind::Consumer);
+}
+
+#[test]
+fn levels_by_days_overdue() {
+    let policy = DunningPolicy::default();
+    assert!(policy.level_for(6).is_none());
+    assert_eq!(policy.level_for(7).unwrap().title, "Zahlungserinnerung");
+    assert_eq!(policy.level_for(30).unwrap().level, 2);
+    assert_eq!(policy.level_for(365).unwrap().title, "Letzte Mahnung");
+
+    let ledger = ledger_with(&invoice(business()));
+    assert!(policy.notices(&ledger, date(2024, 5, 5)).is_empty());
+}
+
+#[test]
+fn reminder_claims_no_interest() {
+    let ledger = ledger_with(&invoice(business()));
+    let notice = DunningPolicy::default()
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 5, 10))
+        .unwrap();
+    assert_eq!(notice.level, 1);
+    assert_eq!(notice.days_overdue, 9);
+    assert_eq!(notice.charges(), dec!(0));
+    assert_eq!(notice.total(), dec!(1190));
+    assert_eq!(notice.payment_deadline, date(2024, 5, 20));
+}
+
+#[test]
+fn business_interest_and_lump_sum() {
+    let ledger = ledger_with(&invoice(business()));
+    let notice = DunningPolicy::default()
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 7, 31))
+        .unwrap();
+    assert_eq!(notice.level, 4);
+    assert_eq!(notice.default_since, date(2024, 5, 2));
+    // 60 days at 3.62 + 9 and 31 days at 3.37 + 9 on 1190 EUR
+    let expected = dec!(1190) * dec!(12.62) / dec!(100) * dec!(60) / dec!(366)
+        + dec!(1190) * dec!(12.37) / dec!(100) * dec!(31) / dec!(366);
+    assert_eq!(notice.interest, round_amount(expected, "EUR"));
+    assert_eq!(notice.interest_rate, dec!(12.37));
+    // Fees of 20 EUR are covered by the 40 EUR lump sum
+    assert_eq!(notice.lump_sum, dec!(40));
+    assert_eq!(notice.fees, dec!(0));
+}
+
+#[test]
+fn consumer_interest_and_fees() {
+    let ledger = ledger_with(&invoice(consumer()));
+    let notice = DunningPolicy::default()
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 6, 10))
+        .unwrap();
+    assert_eq!(notice.level, 3);
+    assert_eq!(notice.lump_sum, dec!(0));
+    assert_eq!(notice.fees, dec!(10));
+    assert_eq!(notice.interest_rate, dec!(8.62));
+    assert_eq!(
+        notice.interest,
+        default_interest(
+            dec!(1190),
+            date(2024, 5, 2),
+            date(2024, 6, 10),
+            DebtorKind::Consumer,
+            &BaseRateTable::bundesbank(),
+            "EUR",
+        )
+    );
+}
+
+#[test]
+fn interest_follows_partial_payments() {
+    let inv = invoice(consumer());
+    let mut ledger = ledger_with(&inv);
+    ledger
+        .apply_payment("RE-2024-100", date(2024, 5, 11), dec!(590))
+        .unwrap();
+    let policy = DunningPolicy::default();
+    let item = ledger.item("RE-2024-100").unwrap();
+
+    // 9 days on 1190 EUR, then 21 days on 600 EUR, at 3.62 + 5 %
+    let expected = dec!(1190) * dec!(8.62) / dec!(100) * dec!(9) / dec!(366)
+        + dec!(600) * dec!(8.62) / dec!(100) * dec!(21) / dec!(366);
+    assert_eq!(
+        policy.interest(item, date(2024, 5, 31)),
+        round_amount(expected, "EUR")
+    );
+    assert_eq!(policy.interest(item, date(2024, 5, 31)), dec!(5.49));
+
+    ledger
+        .apply_payment("RE-2024-100", date(2024, 6, 1), dec!(600))
+        .unwrap();
+    assert!(
+        policy
+            .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 6, 1))
+            .is_none()
+    );
+}
+
+#[test]
+fn custom_levels() {
+    let policy = DunningPolicy::new()
+        .with_level(DunningLevel::new(2, "Mahnung", 14, dec!(2.50)).payment_days(14))
+        .with_level(DunningLevel::new(1, "Erinnerung", 3, Decimal::ZERO))
+        .claims_from_level(2);
+    assert_eq!(policy.levels[0].title, "Erinnerung");
+
+    let ledger = ledger_with(&invoice(consumer()));
+    let notice = policy
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 5, 20))
+        .unwrap();
+    assert_eq!(notice.title, "Mahnung");
+    assert_eq!(notice.fees, dec!(2.50));
+    assert_eq!(notice.payment_deadline, date(2024, 6, 3));
+    assert!(notice.interest > Decimal::ZERO);
+}
+
+#[test]
+fn reminder_invoice_references_original() {
+    let inv = invoice(business());
+    let ledger = ledger_with(&inv);
+    let notice = DunningPolicy::default()
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 6, 10))
+        .unwrap();
+    let reminder = notice
+        .to_invoice(&inv, "MA-2024-001", date(2024, 6, 10))
+        .unwrap();
+
+    assert_eq!(reminder.preceding_invoices[0].number, "RE-2024-100");
+    assert_eq!(reminder.due_date, Some(notice.payment_deadline));
+    assert_eq!(reminder.lines.len(), 2, "interest and lump sum");
+    assert!(
+        reminder
+            .lines
+            .iter()
+            .all(|l| l.tax_category == TaxCategory::NotSubjectToVat)
+    );
+    let totals = reminder.totals.as_ref().unwrap();
+    assert_eq!(totals.amount_due, notice.charges());
+    assert_eq!(totals.vat_total, dec!(0));
+    assert!(reminder.notes.iter().any(|n| n.contains("1190")));
+    // BR-O-02: no VAT identifiers next to category O lines
+    assert!(reminder.seller.vat_id.is_none());
+    assert!(reminder.buyer.vat_id.is_none());
+    assert_eq!(validate_en16931(&reminder), vec![]);
+    let mut with_vat_id = reminder.clone();
+    with_vat_id.buyer.vat_id = Some("DE987654321".into());
+    assert!(
+        validate_en16931(&with_vat_id)
+            .iter()
+            .any(|e| e.rule.as_deref() == Some("BR-O-02"))
+    );
+
+    let mut without_tax_number = inv.clone();
+    without_tax_number.seller.tax_number = None;
+    assert!(
+        notice
+            .to_invoice(&without_tax_number, "MA-2024-001", date(2024, 6, 10))
+            .is_err()
+    );
+
+    let reminder_level = DunningPolicy::default()
+        .notice(ledger.item("RE-2024-100").unwrap(), date(2024, 5, 10))
+        .unwrap();
+    assert!(
+        reminder_level
+            .to_invoice(&inv, "MA-2024-002", date(2024, 5, 10))
+            .is_err()
+    );
+}
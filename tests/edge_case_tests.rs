@@ -39,6 +39,7 @@ fn buyer() -> Party {
 }
 
 /// XRechnung-compliant seller with electronic address.
+#[cfg(feature = "xrechnung")]
 fn xr_seller() -> Party {
     PartyBuilder::new(
         "ACME GmbH",
@@ -57,6 +58,7 @@ fn xr_seller() -> Party {
 }
 
 /// XRechnung-compliant buyer with electronic address.
+#[cfg(feature = "xrechnung")]
 fn xr_buyer() -> Party {
     PartyBuilder::new(
         "Kunde AG",
@@ -155,6 +157,140 @@ fn non_eur_currency_datev_export() {
     assert!(csv.contains("USD-002") || csv.contains("1190")); // either number or gross amount
 }
 
+#[test]
+fn zero_decimal_currency_rounds_to_whole_units() {
+    let inv = InvoiceBuilder::new("JPY-001", date(2024, 6, 1))
+        .currency("JPY")
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(
+            LineItemBuilder::new("1", "Consulting", dec!(3), "HUR", dec!(12345))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .tax_point_date(date(2024, 6, 1))
+        .build_strict()
+        .unwrap();
+
+    let totals = inv.totals.as_ref().unwrap();
+    assert_eq!(totals.line_net_total, dec!(37035));
+    // 37035 × 19% = 7036.65 → 7037 yen
+    assert_eq!(totals.vat_total, dec!(7037));
+    assert_eq!(totals.gross_total, dec!(44072));
+}
+
+#[test]
+fn three_decimal_currency_capped_at_two_decimals() {
+    let inv = InvoiceBuilder::new("KWD-001", date(2024, 6, 1))
+        .currency("KWD")
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(
+            LineItemBuilder::new("1", "Consulting", dec!(1), "HUR", dec!(10.555))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .tax_point_date(date(2024, 6, 1))
+        .build_strict()
+        .unwrap();
+
+    let totals = inv.totals.as_ref().unwrap();
+    // EN 16931 allows at most 2 decimals: 10.555 → 10.56, × 19% = 2.0064 → 2.01 KWD
+    assert_eq!(totals.net_total, dec!(10.56));
+    assert_eq!(totals.vat_total, dec!(2.01));
+    assert_eq!(totals.gross_total, dec!(12.57));
+    assert!(validate_en16931(&inv).is_empty());
+
+    let mut fils = inv.clone();
+    fils.totals.as_mut().unwrap().amount_due = dec!(12.565);
+    assert!(
+        validate_en16931(&fils)
+            .iter()
+            .any(|e| e.rule.as_deref() == Some("BR-DEC-01") && e.field == "totals.amount_due")
+    );
+}
+
+#[test]
+fn decimal_places_checked_against_currency() {
+    let mut inv = InvoiceBuilder::new("JPY-002", date(2024, 6, 1))
+        .currency("JPY")
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(LineItemBuilder::new("1", "Item", dec!(1), "C62", dec!(1000)).build())
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+    inv.totals.as_mut().unwrap().amount_due = dec!(1190.50);
+
+    let errors = validate_en16931(&inv);
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.rule.as_deref() == Some("BR-DEC-01") && e.field == "totals.amount_due"),
+        "expected BR-DEC-01 for fractional yen: {errors:?}"
+    );
+}
+
+#[test]
+#[cfg(feature = "xrechnung")]
+fn minor_units_in_ubl_and_cii_output() {
+    let jpy = InvoiceBuilder::new("JPY-003", date(2024, 6, 1))
+        .currency("JPY")
+        .seller(xr_seller())
+        .buyer(xr_buyer())
+        .buyer_reference("LEITWEG-123")
+        .add_line(
+            LineItemBuilder::new("1", "Consulting", dec!(3), "HUR", dec!(12345))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+
+    let ubl = faktura::xrechnung::to_ubl_xml(&jpy).unwrap();
+    assert!(ubl.contains(r#"<cbc:PayableAmount currencyID="JPY">44072</cbc:PayableAmount>"#));
+    // Unit prices keep full precision
+    assert!(ubl.contains(r#"<cbc:PriceAmount currencyID="JPY">12345.00</cbc:PriceAmount>"#));
+    let cii = faktura::xrechnung::to_cii_xml(&jpy).unwrap();
+    assert!(cii.contains("<ram:DuePayableAmount>44072</ram:DuePayableAmount>"));
+    let back = faktura::xrechnung::from_cii_xml(&cii).unwrap();
+    assert_eq!(back.totals.unwrap().vat_total, dec!(7037));
+
+    let kwd = InvoiceBuilder::new("KWD-002", date(2024, 6, 1))
+        .currency("KWD")
+        .seller(xr_seller())
+        .buyer(xr_buyer())
+        .buyer_reference("LEITWEG-123")
+        .add_line(LineItemBuilder::new("1", "Item", dec!(2), "C62", dec!(5)).build())
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+    let ubl = faktura::xrechnung::to_ubl_xml(&kwd).unwrap();
+    assert!(ubl.contains(r#"<cbc:TaxAmount currencyID="KWD">1.90</cbc:TaxAmount>"#));
+    let cii = faktura::xrechnung::to_cii_xml(&kwd).unwrap();
+    assert!(cii.contains("<ram:GrandTotalAmount>11.90</ram:GrandTotalAmount>"));
+}
+
+#[test]
+#[cfg(feature = "gdpdu")]
+fn three_decimal_currency_gdpdu_accuracy() {
+    let inv = InvoiceBuilder::new("TND-001", date(2024, 6, 1))
+        .currency("TND")
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(LineItemBuilder::new("1", "Item", dec!(1), "C62", dec!(10.555)).build())
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+
+    let export = faktura::gdpdu::to_gdpdu(&[inv], &Default::default()).unwrap();
+    assert!(export.index_xml.contains("<Accuracy>3</Accuracy>"));
+    let row = export.files[1].1.lines().next().unwrap();
+    // Invoice amounts have at most 2 decimals (EN 16931), written with TND accuracy
+    assert!(row.contains(";10,560;19,00;2,010;12,570;"), "{row}");
+}
+
 // ---- 2. Skonto payment terms (BR-DE-18) ----
 
 #[test]
@@ -239,6 +375,143 @@ fn skonto_missing_prozent_fails_br_de_18() {
     );
 }
 
+#[test]
+#[cfg(feature = "xrechnung")]
+fn structured_skonto_roundtrips_through_ubl_and_cii() {
+    let inv = InvoiceBuilder::new("SK-004", date(2024, 6, 1))
+        .due_date(date(2024, 7, 1))
+        .seller(xr_seller())
+        .buyer(xr_buyer())
+        .buyer_reference("LEITWEG-123")
+        .add_line(
+            LineItemBuilder::new("1", "Widget", dec!(1), "C62", dec!(100))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .payment(sepa_payment())
+        .add_cash_discount(CashDiscount::new(7, dec!(3)))
+        .add_cash_discount(CashDiscount::new(14, dec!(2)).with_base_amount(dec!(100)))
+        .payment_terms("Zahlbar innerhalb 30 Tagen netto")
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+
+    let ubl = faktura::xrechnung::to_ubl_xml(&inv).unwrap();
+    assert!(ubl.contains(
+        "<cbc:Note>#SKONTO#TAGE=7#PROZENT=3.00#\n\
+         #SKONTO#TAGE=14#PROZENT=2.00#BASISBETRAG=100.00#\n\
+         Zahlbar innerhalb 30 Tagen netto</cbc:Note>"
+    ));
+    let cii = faktura::xrechnung::to_cii_xml(&inv).unwrap();
+    assert!(!cii.contains("ApplicableTradePaymentDiscountTerms"));
+
+    for back in [
+        faktura::xrechnung::from_ubl_xml(&ubl).unwrap(),
+        faktura::xrechnung::from_cii_xml(&cii).unwrap(),
+    ] {
+        assert_eq!(back.cash_discounts, inv.cash_discounts);
+        assert_eq!(
+            back.payment_terms.as_deref(),
+            Some("Zahlbar innerhalb 30 Tagen netto")
+        );
+    }
+    assert!(faktura::xrechnung::validate_xrechnung(&inv).is_empty());
+}
+
+#[test]
+fn skonto_discounted_amounts() {
+    let inv = InvoiceBuilder::new("SK-005", date(2024, 6, 1))
+        .seller(seller())
+        .buyer(buyer())
+        .add_line(
+            LineItemBuilder::new("1", "Widget", dec!(1), "C62", dec!(1000))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .add_cash_discount(CashDiscount::new(10, dec!(3)))
+        .add_cash_discount(CashDiscount::new(20, dec!(2)))
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+
+    // Amount due 1190.00
+    assert_eq!(
+        skonto::discounted_amount_due(&inv, date(2024, 6, 11)),
+        dec!(1154.30)
+    );
+    assert_eq!(
+        skonto::discounted_amount_due(&inv, date(2024, 6, 12)),
+        dec!(1166.20)
+    );
+    assert_eq!(
+        skonto::discounted_amount_due(&inv, date(2024, 6, 22)),
+        dec!(1190.00)
+    );
+    assert_eq!(
+        skonto::discount_schedule(&inv),
+        vec![
+            (date(2024, 6, 11), dec!(1154.30)),
+            (date(2024, 6, 21), dec!(1166.20)),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "zugferd")]
+fn skonto_extended_profile_discount_terms() {
+    use faktura::zugferd::{ZugferdProfile, to_xml};
+
+    let inv = InvoiceBuilder::new("SK-006", date(2024, 6, 1))
+        .seller(xr_seller())
+        .buyer(xr_buyer())
+        .add_line(
+            LineItemBuilder::new("1", "Widget", dec!(1), "C62", dec!(100))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .add_cash_discount(CashDiscount::new(14, dec!(2)))
+        .add_cash_discount(CashDiscount::new(30, dec!(1)))
+        .tax_point_date(date(2024, 6, 1))
+        .build()
+        .unwrap();
+
+    let en16931 = to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    assert!(!en16931.contains("ApplicableTradePaymentDiscountTerms"));
+
+    let extended = to_xml(&inv, ZugferdProfile::Extended).unwrap();
+    assert_eq!(
+        extended
+            .matches("<ram:ApplicableTradePaymentDiscountTerms>")
+            .count(),
+        2
+    );
+    assert!(
+        extended.contains(r#"<ram:BasisPeriodMeasure unitCode="DAY">14</ram:BasisPeriodMeasure>"#)
+    );
+    assert!(extended.contains("<ram:CalculationPercent>2.00</ram:CalculationPercent>"));
+
+    // Structured terms alone are enough to recover the discounts
+    let stripped = extended.replace(
+        "#SKONTO#TAGE=14#PROZENT=2.00#\n#SKONTO#TAGE=30#PROZENT=1.00#\n",
+        "Skonto siehe Konditionen",
+    );
+    assert_ne!(stripped, extended);
+    let back = faktura::xrechnung::from_cii_xml(&stripped).unwrap();
+    assert_eq!(back.cash_discounts, inv.cash_discounts);
+}
+
+#[test]
+#[cfg(feature = "xrechnung")]
+fn skonto_parsed_from_kosit_fixture() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/xrechnung-testsuite/standard/01.10a-INVOICE_ubl.xml");
+    let xml = std::fs::read_to_string(path).unwrap();
+    let inv = faktura::xrechnung::from_ubl_xml(&xml).unwrap();
+    let days: Vec<u32> = inv.cash_discounts.iter().map(|d| d.days).collect();
+    assert_eq!(days, vec![7, 14, 30]);
+    assert_eq!(inv.cash_discounts[0].percent, dec!(2.00));
+}
+
 // ---- 3. Exempt VAT end-to-end ----
 
 #[test]
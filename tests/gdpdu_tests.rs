@@ -326,6 +326,58 @@ fn rechnungsausgang_currency() {
     assert!(rechnungen.contains("\"EUR\""));
 }
 
+#[test]
+fn rechnungsausgang_eur_columns_for_foreign_currency() {
+    let inv = InvoiceBuilder::new("RE-2024-USD", date(2024, 6, 17))
+        .currency("USD")
+        .tax_point_date(date(2024, 6, 14))
+        .exchange_rate(ExchangeRate::new(
+            "USD",
+            dec!(1.0708),
+            date(2024, 6, 14),
+            RateSource::EcbDaily,
+        ))
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Kunde AG",
+                AddressBuilder::new("München", "80331", "DE").build(),
+            )
+            .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let export = to_gdpdu(&[inv], &default_config()).unwrap();
+    let row = export.files[1].1.lines().next().unwrap();
+    assert!(
+        row.ends_with(";\"USD\";\"380\";1,070800;266,16;1666,98"),
+        "unexpected EUR columns: {row}"
+    );
+    assert!(export.index_xml.contains("<Name>BruttobetragEUR</Name>"));
+}
+
+#[test]
+fn rechnungsausgang_eur_columns_empty_for_eur() {
+    let export = to_gdpdu(&[domestic_invoice()], &default_config()).unwrap();
+    let row = export.files[1].1.lines().next().unwrap();
+    assert!(
+        row.ends_with(";\"380\";;;"),
+        "unexpected EUR columns: {row}"
+    );
+}
+
 #[test]
 fn rechnungsausgang_type_code() {
     let inv = domestic_invoice();
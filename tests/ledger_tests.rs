@@ -0,0 +1,226 @@
+use chrono::NaiveDate;
+use faktura::core::ledger::{ItemStatus, PostingKind};
+use faktura::core::*;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+/// Invoice over `hours` × 100 EUR net + 19 % VAT, due 30 days after issue.
+fn invoice(number: &str, buyer: &str, issued: NaiveDate, hours: Decimal) -> InvoiceBuilder {
+    InvoiceBuilder::new(number, issued)
+        .due_date(issued + chrono::Duration::days(30))
+        .tax_point_date(issued)
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(buyer, AddressBuilder::new("München", "80331", "DE").build()).build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", hours, "HUR", dec!(100))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+}
+
+#[test]
+fn payment_within_skonto_period_settles_item() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(10))
+        .add_cash_discount(CashDiscount::new(10, dec!(2)))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+
+    let skonto = ledger
+        .apply_payment("RE-1", date(2024, 6, 8), dec!(1166.20))
+        .unwrap();
+    assert_eq!(skonto, dec!(23.80));
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(0));
+    assert_eq!(item.status(), ItemStatus::Paid);
+    assert_eq!(item.postings[1].kind, PostingKind::Skonto);
+}
+
+#[test]
+fn late_skonto_payment_stays_partially_open() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(10))
+        .add_cash_discount(CashDiscount::new(10, dec!(2)))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+
+    let skonto = ledger
+        .apply_payment("RE-1", date(2024, 6, 20), dec!(1166.20))
+        .unwrap();
+    assert_eq!(skonto, dec!(0));
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(23.80));
+    assert_eq!(item.status(), ItemStatus::PartiallyPaid);
+}
+
+#[test]
+fn outstanding_over_time() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(10))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+    ledger
+        .apply_payment("RE-1", date(2024, 7, 1), dec!(500))
+        .unwrap();
+    ledger
+        .apply_payment("RE-1", date(2024, 8, 1), dec!(690))
+        .unwrap();
+
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding_at(date(2024, 5, 31)), dec!(0));
+    assert_eq!(item.outstanding_at(date(2024, 6, 30)), dec!(1190));
+    assert_eq!(item.outstanding_at(date(2024, 7, 1)), dec!(690));
+    assert_eq!(item.outstanding_at(date(2024, 8, 1)), dec!(0));
+    assert!(ledger.open_items(date(2024, 8, 1)).is_empty());
+    assert_eq!(ledger.balance(date(2024, 7, 15))["EUR"], dec!(690));
+}
+
+#[test]
+fn overpayment() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(1))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+    ledger
+        .apply_payment("RE-1", date(2024, 6, 10), dec!(120))
+        .unwrap();
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(-1));
+    assert_eq!(item.status(), ItemStatus::Overpaid);
+}
+
+#[test]
+fn credit_note_applied_to_preceding_invoice() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(10))
+        .build()
+        .unwrap();
+    let credit = invoice("GS-1", "Kunde AG", date(2024, 6, 10), dec!(4))
+        .type_code(InvoiceTypeCode::CreditNote)
+        .add_preceding_invoice("RE-1", Some(date(2024, 6, 1)))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+    ledger.add_invoice(&credit).unwrap();
+
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(714));
+    assert_eq!(
+        item.postings[0].kind,
+        PostingKind::CreditNote("GS-1".into())
+    );
+    assert_eq!(ledger.item("GS-1").unwrap().outstanding(), dec!(0));
+
+    ledger
+        .apply_payment("RE-1", date(2024, 6, 20), dec!(714))
+        .unwrap();
+    assert_eq!(ledger.item("RE-1").unwrap().status(), ItemStatus::Credited);
+}
+
+#[test]
+fn credit_note_without_open_invoice_is_open_credit() {
+    let credit = invoice("GS-1", "Kunde AG", date(2024, 6, 10), dec!(1))
+        .type_code(InvoiceTypeCode::CreditNote)
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&credit).unwrap();
+    let item = ledger.item("GS-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(-119));
+    assert_eq!(item.status(), ItemStatus::Open);
+    assert_eq!(ledger.balance(date(2024, 6, 30))["EUR"], dec!(-119));
+}
+
+#[test]
+fn write_off_remainder() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 1, 1), dec!(10))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+    ledger
+        .apply_payment("RE-1", date(2024, 2, 1), dec!(190))
+        .unwrap();
+    ledger.write_off("RE-1", date(2024, 6, 30), None).unwrap();
+
+    let item = ledger.item("RE-1").unwrap();
+    assert_eq!(item.outstanding(), dec!(0));
+    assert_eq!(item.status(), ItemStatus::WrittenOff);
+    assert_eq!(item.outstanding_at(date(2024, 6, 29)), dec!(1000));
+}
+
+#[test]
+fn duplicate_and_unknown_invoices_rejected() {
+    let inv = invoice("RE-1", "Kunde AG", date(2024, 6, 1), dec!(1))
+        .build()
+        .unwrap();
+    let mut ledger = OpenItemsLedger::new();
+    ledger.add_invoice(&inv).unwrap();
+    assert!(ledger.add_invoice(&inv).is_err());
+    assert!(
+        ledger
+            .apply_payment("RE-2", date(2024, 6, 10), dec!(1))
+            .is_err()
+    );
+    assert!(ledger.write_off("RE-2", date(2024, 6, 10), None).is_err());
+    assert!(
+        ledger
+            .apply_payment("RE-1", date(2024, 6, 10), dec!(0))
+            .is_err()
+    );
+    assert!(
+        ledger
+            .apply_payment("RE-1", date(2024, 6, 10), dec!(-5))
+            .is_err()
+    );
+    assert!(ledger.item("RE-1").unwrap().postings.is_empty());
+}
+
+#[test]
+fn aging_report_by_buyer() {
+    let mut ledger = OpenItemsLedger::new();
+    // Due dates: 2024-07-01, 2024-05-02, 2024-03-02, 2024-01-31
+    for (number, buyer, issued) in [
+        ("RE-1", "Alpha GmbH", date(2024, 6, 1)),
+        ("RE-2", "Alpha GmbH", date(2024, 4, 2)),
+        ("RE-3", "Beta KG", date(2024, 2, 1)),
+        ("RE-4", "Beta KG", date(2024, 1, 1)),
+    ] {
+        let inv = invoice(number, buyer, issued, dec!(1)).build().unwrap();
+        ledger.add_invoice(&inv).unwrap();
+    }
+    ledger
+        .apply_payment("RE-2", date(2024, 5, 10), dec!(19))
+        .unwrap();
+
+    let report = ledger.aging(date(2024, 6, 15));
+    assert_eq!(report.rows.len(), 2);
+
+    let alpha = &report.rows[0];
+    assert_eq!(alpha.buyer, "Alpha GmbH");
+    assert_eq!(alpha.not_due, dec!(119));
+    assert_eq!(alpha.days_31_60, dec!(100));
+    assert_eq!(alpha.total(), dec!(219));
+
+    let beta = &report.rows[1];
+    assert_eq!(beta.buyer, "Beta KG");
+    assert_eq!(beta.days_61_90, dec!(0));
+    assert_eq!(beta.over_90, dec!(238));
+}
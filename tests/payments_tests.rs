@@ -0,0 +1,320 @@
+#![cfg(feature = "payments")]
+
+use chrono::NaiveDate;
+use faktura::core::*;
+use faktura::payments::*;
+use rust_decimal_macros::dec;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn invoice(number: &str, buyer: &str, qty: rust_decimal::Decimal) -> InvoiceBuilder {
+    InvoiceBuilder::new(number, date(2024, 6, 15))
+        .tax_point_date(date(2024, 6, 15))
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(buyer, AddressBuilder::new("München", "80331", "DE").build()).build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", qty, "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+}
+
+const CAMT_053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>STMT-1</MsgId><CreDtTm>2024-06-21T06:00:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>2024-06-20-001</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1000.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-06-19</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">4519.30</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-06-20</Dt></Dt>
+      </Bal>
+      <Ntry>
+        <Amt Ccy="EUR">1749.30</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2024-06-20</Dt></BookgDt>
+        <ValDt><Dt>2024-06-20</Dt></ValDt>
+        <AcctSvcrRef>BANK-0001</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+          <RltdPties>
+            <Dbtr><Pty><Nm>Kunde AG</Nm></Pty></Dbtr>
+            <DbtrAcct><Id><IBAN>DE02120300000000202051</IBAN></Id></DbtrAcct>
+            <Cdtr><Pty><Nm>ACME GmbH</Nm></Pty></Cdtr>
+          </RltdPties>
+          <RmtInf><Ustrd>Rechnung RE-2024-001 abzgl. 2% Skonto</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">2000.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2024-06-20</Dt></BookgDt>
+        <ValDt><Dt>2024-06-20</Dt></ValDt>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>RE-2024-002</EndToEndId></Refs>
+            <Amt Ccy="EUR">1500.00</Amt>
+            <RltdPties><Dbtr><Pty><Nm>Beta KG</Nm></Pty></Dbtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <Amt Ccy="EUR">500.00</Amt>
+            <RltdPties><Dbtr><Nm>Unbekannt</Nm></Dbtr></RltdPties>
+            <RmtInf><Ustrd>Spende</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">230.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2024-06-20</Dt></BookgDt>
+        <AddtlNtryInf>Miete Juni</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+const MT940: &str =
+    "{1:F01COBADEFFAXXX0000000000}{2:O9400000000000COBADEFFXXXX00000000000000000000N}{4:
+:20:STARTUMS
+:25:37040044/0532013000
+:28C:00117/001
+:60F:C240619EUR1000,00
+:61:2406200620CR1785,00NTRFNONREF//0815
+:86:166?00GUTSCHRIFT?100001?20EREF+RE-2024-003?21SVWZ+Rechnung RE-2024-003 v
+?22om 15.06.2024?30COBADEFFXXX?31DE02120300000000202051?32Kunde AG
+:61:240620D230,00NMSCNONREF
+:86:Miete Juni
+:62F:C240620EUR2555,00
+-}";
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+#[test]
+fn camt053_statement() {
+    let statements = from_camt_xml(CAMT_053).unwrap();
+    assert_eq!(statements.len(), 1);
+    let stmt = &statements[0];
+    assert_eq!(stmt.id, "2024-06-20-001");
+    assert_eq!(stmt.account.as_deref(), Some("DE89370400440532013000"));
+    assert_eq!(stmt.opening_balance, Some(dec!(1000.00)));
+    assert_eq!(stmt.closing_balance, Some(dec!(4519.30)));
+    assert_eq!(stmt.entries.len(), 4, "batch booking split per TxDtls");
+
+    let first = &stmt.entries[0];
+    assert_eq!(first.amount, dec!(1749.30));
+    assert_eq!(first.direction, CreditDebit::Credit);
+    assert_eq!(first.value_date, Some(date(2024, 6, 20)));
+    assert_eq!(first.counterparty_name.as_deref(), Some("Kunde AG"));
+    assert_eq!(
+        first.counterparty_iban.as_deref(),
+        Some("DE02120300000000202051")
+    );
+    assert_eq!(first.end_to_end_id, None);
+    assert_eq!(first.bank_reference.as_deref(), Some("BANK-0001"));
+
+    assert_eq!(stmt.entries[1].amount, dec!(1500.00));
+    assert_eq!(
+        stmt.entries[1].end_to_end_id.as_deref(),
+        Some("RE-2024-002")
+    );
+    assert_eq!(stmt.entries[2].amount, dec!(500.00));
+    assert_eq!(
+        stmt.entries[2].counterparty_name.as_deref(),
+        Some("Unbekannt")
+    );
+
+    let debit = &stmt.entries[3];
+    assert_eq!(debit.direction, CreditDebit::Debit);
+    assert_eq!(debit.remittance_info.as_deref(), Some("Miete Juni"));
+}
+
+#[test]
+fn camt054_notification() {
+    let xml = r#"<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.054.001.02">
+      <BkToCstmrDbtCdtNtfctn><Ntfctn>
+        <Id>NTF-1</Id>
+        <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+        <Ntry>
+          <Amt Ccy="EUR">100.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+          <BookgDt><DtTm>2024-06-20T10:15:00</DtTm></BookgDt>
+          <NtryDtls><TxDtls>
+            <RmtInf><Strd><CdtrRefInf><Ref>RF18539007547034</Ref></CdtrRefInf></Strd></RmtInf>
+          </TxDtls></NtryDtls>
+        </Ntry>
+      </Ntfctn></BkToCstmrDbtCdtNtfctn>
+    </Document>"#;
+    let stmt = &from_camt_xml(xml).unwrap()[0];
+    assert_eq!(stmt.id, "NTF-1");
+    assert_eq!(stmt.entries[0].booking_date, Some(date(2024, 6, 20)));
+    assert_eq!(
+        stmt.entries[0].creditor_reference.as_deref(),
+        Some("RF18539007547034")
+    );
+    assert!(from_camt_xml("<Document/>").is_err());
+}
+
+#[test]
+fn mt940_statement() {
+    let statements = from_mt940(MT940).unwrap();
+    assert_eq!(statements.len(), 1);
+    let stmt = &statements[0];
+    assert_eq!(stmt.id, "00117/001");
+    assert_eq!(stmt.account.as_deref(), Some("37040044/0532013000"));
+    assert_eq!(stmt.currency.as_deref(), Some("EUR"));
+    assert_eq!(stmt.opening_balance, Some(dec!(1000.00)));
+    assert_eq!(stmt.closing_balance, Some(dec!(2555.00)));
+    assert_eq!(stmt.entries.len(), 2);
+
+    let credit = &stmt.entries[0];
+    assert_eq!(credit.amount, dec!(1785.00));
+    assert_eq!(credit.direction, CreditDebit::Credit);
+    assert_eq!(credit.booking_date, Some(date(2024, 6, 20)));
+    assert_eq!(credit.end_to_end_id.as_deref(), Some("RE-2024-003"));
+    assert_eq!(
+        credit.remittance_info.as_deref(),
+        Some("Rechnung RE-2024-003 vom 15.06.2024")
+    );
+    assert_eq!(credit.counterparty_name.as_deref(), Some("Kunde AG"));
+    assert_eq!(
+        credit.counterparty_iban.as_deref(),
+        Some("DE02120300000000202051")
+    );
+    assert_eq!(credit.bank_reference.as_deref(), Some("0815"));
+
+    let debit = &stmt.entries[1];
+    assert_eq!(debit.direction, CreditDebit::Debit);
+    assert_eq!(debit.remittance_info.as_deref(), Some("Miete Juni"));
+}
+
+// ---------------------------------------------------------------------------
+// Matching
+// ---------------------------------------------------------------------------
+
+#[test]
+fn match_camt_credits() {
+    let invoices = vec![
+        invoice("RE-2024-001", "Kunde AG", dec!(10))
+            .add_cash_discount(CashDiscount::new(10, dec!(2)))
+            .build()
+            .unwrap(),
+        invoice("RE-2024-002", "Beta KG", dec!(10)).build().unwrap(),
+    ];
+    let stmt = &from_camt_xml(CAMT_053).unwrap()[0];
+    let matches = PaymentMatcher::new(&invoices).match_statement(stmt);
+    assert_eq!(matches.len(), 3, "debits are skipped");
+
+    let skonto = &matches[0];
+    assert_eq!(skonto.status, MatchStatus::Matched);
+    assert_eq!(skonto.invoice.unwrap().number, "RE-2024-001");
+    assert_eq!(skonto.skonto, dec!(35.70));
+    assert_eq!(skonto.confidence, 90);
+
+    let partial = &matches[1];
+    assert_eq!(partial.status, MatchStatus::Partial);
+    assert_eq!(partial.invoice.unwrap().number, "RE-2024-002");
+    assert_eq!(partial.difference, dec!(285.00));
+    assert_eq!(partial.confidence, 60);
+
+    assert_eq!(matches[2].status, MatchStatus::Unmatched);
+    assert!(matches[2].invoice.is_none());
+}
+
+#[test]
+fn match_by_iban_and_amount() {
+    let invoices = vec![
+        invoice("RE-7", "Kunde AG", dec!(10)).build().unwrap(),
+        invoice("RE-8", "Andere GmbH", dec!(10)).build().unwrap(),
+    ];
+    let mut entry = from_mt940(MT940).unwrap()[0].entries[0].clone();
+    entry.remittance_info = Some("Danke".into());
+    entry.end_to_end_id = None;
+    let entries = [entry];
+
+    // Amount alone is ambiguous between both invoices
+    let m = &match_payments(&entries, &invoices)[0];
+    assert_eq!(m.status, MatchStatus::Unmatched);
+
+    let matcher = PaymentMatcher::new(&invoices)
+        .with_buyer_account("Kunde AG", "DE02 1203 0000 0000 2020 51");
+    let m = &matcher.match_entries(&entries)[0];
+    assert_eq!(m.status, MatchStatus::Matched);
+    assert_eq!(m.invoice.unwrap().number, "RE-7");
+    assert_eq!(m.confidence, 60);
+}
+
+#[test]
+fn match_overpayment_and_late_skonto() {
+    let invoices = vec![
+        invoice("RE-2024-003", "Kunde AG", dec!(10))
+            .add_cash_discount(CashDiscount::new(3, dec!(2)))
+            .build()
+            .unwrap(),
+    ];
+    let stmt = &from_mt940(MT940).unwrap()[0];
+
+    let m = &match_payments(&stmt.entries, &invoices)[0];
+    assert_eq!(m.status, MatchStatus::Matched);
+    assert_eq!(m.skonto, dec!(0));
+
+    let mut over = stmt.entries[0].clone();
+    over.amount = dec!(1800.00);
+    let late = StatementEntry {
+        amount: dec!(1749.30),
+        ..stmt.entries[0].clone()
+    };
+    let entries = [over, late];
+    let matches = match_payments(&entries, &invoices);
+    assert_eq!(matches[0].status, MatchStatus::Overpaid);
+    assert_eq!(matches[0].difference, dec!(15.00));
+    assert_eq!(
+        matches[1].status,
+        MatchStatus::Partial,
+        "Skonto deducted after the discount period"
+    );
+    assert_eq!(matches[1].difference, dec!(35.70));
+}
+
+#[test]
+fn apply_matches_to_ledger() {
+    let invoices = vec![
+        invoice("RE-2024-001", "Kunde AG", dec!(10))
+            .add_cash_discount(CashDiscount::new(10, dec!(2)))
+            .build()
+            .unwrap(),
+        invoice("RE-2024-002", "Beta KG", dec!(10)).build().unwrap(),
+    ];
+    let mut ledger = OpenItemsLedger::new();
+    for inv in &invoices {
+        ledger.add_invoice(inv).unwrap();
+    }
+    let stmt = &from_camt_xml(CAMT_053).unwrap()[0];
+    let matches = PaymentMatcher::new(&invoices).match_statement(stmt);
+
+    assert_eq!(apply_matches(&mut ledger, &matches).unwrap(), 2);
+    assert_eq!(ledger.item("RE-2024-001").unwrap().outstanding(), dec!(0));
+    assert_eq!(
+        ledger.item("RE-2024-002").unwrap().outstanding(),
+        dec!(285.00)
+    );
+}
@@ -395,6 +395,79 @@ fn eas_case_insensitive() {
     assert!(eas_scheme_for_country("De").is_some());
 }
 
+#[test]
+fn eas_full_code_list() {
+    assert!(EasScheme::all().count() > 90);
+    assert_eq!(EasScheme::from_code("0060").unwrap().country(), None);
+    assert_eq!(EasScheme::from_code("0009").unwrap().country(), Some("FR"));
+    assert!(is_known_eas_code("EM"));
+    assert!(is_known_eas_code("0230"));
+    assert!(!is_known_eas_code("9999"));
+    let fr: Vec<&str> = eas_schemes_for_country("FR")
+        .iter()
+        .map(|s| s.code)
+        .collect();
+    assert_eq!(fr, ["0002", "0009", "0225", "9957"]);
+}
+
+#[test]
+fn peppol_checks_electronic_address_identifiers() {
+    assert!(validate_peppol(&routed_invoice()).is_empty());
+
+    let mut invoice = routed_invoice();
+    invoice.seller.electronic_address = Some(ElectronicAddress {
+        scheme: "0088".into(),
+        value: "4035811991022".into(),
+    });
+    invoice.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0208".into(),
+        value: "0403170702".into(),
+    });
+    let rules: Vec<_> = validate_peppol(&invoice)
+        .into_iter()
+        .filter_map(|e| e.rule)
+        .collect();
+    assert_eq!(rules, ["PEPPOL-COMMON-R040", "PEPPOL-COMMON-R043"]);
+
+    invoice.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0192".into(),
+        value: "974760673".into(),
+    });
+    invoice.seller.electronic_address = Some(ElectronicAddress {
+        scheme: "1234".into(),
+        value: "whatever".into(),
+    });
+    let errors = validate_peppol(&invoice);
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].rule.as_deref(), Some("PEPPOL-EN16931-CL008"));
+
+    let mut invoice = routed_invoice();
+    invoice.seller.registration_id = Some("974760674".into());
+    invoice.seller.registration_scheme = Some("0192".into());
+    invoice.buyer.registration_id = Some("NL-123".into());
+    invoice.buyer.registration_scheme = Some("EM".into());
+    let rules: Vec<_> = validate_peppol(&invoice)
+        .into_iter()
+        .filter_map(|e| e.rule)
+        .collect();
+    assert_eq!(rules, ["PEPPOL-COMMON-R041", "BR-CL-11"]);
+}
+
+#[test]
+fn invoice_response_checks_endpoint_identifiers() {
+    let mut response = InvoiceResponse::for_invoice(
+        &routed_invoice(),
+        "IR-009",
+        date(2024, 6, 20),
+        InvoiceStatus::Accepted,
+    )
+    .unwrap();
+    response.sender.endpoint = ParticipantId::new("0204", "991-12345-67");
+    let errors = validate_invoice_response(&response);
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].field, "sender.endpoint");
+}
+
 // ---------------------------------------------------------------------------
 // Constants
 // ---------------------------------------------------------------------------
@@ -406,3 +479,590 @@ fn peppol_constants_are_correct() {
     assert!(PEPPOL_INVOICE_DOCTYPE.contains("Invoice"));
     assert!(PEPPOL_CREDIT_NOTE_DOCTYPE.contains("CreditNote"));
 }
+
+// ---------------------------------------------------------------------------
+// SBDH envelope
+// ---------------------------------------------------------------------------
+
+fn routed_invoice() -> Invoice {
+    let mut invoice = peppol_invoice();
+    invoice.seller.electronic_address = Some(ElectronicAddress {
+        scheme: "9930".into(),
+        value: "DE123456789".into(),
+    });
+    invoice.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0204".into(),
+        value: "991-12345-73".into(),
+    });
+    invoice
+}
+
+#[test]
+fn sbdh_wrap_defaults_participants_from_electronic_addresses() {
+    let xml = sbdh::wrap(&routed_invoice(), None, None).unwrap();
+    assert!(xml.contains(sbdh::SBDH_NAMESPACE));
+    assert!(
+        xml.contains(
+            r#"<Identifier Authority="iso6523-actorid-upis">9930:DE123456789</Identifier>"#
+        )
+    );
+    assert!(xml.contains(
+        r#"<Identifier Authority="iso6523-actorid-upis">0204:991-12345-73</Identifier>"#
+    ));
+    assert!(xml.contains(PEPPOL_INVOICE_DOCTYPE));
+    assert!(xml.contains("<Type>COUNTRY_C1</Type>"));
+    assert!(xml.contains(PEPPOL_CUSTOMIZATION_ID));
+    assert!(xml.trim_end().ends_with("</StandardBusinessDocument>"));
+}
+
+#[test]
+fn sbdh_wrap_requires_peppol_participants() {
+    // E-mail addresses are not Peppol participant identifiers
+    assert!(sbdh::wrap(&peppol_invoice(), None, None).is_err());
+
+    let xml = sbdh::wrap(
+        &peppol_invoice(),
+        Some(ParticipantId::new("9930", "DE123456789")),
+        Some(ParticipantId::parse("iso6523-actorid-upis::0088:4035811991021").unwrap()),
+    )
+    .unwrap();
+    assert!(xml.contains("0088:4035811991021"));
+}
+
+#[test]
+fn sbdh_roundtrip() {
+    let original = routed_invoice();
+    let xml = sbdh::wrap(&original, None, None).unwrap();
+    let (header, invoice) = sbdh::unwrap(&xml).unwrap();
+
+    assert_eq!(header.sender, ParticipantId::new("9930", "DE123456789"));
+    assert_eq!(header.receiver.value, "0204:991-12345-73");
+    assert_eq!(header.document_type, "Invoice");
+    assert_eq!(header.document_type_id, PEPPOL_INVOICE_DOCTYPE);
+    assert_eq!(header.process_id, PEPPOL_PROFILE_ID);
+    assert_eq!(header.country_c1.as_deref(), Some("DE"));
+    assert_eq!(header.instance_identifier.len(), 36);
+    assert!(header.validate().is_empty());
+
+    assert_eq!(invoice.number, original.number);
+    assert_eq!(
+        invoice.totals.unwrap().amount_due,
+        original.totals.unwrap().amount_due
+    );
+}
+
+#[test]
+fn sbdh_credit_note_document_type() {
+    let mut credit_note = peppol_credit_note();
+    credit_note.seller.electronic_address = routed_invoice().seller.electronic_address;
+    credit_note.buyer.electronic_address = routed_invoice().buyer.electronic_address;
+    let xml = sbdh::wrap(&credit_note, None, None).unwrap();
+    let (header, parsed) = sbdh::unwrap(&xml).unwrap();
+    assert_eq!(header.document_type, "CreditNote");
+    assert_eq!(header.document_type_id, PEPPOL_CREDIT_NOTE_DOCTYPE);
+    assert_eq!(parsed.type_code, InvoiceTypeCode::CreditNote);
+}
+
+#[test]
+fn self_billed_invoice_uses_self_billing_profile() {
+    let mut invoice = routed_invoice();
+    invoice.type_code = InvoiceTypeCode::SelfBilled;
+    invoice.notes.push("Gutschrift".into());
+    invoice.seller.address.country_code = "AT".into();
+    assert!(validate_peppol(&invoice).is_empty());
+
+    let xml = to_ubl_xml(&invoice).unwrap();
+    assert!(xml.contains(PEPPOL_SELF_BILLING_CUSTOMIZATION_ID));
+    assert!(xml.contains(PEPPOL_SELF_BILLING_PROFILE_ID));
+    assert!(xml.contains("<cbc:InvoiceTypeCode>389</cbc:InvoiceTypeCode>"));
+
+    // The buyer issues the Gutschrift and sends it to the seller
+    let (header, parsed) = sbdh::unwrap(&sbdh::wrap(&invoice, None, None).unwrap()).unwrap();
+    assert_eq!(header.sender.value, "0204:991-12345-73");
+    assert_eq!(header.receiver, ParticipantId::new("9930", "DE123456789"));
+    assert_eq!(header.document_type_id, PEPPOL_SELF_BILLING_INVOICE_DOCTYPE);
+    assert_eq!(header.process_id, PEPPOL_SELF_BILLING_PROFILE_ID);
+    assert_eq!(header.country_c1.as_deref(), Some("DE"));
+    assert_eq!(parsed.type_code, InvoiceTypeCode::SelfBilled);
+}
+
+#[test]
+fn self_billed_credit_note_document_type() {
+    let mut credit_note = routed_invoice();
+    credit_note.type_code = InvoiceTypeCode::SelfBilledCreditNote;
+    let xml = sbdh::wrap(&credit_note, None, None).unwrap();
+    let (header, parsed) = sbdh::unwrap(&xml).unwrap();
+    assert_eq!(header.document_type, "CreditNote");
+    assert_eq!(
+        header.document_type_id,
+        PEPPOL_SELF_BILLING_CREDIT_NOTE_DOCTYPE
+    );
+    assert_eq!(parsed.type_code, InvoiceTypeCode::SelfBilledCreditNote);
+}
+
+#[test]
+fn sbdh_validation_errors() {
+    let mut header = Sbdh::for_invoice(&routed_invoice(), None, None).unwrap();
+    header.receiver.value = "invalid".into();
+    header.country_c1 = None;
+    header.document_type_id = PEPPOL_CREDIT_NOTE_DOCTYPE.into();
+    let errors = header.validate();
+    assert!(errors.iter().any(|e| e.field == "receiver.value"));
+    assert!(errors.iter().any(|e| e.field == "country_c1"));
+    assert!(errors.iter().any(|e| e.field == "document_type_id"));
+    assert!(
+        header
+            .wrap_payload(&to_ubl_xml(&routed_invoice()).unwrap())
+            .is_err()
+    );
+}
+
+#[test]
+fn sbdh_unwrap_rejects_mismatched_payload() {
+    let xml = sbdh::wrap(&routed_invoice(), None, None).unwrap();
+    let tampered = xml.replacen("<Type>Invoice</Type>", "<Type>CreditNote</Type>", 1);
+    assert!(sbdh::unwrap(&tampered).is_err());
+
+    let no_country = xml.replace("<Type>COUNTRY_C1</Type>", "<Type>OTHER</Type>");
+    assert!(sbdh::unwrap(&no_country).is_err());
+
+    assert!(sbdh::unwrap(&to_ubl_xml(&routed_invoice()).unwrap()).is_err());
+}
+
+#[test]
+fn sbdh_payload_namespace_declared_on_envelope() {
+    let ubl = "xmlns:ubl=\"urn:oasis:names:specification:ubl:schema:xsd:Invoice-2\"";
+    let xml = sbdh::wrap(&routed_invoice(), None, None)
+        .unwrap()
+        .replacen(&format!(" {ubl}"), "", 1)
+        .replacen(
+            "<StandardBusinessDocument ",
+            &format!("<StandardBusinessDocument {ubl} "),
+            1,
+        );
+    let (header, payload) = sbdh::parse_envelope(&xml).unwrap();
+    assert_eq!(header.document_type, "Invoice");
+    assert!(!payload.contains(ubl));
+}
+
+// ---------------------------------------------------------------------------
+// Invoice Response and Message Level Response
+// ---------------------------------------------------------------------------
+
+#[test]
+fn invoice_response_generation() {
+    let invoice = routed_invoice();
+    let response = InvoiceResponse::for_invoice(
+        &invoice,
+        "IR-001",
+        date(2024, 6, 20),
+        InvoiceStatus::Rejected,
+    )
+    .unwrap()
+    .reason(
+        response::StatusReason::Prices,
+        Some("Unit price differs from the order"),
+    )
+    .action(response::StatusAction::NewInvoice, None);
+    assert!(response.responds_to(&invoice));
+    assert!(validate_invoice_response(&response).is_empty());
+
+    let xml = response.to_xml().unwrap();
+    assert!(xml.contains(response::APPLICATION_RESPONSE_NAMESPACE));
+    assert!(xml.contains(
+        "<cbc:CustomizationID>urn:fdc:peppol.eu:poacc:trns:invoice_response:3</cbc:CustomizationID>"
+    ));
+    assert!(xml.contains(r#"<cbc:EndpointID schemeID="0204">991-12345-73</cbc:EndpointID>"#));
+    assert!(xml.contains("<cbc:RegistrationName>Buyer AG</cbc:RegistrationName>"));
+    assert!(xml.contains(r#"<cbc:ResponseCode listID="UNCL4343OpSubset">RE</cbc:ResponseCode>"#));
+    assert!(
+        xml.contains(r#"<cbc:StatusReasonCode listID="OPStatusReason">PRI</cbc:StatusReasonCode>"#)
+    );
+    assert!(
+        xml.contains(r#"<cbc:StatusReasonCode listID="OPStatusAction">NIN</cbc:StatusReasonCode>"#)
+    );
+    assert!(xml.contains("<cbc:ID>PEPP-001</cbc:ID>"));
+    assert!(xml.contains("<cbc:DocumentTypeCode>380</cbc:DocumentTypeCode>"));
+    assert!(xml.starts_with("<?xml"));
+}
+
+#[test]
+fn invoice_response_roundtrip() {
+    let invoice = routed_invoice();
+    let mut response =
+        InvoiceResponse::for_invoice(&invoice, "IR-002", date(2024, 6, 20), InvoiceStatus::Paid)
+            .unwrap()
+            .reason(response::StatusReason::PartiallyPaid, Some("50 % paid"));
+    response.effective_date = Some(date(2024, 6, 19));
+    response.note = Some("Rest follows".into());
+    let parsed = InvoiceResponse::from_xml(&response.to_xml().unwrap()).unwrap();
+    assert_eq!(parsed, response);
+    assert!(parsed.responds_to(&invoice));
+
+    let mut other = routed_invoice();
+    other.number = "PEPP-002".into();
+    assert!(!parsed.responds_to(&other));
+}
+
+#[test]
+fn invoice_response_needs_peppol_addresses_and_valid_rules() {
+    // E-mail addresses cannot be Peppol endpoints
+    assert!(
+        InvoiceResponse::for_invoice(
+            &peppol_invoice(),
+            "IR-003",
+            date(2024, 6, 20),
+            InvoiceStatus::Accepted
+        )
+        .is_err()
+    );
+    let response = InvoiceResponse::for_invoice(
+        &routed_invoice(),
+        "IR-003",
+        date(2024, 6, 20),
+        InvoiceStatus::UnderQuery,
+    )
+    .unwrap();
+    let errors = validate_invoice_response(&response);
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert!(matches!(
+        response.to_xml(),
+        Err(RechnungError::Validation(_))
+    ));
+}
+
+#[test]
+fn invoice_response_rejects_other_documents() {
+    let mlr = MessageLevelResponse::for_message(
+        &sbdh::Sbdh::for_invoice(&routed_invoice(), None, None).unwrap(),
+        "MLR-1",
+        date(2024, 6, 20),
+        &[],
+    );
+    let xml = mlr.to_xml().unwrap();
+    assert!(InvoiceResponse::from_xml(&xml).is_err());
+    assert!(InvoiceResponse::from_xml(&to_ubl_xml(&routed_invoice()).unwrap()).is_err());
+}
+
+#[test]
+fn mlr_reports_validation_errors() {
+    let mut invoice = routed_invoice();
+    invoice.buyer_reference = None;
+    let envelope = sbdh::wrap(&invoice, None, None).unwrap();
+    let (header, received) = sbdh::unwrap(&envelope).unwrap();
+    let errors = validate_peppol(&received);
+    assert!(!errors.is_empty());
+
+    let mlr = MessageLevelResponse::for_message(&header, "MLR-2", date(2024, 6, 20), &errors);
+    assert_eq!(mlr.code, response::MlrCode::Rejected);
+    assert_eq!(mlr.sender.endpoint, header.receiver);
+    assert_eq!(mlr.receiver.endpoint, header.sender);
+    assert!(validate_mlr(&mlr).is_empty());
+
+    let xml = mlr.to_xml().unwrap();
+    assert!(
+        xml.contains(
+            "<cbc:CustomizationID>urn:fdc:peppol.eu:poacc:trns:mlr:3</cbc:CustomizationID>"
+        )
+    );
+    assert!(xml.contains(&format!("<cbc:ID>{}</cbc:ID>", header.instance_identifier)));
+    assert!(xml.contains("<cbc:LineID>NA</cbc:LineID>"));
+    assert!(
+        xml.contains(r#"<cbc:StatusReasonCode listID="PEPPOLSubset">BV</cbc:StatusReasonCode>"#)
+    );
+    assert!(xml.contains("PEPPOL-EN16931-R003"));
+
+    let parsed = MessageLevelResponse::from_xml(&xml).unwrap();
+    assert_eq!(parsed, mlr);
+}
+
+#[test]
+fn mlr_accepts_valid_message_and_wraps_in_sbdh() {
+    let header = sbdh::Sbdh::for_invoice(&routed_invoice(), None, None).unwrap();
+    let mut mlr = MessageLevelResponse::for_message(&header, "MLR-3", date(2024, 6, 20), &[]);
+    assert_eq!(mlr.code, response::MlrCode::Accepted);
+    mlr.issues.push(response::MlrIssue {
+        location: Some("/ubl:Invoice/cbc:Note".into()),
+        kind: response::IssueKind::BusinessRuleWarning,
+        description: "Note is very long".into(),
+    });
+    assert!(validate_mlr(&mlr).is_empty());
+
+    let envelope = mlr.wrap("DE").unwrap();
+    let (mlr_header, payload) = sbdh::parse_envelope(&envelope).unwrap();
+    assert_eq!(mlr_header.document_type_id, response::PEPPOL_MLR_DOCTYPE);
+    assert_eq!(mlr_header.sender, header.receiver);
+    assert_eq!(MessageLevelResponse::from_xml(payload).unwrap(), mlr);
+
+    mlr.code = response::MlrCode::Rejected;
+    assert!(mlr.wrap("DE").is_err());
+}
+
+// ---------------------------------------------------------------------------
+// SMP / SML lookup
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "peppol-smp")]
+mod smp_lookup {
+    use super::*;
+    use faktura::peppol::smp::*;
+    use std::collections::HashMap;
+
+    /// Self-signed test certificate: CN=PDE000123, serial 1a2b3c.
+    const CERTIFICATE: &[&str] = &[
+        "MIIDbDCCAlSgAwIBAgIDGis8MA0GCSqGSIb3DQEBCwUAME4xCzAJBgNVBAYTAkRF",
+        "MRIwEAYDVQQKDAlBQ01FIEdtYkgxFzAVBgNVBAsMDlBFUFBPTCBURVNUIEFQMRIw",
+        "EAYDVQQDDAlQREUwMDAxMjMwHhcNMjYxMDE4MTMzMDE1WhcNMjgxMDE3MTMzMDE1",
+        "WjBOMQswCQYDVQQGEwJERTESMBAGA1UECgwJQUNNRSBHbWJIMRcwFQYDVQQLDA5Q",
+        "RVBQT0wgVEVTVCBBUDESMBAGA1UEAwwJUERFMDAwMTIzMIIBIjANBgkqhkiG9w0B",
+        "AQEFAAOCAQ8AMIIBCgKCAQEAg8WU0Y/74LKC49O2+I9phl3mkOHCReA1POqfRCAH",
+        "StprvQ2Fn+PRIA0yRd/PzesoVHlCsKl34zZz+qpht3tDjqDQCNNQQoX1yy1kW4RN",
+        "rO9K4a78xSsHkK73azKPYJprZbPtSBYR99Aae+rkDjFW1hNmglS06mqAELeBI6p4",
+        "L8mcwsJ0Zpt9P3z8Wd07be0fJUHA53rdY2Xt3OrzdOaYxQkho/eTkGRJFAJMDHvF",
+        "OTxlquymM4NaKFiTRJJg6U/Pvi7pxuEq1mt3FezeKqAQ3eOQnf40c6zrWehyaOsN",
+        "rpbDbUmfhbNLB4LrvEJucPhr1+ifxiOSgWxGHmjLSRxHIQIDAQABo1MwUTAdBgNV",
+        "HQ4EFgQUXKMBR3KFV1MxCjlHbx3i+8Vr5rMwHwYDVR0jBBgwFoAUXKMBR3KFV1Mx",
+        "CjlHbx3i+8Vr5rMwDwYDVR0TAQH/BAUwAwEB/zANBgkqhkiG9w0BAQsFAAOCAQEA",
+        "awwrknxZEv2Txxsy1yfz0WyMkCNjxp1cQSNgdKlaPdTiLjJL+9dNcJa5c1JGvRIa",
+        "g9p3x529LjPB64TU6a8jbclgkEbHMkDQiS6nmqly2uwfsB/tbsNMLHvB0M9WNRTo",
+        "2wZhxSNt5lS/joFvzbllsLmyK4sevWPz7ybEWFJ34cMD9FAUeSveMLd/bd4zyBm0",
+        "Qa/h083rmzHFe5HH7YefanJmjfeVAGF1GsIy+1MEFWwcBlzXrrl72t9xeTY4gwBf",
+        "t3L7lzXFu4sYiITYws504OYKwwUf0rcAbW3FEbJMW1UZOE7wNwACsZJLRG2dEVas",
+        "pG2ZjwPPzjTfe6oa+xHy+g==",
+    ];
+
+    const SMP: &str = "http://smp.example.org";
+
+    struct StubDns(HashMap<String, Vec<NaptrRecord>>);
+
+    impl DnsResolver for StubDns {
+        fn naptr(&self, name: &str) -> Result<Vec<NaptrRecord>, SmpError> {
+            Ok(self.0.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    struct StubHttp(HashMap<String, String>);
+
+    impl HttpClient for StubHttp {
+        fn get(&self, url: &str) -> Result<HttpResponse, SmpError> {
+            Ok(match self.0.get(url) {
+                Some(body) => HttpResponse {
+                    status: 200,
+                    body: body.clone(),
+                },
+                None => HttpResponse {
+                    status: 404,
+                    body: String::new(),
+                },
+            })
+        }
+    }
+
+    fn encode(text: &str) -> String {
+        text.replace(':', "%3A").replace('#', "%23")
+    }
+
+    fn buyer() -> ParticipantId {
+        ParticipantId::new("0204", "991-12345-67")
+    }
+
+    fn naptr(smp: &str) -> NaptrRecord {
+        NaptrRecord {
+            order: 100,
+            preference: 10,
+            flags: "U".into(),
+            service: "Meta:SMP".into(),
+            regexp: format!("!^.*$!{smp}!"),
+            replacement: ".".into(),
+        }
+    }
+
+    fn service_metadata(doctype: &str, address: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<SignedServiceMetadata xmlns="http://busdox.org/serviceMetadata/publishing/1.0/"
+    xmlns:ids="http://busdox.org/transport/identifiers/1.0/"
+    xmlns:wsa="http://www.w3.org/2005/08/addressing">
+  <ServiceMetadata>
+    <ServiceInformation>
+      <ids:ParticipantIdentifier scheme="iso6523-actorid-upis">0204:991-12345-67</ids:ParticipantIdentifier>
+      <ids:DocumentIdentifier scheme="busdox-docid-qns">{doctype}</ids:DocumentIdentifier>
+      <ProcessList>
+        <Process>
+          <ids:ProcessIdentifier scheme="cenbii-procid-ubl">urn:fdc:peppol.eu:2017:poacc:billing:01:1.0</ids:ProcessIdentifier>
+          <ServiceEndpointList>
+            <Endpoint transportProfile="peppol-transport-as4-v2_0">
+              <wsa:EndpointReference><wsa:Address>{address}</wsa:Address></wsa:EndpointReference>
+              <RequireBusinessLevelSignature>false</RequireBusinessLevelSignature>
+              <ServiceActivationDate>2024-01-01T00:00:00Z</ServiceActivationDate>
+              <Certificate>{}</Certificate>
+              <ServiceDescription>ACME Access Point</ServiceDescription>
+              <TechnicalContactUrl>mailto:ap@acme.example</TechnicalContactUrl>
+            </Endpoint>
+          </ServiceEndpointList>
+        </Process>
+      </ProcessList>
+    </ServiceInformation>
+  </ServiceMetadata>
+  <Signature xmlns="http://www.w3.org/2000/09/xmldsig#">
+    <SignedInfo><Reference URI=""><DigestValue>AAAA</DigestValue></Reference></SignedInfo>
+    <SignatureValue>AAAA</SignatureValue>
+  </Signature>
+</SignedServiceMetadata>"#,
+            CERTIFICATE.join("\n")
+        )
+    }
+
+    fn client() -> SmpClient<StubDns, StubHttp> {
+        let participant = buyer();
+        let pid = encode(&participant.to_string());
+        let invoice_url = format!(
+            "{SMP}/{pid}/services/{}",
+            encode(&format!("busdox-docid-qns::{PEPPOL_INVOICE_DOCTYPE}"))
+        );
+        let credit_note_url = format!(
+            "{SMP}/{pid}/services/{}",
+            encode(&format!("busdox-docid-qns::{PEPPOL_CREDIT_NOTE_DOCTYPE}"))
+        );
+        let group = format!(
+            r#"<ServiceGroup xmlns="http://busdox.org/serviceMetadata/publishing/1.0/"
+    xmlns:ids="http://busdox.org/transport/identifiers/1.0/">
+  <ids:ParticipantIdentifier scheme="iso6523-actorid-upis">0204:991-12345-67</ids:ParticipantIdentifier>
+  <ServiceMetadataReferenceCollection>
+    <ServiceMetadataReference href="{invoice_url}"/>
+    <ServiceMetadataReference href="{credit_note_url}"/>
+  </ServiceMetadataReferenceCollection>
+</ServiceGroup>"#
+        );
+        // The credit note metadata lives on another SMP.
+        let redirect_target = "http://smp2.example.org/credit-note";
+        let redirect = format!(
+            r#"<SignedServiceMetadata xmlns="http://busdox.org/serviceMetadata/publishing/1.0/">
+  <ServiceMetadata><Redirect href="{redirect_target}"><CertificateUID>X</CertificateUID></Redirect></ServiceMetadata>
+</SignedServiceMetadata>"#
+        );
+
+        let dns = StubDns(HashMap::from([(
+            bdxl_name(&participant, SMK_ZONE),
+            vec![naptr(SMP)],
+        )]));
+        let http = StubHttp(HashMap::from([
+            (format!("{SMP}/{pid}"), group),
+            (
+                invoice_url,
+                service_metadata(PEPPOL_INVOICE_DOCTYPE, "https://ap.acme.example/as4"),
+            ),
+            (credit_note_url, redirect),
+            (
+                redirect_target.to_string(),
+                service_metadata(PEPPOL_CREDIT_NOTE_DOCTYPE, "https://ap2.acme.example/as4"),
+            ),
+        ]));
+        SmpClient::new(dns, http).with_zone(SMK_ZONE)
+    }
+
+    #[test]
+    fn bdxl_name_format() {
+        let name = bdxl_name(&buyer(), SML_ZONE);
+        let (hash, zone) = name.split_once('.').unwrap();
+        assert_eq!(zone, "iso6523-actorid-upis.edelivery.tech.ec.europa.eu");
+        assert_eq!(hash.len(), 52);
+        assert!(
+            hash.chars()
+                .all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c))
+        );
+        assert_eq!(
+            name,
+            bdxl_name(&ParticipantId::new("0204", "991-12345-67"), SML_ZONE)
+        );
+    }
+
+    #[test]
+    fn lookup_reports_document_types_and_endpoints() {
+        let client = client();
+        assert_eq!(client.smp_url(&buyer()).unwrap().as_deref(), Some(SMP));
+
+        let info = client.lookup(&buyer()).unwrap().unwrap();
+        assert_eq!(info.smp_url, SMP);
+        assert_eq!(info.services.len(), 2);
+        assert!(info.supports(PEPPOL_INVOICE_DOCTYPE));
+        assert!(info.supports(PEPPOL_CREDIT_NOTE_DOCTYPE));
+        assert!(!info.supports("urn:example:unknown"));
+
+        let invoice = info.service(PEPPOL_INVOICE_DOCTYPE).unwrap();
+        assert_eq!(
+            invoice.document_type,
+            format!("busdox-docid-qns::{PEPPOL_INVOICE_DOCTYPE}")
+        );
+        assert_eq!(invoice.participant, Some(buyer()));
+        assert_eq!(invoice.transport_profiles(), vec![TRANSPORT_AS4]);
+
+        let endpoint = info
+            .endpoint(
+                PEPPOL_INVOICE_DOCTYPE,
+                "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0",
+                TRANSPORT_AS4,
+            )
+            .unwrap();
+        assert_eq!(endpoint.address, "https://ap.acme.example/as4");
+        assert!(!endpoint.require_business_level_signature);
+        assert_eq!(endpoint.description.as_deref(), Some("ACME Access Point"));
+        assert!(endpoint.activation.is_some());
+        assert!(endpoint.expiration.is_none());
+
+        let certificate = endpoint.certificate.as_ref().unwrap();
+        assert_eq!(certificate.common_name(), Some("PDE000123"));
+        assert_eq!(
+            certificate.subject,
+            "CN=PDE000123,OU=PEPPOL TEST AP,O=ACME GmbH,C=DE"
+        );
+        assert_eq!(certificate.issuer, certificate.subject);
+        assert_eq!(certificate.serial, "1a2b3c");
+        assert_eq!(
+            certificate.sha256_fingerprint,
+            "bba4b8a28200a6ca94e59333aecfdfd3131def3b65af04f299791abba0ad0560"
+        );
+        assert!(certificate.is_valid_at(date(2027, 1, 1).and_hms_opt(0, 0, 0).unwrap()));
+        assert!(!certificate.is_valid_at(date(2029, 1, 1).and_hms_opt(0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn service_metadata_follows_redirect() {
+        let metadata = client()
+            .service_metadata(&buyer(), PEPPOL_CREDIT_NOTE_DOCTYPE)
+            .unwrap();
+        assert!(metadata.redirect.is_none());
+        let endpoint = metadata
+            .endpoint("urn:fdc:peppol.eu:2017:poacc:billing:01:1.0", TRANSPORT_AS4)
+            .unwrap();
+        assert_eq!(endpoint.address, "https://ap2.acme.example/as4");
+    }
+
+    #[test]
+    fn unregistered_participant() {
+        let client = client();
+        let unknown = ParticipantId::new("9930", "DE999999999");
+        assert!(!client.is_registered(&unknown).unwrap());
+        assert!(client.lookup(&unknown).unwrap().is_none());
+        assert!(matches!(
+            client.service_group(&unknown),
+            Err(SmpError::NotFound(_))
+        ));
+        assert!(matches!(
+            client.service_metadata(&buyer(), "urn:example:unknown"),
+            Err(SmpError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse_service_group("<Invoice/>"),
+            Err(SmpError::Parse(_))
+        ));
+        assert!(parse_service_metadata("<ServiceGroup/>").is_err());
+        let broken =
+            service_metadata(PEPPOL_INVOICE_DOCTYPE, "x").replace(&CERTIFICATE[1][..8], "!!!!!!!!");
+        assert!(parse_service_metadata(&broken).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+#![cfg(feature = "qr")]
+
+use chrono::NaiveDate;
+use faktura::core::*;
+use faktura::qr::*;
+use rust_decimal_macros::dec;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn payment(iban: &str, remittance: Option<&str>) -> PaymentInstructions {
+    PaymentInstructions {
+        means_code: PaymentMeansCode::SepaCreditTransfer,
+        means_text: None,
+        remittance_info: remittance.map(Into::into),
+        credit_transfer: Some(CreditTransfer {
+            iban: iban.into(),
+            bic: Some("COBADEFFXXX".into()),
+            account_name: Some("ACME GmbH".into()),
+        }),
+        card_payment: None,
+        direct_debit: None,
+    }
+}
+
+fn invoice(currency: &str, payment: PaymentInstructions) -> Invoice {
+    InvoiceBuilder::new("RE-2024-001", date(2024, 6, 15))
+        .currency(currency)
+        .tax_point_date(date(2024, 6, 15))
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE")
+                    .street("Friedrichstraße 123")
+                    .build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Kunde AG",
+                AddressBuilder::new("Zürich", "8001", "CH")
+                    .street("Bahnhofstrasse 1")
+                    .build(),
+            )
+            .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .payment(payment)
+        .build()
+        .unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// EPC069-12 GiroCode
+// ---------------------------------------------------------------------------
+
+#[test]
+fn epc_payload_from_invoice() {
+    let inv = invoice("EUR", payment("DE89 3704 0044 0532 0130 00", None));
+    let epc = EpcPayload::from_invoice(&inv).unwrap();
+    assert_eq!(
+        epc.to_payload().unwrap(),
+        "BCD\n002\n1\nSCT\nCOBADEFFXXX\nACME GmbH\nDE89370400440532013000\nEUR1785.00\n\n\nRE-2024-001"
+    );
+}
+
+#[test]
+fn epc_structured_reference() {
+    let inv = invoice(
+        "EUR",
+        payment("DE89370400440532013000", Some("RF18 5390 0754 7034")),
+    );
+    let epc = EpcPayload::from_invoice(&inv).unwrap();
+    assert_eq!(
+        epc.remittance,
+        Some(EpcRemittance::Structured("RF18539007547034".into()))
+    );
+    let payload = epc.to_payload().unwrap();
+    assert!(payload.ends_with("EUR1785.00\n\nRF18539007547034"));
+}
+
+#[test]
+fn epc_latin1_encoding() {
+    let mut epc = EpcPayload::new("Müller & Söhne", "DE89370400440532013000");
+    epc.charset = EpcCharset::Latin1;
+    let bytes = epc.to_bytes().unwrap();
+    assert!(bytes.starts_with(b"BCD\n002\n2\nSCT\n\nM\xfcller & S\xf6hne\n"));
+}
+
+#[test]
+fn epc_rejects_foreign_currency() {
+    let inv = invoice("CHF", payment("DE89370400440532013000", None));
+    assert!(EpcPayload::from_invoice(&inv).is_err());
+}
+
+#[test]
+fn epc_payload_size_limit() {
+    let mut epc = EpcPayload::new("A".repeat(70), "DE89370400440532013000");
+    epc.remittance = Some(EpcRemittance::Unstructured("ü".repeat(140)));
+    epc.information = Some("B".repeat(70));
+    // 140 × 2 UTF-8 bytes pushes the payload past 331 bytes
+    assert!(epc.to_payload().is_err());
+}
+
+#[test]
+fn epc_qr_symbol() {
+    let inv = invoice("EUR", payment("DE89370400440532013000", None));
+    let qr = EpcPayload::from_invoice(&inv).unwrap().to_qr().unwrap();
+    assert_eq!(qr.size(), qr.version() as usize * 4 + 17);
+    let svg = qr.to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    let dim = qr.size() + 8;
+    assert!(svg.contains(&format!("viewBox=\"0 0 {dim} {dim}\"")));
+}
+
+// ---------------------------------------------------------------------------
+// Swiss QR-bill
+// ---------------------------------------------------------------------------
+
+#[test]
+fn swiss_qr_bill_with_qr_reference() {
+    let inv = invoice(
+        "CHF",
+        payment(
+            "CH44 3199 9123 0008 8901 2",
+            Some("21 00000 00003 13947 14300 09017"),
+        ),
+    );
+    let bill = SwissQrBill::from_invoice(&inv).unwrap();
+    let payload = bill.to_payload().unwrap();
+    let lines: Vec<&str> = payload.split('\n').collect();
+    assert_eq!(lines.len(), 31);
+    assert_eq!(&lines[..4], &["SPC", "0200", "1", "CH4431999123000889012"]);
+    assert_eq!(
+        &lines[4..11],
+        &[
+            "S",
+            "ACME GmbH",
+            "Friedrichstraße 123",
+            "",
+            "10115",
+            "Berlin",
+            "DE"
+        ]
+    );
+    assert!(lines[11..18].iter().all(|l| l.is_empty()));
+    assert_eq!(&lines[18..20], &["1785.00", "CHF"]);
+    assert_eq!(lines[21], "Kunde AG");
+    assert_eq!(
+        &lines[27..],
+        &["QRR", "210000000003139471430009017", "RE-2024-001", "EPD"]
+    );
+    assert!(bill.to_svg().unwrap().contains("fill=\"#FFFFFF\"/></svg>"));
+}
+
+#[test]
+fn swiss_qr_bill_without_reference() {
+    let inv = invoice("CHF", payment("CH93 0076 2011 6238 5295 7", None));
+    let payload = SwissQrBill::from_invoice(&inv)
+        .unwrap()
+        .to_payload()
+        .unwrap();
+    assert!(payload.ends_with("NON\n\nRE-2024-001\nEPD"));
+}
+
+#[test]
+fn swiss_qr_iban_requires_qr_reference() {
+    let inv = invoice(
+        "CHF",
+        payment("CH44 3199 9123 0008 8901 2", Some("RE-2024-001")),
+    );
+    let bill = SwissQrBill::from_invoice(&inv).unwrap();
+    assert!(bill.to_payload().is_err());
+
+    let inv = invoice("CHF", payment("DE89370400440532013000", None));
+    let bill = SwissQrBill::from_invoice(&inv).unwrap();
+    assert!(bill.validate().is_err(), "German IBAN on a QR-bill");
+}
+
+// ---------------------------------------------------------------------------
+// Round trip through a reference decoder
+// ---------------------------------------------------------------------------
+
+/// Error correction codewords per block at level M (ISO/IEC 18004 Table 9).
+const ECC_PER_BLOCK_M: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+
+/// Error correction blocks at level M (ISO/IEC 18004 Table 9).
+const BLOCKS_M: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23,
+    25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+
+/// Alignment pattern centres (ISO/IEC 18004 Annex E), versions 1–20.
+const ALIGNMENT: [&[usize]; 21] = [
+    &[],
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+    &[6, 30, 54],
+    &[6, 32, 58],
+    &[6, 34, 62],
+    &[6, 26, 46, 66],
+    &[6, 26, 48, 70],
+    &[6, 26, 50, 74],
+    &[6, 30, 54, 78],
+    &[6, 30, 56, 82],
+    &[6, 30, 58, 86],
+    &[6, 34, 62, 90],
+];
+
+/// Remainder of `value` (already shifted) divided by the BCH generator.
+fn bch_remainder(mut value: u32, generator: u32) -> u32 {
+    let degree = 31 - generator.leading_zeros();
+    while value >> degree != 0 {
+        value ^= generator << (31 - value.leading_zeros() - degree);
+    }
+    value
+}
+
+fn gf_mul(mut x: u8, mut y: u8) -> u8 {
+    let mut product = 0;
+    while y != 0 {
+        if y & 1 != 0 {
+            product ^= x;
+        }
+        x = (x << 1) ^ if x & 0x80 != 0 { 0x1d } else { 0 };
+        y >>= 1;
+    }
+    product
+}
+
+/// Read a level-M symbol back into its byte-mode payload, checking the
+/// format and version information and the Reed–Solomon syndromes of every
+/// block on the way.
+fn decode(qr: &QrCode) -> Vec<u8> {
+    decode_modules(qr.size(), |x, y| qr.is_dark(x, y))
+}
+
+fn decode_modules(size: usize, dark: impl Fn(usize, usize) -> bool) -> Vec<u8> {
+    let version = (size - 17) / 4;
+    assert!(version <= 20, "reference decoder covers versions 1–20");
+    let bit = |x: usize, y: usize| u32::from(dark(x, y));
+
+    // Format information, first copy: bit 14 at (0, 8) … bit 0 at (8, 0)
+    let mut format = 0;
+    for x in [0, 1, 2, 3, 4, 5, 7, 8] {
+        format = format << 1 | bit(x, 8);
+    }
+    for y in [7, 5, 4, 3, 2, 1, 0] {
+        format = format << 1 | bit(8, y);
+    }
+    let mut second = 0;
+    for y in (size - 7..size).rev() {
+        second = second << 1 | bit(8, y);
+    }
+    for x in size - 8..size {
+        second = second << 1 | bit(x, 8);
+    }
+    assert_eq!(format, second, "format information copies differ");
+    let data = (format ^ 0x5412) >> 10;
+    assert_eq!(
+        (data << 10 | bch_remainder(data << 10, 0x537)) ^ 0x5412,
+        format
+    );
+    assert_eq!(data >> 3, 0b00, "error correction level M");
+    let mask = data & 7;
+
+    if version >= 7 {
+        let expected = (version as u32) << 12 | bch_remainder((version as u32) << 12, 0x1f25);
+        for i in 0..18 {
+            let (a, b) = (size - 11 + i % 3, i / 3);
+            assert_eq!(bit(a, b), expected >> i & 1, "version information");
+            assert_eq!(bit(b, a), expected >> i & 1, "version information");
+        }
+    }
+
+    let last = size - 1;
+    let function = |x: usize, y: usize| {
+        let finder = ((x < 9 || x >= size - 8) && y < 9) || (x < 9 && y >= size - 8);
+        let alignment = ALIGNMENT[version].iter().any(|&cy| {
+            ALIGNMENT[version].iter().any(|&cx| {
+                let corner =
+                    (cx == 6 && (cy == 6 || cy == last - 6)) || (cx == last - 6 && cy == 6);
+                !corner && x.abs_diff(cx) <= 2 && y.abs_diff(cy) <= 2
+            })
+        });
+        let version_info = version >= 7
+            && ((x < 6 && y >= size - 11 && y < size - 8)
+                || (y < 6 && x >= size - 11 && x < size - 8));
+        finder || alignment || version_info || x == 6 || y == 6
+    };
+    let masked = |x: usize, y: usize| {
+        let (i, j) = (y, x);
+        match mask {
+            0 => (i + j) % 2 == 0,
+            1 => i % 2 == 0,
+            2 => j % 3 == 0,
+            3 => (i + j) % 3 == 0,
+            4 => (i / 2 + j / 3) % 2 == 0,
+            5 => (i * j) % 2 + (i * j) % 3 == 0,
+            6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+            _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+        }
+    };
+
+    // Two-column zigzag from the bottom right, skipping the vertical timing
+    let mut bits = Vec::new();
+    let mut right = last as isize;
+    let mut upward = true;
+    while right > 0 {
+        if right == 6 {
+            right = 5;
+        }
+        for step in 0..size {
+            let y = if upward { last - step } else { step };
+            for x in [right as usize, right as usize - 1] {
+                if !function(x, y) {
+                    bits.push(dark(x, y) ^ masked(x, y));
+                }
+            }
+        }
+        upward = !upward;
+        right -= 2;
+    }
+    let codewords: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0, |acc, &b| acc << 1 | u8::from(b)))
+        .collect();
+
+    // De-interleave: short blocks first, then blocks one data codeword longer
+    let (blocks, ecc) = (BLOCKS_M[version], ECC_PER_BLOCK_M[version]);
+    let short_len = codewords.len() / blocks;
+    let long_blocks = codewords.len() % blocks;
+    let data_len = |block: usize| short_len - ecc + usize::from(block >= blocks - long_blocks);
+    let mut split: Vec<Vec<u8>> = vec![Vec::new(); blocks];
+    let mut next = codewords.iter();
+    for i in 0..=short_len - ecc {
+        for (block, words) in split.iter_mut().enumerate() {
+            if i < data_len(block) {
+                words.push(*next.next().unwrap());
+            }
+        }
+    }
+    for _ in 0..ecc {
+        for words in split.iter_mut() {
+            words.push(*next.next().unwrap());
+        }
+    }
+    assert!(next.next().is_none());
+
+    let mut data = Vec::new();
+    for (block, words) in split.iter().enumerate() {
+        let mut alpha = 1u8;
+        for _ in 0..ecc {
+            let syndrome = words.iter().fold(0, |acc, &w| gf_mul(acc, alpha) ^ w);
+            assert_eq!(syndrome, 0, "Reed–Solomon syndrome of block {block}");
+            alpha = gf_mul(alpha, 2);
+        }
+        data.extend_from_slice(&words[..data_len(block)]);
+    }
+
+    // Byte mode segment, terminator and pad codewords
+    let bits: Vec<bool> = data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 != 0))
+        .collect();
+    let read = |from: usize, count: usize| {
+        bits[from..from + count]
+            .iter()
+            .fold(0usize, |acc, &b| acc << 1 | usize::from(b))
+    };
+    assert_eq!(read(0, 4), 0b0100, "byte mode");
+    let count_bits = if version < 10 { 8 } else { 16 };
+    let len = read(4, count_bits);
+    let start = 4 + count_bits;
+    let payload: Vec<u8> = (0..len).map(|i| read(start + i * 8, 8) as u8).collect();
+    let end = start + len * 8;
+    let padding = end.div_ceil(8).max((end + 4).min(bits.len()).div_ceil(8));
+    assert!(bits[end..padding * 8].iter().all(|&b| !b), "terminator");
+    for (i, &pad) in data[padding..].iter().enumerate() {
+        assert_eq!(pad, [0xec, 0x11][i % 2], "pad codeword {i}");
+    }
+    payload
+}
+
+#[test]
+fn epc_qr_symbol_decodes_to_payload() {
+    let inv = invoice(
+        "EUR",
+        payment("DE89370400440532013000", Some("RF18 5390 0754 7034")),
+    );
+    let epc = EpcPayload::from_invoice(&inv).unwrap();
+    assert_eq!(decode(&epc.to_qr().unwrap()), epc.to_bytes().unwrap());
+
+    let mut latin1 = EpcPayload::new("Müller & Söhne", "DE89370400440532013000");
+    latin1.charset = EpcCharset::Latin1;
+    latin1.remittance = Some(EpcRemittance::Unstructured("Straße ".repeat(20)));
+    let qr = latin1.to_qr().unwrap();
+    assert!(qr.version() >= 7, "version information is covered");
+    assert_eq!(decode(&qr), latin1.to_bytes().unwrap());
+}
+
+#[test]
+fn swiss_qr_symbol_decodes_to_payload() {
+    let inv = invoice(
+        "CHF",
+        payment(
+            "CH44 3199 9123 0008 8901 2",
+            Some("21 00000 00003 13947 14300 09017"),
+        ),
+    );
+    let bill = SwissQrBill::from_invoice(&inv).unwrap();
+    let qr = bill.to_qr().unwrap();
+    assert!(qr.version() >= 10, "16-bit character count is covered");
+    assert_eq!(decode(&qr), bill.to_payload().unwrap().into_bytes());
+}
+
+#[test]
+fn qr_symbols_decode_at_every_length() {
+    for len in (0..=600).step_by(7) {
+        let data: Vec<u8> = (0..len).map(|i| (i * 31 % 251) as u8).collect();
+        let qr = QrCode::encode(&data, EcLevel::M).unwrap();
+        if qr.version() > 20 {
+            break;
+        }
+        assert_eq!(decode(&qr), data, "{len} bytes");
+    }
+}
+
+#[test]
+#[should_panic(expected = "Reed–Solomon syndrome")]
+fn reference_decoder_notices_a_flipped_module() {
+    let qr = QrCode::encode(b"BCD\n002\n1\nSCT", EcLevel::M).unwrap();
+    let last = qr.size() - 1;
+    decode_modules(qr.size(), |x, y| {
+        qr.is_dark(x, y) != (x == last && y == last)
+    });
+}
@@ -0,0 +1,362 @@
+#![cfg(feature = "sepa")]
+
+use chrono::{NaiveDate, NaiveDateTime};
+use faktura::core::*;
+use faktura::sepa::*;
+use rust_decimal_macros::dec;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn created() -> NaiveDateTime {
+    date(2024, 6, 16).and_hms_opt(9, 30, 0).unwrap()
+}
+
+fn transfer(iban: &str, remittance: Option<&str>) -> PaymentInstructions {
+    PaymentInstructions {
+        means_code: PaymentMeansCode::SepaCreditTransfer,
+        means_text: None,
+        remittance_info: remittance.map(Into::into),
+        credit_transfer: Some(CreditTransfer {
+            iban: iban.into(),
+            bic: Some("COBADEFFXXX".into()),
+            account_name: None,
+        }),
+        card_payment: None,
+        direct_debit: None,
+    }
+}
+
+fn direct_debit(mandate: &str, creditor_id: &str) -> PaymentInstructions {
+    PaymentInstructions {
+        means_code: PaymentMeansCode::SepaDirectDebit,
+        means_text: None,
+        remittance_info: None,
+        credit_transfer: None,
+        card_payment: None,
+        direct_debit: Some(DirectDebit {
+            mandate_id: Some(mandate.into()),
+            creditor_id: Some(creditor_id.into()),
+            debited_account_id: Some("DE02 1203 0000 0000 2020 51".into()),
+        }),
+    }
+}
+
+fn invoice(number: &str, payment: PaymentInstructions) -> InvoiceBuilder {
+    InvoiceBuilder::new(number, date(2024, 6, 15))
+        .tax_point_date(date(2024, 6, 15))
+        .due_date(date(2024, 7, 15))
+        .seller(
+            PartyBuilder::new(
+                "Lieferant GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Kunde AG",
+                AddressBuilder::new("München", "80331", "DE").build(),
+            )
+            .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .payment(payment)
+}
+
+fn debtor() -> SepaAccount {
+    SepaAccount::new("ACME GmbH", "DE89 3704 0044 0532 0130 00").with_bic("COBADEFFXXX")
+}
+
+// ---------------------------------------------------------------------------
+// pain.001 credit transfers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn credit_transfer_from_invoice_with_skonto() {
+    let inv = invoice("ER-4711", transfer("DE02 1203 0000 0000 2020 51", None))
+        .add_cash_discount(CashDiscount::new(10, dec!(2)))
+        .build()
+        .unwrap();
+
+    let within = CreditTransferTransaction::from_invoice(&inv, date(2024, 6, 25)).unwrap();
+    assert_eq!(within.amount, dec!(1749.30));
+    assert_eq!(within.creditor.name, "Lieferant GmbH");
+    assert_eq!(within.creditor.iban, "DE02120300000000202051");
+    assert_eq!(
+        within.remittance,
+        Some(SepaRemittance::Unstructured("ER-4711".into()))
+    );
+
+    let late = CreditTransferTransaction::from_invoice(&inv, date(2024, 6, 26)).unwrap();
+    assert_eq!(late.amount, dec!(1785.00));
+}
+
+#[test]
+fn suggested_execution_date_prefers_skonto() {
+    let inv = invoice("ER-1", transfer("DE02120300000000202051", None))
+        .add_cash_discount(CashDiscount::new(10, dec!(3)))
+        .add_cash_discount(CashDiscount::new(20, dec!(2)))
+        .build()
+        .unwrap();
+    assert_eq!(
+        suggested_execution_date(&inv, date(2024, 6, 16)),
+        date(2024, 6, 25)
+    );
+    assert_eq!(
+        suggested_execution_date(&inv, date(2024, 6, 30)),
+        date(2024, 7, 5)
+    );
+    assert_eq!(
+        suggested_execution_date(&inv, date(2024, 7, 6)),
+        date(2024, 7, 15),
+        "no Skonto left, pay on the due date"
+    );
+    assert_eq!(
+        suggested_execution_date(&inv, date(2024, 8, 1)),
+        date(2024, 8, 1)
+    );
+}
+
+#[test]
+fn pain001_groups_by_execution_date() {
+    let a = invoice("ER-1", transfer("DE02120300000000202051", None))
+        .build()
+        .unwrap();
+    let b = invoice(
+        "ER-2",
+        transfer("DE02120300000000202051", Some("RF18 5390 0754 7034")),
+    )
+    .build()
+    .unwrap();
+
+    let mut batch = CreditTransferBatch::new("PAY-20240616", created(), debtor());
+    batch
+        .add(CreditTransferTransaction::from_invoice(&a, date(2024, 6, 20)).unwrap())
+        .add(CreditTransferTransaction::from_invoice(&b, date(2024, 6, 18)).unwrap())
+        .add(CreditTransferTransaction::from_invoice(&a, date(2024, 6, 20)).unwrap());
+    assert_eq!(batch.control_sum(), dec!(5355.00));
+
+    let xml = batch.to_xml().unwrap();
+    assert!(xml.contains(&format!("<Document xmlns=\"{PAIN_001_NAMESPACE}\">")));
+    assert!(xml.contains("<MsgId>PAY-20240616</MsgId>"));
+    assert!(xml.contains("<CreDtTm>2024-06-16T09:30:00</CreDtTm>"));
+    assert!(xml.contains("<CtrlSum>5355.00</CtrlSum>"));
+    assert_eq!(xml.matches("<PmtInf>").count(), 2);
+    let first = xml.find("<Dt>2024-06-18</Dt>").unwrap();
+    let second = xml.find("<Dt>2024-06-20</Dt>").unwrap();
+    assert!(first < second, "blocks ordered by date");
+    assert!(xml.contains("<PmtInfId>PAY-20240616-2</PmtInfId>"));
+    assert!(xml.contains("<InstdAmt Ccy=\"EUR\">1785.00</InstdAmt>"));
+    assert!(xml.contains("<BICFI>COBADEFFXXX</BICFI>"));
+    assert!(xml.contains("<Cd>SCOR</Cd>"));
+    assert!(xml.contains("<Ref>RF18539007547034</Ref>"));
+    assert!(xml.contains("<Ustrd>ER-1</Ustrd>"));
+}
+
+#[test]
+fn payment_block_ids_unique_for_long_message_id() {
+    let message_id = "PAY-2024-06-16-0930-ABCDEFGHIJKLMNO";
+    assert_eq!(message_id.len(), 35);
+    let inv = invoice("ER-1", transfer("DE02120300000000202051", None))
+        .build()
+        .unwrap();
+    let mut batch = CreditTransferBatch::new(message_id, created(), debtor());
+    batch
+        .add(CreditTransferTransaction::from_invoice(&inv, date(2024, 6, 18)).unwrap())
+        .add(CreditTransferTransaction::from_invoice(&inv, date(2024, 6, 20)).unwrap());
+    let xml = batch.to_xml().unwrap();
+    assert!(xml.contains(&format!("<MsgId>{message_id}</MsgId>")));
+    assert!(xml.contains("<PmtInfId>PAY-2024-06-16-0930-ABCDEFGHIJKLM-1</PmtInfId>"));
+    assert!(xml.contains("<PmtInfId>PAY-2024-06-16-0930-ABCDEFGHIJKLM-2</PmtInfId>"));
+
+    let dd = invoice("RE-1", direct_debit("M-1", "DE98ZZZ09999999999"))
+        .build()
+        .unwrap();
+    let mut tx =
+        DirectDebitTransaction::from_invoice(&dd, date(2023, 1, 10), date(2024, 6, 20)).unwrap();
+    let mut batch = DirectDebitBatch::new(
+        message_id,
+        created(),
+        creditor(),
+        "DE98ZZZ09999999999",
+        SepaScheme::Core,
+    );
+    batch.add(tx.clone());
+    tx.sequence = SequenceType::First;
+    batch.add(tx);
+    let xml = batch.to_xml().unwrap();
+    assert!(xml.contains("<PmtInfId>PAY-2024-06-16-0930-ABCDEFGHIJKLM-1</PmtInfId>"));
+    assert!(xml.contains("<PmtInfId>PAY-2024-06-16-0930-ABCDEFGHIJKLM-2</PmtInfId>"));
+}
+
+#[test]
+fn pain001_rejects_invalid_iban() {
+    let inv = invoice("ER-1", transfer("DE03120300000000202051", None))
+        .build()
+        .unwrap();
+    let mut batch = CreditTransferBatch::new("PAY-1", created(), debtor());
+    batch.add(CreditTransferTransaction::from_invoice(&inv, date(2024, 6, 20)).unwrap());
+    let err = batch.to_xml().unwrap_err();
+    assert!(err.to_string().contains("creditor IBAN"), "{err}");
+
+    let empty = CreditTransferBatch::new("PAY-2", created(), debtor());
+    assert!(empty.to_xml().is_err());
+}
+
+#[test]
+fn credit_transfer_requires_eur_and_account() {
+    let chf = invoice("ER-1", transfer("DE02120300000000202051", None))
+        .currency("CHF")
+        .build()
+        .unwrap();
+    assert!(CreditTransferTransaction::from_invoice(&chf, date(2024, 6, 20)).is_err());
+
+    let dd = invoice("ER-2", direct_debit("M-1", "DE98ZZZ09999999999"))
+        .build()
+        .unwrap();
+    assert!(CreditTransferTransaction::from_invoice(&dd, date(2024, 6, 20)).is_err());
+}
+
+// ---------------------------------------------------------------------------
+// pain.008 direct debits
+// ---------------------------------------------------------------------------
+
+fn creditor() -> SepaAccount {
+    SepaAccount::new("ACME GmbH", "DE89370400440532013000")
+}
+
+#[test]
+fn pain008_core_batch() {
+    let inv = invoice(
+        "RE-2024-001",
+        direct_debit("MANDAT-42", "DE98ZZZ09999999999"),
+    )
+    .build()
+    .unwrap();
+    let mut tx =
+        DirectDebitTransaction::from_invoice(&inv, date(2023, 1, 10), date(2024, 6, 20)).unwrap();
+    assert_eq!(tx.debtor.name, "Kunde AG");
+    assert_eq!(tx.debtor.iban, "DE02120300000000202051");
+    assert_eq!(tx.sequence, SequenceType::Recurring);
+
+    let mut batch = DirectDebitBatch::new(
+        "DD-20240616",
+        created(),
+        creditor(),
+        "DE98ZZZ09999999999",
+        SepaScheme::Core,
+    );
+    batch.add(tx.clone());
+    tx.sequence = SequenceType::First;
+    batch.add(tx);
+
+    let xml = batch.to_xml().unwrap();
+    assert!(xml.contains(&format!("<Document xmlns=\"{PAIN_008_NAMESPACE}\">")));
+    assert_eq!(xml.matches("<PmtInf>").count(), 2, "one block per SeqTp");
+    assert!(xml.contains("<SeqTp>FRST</SeqTp>"));
+    assert!(xml.contains("<SeqTp>RCUR</SeqTp>"));
+    assert!(xml.contains("<Cd>CORE</Cd>"));
+    assert!(xml.contains("<ReqdColltnDt>2024-06-20</ReqdColltnDt>"));
+    assert!(xml.contains("<Id>DE98ZZZ09999999999</Id>"));
+    assert!(xml.contains("<MndtId>MANDAT-42</MndtId>"));
+    assert!(xml.contains("<DtOfSgntr>2023-01-10</DtOfSgntr>"));
+    assert!(xml.contains("<Id>NOTPROVIDED</Id>"));
+    assert!(xml.contains("<CtrlSum>3570.00</CtrlSum>"));
+}
+
+#[test]
+fn pain008_b2b_and_skonto() {
+    let inv = invoice(
+        "RE-2024-002",
+        direct_debit("MANDAT-7", "DE98ZZZ09999999999"),
+    )
+    .add_cash_discount(CashDiscount::new(14, dec!(2)))
+    .build()
+    .unwrap();
+    let tx =
+        DirectDebitTransaction::from_invoice(&inv, date(2023, 1, 10), date(2024, 6, 20)).unwrap();
+    assert_eq!(tx.amount, dec!(1749.30));
+
+    let mut batch = DirectDebitBatch::new(
+        "DD-2",
+        created(),
+        creditor(),
+        "DE98ZZZ09999999999",
+        SepaScheme::B2b,
+    );
+    batch.add(tx);
+    let xml = batch.to_xml().unwrap();
+    assert!(xml.contains("<Cd>B2B</Cd>"));
+    assert!(xml.contains("<InstdAmt Ccy=\"EUR\">1749.30</InstdAmt>"));
+}
+
+#[test]
+fn pain008_validation() {
+    let inv = invoice("RE-1", direct_debit("M-1", "DE98ZZZ09999999999"))
+        .build()
+        .unwrap();
+    let tx =
+        DirectDebitTransaction::from_invoice(&inv, date(2023, 1, 10), date(2024, 6, 20)).unwrap();
+
+    let mut batch = DirectDebitBatch::new(
+        "DD-3",
+        created(),
+        creditor(),
+        "DE97ZZZ09999999999",
+        SepaScheme::Core,
+    );
+    batch.add(tx.clone());
+    assert!(batch.to_xml().is_err(), "invalid creditor ID");
+
+    let other = invoice("RE-2", direct_debit("M-2", "AT61ZZZ01234567890"))
+        .build()
+        .unwrap();
+    let mut batch = DirectDebitBatch::new(
+        "DD-4",
+        created(),
+        creditor(),
+        "DE98ZZZ09999999999",
+        SepaScheme::Core,
+    );
+    batch.add(
+        DirectDebitTransaction::from_invoice(&other, date(2023, 1, 10), date(2024, 6, 20)).unwrap(),
+    );
+    assert!(batch.to_xml().is_err(), "creditor ID differs from batch");
+
+    let mut batch = DirectDebitBatch::new(
+        "DD-5",
+        created(),
+        creditor(),
+        "DE98ZZZ09999999999",
+        SepaScheme::Core,
+    );
+    let mut past = tx;
+    past.collection_date = date(2024, 6, 1);
+    batch.add(past);
+    assert!(batch.to_xml().is_err(), "collection date in the past");
+
+    let no_mandate = invoice(
+        "RE-3",
+        PaymentInstructions {
+            direct_debit: Some(DirectDebit {
+                mandate_id: None,
+                creditor_id: None,
+                debited_account_id: Some("DE02120300000000202051".into()),
+            }),
+            ..direct_debit("", "")
+        },
+    )
+    .build()
+    .unwrap();
+    assert!(
+        DirectDebitTransaction::from_invoice(&no_mandate, date(2023, 1, 10), date(2024, 6, 20))
+            .is_err()
+    );
+}
@@ -0,0 +1,226 @@
+#![cfg(feature = "signature")]
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use faktura::core::*;
+use faktura::signature::*;
+use faktura::xrechnung;
+use rust_decimal_macros::dec;
+
+const SIGNER_P12: &[u8] = include_bytes!("fixtures/signature/signer.p12");
+const SIGNER_LEGACY_P12: &[u8] = include_bytes!("fixtures/signature/signer-legacy.p12");
+const CA_CERT: &str = include_str!("fixtures/signature/ca.crt");
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn invoice() -> Invoice {
+    InvoiceBuilder::new("SIG-001", date(2026, 3, 1))
+        .tax_point_date(date(2026, 3, 1))
+        .buyer_reference("04011000-12345-03")
+        .seller(
+            PartyBuilder::new(
+                "ACME GmbH",
+                AddressBuilder::new("Berlin", "10115", "DE").build(),
+            )
+            .vat_id("DE123456789")
+            .electronic_address("EM", "seller@acme.de")
+            .build(),
+        )
+        .buyer(
+            PartyBuilder::new(
+                "Kunde AG",
+                AddressBuilder::new("München", "80331", "DE").build(),
+            )
+            .electronic_address("EM", "buyer@kunde.de")
+            .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1", "Beratung", dec!(10), "HUR", dec!(150))
+                .tax(TaxCategory::StandardRate, dec!(19))
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+fn identity() -> SigningIdentity {
+    SigningIdentity::from_pkcs12(SIGNER_P12, "faktura").unwrap()
+}
+
+fn root() -> Certificate {
+    Certificate::from_pem(CA_CERT).unwrap()
+}
+
+fn options() -> SignOptions {
+    SignOptions::new()
+        .with_signing_time(Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 0).unwrap())
+        .with_reason("Rechnungsstellung")
+        .with_location("Berlin")
+}
+
+// ---------------------------------------------------------------------------
+// Keys and certificates
+// ---------------------------------------------------------------------------
+
+#[test]
+fn pkcs12_modern_and_legacy_encryption() {
+    for p12 in [SIGNER_P12, SIGNER_LEGACY_P12] {
+        let identity = SigningIdentity::from_pkcs12(p12, "faktura").unwrap();
+        let certificate = identity.certificate();
+        assert_eq!(certificate.common_name(), Some("Max Mustermann"));
+        assert_eq!(certificate.subject, "CN=Max Mustermann,O=ACME GmbH,C=DE");
+        assert_eq!(certificate.issuer, root().subject);
+        assert_eq!(identity.chain(), &[root()]);
+    }
+}
+
+#[test]
+fn pkcs12_wrong_password() {
+    let err = SigningIdentity::from_pkcs12(SIGNER_P12, "wrong").unwrap_err();
+    assert!(matches!(err, SignatureError::Pkcs12(_)), "{err}");
+    assert!(SigningIdentity::from_pkcs12(b"not a p12 file", "faktura").is_err());
+}
+
+#[test]
+fn certificate_from_pem() {
+    let root = root();
+    assert_eq!(root.common_name(), Some("Faktura Test Root CA"));
+    assert_eq!(root.issuer, root.subject);
+    assert_eq!(
+        root.not_before,
+        date(2025, 1, 1).and_hms_opt(0, 0, 0).unwrap()
+    );
+    assert_eq!(root.sha256_fingerprint.len(), 64);
+    assert!(root.is_valid_at(date(2026, 3, 1).and_hms_opt(0, 0, 0).unwrap()));
+    assert!(Certificate::from_pem("no certificate").is_err());
+}
+
+// ---------------------------------------------------------------------------
+// XAdES
+// ---------------------------------------------------------------------------
+
+#[test]
+fn xades_ubl_roundtrip() {
+    let xml = xrechnung::to_ubl_xml(&invoice()).unwrap();
+    let signed = sign_xml(&xml, &identity(), &options()).unwrap();
+
+    // The signature sits in the first child, ext:UBLExtensions
+    let body = signed.split_once("<ubl:Invoice").unwrap().1;
+    let first_child = body.split_once('>').unwrap().1.trim_start();
+    assert!(
+        first_child.starts_with("<ext:UBLExtensions"),
+        "{first_child}"
+    );
+    assert_eq!(xrechnung::from_ubl_xml(&signed).unwrap().number, "SIG-001");
+
+    assert_eq!(signed.matches("<ds:Signature ").count(), 1);
+    assert!(signed.contains("<xades:SigningTime>2026-03-01T10:15:00Z</xades:SigningTime>"));
+    // Signer certificate and its issuer
+    assert_eq!(signed.matches("<ds:X509Certificate>").count(), 2);
+    assert!(!signed.contains("<xades:UnsignedProperties>"));
+}
+
+#[test]
+fn xades_cii_roundtrip() {
+    let xml = xrechnung::to_cii_xml(&invoice()).unwrap();
+    let signed = sign_xml(&xml, &identity(), &options()).unwrap();
+    assert!(
+        signed
+            .trim_end()
+            .ends_with("</ds:Signature></rsm:CrossIndustryInvoice>")
+    );
+    assert_eq!(xrechnung::from_cii_xml(&signed).unwrap().number, "SIG-001");
+}
+
+#[test]
+fn xades_second_signature_on_ubl() {
+    let xml = xrechnung::to_ubl_xml(&invoice()).unwrap();
+    let once = sign_xml(&xml, &identity(), &options()).unwrap();
+    let twice = sign_xml(&once, &identity(), &options()).unwrap();
+    assert_eq!(twice.matches("<ds:Signature ").count(), 2);
+    // Both signatures share the one ext:UBLExtensions container
+    assert_eq!(twice.matches("<ext:UBLExtensions").count(), 1);
+    assert_eq!(xrechnung::from_ubl_xml(&twice).unwrap().number, "SIG-001");
+}
+
+// ---------------------------------------------------------------------------
+// PAdES
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "zugferd")]
+mod pades {
+    use super::*;
+    use faktura::zugferd::{self, ZugferdProfile};
+
+    fn minimal_pdf() -> Vec<u8> {
+        use lopdf::{Document, Object, Stream, dictionary};
+
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"".to_vec()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => Object::Reference(pages_id),
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Contents" => Object::Reference(content_id),
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![Object::Reference(page_id)],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => Object::Reference(pages_id),
+        });
+        doc.trailer.set("Root", Object::Reference(catalog_id));
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    fn zugferd_pdf() -> Vec<u8> {
+        let xml = zugferd::to_xml(&invoice(), ZugferdProfile::EN16931).unwrap();
+        zugferd::embed_in_pdf(&minimal_pdf(), &xml, ZugferdProfile::EN16931).unwrap()
+    }
+
+    #[test]
+    fn sign_zugferd_pdf() {
+        let pdf = zugferd_pdf();
+        let signed = sign_pdf(&pdf, &identity(), &options()).unwrap();
+
+        // Incremental update: the original revision is unchanged
+        assert_eq!(&signed[..pdf.len()], pdf.as_slice());
+        assert_eq!(
+            zugferd::extract_from_pdf(&signed).unwrap(),
+            zugferd::extract_from_pdf(&pdf).unwrap()
+        );
+        assert_eq!(zugferd::preflight(&signed), zugferd::preflight(&pdf));
+
+        let revision = String::from_utf8_lossy(&signed[pdf.len()..]);
+        assert!(
+            revision.contains("/SubFilter/ETSI.CAdES.detached"),
+            "{revision}"
+        );
+        assert!(
+            revision.contains("/Reason(Rechnungsstellung)"),
+            "{revision}"
+        );
+    }
+
+    #[test]
+    fn second_signature_leaves_first_intact() {
+        let once = sign_pdf(&zugferd_pdf(), &identity(), &options()).unwrap();
+        let twice = sign_pdf(&once, &identity(), &SignOptions::new()).unwrap();
+        assert_eq!(&twice[..once.len()], once.as_slice());
+        let revision = String::from_utf8_lossy(&twice[once.len()..]);
+        assert!(
+            revision.contains("/SubFilter/ETSI.CAdES.detached"),
+            "{revision}"
+        );
+    }
+}
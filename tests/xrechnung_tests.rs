@@ -252,6 +252,102 @@ fn ubl_roundtrip() {
     assert_eq!(parsed_ct.bic, orig_ct.bic);
 }
 
+#[test]
+fn ubl_roundtrip_document_references() {
+    let reference = |id: &str, type_code: &str, scheme: Option<&str>| AdditionalReference {
+        id: id.into(),
+        type_code: type_code.into(),
+        reference_type: scheme.map(Into::into),
+        name: None,
+        issue_date: None,
+    };
+    let mut original = xrechnung_invoice();
+    original.despatch_advices.push(DocumentReference {
+        id: "LS-1".into(),
+        issue_date: None,
+    });
+    original
+        .additional_references
+        .push(reference("LOS-3", "50", None));
+    original
+        .additional_references
+        .push(reference("METER-42", "130", Some("AAB")));
+    original.lines[0].order_line_id = Some("10".into());
+    original.lines[0]
+        .additional_references
+        .push(reference("SN-1", "130", Some("AFU")));
+
+    let xml = xrechnung::to_ubl_xml(&original).unwrap();
+    assert!(xml.contains("cac:DespatchDocumentReference"));
+    assert!(xml.contains("cac:OriginatorDocumentReference"));
+    assert!(xml.contains("<cbc:LineID>10</cbc:LineID>"));
+    let parsed = xrechnung::from_ubl_xml(&xml).unwrap();
+
+    // BT-16
+    assert_eq!(parsed.despatch_advices.len(), 1);
+    assert_eq!(parsed.despatch_advices[0].id, "LS-1");
+    // BT-17, BT-18 / BT-18-1
+    let refs: Vec<_> = parsed
+        .additional_references
+        .iter()
+        .map(|r| {
+            (
+                r.id.as_str(),
+                r.type_code.as_str(),
+                r.reference_type.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        refs,
+        [("LOS-3", "50", None), ("METER-42", "130", Some("AAB"))]
+    );
+    assert!(parsed.attachments.is_empty());
+    // BT-132, BT-128 / BT-128-1
+    assert_eq!(parsed.lines[0].order_line_id.as_deref(), Some("10"));
+    assert_eq!(parsed.lines[0].additional_references.len(), 1);
+    assert_eq!(parsed.lines[0].additional_references[0].id, "SN-1");
+    assert_eq!(
+        parsed.lines[0].additional_references[0]
+            .reference_type
+            .as_deref(),
+        Some("AFU")
+    );
+    assert!(parsed.lines[1].order_line_id.is_none());
+}
+
+#[test]
+fn ubl_and_cii_leave_out_group_and_information_lines() {
+    let mut inv = xrechnung_invoice();
+    inv.lines.insert(
+        0,
+        LineItemBuilder::new("0", "Projektpaket", dec!(1), "C62", dec!(9649.90))
+            .tax(TaxCategory::StandardRate, dec!(19))
+            .line_status(LineStatus::Group)
+            .build(),
+    );
+    inv.lines.push(
+        LineItemBuilder::new("3", "Hinweis", dec!(1), "C62", dec!(10))
+            .tax(TaxCategory::StandardRate, dec!(19))
+            .line_status(LineStatus::Information)
+            .build(),
+    );
+    calculate_totals(&mut inv, dec!(0));
+    assert_eq!(inv.totals.as_ref().unwrap().line_net_total, dec!(9649.90));
+    assert!(xrechnung::validate_xrechnung(&inv).is_empty());
+
+    let ubl = xrechnung::to_ubl_xml(&inv).unwrap();
+    assert!(!ubl.contains("Projektpaket") && !ubl.contains("Hinweis"));
+    let parsed = xrechnung::from_ubl_xml(&ubl).unwrap();
+    assert_eq!(parsed.lines.len(), 2);
+    let sum: rust_decimal::Decimal = parsed.lines.iter().filter_map(|l| l.line_amount).sum();
+    assert_eq!(sum, parsed.totals.unwrap().line_net_total);
+
+    let cii = xrechnung::to_cii_xml(&inv).unwrap();
+    assert!(!cii.contains("Projektpaket") && !cii.contains("LineStatusReasonCode"));
+    assert_eq!(xrechnung::from_cii_xml(&cii).unwrap().lines.len(), 2);
+}
+
 // ---------------------------------------------------------------------------
 // CII Roundtrip
 // ---------------------------------------------------------------------------
@@ -436,6 +532,106 @@ fn xrechnung_missing_electronic_addresses() {
     assert!(errors.iter().any(|e| e.rule.as_deref() == Some("BR-DE-28")));
 }
 
+#[test]
+fn xrechnung_checks_electronic_address_identifiers() {
+    let mut inv = xrechnung_invoice();
+    inv.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0204".into(),
+        value: "04011000-12345-04".into(),
+    });
+    let errors = xrechnung::validate_xrechnung(&inv);
+    let error = errors
+        .iter()
+        .find(|e| e.field == "buyer.electronic_address.value")
+        .expect("Leitweg-ID check digits flagged");
+    assert!(error.message.contains("must be 03"), "{error}");
+    assert_eq!(error.rule.as_deref(), Some("FAKTURA-ID-01"));
+
+    inv.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0204".into(),
+        value: "04011000-12345-03".into(),
+    });
+    inv.seller.electronic_address = Some(ElectronicAddress {
+        scheme: "XX".into(),
+        value: "seller@acme.de".into(),
+    });
+    let errors = xrechnung::validate_xrechnung(&inv);
+    assert!(
+        errors
+            .iter()
+            .all(|e| e.field != "buyer.electronic_address.value")
+    );
+    assert!(errors.iter().any(|e| e.rule.as_deref() == Some("BR-CL-25")));
+}
+
+#[test]
+fn xrechnung_checks_registration_identifiers() {
+    let mut inv = xrechnung_invoice();
+    inv.seller.registration_id = Some("4035811991022".into());
+    inv.seller.registration_scheme = Some("0088".into());
+    inv.buyer.registration_id = Some("HRB 12345".into());
+    inv.buyer.registration_scheme = Some("9930".into());
+    let errors = xrechnung::validate_xrechnung(&inv);
+    let seller = errors
+        .iter()
+        .find(|e| e.field == "seller.registration_id")
+        .expect("GLN check digit flagged");
+    assert_eq!(seller.rule.as_deref(), Some("FAKTURA-ID-01"));
+    assert!(
+        errors.iter().any(
+            |e| e.field == "buyer.registration_scheme" && e.rule.as_deref() == Some("BR-CL-11")
+        )
+    );
+
+    inv.seller.registration_id = Some("4035811991021".into());
+    inv.buyer.registration_scheme = None;
+    let errors = xrechnung::validate_xrechnung(&inv);
+    assert!(errors.iter().all(|e| !e.field.contains("registration")));
+
+    let xml = xrechnung::to_ubl_xml(&inv).unwrap();
+    assert!(xml.contains(r#"<cbc:CompanyID schemeID="0088">4035811991021</cbc:CompanyID>"#));
+    let parsed = xrechnung::from_ubl_xml(&xml).unwrap();
+    assert_eq!(parsed.seller.registration_scheme.as_deref(), Some("0088"));
+    assert_eq!(parsed.buyer.registration_scheme, None);
+    let parsed = xrechnung::from_cii_xml(&xrechnung::to_cii_xml(&inv).unwrap()).unwrap();
+    assert_eq!(parsed.seller.registration_scheme.as_deref(), Some("0088"));
+    assert_eq!(parsed.buyer.registration_id.as_deref(), Some("HRB 12345"));
+    assert_eq!(parsed.buyer.registration_scheme, None);
+}
+
+#[test]
+fn xrechnung_checks_leitweg_id_in_buyer_reference() {
+    let mut inv = xrechnung_invoice();
+    inv.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "0204".into(),
+        value: "04011000-12345-03".into(),
+    });
+    assert!(
+        xrechnung::validate_xrechnung(&inv)
+            .iter()
+            .all(|e| e.field != "buyer_reference")
+    );
+
+    inv.buyer_reference = Some("04011000-12354-03".into());
+    let errors = xrechnung::validate_xrechnung(&inv);
+    let error = errors
+        .iter()
+        .find(|e| e.field == "buyer_reference")
+        .expect("transposed Leitweg-ID flagged");
+    assert_eq!(error.rule.as_deref(), Some("FAKTURA-ID-01"));
+
+    // Private buyers may use any buyer reference
+    inv.buyer.electronic_address = Some(ElectronicAddress {
+        scheme: "EM".into(),
+        value: "buyer@kunde.de".into(),
+    });
+    assert!(
+        xrechnung::validate_xrechnung(&inv)
+            .iter()
+            .all(|e| e.field != "buyer_reference")
+    );
+}
+
 #[test]
 fn xrechnung_missing_payment() {
     let inv = InvoiceBuilder::new("RE-001", date(2024, 6, 15))
@@ -903,6 +1099,33 @@ fn credit_note_cii_roundtrip() {
     assert_eq!(parsed.lines[0].quantity, dec!(5));
 }
 
+#[test]
+fn self_billed_invoice_roundtrip() {
+    let mut inv = xrechnung_invoice();
+    inv.type_code = InvoiceTypeCode::SelfBilled;
+    let errors = xrechnung::validate_xrechnung_full(&inv);
+    assert!(errors.iter().any(|e| e.message.contains("Gutschrift")));
+
+    inv.notes.insert(0, "Gutschrift".into());
+    assert!(xrechnung::validate_xrechnung_full(&inv).is_empty());
+
+    let ubl = xrechnung::to_ubl_xml(&inv).unwrap();
+    assert!(ubl.contains("<cbc:InvoiceTypeCode>389</cbc:InvoiceTypeCode>"));
+    assert!(ubl.contains(xrechnung::PEPPOL_SELF_BILLING_PROFILE_ID));
+    assert!(ubl.contains("<cbc:Note>Gutschrift</cbc:Note>"));
+    assert_eq!(
+        xrechnung::from_ubl_xml(&ubl).unwrap().type_code,
+        InvoiceTypeCode::SelfBilled
+    );
+
+    let cii = xrechnung::to_cii_xml(&inv).unwrap();
+    assert!(cii.contains("<ram:TypeCode>389</ram:TypeCode>"));
+    assert!(cii.contains(xrechnung::PEPPOL_SELF_BILLING_PROFILE_ID));
+    let parsed = xrechnung::from_cii_xml(&cii).unwrap();
+    assert_eq!(parsed.type_code, InvoiceTypeCode::SelfBilled);
+    assert_eq!(parsed.notes[0], "Gutschrift");
+}
+
 #[test]
 fn credit_note_xrechnung_valid() {
     let inv = credit_note_invoice();
@@ -74,10 +74,91 @@ fn extract_from_extended_pdfa3a() {
     assert!(!inv.number.is_empty());
 }
 
+#[test]
+fn extract_document_from_reference_pdfs() {
+    for (file, profile) in [
+        ("EN16931_Einfach.pdf", ZugferdProfile::EN16931),
+        ("MustangBeispiel20221026.pdf", ZugferdProfile::EN16931),
+        ("zugferd_2p1_EXTENDED_PDFA-3A.pdf", ZugferdProfile::Extended),
+    ] {
+        let path = fixtures_dir().join(file);
+        if !path.exists() {
+            eprintln!("skipping: {file} not found");
+            continue;
+        }
+        let pdf_bytes = fs::read(&path).unwrap();
+        let doc = zugferd::extract_document(&pdf_bytes).unwrap();
+
+        assert_eq!(
+            doc.zugferd_version(),
+            Some(zugferd::ZugferdVersion::V2),
+            "{file}"
+        );
+        assert_eq!(doc.profile(), Some(profile), "{file}");
+        assert!(doc.af_relationship.is_some(), "{file}");
+        let xmp = doc.xmp.as_ref().expect(file);
+        assert_eq!(xmp.pdfa_part, Some(3), "{file}");
+        assert!(
+            doc.check_consistency().is_empty(),
+            "{file}: {:?}",
+            doc.check_consistency()
+        );
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Embed into reference PDFs (multi-page input test)
 // ---------------------------------------------------------------------------
 
+#[test]
+fn preflight_reference_pdfs() {
+    let path = fixtures_dir().join("zugferd_2p1_EXTENDED_PDFA-3A.pdf");
+    if !path.exists() {
+        eprintln!("skipping: reference PDF not found");
+        return;
+    }
+    let pdf_bytes = fs::read(&path).unwrap();
+    assert_eq!(zugferd::preflight(&pdf_bytes), vec![]);
+
+    // The Mustang sample's Info dictionary disagrees with its XMP
+    let path = fixtures_dir().join("MustangBeispiel20221026.pdf");
+    if path.exists() {
+        let issues = zugferd::preflight(&fs::read(&path).unwrap());
+        assert!(!issues.is_empty());
+        assert!(
+            issues.iter().all(|i| i.clause == "ISO 19005-3 6.6.3"),
+            "{issues:?}"
+        );
+    }
+}
+
+#[test]
+fn check_hybrid_reference_pdfs() {
+    for file in ["EN16931_Einfach.pdf", "zugferd_2p1_EXTENDED_PDFA-3A.pdf"] {
+        let path = fixtures_dir().join(file);
+        if !path.exists() {
+            eprintln!("skipping: {file} not found");
+            continue;
+        }
+        let errors = zugferd::check_hybrid(&fs::read(&path).unwrap()).unwrap();
+        assert!(errors.is_empty(), "{file}: {errors:?}");
+    }
+
+    // The Mustang sample prints 963,12 while its XML says 963.11
+    let path = fixtures_dir().join("MustangBeispiel20221026.pdf");
+    if path.exists() {
+        let errors = zugferd::check_hybrid(&fs::read(&path).unwrap()).unwrap();
+        let gross = errors
+            .iter()
+            .find(|e| e.field == "totals.gross_total")
+            .expect("gross total mismatch");
+        assert_eq!(
+            gross.message,
+            "gross total (BT-112) is 963.11 in the XML but the PDF shows 963,12"
+        );
+    }
+}
+
 #[test]
 fn embed_into_extended_pdf_preserves_pages() {
     let path = fixtures_dir().join("zugferd_2p1_EXTENDED_PDFA-3A.pdf");
@@ -155,6 +155,350 @@ fn xml_generation_xrechnung_profile() {
     assert!(xml.contains(xrechnung::XRECHNUNG_CUSTOMIZATION_ID));
 }
 
+// ---------------------------------------------------------------------------
+// Profile conformance
+// ---------------------------------------------------------------------------
+
+fn dropped(inv: &Invoice, profile: ZugferdProfile) -> Vec<String> {
+    zugferd::dropped_fields(inv, profile)
+        .into_iter()
+        .map(|d| d.field)
+        .collect()
+}
+
+#[test]
+fn best_profile_follows_the_data() {
+    let mut inv = test_invoice();
+    assert_eq!(zugferd::best_profile(&inv), ZugferdProfile::EN16931);
+    assert_eq!(
+        dropped(&inv, ZugferdProfile::Basic),
+        [
+            "seller.contact",
+            "payment.credit_transfer.account_name",
+            "payment.credit_transfer.bic"
+        ]
+    );
+    assert!(dropped(&inv, ZugferdProfile::EN16931).is_empty());
+
+    inv.seller.contact = None;
+    let ct = inv
+        .payment
+        .as_mut()
+        .unwrap()
+        .credit_transfer
+        .as_mut()
+        .unwrap();
+    ct.bic = None;
+    ct.account_name = None;
+    assert_eq!(zugferd::best_profile(&inv), ZugferdProfile::Basic);
+
+    inv.lines.clear();
+    assert_eq!(zugferd::best_profile(&inv), ZugferdProfile::BasicWl);
+}
+
+#[test]
+fn basicwl_drops_line_items() {
+    let inv = test_invoice();
+    let losses = zugferd::dropped_fields(&inv, ZugferdProfile::BasicWl);
+    let lines = losses.iter().find(|d| d.field == "lines").unwrap();
+    assert_eq!(lines.business_term, "BG-25");
+    assert_eq!(lines.required_profile, ZugferdProfile::Basic);
+    assert_eq!(lines.to_string(), "lines (BG-25) needs BASIC or higher");
+
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::BasicWl).unwrap();
+    assert!(!xml.contains("IncludedSupplyChainTradeLineItem"));
+    assert!(!xml.contains("DefinedTradeContact"));
+    assert!(!xml.contains("COBADEFFXXX"));
+    assert!(xml.contains("<ram:IBANID>DE89370400440532013000</ram:IBANID>"));
+    assert!(xml.contains("<ram:LineTotalAmount>1500.00</ram:LineTotalAmount>"));
+    assert!(xml.contains("<ram:CategoryCode>S</ram:CategoryCode>"));
+}
+
+#[test]
+fn minimum_keeps_only_header_data() {
+    let mut inv = test_invoice();
+    inv.order_reference = Some("PO-4711".into());
+    assert_eq!(
+        dropped(&inv, ZugferdProfile::Minimum),
+        [
+            "seller.contact",
+            "seller.electronic_address",
+            "seller.address",
+            "buyer.electronic_address",
+            "buyer.address",
+            "tax_point_date",
+            "due_date",
+            "payment_terms",
+            "payment",
+            "totals.vat_breakdown",
+            "lines"
+        ]
+    );
+
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::Minimum).unwrap();
+    assert!(xml.contains("<ram:CountryID>DE</ram:CountryID>"));
+    assert!(xml.contains(r#"<ram:ID schemeID="VA">DE123456789</ram:ID>"#));
+    assert!(xml.contains("<ram:IssuerAssignedID>PO-4711</ram:IssuerAssignedID>"));
+    assert!(!xml.contains("Berlin"));
+    assert!(!xml.contains("IBANID"));
+}
+
+#[test]
+fn validate_zugferd_per_profile() {
+    let inv = test_invoice();
+    for profile in [
+        ZugferdProfile::Minimum,
+        ZugferdProfile::BasicWl,
+        ZugferdProfile::Basic,
+        ZugferdProfile::EN16931,
+        ZugferdProfile::Extended,
+    ] {
+        let errors = zugferd::validate_zugferd(&inv, profile);
+        assert!(errors.is_empty(), "{profile:?}: {errors:?}");
+    }
+
+    let mut inv = test_invoice();
+    inv.buyer.address.country_code.clear();
+    inv.payment.as_mut().unwrap().credit_transfer = None;
+    let rules = |inv: &Invoice, profile| {
+        zugferd::validate_zugferd(inv, profile)
+            .into_iter()
+            .filter_map(|e| e.rule)
+            .collect::<Vec<_>>()
+    };
+    // Minimum carries neither the buyer address nor payment means
+    assert!(rules(&inv, ZugferdProfile::Minimum).is_empty());
+    assert_eq!(rules(&inv, ZugferdProfile::BasicWl), ["BR-11", "BR-61"]);
+
+    inv.lines.clear();
+    assert!(rules(&inv, ZugferdProfile::Basic).contains(&"BR-16".to_string()));
+    assert!(!rules(&inv, ZugferdProfile::BasicWl).contains(&"BR-16".to_string()));
+}
+
+#[test]
+fn convert_between_profiles() {
+    let inv = test_invoice();
+    let en16931 = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+
+    let down = zugferd::convert(&en16931, ZugferdProfile::BasicWl).unwrap();
+    assert_eq!(down.source_profile, Some(ZugferdProfile::EN16931));
+    assert!(down.dropped.iter().any(|d| d.field == "lines"));
+    assert!(down.errors.is_empty(), "{:?}", down.errors);
+    assert!(down.xml.contains(ZugferdProfile::BasicWl.urn()));
+
+    let up = zugferd::convert(&down.xml, ZugferdProfile::Basic).unwrap();
+    assert_eq!(up.source_profile, Some(ZugferdProfile::BasicWl));
+    assert!(up.dropped.is_empty());
+    assert!(up.errors.iter().any(|e| e.rule.as_deref() == Some("BR-16")));
+}
+
+// ---------------------------------------------------------------------------
+// Extended content
+// ---------------------------------------------------------------------------
+
+fn extended_invoice() -> Invoice {
+    let reference = |id: &str, type_code: &str| AdditionalReference {
+        id: id.into(),
+        type_code: type_code.into(),
+        reference_type: None,
+        name: None,
+        issue_date: None,
+    };
+    let base = test_invoice();
+    InvoiceBuilder::new("RE-2024-002", date(2024, 6, 15))
+        .due_date(date(2024, 7, 15))
+        .tax_point_date(date(2024, 6, 15))
+        .buyer_reference("04011000-12345-03")
+        .order_reference("PO-4711")
+        .seller(base.seller)
+        .buyer(base.buyer)
+        .payment(base.payment.unwrap())
+        .payment_terms("Zahlbar in zwei Raten")
+        .add_despatch_advice("LS-1", Some(date(2024, 6, 10)))
+        .add_despatch_advice("LS-2", None)
+        .add_delivery_note("DN-7", Some(date(2024, 6, 11)))
+        .add_additional_reference(AdditionalReference {
+            reference_type: Some("AAB".into()),
+            ..reference("METER-42", "130")
+        })
+        .add_additional_reference(reference("AB-2024-17", "220"))
+        .add_logistics_charge(LogisticsServiceCharge {
+            description: "Fracht".into(),
+            amount: dec!(25),
+            tax_category: TaxCategory::StandardRate,
+            tax_rate: dec!(19),
+        })
+        .add_payment_term(PaymentTerm {
+            description: Some("1. Rate".into()),
+            due_date: Some(date(2024, 7, 15)),
+            amount: Some(dec!(200)),
+            penalty: None,
+        })
+        .add_payment_term(PaymentTerm {
+            description: Some("2. Rate".into()),
+            due_date: Some(date(2024, 8, 15)),
+            amount: Some(dec!(127.45)),
+            penalty: Some(PaymentPenalty {
+                days: 14,
+                percent: dec!(1.5),
+                base_amount: None,
+            }),
+        })
+        .add_line(
+            LineItemBuilder::new("1", "Montageset", dec!(1), "C62", dec!(250))
+                .line_status(LineStatus::Group)
+                .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1.1", "Schrauben", dec!(2), "C62", dec!(100))
+                .parent_line("1")
+                .order_line_id("10")
+                .order_reference("PO-4712")
+                .add_measured_attribute("Gewicht", dec!(2.5), "KGM")
+                .add_additional_reference(reference("SN-1", "130"))
+                .add_additional_reference(reference("QS-9", "50"))
+                .build(),
+        )
+        .add_line(
+            LineItemBuilder::new("1.2", "Dübel", dec!(1), "C62", dec!(50))
+                .parent_line("1")
+                .build(),
+        )
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn extended_totals_count_detail_lines_and_logistics_charges() {
+    let inv = extended_invoice();
+    let totals = inv.totals.as_ref().unwrap();
+    assert_eq!(totals.line_net_total, dec!(250));
+    assert_eq!(totals.charges_total, dec!(25));
+    assert_eq!(totals.net_total, dec!(275));
+    assert_eq!(totals.gross_total, dec!(327.25));
+    assert!(validate_arithmetic(&inv).is_empty());
+    let errors = zugferd::validate_zugferd(&inv, ZugferdProfile::Extended);
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(zugferd::best_profile(&inv), ZugferdProfile::Extended);
+
+    let mut broken = inv.clone();
+    broken.lines[2].parent_line_id = Some("9".into());
+    broken.lines[0].line_amount = Some(dec!(240));
+    let fields: Vec<_> = zugferd::validate_zugferd(&broken, ZugferdProfile::Extended)
+        .into_iter()
+        .map(|e| e.field)
+        .collect();
+    assert_eq!(fields, ["lines[0].line_amount", "lines[2].parent_line_id"]);
+}
+
+#[test]
+fn extended_roundtrip() {
+    let inv = extended_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::Extended).unwrap();
+    assert!(xml.contains("<ram:ParentLineID>1</ram:ParentLineID>"));
+    assert!(xml.contains("<ram:LineStatusReasonCode>GROUP</ram:LineStatusReasonCode>"));
+    assert!(xml.contains(r#"<ram:ValueMeasure unitCode="KGM">2.50</ram:ValueMeasure>"#));
+    assert!(xml.contains("<ram:SpecifiedLogisticsServiceCharge>"));
+    assert!(xml.contains("<ram:PartialPaymentAmount>127.45</ram:PartialPaymentAmount>"));
+
+    let (parsed, _) = zugferd::from_xml(&xml).unwrap();
+    assert_eq!(parsed.lines.len(), 3);
+    let sub = &parsed.lines[1];
+    assert_eq!(sub.parent_line_id.as_deref(), Some("1"));
+    assert_eq!(sub.line_status, Some(LineStatus::Detail));
+    assert_eq!(sub.order_line_id.as_deref(), Some("10"));
+    assert_eq!(sub.order_reference.as_deref(), Some("PO-4712"));
+    assert_eq!(sub.attributes[0].measure.as_ref().unwrap().unit, "KGM");
+    let line_refs: Vec<_> = sub
+        .additional_references
+        .iter()
+        .map(|r| (r.id.as_str(), r.type_code.as_str()))
+        .collect();
+    assert_eq!(line_refs, [("QS-9", "50"), ("SN-1", "130")]);
+    assert_eq!(parsed.lines[0].line_status, Some(LineStatus::Group));
+    assert_eq!(parsed.order_reference.as_deref(), Some("PO-4711"));
+
+    assert_eq!(parsed.despatch_advices.len(), 2);
+    assert_eq!(
+        parsed.despatch_advices[0].issue_date,
+        Some(date(2024, 6, 10))
+    );
+    assert_eq!(parsed.delivery_notes[0].id, "DN-7");
+    assert!(parsed.attachments.is_empty());
+    assert_eq!(parsed.additional_references.len(), 2);
+    assert_eq!(
+        parsed.additional_references[0].reference_type.as_deref(),
+        Some("AAB")
+    );
+    assert_eq!(parsed.logistics_charges[0].amount, dec!(25));
+    assert!(parsed.charges.is_empty());
+    assert_eq!(
+        parsed.payment_terms.as_deref(),
+        Some("Zahlbar in zwei Raten")
+    );
+    assert_eq!(parsed.due_date, Some(date(2024, 7, 15)));
+    assert_eq!(parsed.payment_schedule.len(), 2);
+    assert_eq!(
+        parsed.payment_schedule[1].penalty.as_ref().unwrap().days,
+        14
+    );
+    let (expected, actual) = (inv.totals.unwrap(), parsed.totals.unwrap());
+    assert_eq!(actual.line_net_total, expected.line_net_total);
+    assert_eq!(actual.charges_total, expected.charges_total);
+    assert_eq!(actual.gross_total, expected.gross_total);
+}
+
+#[test]
+fn extended_content_below_extended() {
+    let inv = extended_invoice();
+    let losses = zugferd::dropped_fields(&inv, ZugferdProfile::EN16931);
+    let fields: Vec<_> = losses.iter().map(|d| d.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        [
+            "additional_references[1]",
+            "despatch_advices",
+            "delivery_notes",
+            "payment_schedule",
+            "lines[0]",
+            "lines[1].parent_line_id",
+            "lines[1].line_status",
+            "lines[1].attributes[0].measure",
+            "lines[1].order_reference",
+            "lines[1].additional_references[1]",
+            "lines[2].parent_line_id",
+            "lines[2].line_status",
+        ]
+    );
+    assert!(
+        losses
+            .iter()
+            .all(|d| d.required_profile == ZugferdProfile::Extended)
+    );
+
+    // Freight becomes an ordinary charge; the totals are unchanged
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    assert!(!xml.contains("SpecifiedLogisticsServiceCharge"));
+    assert!(!xml.contains("ParentLineID"));
+    assert!(!xml.contains("DN-7"));
+    assert!(!xml.contains("LS-2"));
+    assert!(xml.contains("<ram:IssuerAssignedID>LS-1</ram:IssuerAssignedID>"));
+    assert!(xml.contains("<ram:LineID>10</ram:LineID>"));
+    assert!(xml.contains("<ram:Reason>Fracht</ram:Reason>"));
+    let errors = zugferd::validate_zugferd(&inv, ZugferdProfile::EN16931);
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let (parsed, _) = zugferd::from_xml(&xml).unwrap();
+    assert_eq!(parsed.lines.len(), 2);
+    assert_eq!(parsed.charges[0].amount, dec!(25));
+    assert_eq!(parsed.additional_references.len(), 1);
+    assert_eq!(parsed.lines[0].additional_references.len(), 1);
+    let (expected, actual) = (inv.totals.unwrap(), parsed.totals.unwrap());
+    assert_eq!(actual.line_net_total, expected.line_net_total);
+    assert_eq!(actual.charges_total, expected.charges_total);
+    assert_eq!(actual.gross_total, expected.gross_total);
+}
+
 // ---------------------------------------------------------------------------
 // PDF Embedding
 // ---------------------------------------------------------------------------
@@ -200,6 +544,68 @@ fn embed_creates_valid_pdf_structure() {
     );
 }
 
+#[test]
+fn embed_with_options_is_reproducible() {
+    use chrono::{FixedOffset, TimeZone};
+    use zugferd::{Attachment, DocumentId, EmbedOptions};
+
+    let xml = zugferd::to_xml(&test_invoice(), ZugferdProfile::EN16931).unwrap();
+    let cet = FixedOffset::east_opt(3600).unwrap();
+    let options = EmbedOptions::new()
+        .with_now(cet.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap())
+        .with_document_id(DocumentId::XmlHash)
+        .with_producer("Acme Billing 4.2")
+        .with_dates(
+            cet.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap(),
+            cet.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap(),
+        );
+    let attachments = [Attachment::new("stunden.csv", b"Datum;Stunden\n".to_vec())];
+    let embed = |xml: &str| {
+        zugferd::embed_in_pdf_with_options(
+            &minimal_pdf(),
+            xml,
+            ZugferdProfile::EN16931,
+            &attachments,
+            &options,
+        )
+        .unwrap()
+    };
+
+    let pdf = embed(&xml);
+    assert_eq!(pdf, embed(&xml));
+
+    let doc = lopdf::Document::load_mem(&pdf).unwrap();
+    let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+    assert_eq!(id[0].as_str().unwrap().len(), 16);
+    assert_eq!(id[0], id[1]);
+    let other = embed(&xml.replace("RE-2024-001", "RE-2024-002"));
+    let other_doc = lopdf::Document::load_mem(&other).unwrap();
+    assert_ne!(
+        other_doc.trailer.get(b"ID").unwrap().as_array().unwrap(),
+        id
+    );
+
+    assert_eq!(
+        info_entry(&pdf, "Producer").as_deref(),
+        Some("Acme Billing 4.2")
+    );
+    assert_eq!(
+        info_entry(&pdf, "CreationDate").as_deref(),
+        Some("D:20240601090000+01'00'")
+    );
+    assert_eq!(
+        info_entry(&pdf, "ModDate").as_deref(),
+        Some("D:20240602090000+01'00'")
+    );
+    let xmp = output_xmp(&pdf);
+    assert!(xmp.contains("<xmp:ModifyDate>2024-06-02T09:00:00+01:00</xmp:ModifyDate>"));
+    let extracted = zugferd::extract_document(&pdf).unwrap();
+    assert_eq!(
+        extracted.attachments[0].modified,
+        Some(cet.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap())
+    );
+}
+
 // ---------------------------------------------------------------------------
 // Roundtrip: Embed → Extract
 // ---------------------------------------------------------------------------
@@ -293,6 +699,830 @@ fn embed_xrechnung_profile_xmp() {
     );
 }
 
+// ---------------------------------------------------------------------------
+// Extract document: metadata and attachments
+// ---------------------------------------------------------------------------
+
+/// Add a supplementary attachment (UTF-16 filename) to a ZUGFeRD PDF.
+fn add_attachment(pdf: &[u8], filename: &str, mime: &str, data: &[u8]) -> Vec<u8> {
+    use lopdf::{Document, Object, Stream, StringFormat, dictionary};
+
+    let mut doc = Document::load_mem(pdf).unwrap();
+    let stream_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Subtype" => Object::Name(mime.as_bytes().to_vec()),
+        },
+        data.to_vec(),
+    ));
+    let mut utf16 = vec![0xFE, 0xFF];
+    utf16.extend(filename.encode_utf16().flat_map(u16::to_be_bytes));
+    let spec_id = doc.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(filename),
+        "UF" => Object::String(utf16, StringFormat::Hexadecimal),
+        "Desc" => Object::string_literal("Lieferschein"),
+        "AFRelationship" => "Supplement",
+        "EF" => dictionary! { "F" => Object::Reference(stream_id) },
+    });
+
+    let catalog = doc.catalog().unwrap();
+    let names_id = catalog.get(b"Names").unwrap().as_reference().unwrap();
+    let tree_id = doc
+        .get_dictionary(names_id)
+        .unwrap()
+        .get(b"EmbeddedFiles")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+    let tree = doc.get_dictionary_mut(tree_id).unwrap();
+    let names = tree.get_mut(b"Names").unwrap().as_array_mut().unwrap();
+    names.push(Object::string_literal(filename));
+    names.push(Object::Reference(spec_id));
+    let catalog = doc.catalog_mut().unwrap();
+    let af = catalog.get_mut(b"AF").unwrap().as_array_mut().unwrap();
+    af.push(Object::Reference(spec_id));
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn extract_document_reads_xmp_and_filespec() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let pdf = zugferd::embed_in_pdf(&minimal_pdf(), &xml, ZugferdProfile::EN16931).unwrap();
+
+    let doc = zugferd::extract_document(&pdf).unwrap();
+    assert_eq!(doc.xml, xml);
+    assert_eq!(doc.filename, FACTURX_FILENAME);
+    assert_eq!(doc.af_relationship.as_deref(), Some("Alternative"));
+    assert_eq!(doc.zugferd_version(), Some(zugferd::ZugferdVersion::V2));
+    assert_eq!(doc.profile(), Some(ZugferdProfile::EN16931));
+    assert!(doc.attachments.is_empty());
+
+    let xmp = doc.xmp.as_ref().unwrap();
+    assert_eq!(xmp.conformance_level.as_deref(), Some("EN 16931"));
+    assert_eq!(xmp.document_type.as_deref(), Some("INVOICE"));
+    assert_eq!(xmp.document_file_name.as_deref(), Some(FACTURX_FILENAME));
+    assert_eq!(xmp.version.as_deref(), Some("1.0"));
+    assert_eq!(xmp.pdfa_part, Some(3));
+    assert_eq!(xmp.pdfa_conformance.as_deref(), Some("B"));
+
+    assert!(doc.check_consistency().is_empty());
+}
+
+#[test]
+fn extract_document_lists_other_attachments() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let pdf = zugferd::embed_in_pdf(&minimal_pdf(), &xml, ZugferdProfile::EN16931).unwrap();
+    let pdf = add_attachment(
+        &pdf,
+        "Lieferschein-4711.csv",
+        "text/csv",
+        b"pos;menge\n1;10\n",
+    );
+
+    let doc = zugferd::extract_document(&pdf).unwrap();
+    assert_eq!(doc.filename, FACTURX_FILENAME);
+    assert_eq!(doc.attachments.len(), 1);
+    let att = &doc.attachments[0];
+    assert_eq!(att.filename, "Lieferschein-4711.csv");
+    assert_eq!(att.mime_type.as_deref(), Some("text/csv"));
+    assert_eq!(att.description.as_deref(), Some("Lieferschein"));
+    assert_eq!(att.af_relationship.as_deref(), Some("Supplement"));
+    assert_eq!(att.data, b"pos;menge\n1;10\n");
+
+    // The plain extraction still returns the invoice, not the first attachment
+    assert_eq!(zugferd::extract_from_pdf(&pdf).unwrap(), xml);
+}
+
+#[test]
+fn extract_document_detects_profile_mismatch() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    // XMP claims EXTENDED while the XML declares EN 16931
+    let pdf = zugferd::embed_in_pdf(&minimal_pdf(), &xml, ZugferdProfile::Extended).unwrap();
+
+    let doc = zugferd::extract_document(&pdf).unwrap();
+    let errors = doc.check_consistency();
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].field, "xmp.ConformanceLevel");
+    assert!(errors[0].message.contains("EXTENDED"));
+}
+
+#[test]
+fn embed_with_attachments_roundtrip() {
+    use chrono::DateTime;
+
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let created = DateTime::parse_from_rfc3339("2024-06-14T17:45:00+02:00").unwrap();
+    let modified = DateTime::parse_from_rfc3339("2024-06-15T08:00:00+02:00").unwrap();
+    let attachments = [
+        zugferd::Attachment::new(
+            "stundenzettel.csv",
+            b"datum;stunden\n2024-06-14;8\n".to_vec(),
+        )
+        .with_description("Stundenzettel Juni")
+        .with_dates(created, modified),
+        zugferd::Attachment::new("Lieferschein-Übergabe.pdf", b"%PDF-1.7 signed".to_vec())
+            .with_relationship("Source"),
+        zugferd::Attachment::new("xrechnung.xml", xrechnung::to_cii_xml(&inv).unwrap())
+            .with_relationship("Data"),
+    ];
+    let pdf = zugferd::embed_in_pdf_with_attachments(
+        &minimal_pdf(),
+        &xml,
+        ZugferdProfile::EN16931,
+        &attachments,
+    )
+    .unwrap();
+
+    let doc = zugferd::extract_document(&pdf).unwrap();
+    assert_eq!(doc.xml, xml);
+    assert_eq!(doc.filename, FACTURX_FILENAME);
+    assert_eq!(doc.attachments.len(), 3);
+    for expected in &attachments {
+        let att = doc
+            .attachments
+            .iter()
+            .find(|a| a.filename == expected.filename)
+            .unwrap();
+        // Files without a modification date get the embedding time
+        assert!(att.modified.is_some());
+        let expected = zugferd::Attachment {
+            modified: expected.modified.or(att.modified),
+            ..expected.clone()
+        };
+        assert_eq!(*att, expected);
+    }
+    assert_eq!(
+        doc.attachments
+            .iter()
+            .find(|a| a.filename.ends_with(".pdf"))
+            .and_then(|a| a.mime_type.as_deref()),
+        Some("application/pdf")
+    );
+
+    // Name tree keys are sorted; AF lists the invoice first, then the attachments
+    let pdf_doc = lopdf::Document::load_mem(&pdf).unwrap();
+    let catalog = pdf_doc.catalog().unwrap();
+    let af = catalog.get(b"AF").unwrap().as_array().unwrap();
+    assert_eq!(af.len(), 4);
+    let names_id = catalog.get(b"Names").unwrap().as_reference().unwrap();
+    let tree_id = pdf_doc
+        .get_dictionary(names_id)
+        .unwrap()
+        .get(b"EmbeddedFiles")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+    let names = pdf_doc
+        .get_dictionary(tree_id)
+        .unwrap()
+        .get(b"Names")
+        .unwrap()
+        .as_array()
+        .unwrap();
+    let keys: Vec<&[u8]> = names
+        .iter()
+        .step_by(2)
+        .map(|k| k.as_str().unwrap())
+        .collect();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+
+    // Every embedded file carries size and MD5 checksum
+    let spec = pdf_doc
+        .get_dictionary(af[1].as_reference().unwrap())
+        .unwrap();
+    let ef = spec.get(b"EF").unwrap().as_dict().unwrap();
+    let stream = pdf_doc
+        .get_object(ef.get(b"F").unwrap().as_reference().unwrap())
+        .unwrap()
+        .as_stream()
+        .unwrap();
+    let params = stream.dict.get(b"Params").unwrap().as_dict().unwrap();
+    assert_eq!(params.get(b"Size").unwrap().as_i64().unwrap(), 27);
+    let checksum = params.get(b"CheckSum").unwrap().as_str().unwrap();
+    let hex: String = checksum.iter().map(|b| format!("{b:02x}")).collect();
+    assert_eq!(hex, "903f61de3d34bc8a5bcc25f04a592919");
+}
+
+#[test]
+fn embed_with_attachments_rejects_invalid_input() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let embed = |attachments: &[zugferd::Attachment]| {
+        zugferd::embed_in_pdf_with_attachments(
+            &minimal_pdf(),
+            &xml,
+            ZugferdProfile::EN16931,
+            attachments,
+        )
+    };
+
+    let err = embed(&[zugferd::Attachment::new("Factur-X.xml", b"<x/>".to_vec())]).unwrap_err();
+    assert!(err.to_string().contains("duplicate"), "{err}");
+    let err = embed(&[
+        zugferd::Attachment::new("a.csv", b"1".to_vec()),
+        zugferd::Attachment::new("a.csv", b"2".to_vec()),
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("duplicate"), "{err}");
+    let err = embed(&[zugferd::Attachment::new("a.csv", b"1".to_vec()).with_relationship("Other")])
+        .unwrap_err();
+    assert!(err.to_string().contains("AFRelationship"), "{err}");
+}
+
+#[test]
+fn profile_from_urn() {
+    for profile in [
+        ZugferdProfile::Minimum,
+        ZugferdProfile::BasicWl,
+        ZugferdProfile::Basic,
+        ZugferdProfile::EN16931,
+        ZugferdProfile::Extended,
+        ZugferdProfile::XRechnung,
+    ] {
+        assert_eq!(ZugferdProfile::from_urn(profile.urn()), Some(profile));
+    }
+    assert_eq!(
+        ZugferdProfile::from_urn("urn:cen.eu:en16931:2017#conformant#urn:zugferd.de:2p0:extended"),
+        Some(ZugferdProfile::Extended)
+    );
+    assert_eq!(
+        ZugferdProfile::from_urn(
+            "urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_2.3"
+        ),
+        Some(ZugferdProfile::XRechnung)
+    );
+    assert_eq!(
+        ZugferdProfile::from_urn("urn:ferd:CrossIndustryDocument:invoice:1p0:basic"),
+        None
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Existing metadata and PDF/A inputs
+// ---------------------------------------------------------------------------
+
+/// Load a PDF, change it and save it again.
+fn modify_pdf(pdf: &[u8], change: impl FnOnce(&mut lopdf::Document)) -> Vec<u8> {
+    let mut doc = lopdf::Document::load_mem(pdf).unwrap();
+    change(&mut doc);
+    let mut out = Vec::new();
+    doc.save_to(&mut out).unwrap();
+    out
+}
+
+/// Give every font a (dummy) embedded font program.
+fn embed_fonts(doc: &mut lopdf::Document) {
+    use lopdf::{Object, Stream, dictionary};
+
+    let file = doc.add_object(Stream::new(dictionary! {}, vec![0; 16]));
+    let descriptor = doc.add_object(dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => "Helvetica",
+        "FontFile2" => Object::Reference(file),
+    });
+    for obj in doc.objects.values_mut() {
+        if let Ok(dict) = obj.as_dict_mut() {
+            if dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"Font".as_slice()) {
+                dict.set("FontDescriptor", Object::Reference(descriptor));
+            }
+        }
+    }
+}
+
+fn set_xmp(doc: &mut lopdf::Document, xmp: &str) {
+    use lopdf::{Object, Stream, dictionary};
+
+    let id = doc.add_object(Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        xmp.as_bytes().to_vec(),
+    ));
+    doc.catalog_mut()
+        .unwrap()
+        .set("Metadata", Object::Reference(id));
+}
+
+fn output_xmp(pdf: &[u8]) -> String {
+    let doc = lopdf::Document::load_mem(pdf).unwrap();
+    let id = doc
+        .catalog()
+        .unwrap()
+        .get(b"Metadata")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+    let stream = doc.get_object(id).unwrap().as_stream().unwrap();
+    String::from_utf8(stream.content.clone()).unwrap()
+}
+
+fn info_entry(pdf: &[u8], key: &str) -> Option<String> {
+    let doc = lopdf::Document::load_mem(pdf).unwrap();
+    let id = doc.trailer.get(b"Info").ok()?.as_reference().ok()?;
+    let value = doc.get_dictionary(id).ok()?.get(key.as_bytes()).ok()?;
+    Some(String::from_utf8_lossy(value.as_str().ok()?).into_owned())
+}
+
+const PDFA2A_XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+      xmlns:xmpMM="http://ns.adobe.com/xap/1.0/mm/">
+    <rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+        pdfaid:part="2" pdfaid:conformance="A"/>
+    <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Rechnung RE-2024-001</rdf:li></rdf:Alt></dc:title>
+      <dc:creator><rdf:Seq><rdf:li>ACME GmbH</rdf:li></rdf:Seq></dc:creator>
+      <dc:format>application/pdf</dc:format>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+        pdf:Producer="Renderer 4.2" xmp:CreateDate="2024-06-15T10:00:00+02:00">
+      <xmpMM:DocumentID>uuid:2f0c4cde-1f6e-4f55-9c47-3a1c1e7f2b10</xmpMM:DocumentID>
+    </rdf:Description>
+    <rdf:Description rdf:about="" xmlns:inv="http://example.com/ns/invoice/">
+      <inv:Batch>2024-06</inv:Batch>
+    </rdf:Description>
+    <rdf:Description rdf:about=""
+        xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/"
+        xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#"
+        xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+      <pdfaExtension:schemas>
+        <rdf:Bag>
+          <rdf:li rdf:parseType="Resource">
+            <pdfaSchema:schema>Invoice batch</pdfaSchema:schema>
+            <pdfaSchema:namespaceURI>http://example.com/ns/invoice/</pdfaSchema:namespaceURI>
+            <pdfaSchema:prefix>inv</pdfaSchema:prefix>
+          </rdf:li>
+        </rdf:Bag>
+      </pdfaExtension:schemas>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+#[test]
+fn embed_merges_existing_xmp_and_info() {
+    use lopdf::{Object, dictionary};
+
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let input = modify_pdf(&minimal_pdf(), |doc| {
+        embed_fonts(doc);
+        set_xmp(doc, PDFA2A_XMP);
+        let info = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Rechnung RE-2024-001"),
+            "Producer" => Object::string_literal("Renderer 4.2"),
+            "CreationDate" => Object::string_literal("D:20240615100000+02'00'"),
+        });
+        doc.trailer.set("Info", Object::Reference(info));
+    });
+
+    let report = zugferd::inspect_pdf(&input).unwrap();
+    assert_eq!(report.kind(), zugferd::PdfInputKind::Upgradable);
+    assert_eq!(report.output_conformance(), "A");
+
+    let pdf = zugferd::embed_in_pdf(&input, &xml, ZugferdProfile::EN16931).unwrap();
+    let meta = zugferd::extract_document(&pdf).unwrap().xmp.unwrap();
+    assert_eq!(meta.pdfa_part, Some(3));
+    assert_eq!(meta.pdfa_conformance.as_deref(), Some("A"));
+    assert_eq!(meta.conformance_level.as_deref(), Some("EN 16931"));
+
+    let xmp = output_xmp(&pdf);
+    for expected in [
+        "Rechnung RE-2024-001",
+        "<rdf:li>ACME GmbH</rdf:li>",
+        "<dc:format>application/pdf</dc:format>",
+        "<pdf:Producer>Renderer 4.2</pdf:Producer>",
+        "<xmp:CreateDate>2024-06-15T10:00:00+02:00</xmp:CreateDate>",
+        "uuid:2f0c4cde-1f6e-4f55-9c47-3a1c1e7f2b10",
+        "<inv:Batch>2024-06</inv:Batch>",
+        "<pdfaSchema:namespaceURI>http://example.com/ns/invoice/</pdfaSchema:namespaceURI>",
+        "Factur-X PDFA Extension Schema",
+    ] {
+        assert!(xmp.contains(expected), "missing {expected} in\n{xmp}");
+    }
+    assert_eq!(xmp.matches("pdfaid:part").count(), 2, "{xmp}");
+    assert!(!xmp.contains("pdfaid:part=\"2\""));
+
+    // Info and XMP stay in sync
+    assert_eq!(
+        info_entry(&pdf, "Title").as_deref(),
+        Some("Rechnung RE-2024-001")
+    );
+    assert_eq!(info_entry(&pdf, "Author").as_deref(), Some("ACME GmbH"));
+    assert_eq!(
+        info_entry(&pdf, "Producer").as_deref(),
+        Some("Renderer 4.2")
+    );
+    assert_eq!(
+        info_entry(&pdf, "CreationDate").as_deref(),
+        Some("D:20240615100000+02'00'")
+    );
+    assert!(info_entry(&pdf, "ModDate").is_some());
+    assert!(xmp.contains("<xmp:ModifyDate>"));
+}
+
+#[test]
+fn embed_twice_keeps_one_facturx_schema() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let once = zugferd::embed_in_pdf(&minimal_pdf(), &xml, ZugferdProfile::EN16931).unwrap();
+    let twice = zugferd::embed_in_pdf(&once, &xml, ZugferdProfile::Extended).unwrap();
+
+    let xmp = output_xmp(&twice);
+    assert_eq!(xmp.matches("Factur-X PDFA Extension Schema").count(), 1);
+    assert_eq!(xmp.matches("<fx:ConformanceLevel>").count(), 1);
+    assert!(xmp.contains("<fx:ConformanceLevel>EXTENDED</fx:ConformanceLevel>"));
+    assert_eq!(xmp.matches("<dc:title>").count(), 1);
+}
+
+#[test]
+fn inspect_pdf_classifies_inputs() {
+    use lopdf::dictionary;
+    use zugferd::{PdfInputIssue, PdfInputKind};
+
+    // Helvetica is a standard 14 font without an embedded program
+    let report = zugferd::inspect_pdf(&minimal_pdf()).unwrap();
+    assert_eq!(report.kind(), PdfInputKind::NonConformant);
+    assert_eq!(
+        report.issues,
+        vec![PdfInputIssue::FontNotEmbedded("Helvetica".into())]
+    );
+
+    let plain = modify_pdf(&minimal_pdf(), embed_fonts);
+    let report = zugferd::inspect_pdf(&plain).unwrap();
+    assert_eq!(report.kind(), PdfInputKind::Plain);
+    assert_eq!(report.output_conformance(), "B");
+
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let zugferd_pdf = zugferd::embed_in_pdf(&plain, &xml, ZugferdProfile::EN16931).unwrap();
+    assert_eq!(
+        zugferd::inspect_pdf(&zugferd_pdf).unwrap().kind(),
+        PdfInputKind::PdfA3
+    );
+
+    let transparent = modify_pdf(&plain, |doc| {
+        doc.add_object(dictionary! { "Type" => "ExtGState", "ca" => 0.5 });
+    });
+    let report = zugferd::inspect_pdf(&transparent).unwrap();
+    assert_eq!(report.kind(), PdfInputKind::Plain);
+    assert!(matches!(report.issues[0], PdfInputIssue::Transparency(_)));
+    assert!(!report.issues[0].is_blocking());
+
+    let upgradable = zugferd::inspect_pdf(&modify_pdf(&zugferd_pdf, |doc| {
+        doc.add_object(dictionary! { "Type" => "ExtGState", "BM" => "Multiply" });
+    }))
+    .unwrap();
+    assert_eq!(upgradable.kind(), PdfInputKind::PdfA3);
+}
+
+#[test]
+fn embed_rejects_encrypted_pdf() {
+    use lopdf::{Object, dictionary};
+
+    let encrypted = modify_pdf(&minimal_pdf(), |doc| {
+        let id = doc.add_object(dictionary! { "Filter" => "Standard", "V" => 1 });
+        doc.trailer.set("Encrypt", Object::Reference(id));
+    });
+    let report = zugferd::inspect_pdf(&encrypted).unwrap();
+    assert!(report.issues.contains(&zugferd::PdfInputIssue::Encrypted));
+
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let err = zugferd::embed_in_pdf(&encrypted, &xml, ZugferdProfile::EN16931).unwrap_err();
+    assert!(err.to_string().contains("encrypted"), "{err}");
+}
+
+// ---------------------------------------------------------------------------
+// PDF/A-3 pre-flight
+// ---------------------------------------------------------------------------
+
+fn clauses(issues: &[zugferd::PdfaIssue]) -> Vec<&str> {
+    issues.iter().map(|issue| issue.clause).collect()
+}
+
+#[test]
+fn preflight_accepts_embed_output() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let input = modify_pdf(&minimal_pdf(), embed_fonts);
+    let pdf = zugferd::embed_in_pdf_with_attachments(
+        &input,
+        &xml,
+        ZugferdProfile::EN16931,
+        &[zugferd::Attachment::new(
+            "stundenzettel.csv",
+            b"datum;stunden\n".to_vec(),
+        )],
+    )
+    .unwrap();
+    assert_eq!(zugferd::preflight(&pdf), vec![]);
+
+    // Existing PDF/A-2A metadata and Info entries stay consistent
+    let input = modify_pdf(&input, |doc| set_xmp(doc, PDFA2A_XMP));
+    let pdf = zugferd::embed_in_pdf(&input, &xml, ZugferdProfile::EN16931).unwrap();
+    assert_eq!(zugferd::preflight(&pdf), vec![]);
+}
+
+#[test]
+fn preflight_reports_plain_pdf() {
+    let issues = zugferd::preflight(&minimal_pdf());
+    let found = clauses(&issues);
+    for expected in [
+        "ISO 19005-3 6.1.2",
+        "ISO 19005-3 6.1.3",
+        "ISO 19005-3 6.2.2",
+        "ISO 19005-3 6.2.11.4.1",
+        "ISO 19005-3 6.6.2.1",
+        "Factur-X",
+    ] {
+        assert!(
+            found.contains(&expected),
+            "missing {expected} in {issues:?}"
+        );
+    }
+    assert!(
+        issues
+            .iter()
+            .any(|i| i.to_string() == "[ISO 19005-3 6.2.11.4.1] font Helvetica is not embedded")
+    );
+
+    let issues = zugferd::preflight(b"this is not a PDF file");
+    assert_eq!(clauses(&issues), ["ISO 19005-3 6.1.2", "ISO 19005-3 6.1"]);
+}
+
+#[test]
+fn preflight_reports_broken_zugferd_pdf() {
+    use lopdf::{Object, dictionary};
+
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let pdf = zugferd::embed_in_pdf(
+        &modify_pdf(&minimal_pdf(), embed_fonts),
+        &xml,
+        ZugferdProfile::EN16931,
+    )
+    .unwrap();
+    let broken = modify_pdf(&pdf, |doc| {
+        doc.trailer.remove(b"ID");
+        let info = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(info)
+            .unwrap()
+            .set("Title", Object::string_literal("Andere Rechnung"));
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.remove(b"AF");
+        catalog.set(
+            "OpenAction",
+            dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") },
+        );
+    });
+
+    let issues = zugferd::preflight(&broken);
+    let found = clauses(&issues);
+    for expected in [
+        "ISO 19005-3 6.1.3",
+        "ISO 19005-3 6.5.1",
+        "ISO 19005-3 6.6.3",
+        "ISO 19005-3 6.8",
+    ] {
+        assert!(
+            found.contains(&expected),
+            "missing {expected} in {issues:?}"
+        );
+    }
+    assert!(
+        issues
+            .iter()
+            .any(|i| i.message == "factur-x.xml is not referenced from an AF array"),
+        "{issues:?}"
+    );
+    assert!(!found.contains(&"ISO 19005-3 6.2.2"), "{issues:?}");
+}
+
+// ---------------------------------------------------------------------------
+// Hybrid consistency: visible text vs. embedded XML
+// ---------------------------------------------------------------------------
+
+/// A one-page PDF showing `content` (raw text operators, WinAnsi strings)
+/// in Helvetica.
+fn text_pdf(content: &str) -> Vec<u8> {
+    use lopdf::{Object, Stream, dictionary};
+
+    modify_pdf(&minimal_pdf(), |doc| {
+        let font = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        // Latin-1 bytes, which WinAnsi agrees with for umlauts and ß
+        let bytes = content.chars().map(|c| c as u8).collect();
+        let stream = doc.add_object(Stream::new(dictionary! {}, bytes));
+        let page = doc.page_iter().next().unwrap();
+        let page = doc.get_dictionary_mut(page).unwrap();
+        page.set("Contents", Object::Reference(stream));
+        page.set(
+            "Resources",
+            dictionary! { "Font" => dictionary! { "F1" => Object::Reference(font) } },
+        );
+    })
+}
+
+fn invoice_page(number: &str, gross: &str, iban: &str) -> String {
+    [
+        "BT /F1 10 Tf 50 800 Td (ACME GmbH, Friedrichstraße 123, 10115 Berlin) Tj ET".to_string(),
+        format!("BT /F1 10 Tf 50 760 Td (Rechnungsnummer: {number}) Tj ET"),
+        "BT /F1 10 Tf 50 745 Td (Rechnungsdatum: 15.06.2024) Tj 0 -15 Td (Leistungsdatum: 15. Juni 2024) Tj ET".to_string(),
+        "BT /F1 10 Tf 50 700 Td (Beratung) Tj 250 0 Td (10 Std.) Tj ET".to_string(),
+        "BT /F1 10 Tf 400 700 Td (1.500,00 EUR) Tj ET".to_string(),
+        "BT /F1 10 Tf 50 680 Td (Nettobetrag) Tj ET BT /F1 10 Tf 400 680 Td (1.500,00 EUR) Tj ET".to_string(),
+        "BT /F1 10 Tf 50 665 Td (USt 19 %) Tj ET BT /F1 10 Tf 400 665 Td (285,00 EUR) Tj ET".to_string(),
+        format!("BT /F1 10 Tf 50 650 Td [(Gesamt)-20(betrag)] TJ ET BT /F1 10 Tf 400 650 Td ({gross} EUR) Tj ET"),
+        "BT /F1 10 Tf 12 TL 50 620 Td (Zahlbar bis 15.07.2024) Tj".to_string(),
+        format!("(IBAN: {iban}) ' ET"),
+    ]
+    .join("\n")
+}
+
+#[test]
+fn extract_page_text_lines() {
+    let pdf = text_pdf(&invoice_page(
+        "RE-2024-001",
+        "1.785,00",
+        "DE89 3704 0044 0532 0130 00",
+    ));
+    let pages = zugferd::extract_page_text(&pdf).unwrap();
+    assert_eq!(pages.len(), 1);
+    let lines: Vec<&str> = pages[0].lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "ACME GmbH, Friedrichstraße 123, 10115 Berlin",
+            "Rechnungsnummer: RE-2024-001",
+            "Rechnungsdatum: 15.06.2024",
+            "Leistungsdatum: 15. Juni 2024",
+            "Beratung 10 Std. 1.500,00 EUR",
+            "Nettobetrag 1.500,00 EUR",
+            "USt 19 % 285,00 EUR",
+            "Gesamtbetrag 1.785,00 EUR",
+            "Zahlbar bis 15.07.2024",
+            "IBAN: DE89 3704 0044 0532 0130 00",
+        ]
+    );
+}
+
+#[test]
+fn extract_page_text_reads_form_xobjects() {
+    use lopdf::{Object, Stream, dictionary};
+
+    // Letterhead in a form with its own font, the IBAN in a form nested in it
+    let pdf = modify_pdf(
+        &text_pdf("BT /F1 10 Tf 50 700 Td (Beratung) Tj ET\n/Head Do"),
+        |doc| {
+            let font = doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => "Helvetica",
+                "Encoding" => "WinAnsiEncoding",
+            });
+            let footer = doc.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Form", "BBox" => vec![0.into(), 0.into(), 595.into(), 842.into()] },
+            b"BT /F1 8 Tf 50 40 Td (IBAN: DE89 3704 0044 0532 0130 00) Tj ET".to_vec(),
+        ));
+            let head = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Form",
+                    "BBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+                    "Resources" => dictionary! {
+                        "Font" => dictionary! { "H1" => Object::Reference(font) },
+                        "XObject" => dictionary! { "Foot" => Object::Reference(footer) },
+                    },
+                },
+                b"BT /H1 10 Tf 50 800 Td (ACME GmbH, Friedrichstra\xdfe 123) Tj ET /Foot Do"
+                    .to_vec(),
+            ));
+            let page = doc.page_iter().next().unwrap();
+            let resources = doc
+                .get_dictionary_mut(page)
+                .unwrap()
+                .get_mut(b"Resources")
+                .unwrap()
+                .as_dict_mut()
+                .unwrap();
+            resources.set("XObject", dictionary! { "Head" => Object::Reference(head) });
+        },
+    );
+
+    let pages = zugferd::extract_page_text(&pdf).unwrap();
+    let lines: Vec<&str> = pages[0].lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "Beratung",
+            "ACME GmbH, Friedrichstraße 123",
+            "IBAN: DE89 3704 0044 0532 0130 00",
+        ]
+    );
+}
+
+#[test]
+fn check_hybrid_accepts_matching_pdf() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let page = invoice_page("RE-2024-001", "1.785,00", "DE89 3704 0044 0532 0130 00");
+    let pdf = zugferd::embed_in_pdf(&text_pdf(&page), &xml, ZugferdProfile::EN16931).unwrap();
+    assert_eq!(zugferd::check_hybrid(&pdf).unwrap(), vec![]);
+
+    // English notation
+    let text = "Invoice number RE-2024-001\nInvoice date 2024-06-15\nDelivery date June 15, 2024\n\
+                Due date 07/15/2024\nNet amount 1,500.00\nVAT 19% 285.00\nTotal EUR 1,785.00\n\
+                IBAN DE89370400440532013000";
+    assert_eq!(zugferd::check_visible_text(text, &inv), vec![]);
+}
+
+#[test]
+fn check_hybrid_reports_mismatches() {
+    let inv = test_invoice();
+    let xml = zugferd::to_xml(&inv, ZugferdProfile::EN16931).unwrap();
+    let page = invoice_page("RE-2024-010", "1.758,00", "DE02 1203 0000 0000 2020 51")
+        .replace("Zahlbar bis 15.07.2024", "Zahlbar sofort");
+    let pdf = zugferd::embed_in_pdf(&text_pdf(&page), &xml, ZugferdProfile::EN16931).unwrap();
+
+    let errors = zugferd::check_hybrid(&pdf).unwrap();
+    let messages: Vec<(&str, &str)> = errors
+        .iter()
+        .map(|e| (e.field.as_str(), e.message.as_str()))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            (
+                "number",
+                "invoice number (BT-1) is RE-2024-001 in the XML but the PDF shows RE-2024-010"
+            ),
+            (
+                "due_date",
+                "due date (BT-9) 15.07.2024 not found in the PDF text"
+            ),
+            (
+                "totals.gross_total",
+                "gross total (BT-112) is 1785.00 in the XML but the PDF shows 1.758,00"
+            ),
+            (
+                "payment.credit_transfer.iban",
+                "IBAN (BT-84) is DE89370400440532013000 in the XML but the PDF shows \
+                 DE02 1203 0000 0000 2020 51"
+            ),
+        ]
+    );
+
+    // A PDF without text cannot be compared
+    let blank = zugferd::embed_in_pdf(&text_pdf(""), &xml, ZugferdProfile::EN16931).unwrap();
+    assert!(zugferd::check_hybrid(&blank).is_err());
+}
+
+#[test]
+fn check_visible_text_names_the_delivery_date_source() {
+    let text = "Rechnungsnummer RE-2024-001\nRechnungsdatum 15.06.2024\nLeistungsdatum 14.06.2024\n\
+                Zahlbar bis 15.07.2024\nNettobetrag 1.500,00\nUSt 19 % 285,00\n\
+                Gesamtbetrag 1.785,00\nIBAN DE89370400440532013000";
+    let mismatch = |inv: &Invoice| {
+        let errors = zugferd::check_visible_text(text, inv);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        (errors[0].field.clone(), errors[0].message.clone())
+    };
+
+    // Without BT-72 the tax point date (BT-7) is compared
+    let mut inv = test_invoice();
+    inv.delivery = None;
+    inv.tax_point_date = Some(date(2024, 6, 12));
+    assert_eq!(
+        mismatch(&inv),
+        (
+            "tax_point_date".to_string(),
+            "tax point date (BT-7) is 12.06.2024 in the XML but the PDF shows 14.06.2024"
+                .to_string()
+        )
+    );
+
+    inv.delivery = Some(DeliveryInformation {
+        actual_delivery_date: Some(date(2024, 6, 13)),
+        delivery_party: None,
+        delivery_address: None,
+    });
+    assert_eq!(mismatch(&inv).0, "delivery.actual_delivery_date");
+}
+
 // ---------------------------------------------------------------------------
 // Corrupt PDF input — extract_from_pdf
 // ---------------------------------------------------------------------------
@@ -375,3 +1605,140 @@ fn profile_conformance_levels() {
     assert_eq!(ZugferdProfile::Extended.conformance_level(), "EXTENDED");
     assert_eq!(ZugferdProfile::XRechnung.conformance_level(), "XRECHNUNG");
 }
+
+// ---------------------------------------------------------------------------
+// ZUGFeRD 1.0
+// ---------------------------------------------------------------------------
+
+const ZUGFERD1_COMFORT: &str = include_str!("fixtures/zugferd-v1/ZUGFeRD-invoice_comfort.xml");
+
+#[test]
+fn zugferd1_comfort_parses_into_invoice() {
+    let (inv, version) = zugferd::from_xml(ZUGFERD1_COMFORT).unwrap();
+    assert_eq!(version, zugferd::ZugferdVersion::V1);
+
+    assert_eq!(inv.number, "471102");
+    assert_eq!(inv.type_code, InvoiceTypeCode::Invoice);
+    assert_eq!(inv.issue_date, date(2013, 3, 5));
+    assert_eq!(inv.due_date, Some(date(2013, 4, 4)));
+    assert_eq!(inv.tax_point_date, Some(date(2013, 3, 4)));
+    assert_eq!(inv.notes.len(), 2);
+    assert_eq!(inv.buyer_reference.as_deref(), Some("AB-312"));
+    assert_eq!(inv.order_reference.as_deref(), Some("PO-2013-0815"));
+    assert_eq!(inv.contract_reference.as_deref(), Some("RV-2012-07"));
+
+    assert_eq!(inv.seller.name, "Lieferant GmbH");
+    assert_eq!(inv.seller.vat_id.as_deref(), Some("DE123456789"));
+    assert_eq!(inv.seller.tax_number.as_deref(), Some("201/113/40209"));
+    assert_eq!(inv.seller.address.city, "München");
+    assert_eq!(inv.buyer.name, "Kunden AG Mitte");
+    assert_eq!(inv.buyer.address.postal_code, "69876");
+
+    let payment = inv.payment.as_ref().unwrap();
+    assert_eq!(payment.means_code, PaymentMeansCode::SepaCreditTransfer);
+    let transfer = payment.credit_transfer.as_ref().unwrap();
+    assert_eq!(transfer.iban, "DE08700901001234567890");
+    assert_eq!(transfer.bic.as_deref(), Some("GENODEF1M04"));
+
+    assert_eq!(inv.lines.len(), 2);
+    assert_eq!(inv.lines[0].item_name, "Trennblätter A4");
+    assert_eq!(inv.lines[0].quantity, dec!(20));
+    assert_eq!(inv.lines[0].unit_price, dec!(9.90));
+    assert_eq!(inv.lines[0].tax_rate, dec!(19));
+    assert_eq!(inv.lines[1].tax_rate, dec!(7));
+
+    // Freight (SpecifiedLogisticsServiceCharge) becomes a document charge
+    assert_eq!(inv.charges.len(), 1);
+    assert_eq!(inv.charges[0].amount, dec!(5.80));
+    assert_eq!(inv.charges[0].reason.as_deref(), Some("Versandkosten"));
+    assert_eq!(inv.allowances.len(), 1);
+
+    let totals = inv.totals.as_ref().unwrap();
+    assert_eq!(totals.line_net_total, dec!(473.00));
+    assert_eq!(totals.vat_total, dec!(56.07));
+    assert_eq!(totals.amount_due, dec!(524.87));
+    assert_eq!(totals.vat_breakdown.len(), 2);
+    assert!(validate_arithmetic(&inv).is_empty());
+}
+
+#[test]
+fn zugferd1_detection() {
+    assert_eq!(
+        zugferd::detect_version(ZUGFERD1_COMFORT),
+        Some(zugferd::ZugferdVersion::V1)
+    );
+    assert_eq!(
+        zugferd::guideline_id(ZUGFERD1_COMFORT)
+            .as_deref()
+            .and_then(zugferd::ZugferdV1Profile::from_urn),
+        Some(zugferd::ZugferdV1Profile::Comfort)
+    );
+
+    let v2 = zugferd::to_xml(&test_invoice(), ZugferdProfile::EN16931).unwrap();
+    assert_eq!(
+        zugferd::detect_version(&v2),
+        Some(zugferd::ZugferdVersion::V2)
+    );
+    assert_eq!(
+        zugferd::guideline_id(&v2).as_deref(),
+        Some(ZugferdProfile::EN16931.urn())
+    );
+
+    let (_, syntax) = xrechnung::from_xml(ZUGFERD1_COMFORT).unwrap();
+    assert_eq!(syntax, xrechnung::XmlSyntax::CiiD13b);
+}
+
+#[test]
+fn zugferd1_basic_minimal() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CrossIndustryDocument xmlns="urn:ferd:CrossIndustryDocument:invoice:1p0" xmlns:r="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:12" xmlns:u="urn:un:unece:uncefact:data:standard:UnqualifiedDataType:15">
+  <SpecifiedExchangedDocumentContext>
+    <r:GuidelineSpecifiedDocumentContextParameter><r:ID>urn:ferd:CrossIndustryDocument:invoice:1p0:basic</r:ID></r:GuidelineSpecifiedDocumentContextParameter>
+  </SpecifiedExchangedDocumentContext>
+  <HeaderExchangedDocument>
+    <r:ID>B-1</r:ID><r:TypeCode>380</r:TypeCode>
+    <r:IssueDateTime><u:DateTimeString format="102">20140102</u:DateTimeString></r:IssueDateTime>
+  </HeaderExchangedDocument>
+  <SpecifiedSupplyChainTradeTransaction>
+    <r:ApplicableSupplyChainTradeAgreement>
+      <r:SellerTradeParty><r:Name>S &amp; Co</r:Name><r:PostalTradeAddress><r:CountryID>DE</r:CountryID></r:PostalTradeAddress></r:SellerTradeParty>
+      <r:BuyerTradeParty><r:Name>B</r:Name><r:PostalTradeAddress><r:CountryID>DE</r:CountryID></r:PostalTradeAddress></r:BuyerTradeParty>
+    </r:ApplicableSupplyChainTradeAgreement>
+    <r:ApplicableSupplyChainTradeSettlement>
+      <r:InvoiceCurrencyCode>EUR</r:InvoiceCurrencyCode>
+      <r:ApplicableTradeTax>
+        <r:CalculatedAmount currencyID="EUR">19.00</r:CalculatedAmount><r:TypeCode>VAT</r:TypeCode>
+        <r:BasisAmount currencyID="EUR">100.00</r:BasisAmount><r:ApplicablePercent>19</r:ApplicablePercent>
+      </r:ApplicableTradeTax>
+      <r:SpecifiedTradeSettlementMonetarySummation>
+        <r:LineTotalAmount currencyID="EUR">100.00</r:LineTotalAmount>
+        <r:TaxBasisTotalAmount currencyID="EUR">100.00</r:TaxBasisTotalAmount>
+        <r:TaxTotalAmount currencyID="EUR">19.00</r:TaxTotalAmount>
+        <r:GrandTotalAmount currencyID="EUR">119.00</r:GrandTotalAmount>
+        <r:DuePayableAmount currencyID="EUR">119.00</r:DuePayableAmount>
+      </r:SpecifiedTradeSettlementMonetarySummation>
+    </r:ApplicableSupplyChainTradeSettlement>
+    <r:IncludedSupplyChainTradeLineItem>
+      <r:AssociatedDocumentLineDocument><r:LineID>1</r:LineID></r:AssociatedDocumentLineDocument>
+      <r:SpecifiedSupplyChainTradeDelivery><r:BilledQuantity unitCode="HUR">1</r:BilledQuantity></r:SpecifiedSupplyChainTradeDelivery>
+      <r:SpecifiedSupplyChainTradeSettlement>
+        <r:SpecifiedTradeSettlementMonetarySummation><r:LineTotalAmount currencyID="EUR">100.00</r:LineTotalAmount></r:SpecifiedTradeSettlementMonetarySummation>
+      </r:SpecifiedSupplyChainTradeSettlement>
+      <r:SpecifiedTradeProduct><r:Name>Beratung</r:Name></r:SpecifiedTradeProduct>
+    </r:IncludedSupplyChainTradeLineItem>
+  </SpecifiedSupplyChainTradeTransaction>
+</CrossIndustryDocument>"#;
+
+    // Default namespace and non-standard prefixes
+    let inv = xrechnung::from_cii_d13b_xml(xml).unwrap();
+    assert_eq!(inv.number, "B-1");
+    assert_eq!(inv.seller.name, "S & Co");
+    assert_eq!(inv.lines[0].item_name, "Beratung");
+    assert_eq!(inv.totals.unwrap().gross_total, dec!(119.00));
+}
+
+#[test]
+fn zugferd1_rejects_other_roots() {
+    let v2 = zugferd::to_xml(&test_invoice(), ZugferdProfile::EN16931).unwrap();
+    assert!(xrechnung::from_cii_d13b_xml(&v2).is_err());
+}